@@ -29,6 +29,11 @@ pub fn start_processor_process(settings: &ProcessorSettingsSave) -> Option<Child
                 .arg("--buffer-latency").arg(settings.latency.to_string())
                 .arg("--tuner-periods").arg(settings.tuner_periods.to_string())
                 .arg("--upsample-passes").arg(settings.upsample_passes.to_string())
+                .arg("--resampler-quality").arg(settings.resampler_quality.to_string())
+                .arg("--output-limiter").arg(settings.output_limiter.to_string())
+                .arg("--pre-gain").arg(settings.pre_gain_db.to_string())
+                .arg("--output-ceiling").arg(settings.output_ceiling_db.to_string())
+                .arg("--on-disconnect").arg(settings.on_disconnect.arg())
                 .stdin(std::process::Stdio::null())
                 .stdout(std::process::Stdio::null())
                 .stderr(std::process::Stdio::null());
@@ -43,6 +48,19 @@ pub fn start_processor_process(settings: &ProcessorSettingsSave) -> Option<Child
             if let Some(preferred_sample_rate) = settings.preferred_sample_rate {
                 full_command.arg("--preferred-sample-rate").arg(preferred_sample_rate.to_string());
             }
+            if let Some(internal_sample_rate) = settings.internal_sample_rate {
+                full_command.arg("--internal-sample-rate").arg(internal_sample_rate.to_string());
+            }
+            if let Some(fx_loop) = &settings.fx_loop {
+                full_command.arg("--fx-send-channel").arg(fx_loop.send_channel.to_string())
+                    .arg("--fx-return-channel").arg(fx_loop.return_channel.to_string());
+            }
+            if let Some(dry_monitor_channel) = settings.dry_monitor_channel {
+                full_command.arg("--dry-monitor-channel").arg(dry_monitor_channel.to_string());
+            }
+            if let Some(input_channel) = settings.input_channel {
+                full_command.arg("--input-channel").arg(input_channel.to_string());
+            }
 
             tracing::info!("Full command to start processor: {:?}", full_command);
             let process = full_command.spawn();
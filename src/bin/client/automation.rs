@@ -0,0 +1,290 @@
+use std::time::{Duration, Instant};
+
+use rs_pedalboard::{pedalboard::ParameterPath, pedals::PedalParameterValue};
+
+/// Most parameter changes kept in a take. Later changes are dropped, so a long take can't use unbounded memory.
+pub const MAX_AUTOMATION_EVENTS: usize = 20000;
+/// Changes to a parameter closer together than this replace the previous change, so dragging a knob doesn't fill the take
+const MIN_EVENT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A parameter change, at a time from the start of the take
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutomationEvent {
+    pub time: Duration,
+    pub path: ParameterPath,
+    pub value: PedalParameterValue
+}
+
+/// Parameter changes recorded over a take, in time order
+#[derive(Clone, Debug, Default)]
+pub struct AutomationTake {
+    events: Vec<AutomationEvent>,
+    length: Duration,
+    // Whether changes were dropped after reaching MAX_AUTOMATION_EVENTS
+    full: bool
+}
+
+impl AutomationTake {
+    /// Add a change at the given time, which must not be before the previous change.
+    /// Returns false if the take is full and the change was dropped.
+    pub fn push(&mut self, time: Duration, path: ParameterPath, value: PedalParameterValue) -> bool {
+        self.length = self.length.max(time);
+
+        let recent = self.events.iter_mut()
+            .rev()
+            .take_while(|event| time.saturating_sub(event.time) < MIN_EVENT_INTERVAL)
+            .find(|event| event.path == path);
+        if let Some(event) = recent {
+            event.value = value;
+            return true;
+        }
+
+        if self.events.len() >= MAX_AUTOMATION_EVENTS {
+            self.full = true;
+            return false;
+        }
+
+        self.events.push(AutomationEvent { time, path, value });
+        true
+    }
+
+    pub fn events(&self) -> &[AutomationEvent] {
+        &self.events
+    }
+
+    /// Time from the start of the take to the end of recording
+    pub fn length(&self) -> Duration {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+}
+
+/// Length of a beat at the given tempo
+pub fn beat_duration(bpm: u32) -> Duration {
+    Duration::from_secs_f64(60.0 / bpm.max(1) as f64)
+}
+
+/// Time an event is played at, moved to the nearest beat if quantizing
+fn playback_time(time: Duration, quantize_bpm: Option<u32>) -> Duration {
+    match quantize_bpm {
+        Some(bpm) => {
+            let beat = beat_duration(bpm).as_secs_f64();
+            Duration::from_secs_f64((time.as_secs_f64() / beat).round() * beat)
+        },
+        None => time
+    }
+}
+
+enum AutomationMode {
+    Idle,
+    Recording { start: Instant },
+    // Index of the next event to play
+    Playing { start: Instant, next_event: usize }
+}
+
+/// Records parameter changes over a take and plays them back, for evolving sounds without touching the controls.
+///
+/// Playback only happens as often as [`Automation::take_due_events`] is called, i.e. once per UI frame.
+pub struct Automation {
+    take: AutomationTake,
+    mode: AutomationMode,
+    // Move played changes to the nearest beat of the metronome
+    pub quantize: bool,
+    // Start again from the beginning at the end of the take
+    pub looping: bool,
+    // Start and stop with the recorder
+    pub sync_with_recorder: bool
+}
+
+impl Automation {
+    pub fn new() -> Self {
+        Self {
+            take: AutomationTake::default(),
+            mode: AutomationMode::Idle,
+            quantize: false,
+            looping: false,
+            sync_with_recorder: false
+        }
+    }
+
+    pub fn take(&self) -> &AutomationTake {
+        &self.take
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(self.mode, AutomationMode::Recording { .. })
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.mode, AutomationMode::Playing { .. })
+    }
+
+    /// Start a new take, replacing the current take
+    pub fn start_recording(&mut self) {
+        self.take = AutomationTake::default();
+        self.mode = AutomationMode::Recording { start: Instant::now() };
+    }
+
+    /// Play the take from the start, at the given time
+    pub fn start_playback(&mut self, start: Instant) {
+        if self.take.is_empty() {
+            return;
+        }
+        self.mode = AutomationMode::Playing { start, next_event: 0 };
+    }
+
+    pub fn stop(&mut self) {
+        if let AutomationMode::Recording { start } = self.mode {
+            self.take.length = self.take.length.max(start.elapsed());
+        }
+        self.mode = AutomationMode::Idle;
+    }
+
+    /// Add a parameter change to the take, if recording
+    pub fn record(&mut self, path: ParameterPath, value: PedalParameterValue) {
+        if let AutomationMode::Recording { start } = self.mode {
+            let was_full = self.take.is_full();
+            if !self.take.push(start.elapsed(), path, value) && !was_full {
+                tracing::warn!("Automation take is full, further parameter changes are not recorded");
+            }
+        }
+    }
+
+    /// Length of each pass of the take when playing, which is a whole number of beats if quantizing
+    fn loop_length(&self, quantize_bpm: Option<u32>) -> Duration {
+        match quantize_bpm {
+            Some(bpm) => {
+                let beat = beat_duration(bpm).as_secs_f64();
+                Duration::from_secs_f64((self.take.length.as_secs_f64() / beat).ceil().max(1.0) * beat)
+            },
+            None => self.take.length
+        }
+    }
+
+    /// Time since the start of the current pass, while recording or playing
+    pub fn position(&self, now: Instant) -> Option<Duration> {
+        match self.mode {
+            AutomationMode::Idle => None,
+            AutomationMode::Recording { start } | AutomationMode::Playing { start, .. } => Some(now.saturating_duration_since(start))
+        }
+    }
+
+    /// Changes due to be played by `now`, in order, advancing the playback.
+    /// Playback stops at the end of the take, unless looping.
+    pub fn take_due_events(&mut self, now: Instant, quantize_bpm: Option<u32>) -> Vec<(ParameterPath, PedalParameterValue)> {
+        let mut due = Vec::new();
+        let loop_length = self.loop_length(quantize_bpm);
+
+        while let AutomationMode::Playing { start, next_event } = &mut self.mode {
+            let elapsed = now.saturating_duration_since(*start);
+            while let Some(event) = self.take.events.get(*next_event) {
+                if playback_time(event.time, quantize_bpm) > elapsed {
+                    break;
+                }
+                due.push((event.path.clone(), event.value.clone()));
+                *next_event += 1;
+            }
+
+            if *next_event < self.take.events.len() || elapsed < loop_length {
+                break;
+            }
+
+            if self.looping && !loop_length.is_zero() {
+                *start += loop_length;
+                *next_event = 0;
+            } else {
+                self.mode = AutomationMode::Idle;
+            }
+        }
+
+        due
+    }
+
+    /// Time until the next change is due, while playing
+    pub fn time_to_next_event(&self, now: Instant, quantize_bpm: Option<u32>) -> Option<Duration> {
+        let AutomationMode::Playing { start, next_event } = self.mode else {
+            return None;
+        };
+        let next_time = self.take.events.get(next_event)
+            .map(|event| playback_time(event.time, quantize_bpm))
+            .unwrap_or(self.loop_length(quantize_bpm));
+        Some((start + next_time).saturating_duration_since(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(parameter_name: &str) -> ParameterPath {
+        ParameterPath { pedalboard_id: 1, pedal_id: 2, parameter_name: parameter_name.to_string() }
+    }
+
+    fn take(times_ms: &[u64]) -> Automation {
+        let mut automation = Automation::new();
+        for (i, &time) in times_ms.iter().enumerate() {
+            automation.take.push(Duration::from_millis(time), path("Gain"), PedalParameterValue::Int(i as i16));
+        }
+        automation.take.length = Duration::from_millis(1000);
+        automation
+    }
+
+    #[test]
+    fn test_take_is_bounded() {
+        let mut take = AutomationTake::default();
+
+        // Quick changes to the same parameter replace each other
+        assert!(take.push(Duration::from_millis(0), path("Gain"), PedalParameterValue::Float(0.1)));
+        assert!(take.push(Duration::from_millis(5), path("Gain"), PedalParameterValue::Float(0.2)));
+        assert!(take.push(Duration::from_millis(5), path("Level"), PedalParameterValue::Float(0.5)));
+        assert_eq!(take.events().len(), 2);
+        assert_eq!(take.events()[0].value, PedalParameterValue::Float(0.2));
+
+        for i in 0..MAX_AUTOMATION_EVENTS as u64 {
+            take.push(Duration::from_millis(100 + i * 20), path("Gain"), PedalParameterValue::Float(0.3));
+        }
+        assert!(take.is_full());
+        assert_eq!(take.events().len(), MAX_AUTOMATION_EVENTS);
+    }
+
+    #[test]
+    fn test_playback_times() {
+        let mut automation = take(&[100, 400, 900]);
+        let start = Instant::now();
+        automation.start_playback(start);
+
+        assert!(automation.take_due_events(start + Duration::from_millis(50), None).is_empty());
+        assert_eq!(automation.take_due_events(start + Duration::from_millis(450), None).len(), 2);
+        assert_eq!(automation.time_to_next_event(start + Duration::from_millis(500), None), Some(Duration::from_millis(400)));
+        assert_eq!(automation.take_due_events(start + Duration::from_millis(1000), None).len(), 1);
+        assert!(!automation.is_playing());
+
+        // Looping plays the take again from the start
+        automation.looping = true;
+        automation.start_playback(start);
+        assert_eq!(automation.take_due_events(start + Duration::from_millis(1150), None).len(), 4);
+        assert!(automation.is_playing());
+    }
+
+    #[test]
+    fn test_quantized_playback() {
+        // At 120 BPM the beats are 500ms apart
+        let mut automation = take(&[100, 400, 900]);
+        let start = Instant::now();
+        automation.start_playback(start);
+
+        // The change at 100ms moves to the first beat, and the later changes to the next beats
+        assert_eq!(automation.take_due_events(start, Some(120)).len(), 1);
+        assert!(automation.take_due_events(start + Duration::from_millis(450), Some(120)).is_empty());
+        assert_eq!(automation.take_due_events(start + Duration::from_millis(500), Some(120)).len(), 1);
+        assert_eq!(automation.take_due_events(start + Duration::from_millis(1000), Some(120)).len(), 1);
+        assert!(!automation.is_playing());
+    }
+}
@@ -1,4 +1,5 @@
 mod socket;
+use socket::Command;
 mod state;
 use state::State;
 mod saved_pedalboards;
@@ -15,13 +16,15 @@ mod settings;
 use settings::{SettingsScreen, ProcessorLaunchState};
 mod audio_processor_handler;
 mod midi;
+mod notifications;
+mod automation;
 
 #[cfg(feature = "virtual_keyboard")]
 use egui_keyboard::{Keyboard, layouts::KeyboardLayout};
 
 use eframe::egui::{self, include_image, Button, Color32, FontId, Id, ImageButton, RichText, Vec2, FontFamily};
-use rs_pedalboard::{init_tracing, SAVE_DIR};
-use std::{sync::Arc, time::Instant};
+use rs_pedalboard::{init_tracing, SAVE_DIR, pedals::PedalParameterValue};
+use std::{sync::Arc, time::{Duration, Instant}};
 
 const PROCESSOR_PORT: u16 = 29475;
 const WINDOW_HEIGHT: f32 = 1080.0;
@@ -151,6 +154,30 @@ fn main() {
     )).expect("Failed to run app");
 }
 
+/// Start the processor process and wait for it to connect, returning a message for the user if it fails
+fn start_processor_on_startup(state: &State, settings_screen: &mut SettingsScreen) -> Result<(), String> {
+    if !settings_screen.ready_to_start_processor(&state.processor_settings.borrow()) {
+        return Err("Set the input and output devices in settings to start the processor".to_string());
+    }
+
+    let child = audio_processor_handler::start_processor_process(&state.processor_settings.borrow())
+        .ok_or_else(|| format!("Failed to start the processor process. Check that the processor is on the PATH or set by {}.", audio_processor_handler::PROCESSOR_ENV_VAR))?;
+
+    settings_screen.processor_launch_state = ProcessorLaunchState::AwaitingStart { start_time: Instant::now(), process: child };
+    loop {
+        settings_screen.handle_processor_launch();
+        if !settings_screen.processor_launch_state.is_awaiting() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+
+    match settings_screen.processor_launch_state {
+        ProcessorLaunchState::StartError => Err("The processor started but did not connect. Check the processor logs and audio devices.".to_string()),
+        _ => Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Screen {
     Stage,
@@ -170,7 +197,10 @@ pub struct PedalboardClientApp {
     pedalboard_library_screen: PedalboardLibraryScreen,
     utilities_screen: UtilitiesScreen,
     songs_screen: SongsScreen,
-    settings_screen: SettingsScreen
+    settings_screen: SettingsScreen,
+
+    // Time the state was last auto saved, or the app started
+    last_auto_save: Instant
 }
 
 impl PedalboardClientApp {
@@ -187,25 +217,28 @@ impl PedalboardClientApp {
         let mut settings_screen = SettingsScreen::new(leaked_state);
 
         let no_processor_start_arg = std::env::args().any(|arg| arg == "--no-processor");
+        let ready_to_play = leaked_state.client_settings.borrow().ready_to_play_on_startup;
         // Start up the audio processor process if configured to do so, not already connected and not running with the `--no-processor` argument
-        if leaked_state.client_settings.borrow().startup_processor && !leaked_state.is_connected() && !no_processor_start_arg {
+        let start_processor = leaked_state.client_settings.borrow().startup_processor || ready_to_play;
+        let mut startup_failed = false;
+        if start_processor && !leaked_state.is_connected() && !no_processor_start_arg {
             tracing::info!("Starting processor on startup");
-            if settings_screen.ready_to_start_processor(&leaked_state.processor_settings.borrow()) {
-                match audio_processor_handler::start_processor_process(&leaked_state.processor_settings.borrow()) {
-                    Some(child) => {
-                        settings_screen.processor_launch_state = ProcessorLaunchState::AwaitingStart { start_time: Instant::now(), process: child };
-                        loop {
-                            settings_screen.handle_processor_launch();
-                            if !settings_screen.processor_launch_state.is_awaiting() {
-                                break;
-                            }
-                            std::thread::sleep(std::time::Duration::from_millis(100));
-                        }
-                    },
-                    None => tracing::error!("Failed to start processor process")
-                }
+            if let Err(e) = start_processor_on_startup(leaked_state, &mut settings_screen) {
+                tracing::error!("{e}");
+                leaked_state.notify(e);
+                startup_failed = true;
+            }
+        }
+
+        if ready_to_play {
+            if leaked_state.is_connected() {
+                // The last stage is played once the processor confirms the session
+                leaked_state.set_screen(Screen::Stage);
             } else {
-                tracing::error!("Set input and output device to launch processor on start");
+                if !startup_failed {
+                    leaked_state.notify("Not connected to the processor, so not ready to play. Check the processor settings.".to_string());
+                }
+                leaked_state.set_screen(Screen::Settings);
             }
         }
 
@@ -224,6 +257,37 @@ impl PedalboardClientApp {
             state: leaked_state,
             #[cfg(feature = "virtual_keyboard")]
             keyboard: Keyboard::default().layout(KeyboardLayout::Qwerty),
+            last_auto_save: Instant::now(),
+        }
+    }
+
+    fn save_state(&self) {
+        // Remove any MIDI parameter functions that refer to pedalboards that no longer exist
+        self.state.midi_state.borrow_mut().remove_old_parameter_functions(&self.state.all_pedalboard_ids());
+
+        tracing::info!("Saving state");
+        if let Err(e) = self.state.save_state() {
+            tracing::error!("Failed to save state: {}", e);
+        } else {
+            tracing::info!("State saved successfully");
+        }
+    }
+
+    /// Save the state if there are unsaved changes and the auto save interval has passed since the last save,
+    /// so that a crash doesn't lose the changes
+    fn auto_save(&mut self, ctx: &egui::Context) {
+        if !self.state.has_unsaved_changes() {
+            return;
+        }
+
+        let interval = Duration::from_secs(self.state.client_settings.borrow().auto_save_secs as u64);
+        let elapsed = self.last_auto_save.elapsed();
+        if elapsed >= interval {
+            self.save_state();
+            self.last_auto_save = Instant::now();
+        } else {
+            // Make sure the app updates to save, even if nothing else changes
+            ctx.request_repaint_after(interval - elapsed);
         }
     }
 }
@@ -241,11 +305,200 @@ impl eframe::App for PedalboardClientApp {
 
         self.state.update_socket_responses();
         self.state.handle_other_thread_commands(ctx);
+        self.state.update_morph(ctx);
+        self.state.update_automation(ctx);
+
+        let mut session_buf = Vec::new();
+        self.state.get_commands("session", &mut session_buf);
+        for session in session_buf.iter() {
+            match session.as_str() {
+                // No other clients are connected, so the processor uses this client's pedalboards,
+                // unless this client is only observing
+                "new" if self.state.is_observer() => self.state.request_processor_set(),
+                "new" => self.state.load_active_set(),
+                // The processor will send the pedalboards that the other clients are using
+                "join" => tracing::info!("Joined processor session with other connected clients"),
+                _ => tracing::error!("Invalid session command format: {}", session)
+            }
+        }
+
+        let mut pedalboard_set_buf = Vec::new();
+        self.state.get_commands("pedalboardset", &mut pedalboard_set_buf);
+        if let Some(pedalboard_set) = pedalboard_set_buf.pop() {
+            self.state.apply_synced_command(Command::LoadSet(pedalboard_set), ctx);
+        }
+
+        let mut synced_buf = Vec::new();
+        self.state.get_commands("sync", &mut synced_buf);
+        for command in synced_buf.drain(..) {
+            match Command::from_synced_command(&command) {
+                Some(command) => self.state.apply_synced_command(command, ctx),
+                None => tracing::error!("Invalid synced command format: {}", command)
+            }
+        }
 
         let mut sr_buf = Vec::new();
         self.state.get_commands("sr", &mut sr_buf);
         if !sr_buf.is_empty() {
             tracing::info!("Processor is using sample rate: {}hz", sr_buf[0]);
+            match sr_buf[0].parse::<u32>() {
+                Ok(sample_rate) => self.state.processor_sample_rate.set(Some(sample_rate)),
+                Err(e) => tracing::error!("Invalid sample rate command format: {}", e)
+            }
+        }
+
+        let mut sample_rate_change_buf = Vec::new();
+        self.state.get_commands("setsamplerate", &mut sample_rate_change_buf);
+        if let Some(latest) = sample_rate_change_buf.last() {
+            match latest.split_once(' ') {
+                Some(("ok", sample_rate)) => match sample_rate.parse::<u32>() {
+                    Ok(sample_rate) => {
+                        tracing::info!("Processor changed sample rate to {}hz", sample_rate);
+                        self.state.processor_sample_rate.set(Some(sample_rate));
+                        self.state.sample_rate_change_error.replace(None);
+                    },
+                    Err(e) => tracing::error!("Invalid sample rate change command format: {}", e)
+                },
+                Some(("failed", reason)) => {
+                    tracing::warn!("Processor failed to change sample rate: {}", reason);
+                    self.state.sample_rate_change_error.replace(Some(reason.to_string()));
+                },
+                _ => tracing::error!("Invalid sample rate change command format: {}", latest)
+            }
+        }
+
+        let mut error_buf = Vec::new();
+        self.state.get_commands("error", &mut error_buf);
+        for error in error_buf {
+            tracing::warn!("Processor reported an error: {}", error);
+            self.state.notify(error);
+        }
+
+        // Repeated while the input keeps clipping, which keeps the same notification shown
+        let mut input_clipped_buf = Vec::new();
+        self.state.get_commands("inputclipped", &mut input_clipped_buf);
+        if !input_clipped_buf.is_empty() {
+            self.state.notify("The input is clipping before the pedalboard. Reduce the gain on your audio interface.".to_string());
+        }
+
+        let mut latency_buf = Vec::new();
+        self.state.get_commands("latency", &mut latency_buf);
+        if let Some(latest) = latency_buf.last() {
+            match latest.parse::<usize>() {
+                Ok(latency) => self.state.pedal_latency.set(Some(latency)),
+                Err(e) => tracing::error!("Invalid latency command format: {}", e)
+            }
+        }
+
+        let mut wetness_buf = Vec::new();
+        self.state.get_commands("wetness", &mut wetness_buf);
+        if let Some(latest) = wetness_buf.last() {
+            match latest.parse::<u32>() {
+                Ok(wetness) => self.state.wetness.set(Some(wetness)),
+                Err(e) => tracing::error!("Invalid wetness command format: {}", e)
+            }
+        }
+
+        // Pedal CPU usage is consumed every frame so it doesn't build up when the stage is not shown
+        let mut pedal_cpu_buf = Vec::new();
+        self.state.get_commands("pedalcpu", &mut pedal_cpu_buf);
+        if !pedal_cpu_buf.is_empty() {
+            let mut pedal_cpu = self.state.pedal_cpu.borrow_mut();
+            for command in pedal_cpu_buf.iter() {
+                match command.split_once(' ').and_then(|(pedal_id, percent)| Some((pedal_id.parse::<u32>().ok()?, percent.parse::<f32>().ok()?))) {
+                    Some((pedal_id, percent)) => { pedal_cpu.insert(pedal_id, percent); },
+                    None => tracing::error!("Invalid pedal cpu command format: {}", command)
+                }
+            }
+        }
+
+        // Metronome beats are consumed every frame so they don't build up when the metronome widget is not shown
+        let mut beat_buf = Vec::new();
+        self.state.get_commands("beat", &mut beat_buf);
+        if let Some(beat) = beat_buf.last().and_then(|beat| beat.parse::<usize>().ok()) {
+            self.state.metronome_last_beat.set(Some((beat, std::time::Instant::now())));
+        }
+
+        let mut detected_tempo_buf = Vec::new();
+        self.state.get_commands("detectedtempo", &mut detected_tempo_buf);
+        if let Some(latest) = detected_tempo_buf.last() {
+            match latest.parse::<u32>() {
+                Ok(bpm) => {
+                    tracing::info!("Processor detected a tempo of {} BPM", bpm);
+                    self.state.metronome_bpm.set(bpm);
+                },
+                Err(e) => tracing::error!("Invalid detected tempo command format: {}", e)
+            }
+        }
+
+        // Buffer fill is consumed every frame so it doesn't build up when the stage is not shown
+        let mut buffer_fill_buf = Vec::new();
+        self.state.get_commands("bufferfill", &mut buffer_fill_buf);
+        if let Some(latest) = buffer_fill_buf.last() {
+            match latest.split_once(' ').and_then(|(fill, latency)| Some((fill.parse::<f32>().ok()?, latency.parse::<f32>().ok()?))) {
+                Some(buffer_fill) => self.state.buffer_fill.set(Some(buffer_fill)),
+                None => tracing::error!("Invalid buffer fill command format: {}", latest)
+            }
+        }
+
+        let mut noise_floor_buf = Vec::new();
+        self.state.get_commands("noisefloorprogress", &mut noise_floor_buf);
+        if let Some(latest) = noise_floor_buf.last() {
+            match latest.split_once(' ').and_then(|(pedal_id, progress)| Some((pedal_id.parse::<u32>().ok()?, progress.parse::<f32>().ok()?))) {
+                Some(progress) => self.state.noise_floor_learning.set(Some(progress)),
+                None => tracing::error!("Invalid noise floor progress command format: {}", latest)
+            }
+        }
+
+        noise_floor_buf.clear();
+        self.state.get_commands("gatethreshold", &mut noise_floor_buf);
+        for command in noise_floor_buf.iter() {
+            let parsed = match command.split(' ').collect::<Vec<_>>().as_slice() {
+                [pedalboard_id, pedal_id, threshold_db] => pedalboard_id.parse::<u32>().ok()
+                    .zip(pedal_id.parse::<u32>().ok())
+                    .zip(threshold_db.parse::<f32>().ok())
+                    .map(|((pedalboard_id, pedal_id), threshold_db)| (pedalboard_id, pedal_id, threshold_db)),
+                _ => None
+            };
+
+            match parsed {
+                Some((pedalboard_id, pedal_id, threshold_db)) => self.state.apply_learned_gate_threshold(pedalboard_id, pedal_id, threshold_db, ctx),
+                None => tracing::error!("Invalid gate threshold command format: {}", command)
+            }
+        }
+
+        let mut pedalboard_level_buf = Vec::new();
+        self.state.get_commands("pedalboardlevel", &mut pedalboard_level_buf);
+        for command in pedalboard_level_buf.iter() {
+            match command.split_once(' ') {
+                Some((pedalboard_id, "failed")) => {
+                    tracing::warn!("Processor failed to measure level of pedalboard {}", pedalboard_id);
+                    self.state.notify("The imported pedalboard is silent, so its level could not be normalized".to_string());
+                },
+                Some((pedalboard_id, gain_db)) => match (pedalboard_id.parse::<u32>(), gain_db.parse::<f32>()) {
+                    (Ok(pedalboard_id), Ok(gain_db)) => self.state.apply_measured_pedalboard_level(pedalboard_id, gain_db),
+                    _ => tracing::error!("Invalid pedalboard level command format: {}", command)
+                },
+                None => tracing::error!("Invalid pedalboard level command format: {}", command)
+            }
+        }
+
+        // Values the processor changed when setting a parameter, e.g. clamped into range
+        let mut parameter_update_buf = Vec::new();
+        self.state.get_commands("parameterupdate", &mut parameter_update_buf);
+        for command in parameter_update_buf.iter() {
+            let parsed = match command.splitn(4, '|').collect::<Vec<_>>().as_slice() {
+                [pedalboard_id, pedal_id, parameter_name, value] => pedalboard_id.parse::<u32>().ok()
+                    .zip(pedal_id.parse::<u32>().ok())
+                    .zip(serde_json::from_str::<PedalParameterValue>(value).ok())
+                    .map(|((pedalboard_id, pedal_id), value)| (pedalboard_id, pedal_id, parameter_name.to_string(), value)),
+                _ => None
+            };
+
+            match parsed {
+                Some((pedalboard_id, pedal_id, parameter_name, value)) => self.state.set_parameter(pedalboard_id, pedal_id, parameter_name, value, true, ctx),
+                None => tracing::error!("Invalid parameter update command format: {}", command)
+            }
         }
 
         let bottom_window_select_height = ctx.screen_rect().height() * 0.1;
@@ -369,19 +622,22 @@ impl eframe::App for PedalboardClientApp {
             };
         });
         drop(enter);
+
+        self.state.notifications.borrow_mut().show(ctx);
+
+        // After drawing the screens, so leaving the performance view this frame resumes updates straight away
+        self.state.update_ui_freeze(ctx);
+        self.auto_save(ctx);
     }
 
     #[tracing::instrument(level = "debug", skip_all)]
     fn save(&mut self, _storage: &mut dyn eframe::Storage) {
-        // Remove any MIDI parameter functions that refer to pedalboards that no longer exist
-        self.state.midi_state.borrow_mut().remove_old_parameter_functions(&self.state.all_pedalboard_ids());
+        self.save_state();
+    }
 
-        tracing::info!("Saving state");
-        if let Err(e) = self.state.save_state() {
-            tracing::error!("Failed to save state: {}", e);
-        } else {
-            tracing::info!("State saved successfully");
-        }
+    /// The state is auto saved by the app while there are unsaved changes, so eframe only needs to save on exit
+    fn auto_save_interval(&self) -> Duration {
+        Duration::from_secs(24 * 60 * 60)
     }
 
     #[tracing::instrument(level = "debug", skip_all)]
@@ -1,16 +1,73 @@
-use eframe::egui::{self, Layout, RichText, TextEdit, Vec2, Widget};
-use rs_pedalboard::pedalboard::Pedalboard;
-use crate::state::State;
+use std::ops::Range;
+
+use eframe::egui::{self, text::LayoutJob, Layout, RichText, TextEdit, TextFormat, Vec2, Widget};
+use rs_pedalboard::{pedalboard::Pedalboard, pedals::PedalDiscriminants};
+use crate::{saved_pedalboards::SavedPedalboards, state::State};
 
 pub enum RowAction {
     Load,
-    Delete
+    Delete,
+    Copy,
+    Paste,
+    Export,
+    EditTags,
+    ToggleTagFilter(String),
+    ToggleFavorite
+}
+
+/// What a search term matched in a pedalboard, for highlighting
+pub struct SearchMatch {
+    // Byte range of the match in the pedalboard name
+    pub name: Option<Range<usize>>,
+    // Display names of the pedal types in the pedalboard that match
+    pub pedal_types: Vec<&'static str>
+}
+
+impl SearchMatch {
+    /// Match a pedalboard's name and the types of its pedals against a search term, ignoring case.
+    /// Pedal types also match their initials, e.g. "NAM" for Neural Amp Modeler.
+    ///
+    /// Returns None if nothing matches.
+    pub fn find(pedalboard: &Pedalboard, search_term: &str) -> Option<Self> {
+        let search_term = search_term.trim().to_lowercase();
+        if search_term.is_empty() {
+            return Some(SearchMatch { name: None, pedal_types: Vec::new() });
+        }
+
+        // ASCII lowercase keeps the byte positions, so the range can be used on the original name
+        let name = pedalboard.name.to_ascii_lowercase()
+            .find(&search_term)
+            .map(|start| start..start + search_term.len());
+
+        let mut pedal_types = Vec::new();
+        for pedal in &pedalboard.pedals {
+            let display_name = PedalDiscriminants::from(pedal).display_name();
+            let initials: String = display_name.split_whitespace().filter_map(|word| word.chars().next()).collect();
+            let matches = display_name.to_lowercase().contains(&search_term) || initials.to_lowercase() == search_term;
+            if matches && !pedal_types.contains(&display_name) {
+                pedal_types.push(display_name);
+            }
+        }
+
+        (name.is_some() || !pedal_types.is_empty()).then_some(SearchMatch { name, pedal_types })
+    }
 }
 
 pub struct PedalboardLibraryScreen {
     // Store pedalboards by unique name
     state: &'static State,
     search_term: String,
+    import_dialog: egui_file::FileDialog,
+    // Pedalboard ID being exported, and the dialog to choose its file
+    export_dialog: Option<(u32, egui_file::FileDialog)>,
+    // Result of the last import/export, shown under the search bar
+    message: Option<String>,
+    // Set the output trim of imported pedalboards so their level matches their input
+    normalize_import: bool,
+    // Only pedalboards with all of these tags are shown
+    tag_filter: Vec<String>,
+    // Pedalboard ID having its tags edited, and the tag being typed
+    editing_tags: Option<(u32, String)>
 }
 
 impl PedalboardLibraryScreen {
@@ -18,10 +75,195 @@ impl PedalboardLibraryScreen {
         Self {
             state,
             search_term: String::new(),
+            import_dialog: egui_file::FileDialog::open_file(None),
+            export_dialog: None,
+            message: None,
+            normalize_import: false,
+            tag_filter: Vec::new(),
+            editing_tags: None
+        }
+    }
+
+    fn passes_tag_filter(&self, pedalboard: &Pedalboard) -> bool {
+        self.tag_filter.iter().all(|tag| pedalboard.has_tag(tag))
+    }
+
+    fn toggle_tag_filter(&mut self, tag: String) {
+        if let Some(index) = self.tag_filter.iter().position(|t| t.eq_ignore_ascii_case(&tag)) {
+            self.tag_filter.remove(index);
+        } else {
+            self.tag_filter.push(tag);
+        }
+    }
+
+    /// Every tag used in the library, sorted and without duplicates (ignoring case)
+    fn library_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for pedalboard in self.state.pedalboards.pedalboard_library.borrow().iter() {
+            for tag in &pedalboard.tags {
+                if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                    tags.push(tag.clone());
+                }
+            }
+        }
+        tags.sort_by_key(|tag| tag.to_lowercase());
+        tags
+    }
+
+    /// Toggles for filtering the library by tag, if any pedalboards have tags
+    fn tag_filter_ui(&mut self, ui: &mut egui::Ui) {
+        let tags = self.library_tags();
+        // Forget filters for tags that no longer exist, otherwise nothing could be shown
+        self.tag_filter.retain(|tag| tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+        if tags.is_empty() {
+            return;
+        }
+
+        ui.add_space(5.0);
+        ui.horizontal_wrapped(|ui| {
+            ui.add_space(20.0);
+            ui.label(RichText::new("Tags:").color(crate::FAINT_TEXT_COLOR));
+            for tag in tags {
+                let selected = self.tag_filter.iter().any(|t| t.eq_ignore_ascii_case(&tag));
+                if ui.selectable_label(selected, &tag).clicked() {
+                    self.toggle_tag_filter(tag);
+                }
+            }
+
+            if !self.tag_filter.is_empty() && ui.button("Clear").clicked() {
+                self.tag_filter.clear();
+            }
+        });
+    }
+
+    /// Window for adding and removing the tags of the pedalboard being edited
+    fn edit_tags_window(&mut self, ctx: &egui::Context) {
+        let Some((pedalboard_id, new_tag)) = &mut self.editing_tags else {
+            return;
+        };
+        let pedalboard_id = *pedalboard_id;
+
+        let (name, tags) = match self.state.pedalboards.pedalboard_library.borrow().iter().find(|p| p.get_id() == pedalboard_id) {
+            Some(pedalboard) => (pedalboard.name.clone(), pedalboard.tags.clone()),
+            None => {
+                // Deleted while editing
+                self.editing_tags = None;
+                return;
+            }
+        };
+
+        let mut open = true;
+        let mut to_add = None;
+        let mut to_remove = None;
+        egui::Window::new(format!("Tags - {name}"))
+            .id(egui::Id::new("edit_pedalboard_tags"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if tags.is_empty() {
+                    ui.label(RichText::new("No tags").color(crate::FAINT_TEXT_COLOR));
+                }
+                ui.horizontal_wrapped(|ui| {
+                    for tag in &tags {
+                        if ui.button(format!("{tag} ✖")).on_hover_text("Remove tag").clicked() {
+                            to_remove = Some(tag.clone());
+                        }
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let response = ui.add(TextEdit::singleline(new_tag).hint_text("New tag..."));
+                    let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if (ui.button("Add").clicked() || entered) && !new_tag.trim().is_empty() {
+                        to_add = Some(std::mem::take(new_tag));
+                        response.request_focus();
+                    }
+                });
+            });
+
+        if let Some(tag) = to_add {
+            self.state.add_pedalboard_tag(pedalboard_id, &tag);
+        }
+        if let Some(tag) = to_remove {
+            self.state.remove_pedalboard_tag(pedalboard_id, &tag);
+        }
+        if !open {
+            self.editing_tags = None;
         }
     }
 
-    pub fn pedalboard_row(ui: &mut egui::Ui, pedalboard: &Pedalboard, row_size: Vec2) -> (Option<RowAction>, egui::Response) {
+    /// Show the import and export dialogs, and perform the import/export once a file is chosen
+    fn show_file_dialogs(&mut self, ctx: &egui::Context) {
+        if self.import_dialog.show(ctx).selected() {
+            if let Some(path) = self.import_dialog.path() {
+                self.message = Some(match self.state.pedalboards.import_pedalboard(path) {
+                    Ok((id, name, missing_files)) => {
+                        let mut message = if missing_files.is_empty() {
+                            format!("Imported {name}")
+                        } else {
+                            format!("Imported {name}, but these files were not found: {}", missing_files.join(", "))
+                        };
+
+                        if self.normalize_import {
+                            if self.state.measure_pedalboard_level(id) {
+                                message.push_str(". Measuring its level...");
+                            } else {
+                                message.push_str(". Its level was not normalized as the processor is not connected.");
+                            }
+                        }
+                        message
+                    },
+                    Err(e) => {
+                        tracing::error!("{e}");
+                        e
+                    }
+                });
+            }
+        }
+
+        if let Some((pedalboard_id, dialog)) = &mut self.export_dialog {
+            if dialog.show(ctx).selected() {
+                if let Some(path) = dialog.path() {
+                    let pedalboard_library = self.state.pedalboards.pedalboard_library.borrow();
+                    if let Some(pedalboard) = pedalboard_library.iter().find(|p| p.get_id() == *pedalboard_id) {
+                        self.message = Some(match SavedPedalboards::export_pedalboard(pedalboard, path) {
+                            Ok(()) => format!("Exported {} to {}", pedalboard.name, path.display()),
+                            Err(e) => {
+                                tracing::error!("{e}");
+                                e
+                            }
+                        });
+                    }
+                }
+                self.export_dialog = None;
+            }
+        }
+    }
+
+    /// The pedalboard name, with the part matching the search highlighted
+    fn highlighted_name(ui: &egui::Ui, name: &str, highlight: Option<&Range<usize>>) -> LayoutJob {
+        let font_id = egui::TextStyle::Body.resolve(ui.style());
+        let normal = TextFormat::simple(font_id.clone(), ui.visuals().text_color());
+        let highlighted = TextFormat {
+            background: crate::THEME_COLOR.gamma_multiply(crate::FAINT_THEME_COLOR_ALPHA),
+            ..TextFormat::simple(font_id, egui::Color32::WHITE)
+        };
+
+        let mut job = LayoutJob::default();
+        match highlight.filter(|range| name.get(range.clone()).is_some()) {
+            Some(range) => {
+                job.append(&name[..range.start], 0.0, normal.clone());
+                job.append(&name[range.clone()], 0.0, highlighted);
+                job.append(&name[range.end..], 0.0, normal);
+            },
+            None => job.append(name, 0.0, normal)
+        }
+        job
+    }
+
+    pub fn pedalboard_row(ui: &mut egui::Ui, pedalboard: &Pedalboard, search_match: &SearchMatch, tag_filter: &[String], row_size: Vec2) -> (Option<RowAction>, egui::Response) {
         let mut action = None;
 
         let row_height = row_size.y;
@@ -30,10 +272,49 @@ impl PedalboardLibraryScreen {
             Layout::left_to_right(egui::Align::Center),
             |ui| {
                 ui.set_min_size(row_size);
+                ui.interact(ui.max_rect(), egui::Id::new("library_row").with(pedalboard.get_id()), egui::Sense::click()).context_menu(|ui| {
+                    if ui.button("Copy").clicked() {
+                        action = Some(RowAction::Copy);
+                        ui.close();
+                    }
+                    if ui.button("Paste").clicked() {
+                        action = Some(RowAction::Paste);
+                        ui.close();
+                    }
+                    if ui.button("Export").clicked() {
+                        action = Some(RowAction::Export);
+                        ui.close();
+                    }
+                    if ui.button("Edit Tags").clicked() {
+                        action = Some(RowAction::EditTags);
+                        ui.close();
+                    }
+                });
                 ui.columns(2, |columns| {
                     columns[0].horizontal_centered(|ui| {
                         ui.add_space(20.0);
-                        ui.label(RichText::new(&pedalboard.name));   
+                        let (star, hover_text) = if pedalboard.favorite {
+                            (RichText::new("★").color(crate::THEME_COLOR), "Remove from the favorites on the stage screen")
+                        } else {
+                            (RichText::new("☆").color(crate::FAINT_TEXT_COLOR), "Add to the favorites on the stage screen")
+                        };
+                        if ui.add(egui::Button::new(star.size(18.0)).frame(false)).on_hover_text(hover_text).clicked() {
+                            action = Some(RowAction::ToggleFavorite);
+                        }
+                        ui.label(Self::highlighted_name(ui, &pedalboard.name, search_match.name.as_ref()));
+
+                        // Show which pedals matched the search
+                        for pedal_type in &search_match.pedal_types {
+                            ui.label(RichText::new(*pedal_type).small().color(crate::THEME_COLOR));
+                        }
+
+                        // Clicking a tag filters the library by it
+                        for tag in &pedalboard.tags {
+                            let selected = tag_filter.iter().any(|t| t.eq_ignore_ascii_case(tag));
+                            if ui.add(egui::Button::new(RichText::new(tag).small()).selected(selected)).clicked() {
+                                action = Some(RowAction::ToggleTagFilter(tag.clone()));
+                            }
+                        }
                     });
 
                     columns[1].allocate_ui_with_layout(
@@ -88,11 +369,37 @@ impl Widget for &mut PedalboardLibraryScreen {
             columns[1].add_sized(
                 [0.0, col_height],
                 TextEdit::singleline(&mut self.search_term)
-                    .hint_text(RichText::new("Search pedalboards..."))
+                    .hint_text(RichText::new("Search pedalboards or pedals..."))
                     .vertical_align(egui::Align::Center)
             );
+
+            columns[2].allocate_ui_with_layout(
+                Vec2::new(0.0, col_height),
+                Layout::top_down(egui::Align::Center),
+                |ui| {
+                    if ui.add_sized(
+                        [ui.available_width()*0.5, col_height*0.9],
+                        egui::Button::new(RichText::new("Import Pedalboard"))
+                    ).clicked() {
+                        self.import_dialog.open();
+                    }
+                    ui.checkbox(&mut self.normalize_import, "Normalize Level")
+                        .on_hover_text("Set the output trim of imported pedalboards so they are as loud as the input. Requires the processor to be connected.");
+            });
         });
 
+        self.show_file_dialogs(ui.ctx());
+        self.edit_tags_window(ui.ctx());
+
+        if let Some(message) = &self.message {
+            ui.add_space(5.0);
+            ui.vertical_centered(|ui| {
+                ui.label(RichText::new(message).color(crate::FAINT_TEXT_COLOR));
+            });
+        }
+
+        self.tag_filter_ui(ui);
+
         ui.add_space(5.0);
         ui.separator();
         ui.add_space(10.0);
@@ -122,8 +429,11 @@ impl Widget for &mut PedalboardLibraryScreen {
                     .spacing(Vec2::new(10.0, 20.0))
                     .show(ui, |ui| {
                         for pedalboard in pedalboard_library.iter() {
-                            if self.search_term.is_empty() || pedalboard.name.contains(&self.search_term) {
-                                PedalboardLibraryScreen::pedalboard_row(ui, pedalboard, row_size).0.map(|row_action| {
+                            if !self.passes_tag_filter(pedalboard) {
+                                continue;
+                            }
+                            if let Some(search_match) = SearchMatch::find(pedalboard, &self.search_term) {
+                                PedalboardLibraryScreen::pedalboard_row(ui, pedalboard, &search_match, &self.tag_filter, row_size).0.map(|row_action| {
                                     action = Some((pedalboard.get_id(), row_action));
                                 });
                                 ui.end_row();
@@ -141,6 +451,32 @@ impl Widget for &mut PedalboardLibraryScreen {
                         RowAction::Delete => {
                             drop(pedalboard_library);
                             self.state.pedalboards.delete_pedalboard(pedalboard_id);
+                        },
+                        RowAction::Copy => {
+                            let pedalboard = pedalboard_library.iter().find(|p| p.get_id() == pedalboard_id).unwrap();
+                            self.state.copy_pedalboard(pedalboard);
+                        },
+                        RowAction::Paste => {
+                            drop(pedalboard_library);
+                            self.state.paste_pedalboard_to_library();
+                        },
+                        RowAction::Export => {
+                            let pedalboard = pedalboard_library.iter().find(|p| p.get_id() == pedalboard_id).unwrap();
+                            let mut dialog = egui_file::FileDialog::save_file(None)
+                                .default_filename(format!("{}.json", pedalboard.name));
+                            dialog.open();
+                            self.export_dialog = Some((pedalboard_id, dialog));
+                        },
+                        RowAction::EditTags => {
+                            self.editing_tags = Some((pedalboard_id, String::new()));
+                        },
+                        RowAction::ToggleTagFilter(tag) => {
+                            self.toggle_tag_filter(tag);
+                        },
+                        RowAction::ToggleFavorite => {
+                            let favorite = pedalboard_library.iter().find(|p| p.get_id() == pedalboard_id).unwrap().favorite;
+                            drop(pedalboard_library);
+                            self.state.set_pedalboard_favorite(pedalboard_id, !favorite);
                         }
                     }
                 };
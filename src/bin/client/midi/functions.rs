@@ -4,9 +4,13 @@ use strum_macros::EnumIter;
 
 use crate::socket::Command;
 
+/// Range of the metronome BPM set by a MIDI control, narrower than the full metronome range so each step of an encoder is a usable tempo
+pub const MIDI_METRONOME_BPM_RANGE: std::ops::RangeInclusive<u32> = 40..=240;
+
 #[derive(Debug, Clone, Serialize, Deserialize, EnumIter, PartialEq)]
 pub enum GlobalMidiFunction {
     ToggleMute,
+    ToggleBypass,
     SetMasterIn,
     SetMasterOut,
     NextPedalboard,
@@ -20,13 +24,21 @@ pub enum GlobalMidiFunction {
     SongsView,
     SettingsView,
     ChangeActiveParameter,
-    ResetVolumeNormalization
+    ResetVolumeNormalization,
+    NextSong,
+    PrevSong,
+    Morph,
+    ToggleTuner,
+    TapTempo,
+    NextScene,
+    SetMetronomeBpm
 }
 
 impl std::fmt::Display for GlobalMidiFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let name = match self {
             GlobalMidiFunction::ToggleMute => "Toggle Mute",
+            GlobalMidiFunction::ToggleBypass => "Toggle Bypass",
             GlobalMidiFunction::SetMasterIn => "Set Master In Volume",
             GlobalMidiFunction::SetMasterOut => "Set Master Out Volume",
             GlobalMidiFunction::NextPedalboard => "Next Pedalboard",
@@ -40,16 +52,40 @@ impl std::fmt::Display for GlobalMidiFunction {
             GlobalMidiFunction::SongsView => "Songs View",
             GlobalMidiFunction::SettingsView => "Settings View",
             GlobalMidiFunction::ChangeActiveParameter => "Change Active Parameter",
-            GlobalMidiFunction::ResetVolumeNormalization => "Reset Volume Normalization"
+            GlobalMidiFunction::ResetVolumeNormalization => "Reset Volume Normalization",
+            GlobalMidiFunction::NextSong => "Next Song",
+            GlobalMidiFunction::PrevSong => "Previous Song",
+            GlobalMidiFunction::Morph => "Morph Amount",
+            GlobalMidiFunction::ToggleTuner => "Toggle Tuner",
+            GlobalMidiFunction::TapTempo => "Tap Tempo",
+            GlobalMidiFunction::NextScene => "Next Scene",
+            GlobalMidiFunction::SetMetronomeBpm => "Set Metronome BPM"
         };
         write!(f, "{name}")
     }
 }
 
 impl GlobalMidiFunction {
+    /// Whether the function sets a value from the device, rather than triggering an action
+    pub fn sets_value(&self) -> bool {
+        matches!(
+            self,
+            GlobalMidiFunction::SetMasterIn |
+            GlobalMidiFunction::SetMasterOut |
+            GlobalMidiFunction::Morph |
+            GlobalMidiFunction::SetMetronomeBpm
+        )
+    }
+
+    /// Whether the function can be triggered without a value, e.g. by an on-screen footswitch
+    pub fn is_action(&self) -> bool {
+        !self.sets_value() && *self != GlobalMidiFunction::ChangeActiveParameter
+    }
+
     pub fn command_from_function(&self, value: f32) -> Command {
         match self {
             GlobalMidiFunction::ToggleMute => Command::ToggleMute,
+            GlobalMidiFunction::ToggleBypass => Command::ToggleBypass,
             GlobalMidiFunction::SetMasterIn => Command::MasterIn(value),
             GlobalMidiFunction::SetMasterOut => Command::MasterOut(value),
             GlobalMidiFunction::NextPedalboard => Command::NextPedalboard,
@@ -63,7 +99,17 @@ impl GlobalMidiFunction {
             GlobalMidiFunction::SongsView => Command::SongsView,
             GlobalMidiFunction::SettingsView => Command::SettingsView,
             GlobalMidiFunction::ChangeActiveParameter => Command::ChangeActiveParameter(value),
-            GlobalMidiFunction::ResetVolumeNormalization => Command::VolumeNormalizationReset
+            GlobalMidiFunction::ResetVolumeNormalization => Command::VolumeNormalizationReset,
+            GlobalMidiFunction::NextSong => Command::NextSong,
+            GlobalMidiFunction::PrevSong => Command::PrevSong,
+            GlobalMidiFunction::Morph => Command::Morph(value),
+            GlobalMidiFunction::ToggleTuner => Command::ToggleTuner,
+            GlobalMidiFunction::TapTempo => Command::TapTempo,
+            GlobalMidiFunction::NextScene => Command::NextScene,
+            GlobalMidiFunction::SetMetronomeBpm => {
+                let (min, max) = (*MIDI_METRONOME_BPM_RANGE.start(), *MIDI_METRONOME_BPM_RANGE.end());
+                Command::SetMetronomeBpm(min + ((max - min) as f32 * value.clamp(0.0, 1.0)).round() as u32)
+            }
         }
     }
 }
@@ -3,7 +3,7 @@ use rs_pedalboard::unique_time_id;
 use rs_pedalboard::pedalboard::ParameterPath;
 use strum::IntoEnumIterator;
 
-use std::{collections::{HashMap, HashSet}, sync::{atomic::AtomicU32, Arc, Mutex}};
+use std::{collections::{HashMap, HashSet, VecDeque}, sync::{atomic::AtomicU32, Arc, Mutex}, time::{Duration, Instant}};
 use midir::{MidiInput, MidiInputConnection, MidiInputPort};
 use serde::{Serialize, Deserialize, Serializer, Deserializer, ser::SerializeStruct};
 use eframe::egui::{self, Id, Rangef, RichText};
@@ -13,6 +13,136 @@ use crossbeam::channel::Sender;
 use crate::{midi::{functions::{GlobalMidiFunction, ParameterMidiFunctionValues}}, socket::{ClientSocketThreadHandle, Command}, SAVE_DIR};
 
 pub const MIDI_SETTINGS_SAVE_NAME: &'static str = "midi_settings.json";
+/// Number of commands waiting for the UI thread above which MIDI messages are arriving faster than they are handled
+const MAX_UI_BACKLOG: usize = 64;
+/// Time over which the message rate of a port is measured
+const MESSAGE_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Largest value of a 14-bit CC
+const HIGH_RES_MAX_VALUE: u16 = 16383;
+/// Number of most recent messages kept by the MIDI monitor
+const MAX_MONITOR_MESSAGES: usize = 200;
+/// Minimum time between repaints for new messages in the MIDI monitor, so fast controllers don't repaint every message
+const MONITOR_REPAINT_INTERVAL: Duration = Duration::from_millis(50);
+/// The monitor stops recording if it hasn't been shown for this long, e.g. when the settings screen is closed
+const MONITOR_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A raw message received from a connected port, shown in the MIDI monitor
+#[derive(Clone, Debug)]
+pub struct MidiMonitorMessage {
+    pub time: chrono::DateTime<chrono::Local>,
+    pub port_id: String,
+    pub bytes: Vec<u8>
+}
+
+impl MidiMonitorMessage {
+    /// Name of the message type and its channel (1-16), from the status byte
+    pub fn describe_status(&self) -> (&'static str, Option<u8>) {
+        let Some(&status) = self.bytes.first() else {
+            return ("Empty", None);
+        };
+
+        let channel = Some((status & 0x0F) + 1);
+        match status & 0xF0 {
+            0x80 => ("Note Off", channel),
+            0x90 => ("Note On", channel),
+            0xA0 => ("Aftertouch", channel),
+            0xB0 => ("Control Change", channel),
+            0xC0 => ("Program Change", channel),
+            0xD0 => ("Channel Pressure", channel),
+            0xE0 => ("Pitch Bend", channel),
+            _ => match status {
+                0xF0 => ("SysEx", None),
+                0xF8 => ("Clock", None),
+                0xFA => ("Start", None),
+                0xFB => ("Continue", None),
+                0xFC => ("Stop", None),
+                0xFE => ("Active Sensing", None),
+                _ => ("System", None)
+            }
+        }
+    }
+}
+
+/// Raw messages from all connected ports, recorded while the MIDI monitor is shown
+struct MidiMonitor {
+    messages: VecDeque<MidiMonitorMessage>,
+    // Time the monitor was last shown, None if it has never been shown
+    last_shown: Option<Instant>,
+    last_repaint: Instant
+}
+
+impl MidiMonitor {
+    fn new() -> Self {
+        Self {
+            messages: VecDeque::with_capacity(MAX_MONITOR_MESSAGES),
+            last_shown: None,
+            last_repaint: Instant::now()
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.last_shown.is_some_and(|shown| shown.elapsed() < MONITOR_TIMEOUT)
+    }
+}
+
+/// Message rate and problems of a connected MIDI port, for diagnosing flaky controllers
+#[derive(Clone, Debug)]
+pub struct MidiPortActivity {
+    window_start: Instant,
+    window_messages: u32,
+    // Messages per second over the last complete window
+    last_rate: f32,
+    // Commands from the port's messages that failed to reach the UI thread
+    pub dropped: u64,
+    // Messages received while the UI thread was behind, which are handled late
+    pub over_rate: u64
+}
+
+impl Default for MidiPortActivity {
+    fn default() -> Self {
+        Self {
+            window_start: Instant::now(),
+            window_messages: 0,
+            last_rate: 0.0,
+            dropped: 0,
+            over_rate: 0
+        }
+    }
+}
+
+impl MidiPortActivity {
+    fn add_message(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= MESSAGE_RATE_WINDOW {
+            // A gap of more than one window means no messages in the last window
+            self.last_rate = if elapsed >= MESSAGE_RATE_WINDOW * 2 {
+                0.0
+            } else {
+                self.window_messages as f32 / elapsed.as_secs_f32()
+            };
+            self.window_start = Instant::now();
+            self.window_messages = 0;
+        }
+        self.window_messages += 1;
+    }
+
+    /// Messages per second received over the last second
+    pub fn rate(&self) -> f32 {
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= MESSAGE_RATE_WINDOW * 2 {
+            0.0
+        } else if elapsed >= MESSAGE_RATE_WINDOW {
+            self.window_messages as f32 / elapsed.as_secs_f32()
+        } else {
+            self.last_rate
+        }
+    }
+
+    pub fn has_problems(&self) -> bool {
+        self.dropped > 0 || self.over_rate > 0
+    }
+}
 
 pub struct MidiState {
     settings: Arc<Mutex<MidiSettings>>,
@@ -22,6 +152,9 @@ pub struct MidiState {
     ui_thread_sender: Sender<Command>,
     socket_handle: Option<ClientSocketThreadHandle>,
     pub active_pedalboard_id: Arc<AtomicU32>,
+    // Message rate and dropped messages of each connected port, by port ID
+    port_activity: Arc<Mutex<HashMap<String, MidiPortActivity>>>,
+    monitor: Arc<Mutex<MidiMonitor>>,
     egui_ctx: egui::Context
 }
 
@@ -49,6 +182,8 @@ impl MidiState {
             socket_handle,
             egui_ctx,
             active_pedalboard_id: Arc::new(AtomicU32::new(active_pedalboard_id)),
+            port_activity: Arc::new(Mutex::new(HashMap::new())),
+            monitor: Arc::new(Mutex::new(MidiMonitor::new())),
             ui_thread_sender
         }
     }
@@ -92,6 +227,11 @@ impl MidiState {
         self.socket_handle = handle;
     }
 
+    /// The settings as they would be saved, to compare before and after they are edited
+    pub fn settings_snapshot(&self) -> String {
+        serde_json::to_string(&*self.settings.lock().expect("MidiState: Mutex poisoned.")).unwrap_or_default()
+    }
+
     pub fn save_settings(&self) -> Result<(), std::io::Error> {
         self.settings.lock().map_err(|_e| std::io::Error::new(std::io::ErrorKind::Other, "MIDI settings mutex poisoned"))?.save()
     }
@@ -133,8 +273,42 @@ impl MidiState {
         }
     }
 
+    /// Add a message to the MIDI monitor, if it is open
+    fn record_monitor_message(monitor: &Mutex<MidiMonitor>, port_id: &str, message: &[u8], egui_ctx: &egui::Context) {
+        let mut monitor = monitor.lock().expect("MidiState: Mutex poisoned.");
+        if !monitor.is_open() {
+            return;
+        }
+
+        if monitor.messages.len() == MAX_MONITOR_MESSAGES {
+            monitor.messages.pop_front();
+        }
+        monitor.messages.push_back(MidiMonitorMessage {
+            time: chrono::Local::now(),
+            port_id: port_id.to_string(),
+            bytes: message.to_vec()
+        });
+
+        if monitor.last_repaint.elapsed() >= MONITOR_REPAINT_INTERVAL {
+            monitor.last_repaint = Instant::now();
+            egui_ctx.request_repaint();
+        } else {
+            egui_ctx.request_repaint_after(MONITOR_REPAINT_INTERVAL);
+        }
+    }
+
+    /// If the CC is the LSB of a 14-bit device on the port, returns the CC of the device (its MSB).
+    /// The LSB of a 14-bit CC is sent on the CC 32 above its MSB.
+    fn high_res_msb_cc(settings: &MidiSettings, port_id: &str, cc: u8, channel: u8) -> Option<u8> {
+        let msb_cc = cc.checked_sub(32).filter(|msb_cc| *msb_cc < 32)?;
+        let device = settings.port_settings.get(port_id)?.devices.get(&(msb_cc, channel))?;
+        matches!(device.device_type, MidiDeviceType::HighResAbsoluteEncoder).then_some(msb_cc)
+    }
+
     fn handle_midi_message(
         settings: &Arc<Mutex<MidiSettings>>,
+        port_activity: &Mutex<HashMap<String, MidiPortActivity>>,
+        monitor: &Mutex<MidiMonitor>,
         port_id: &str,
         message: &[u8],
         ui_thread_sender: &Sender<Command>,
@@ -142,6 +316,9 @@ impl MidiState {
         egui_ctx: &egui::Context,
         active_pedalboard_id: u32
     ) {
+        // The monitor shows every message, including those that aren't mapped
+        Self::record_monitor_message(monitor, port_id, message, egui_ctx);
+
         let (channel, cc, value) = match Self::parse_cc_message(message) {
             Some((channel, cc, value)) => (channel, cc, value),
             None => return
@@ -149,43 +326,134 @@ impl MidiState {
 
         tracing::debug!("Received MIDI CC message on port ID '{}': channel {}, cc {}, value {}", port_id, channel, cc, value);
 
+        let backlogged = ui_thread_sender.len() >= MAX_UI_BACKLOG;
+        let mut dropped = 0;
+
         let mut settings_lock = settings.lock().expect("MidiState: Mutex poisoned.");
 
-        if let Some(device) = Self::device_settings_mut(&mut settings_lock, port_id, cc, channel, egui_ctx) {
-            let old_value = device.current_value;
-            device.update_with_midi_value(value);
-            egui_ctx.request_repaint();
-            if device.current_value != old_value {
-                // Activate any MIDI functions for this device
-                if device.use_global {
-                    for function in &device.global_functions {
-                        let command = function.command_from_function(device.current_value);
-                        if let Err(e) = ui_thread_sender.send(command.clone()) {
-                            tracing::error!("Failed to send global MIDI command to UI thread: {}", e);
-                        }
+        // Messages on filtered out channels don't reach (or create) any device
+        let listening = settings_lock.port_settings.get(port_id).map_or(true, |port_settings| port_settings.listens_to(channel));
 
-                        if let Some(handle) = &socket_handle {
-                            handle.send_command(command);
-                        }
-                    }
-                } else {
-                    for (path, function_values) in &device.parameter_functions {
-                        if path.pedalboard_id != active_pedalboard_id {
-                            continue;
-                        }
+        if !listening {
+            tracing::debug!("Ignoring MIDI message on port ID '{}' channel {}, which is filtered out", port_id, channel);
+        } else {
+            let high_res_msb = Self::high_res_msb_cc(&settings_lock, port_id, cc, channel);
+            let device = match high_res_msb {
+                Some(msb_cc) => settings_lock.port_settings.get_mut(port_id).and_then(|port_settings| port_settings.devices.get_mut(&(msb_cc, channel))),
+                None => Self::device_settings_mut(&mut settings_lock, port_id, cc, channel, egui_ctx)
+            };
+
+            if let Some(device) = device {
+                let old_value = device.current_value;
+                match high_res_msb {
+                    Some(_) => device.update_with_midi_lsb(value),
+                    None => device.update_with_midi_value(value)
+                }
+                egui_ctx.request_repaint();
+                if device.current_value != old_value {
+                    // Activate any MIDI functions for this device
+                    dropped = Self::send_device_functions(device, false, ui_thread_sender, socket_handle, active_pedalboard_id);
+                }
+            }
+        }
+        drop(settings_lock);
 
-                        let command = Command::ParameterUpdate(path.clone(), function_values.parameter_from_value(device.current_value));
-                        if let Err(e) = ui_thread_sender.send(command.clone()) {
-                            tracing::error!("Failed to send parameter MIDI command to UI thread: {}", e);
-                        }
+        let mut activity_lock = port_activity.lock().expect("MidiState: Mutex poisoned.");
+        let activity = activity_lock.entry(port_id.to_string()).or_default();
+        activity.add_message();
+        activity.dropped += dropped as u64;
+        if backlogged {
+            if activity.over_rate == 0 {
+                tracing::warn!("MIDI messages on port ID '{}' are arriving faster than the UI can handle them", port_id);
+            }
+            activity.over_rate += 1;
+        }
+    }
 
-                        if let Some(handle) = &socket_handle {
-                            handle.send_command(command);
-                        }
-                    }
+    /// Send the commands for the functions of a device with its current value.
+    /// Returns the number of commands that failed to reach the UI thread.
+    /// 
+    /// If `values_only` is set, global functions that trigger an action (e.g. toggles) are skipped.
+    fn send_device_functions(
+        device: &MidiDevice,
+        values_only: bool,
+        ui_thread_sender: &Sender<Command>,
+        socket_handle: Option<&ClientSocketThreadHandle>,
+        active_pedalboard_id: u32
+    ) -> usize {
+        let mut dropped = 0;
+
+        if device.use_global {
+            for function in &device.global_functions {
+                if values_only && !function.sets_value() {
+                    continue;
+                }
+
+                let command = function.command_from_function(device.current_value);
+                if let Err(e) = ui_thread_sender.send(command.clone()) {
+                    tracing::error!("Failed to send global MIDI command to UI thread: {}", e);
+                    dropped += 1;
+                }
+
+                if let Some(handle) = &socket_handle {
+                    handle.send_command(command);
+                }
+            }
+        } else {
+            for (path, function_values) in &device.parameter_functions {
+                if path.pedalboard_id != active_pedalboard_id {
+                    continue;
+                }
+
+                let command = Command::ParameterUpdate(path.clone(), function_values.parameter_from_value(device.current_value));
+                if let Err(e) = ui_thread_sender.send(command.clone()) {
+                    tracing::error!("Failed to send parameter MIDI command to UI thread: {}", e);
+                    dropped += 1;
+                }
+
+                if let Some(handle) = &socket_handle {
+                    handle.send_command(command);
                 }
             }
         }
+
+        dropped
+    }
+
+    /// Message rate and problems of a connected port, if it has received any messages
+    pub fn port_activity(&self, port_id: &str) -> Option<MidiPortActivity> {
+        self.port_activity.lock().expect("MidiState: Mutex poisoned.").get(port_id).cloned()
+    }
+
+    /// Reset the dropped and over rate message counts of every port
+    pub fn reset_port_activity(&self) {
+        for activity in self.port_activity.lock().expect("MidiState: Mutex poisoned.").values_mut() {
+            activity.dropped = 0;
+            activity.over_rate = 0;
+        }
+    }
+
+    /// Reset the value of every MIDI device to its neutral value, turning off any latched footswitches.
+    /// Used to recover from values stuck by a controller glitch or disconnect.
+    /// 
+    /// If `send` is set, the reset values are also sent to the UI and processor through the device functions.
+    pub fn reset_device_values(&self, send: bool) {
+        let mut settings_lock = self.settings.lock().expect("MidiState: Mutex poisoned.");
+        let active_pedalboard_id = self.active_pedalboard_id.load(std::sync::atomic::Ordering::Relaxed);
+
+        for (_port_id, port_settings) in settings_lock.port_settings.iter_mut() {
+            for (_cc_channel, device) in port_settings.devices.iter_mut() {
+                let old_value = device.current_value;
+                device.reset_value();
+
+                if send && device.current_value != old_value {
+                    Self::send_device_functions(device, true, &self.ui_thread_sender, self.socket_handle.as_ref(), active_pedalboard_id);
+                }
+            }
+        }
+
+        tracing::info!("Reset MIDI device values");
+        self.egui_ctx.request_repaint();
     }
 
     pub fn connect_to_port(&mut self, id: &str) {
@@ -200,6 +468,8 @@ impl MidiState {
                 };
 
                 let settings_clone = self.settings.clone();
+                let port_activity_clone = self.port_activity.clone();
+                let monitor_clone = self.monitor.clone();
                 let ui_thread_sender_clone = self.ui_thread_sender.clone();
                 let socket_thread_handle_clone = self.socket_handle.clone();
                 let active_pedalboard_id_clone = self.active_pedalboard_id.clone();
@@ -210,6 +480,8 @@ impl MidiState {
                     move |_time, message, data| {
                         Self::handle_midi_message(
                             &settings_clone,
+                            &port_activity_clone,
+                            &monitor_clone,
                             data.as_str(),
                             message,
                             &ui_thread_sender_clone,
@@ -248,6 +520,7 @@ impl MidiState {
 
     pub fn disconnect_from_port(&mut self, id: &str) {
         self.input_connections.retain(|(_name, conn_id, _)| conn_id != id);
+        self.port_activity.lock().expect("MidiState: Mutex poisoned.").remove(id);
         self.refresh_available_ports();
     }
 
@@ -323,6 +596,52 @@ impl MidiState {
         None
     }
 
+    /// Message rate of a connected port, and a warning if any of its messages were dropped or handled late
+    fn port_activity_ui(&self, ui: &mut egui::Ui, port_id: &str) {
+        let Some(activity) = self.port_activity(port_id) else {
+            return;
+        };
+
+        let rate = activity.rate();
+        ui.label(RichText::new(format!("{rate:.0} msg/s")).color(crate::FAINT_TEXT_COLOR))
+            .on_hover_text("MIDI messages received per second");
+        if rate > 0.0 {
+            // Keep the rate updating after the messages stop
+            ui.ctx().request_repaint_after(MESSAGE_RATE_WINDOW);
+        }
+
+        if activity.has_problems() {
+            ui.label(RichText::new(format!("{} dropped, {} late", activity.dropped, activity.over_rate)).color(egui::Color32::from_rgb(255, 165, 50)))
+                .on_hover_text("Messages that didn't reach the UI, and messages received faster than the UI could handle them. Reset with 'Reset Counts'.");
+        }
+    }
+
+    /// Drop down to choose the channels a port listens to
+    fn channel_filter_ui(ui: &mut egui::Ui, port_id: &str, port_settings: &mut MidiPortSettings) {
+        egui::ComboBox::from_id_salt(("midi_port_channels", port_id))
+            .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+            .selected_text(port_settings.channels_text())
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(port_settings.channels.is_empty(), "All Channels").clicked() {
+                    port_settings.channels.clear();
+                }
+
+                for channel in 1..=16 {
+                    let selected = port_settings.channels.contains(&channel);
+                    if ui.selectable_label(selected, format!("Channel {channel}")).clicked() {
+                        if selected {
+                            port_settings.channels.retain(|c| *c != channel);
+                        } else {
+                            port_settings.channels.push(channel);
+                            port_settings.channels.sort_unstable();
+                        }
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Channels this port listens to. Messages on other channels are ignored.");
+    }
+
     /// This UI contains a list of ports that we can connect to, and a list of connected ports.
     /// Connected ports have a list of devices from MidiSettings, that can be removed, edited, etc.
     pub fn midi_port_device_settings_ui(&mut self, ui: &mut egui::Ui) {
@@ -361,7 +680,20 @@ impl MidiState {
 
         ui.add_space(40.0);
 
-        ui.label("Connected MIDI Ports:");
+        ui.horizontal(|ui| {
+            ui.label("Connected MIDI Ports:");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("Reset And Send").on_hover_text("Reset all device values and send them to their functions").clicked() {
+                    self.reset_device_values(true);
+                }
+                if ui.button("Reset Values").on_hover_text("Reset all device values, turning off latched footswitches").clicked() {
+                    self.reset_device_values(false);
+                }
+                if ui.button("Reset Counts").on_hover_text("Reset the dropped and late message counts of all ports").clicked() {
+                    self.reset_port_activity();
+                }
+            });
+        });
 
         let mut settings_lock = self.settings.lock().expect("MidiState: Mutex poisoned.");
         
@@ -386,11 +718,21 @@ impl MidiState {
                         ui.painter().rect_filled(ui.available_rect_before_wrap(), 5.0, crate::LIGHT_BACKGROUND_COLOR);
                         let width = ui.available_width();
                         StripBuilder::new(ui)
-                            .size(Size::Absolute { initial: width*0.5, range: Rangef::new(0.0, width*0.5) }) // Port name
-                            .size(Size::Absolute { initial: width*0.25, range: Rangef::new(0.0, width*0.25) }) // Disconnect
-                            .size(Size::Absolute { initial: width*0.25, range: Rangef::new(0.0, width*0.25) }) // Auto-connect
+                            .size(Size::Absolute { initial: width*0.4, range: Rangef::new(0.0, width*0.4) }) // Port name
+                            .size(Size::Absolute { initial: width*0.2, range: Rangef::new(0.0, width*0.2) }) // Channel filter
+                            .size(Size::Absolute { initial: width*0.2, range: Rangef::new(0.0, width*0.2) }) // Disconnect
+                            .size(Size::Absolute { initial: width*0.2, range: Rangef::new(0.0, width*0.2) }) // Auto-connect
                             .horizontal(|mut strip| {
-                                strip.cell(|ui| { ui.horizontal_centered(|ui| ui.label(port_name.as_str())); });
+                                strip.cell(|ui| {
+                                    ui.horizontal_centered(|ui| {
+                                        ui.label(port_name.as_str());
+                                        self.port_activity_ui(ui, port_id);
+                                    });
+                                });
+                                strip.cell(|ui| {
+                                    let port_settings = settings_lock.port_settings.get_mut(port_id).expect("Any connected port should have an entry in port settings.");
+                                    ui.horizontal_centered(|ui| Self::channel_filter_ui(ui, port_id, port_settings));
+                                });
                                 strip.cell(|ui| {
                                     if ui.horizontal_centered(|ui| ui.button("Disconnect")).inner.clicked() {
                                         disconnect = Some(port_id.clone());
@@ -496,6 +838,16 @@ impl MidiState {
                                                                                 max_value: 127,
                                                                             };
                                                                         }
+                                                                        if ui
+                                                                            .selectable_label(
+                                                                                matches!(device.device_type, MidiDeviceType::HighResAbsoluteEncoder),
+                                                                                "14-bit Absolute Encoder",
+                                                                            )
+                                                                            .on_hover_text("Combines this CC with the CC 32 above it for a smoother sweep, on controllers that support 14-bit CCs")
+                                                                            .clicked()
+                                                                        {
+                                                                            device.device_type = MidiDeviceType::HighResAbsoluteEncoder;
+                                                                        }
                                                                         if ui
                                                                             .selectable_label(
                                                                                 matches!(device.device_type, MidiDeviceType::Footswitch { .. }),
@@ -578,6 +930,61 @@ impl MidiState {
             self.disconnect_from_port(&port_id);
         }
     }
+
+    /// Live list of the raw messages received from all connected ports, newest first.
+    /// Messages are only recorded while the monitor is open.
+    pub fn midi_monitor_ui(&self, ui: &mut egui::Ui) {
+        let response = egui::CollapsingHeader::new("MIDI Monitor")
+            .id_salt("midi_monitor")
+            .show(ui, |ui| {
+                let mut monitor = self.monitor.lock().expect("MidiState: Mutex poisoned.");
+                monitor.last_shown = Some(Instant::now());
+
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(format!("Last {} messages from all connected ports", MAX_MONITOR_MESSAGES)).color(crate::FAINT_TEXT_COLOR));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Clear").clicked() {
+                            monitor.messages.clear();
+                        }
+                    });
+                });
+
+                if monitor.messages.is_empty() {
+                    ui.label("No messages received");
+                    return;
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        egui::Grid::new("midi_monitor_grid")
+                            .striped(true)
+                            .num_columns(5)
+                            .show(ui, |ui| {
+                                for message in monitor.messages.iter().rev() {
+                                    let (status_name, channel) = message.describe_status();
+                                    let port_name = self.input_connections.iter()
+                                        .find(|(_name, id, _connection)| *id == message.port_id)
+                                        .map_or(message.port_id.as_str(), |(name, _id, _connection)| name.as_str());
+
+                                    ui.label(RichText::new(message.time.format("%H:%M:%S%.3f").to_string()).monospace());
+                                    ui.label(port_name);
+                                    ui.label(status_name);
+                                    ui.label(channel.map_or(String::new(), |channel| format!("Ch {channel}")));
+                                    let data: Vec<String> = message.bytes.iter().skip(1).map(|byte| byte.to_string()).collect();
+                                    ui.label(RichText::new(data.join(" ")).monospace());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+
+        if response.body_returned.is_none() {
+            // Stop recording straight away when collapsed, rather than waiting for the timeout
+            self.monitor.lock().expect("MidiState: Mutex poisoned.").last_shown = None;
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -591,6 +998,8 @@ pub struct MidiPortSettings {
     // (cc, channel)
     pub devices: HashMap<(u8, u8), MidiDevice>,
     pub auto_connect: bool,
+    // Channels (1-16) that messages are accepted on, or empty to accept all channels
+    pub channels: Vec<u8>,
 }
 
 impl Default for MidiPortSettings {
@@ -598,6 +1007,23 @@ impl Default for MidiPortSettings {
         MidiPortSettings {
             devices: HashMap::new(),
             auto_connect: true,
+            channels: Vec::new(),
+        }
+    }
+}
+
+impl MidiPortSettings {
+    pub fn listens_to(&self, channel: u8) -> bool {
+        self.channels.is_empty() || self.channels.contains(&channel)
+    }
+
+    /// Text summarising the channel filter, e.g. "All Channels" or "Ch 1, 10"
+    pub fn channels_text(&self) -> String {
+        if self.channels.is_empty() {
+            "All Channels".to_string()
+        } else {
+            let channels: Vec<String> = self.channels.iter().map(|channel| channel.to_string()).collect();
+            format!("Ch {}", channels.join(", "))
         }
     }
 }
@@ -617,9 +1043,10 @@ impl Serialize for MidiPortSettings {
             })
             .collect();
 
-        let mut struct_serializer = serializer.serialize_struct("Port", 2)?;
+        let mut struct_serializer = serializer.serialize_struct("Port", 3)?;
         struct_serializer.serialize_field("devices", &converted)?;
         struct_serializer.serialize_field("auto_connect", &self.auto_connect)?;
+        struct_serializer.serialize_field("channels", &self.channels)?;
 
         struct_serializer.end()
     }
@@ -634,6 +1061,9 @@ impl<'de> Deserialize<'de> for MidiPortSettings {
         struct Port {
             devices: HashMap<String, MidiDevice>,
             auto_connect: bool,
+            // Settings saved before the channel filter was added listen to all channels
+            #[serde(default)]
+            channels: Vec<u8>,
         }
 
         // first deserialize into HashMap<String, HashMap<String, MidiDevice>>
@@ -658,6 +1088,7 @@ impl<'de> Deserialize<'de> for MidiPortSettings {
         Ok(MidiPortSettings {
             devices: actual_map,
             auto_connect: raw.auto_connect,
+            channels: raw.channels,
         })
     }
 }
@@ -742,6 +1173,12 @@ impl MidiDevice {
                 let range = *max_value as f32 - *min_value as f32;
                 self.current_value = (midi_value as f32 - *min_value as f32) / range;
             }
+            MidiDeviceType::HighResAbsoluteEncoder => {
+                // A new MSB resets the LSB, which follows it. Reusing the old LSB (or the MSB) would jump
+                // past the value between the two messages, e.g. when sweeping up from 63/127 to 64/0.
+                let msb = midi_value.min(127) as u16;
+                self.current_value = (msb << 7) as f32 / HIGH_RES_MAX_VALUE as f32;
+            }
             MidiDeviceType::Footswitch {
                 on_value,
                 momentary_to_latching
@@ -767,11 +1204,35 @@ impl MidiDevice {
         }
     }
 
+    /// Set the low 7 bits of a 14-bit device's value, keeping the MSB. Other device types ignore it.
+    pub fn update_with_midi_lsb(&mut self, midi_value: u8) {
+        if let MidiDeviceType::HighResAbsoluteEncoder = self.device_type {
+            let msb = (self.current_value.clamp(0.0, 1.0) * HIGH_RES_MAX_VALUE as f32).round() as u16 >> 7;
+            let lsb = midi_value.min(127) as u16;
+            self.current_value = ((msb << 7) | lsb) as f32 / HIGH_RES_MAX_VALUE as f32;
+        }
+    }
+
+    /// Neutral value of the device, used when it is first created or reset
+    pub fn neutral_value(&self) -> f32 {
+        match &self.device_type {
+            MidiDeviceType::RelativeEncoder { .. } | MidiDeviceType::AbsoluteEncoder { .. } | MidiDeviceType::HighResAbsoluteEncoder => 0.5,
+            MidiDeviceType::Footswitch { .. } => 0.0
+        }
+    }
+
+    pub fn reset_value(&mut self) {
+        self.current_value = self.neutral_value();
+    }
+
     pub fn display_value_string(&self) -> String {
         match &self.device_type {
             MidiDeviceType::RelativeEncoder { .. } | MidiDeviceType::AbsoluteEncoder { .. } => {
                 format!("{:.2}", self.current_value)
             },
+            MidiDeviceType::HighResAbsoluteEncoder => {
+                format!("{:.4}", self.current_value)
+            },
             MidiDeviceType::Footswitch { .. } => {
                 if self.current_value == 1.0 {
                     "On".into()
@@ -794,6 +1255,8 @@ pub enum MidiDeviceType {
         min_value: u8,
         max_value: u8
     },
+    // 14-bit absolute encoder, with the MSB on the device's CC and the LSB on the CC 32 above it
+    HighResAbsoluteEncoder,
     Footswitch {
         on_value: u8,
         momentary_to_latching: bool
@@ -805,6 +1268,7 @@ impl MidiDeviceType {
         match self {
             MidiDeviceType::RelativeEncoder { .. } => "Relative Encoder",
             MidiDeviceType::AbsoluteEncoder { .. } => "Absolute Encoder",
+            MidiDeviceType::HighResAbsoluteEncoder => "14-bit Absolute Encoder",
             MidiDeviceType::Footswitch { .. } => "Footswitch",
         }
     }
@@ -832,6 +1296,11 @@ impl MidiDeviceType {
                 ui.add(egui::Slider::new(max_value, 0..=127));
                 ui.end_row();
             },
+            MidiDeviceType::HighResAbsoluteEncoder => {
+                ui.label("LSB:");
+                ui.label("Sent on the CC 32 above this device's CC (CCs 0-31 only). Without it, this works as a 7-bit encoder.");
+                ui.end_row();
+            },
             MidiDeviceType::Footswitch { on_value, momentary_to_latching } => {
                 ui.label("On Value:");
                 ui.add(egui::Slider::new(on_value, 0..=127));
@@ -846,3 +1315,71 @@ impl MidiDeviceType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn high_res_device() -> MidiDevice {
+        MidiDevice {
+            id: 0,
+            name: "Encoder".to_string(),
+            device_type: MidiDeviceType::HighResAbsoluteEncoder,
+            current_value: 0.5,
+            global_functions: Vec::new(),
+            parameter_functions: HashMap::new(),
+            use_global: true
+        }
+    }
+
+    #[test]
+    fn test_high_res_encoder_combines_msb_and_lsb() {
+        let mut device = high_res_device();
+
+        device.update_with_midi_value(64);
+        device.update_with_midi_lsb(1);
+        assert_eq!(device.current_value, 8193.0 / 16383.0);
+
+        device.update_with_midi_value(127);
+        device.update_with_midi_lsb(127);
+        assert_eq!(device.current_value, 1.0);
+
+        device.update_with_midi_value(0);
+        device.update_with_midi_lsb(0);
+        assert_eq!(device.current_value, 0.0);
+    }
+
+    #[test]
+    fn test_high_res_encoder_with_only_msb() {
+        let mut device = high_res_device();
+
+        // Without an LSB, this works as a 7-bit encoder
+        device.update_with_midi_value(127);
+        assert_eq!(device.current_value, (127 << 7) as f32 / 16383.0);
+        device.update_with_midi_value(0);
+        assert_eq!(device.current_value, 0.0);
+    }
+
+    #[test]
+    fn test_high_res_encoder_sweep_is_monotonic() {
+        let mut device = high_res_device();
+        device.update_with_midi_value(0);
+        device.update_with_midi_lsb(0);
+
+        // Every message of an upwards sweep, including the MSB before its LSB, moves the value up or keeps it
+        let mut previous = device.current_value;
+        for value in 1..=HIGH_RES_MAX_VALUE {
+            let (msb, lsb) = ((value >> 7) as u8, (value & 0x7F) as u8);
+            // Controllers send the MSB when it changes, which is when the LSB wraps around
+            if lsb == 0 {
+                device.update_with_midi_value(msb);
+                assert!(device.current_value >= previous, "MSB of {value} went down");
+                previous = device.current_value;
+            }
+            device.update_with_midi_lsb(lsb);
+            assert!(device.current_value >= previous, "LSB of {value} went down");
+            assert_eq!(device.current_value, value as f32 / HIGH_RES_MAX_VALUE as f32);
+            previous = device.current_value;
+        }
+    }
+}
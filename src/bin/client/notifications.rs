@@ -0,0 +1,64 @@
+use std::{collections::VecDeque, time::{Duration, Instant}};
+
+use eframe::egui::{self, Color32, RichText};
+
+/// How long a notification is shown for
+const NOTIFICATION_DURATION: Duration = Duration::from_secs(5);
+/// Time taken to fade out at the end of the duration
+const NOTIFICATION_FADE: Duration = Duration::from_millis(500);
+/// Older notifications are dropped when there are more than this
+const MAX_NOTIFICATIONS: usize = 4;
+
+/// Transient messages shown in the corner of the window, e.g. errors reported by the processor.
+pub struct Notifications {
+    // Message and the time it was added
+    queue: VecDeque<(String, Instant)>
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::with_capacity(MAX_NOTIFICATIONS)
+        }
+    }
+
+    pub fn push(&mut self, message: String) {
+        // Repeated errors (e.g. from a held MIDI control) restart the existing notification
+        self.queue.retain(|(existing, _)| *existing != message);
+        if self.queue.len() >= MAX_NOTIFICATIONS {
+            self.queue.pop_front();
+        }
+        self.queue.push_back((message, Instant::now()));
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.queue.retain(|(_, added)| added.elapsed() < NOTIFICATION_DURATION);
+        if self.queue.is_empty() {
+            return;
+        }
+
+        egui::Area::new(egui::Id::new("notifications"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::Vec2::new(-10.0, 10.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.set_max_width(ctx.screen_rect().width() * 0.3);
+                for (message, added) in self.queue.iter() {
+                    let remaining = NOTIFICATION_DURATION.saturating_sub(added.elapsed());
+                    let opacity = (remaining.as_secs_f32() / NOTIFICATION_FADE.as_secs_f32()).min(1.0);
+
+                    ui.scope(|ui| {
+                        ui.set_opacity(opacity);
+                        egui::Frame::popup(ui.style())
+                            .stroke(egui::Stroke::new(1.0, Color32::from_rgb(255, 50, 50)))
+                            .show(ui, |ui| {
+                                ui.label(RichText::new(message).color(crate::TEXT_COLOR));
+                            });
+                    });
+                    ui.add_space(5.0);
+                }
+            });
+
+        ctx.request_repaint_after(rs_pedalboard::DEFAULT_REFRESH_DURATION);
+    }
+}
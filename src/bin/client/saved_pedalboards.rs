@@ -1,41 +1,127 @@
-use std::{cell::RefCell, collections::HashMap};
+use std::{cell::{Cell, RefCell}, collections::HashMap, io::Write, path::{Path, PathBuf}};
 
-use rs_pedalboard::{pedalboard::Pedalboard, pedalboard_set::PedalboardSet, SAVE_DIR};
+use rs_pedalboard::{pedalboard::Pedalboard, pedalboard_set::PedalboardSet, pedals::{Pedal, PedalTrait}, SAVE_DIR};
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 
 const SAVE_NAME: &str = "pedalboards.json";
+/// Prefix of the timestamped copies of previous save files, kept in the save directory
+const BACKUP_PREFIX: &str = "pedalboards-backup-";
+/// Number of backups kept, the oldest are deleted first
+const MAX_BACKUPS: usize = 5;
+/// Minimum time between backups, as the state is saved periodically while running
+const BACKUP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// Write a file by writing a temporary file next to it then renaming it over the original,
+/// so the original is left intact if writing fails or is interrupted.
+pub fn write_atomically(path: &Path, write: impl FnOnce(&mut std::fs::File) -> std::io::Result<()>) -> std::io::Result<()> {
+    let temp_path = path.with_extension("json.tmp");
+
+    let result = std::fs::File::create(&temp_path).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()
+    });
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    std::fs::rename(&temp_path, path)
+}
+
+/// An ordered list of pedalboards from the library, played through in order.
+/// The songs library is itself ordered, so it can be used as a setlist.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Song {
+    pub name: String,
+    // Pedalboard IDs in the pedalboard library
+    pub pedalboards: Vec<u32>,
+}
+
+/// Settings of the utilities that are restored when the client is reopened
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct UtilityState {
+    pub metronome_bpm: u32,
+    pub metronome_volume: f32,
+    // Also record the unprocessed input when recording
+    pub recording_save_clean: bool,
+}
+
+impl Default for UtilityState {
+    fn default() -> Self {
+        Self {
+            metronome_bpm: 120,
+            metronome_volume: 0.5,
+            recording_save_clean: true,
+        }
+    }
+}
 
 pub struct SavedPedalboards {
     pub active_pedalboardstage: RefCell<PedalboardSet>,
     pub pedalboard_library: RefCell<Vec<Pedalboard>>,
-    pub songs_library: RefCell<HashMap<String, Vec<u32>>>,
+    pub songs_library: RefCell<Vec<Song>>,
+    // Updated from the state just before saving
+    pub utilities: Cell<UtilityState>,
 }
 
 impl Serialize for SavedPedalboards {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-        let mut state = serializer.serialize_struct("SavedPedalboards", 3)?;
+        let mut state = serializer.serialize_struct("SavedPedalboards", 4)?;
         state.serialize_field("active_pedalboardstage", &*self.active_pedalboardstage.borrow())?;
         state.serialize_field("pedalboard_library", &*self.pedalboard_library.borrow())?;
         state.serialize_field("songs_library", &*self.songs_library.borrow())?;
+        state.serialize_field("utilities", &self.utilities.get())?;
         state.end()
     }
 }
 
 impl<'de> Deserialize<'de> for SavedPedalboards {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        // Songs used to be saved as an unordered map of name to pedalboard IDs
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum SongsData {
+            List(Vec<Song>),
+            Map(HashMap<String, Vec<u32>>)
+        }
+
         #[derive(Deserialize)]
         struct SavedPedalboardsData {
             active_pedalboardstage: PedalboardSet,
             pedalboard_library: Vec<Pedalboard>,
-            songs_library: HashMap<String, Vec<u32>>,
+            songs_library: SongsData,
+            #[serde(default)]
+            utilities: UtilityState,
+        }
+
+        let mut data = SavedPedalboardsData::deserialize(deserializer)?;
+
+        // The stage is indexed by the active pedalboard on load, so a damaged save must not leave it out of range
+        let stage = &mut data.active_pedalboardstage;
+        if stage.pedalboards.is_empty() {
+            tracing::warn!("Saved stage has no pedalboards, using default");
+            *stage = PedalboardSet::default();
+        } else if stage.active_pedalboard >= stage.pedalboards.len() {
+            tracing::warn!("Saved active pedalboard {} is out of range, using the first pedalboard", stage.active_pedalboard);
+            stage.active_pedalboard = 0;
         }
 
-        let data = SavedPedalboardsData::deserialize(deserializer)?;
+        let songs_library = match data.songs_library {
+            SongsData::List(songs) => songs,
+            SongsData::Map(songs) => {
+                let mut songs: Vec<Song> = songs.into_iter().map(|(name, pedalboards)| Song { name, pedalboards }).collect();
+                songs.sort_by(|a, b| a.name.cmp(&b.name));
+                songs
+            }
+        };
 
         Ok(SavedPedalboards {
             active_pedalboardstage: RefCell::new(data.active_pedalboardstage),
             pedalboard_library: RefCell::new(data.pedalboard_library),
-            songs_library: RefCell::new(data.songs_library)
+            songs_library: RefCell::new(songs_library),
+            utilities: Cell::new(data.utilities)
         })
     }
 }
@@ -45,7 +131,8 @@ impl Default for SavedPedalboards {
         SavedPedalboards {
             active_pedalboardstage: RefCell::new(PedalboardSet::default()),
             pedalboard_library: RefCell::new(Vec::new()),
-            songs_library: RefCell::new(HashMap::new()),
+            songs_library: RefCell::new(Vec::new()),
+            utilities: Cell::new(UtilityState::default()),
         }
     }
 }
@@ -89,10 +176,8 @@ impl SavedPedalboards {
 
         // Remove the pedalboard from any songs
         let mut songs = self.songs_library.borrow_mut();
-        for (_, pedalboards) in songs.iter_mut() {
-            if let Some(index) = pedalboards.iter().position(|pedalboard_id| *pedalboard_id == id) {
-                pedalboards.remove(index);
-            }
+        for song in songs.iter_mut() {
+            song.pedalboards.retain(|pedalboard_id| *pedalboard_id != id);
         }
     }
 
@@ -110,57 +195,310 @@ impl SavedPedalboards {
             }
         }
 
-        self.songs_library.borrow_mut().insert(song_name, active_pedalboards.iter().map(|pedalboard| pedalboard.get_id()).collect());
+        let pedalboards = active_pedalboards.iter().map(|pedalboard| pedalboard.get_id()).collect();
+        let mut songs_library = self.songs_library.borrow_mut();
+        if let Some(song) = songs_library.iter_mut().find(|song| song.name == song_name) {
+            song.pedalboards = pedalboards;
+        } else {
+            songs_library.push(Song { name: song_name, pedalboards });
+        }
+    }
+
+    /// Create a pedalboard set from the pedalboards of a song, in order.
+    /// Pedalboards that are no longer in the library are skipped.
+    /// 
+    /// Requires a lock on pedalboard_library and songs_library
+    pub fn song_pedalboard_set(&self, song_index: usize) -> Option<PedalboardSet> {
+        let songs_library = self.songs_library.borrow();
+        let song = songs_library.get(song_index)?;
+        let pedalboard_library = self.pedalboard_library.borrow();
+
+        let pedalboards: Vec<Pedalboard> = song.pedalboards.iter()
+            .filter_map(|id| pedalboard_library.iter().find(|pedalboard| pedalboard.get_id() == *id).cloned())
+            .collect();
+
+        match PedalboardSet::from_pedalboards(pedalboards) {
+            Ok(set) => Some(set),
+            Err(e) => {
+                tracing::warn!("Cannot load song {}: {e}", song.name);
+                None
+            }
+        }
+    }
+
+    fn save_dir() -> Result<PathBuf, std::io::Error> {
+        homedir::my_home()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+            .map(|home| home.join(SAVE_DIR))
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "Failed to resolve home directory"))
     }
 
-    /// Save the pedalboard library into the save file
+    /// Save the pedalboard library into the save file, keeping a backup of the previous save
     /// 
     /// Requires a lock on active_pedalboardstage, pedalboard_library, and songs_library
     pub fn save(&self) -> Result<(), std::io::Error> {
         let stringified = serde_json::to_string(self).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-        let dir_path = homedir::my_home().map_err(
-            |e| std::io::Error::new(std::io::ErrorKind::Other, e)
-        )?.unwrap().join(SAVE_DIR);
+        let dir_path = Self::save_dir()?;
 
         if !dir_path.exists() {
             std::fs::create_dir_all(&dir_path)?;
         }
         let file_path = dir_path.join(SAVE_NAME);
 
-        std::fs::write(file_path, stringified)
+        if Self::needs_backup(&dir_path, &file_path, &stringified) {
+            if let Err(e) = Self::backup(&dir_path, &file_path) {
+                tracing::error!("Failed to back up pedalboards save file: {e}");
+            }
+        }
+
+        write_atomically(&file_path, |file| file.write_all(stringified.as_bytes()))
+    }
+
+    /// Whether the existing save file is about to be changed, and was not backed up recently
+    fn needs_backup(dir_path: &Path, file_path: &Path, new_contents: &str) -> bool {
+        match std::fs::read_to_string(file_path) {
+            Ok(existing) if existing != new_contents => {},
+            _ => return false
+        }
+
+        match Self::backups_in(dir_path).first().and_then(|newest| newest.metadata().and_then(|m| m.modified()).ok()) {
+            Some(modified) => modified.elapsed().map_or(true, |elapsed| elapsed >= BACKUP_INTERVAL),
+            None => true
+        }
+    }
+
+    /// Copy the save file to a new timestamped backup, and delete the oldest backups
+    fn backup(dir_path: &Path, file_path: &Path) -> Result<(), std::io::Error> {
+        let backup_name = format!("{BACKUP_PREFIX}{}.json", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        std::fs::copy(file_path, dir_path.join(backup_name))?;
+
+        for old_backup in Self::backups_in(dir_path).into_iter().skip(MAX_BACKUPS) {
+            std::fs::remove_file(old_backup)?;
+        }
+
+        Ok(())
     }
 
-    /// Load the pedalboard library from the save file, or default
+    /// Backups in the directory, newest first
+    fn backups_in(dir_path: &Path) -> Vec<PathBuf> {
+        let mut backups: Vec<PathBuf> = match std::fs::read_dir(dir_path) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(".json"))
+                )
+                .collect(),
+            Err(_) => Vec::new()
+        };
+
+        // Timestamps in the names sort chronologically
+        backups.sort_by(|a, b| b.cmp(a));
+        backups
+    }
+
+    /// Backups of previous saves, newest first
+    pub fn list_backups() -> Vec<PathBuf> {
+        Self::save_dir().map(|dir_path| Self::backups_in(&dir_path)).unwrap_or_default()
+    }
+
+    fn load_file(path: &Path) -> Result<Self, String> {
+        let stringified = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {e}", path))?;
+        serde_json::from_str::<Self>(&stringified).map_err(|e| format!("Failed to parse {:?}: {e}", path))
+    }
+
+    /// Replace the stage, pedalboard library and songs library with those saved in a backup from `list_backups`
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library, and songs_library
+    pub fn restore_from_backup(&self, path: &Path) -> Result<(), String> {
+        let restored = Self::load_file(path)?;
+        self.active_pedalboardstage.replace(restored.active_pedalboardstage.into_inner());
+        self.pedalboard_library.replace(restored.pedalboard_library.into_inner());
+        self.songs_library.replace(restored.songs_library.into_inner());
+        tracing::info!("Restored pedalboards from backup {:?}", path);
+        Ok(())
+    }
+
+    /// Load the pedalboard library from the save file, or the newest valid backup if it is unreadable, or default
     pub fn load_or_default() -> Self {
-        let file_path = match homedir::my_home() {
-            Ok(Some(home)) => home.join(SAVE_DIR).join(SAVE_NAME),
-            Ok(None) => {
-                tracing::error!("Failed to resolve home directory, using default");
-                return Self::default();
-            }
+        let dir_path = match Self::save_dir() {
+            Ok(dir_path) => dir_path,
             Err(e) => {
-                tracing::error!("Error resolving home directory: {e}, using default");
+                tracing::error!("Error resolving save directory: {e}, using default");
                 return Self::default();
             }
         };
+        let file_path = dir_path.join(SAVE_NAME);
 
         if !file_path.exists() {
             tracing::info!("Pedalboard save file not found, using default");
             return Self::default();
         }
 
-        match std::fs::read_to_string(&file_path) {
-            Ok(stringified) => match serde_json::from_str::<Self>(&stringified) {
-                Ok(state) => state,
-                Err(e) => {
-                    tracing::error!("Failed to parse save file {:?}: {e}, using default", file_path);
-                    Self::default()
-                }
-            },
+        match Self::load_file(&file_path) {
+            Ok(state) => state,
             Err(e) => {
-                tracing::error!("Failed to read save file {:?}: {e}, using default", file_path);
+                tracing::error!("{e}, trying backups");
+                for backup in Self::backups_in(&dir_path) {
+                    match Self::load_file(&backup) {
+                        Ok(state) => {
+                            tracing::warn!("Loaded pedalboards from backup {:?}", backup);
+                            return state;
+                        },
+                        Err(e) => tracing::error!("{e}")
+                    }
+                }
+
+                tracing::error!("No valid pedalboard backups, using default");
                 Self::default()
             }
         }
     }
+
+    /// Write a single pedalboard to a file so it can be shared.
+    /// 
+    /// NAM, IR and VST2 paths inside their default save directories are written as relative paths.
+    pub fn export_pedalboard(pedalboard: &Pedalboard, path: &Path) -> Result<(), String> {
+        let stringified = serde_json::to_string_pretty(pedalboard).map_err(|e| format!("Failed to serialize pedalboard: {e}"))?;
+        std::fs::write(path, stringified).map_err(|e| format!("Failed to write {:?}: {e}", path))
+    }
+
+    /// Read a pedalboard exported with `export_pedalboard` and add it to the pedalboard library.
+    /// 
+    /// The pedalboard is given a new ID and a unique name. Returns the new ID, the name it was imported as,
+    /// and the referenced files (NAM models, IRs, VST2 plugins) that could not be found.
+    /// 
+    /// Requires a lock on active_pedalboardstage and pedalboard_library
+    pub fn import_pedalboard(&self, path: &Path) -> Result<(u32, String, Vec<String>), String> {
+        let stringified = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {e}", path))?;
+
+        // Check the general structure first, so an unrelated JSON file gives a useful error
+        let value: serde_json::Value = serde_json::from_str(&stringified).map_err(|e| format!("Invalid pedalboard file: {e}"))?;
+        if !value.get("pedals").is_some_and(|pedals| pedals.is_array()) {
+            return Err("Invalid pedalboard file: missing pedals".to_string());
+        }
+
+        let pedalboard: Pedalboard = serde_json::from_value(value).map_err(|e| format!("Invalid pedalboard file: {e}"))?;
+        let mut new_pedalboard = pedalboard.clone_with_new_id();
+        new_pedalboard.name = self.unique_name(new_pedalboard.name.clone());
+
+        let missing_files = Self::missing_referenced_files(&new_pedalboard);
+        for missing in missing_files.iter() {
+            tracing::warn!("Imported pedalboard references missing file {:?}", missing);
+        }
+
+        let (id, name) = (new_pedalboard.get_id(), new_pedalboard.name.clone());
+        self.pedalboard_library.borrow_mut().push(new_pedalboard);
+        Ok((id, name, missing_files))
+    }
+
+    /// Get all files referenced by pedals in the pedalboard that don't exist
+    fn missing_referenced_files(pedalboard: &Pedalboard) -> Vec<String> {
+        let mut missing = Vec::new();
+
+        for pedal in pedalboard.pedals.iter() {
+            let parameter_name = match pedal {
+                Pedal::Nam(_) => "Model",
+                Pedal::ImpulseResponse(_) => "IR",
+                Pedal::Vst2(_) => "Plugin",
+                _ => continue
+            };
+
+            if let Some(path) = pedal.get_parameters().get(parameter_name).and_then(|p| p.value.as_str()) {
+                if !path.is_empty() && !Path::new(path).exists() {
+                    missing.push(path.to_string());
+                }
+            }
+        }
+
+        missing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rs_pedalboard_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_interrupted_save_keeps_previous_file() {
+        let dir = test_dir("interrupted_save");
+        let path = dir.join(SAVE_NAME);
+        write_atomically(&path, |file| file.write_all(b"good")).unwrap();
+
+        let result = write_atomically(&path, |file| {
+            file.write_all(b"partial")?;
+            Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "Simulated interruption"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "good");
+        assert!(!path.with_extension("json.tmp").exists());
+
+        write_atomically(&path, |file| file.write_all(b"new")).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_oldest_backups_are_removed() {
+        let dir = test_dir("backup_rotation");
+        let path = dir.join(SAVE_NAME);
+        std::fs::write(&path, "save").unwrap();
+
+        for i in 0..MAX_BACKUPS + 2 {
+            std::fs::write(dir.join(format!("{BACKUP_PREFIX}20000101-0000{i:02}.json")), "old").unwrap();
+        }
+        SavedPedalboards::backup(&dir, &path).unwrap();
+
+        let backups = SavedPedalboards::backups_in(&dir);
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        // The new backup is timestamped now, so is newest
+        assert_eq!(std::fs::read_to_string(&backups[0]).unwrap(), "save");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let saved = SavedPedalboards::default();
+        {
+            let mut stage = saved.active_pedalboardstage.borrow_mut();
+            stage.pedalboards.push(Pedalboard::new("Second".to_string()));
+            stage.pedalboards.push(Pedalboard::new("Third".to_string()));
+            stage.active_pedalboard = 2;
+        }
+        let utilities = UtilityState { metronome_bpm: 96, metronome_volume: 0.8, recording_save_clean: false };
+        saved.utilities.set(utilities);
+
+        let loaded: SavedPedalboards = serde_json::from_str(&serde_json::to_string(&saved).unwrap()).unwrap();
+        let stage = loaded.active_pedalboardstage.borrow();
+        assert_eq!(stage.pedalboards.len(), 3);
+        assert_eq!(stage.active_pedalboard, 2);
+        assert_eq!(stage.pedalboards[2].name, "Third");
+        assert_eq!(loaded.utilities.get(), utilities);
+    }
+
+    #[test]
+    fn test_old_save_loads_with_valid_active_index() {
+        let mut set = serde_json::to_value(PedalboardSet::default()).unwrap();
+        set["active_pedalboard"] = serde_json::json!(5);
+        let old_save = serde_json::json!({
+            "active_pedalboardstage": set,
+            "pedalboard_library": [],
+            "songs_library": []
+        });
+
+        let loaded: SavedPedalboards = serde_json::from_value(old_save).unwrap();
+        assert_eq!(loaded.active_pedalboardstage.borrow().active_pedalboard, 0);
+        assert_eq!(loaded.utilities.get(), UtilityState::default());
+    }
 }
@@ -1,17 +1,23 @@
-use std::{process::Child, time::Instant, path::PathBuf};
+use std::{io::Write, process::Child, time::Instant, path::PathBuf};
 
 use cpal::{Host, HostId};
 use eframe::egui::{self, Color32, Layout, Response, RichText, Vec2, Widget};
-use rs_pedalboard::processor_settings::ProcessorSettingsSave;
+use rs_pedalboard::processor_settings::{DisconnectAction, FxLoopChannels, ProcessorSettingsSave, MIN_OUTPUT_CEILING_DB};
+use rs_pedalboard::pedals::{FileWatcher, KnobSkin, KnobStyle, PedalCategory, PedalDiscriminants, PlotRange};
+use rs_pedalboard::dsp_algorithms::{resampler::ResamplerQuality, yin::Temperament};
 use serde::{Deserialize, Serialize};
 use strum::{IntoEnumIterator};
 use strum_macros::EnumIter;
 
+use crate::midi::functions::GlobalMidiFunction;
 use crate::state::State;
+use crate::saved_pedalboards::{write_atomically, SavedPedalboards};
 use crate::audio_processor_handler::start_processor_process;
 use rs_pedalboard::{audio_devices::{get_input_devices, get_output_devices}, processor_settings::{SupportedHost}, SAVE_DIR};
 
 pub const CLIENT_SAVE_NAME: &'static str = "client_settings.json";
+/// Most buttons in the virtual footswitch bank, so they stay large enough to press
+const MAX_VIRTUAL_FOOTSWITCHES: usize = 8;
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, EnumIter, PartialEq, Default)]
 pub enum VolumeNormalizationMode {
@@ -21,12 +27,72 @@ pub enum VolumeNormalizationMode {
     Automatic
 }
 
+/// Accent colours outlining pedals in the pedalboard designer by their category
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct CategoryColors {
+    pub enabled: bool,
+    pub drive: [u8; 3],
+    pub modulation: [u8; 3],
+    pub time: [u8; 3],
+    pub dynamics: [u8; 3],
+    pub utility: [u8; 3],
+}
+
+impl Default for CategoryColors {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            // Drive uses the theme colour, the others are picked to stand out from it on the dark background
+            drive: [255, 105, 46],
+            modulation: [170, 110, 255],
+            time: [60, 160, 255],
+            dynamics: [80, 200, 120],
+            utility: [150, 150, 150],
+        }
+    }
+}
+
+impl CategoryColors {
+    pub fn color_mut(&mut self, category: PedalCategory) -> &mut [u8; 3] {
+        match category {
+            PedalCategory::Drive => &mut self.drive,
+            PedalCategory::Modulation => &mut self.modulation,
+            PedalCategory::Time => &mut self.time,
+            PedalCategory::Dynamics => &mut self.dynamics,
+            PedalCategory::Utility => &mut self.utility,
+        }
+    }
+
+    /// The accent colour of a category, or None if category colours are disabled
+    pub fn color(&self, category: PedalCategory) -> Option<Color32> {
+        if !self.enabled {
+            return None;
+        }
+
+        let [r, g, b] = match category {
+            PedalCategory::Drive => self.drive,
+            PedalCategory::Modulation => self.modulation,
+            PedalCategory::Time => self.time,
+            PedalCategory::Dynamics => self.dynamics,
+            PedalCategory::Utility => self.utility,
+        };
+        Some(Color32::from_rgb(r, g, b))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(default)]
 pub struct ClientSettings {
     pub startup_processor: bool,
+    // Start the processor on startup and, once connected, play the last stage on the stage screen
+    pub ready_to_play_on_startup: bool,
     pub kill_processor_on_close: bool,
     pub show_volume_monitor: bool,
+    // Show a light on the stage screen when there is signal at the input, using the volume monitor levels
+    pub signal_led: bool,
+    // Input peak level in dB above which the signal light is lit
+    pub signal_led_threshold_db: f32,
     pub volume_normalization: VolumeNormalizationMode,
     // Only used if volume_normalization is set to Automatic
     pub auto_volume_normalization_decay: f32,
@@ -35,9 +101,46 @@ pub struct ClientSettings {
     pub nam_folders: Vec<PathBuf>,
     pub ir_folders: Vec<PathBuf>,
     pub vst2_folders: Vec<PathBuf>,
+    // Most recently added pedals first, shown at the top of the pedal picker
+    pub recent_pedals: Vec<PedalDiscriminants>,
+    // Step into the next/previous song when stepping past the end/start of a song's pedalboards
+    pub advance_to_next_song: bool,
+    // Number of xruns within the reset time before the xrun indicator shows many
+    pub xrun_many_threshold: usize,
+    // Seconds without an xrun before the xrun indicator resets
+    pub xrun_reset_secs: f32,
+    // Mirror the processor without changing it, e.g. on a second screen showing another client's rig
+    pub observer_mode: bool,
+    // Pitch of A4 in hz that the tuner shows notes relative to
+    pub tuner_reference: f32,
+    pub tuner_temperament: Temperament,
+    // Silence the output while the tuner is open
+    pub mute_while_tuning: bool,
+    pub knob_style: KnobStyle,
+    // Number of points and frequency range of pedal frequency plots, e.g. the EQ response and live spectrum
+    pub plot_range: PlotRange,
+    pub category_colors: CategoryColors,
+    // Show large pedalboard buttons on the stage screen instead of the editing controls
+    pub performance_mode: bool,
+    // In performance mode, pause the volume monitor and signal light updates to save CPU on low power machines
+    pub freeze_ui_updates: bool,
+    // Show a bank of buttons on the stage screen that trigger global functions, for use without a MIDI controller
+    pub show_virtual_footswitches: bool,
+    pub virtual_footswitches: Vec<GlobalMidiFunction>,
+    // Reload IR and NAM files shown on the stage when they are changed by another program
+    pub hot_reload_files: bool,
+    // Log commands in full rather than truncated, for debugging
+    pub log_full_commands: bool,
+    // Seconds between saves of the pedalboards and settings while there are unsaved changes
+    pub auto_save_secs: u32,
 }
 
 impl ClientSettings {
+    /// Whether the processor should send volume monitor levels, for the volume monitor or the signal light
+    pub fn volume_monitor_needed(&self) -> bool {
+        self.show_volume_monitor || self.signal_led
+    }
+
     fn get_save_path() -> Option<PathBuf> {
         Some(homedir::my_home().ok()??.join(SAVE_DIR).join(CLIENT_SAVE_NAME))
     }
@@ -73,8 +176,8 @@ impl ClientSettings {
 
     pub fn save(&self) -> Result<(), std::io::Error> {
         let data = serde_json::to_string(self).expect("Failed to serialize client settings");
-        std::fs::write(Self::get_save_path().expect("Failed to get client settings save path"), data)?;
-        Ok(())
+        // Saved periodically while running, so don't leave a half written file if the client crashes
+        write_atomically(&Self::get_save_path().expect("Failed to get client settings save path"), |file| file.write_all(data.as_bytes()))
     }
 }
 
@@ -82,8 +185,11 @@ impl Default for ClientSettings {
     fn default() -> Self {
         Self {
             startup_processor: true,
+            ready_to_play_on_startup: false,
             kill_processor_on_close: true,
             show_volume_monitor: true,
+            signal_led: true,
+            signal_led_threshold_db: -50.0,
             volume_normalization: VolumeNormalizationMode::None,
             auto_volume_normalization_decay: 0.95,
             input_volume: 1.0,
@@ -91,6 +197,30 @@ impl Default for ClientSettings {
             nam_folders: vec![],
             ir_folders: vec![],
             vst2_folders: vec![],
+            recent_pedals: vec![],
+            advance_to_next_song: false,
+            xrun_many_threshold: 10,
+            xrun_reset_secs: 2.0,
+            observer_mode: false,
+            tuner_reference: 440.0,
+            tuner_temperament: Temperament::Equal,
+            mute_while_tuning: false,
+            knob_style: KnobStyle::default(),
+            plot_range: PlotRange::default(),
+            category_colors: CategoryColors::default(),
+            performance_mode: false,
+            freeze_ui_updates: false,
+            show_virtual_footswitches: false,
+            virtual_footswitches: vec![
+                GlobalMidiFunction::PrevPedalboard,
+                GlobalMidiFunction::NextPedalboard,
+                GlobalMidiFunction::TapTempo,
+                GlobalMidiFunction::ToggleTuner,
+                GlobalMidiFunction::ToggleMute
+            ],
+            hot_reload_files: false,
+            log_full_commands: false,
+            auto_save_secs: 30,
         }
     }
 }
@@ -160,6 +290,9 @@ pub struct SettingsScreen {
     nam_file_dialog: egui_file::FileDialog,
     ir_file_dialog: egui_file::FileDialog,
     vst2_file_dialog: egui_file::FileDialog,
+
+    pedalboard_backups: Vec<PathBuf>,
+    selected_backup: Option<PathBuf>,
 }
 
 impl SettingsScreen {
@@ -171,6 +304,8 @@ impl SettingsScreen {
             nam_file_dialog: egui_file::FileDialog::select_folder(None),
             ir_file_dialog: egui_file::FileDialog::select_folder(None),
             vst2_file_dialog: egui_file::FileDialog::select_folder(None),
+            pedalboard_backups: SavedPedalboards::list_backups(),
+            selected_backup: None,
         }
     }
 
@@ -247,10 +382,12 @@ impl Widget for &mut SettingsScreen {
     fn ui(self, ui: &mut egui::Ui) -> Response {
         self.handle_processor_launch();
 
+        let settings_before = self.state.settings_snapshot();
+
         let mut processor_settings = self.state.processor_settings.borrow_mut();
         let mut client_settings = self.state.client_settings.borrow_mut();
 
-        ui.allocate_ui_with_layout(ui.available_size(), Layout::left_to_right(egui::Align::Center), |ui| {
+        let response = ui.allocate_ui_with_layout(ui.available_size(), Layout::left_to_right(egui::Align::Center), |ui| {
             ui.add_space(ui.available_width()*0.05);
             ui.allocate_ui_with_layout(ui.available_size()*Vec2::new(0.95, 1.0), Layout::top_down(egui::Align::Min), |ui| {
                 egui::ScrollArea::vertical().show(ui, |ui| {
@@ -382,9 +519,12 @@ impl Widget for &mut SettingsScreen {
                             ).on_hover_text("Higher values may improve accuracy but increase computation, and decrease update time.");
                             ui.end_row();
 
+                            // Sample rate changes are applied to a running processor where possible
+                            let mut sample_rate_changed = false;
+
                             // Preferred Sample Rate
                             ui.label("\tPreferred Sample Rate");
-                            egui::ComboBox::from_id_salt("preferred_sample_rate_dropdown")
+                            sample_rate_changed |= egui::ComboBox::from_id_salt("preferred_sample_rate_dropdown")
                                 .selected_text(match processor_settings.preferred_sample_rate {
                                     Some(rate) => format!("{rate}hz"),
                                     None => "Default".to_string()
@@ -399,12 +539,12 @@ impl Widget for &mut SettingsScreen {
                                     response |= ui.selectable_value(&mut processor_settings.preferred_sample_rate, Some(176400), "176400hz");
                                     response |= ui.selectable_value(&mut processor_settings.preferred_sample_rate, Some(192000), "192000hz");
                                     response
-                                });
+                                }).inner.is_some_and(|r| r.changed());
                             ui.end_row();
                             
                             // Upsample Passes
                             ui.label("\tUpsample");
-                            egui::ComboBox::from_id_salt("upsample_dropdown")
+                            sample_rate_changed |= egui::ComboBox::from_id_salt("upsample_dropdown")
                                 .selected_text(match processor_settings.upsample_passes {
                                     0 => "None",
                                     1 => "2x",
@@ -419,7 +559,130 @@ impl Widget for &mut SettingsScreen {
                                     response |= ui.selectable_value(&mut processor_settings.upsample_passes, 2, "4x");
                                     response |= ui.selectable_value(&mut processor_settings.upsample_passes, 3, "8x");
                                     response
+                                }).inner.is_some_and(|r| r.changed());
+                            ui.end_row();
+
+                            // Internal Sample Rate
+                            ui.label("\tInternal Sample Rate");
+                            let internal_sample_rate_response = egui::ComboBox::from_id_salt("internal_sample_rate_dropdown")
+                                .selected_text(match processor_settings.internal_sample_rate {
+                                    Some(rate) => format!("{rate}hz"),
+                                    None => "Device".to_string()
+                                })
+                                .wrap_mode(egui::TextWrapMode::Truncate)
+                                .show_ui(ui, |ui| {
+                                    let mut response = ui.selectable_value(&mut processor_settings.internal_sample_rate, None, "Device");
+                                    response |= ui.selectable_value(&mut processor_settings.internal_sample_rate, Some(44100), "44100hz");
+                                    response |= ui.selectable_value(&mut processor_settings.internal_sample_rate, Some(48000), "48000hz");
+                                    response |= ui.selectable_value(&mut processor_settings.internal_sample_rate, Some(88200), "88200hz");
+                                    response |= ui.selectable_value(&mut processor_settings.internal_sample_rate, Some(96000), "96000hz");
+                                    response |= ui.selectable_value(&mut processor_settings.internal_sample_rate, Some(192000), "192000hz");
+                                    response
                                 });
+                            internal_sample_rate_response.response.on_hover_text("Process pedals at this sample rate regardless of the device sample rate. Replaces upsampling if set.");
+                            sample_rate_changed |= internal_sample_rate_response.inner.is_some_and(|r| r.changed());
+                            ui.end_row();
+
+                            // Resampler Quality
+                            ui.label("\tResampler Quality");
+                            egui::ComboBox::from_id_salt("resampler_quality_dropdown")
+                                .selected_text(processor_settings.resampler_quality.to_string())
+                                .wrap_mode(egui::TextWrapMode::Truncate)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut processor_settings.resampler_quality, ResamplerQuality::Fast, "Fast");
+                                    ui.selectable_value(&mut processor_settings.resampler_quality, ResamplerQuality::Medium, "Medium");
+                                    ui.selectable_value(&mut processor_settings.resampler_quality, ResamplerQuality::High, "High");
+                                }).response.on_hover_text("Quality of the resampling for upsampling or an internal sample rate. Higher quality uses more CPU.");
+                            ui.end_row();
+
+                            if sample_rate_changed && self.state.is_connected() {
+                                self.state.set_processor_sample_rate(
+                                    processor_settings.preferred_sample_rate,
+                                    processor_settings.upsample_passes,
+                                    processor_settings.internal_sample_rate
+                                );
+                            }
+
+                            // FX Loop Channels
+                            ui.label("\tFX Loop");
+                            ui.horizontal(|ui| {
+                                let mut enabled = processor_settings.fx_loop.is_some();
+                                if ui.checkbox(&mut enabled, "").changed() {
+                                    processor_settings.fx_loop = enabled.then_some(FxLoopChannels { send_channel: 1, return_channel: 1 });
+                                }
+
+                                // Channels are shown 1-indexed
+                                if let Some(fx_loop) = &mut processor_settings.fx_loop {
+                                    ui.add(egui::DragValue::new(&mut fx_loop.send_channel)
+                                        .range(0..=63)
+                                        .custom_formatter(|value, _| format!("Send: Out {}", value as usize + 1))
+                                        .custom_parser(|text| text.trim_start_matches("Send: Out ").parse::<f64>().ok().map(|v| v - 1.0))
+                                    );
+                                    ui.add(egui::DragValue::new(&mut fx_loop.return_channel)
+                                        .range(0..=63)
+                                        .custom_formatter(|value, _| format!("Return: In {}", value as usize + 1))
+                                        .custom_parser(|text| text.trim_start_matches("Return: In ").parse::<f64>().ok().map(|v| v - 1.0))
+                                    );
+                                }
+                            }).response.on_hover_text("Physical channels used by the FX Loop pedal to insert external hardware. Not available when upsampling.");
+                            ui.end_row();
+
+                            // Dry Monitor Channel
+                            ui.label("\tDry Monitor");
+                            ui.horizontal(|ui| {
+                                let mut enabled = processor_settings.dry_monitor_channel.is_some();
+                                if ui.checkbox(&mut enabled, "").changed() {
+                                    processor_settings.dry_monitor_channel = enabled.then_some(1);
+                                }
+
+                                if let Some(channel) = &mut processor_settings.dry_monitor_channel {
+                                    ui.add(egui::DragValue::new(channel)
+                                        .range(0..=63)
+                                        .custom_formatter(|value, _| format!("Out {}", value as usize + 1))
+                                        .custom_parser(|text| text.trim_start_matches("Out ").parse::<f64>().ok().map(|v| v - 1.0))
+                                    );
+                                }
+                            }).response.on_hover_text("While recording, send the unprocessed input to this output channel for monitoring");
+                            ui.end_row();
+
+                            // Input Channel
+                            ui.label("\tInput Channel");
+                            ui.horizontal(|ui| {
+                                let mut enabled = processor_settings.input_channel.is_some();
+                                if ui.checkbox(&mut enabled, "").changed() {
+                                    processor_settings.input_channel = enabled.then_some(0);
+                                }
+
+                                if let Some(channel) = &mut processor_settings.input_channel {
+                                    ui.add(egui::DragValue::new(channel)
+                                        .range(0..=63)
+                                        .custom_formatter(|value, _| format!("In {}", value as usize + 1))
+                                        .custom_parser(|text| text.trim_start_matches("In ").parse::<f64>().ok().map(|v| v - 1.0))
+                                    );
+                                }
+                            }).response.on_hover_text("Only process this input channel, e.g. the instrument input of a multi-input interface. If off, all input channels are mixed.");
+                            ui.end_row();
+
+                            // Output Ceiling
+                            ui.label("\tOutput Ceiling");
+                            ui.add(egui::DragValue::new(&mut processor_settings.output_ceiling_db)
+                                .range(MIN_OUTPUT_CEILING_DB..=0.0)
+                                .speed(0.1)
+                                .fixed_decimals(1)
+                                .suffix(" dB")
+                            ).on_hover_text("Highest output volume, to protect your ears. Nothing can raise the output above this, and the output limiter is always applied below 0 dB.");
+                            ui.end_row();
+
+                            // On Disconnect
+                            ui.label("\tOn Disconnect");
+                            egui::ComboBox::from_id_salt("on_disconnect_dropdown")
+                                .selected_text(processor_settings.on_disconnect.to_string())
+                                .wrap_mode(egui::TextWrapMode::Truncate)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut processor_settings.on_disconnect, DisconnectAction::KeepPlaying, "Keep Playing");
+                                    ui.selectable_value(&mut processor_settings.on_disconnect, DisconnectAction::Mute, "Mute");
+                                    ui.selectable_value(&mut processor_settings.on_disconnect, DisconnectAction::LoadDefault, "Load Default");
+                                }).response.on_hover_text("What the processor does once the client disconnects, e.g. if the client crashes. Load Default replaces the pedalboards with a clean pedalboard.");
 
                             ui.end_row()
                         });
@@ -481,6 +744,10 @@ impl Widget for &mut SettingsScreen {
 
                     ui.add_space(15.0);
 
+                    if let Some(reason) = self.state.sample_rate_change_error.borrow().as_ref() {
+                        ui.label(RichText::new(reason).color(Color32::RED));
+                    }
+
                     match self.processor_launch_state {
                         ProcessorLaunchState::StartError => { ui.label(RichText::new("Failed to start processor. Check the logs for more details.").color(Color32::RED)); },
                         ProcessorLaunchState::KillError => { ui.label(RichText::new("Failed to stop processor. Check the logs for more details.").color(Color32::RED)); },
@@ -545,6 +812,18 @@ impl Widget for &mut SettingsScreen {
                             };
                             ui.end_row();
 
+                            ui.label("Pre Gain");
+                            if ui.add_sized(
+                                Vec2::new(ui.available_width(), 45.0),
+                                egui::Slider::new(&mut processor_settings.pre_gain_db, 0.0..=24.0)
+                                    .show_value(true)
+                                    .fixed_decimals(1)
+                                    .suffix(" dB")
+                            ).on_hover_text("Clean boost before the pedalboard, to drive the whole chain harder").changed() {
+                                self.state.set_pre_gain_processor(processor_settings.pre_gain_db);
+                            };
+                            ui.end_row();
+
                             ui.label("Output Volume");
                             if ui.add_sized(
                                 Vec2::new(ui.available_width(), 45.0),
@@ -575,16 +854,233 @@ impl Widget for &mut SettingsScreen {
                             ui.checkbox(&mut client_settings.startup_processor, "");
                             ui.end_row();
 
+                            ui.label("Ready To Play On Startup");
+                            ui.checkbox(&mut client_settings.ready_to_play_on_startup, "")
+                                .on_hover_text("Start the processor on startup, even if Startup Processor is off, then play the last stage once connected.");
+                            ui.end_row();
+
                             ui.label("Kill Processor on Close");
                             ui.checkbox(&mut client_settings.kill_processor_on_close, "");
                             ui.end_row();
 
+                            ui.label("Output Limiter");
+                            if ui.checkbox(&mut processor_settings.output_limiter, "")
+                                .on_hover_text("Softly limit peaks on the output to prevent harsh digital clipping")
+                                .changed()
+                            {
+                                self.state.set_output_limiter_processor(processor_settings.output_limiter);
+                            }
+                            ui.end_row();
+
                             ui.label("Show Volume Monitor");
                             let volume_monitor_message = "This can affect performance as the UI will have to frequently update";
                             if ui.checkbox(&mut client_settings.show_volume_monitor, "").on_hover_text(volume_monitor_message).changed() {
-                                self.state.set_volume_monitor_active_processor(client_settings.show_volume_monitor);
+                                self.state.set_volume_monitor_active_processor(client_settings.volume_monitor_needed());
+                            }
+                            ui.end_row();
+
+                            ui.label("Signal Light");
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut client_settings.signal_led, "")
+                                    .on_hover_text("Show a light on the stage screen when there is signal at the input")
+                                    .changed()
+                                {
+                                    self.state.set_volume_monitor_active_processor(client_settings.volume_monitor_needed());
+                                }
+                                ui.add_enabled(
+                                    client_settings.signal_led,
+                                    egui::Slider::new(&mut client_settings.signal_led_threshold_db, -70.0..=-20.0).suffix(" dB")
+                                ).on_hover_text("Input level above which the light is lit");
+                            });
+                            ui.end_row();
+
+                            ui.label("Freeze UI Updates");
+                            ui.checkbox(&mut client_settings.freeze_ui_updates, "")
+                                .on_hover_text("In performance mode, pause the volume monitor and signal light to save CPU. They resume when another view is opened.");
+                            ui.end_row();
+
+                            ui.label("Observer Mode");
+                            if ui.checkbox(&mut client_settings.observer_mode, "")
+                                .on_hover_text("Mirror the pedalboards of another client connected to the processor, without changing them")
+                                .changed()
+                            {
+                                self.state.set_observer(client_settings.observer_mode);
+                            }
+                            ui.end_row();
+
+                            ui.label("Advance to Next Song");
+                            ui.checkbox(&mut client_settings.advance_to_next_song, "")
+                                .on_hover_text("When a song is loaded, stepping past its last pedalboard loads the next song");
+                            ui.end_row();
+
+                            ui.label("XRun Warning Count");
+                            ui.add_sized(
+                                Vec2::new(ui.available_width(), 45.0),
+                                egui::Slider::new(&mut client_settings.xrun_many_threshold, 1..=50)
+                            ).on_hover_text("Number of xruns before the xrun indicator turns red");
+                            ui.end_row();
+
+                            ui.label("XRun Reset Time");
+                            ui.add_sized(
+                                Vec2::new(ui.available_width(), 45.0),
+                                egui::Slider::new(&mut client_settings.xrun_reset_secs, 0.5..=10.0)
+                                    .custom_formatter(|value, _| format!("{:.1}s", value))
+                            ).on_hover_text("Time without an xrun before the xrun indicator resets");
+                            ui.end_row();
+
+                            ui.label("Auto Save Interval");
+                            ui.add_sized(
+                                Vec2::new(ui.available_width(), 45.0),
+                                egui::Slider::new(&mut client_settings.auto_save_secs, 5..=300)
+                                    .custom_formatter(|value, _| format!("{}s", value))
+                            ).on_hover_text("Time between saves of the pedalboards and settings while there are unsaved changes, so a crash loses less");
+                            ui.end_row();
+
+                            ui.label("Tuner Reference");
+                            ui.horizontal(|ui| {
+                                ui.add_sized(
+                                    Vec2::new(ui.available_width() * 0.75, 45.0),
+                                    egui::Slider::new(&mut client_settings.tuner_reference, 432.0..=446.0)
+                                        .step_by(0.5)
+                                        .custom_formatter(|value, _| format!("A4 = {:.1}hz", value))
+                                ).on_hover_text("Pitch of A4 that the tuner shows notes relative to");
+                                if ui.button("Reset").clicked() {
+                                    client_settings.tuner_reference = 440.0;
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label("Tuner Temperament");
+                            egui::ComboBox::from_id_salt("tuner_temperament_dropdown")
+                                .selected_text(client_settings.tuner_temperament.to_string())
+                                .show_ui(ui, |ui| {
+                                    for temperament in Temperament::iter() {
+                                        ui.selectable_value(&mut client_settings.tuner_temperament, temperament, temperament.to_string());
+                                    }
+                                }).response.on_hover_text("Tuning system of the notes shown by the tuner, rooted on C with A at the reference pitch");
+                            ui.end_row();
+
+                            ui.label("Mute While Tuning");
+                            if ui.checkbox(&mut client_settings.mute_while_tuning, "")
+                                .on_hover_text("Silence the output while the tuner is open. If the output was already muted, it stays muted afterwards.")
+                                .changed()
+                            {
+                                self.state.set_mute_while_tuning_processor(client_settings.mute_while_tuning);
+                            }
+                            ui.end_row();
+
+                            ui.label("Knob Style");
+                            let knob_style_before = client_settings.knob_style;
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_salt("knob_skin_dropdown")
+                                    .selected_text(format!("{:?}", client_settings.knob_style.skin))
+                                    .show_ui(ui, |ui| {
+                                        for skin in KnobSkin::iter() {
+                                            ui.selectable_value(&mut client_settings.knob_style.skin, skin, format!("{:?}", skin));
+                                        }
+                                    });
+                                ui.color_edit_button_srgb(&mut client_settings.knob_style.color)
+                                    .on_hover_text("Tint of classic knobs, or colour of flat knobs");
+                                if ui.button("Reset").clicked() {
+                                    client_settings.knob_style = KnobStyle::default();
+                                }
+                            });
+                            if client_settings.knob_style != knob_style_before {
+                                client_settings.knob_style.set(ui.ctx());
+                            }
+                            ui.end_row();
+
+                            ui.label("Frequency Plots");
+                            let plot_range_before = client_settings.plot_range;
+                            ui.horizontal(|ui| {
+                                ui.add(egui::Slider::new(&mut client_settings.plot_range.points, 20..=400).suffix(" points"))
+                                    .on_hover_text("Number of points drawn in frequency plots");
+                                ui.add(
+                                    egui::Slider::new(&mut client_settings.plot_range.min_frequency, 20.0..=500.0)
+                                        .logarithmic(true)
+                                        .suffix("hz")
+                                ).on_hover_text("Lowest frequency shown in frequency plots");
+                                ui.add(
+                                    egui::Slider::new(&mut client_settings.plot_range.max_frequency, 2000.0..=20000.0)
+                                        .logarithmic(true)
+                                        .suffix("hz")
+                                ).on_hover_text("Highest frequency shown in frequency plots. The live spectrum only goes up to the default.");
+                                if ui.button("Reset").clicked() {
+                                    client_settings.plot_range = PlotRange::default();
+                                }
+                            });
+                            if client_settings.plot_range != plot_range_before {
+                                client_settings.plot_range.set(ui.ctx());
+                            }
+                            ui.end_row();
+
+                            ui.label("Category Colors");
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut client_settings.category_colors.enabled, "")
+                                    .on_hover_text("Outline pedals in the pedalboard designer with the colour of their category");
+                                ui.add_enabled_ui(client_settings.category_colors.enabled, |ui| {
+                                    for category in PedalCategory::iter() {
+                                        ui.color_edit_button_srgb(client_settings.category_colors.color_mut(category))
+                                            .on_hover_text(category.display_name());
+                                    }
+                                });
+                                if ui.button("Reset").clicked() {
+                                    client_settings.category_colors = CategoryColors::default();
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label("Hot Reload Files");
+                            if ui.checkbox(&mut client_settings.hot_reload_files, "")
+                                .on_hover_text("Reload IR and NAM files when they are changed by another program, while their pedal is shown")
+                                .changed()
+                            {
+                                FileWatcher::set_enabled(ui.ctx(), client_settings.hot_reload_files);
+                            }
+                            ui.end_row();
+
+                            ui.label("Log Full Commands");
+                            if ui.checkbox(&mut client_settings.log_full_commands, "")
+                                .on_hover_text("Log the full commands sent between the client and processor, instead of truncating them. Useful for debugging.")
+                                .changed()
+                            {
+                                self.state.set_log_full_commands(client_settings.log_full_commands);
                             }
                             ui.end_row();
+
+                            ui.label("Virtual Footswitches");
+                            ui.checkbox(&mut client_settings.show_virtual_footswitches, "")
+                                .on_hover_text("Show buttons on the stage screen that trigger global functions, for use without a MIDI controller");
+                            ui.end_row();
+
+                            if client_settings.show_virtual_footswitches {
+                                ui.label("");
+                                ui.horizontal_wrapped(|ui| {
+                                    let mut remove = None;
+                                    for (i, function) in client_settings.virtual_footswitches.iter_mut().enumerate() {
+                                        egui::ComboBox::from_id_salt(("virtual_footswitch_dropdown", i))
+                                            .selected_text(function.to_string())
+                                            .show_ui(ui, |ui| {
+                                                for option in GlobalMidiFunction::iter().filter(GlobalMidiFunction::is_action) {
+                                                    let text = option.to_string();
+                                                    ui.selectable_value(function, option, text);
+                                                }
+                                            });
+                                        if ui.small_button("x").clicked() {
+                                            remove = Some(i);
+                                        }
+                                        ui.add_space(5.0);
+                                    }
+                                    if let Some(i) = remove {
+                                        client_settings.virtual_footswitches.remove(i);
+                                    }
+
+                                    if client_settings.virtual_footswitches.len() < MAX_VIRTUAL_FOOTSWITCHES && ui.button("Add").clicked() {
+                                        client_settings.virtual_footswitches.push(GlobalMidiFunction::NextPedalboard);
+                                    }
+                                });
+                                ui.end_row();
+                            }
                         });
 
                     ui.add_space(SECTION_SPACE);
@@ -658,10 +1154,48 @@ impl Widget for &mut SettingsScreen {
 
                     ui.add_space(SECTION_SPACE);
 
+                    ui.heading("Pedalboard Backups");
+                    ui.separator();
+                    ui.add_space(20.0);
+                    let mut restore_backup = None;
+                    ui.horizontal(|ui| {
+                        let backup_name = |path: &PathBuf| path.file_name().map_or(String::new(), |name| name.to_string_lossy().to_string());
+
+                        egui::ComboBox::from_id_salt("pedalboard_backups_dropdown")
+                            .selected_text(self.selected_backup.as_ref().map_or(String::from("Select Backup"), backup_name))
+                            .show_ui(ui, |ui| {
+                                for backup in self.pedalboard_backups.iter() {
+                                    ui.selectable_value(&mut self.selected_backup, Some(backup.clone()), backup_name(backup));
+                                }
+                            });
+
+                        if ui.button("Refresh").clicked() {
+                            self.pedalboard_backups = SavedPedalboards::list_backups();
+                        }
+
+                        if ui.add_enabled(
+                            self.selected_backup.is_some() && !self.state.is_observer(),
+                            egui::Button::new("Restore")
+                        ).on_hover_text("Replace the stage, pedalboards and songs with those in the backup").clicked() {
+                            restore_backup = self.selected_backup.clone();
+                        }
+                    });
+
+                    if let Some(path) = restore_backup {
+                        match self.state.pedalboards.restore_from_backup(&path) {
+                            Ok(()) => self.state.load_active_set(),
+                            Err(e) => self.state.notify(e)
+                        }
+                    }
+
+                    ui.add_space(SECTION_SPACE);
+
                     ui.heading("MIDI");
                     ui.separator();
 
                     self.state.midi_state.borrow_mut().midi_port_device_settings_ui(ui);
+                    ui.add_space(10.0);
+                    self.state.midi_state.borrow().midi_monitor_ui(ui);
 
                     if connect_button.is_some_and(|r| r.clicked()) {
                         drop(client_settings);
@@ -669,7 +1203,14 @@ impl Widget for &mut SettingsScreen {
                     }
                 })
             });
-        }).response
+        }).response;
+
+        drop(processor_settings);
+        if self.state.settings_snapshot() != settings_before {
+            self.state.mark_unsaved();
+        }
+
+        response
     }
 }
 
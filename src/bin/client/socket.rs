@@ -7,8 +7,9 @@ use smol::io::{AsyncWriteExt, AsyncWrite};
 use smol::net::{TcpStream, Ipv4Addr};
 
 use rs_pedalboard::pedals::PedalParameterValue;
-use rs_pedalboard::socket_helper::CommandReceiver;
-use rs_pedalboard::pedalboard::ParameterPath;
+use rs_pedalboard::dsp_algorithms::test_signal::TestSignalKind;
+use rs_pedalboard::socket_helper::{log_full_commands, CommandReceiver};
+use rs_pedalboard::pedalboard::{ParallelBranch, ParameterPath};
 
 use crate::settings::VolumeNormalizationMode;
 
@@ -125,11 +126,15 @@ pub enum Command {
     AddPedalboard(String),
     // pedalboard id, serialized pedal
     AddPedal(u32, String),
+    // pedalboard id, branch
+    SetParallelBranch(u32, Option<ParallelBranch>),
     KillProcessor,
     MasterIn(f32),
     MasterOut(f32),
     VolumeNormalization(VolumeNormalizationMode, Option<f32>),
     VolumeNormalizationReset,
+    OutputLimiter(bool),
+    PreGain(f32),
     SetRecording(bool),
     ToggleRecording,
     RecordClean(bool),
@@ -146,8 +151,28 @@ pub enum Command {
     NextPedalboard,
     PrevPedalboard,
     RequestSampleRate,
+    // Ask for the pedalboard set the processor is using
+    RequestSet,
+    // Device sample rate (None for the current rate), upsample passes, internal sample rate (None for the device rate)
+    SetSampleRate(Option<u32>, u32, Option<u32>),
+    // pedalboard id, pedal id
+    LearnNoiseFloor(u32, u32),
     SetMute(bool),
+    MuteWhileTuning(bool),
     ToggleMute,
+    Bypass(bool),
+    ToggleBypass,
+    // Signal to replace the input with (None to use the input), level
+    TestSignal(Option<TestSignalKind>, f32),
+    PedalProfiling(bool),
+    // Infer the metronome tempo from transients in the input
+    TransientTempo(bool),
+    // Log commands in full on both the client and processor, rather than truncated
+    LogFullCommands(bool),
+    // Serialized pedalboard to measure the level change of
+    MeasurePedalboardLevel(String),
+    // pedalboard id, serialized pedal with the files to reload
+    ReloadPedal(u32, String),
 
     SubscribeToResponses(Sender<String>),
     ThreadAliveTest,
@@ -159,6 +184,89 @@ pub enum Command {
     SongsView,
     SettingsView,
     ChangeActiveParameter(f32),
+    // Index in the songs library
+    LoadSong(usize),
+    NextSong,
+    PrevSong,
+    // Morph amount, 0.0 to 1.0
+    Morph(f32),
+    // Sets the metronome BPM, which is sent as a metronome command
+    TapTempo,
+    // Recalls the next scene of the active pedalboard, which is sent as parameter updates
+    NextScene,
+    // Sets the metronome BPM, which is sent as a metronome command
+    SetMetronomeBpm(u32),
+}
+
+impl Command {
+    /// Whether the command changes the processor or the pedalboards, which observers must not do
+    pub fn is_mutating(&self) -> bool {
+        !matches!(
+            self,
+            Command::Tuner(_) |
+            Command::ToggleTuner |
+            Command::VolumeMonitor(_) |
+            Command::ToggleVolumeMonitor |
+            Command::PedalProfiling(_) |
+            Command::LogFullCommands(_) |
+            Command::MeasurePedalboardLevel(_) |
+            Command::RequestSampleRate |
+            Command::RequestSet |
+            Command::SubscribeToResponses(_) |
+            Command::ThreadAliveTest |
+            Command::StageView |
+            Command::LibraryView |
+            Command::UtilitiesView |
+            Command::SongsView |
+            Command::SettingsView
+        )
+    }
+
+    /// Parse a command that another client sent to the processor, which the processor has forwarded to this client.
+    /// 
+    /// Only commands that change the pedalboards are forwarded.
+    pub fn from_synced_command(command: &str) -> Option<Command> {
+        let (name, arguments) = command.split_once('|').unwrap_or((command, ""));
+
+        match name {
+            "setparameter" => {
+                let mut parts = arguments.splitn(4, '|');
+                let pedalboard_id = parts.next()?.parse().ok()?;
+                let pedal_id = parts.next()?.parse().ok()?;
+                let parameter_name = parts.next()?.to_string();
+                let value = serde_json::from_str(parts.next()?).ok()?;
+                Some(Command::ParameterUpdate(ParameterPath { pedalboard_id, pedal_id, parameter_name }, value))
+            },
+            "movepedal" => {
+                let mut parts = arguments.split('|');
+                Some(Command::MovePedal(parts.next()?.parse().ok()?, parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+            },
+            "deletepedal" => {
+                let mut parts = arguments.split('|');
+                Some(Command::DeletePedal(parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+            },
+            "setparallelbranch" => {
+                let (pedalboard_id, branch) = arguments.split_once('|')?;
+                Some(Command::SetParallelBranch(pedalboard_id.parse().ok()?, serde_json::from_str(branch).ok()?))
+            },
+            "addpedal" => {
+                let (pedalboard_id, pedal) = arguments.split_once('|')?;
+                Some(Command::AddPedal(pedalboard_id.parse().ok()?, pedal.to_string()))
+            },
+            "movepedalboard" => {
+                let mut parts = arguments.split('|');
+                Some(Command::MovePedalboard(parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+            },
+            "deletepedalboard" if arguments == "active" => Some(Command::DeleteActivePedalboard),
+            "deletepedalboard" => Some(Command::DeletePedalboard(arguments.parse().ok()?)),
+            "addpedalboard" => Some(Command::AddPedalboard(arguments.to_string())),
+            "loadset" => Some(Command::LoadSet(arguments.to_string())),
+            "play" => Some(Command::Play(arguments.parse().ok()?)),
+            "nextpedalboard" => Some(Command::NextPedalboard),
+            "prevpedalboard" => Some(Command::PrevPedalboard),
+            _ => None
+        }
+    }
 }
 
 pub struct ClientSocketThreadHandle {
@@ -399,6 +507,26 @@ async fn client_socket_event_loop(
                             break;
                         }
                     },
+                    Command::SetParallelBranch(pedalboard_id, branch) => {
+                        let message = format!(
+                            "setparallelbranch|{}|{}\n",
+                            pedalboard_id,
+                            serde_json::to_string(&branch).unwrap()
+                        );
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
+                    Command::LearnNoiseFloor(pedalboard_id, pedal_id) => {
+                        let message = format!(
+                            "learnnoisefloor|{}|{}\n",
+                            pedalboard_id,
+                            pedal_id
+                        );
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
                     Command::MovePedalboard(from_index, to_index) => {
                         let message = format!(
                             "movepedalboard|{}|{}\n",
@@ -443,6 +571,25 @@ async fn client_socket_event_loop(
                             break;
                         }
                     },
+                    Command::MeasurePedalboardLevel(serialized_pedalboard) => {
+                        let message = format!(
+                            "measurelevel|{}\n",
+                            serialized_pedalboard
+                        );
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
+                    Command::ReloadPedal(pedalboard_id, serialized_pedal) => {
+                        let message = format!(
+                            "reloadpedal|{}|{}\n",
+                            pedalboard_id,
+                            serialized_pedal
+                        );
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
                     Command::LoadSet(pedalboard_set) => {
                         let message = format!(
                             "loadset|{}\n",
@@ -490,6 +637,18 @@ async fn client_socket_event_loop(
                             break;
                         }
                     }
+                    Command::OutputLimiter(enable) => {
+                        let message = format!("outputlimiter|{}\n", if enable { "on" } else { "off" });
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
+                    Command::PreGain(db) => {
+                        let message = format!("pregain|{}\n", db);
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
                     Command::SetRecording(active) => {
                         let message = format!("recording|{}\n", if active { "on" } else { "off" });
                         if socket_send(&mut stream_writer, &message).await {
@@ -571,21 +730,83 @@ async fn client_socket_event_loop(
                             break;
                         }
                     },
+                    Command::RequestSet => {
+                        if socket_send(&mut stream_writer, "requestset\n").await {
+                            break;
+                        }
+                    },
+                    Command::SetSampleRate(sample_rate, upsample_passes, internal_sample_rate) => {
+                        let sample_rate = sample_rate.map_or_else(|| "default".to_string(), |rate| rate.to_string());
+                        let internal_sample_rate = internal_sample_rate.map_or_else(|| "device".to_string(), |rate| rate.to_string());
+                        let message = format!("setsamplerate|{}|{}|{}\n", sample_rate, upsample_passes, internal_sample_rate);
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
                     Command::SetMute(mute) => {
                         let message = format!("mute|{}\n", if mute { "on" } else { "off" });
                         if socket_send(&mut stream_writer, &message).await {
                             break;
                         }
                     },
+                    Command::MuteWhileTuning(enable) => {
+                        let message = format!("mutewhiletuning|{}\n", if enable { "on" } else { "off" });
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
                     Command::ToggleMute => {
                         let message = "mute|toggle\n";
                         if socket_send(&mut stream_writer, message).await {
                             break;
                         }
                     },
+                    Command::Bypass(enable) => {
+                        let message = format!("bypass|{}\n", if enable { "on" } else { "off" });
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
+                    Command::ToggleBypass => {
+                        let message = "bypass|toggle\n";
+                        if socket_send(&mut stream_writer, message).await {
+                            break;
+                        }
+                    },
+                    Command::TestSignal(kind, level) => {
+                        let message = match kind {
+                            None => "testsignal|off\n".to_string(),
+                            Some(TestSignalKind::Sine(frequency)) => format!("testsignal|sine|{}|{}\n", level, frequency),
+                            Some(TestSignalKind::WhiteNoise) => format!("testsignal|white|{}\n", level),
+                            Some(TestSignalKind::PinkNoise) => format!("testsignal|pink|{}\n", level),
+                            Some(TestSignalKind::Impulse) => format!("testsignal|impulse|{}\n", level)
+                        };
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
+                    Command::PedalProfiling(enable) => {
+                        let message = format!("pedalprofile|{}\n", if enable { "on" } else { "off" });
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
+                    Command::TransientTempo(enable) => {
+                        let message = format!("transienttempo|{}\n", if enable { "on" } else { "off" });
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
+                    Command::LogFullCommands(enable) => {
+                        let message = format!("logfullcommands|{}\n", if enable { "on" } else { "off" });
+                        if socket_send(&mut stream_writer, &message).await {
+                            break;
+                        }
+                    },
                     Command::ThreadAliveTest => { },
                     // Client only
-                    Command::StageView | Command::LibraryView | Command::UtilitiesView | Command::SongsView | Command::SettingsView | Command::ChangeActiveParameter(_) => {}
+                    Command::StageView | Command::LibraryView | Command::UtilitiesView | Command::SongsView | Command::SettingsView | Command::ChangeActiveParameter(_) |
+                    Command::LoadSong(_) | Command::NextSong | Command::PrevSong | Command::Morph(_) | Command::TapTempo | Command::NextScene | Command::SetMetronomeBpm(_) => {}
                 }
             }
         }
@@ -596,7 +817,7 @@ async fn client_socket_event_loop(
 async fn socket_send(mut stream: impl AsyncWrite + Unpin, message: &str) -> bool {
     match stream.write_all(message.as_bytes()).await {
         Ok(()) => {
-            if message.len() < 40 || cfg!(feature="log_full_commands") {
+            if message.len() < 40 || log_full_commands() {
                 tracing::info!("Sent: {:?}", message);
             } else {
                 tracing::info!("Sent: {:?}...", &message[..40]);
@@ -1,6 +1,6 @@
 use eframe::egui::{self, Layout, RichText, TextEdit, Vec2, Widget};
 
-use crate::State;
+use crate::{saved_pedalboards::Song, State};
 
 pub enum RowAction {
     Load,
@@ -20,7 +20,7 @@ impl SongsScreen {
         }
     }
 
-    pub fn songs_row(&self, ui: &mut egui::Ui, song_name: &str, pedalboard_ids: &[u32], row_size: Vec2) -> (Option<RowAction>, egui::Response) {
+    pub fn songs_row(&self, ui: &mut egui::Ui, song: &Song, active: bool, row_size: Vec2) -> (Option<RowAction>, egui::Response) {
         let mut action = None;
 
         let row_height = row_size.y;
@@ -34,7 +34,11 @@ impl SongsScreen {
                         Layout::left_to_right(egui::Align::Center),
                         |ui| {
                             ui.add_space(20.0);
-                            ui.label(song_name);
+                            if active {
+                                ui.label(RichText::new(&song.name).color(crate::THEME_COLOR));
+                            } else {
+                                ui.label(&song.name);
+                            }
                         }
                     );
 
@@ -63,7 +67,7 @@ impl SongsScreen {
                 let mut pedalboards_text = String::new();
 
                 let pedalboard_library = self.state.pedalboards.pedalboard_library.borrow();
-                for (i, pedalboard_id) in pedalboard_ids.iter().enumerate() {
+                for (i, pedalboard_id) in song.pedalboards.iter().enumerate() {
                     if i > 0 {
                         pedalboards_text.push_str(", ");
                     }
@@ -85,13 +89,41 @@ impl Widget for &mut SongsScreen {
         ui.vertical_centered(|ui| {
             ui.add_space(5.0);
             
-            // === Search bar ===
-            ui.add_sized(
-                [ui.available_width()/3.0, ui.available_height() * 0.08],
-                TextEdit::singleline(&mut self.search_term)
-                    .hint_text(RichText::new("Search songs..."))
-                    .vertical_align(egui::Align::Center)
-            );
+            // === Search bar and song navigation ===
+            let col_height = ui.available_height() * 0.08;
+            let (mut prev_song, mut next_song) = (false, false);
+            ui.columns_const(|[prev_ui, search_ui, next_ui]| {
+                prev_ui.allocate_ui_with_layout(
+                    Vec2::new(0.0, col_height),
+                    Layout::top_down(egui::Align::Center),
+                    |ui| {
+                        if ui.add_sized([ui.available_width()*0.5, col_height*0.9], egui::Button::new("Previous Song")).clicked() {
+                            prev_song = true;
+                        }
+                });
+
+                search_ui.add_sized(
+                    [0.0, col_height],
+                    TextEdit::singleline(&mut self.search_term)
+                        .hint_text(RichText::new("Search songs..."))
+                        .vertical_align(egui::Align::Center)
+                );
+
+                next_ui.allocate_ui_with_layout(
+                    Vec2::new(0.0, col_height),
+                    Layout::top_down(egui::Align::Center),
+                    |ui| {
+                        if ui.add_sized([ui.available_width()*0.5, col_height*0.9], egui::Button::new("Next Song")).clicked() {
+                            next_song = true;
+                        }
+                });
+            });
+
+            if prev_song {
+                self.state.prev_song(false);
+            } else if next_song {
+                self.state.next_song();
+            }
 
             ui.add_space(5.0);
             ui.separator();
@@ -120,10 +152,11 @@ impl Widget for &mut SongsScreen {
                     })
                     .spacing(Vec2::new(10.0, 10.0))
                     .show(ui, |ui| {
-                        for (song, pedalboards) in songs_library.iter() {
-                            if self.search_term.is_empty() || song.contains(&self.search_term) {
-                                SongsScreen::songs_row(self, ui, song, pedalboards, row_size).0.map(|row_action| {
-                                    action = Some((song, row_action));
+                        let active_song = self.state.active_song.get();
+                        for (i, song) in songs_library.iter().enumerate() {
+                            if self.search_term.is_empty() || song.name.contains(&self.search_term) {
+                                SongsScreen::songs_row(self, ui, song, active_song == Some(i), row_size).0.map(|row_action| {
+                                    action = Some((i, row_action));
                                 });
                                 ui.end_row();
                             }
@@ -131,22 +164,19 @@ impl Widget for &mut SongsScreen {
                 });
 
                 // Perform any actions performed in this frame
-                if let Some((song, action)) = action {
+                if let Some((song_index, action)) = action {
                     match action {
                         RowAction::Load => {
-                            let song = songs_library.get(song).unwrap();
-                            let pedalboard_library = self.state.pedalboards.pedalboard_library.borrow();
-                            for pedalboard_id in song {
-                                if let Some(pedalboard) = pedalboard_library.iter().find(|pedalboard| &pedalboard.get_id() == pedalboard_id) {
-                                    self.state.pedalboards.active_pedalboardstage.borrow_mut().pedalboards.push(pedalboard.clone());
-                                    self.state.load_active_set();
-                                }
-                            }
+                            drop(songs_library);
+                            self.state.load_song(song_index, 0);
                         },
                         RowAction::Delete => {
-                            // Can't get from the hashmap with a reference to the String key
-                            let cloned = song.clone();
-                            songs_library.remove(&cloned);
+                            songs_library.remove(song_index);
+                            match self.state.active_song.get() {
+                                Some(active) if active == song_index => self.state.active_song.set(None),
+                                Some(active) if active > song_index => self.state.active_song.set(Some(active - 1)),
+                                _ => {}
+                            }
                         }
                     }
                 };
@@ -0,0 +1,45 @@
+use super::PedalboardStageScreen;
+
+use eframe::egui::{self, Button, Color32, RichText};
+use crate::THEME_COLOR;
+
+/// IDs and names of the favorite pedalboards in the library
+pub fn favorite_pedalboards(screen: &PedalboardStageScreen) -> Vec<(u32, String)> {
+    screen.state.pedalboards.pedalboard_library.borrow().iter()
+        .filter(|pedalboard| pedalboard.favorite)
+        .map(|pedalboard| (pedalboard.get_id(), pedalboard.name.clone()))
+        .collect()
+}
+
+/// A row of buttons for the favorite pedalboards in the library, which play the pedalboard
+/// (adding it to the stage if needed) without going through the library.
+pub fn favorites_row(screen: &mut PedalboardStageScreen, ui: &mut egui::Ui, favorites: &[(u32, String)], height: f32) {
+    let active_id = {
+        let pedalboard_set = screen.state.pedalboards.active_pedalboardstage.borrow();
+        pedalboard_set.pedalboards.get(pedalboard_set.active_pedalboard).map(|pedalboard| pedalboard.get_id())
+    };
+
+    let mut to_play = None;
+    egui::ScrollArea::horizontal().id_salt("favorites_row").show(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("★").size(height * 0.5).color(THEME_COLOR));
+            for (pedalboard_id, name) in favorites {
+                let button = if active_id == Some(*pedalboard_id) {
+                    Button::new(RichText::new(name).color(Color32::WHITE))
+                        .fill(THEME_COLOR.gamma_multiply(0.5))
+                        .stroke(egui::Stroke::new(2.0, THEME_COLOR))
+                } else {
+                    Button::new(name).stroke(egui::Stroke::new(1.0, THEME_COLOR))
+                };
+
+                if ui.add_sized([(height * 3.0).max(ui.spacing().interact_size.x), height], button.truncate()).on_hover_text(name).clicked() {
+                    to_play = Some(*pedalboard_id);
+                }
+            }
+        });
+    });
+
+    if let Some(pedalboard_id) = to_play {
+        screen.state.play_from_library(pedalboard_id);
+    }
+}
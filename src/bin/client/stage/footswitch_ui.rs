@@ -0,0 +1,68 @@
+use super::PedalboardStageScreen;
+
+use eframe::egui::{self, Button, Color32, RichText};
+use crate::{midi::functions::GlobalMidiFunction, state::State, THEME_COLOR};
+
+/// Whether the state toggled by a function is on, for functions that toggle a state
+fn latched_state(function: &GlobalMidiFunction, state: &State) -> Option<bool> {
+    match function {
+        GlobalMidiFunction::ToggleMute => Some(state.muted.get()),
+        GlobalMidiFunction::ToggleBypass => Some(state.bypass_active.get()),
+        GlobalMidiFunction::ToggleRecording => Some(state.recording_time.get().is_some()),
+        GlobalMidiFunction::ToggleMetronome => Some(state.metronome_active.get()),
+        GlobalMidiFunction::ToggleTuner => Some(state.tuner_active.get()),
+        _ => None
+    }
+}
+
+/// Name of the active scene of the active pedalboard, if it has scenes
+fn active_scene_name(state: &State) -> Option<String> {
+    let pedalboard_set = state.pedalboards.active_pedalboardstage.borrow();
+    let pedalboard = &pedalboard_set.pedalboards[pedalboard_set.active_pedalboard];
+    pedalboard.scenes.get(pedalboard.active_scene).map(|scene| scene.name.clone())
+}
+
+/// A row of on-screen buttons that trigger the global functions assigned in the client settings,
+/// the same as a footswitch on a MIDI controller.
+pub fn virtual_footswitches(screen: &mut PedalboardStageScreen, ui: &mut egui::Ui, height: f32) {
+    let functions = screen.state.client_settings.borrow().virtual_footswitches.clone();
+    if functions.is_empty() {
+        return;
+    }
+
+    let spacing = 10.0;
+    let button_width = (ui.available_width() - spacing * (functions.len() - 1) as f32) / functions.len() as f32;
+
+    let mut triggered = None;
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = spacing;
+        for function in functions.iter() {
+            let text = match function {
+                GlobalMidiFunction::TapTempo => RichText::new(format!("{}\n{} BPM", function, screen.state.metronome_bpm.get())),
+                GlobalMidiFunction::NextScene => match active_scene_name(screen.state) {
+                    Some(scene_name) => RichText::new(format!("{}\nScene {}", function, scene_name)),
+                    None => RichText::new(function.to_string())
+                },
+                _ => RichText::new(function.to_string())
+            };
+
+            let button = match latched_state(function, screen.state) {
+                Some(true) => Button::new(text.color(Color32::WHITE))
+                    .fill(THEME_COLOR.gamma_multiply(0.5))
+                    .stroke(egui::Stroke::new(2.0, THEME_COLOR)),
+                _ => Button::new(text).stroke(egui::Stroke::new(1.0, THEME_COLOR))
+            };
+
+            let clicked = ui.add_enabled_ui(screen.state.is_connected(), |ui| {
+                ui.add_sized([button_width, height], button.wrap())
+            }).inner.clicked();
+            if clicked {
+                triggered = Some(function.clone());
+            }
+        }
+    });
+
+    if let Some(function) = triggered {
+        screen.state.send_global_command(function.command_from_function(1.0), ui.ctx());
+    }
+}
@@ -1,11 +1,20 @@
 mod pedalboard_panel_ui;
-use std::{collections::HashMap, time::{Duration, Instant}};
+use std::{collections::{HashMap, HashSet}, time::{Duration, Instant}};
 
 use pedalboard_panel_ui::pedalboard_stage_panel;
 
 mod pedalboard_designer;
 use pedalboard_designer::pedalboard_designer;
 
+mod performance_ui;
+use performance_ui::performance_view;
+
+mod footswitch_ui;
+use footswitch_ui::virtual_footswitches;
+
+mod favorites_ui;
+use favorites_ui::{favorite_pedalboards, favorites_row};
+
 mod volume_monitor_ui;
 
 mod parameter_window;
@@ -17,8 +26,19 @@ use crate::{stage::volume_monitor_ui::VolumeMonitorWidget, state::State};
 /// Repaint duration for pedalboard stage for stats, time etc.
 const STATS_STAGE_REPAINT_DURATION: std::time::Duration = Duration::from_secs(1);
 
+/// Fraction of the screen height taken by the virtual footswitches, and the space above them
+const FOOTSWITCH_HEIGHT_FRACTION: f32 = 0.12;
+const FOOTSWITCH_PADDING: f32 = 10.0;
+
+/// Fraction of the screen height taken by the favorite pedalboards row, when there are favorites
+const FAVORITES_HEIGHT_FRACTION: f32 = 0.06;
+
 /// Duration after which the clipping state is reset if no clipping occurs
 pub const CLIPPING_STATE_DURATION: Duration = Duration::from_secs(2);
+
+/// Output buffer fill percentages outside of which a warning is shown
+pub const BUFFER_FILL_LOW_WARNING: f32 = 10.0;
+pub const BUFFER_FILL_HIGH_WARNING: f32 = 90.0;
 pub enum CurrentAction {
     DuplicateLinked(usize),
     DuplicateNew(usize),
@@ -27,7 +47,12 @@ pub enum CurrentAction {
     Rename((usize, String)),
     SaveToLibrary(usize),
     ChangeActive(usize),
-    AddPedalboard
+    // Pedalboard ID to make the home pedalboard, or None to clear it
+    SetHome(Option<u32>),
+    // Pedalboard ID to reset the pedals of
+    ResetPedals(u32),
+    AddPedalboard,
+    PastePedalboard
 }
 
 pub enum ClippingState {
@@ -38,18 +63,24 @@ pub enum ClippingState {
 
 pub enum XRunState {
     None,
-    // How many occurred since the first one, time that the last xrun occurred
-    Few((usize, Instant)),
-    // Time that the last xrun occurred
-    Many(Instant)
+    // How many occurred since the first one, samples dropped since the first one, time that the last xrun occurred
+    Few((usize, usize, Instant)),
+    // How many occurred since the first one, samples dropped since the first one, time that the last xrun occurred
+    Many((usize, usize, Instant))
 }
 
 pub struct PedalboardStageScreen {
     state: &'static State,
     show_pedal_menu: bool,
+    // Window comparing the active pedalboard with the saved pedalboard in the library
+    show_changes: bool,
+    // Text filter for the add pedal menu
+    pedal_menu_filter: String,
     current_action: Option<CurrentAction>,
     // For the Scene in pedalboard designer
     pedalboard_rect: Rect,
+    // IDs of the pedals shown collapsed in the pedalboard designer. Only kept while the client is open.
+    collapsed_pedals: HashSet<u32>,
     // For CPU/RAM usage
     system: System,
     last_system_refresh: std::time::Instant,
@@ -77,8 +108,11 @@ impl PedalboardStageScreen {
         Self {
             state,
             show_pedal_menu: false,
+            show_changes: false,
+            pedal_menu_filter: String::new(),
             current_action: None,
             pedalboard_rect: Rect::ZERO,
+            collapsed_pedals: HashSet::new(),
             system,
             last_system_refresh: Instant::now(),
             command_buffer: Vec::new(),
@@ -105,34 +139,43 @@ impl PedalboardStageScreen {
         self.command_buffer.clear();
         self.state.get_commands("xrun", &mut self.command_buffer);
         let xrun_count = self.command_buffer.len();
+        // Older processors don't send the dropped sample count
+        let dropped: usize = self.command_buffer.iter()
+            .map(|cmd| cmd.parse::<usize>().unwrap_or(0))
+            .sum();
+
+        let (many_threshold, reset_secs) = {
+            let client_settings = self.state.client_settings.borrow();
+            (client_settings.xrun_many_threshold, client_settings.xrun_reset_secs)
+        };
 
         match self.xrun_state {
             XRunState::None => {
                 if xrun_count > 0 {
-                    self.xrun_state = XRunState::Few((xrun_count, Instant::now()));
+                    self.xrun_state = XRunState::Few((xrun_count, dropped, Instant::now()));
                 }
             },
-            XRunState::Few((count, last_xrun)) => {
-                // If no xrun occurred for more than 2 seconds, reset the state
-                if xrun_count == 0 && last_xrun.elapsed().as_secs() > 2 {
+            XRunState::Few((count, total_dropped, last_xrun)) => {
+                // If no xrun occurred for the reset time, reset the state
+                if xrun_count == 0 && last_xrun.elapsed().as_secs_f32() > reset_secs {
                     self.xrun_state = XRunState::None;
                     return;
                 }
 
-                // If more than 10 xruns have occurred, switch to Many state
+                // If more than the threshold of xruns have occurred, switch to Many state
                 let total = count + xrun_count;
-                if total > 10 {
-                    self.xrun_state = XRunState::Many(Instant::now());
+                if total > many_threshold {
+                    self.xrun_state = XRunState::Many((total, total_dropped + dropped, Instant::now()));
                 } else if xrun_count > 0 {
-                    self.xrun_state = XRunState::Few((total, Instant::now()));
+                    self.xrun_state = XRunState::Few((total, total_dropped + dropped, Instant::now()));
                 }
             },
-            XRunState::Many(last_xrun) => {
-                // If no xrun occurred for more than 2 seconds, reset the state
-                if xrun_count == 0 && last_xrun.elapsed().as_secs() > 2 {
+            XRunState::Many((count, total_dropped, last_xrun)) => {
+                // If no xrun occurred for the reset time, reset the state
+                if xrun_count == 0 && last_xrun.elapsed().as_secs_f32() > reset_secs {
                     self.xrun_state = XRunState::None;
                 } else if xrun_count > 0 {
-                    self.xrun_state = XRunState::Many(Instant::now());
+                    self.xrun_state = XRunState::Many((count + xrun_count, total_dropped + dropped, Instant::now()));
                 }
             }
         }
@@ -177,6 +220,24 @@ impl PedalboardStageScreen {
         tracing::error!("Invalid volume monitor command format: {}", latest_command);
     }
 
+    /// A light that is lit while the input is above the signal light threshold, if the signal light is enabled
+    fn signal_led_ui(&self, ui: &mut egui::Ui, radius: f32) {
+        let (enabled, threshold_db) = {
+            let client_settings = self.state.client_settings.borrow();
+            (client_settings.signal_led, client_settings.signal_led_threshold_db)
+        };
+        if !enabled || !self.state.is_connected() || self.state.ui_updates_frozen() {
+            return;
+        }
+
+        let lit = self.volume_monitors.0.volume() >= 10f32.powf(threshold_db / 20.0);
+        let color = if lit { egui::Color32::from_rgb(50, 255, 50) } else { egui::Color32::from_gray(45) };
+
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(radius * 2.0), egui::Sense::hover());
+        ui.painter().circle_filled(rect.center(), radius, color);
+        response.on_hover_text("Signal at the input");
+    }
+
     fn save_song_input_window(&mut self, ui: &mut egui::Ui, title: &str, input: &mut String, open: &mut bool) -> bool {
         let mut saved = false;
         egui::Window::new(title)
@@ -227,7 +288,7 @@ impl Widget for &mut PedalboardStageScreen {
             self.last_system_refresh = Instant::now();
         }
 
-        if self.state.client_settings.borrow().show_volume_monitor {
+        if self.state.volume_monitor_needed() {
             self.update_volume_monitors_from_commands();
             ui.ctx().request_repaint_after(rs_pedalboard::DEFAULT_REFRESH_DURATION);
         }
@@ -237,18 +298,50 @@ impl Widget for &mut PedalboardStageScreen {
 
         let right_padding = 5.0;
         let width = ui.available_width() - right_padding;
-        let height = ui.available_height();
-        ui.horizontal(|ui| {
+        let (performance_mode, show_footswitches) = {
+            let client_settings = self.state.client_settings.borrow();
+            (client_settings.performance_mode, client_settings.show_virtual_footswitches)
+        };
+
+        let footswitch_height = if show_footswitches { ui.available_height() * FOOTSWITCH_HEIGHT_FRACTION } else { 0.0 };
+
+        let favorites = favorite_pedalboards(self);
+        if !favorites.is_empty() {
+            let favorites_height = ui.available_height() * FAVORITES_HEIGHT_FRACTION;
+            favorites_row(self, ui, &favorites, favorites_height);
+        }
+
+        let height = ui.available_height() - footswitch_height;
+
+        let response = if performance_mode {
             ui.allocate_ui_with_layout(
-                Vec2::new(width * 0.33, height),
+                Vec2::new(width, height),
+                Layout::top_down(egui::Align::Center),
+                |ui| performance_view(self, ui)
+            ).response
+        } else {
+            ui.horizontal(|ui| {
+                ui.allocate_ui_with_layout(
+                    Vec2::new(width * 0.33, height),
+                        Layout::top_down(egui::Align::Center),
+                        |ui| pedalboard_stage_panel(self, ui)
+                );
+                ui.allocate_ui_with_layout(
+                    Vec2::new(width * 0.67, height),
                     Layout::top_down(egui::Align::Center),
-                    |ui| pedalboard_stage_panel(self, ui)
-            );
+                    |ui| pedalboard_designer(self, ui)
+                );
+            }).response
+        };
+
+        if show_footswitches {
             ui.allocate_ui_with_layout(
-                Vec2::new(width * 0.67, height),
-                Layout::top_down(egui::Align::Center),
-                |ui| pedalboard_designer(self, ui)
+                Vec2::new(width, footswitch_height),
+                Layout::bottom_up(egui::Align::Center),
+                |ui| virtual_footswitches(self, ui, footswitch_height - FOOTSWITCH_PADDING)
             );
-        }).response
+        }
+
+        response
     }
 }
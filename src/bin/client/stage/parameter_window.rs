@@ -44,10 +44,13 @@ pub enum ParameterWindowChange {
     // Changed device on existing MIDI function. (parameter function, new device id, old device id)
     ChangeMidiFunctionDevice(ParameterPath, u32, u32),
     // Remove existing MIDI function (parameter path, device id)
-    RemoveMidiFunction(ParameterPath, u32)
+    RemoveMidiFunction(ParameterPath, u32),
+    // Measure the noise floor to set the threshold of a noise gate
+    LearnNoiseFloor
 }
 
-pub fn draw_parameter_window(ui: &mut egui::Ui, pedalboard_id: u32, pedal: &mut Pedal, devices: &HashMap<u32, String>) -> Option<ParameterWindowChange> {
+/// `learn_progress` is the progress of the noise floor measurement for this pedal, if it is being measured
+pub fn draw_parameter_window(ui: &mut egui::Ui, pedalboard_id: u32, pedal: &mut Pedal, devices: &HashMap<u32, String>, learn_progress: Option<f32>) -> Option<ParameterWindowChange> {
     let id = get_window_id(pedal);
     let open_id = get_window_open_id(pedal);
     let height_id = get_window_height_id(pedal);
@@ -100,6 +103,25 @@ pub fn draw_parameter_window(ui: &mut egui::Ui, pedalboard_id: u32, pedal: &mut
                         }
                     }
                 });
+
+            if matches!(pedal, Pedal::NoiseGate(_)) {
+                ui.separator();
+                match learn_progress {
+                    Some(progress) => {
+                        ui.label("Measuring noise floor, don't play...");
+                        ui.add(egui::ProgressBar::new(progress).show_percentage());
+                        ui.ctx().request_repaint_after(rs_pedalboard::DEFAULT_REFRESH_DURATION);
+                    },
+                    None => {
+                        if ui.button("Learn Threshold")
+                            .on_hover_text("Measure the noise floor for a couple of seconds and set the threshold just above it. Mute your playing while measuring.")
+                            .clicked()
+                        {
+                            to_change = Some(ParameterWindowChange::LearnNoiseFloor);
+                        }
+                    }
+                }
+            }
             
             ui.ctx().data_mut(|r| r.insert_temp(height_id, ui.min_size().y));
         });
@@ -1,18 +1,24 @@
 use core::f32;
 
-use crate::stage::{parameter_window::{draw_parameter_window, ParameterWindowChange}, ClippingState, XRunState};
+use crate::stage::{parameter_window::{draw_parameter_window, ParameterWindowChange}, ClippingState, XRunState, BUFFER_FILL_HIGH_WARNING, BUFFER_FILL_LOW_WARNING};
 
 use super::PedalboardStageScreen;
 
 use eframe::egui::{self, Button, Color32, Layout, Pos2, Rect, RichText, Sense, Ui, UiBuilder, Vec2, Widget};
-use rs_pedalboard::pedals::{PedalDiscriminants, PedalParameterValue, PedalTrait};
+use rs_pedalboard::{pedalboard::{ParallelBranch, MAX_SCENES}, pedals::{FileWatcher, Pedal, PedalCategory, PedalDiscriminants, PedalParameterValue, PedalTrait}};
 use strum::IntoEnumIterator;
 
 const PEDAL_ROW_COUNT: usize = 6;
 // Must be high enough to fit any pedal
 // PEDAL_HEIGHT_RATIO * width = height
 const PEDAL_HEIGHT_RATIO: f32 = 2.2;
+// COLLAPSED_PEDAL_HEIGHT_RATIO * width = height of a collapsed pedal
+const COLLAPSED_PEDAL_HEIGHT_RATIO: f32 = 0.6;
 const MAX_PEDAL_COUNT: usize = 12;
+// Number of recently added pedals shown at the top of the pedal menu
+const RECENT_PEDAL_COUNT: usize = 4;
+/// Pedal CPU usage (percentage of real time) that fills the load bar
+const PEDAL_LOAD_BAR_FULL_PERCENT: f32 = 10.0;
 
 /// Assumes scene rect is smaller than available size
 fn bound_scene_rect(scene_rect: &mut Rect, available_size: &Vec2) {
@@ -33,6 +39,121 @@ fn bound_scene_rect(scene_rect: &mut Rect, available_size: &Vec2) {
     }
 }
 
+/// Random values within the range of each of a pedal's numeric parameters, except Active
+fn randomized_parameters(pedal: &Pedal) -> Vec<(String, PedalParameterValue)> {
+    // Xorshift seeded from the clock
+    let mut rng_state = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0) | 1;
+
+    pedal.get_parameters().iter()
+        .filter(|(name, _)| name.as_str() != "Active")
+        .filter_map(|(name, parameter)| {
+            rng_state ^= rng_state << 13;
+            rng_state ^= rng_state >> 17;
+            rng_state ^= rng_state << 5;
+            let fraction = rng_state as f32 / u32::MAX as f32;
+            parameter.value_in_range(fraction).map(|value| (name.clone(), value))
+        })
+        .collect()
+}
+
+/// The parameters of a pedal that differ from a newly created pedal, with their default values.
+///
+/// Active and string parameters without choices (e.g. loaded files) are left unchanged.
+fn initial_parameters(pedal: &Pedal) -> Vec<(String, PedalParameterValue)> {
+    let default_pedal = PedalDiscriminants::from(pedal).new_pedal();
+    let parameters = pedal.get_parameters();
+
+    default_pedal.get_parameters().iter()
+        .filter(|(name, parameter)| name.as_str() != "Active" && (parameter.choices.is_some() || !matches!(parameter.value, PedalParameterValue::String(_))))
+        .filter(|(name, parameter)| parameters.get(*name).is_some_and(|current| current.value != parameter.value))
+        .map(|(name, parameter)| (name.clone(), parameter.value.clone()))
+        .collect()
+}
+
+/// A collapsed pedal, showing only its name and active toggle
+fn collapsed_pedal_ui(ui: &mut Ui, pedal: &Pedal, rect: Rect) -> Option<(String, PedalParameterValue)> {
+    let mut to_change = None;
+
+    ui.painter().rect_filled(rect, 5.0, crate::WIDGET_BACKGROUND_COLOR);
+    ui.scope_builder(UiBuilder::new().max_rect(rect.shrink(5.0)).layout(Layout::top_down(egui::Align::Center)), |ui| {
+        ui.add(egui::Label::new(RichText::new(PedalDiscriminants::from(pedal).display_name()).strong()).truncate());
+
+        if let Some(active) = pedal.get_parameters().get("Active").and_then(|parameter| parameter.value.as_bool()) {
+            ui.add_space(5.0);
+            if ui.add(Button::new(if active { "On" } else { "Off" }).selected(active)).clicked() {
+                to_change = Some(("Active".to_string(), PedalParameterValue::Bool(!active)));
+            }
+        }
+    });
+
+    to_change
+}
+
+fn parameter_value_string(value: &PedalParameterValue) -> String {
+    match value {
+        PedalParameterValue::Float(value) => format!("{value:.2}"),
+        PedalParameterValue::Int(value) => value.to_string(),
+        PedalParameterValue::Bool(value) => if *value { "On".to_string() } else { "Off".to_string() },
+        PedalParameterValue::String(value) => value.clone(),
+        PedalParameterValue::Oscillator(_) => "Oscillator".to_string()
+    }
+}
+
+/// Window listing the parameters of the active pedalboard that differ from the pedalboard saved in the library
+fn changes_window(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
+    let state = screen.state;
+    let active_pedalboard_id = {
+        let pedalboard_set = state.pedalboards.active_pedalboardstage.borrow();
+        pedalboard_set.pedalboards[pedalboard_set.active_pedalboard].get_id()
+    };
+    let differences = state.parameter_differences(active_pedalboard_id);
+
+    let mut revert = false;
+    egui::Window::new("Changes From Saved")
+        .open(&mut screen.show_changes)
+        .vscroll(true)
+        .show(ui.ctx(), |ui| {
+            match &differences {
+                None => {
+                    ui.label(RichText::new("This pedalboard is not saved in the library").color(crate::FAINT_TEXT_COLOR));
+                },
+                Some(differences) if differences.is_empty() => {
+                    ui.label(RichText::new("No parameters have changed").color(crate::FAINT_TEXT_COLOR));
+                },
+                Some(differences) => {
+                    egui::Grid::new("parameter_differences_grid")
+                        .num_columns(4)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label("Parameter");
+                            ui.label("Saved");
+                            ui.label("Current");
+                            ui.label("Change");
+                            ui.end_row();
+
+                            for difference in differences {
+                                ui.label(format!("{} - {}", difference.pedal_name, difference.parameter_name));
+                                ui.label(parameter_value_string(&difference.saved));
+                                ui.label(parameter_value_string(&difference.current));
+                                ui.label(difference.change().map_or_else(String::new, |change| format!("{change:+.2}")));
+                                ui.end_row();
+                            }
+                        });
+
+                    ui.add_space(10.0);
+                    revert = ui.add_enabled(!state.is_observer(), Button::new("Revert To Saved")).clicked();
+                }
+            }
+        });
+
+    if revert {
+        state.revert_to_saved(active_pedalboard_id, ui.ctx());
+    }
+}
+
 fn add_pedal_menu(screen: &mut PedalboardStageScreen, ui: &mut Ui, rect: Rect) {
     let menu_layer_id = egui::LayerId::new(egui::Order::Foreground, ui.id().with("pedal_menu"));
     let mut menu_ui = ui.new_child(
@@ -48,19 +169,165 @@ fn add_pedal_menu(screen: &mut PedalboardStageScreen, ui: &mut Ui, rect: Rect) {
         Color32::from_gray(30),
     );
 
+    menu_ui.add_space(5.0);
+    menu_ui.add_sized(
+        Vec2::new(menu_ui.available_width()*0.95, 35.0),
+        egui::TextEdit::singleline(&mut screen.pedal_menu_filter)
+            .hint_text("Search pedals...")
+            .vertical_align(egui::Align::Center)
+    );
+    menu_ui.add_space(5.0);
+
+    let filter = screen.pedal_menu_filter.to_lowercase();
+    let matches_filter = |pedal: &PedalDiscriminants| {
+        filter.is_empty()
+            || pedal.display_name().to_lowercase().contains(&filter)
+            || pedal.category().display_name().to_lowercase().contains(&filter)
+    };
+
+    let recent_pedals: Vec<PedalDiscriminants> = screen.state.client_settings.borrow().recent_pedals.iter()
+        .filter(|pedal| matches_filter(pedal))
+        .cloned()
+        .collect();
+
+    let category_colors = screen.state.client_settings.borrow().category_colors;
+
+    let mut selected = None;
     egui::ScrollArea::vertical()
         .max_height(menu_ui.available_height())
         .show(&mut menu_ui, |ui| {
-            ui.add_space(5.0);
-            for pedal in PedalDiscriminants::iter() {
+            let mut pedal_button = |ui: &mut Ui, pedal: PedalDiscriminants| {
                 if ui.add_sized(Vec2::new(ui.available_width()*0.95, 35.0), egui::Button::new(pedal.display_name())).clicked() {
-                    let new_pedal = pedal.new_pedal();
-                    screen.state.add_pedal_to_active(&new_pedal, false);
-                    screen.show_pedal_menu = false
+                    selected = Some(pedal);
                 }
                 ui.separator();
+            };
+
+            if !recent_pedals.is_empty() {
+                ui.label(RichText::new("Recent").color(crate::FAINT_TEXT_COLOR));
+                for pedal in recent_pedals {
+                    pedal_button(ui, pedal);
+                }
+            }
+
+            for category in PedalCategory::iter() {
+                let mut pedals = PedalDiscriminants::iter()
+                    .filter(|pedal| pedal.category() == category && matches_filter(pedal))
+                    .peekable();
+
+                if pedals.peek().is_none() {
+                    continue;
+                }
+
+                let category_color = category_colors.color(category).unwrap_or(crate::FAINT_TEXT_COLOR);
+                ui.label(RichText::new(category.display_name()).color(category_color));
+                for pedal in pedals {
+                    pedal_button(ui, pedal);
+                }
             }
         });
+
+    if let Some(pedal) = selected {
+        let new_pedal = pedal.new_pedal();
+        screen.state.add_pedal_to_active(&new_pedal, false);
+        screen.show_pedal_menu = false;
+        screen.pedal_menu_filter.clear();
+
+        let mut client_settings = screen.state.client_settings.borrow_mut();
+        client_settings.recent_pedals.retain(|recent| *recent != pedal);
+        client_settings.recent_pedals.insert(0, pedal);
+        client_settings.recent_pedals.truncate(RECENT_PEDAL_COUNT);
+    }
+}
+
+/// Draw a small bar across the top of a pedal showing its CPU usage
+fn draw_pedal_load_bar(ui: &mut Ui, pedal_rect: Rect, percent: f32) {
+    let bar_rect = pedal_rect.with_max_y(pedal_rect.min.y + 0.025 * pedal_rect.height());
+    let fraction = (percent / PEDAL_LOAD_BAR_FULL_PERCENT).clamp(0.0, 1.0);
+    let color = if fraction < 0.5 {
+        Color32::from_rgb(50, 255, 50)
+    } else if fraction < 0.8 {
+        Color32::from_rgb(255, 165, 50)
+    } else {
+        Color32::from_rgb(255, 50, 50)
+    };
+
+    let painter = ui.painter();
+    painter.rect_filled(bar_rect, 2.0, Color32::from_black_alpha(180));
+    painter.rect_filled(bar_rect.with_max_x(bar_rect.min.x + bar_rect.width() * fraction), 2.0, color);
+    painter.text(
+        bar_rect.right_bottom() + Vec2::new(-2.0, 2.0),
+        egui::Align2::RIGHT_TOP,
+        format!("{percent:.1}%"),
+        egui::FontId::proportional(9.0),
+        Color32::WHITE
+    );
+}
+
+/// Buttons to recall, add and delete the scenes of the active pedalboard, and to collapse or expand all of its pedals
+fn scene_bar(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
+    let (pedalboard_id, scene_names, active_scene, pedal_ids) = {
+        let pedalboard_set = screen.state.pedalboards.active_pedalboardstage.borrow();
+        let pedalboard = &pedalboard_set.pedalboards[pedalboard_set.active_pedalboard];
+        let scene_names: Vec<String> = pedalboard.scenes.iter().map(|scene| scene.name.clone()).collect();
+        let pedal_ids: Vec<u32> = pedalboard.pedals.iter().map(|pedal| pedal.get_id()).collect();
+        (pedalboard.get_id(), scene_names, pedalboard.active_scene, pedal_ids)
+    };
+
+    let mut recall = None;
+    let mut delete = None;
+    let mut add = false;
+    ui.horizontal(|ui| {
+        ui.add_space(20.0);
+        ui.label("Scenes");
+        ui.add_space(5.0);
+
+        if scene_names.is_empty() {
+            ui.add(egui::Button::new(RichText::new("A")).selected(true).stroke(egui::Stroke::new(1.0, crate::THEME_COLOR)))
+                .on_hover_text("Add a scene to switch between sets of parameters");
+        }
+
+        for (i, name) in scene_names.iter().enumerate() {
+            let response = ui.add(
+                egui::Button::new(RichText::new(name)).selected(i == active_scene).stroke(egui::Stroke::new(1.0, crate::THEME_COLOR))
+            );
+            if response.clicked() {
+                recall = Some(i);
+            }
+            response.context_menu(|ui| {
+                if ui.button("Delete").clicked() {
+                    delete = Some(i);
+                    ui.close();
+                }
+            });
+        }
+
+        if scene_names.len() < MAX_SCENES && ui.button("+").on_hover_text("Add a scene with the current parameters").clicked() {
+            add = true;
+        }
+
+        ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_space(20.0);
+            if ui.button("Expand All").clicked() {
+                for pedal_id in &pedal_ids {
+                    screen.collapsed_pedals.remove(pedal_id);
+                }
+            }
+            if ui.button("Collapse All").on_hover_text("Show only the name and active toggle of each pedal").clicked() {
+                screen.collapsed_pedals.extend(pedal_ids.iter().copied());
+            }
+        });
+    });
+
+    if let Some(index) = recall {
+        screen.state.set_scene(pedalboard_id, index, ui.ctx());
+    }
+    if let Some(index) = delete {
+        screen.state.delete_scene(pedalboard_id, index, ui.ctx());
+    }
+    if add {
+        screen.state.add_scene(pedalboard_id);
+    }
 }
 
 fn current_time_string() -> String {
@@ -106,9 +373,61 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
                     {
                         screen.show_pedal_menu = !screen.show_pedal_menu;
                     };
+                    ui.add_space(10.0);
+
+                    let bypassed = screen.state.bypass_active.get();
+                    if ui
+                        .add_enabled_ui(
+                            screen.state.is_connected(),
+                            |ui| {
+                                ui.add_sized(
+                                    [ui.available_width()*0.12, ui.available_height()],
+                                    egui::Button::new(RichText::new("Bypass")).selected(bypassed).stroke(egui::Stroke::new(1.0, crate::THEME_COLOR))
+                                ).on_hover_text("Pass the input to the output without any pedals")
+                            },
+                        )
+                        .inner
+                        .clicked()
+                    {
+                        screen.state.set_bypass(!bypassed);
+                    };
+                    ui.add_space(10.0);
+
+                    let profiling = screen.state.pedal_profiling.get();
+                    if ui
+                        .add_enabled_ui(
+                            screen.state.is_connected(),
+                            |ui| {
+                                ui.add_sized(
+                                    [ui.available_width()*0.12, ui.available_height()],
+                                    egui::Button::new(RichText::new("Perf")).selected(profiling).stroke(egui::Stroke::new(1.0, crate::THEME_COLOR))
+                                ).on_hover_text("Show the CPU usage of each pedal")
+                            },
+                        )
+                        .inner
+                        .clicked()
+                    {
+                        screen.state.set_pedal_profiling(!profiling);
+                    };
+                    ui.add_space(10.0);
+
+                    if ui.add_sized(
+                        [ui.available_width()*0.12, ui.available_height()],
+                        egui::Button::new(RichText::new("Changes")).selected(screen.show_changes).stroke(egui::Stroke::new(1.0, crate::THEME_COLOR))
+                    ).on_hover_text("Compare the parameters with the pedalboard saved in the library").clicked() {
+                        screen.show_changes = !screen.show_changes;
+                    };
+                    ui.add_space(10.0);
+
+                    if ui.add_sized(
+                        [ui.available_width()*0.12, ui.available_height()],
+                        egui::Button::new(RichText::new("Perform")).stroke(egui::Stroke::new(1.0, crate::THEME_COLOR))
+                    ).on_hover_text("Show large pedalboard buttons for playing live").clicked() {
+                        screen.state.client_settings.borrow_mut().performance_mode = true;
+                    };
                     ui.add_space(20.0);
 
-                    ui.columns_const(|[ui_1, ui_2, ui_3, ui_4, ui_5]| {
+                    ui.columns_const(|[ui_1, ui_2, ui_3, ui_4, ui_5, ui_6, ui_7]| {
                         if screen.state.is_connected() {
                             // XRun monitor
                             ui_1.allocate_ui_with_layout(
@@ -122,9 +441,18 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
                                         XRunState::Many(_) => Color32::from_rgb(255, 50, 50),
                                     };
 
+                                    let xrun_details = match screen.xrun_state {
+                                        XRunState::None => None,
+                                        XRunState::Few((count, dropped, _)) | XRunState::Many((count, dropped, _)) => Some((count, dropped))
+                                    };
+
                                     ui.label("XRun");
-                                    let (_id, rect) = ui.allocate_space(Vec2::splat(20.0));
+                                    let (id, rect) = ui.allocate_space(Vec2::splat(20.0));
                                     ui.painter().rect_filled(rect, 2.0, xrun_color);
+                                    if let Some((count, dropped)) = xrun_details {
+                                        ui.interact(rect, id, egui::Sense::hover())
+                                            .on_hover_text(format!("{count} xruns, {dropped} samples dropped"));
+                                    }
                                 },
                             );
 
@@ -133,6 +461,7 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
                                 ui_2.available_size(),
                                 Layout::left_to_right(egui::Align::Center),
                                 |ui| {
+                                    screen.signal_led_ui(ui, 8.0);
                                     ui.label("Clip");
                                     let clipping_color = match screen.clipping_state {
                                         ClippingState::None => Color32::from_rgb(50, 255, 50),
@@ -142,18 +471,69 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
                                     ui.painter().rect_filled(rect, 2.0, clipping_color);
                                 },
                             );
+
+                            // Output buffer fill
+                            ui_3.allocate_ui_with_layout(
+                                ui_3.available_size(),
+                                Layout::left_to_right(egui::Align::Center),
+                                |ui| {
+                                    match screen.state.buffer_fill.get() {
+                                        Some((fill, latency_ms)) => {
+                                            let text = format!("Buf: {:.0}% ({:.1}ms)", fill, latency_ms);
+                                            if fill < BUFFER_FILL_LOW_WARNING {
+                                                ui.label(RichText::new(text).color(Color32::from_rgb(255, 165, 50)))
+                                                    .on_hover_text("Output buffer is almost empty, X Runs are likely. Increase the latency in settings.");
+                                            } else if fill > BUFFER_FILL_HIGH_WARNING {
+                                                ui.label(RichText::new(text).color(Color32::from_rgb(255, 165, 50)))
+                                                    .on_hover_text("Output buffer is almost full, adding latency. Decrease the latency in settings.");
+                                            } else {
+                                                ui.label(text)
+                                                    .on_hover_text("Average output buffer fill and the latency it adds");
+                                            }
+                                        },
+                                        None => {
+                                            ui.label("Buf: -");
+                                        }
+                                    }
+                                },
+                            );
+
+                            // Total pedal latency
+                            ui_4.allocate_ui_with_layout(
+                                ui_4.available_size(),
+                                Layout::left_to_right(egui::Align::Center),
+                                |ui| {
+                                    match screen.state.pedal_latency.get().zip(screen.state.processor_sample_rate.get()) {
+                                        Some((latency, sample_rate)) => {
+                                            let latency_ms = latency as f32 / sample_rate as f32 * 1000.0;
+                                            ui.label(format!("Pedals: {:.1}ms", latency_ms))
+                                                .on_hover_text(format!("Latency added by the pedals in the active pedalboard ({} samples)", latency));
+                                        },
+                                        None => {
+                                            ui.label("Pedals: -");
+                                        }
+                                    }
+
+                                    let wetness_text = match screen.state.wetness.get() {
+                                        Some(wetness) => format!("Wet: {}%", wetness),
+                                        None => "Wet: -".to_string()
+                                    };
+                                    ui.label(wetness_text)
+                                        .on_hover_text("How much of the output is effect rather than the dry input, across the whole pedalboard");
+                                },
+                            );
                         }
 
-                        let col_vertical_padding = (ui_3.available_height() - 20.0) * 0.5;
+                        let col_vertical_padding = (ui_5.available_height() - 20.0) * 0.5;
                         // CPU Usage
-                        ui_3.with_layout(Layout::top_down(egui::Align::Center), |ui| {
+                        ui_5.with_layout(Layout::top_down(egui::Align::Center), |ui| {
                             ui.add_space(col_vertical_padding);
                             let cpu_usage = screen.system.global_cpu_usage();
                             ui.label(format!("CPU: {:.0}%", cpu_usage.round()));
                         });
 
                         // RAM Usage
-                        ui_4.with_layout(Layout::top_down(egui::Align::Center), |ui| {
+                        ui_6.with_layout(Layout::top_down(egui::Align::Center), |ui| {
                             ui.add_space(col_vertical_padding);
                             let memory = screen.system.total_memory();
                             let used_memory = screen.system.used_memory();
@@ -162,7 +542,7 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
                         });
 
                         // Time
-                        ui_5.with_layout(Layout::top_down(egui::Align::Center), |ui| {
+                        ui_7.with_layout(Layout::top_down(egui::Align::Center), |ui| {
                             ui.add_space(col_vertical_padding);
                             ui.label(current_time_string());
                         });
@@ -174,6 +554,8 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
         },
     );
 
+    scene_bar(screen, ui);
+
     // Available rect for the pedalboard itself
     let available_rect = ui.available_rect_before_wrap();
     let drawing_volume_monitor = screen.state.client_settings.borrow().show_volume_monitor && screen.state.is_connected();
@@ -216,6 +598,26 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
         .max_rect(delete_button_rect));
 
     let mut changed: Option<(u32, (String, PedalParameterValue))> = None;
+    // Randomized or initialized parameters of a pedal
+    let mut changed_many: Option<(u32, Vec<(String, PedalParameterValue)>)> = None;
+    // Pedal with a file that changed on disk
+    let mut reload_pedal: Option<Pedal> = None;
+    // Pedal with its solo button clicked
+    let mut toggle_solo: Option<u32> = None;
+    // Pedal with its collapse button clicked
+    let mut toggle_collapse: Option<u32> = None;
+    let soloed_pedal = screen.state.soloed_pedal();
+    let category_colors = screen.state.client_settings.borrow().category_colors;
+
+    // Keyboard copy/paste, copies the hovered pedal
+    let (copy_pressed, mut paste_pedal) = if ui.ctx().wants_keyboard_input() {
+        (false, false)
+    } else {
+        ui.ctx().input_mut(|i| (
+            i.consume_key(egui::Modifiers::COMMAND, egui::Key::C),
+            i.consume_key(egui::Modifiers::COMMAND, egui::Key::V)
+        ))
+    };
     ui.horizontal(|ui| {
         if drawing_volume_monitor {
             // Input Volume Monitor
@@ -243,34 +645,146 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
                             let active_pedalboard = &mut pedalboard_set.pedalboards[active_index];
                             let active_id = active_pedalboard.get_id();
 
-                            let dnd_response = egui_dnd::dnd(ui, "pedalboard_designer_dnd").show_sized(active_pedalboard.pedals.iter_mut(), Vec2::new(pedal_width, pedal_width*PEDAL_HEIGHT_RATIO), |ui, pedal, handle, _state| {
-                                let whole_pedal_rect = ui.available_rect_before_wrap();
-                                ui.allocate_ui_with_layout(Vec2::new(pedal_width, pedal_width*PEDAL_HEIGHT_RATIO*0.95), Layout::top_down(egui::Align::Center), |ui| {
-                                    ui.spacing_mut().item_spacing = Vec2::ZERO;
-                                    
-                                    let mut command_buffer = Vec::new();
-                                    screen.state.get_commands(&format!("pedalmsg{}", pedal.get_id()), &mut command_buffer);
-                                    if let Some(v) = pedal.ui(ui, &command_buffer) {
-                                        changed = Some((pedal.get_id(), v));
-                                    }
-                                });
-        
-                                let button_rect = whole_pedal_rect.with_min_y(whole_pedal_rect.max.y - 0.05 * whole_pedal_rect.height());
-                                ui.scope_builder(UiBuilder::new().max_rect(button_rect), |ui| {
-                                    handle.sense(egui::Sense::DRAG).ui_sized(
-                                        ui,
-                                        ui.available_size(),
-                                        |ui| {
-                                            if ui.add_sized(ui.available_size(), Button::new("Click/Drag").sense(egui::Sense::click())).clicked() {
-                                                // Open the parameter window
-                                                let window_open_id = super::parameter_window::get_window_open_id(pedal);
-                                                ui.ctx().data_mut(
-                                                    |r| r.insert_temp(window_open_id, !r.get_temp(window_open_id).unwrap_or(false))
-                                                );
-                                            };
+                            let parallel_branch = active_pedalboard.parallel_branch;
+                            let branch_pedal_ids: Vec<u32> = active_pedalboard.parallel_branch_range()
+                                .map(|(start, end)| active_pedalboard.pedals[start..=end].iter().map(|p| p.get_id()).collect())
+                                .unwrap_or_default();
+                            let mut new_parallel_branch: Option<Option<ParallelBranch>> = None;
+                            let pedal_cpu = screen.state.pedal_profiling.get().then(|| screen.state.pedal_cpu.borrow());
+
+                            let full_size = Vec2::new(pedal_width, pedal_width*PEDAL_HEIGHT_RATIO);
+                            let collapsed_size = Vec2::new(pedal_width, pedal_width*COLLAPSED_PEDAL_HEIGHT_RATIO);
+                            let dnd_response = egui_dnd::dnd(ui, "pedalboard_designer_dnd").show_custom(|ui, iter| {
+                                for (i, pedal) in active_pedalboard.pedals.iter_mut().enumerate() {
+                                    let collapsed = screen.collapsed_pedals.contains(&pedal.get_id());
+                                    let size = if collapsed { collapsed_size } else { full_size };
+                                    iter.next(ui, egui::Id::new(&*pedal), i, true, |ui, item| item.ui_sized(ui, size, |ui, handle, _state| {
+                                        let whole_pedal_rect = ui.available_rect_before_wrap();
+                                        let in_branch = branch_pedal_ids.contains(&pedal.get_id());
+                                        if in_branch {
+                                            // Outline pedals in the parallel branch
+                                            ui.painter().rect_stroke(whole_pedal_rect.expand(4.0), 4.0, egui::Stroke::new(2.0, crate::THEME_COLOR), egui::StrokeKind::Outside);
+                                        }
+                                        if let Some(color) = category_colors.color(PedalDiscriminants::from(&*pedal).category()) {
+                                            // Inside the branch outline, so both can be seen
+                                            ui.painter().rect_stroke(whole_pedal_rect.expand(1.0), 3.0, egui::Stroke::new(2.0, color), egui::StrokeKind::Outside);
                                         }
-                                    );
-                                });
+                                        if copy_pressed && ui.rect_contains_pointer(whole_pedal_rect) {
+                                            screen.state.copy_pedal(pedal);
+                                        }
+                                        let button_row_height = full_size.y * 0.05;
+                                        if collapsed {
+                                            if let Some(v) = collapsed_pedal_ui(ui, pedal, whole_pedal_rect.with_max_y(whole_pedal_rect.max.y - button_row_height)) {
+                                                changed = Some((pedal.get_id(), v));
+                                            }
+                                        } else {
+                                            ui.allocate_ui_with_layout(Vec2::new(pedal_width, pedal_width*PEDAL_HEIGHT_RATIO*0.95), Layout::top_down(egui::Align::Center), |ui| {
+                                                ui.spacing_mut().item_spacing = Vec2::ZERO;
+
+                                                let mut command_buffer = Vec::new();
+                                                screen.state.get_commands(&format!("pedalmsg{}", pedal.get_id()), &mut command_buffer);
+                                                if let Some(v) = pedal.ui(ui, &command_buffer) {
+                                                    changed = Some((pedal.get_id(), v));
+                                                }
+                                                if FileWatcher::take_reload_request(ui.ctx(), pedal.get_id()) {
+                                                    reload_pedal = Some(pedal.clone());
+                                                }
+                                            });
+                                        }
+
+                                        if let Some(percent) = pedal_cpu.as_ref().and_then(|cpu| cpu.get(&pedal.get_id())) {
+                                            draw_pedal_load_bar(ui, whole_pedal_rect, *percent);
+                                        }
+        
+                                        let button_rect = whole_pedal_rect.with_min_y(whole_pedal_rect.max.y - button_row_height);
+                                        ui.scope_builder(UiBuilder::new().max_rect(button_rect).layout(Layout::left_to_right(egui::Align::Center)), |ui| {
+                                            let soloed = soloed_pedal == Some((active_id, pedal.get_id()));
+                                            if ui.add_sized(
+                                                Vec2::new(ui.available_height() * 1.5, ui.available_height()),
+                                                Button::new("S").selected(soloed)
+                                            ).on_hover_text("Solo: hear this pedal with the other pedals turned off").clicked() {
+                                                toggle_solo = Some(pedal.get_id());
+                                            }
+
+                                            let (collapse_text, collapse_hover) = if collapsed { ("+", "Expand this pedal") } else { ("-", "Collapse this pedal to its name and active toggle") };
+                                            if ui.add_sized(
+                                                Vec2::new(ui.available_height() * 1.5, ui.available_height()),
+                                                Button::new(collapse_text)
+                                            ).on_hover_text(collapse_hover).clicked() {
+                                                toggle_collapse = Some(pedal.get_id());
+                                            }
+
+                                            handle.sense(egui::Sense::DRAG).ui_sized(
+                                                ui,
+                                                ui.available_size(),
+                                                |ui| {
+                                                    let button_response = ui.add_sized(ui.available_size(), Button::new("Click/Drag").sense(egui::Sense::click()));
+                                                    if button_response.clicked() {
+                                                        // Open the parameter window
+                                                        let window_open_id = super::parameter_window::get_window_open_id(pedal);
+                                                        ui.ctx().data_mut(
+                                                            |r| r.insert_temp(window_open_id, !r.get_temp(window_open_id).unwrap_or(false))
+                                                        );
+                                                    };
+
+                                                    button_response.context_menu(|ui| {
+                                                        if ui.button("Copy").clicked() {
+                                                            screen.state.copy_pedal(pedal);
+                                                            ui.close();
+                                                        }
+                                                        if ui.button("Paste").clicked() {
+                                                            paste_pedal = true;
+                                                            ui.close();
+                                                        }
+
+                                                        ui.separator();
+                                                        if ui.button("Randomize").clicked() {
+                                                            changed_many = Some((pedal.get_id(), randomized_parameters(pedal)));
+                                                            ui.close();
+                                                        }
+                                                        if ui.button("Initialize").clicked() {
+                                                            changed_many = Some((pedal.get_id(), initial_parameters(pedal)));
+                                                            ui.close();
+                                                        }
+
+                                                        ui.separator();
+                                                        let pedal_id = pedal.get_id();
+                                                        if ui.button("Start Parallel Branch Here").clicked() {
+                                                            new_parallel_branch = Some(Some(ParallelBranch {
+                                                                start_pedal_id: pedal_id,
+                                                                end_pedal_id: parallel_branch.map_or(pedal_id, |b| b.end_pedal_id),
+                                                                mix: parallel_branch.map_or(0.5, |b| b.mix)
+                                                            }));
+                                                            ui.close();
+                                                        }
+                                                        if ui.button("End Parallel Branch Here").clicked() {
+                                                            new_parallel_branch = Some(Some(ParallelBranch {
+                                                                start_pedal_id: parallel_branch.map_or(pedal_id, |b| b.start_pedal_id),
+                                                                end_pedal_id: pedal_id,
+                                                                mix: parallel_branch.map_or(0.5, |b| b.mix)
+                                                            }));
+                                                            ui.close();
+                                                        }
+                                                        if let Some(mut branch) = parallel_branch {
+                                                            if in_branch {
+                                                                ui.horizontal(|ui| {
+                                                                    ui.label("Branch Mix");
+                                                                    if ui.add(egui::Slider::new(&mut branch.mix, 0.0..=1.0).fixed_decimals(2)).changed() {
+                                                                        new_parallel_branch = Some(Some(branch));
+                                                                    }
+                                                                });
+                                                            }
+                                                            if ui.button("Remove Parallel Branch").clicked() {
+                                                                new_parallel_branch = Some(None);
+                                                                ui.close();
+                                                            }
+                                                        }
+                                                    });
+                                                }
+                                            );
+                                        });
+                                    }));
+                                }
                             });
         
                             let mouse_over_delete = delete_button_rect.contains(ui.ctx().input(|i| i.pointer.hover_pos()).unwrap_or(Pos2::ZERO));
@@ -285,7 +799,10 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
                                 button_ui.put(button_ui.available_rect_before_wrap(), button);
                             }
         
-                            if dnd_response.is_drag_finished() {
+                            if let Some(branch) = new_parallel_branch {
+                                drop(pedalboard_set);
+                                screen.state.set_parallel_branch(active_id, branch, false);
+                            } else if dnd_response.is_drag_finished() {
                                 if let Some(update) = &dnd_response.update {
                                     let pedal_id = active_pedalboard.pedals[update.from].get_id();
                                     if mouse_over_delete {
@@ -328,26 +845,46 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
             screen.cached_midi_devices = screen.state.midi_state.borrow().get_all_parameter_devices();
         }
 
+        let noise_floor_learning = screen.state.noise_floor_learning.get();
+
         for pedal in active_pedalboards.pedalboards[active_pedalboard].pedals.iter_mut() {
-            match draw_parameter_window(ui, active_pedalboard_id, pedal, &screen.cached_midi_devices) {
+            let learn_progress = noise_floor_learning
+                .filter(|(pedal_id, _)| *pedal_id == pedal.get_id())
+                .map(|(_, progress)| progress);
+
+            match draw_parameter_window(ui, active_pedalboard_id, pedal, &screen.cached_midi_devices, learn_progress) {
                 Some(ParameterWindowChange::ParameterChanged(name, value)) => changed = Some((pedal.get_id(), (name, value))),
                 Some(ParameterWindowChange::AddMidiFunction(parameter_path, midi_function_values, device_id)) => {
                     screen.state.midi_state.borrow_mut().add_midi_parameter_function_to_device(parameter_path, midi_function_values, device_id);
+                    screen.state.mark_unsaved();
                 },
                 Some(ParameterWindowChange::RemoveMidiFunction(parameter, device_id)) => {
                     screen.state.midi_state.borrow_mut().remove_midi_parameter_function_from_device(&parameter, device_id);
+                    screen.state.mark_unsaved();
                 },
                 Some(ParameterWindowChange::ChangeMidiFunctionDevice(parameter, old_id, new_id)) => {
                     let midi_state = screen.state.midi_state.borrow_mut();
                     if let Some(parameter_functions) = midi_state.remove_midi_parameter_function_from_device(&parameter, old_id) {
                         midi_state.add_midi_parameter_function_to_device(parameter, parameter_functions, new_id);
                     }
+                    screen.state.mark_unsaved();
+                },
+                Some(ParameterWindowChange::LearnNoiseFloor) => {
+                    if screen.state.is_connected() {
+                        screen.state.learn_noise_floor(active_pedalboard_id, pedal.get_id(), false);
+                    } else {
+                        tracing::warn!("Cannot learn noise floor, not connected to processor");
+                    }
                 },
                 None => {},
             }
         }
     }
 
+    if screen.show_changes {
+        changes_window(screen, ui);
+    }
+
     if let Some((pedal_id, (name, value))) = changed {
         let active_pedalboard_id = {
             let pedalboard_set = screen.state.pedalboards.active_pedalboardstage.borrow_mut();
@@ -364,6 +901,61 @@ pub fn pedalboard_designer(screen: &mut PedalboardStageScreen, ui: &mut Ui) {
         );
     }
 
+    if let Some(pedal_id) = toggle_solo {
+        let active_pedalboard_id = {
+            let pedalboard_set = screen.state.pedalboards.active_pedalboardstage.borrow();
+            pedalboard_set.pedalboards[pedalboard_set.active_pedalboard].get_id()
+        };
+
+        screen.state.toggle_solo(active_pedalboard_id, pedal_id);
+    }
+
+    if let Some(pedal_id) = toggle_collapse {
+        if !screen.collapsed_pedals.remove(&pedal_id) {
+            screen.collapsed_pedals.insert(pedal_id);
+        }
+    }
+
+    if let Some(pedal) = reload_pedal {
+        let active_pedalboard_id = {
+            let pedalboard_set = screen.state.pedalboards.active_pedalboardstage.borrow();
+            pedalboard_set.pedalboards[pedalboard_set.active_pedalboard].get_id()
+        };
+
+        screen.state.reload_pedal_processor(active_pedalboard_id, &pedal);
+    }
+
+    if let Some((pedal_id, parameters)) = changed_many {
+        let active_pedalboard_id = {
+            let pedalboard_set = screen.state.pedalboards.active_pedalboardstage.borrow_mut();
+            pedalboard_set.pedalboards[pedalboard_set.active_pedalboard].get_id()
+        };
+
+        for (name, value) in parameters {
+            screen.state.set_parameter(
+                active_pedalboard_id,
+                pedal_id,
+                name,
+                value,
+                false,
+                ui.ctx()
+            );
+        }
+    }
+
+    if paste_pedal {
+        let pedal_count = {
+            let pedalboard_set = screen.state.pedalboards.active_pedalboardstage.borrow();
+            pedalboard_set.pedalboards[pedalboard_set.active_pedalboard].pedals.len()
+        };
+
+        if pedal_count < MAX_PEDAL_COUNT {
+            screen.state.paste_pedal_to_active();
+        } else {
+            tracing::warn!("Cannot paste pedal: pedalboard is full");
+        }
+    }
+
     if screen.show_pedal_menu {
         add_pedal_menu(screen, ui, pedalboard_available_rect.scale_from_center2(Vec2::new(0.6, 0.9)));
     }
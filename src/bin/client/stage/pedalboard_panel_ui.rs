@@ -5,6 +5,53 @@ use egui_dnd::dnd;
 use rs_pedalboard::{pedalboard::Pedalboard, pedalboard_set::PedalboardSet};
 use crate::THEME_COLOR;
 
+/// Select a pedalboard to morph the active pedalboard towards, and the morph amount
+fn morph_controls(screen: &mut PedalboardStageScreen, ui: &mut egui::Ui) {
+    let (active_index, pedalboard_names) = {
+        let active_pedalboards = screen.state.pedalboards.active_pedalboardstage.borrow();
+        let names: Vec<String> = active_pedalboards.pedalboards.iter().map(|pedalboard| pedalboard.name.clone()).collect();
+        (active_pedalboards.active_pedalboard, names)
+    };
+
+    let (morph_target_name, mut amount) = match &*screen.state.morph.borrow() {
+        Some(morph) => (Some(morph.to.name.clone()), morph.amount),
+        None => (None, 0.0)
+    };
+
+    let mut new_target = None;
+    let mut stop = false;
+    ui.horizontal(|ui| {
+        ui.add_space(10.0);
+        ui.label("Morph To");
+        egui::ComboBox::from_id_salt("morph_target")
+            .selected_text(morph_target_name.clone().unwrap_or_else(|| "None".to_string()))
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(morph_target_name.is_none(), "None").clicked() {
+                    stop = true;
+                }
+                for (i, name) in pedalboard_names.iter().enumerate() {
+                    if i != active_index && ui.selectable_label(morph_target_name.as_ref() == Some(name), name).clicked() {
+                        new_target = Some(i);
+                    }
+                }
+            });
+
+        if morph_target_name.is_some() {
+            ui.style_mut().spacing.slider_width = ui.available_width() * 0.8;
+            if ui.add(egui::Slider::new(&mut amount, 0.0..=1.0).show_value(false)).changed() {
+                screen.state.set_morph_amount(amount);
+            }
+        }
+    });
+
+    if stop {
+        screen.state.stop_morph(ui.ctx());
+    } else if let Some(target) = new_target {
+        screen.state.stop_morph(ui.ctx());
+        screen.state.start_morph(target);
+    }
+}
+
 // Big ugly function to display the pedalboard stage panel
 // Effectively a method on PedalboardStageScreen
 pub fn pedalboard_stage_panel(screen: &mut PedalboardStageScreen, ui: &mut egui::Ui) {
@@ -31,6 +78,7 @@ pub fn pedalboard_stage_panel(screen: &mut PedalboardStageScreen, ui: &mut egui:
         
         if columns[2].add_sized(buttons_row_size, egui::Button::new("Clear Stage")).clicked() {
             *screen.state.pedalboards.active_pedalboardstage.borrow_mut() = PedalboardSet::default();
+            screen.state.active_song.set(None);
             screen.state.load_active_set();
         }
     });
@@ -39,6 +87,13 @@ pub fn pedalboard_stage_panel(screen: &mut PedalboardStageScreen, ui: &mut egui:
     ui.separator();
     ui.add_space(5.0);
 
+    // === Morph ===
+    morph_controls(screen, ui);
+
+    ui.add_space(5.0);
+    ui.separator();
+    ui.add_space(5.0);
+
     // === Active Pedalboard stage List ===
     let row_width = ui.available_width();
     let row_height = ui.available_height() * 0.1;
@@ -55,9 +110,20 @@ pub fn pedalboard_stage_panel(screen: &mut PedalboardStageScreen, ui: &mut egui:
                     Layout::left_to_right(egui::Align::Center),
                     |ui| {
                         // === Each Row ===
-                        if ui.interact(ui.available_rect_before_wrap(), egui::Id::new(i), egui::Sense::CLICK).clicked() {
+                        let row_response = ui.interact(ui.available_rect_before_wrap(), egui::Id::new(i), egui::Sense::CLICK);
+                        if row_response.clicked() {
                             screen.current_action = Some(CurrentAction::ChangeActive(i));
                         }
+                        row_response.context_menu(|ui| {
+                            if ui.button("Copy").clicked() {
+                                screen.state.copy_pedalboard(pedalboard);
+                                ui.close();
+                            }
+                            if ui.button("Paste").clicked() {
+                                screen.current_action = Some(CurrentAction::PastePedalboard);
+                                ui.close();
+                            }
+                        });
 
                         if active_pedalboards.active_pedalboard == i {
                             ui.painter().rect_filled(
@@ -95,6 +161,11 @@ pub fn pedalboard_stage_panel(screen: &mut PedalboardStageScreen, ui: &mut egui:
                                 {
                                     screen.current_action = Some(CurrentAction::ChangeActive(i));
                                 }
+
+                                if active_pedalboards.home_pedalboard == Some(pedalboard.get_id()) {
+                                    ui.label(RichText::new("Home").color(crate::FAINT_TEXT_COLOR))
+                                        .on_hover_text("Activated whenever the stage is loaded");
+                                }
                             });
 
                             columns[1].allocate_ui_with_layout(
@@ -138,6 +209,32 @@ pub fn pedalboard_stage_panel(screen: &mut PedalboardStageScreen, ui: &mut egui:
                                         if ui.add(egui::Button::new(RichText::new("Duplicate New").text_style(egui::TextStyle::Heading))).clicked() {
                                             screen.current_action = Some(CurrentAction::DuplicateNew(i));
                                         }
+                                        ui.add_space(2.0);
+                                        ui.separator();
+                                        ui.add_space(2.0);
+                                        if ui.add(egui::Button::new(RichText::new("Copy").text_style(egui::TextStyle::Heading))).clicked() {
+                                            screen.state.copy_pedalboard(pedalboard);
+                                        }
+                                        ui.add_space(2.0);
+                                        ui.separator();
+                                        ui.add_space(2.0);
+                                        let is_home = active_pedalboards.home_pedalboard == Some(pedalboard.get_id());
+                                        let home_text = if is_home { "Clear Home" } else { "Set As Home" };
+                                        if ui.add(egui::Button::new(RichText::new(home_text).text_style(egui::TextStyle::Heading)))
+                                            .on_hover_text("Activate this pedalboard whenever the stage is loaded, instead of the last active pedalboard")
+                                            .clicked()
+                                        {
+                                            screen.current_action = Some(CurrentAction::SetHome((!is_home).then(|| pedalboard.get_id())));
+                                        }
+                                        ui.add_space(2.0);
+                                        ui.separator();
+                                        ui.add_space(2.0);
+                                        if ui.add(egui::Button::new(RichText::new("Reset Pedals").text_style(egui::TextStyle::Heading)))
+                                            .on_hover_text("Reset every pedal in this pedalboard to its default settings")
+                                            .clicked()
+                                        {
+                                            screen.current_action = Some(CurrentAction::ResetPedals(pedalboard.get_id()));
+                                        }
                                         ui.add_space(5.0);
                                     });
 
@@ -234,6 +331,20 @@ pub fn pedalboard_stage_panel(screen: &mut PedalboardStageScreen, ui: &mut egui:
             drop(active_pedalboards);
             screen.state.play(index, false);
         },
+        Some(CurrentAction::SetHome(pedalboard_id)) => {
+            drop(active_pedalboards);
+            screen.state.set_home_pedalboard(pedalboard_id);
+        },
+        Some(CurrentAction::ResetPedals(pedalboard_id)) => {
+            drop(active_pedalboards);
+            drop(pedalboard_library);
+            screen.state.reset_pedals(pedalboard_id, ui.ctx());
+        },
+        Some(CurrentAction::PastePedalboard) => {
+            drop(active_pedalboards);
+            drop(pedalboard_library);
+            screen.state.paste_pedalboard_to_stage();
+        },
         Some(CurrentAction::AddPedalboard) => {
             drop(active_pedalboards);
             drop(pedalboard_library);
@@ -0,0 +1,113 @@
+use super::PedalboardStageScreen;
+
+use eframe::egui::{self, Button, Color32, Layout, RichText, Vec2};
+use crate::{socket::Command, THEME_COLOR};
+
+/// Number of pedalboard buttons in each row of the performance view
+const PERFORMANCE_COLUMNS: usize = 3;
+/// Smallest height of a pedalboard button, so they stay easy to hit with many pedalboards
+const MIN_PEDALBOARD_BUTTON_HEIGHT: f32 = 90.0;
+
+/// Large pedalboard selector buttons for playing live, in place of the editing controls.
+///
+/// Footswitches and other MIDI functions are handled globally, so keep working in this view.
+pub fn performance_view(screen: &mut PedalboardStageScreen, ui: &mut egui::Ui) {
+    let (active_index, pedalboard_names) = {
+        let active_pedalboards = screen.state.pedalboards.active_pedalboardstage.borrow();
+        let names: Vec<String> = active_pedalboards.pedalboards.iter().map(|pedalboard| pedalboard.name.clone()).collect();
+        (active_pedalboards.active_pedalboard, names)
+    };
+
+    let spacing = 10.0;
+    let header_height = ui.available_height() * 0.12;
+    let footer_height = ui.available_height() * 0.2;
+
+    // === Header ===
+    ui.allocate_ui_with_layout(
+        Vec2::new(ui.available_width(), header_height),
+        Layout::left_to_right(egui::Align::Center),
+        |ui| {
+            ui.painter().rect_filled(ui.available_rect_before_wrap(), 5.0, crate::LIGHT_BACKGROUND_COLOR);
+            ui.add_space(spacing);
+
+            if ui.add_sized([ui.available_width() * 0.15, header_height * 0.7], Button::new("Exit")).clicked() {
+                screen.state.client_settings.borrow_mut().performance_mode = false;
+            }
+
+            let bypassed = screen.state.bypass_active.get();
+            ui.allocate_ui_with_layout(
+                Vec2::new(ui.available_width() * 0.82, header_height),
+                Layout::centered_and_justified(egui::Direction::TopDown),
+                |ui| {
+                    let name = pedalboard_names.get(active_index).cloned().unwrap_or_default();
+                    let color = if bypassed { crate::FAINT_TEXT_COLOR } else { crate::TEXT_COLOR };
+                    ui.label(RichText::new(name).size(header_height * 0.5).color(color));
+                }
+            );
+
+            screen.signal_led_ui(ui, header_height * 0.1);
+            ui.add_space(spacing);
+
+            if bypassed {
+                ui.label(RichText::new("Bypassed").color(Color32::from_rgb(255, 165, 50)));
+            } else {
+                ui.label(RichText::new(format!("{} / {}", active_index + 1, pedalboard_names.len())).color(crate::FAINT_TEXT_COLOR));
+            }
+        }
+    );
+
+    ui.add_space(spacing);
+
+    // === Pedalboard buttons ===
+    let grid_height = ui.available_height() - footer_height - spacing;
+    let rows = pedalboard_names.len().div_ceil(PERFORMANCE_COLUMNS).max(1);
+    let button_width = (ui.available_width() - spacing * (PERFORMANCE_COLUMNS - 1) as f32) / PERFORMANCE_COLUMNS as f32;
+    let button_height = ((grid_height - spacing * (rows - 1) as f32) / rows as f32).max(MIN_PEDALBOARD_BUTTON_HEIGHT);
+
+    let mut play_index = None;
+    ui.allocate_ui(Vec2::new(ui.available_width(), grid_height), |ui| {
+        egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            for (row, names) in pedalboard_names.chunks(PERFORMANCE_COLUMNS).enumerate() {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = spacing;
+                    for (column, name) in names.iter().enumerate() {
+                        let index = row * PERFORMANCE_COLUMNS + column;
+                        let button = if index == active_index {
+                            Button::new(RichText::new(name).size(button_height * 0.2).color(Color32::WHITE))
+                                .fill(THEME_COLOR.gamma_multiply(0.5))
+                                .stroke(egui::Stroke::new(2.0, THEME_COLOR))
+                        } else {
+                            Button::new(RichText::new(name).size(button_height * 0.2))
+                                .fill(crate::LIGHT_BACKGROUND_COLOR)
+                        };
+
+                        if ui.add_sized([button_width, button_height], button.wrap()).clicked() {
+                            play_index = Some(index);
+                        }
+                    }
+                });
+                ui.add_space(spacing);
+            }
+        });
+    });
+
+    if let Some(index) = play_index {
+        screen.state.play(index, false);
+    }
+
+    // === Previous/Next ===
+    ui.with_layout(Layout::bottom_up(egui::Align::Center), |ui| {
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = spacing;
+            let size = [(ui.available_width() - spacing) / 2.0, footer_height];
+            let text_size = footer_height * 0.25;
+
+            if ui.add_sized(size, Button::new(RichText::new("Previous").size(text_size))).clicked() {
+                screen.state.send_global_command(Command::PrevPedalboard, ui.ctx());
+            }
+            if ui.add_sized(size, Button::new(RichText::new("Next").size(text_size))).clicked() {
+                screen.state.send_global_command(Command::NextPedalboard, ui.ctx());
+            }
+        });
+    });
+}
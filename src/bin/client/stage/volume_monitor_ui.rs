@@ -35,6 +35,11 @@ impl VolumeMonitorWidget {
         }
     }
 
+    /// Latest peak volume received, without smoothing
+    pub fn volume(&self) -> f32 {
+        self.target_volume
+    }
+
     fn compute_smoothing_factor(update_interval: Duration) -> f32 {
         let tau = 0.3;
         let dt = update_interval.as_secs_f32();
@@ -1,9 +1,93 @@
-use std::{cell::{Cell, RefCell}, collections::HashSet, time::Instant};
+use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet}, time::{Duration, Instant}};
 use crossbeam::channel::Receiver;
-use rs_pedalboard::{pedalboard::{Pedalboard, ParameterPath}, pedals::{Pedal, PedalParameterValue, PedalTrait}, processor_settings::ProcessorSettingsSave};
-use crate::{midi::{MidiSettings, MidiState}, saved_pedalboards::SavedPedalboards, settings::{ClientSettings, VolumeNormalizationMode}, socket::{ClientSocket, Command}, Screen};
+use rs_pedalboard::{dsp_algorithms::test_signal::TestSignalKind, pedalboard::{ParallelBranch, Pedalboard, ParameterPath, MAX_SCENES, SCENE_NAMES}, pedals::{FileWatcher, Pedal, PedalDiscriminants, PedalParameterValue, PedalTrait}, processor_settings::ProcessorSettingsSave, socket_helper::set_log_full_commands, DEFAULT_REFRESH_DURATION};
+use crate::{automation::{beat_duration, Automation}, midi::{MidiSettings, MidiState}, notifications::Notifications, saved_pedalboards::{SavedPedalboards, UtilityState}, settings::{ClientSettings, VolumeNormalizationMode}, socket::{ClientSocket, Command}, Screen};
 use eframe::egui;
 
+/// Largest output trim set when normalizing the level of a pedalboard
+const MAX_OUTPUT_TRIM_DB: f32 = 24.0;
+
+/// Taps further apart than this start a new tap tempo
+const TAP_TEMPO_TIMEOUT: Duration = Duration::from_secs(2);
+/// Number of most recent taps that the tap tempo is averaged over
+const TAP_TEMPO_TAPS: usize = 4;
+/// Range of the metronome BPM
+pub const METRONOME_BPM_RANGE: std::ops::RangeInclusive<u32> = 40..=360;
+
+/// An item copied by the user, stored serialized so later edits to the source don't affect it
+pub enum ClipboardItem {
+    Pedal(String),
+    Pedalboard(String)
+}
+
+/// Interpolates the parameters of the active pedalboard towards another pedalboard
+pub struct PedalboardMorph {
+    // Snapshots of the pedalboards, taken when the morph was started.
+    // `from` is the active pedalboard, which has its parameters changed.
+    pub from: Pedalboard,
+    pub to: Pedalboard,
+    // 0.0 = `from`, 1.0 = `to`
+    pub amount: f32,
+    // The last amount sent to the processor
+    applied_amount: f32,
+    last_applied: Instant
+}
+
+impl PedalboardMorph {
+    /// Interpolated values for all parameters shared between the pedalboards, as (pedal id, parameter name, value).
+    /// Pedals are matched by position and type.
+    pub fn parameter_values(&self) -> Vec<(u32, String, PedalParameterValue)> {
+        let mut values = Vec::new();
+
+        for (from_pedal, to_pedal) in self.from.pedals.iter().zip(self.to.pedals.iter()) {
+            if PedalDiscriminants::from(from_pedal) != PedalDiscriminants::from(to_pedal) {
+                continue;
+            }
+
+            for (name, from_parameter) in from_pedal.get_parameters() {
+                if let Some(to_parameter) = to_pedal.get_parameters().get(name) {
+                    values.push((
+                        from_pedal.get_id(),
+                        name.clone(),
+                        from_parameter.value.interpolate(&to_parameter.value, self.amount)
+                    ));
+                }
+            }
+        }
+
+        values
+    }
+}
+
+/// A parameter of a pedal on the stage that differs from the pedalboard with the same ID in the library
+pub struct ParameterDifference {
+    pub pedal_id: u32,
+    pub pedal_name: &'static str,
+    pub parameter_name: String,
+    pub saved: PedalParameterValue,
+    pub current: PedalParameterValue
+}
+
+impl ParameterDifference {
+    /// How far the current value is from the saved value, for numeric parameters
+    pub fn change(&self) -> Option<f32> {
+        match (&self.saved, &self.current) {
+            (PedalParameterValue::Float(saved), PedalParameterValue::Float(current)) => Some(current - saved),
+            (PedalParameterValue::Int(saved), PedalParameterValue::Int(current)) => Some((current - saved) as f32),
+            _ => None
+        }
+    }
+}
+
+/// A pedal heard in isolation, with the other pedals of its pedalboard turned off
+pub struct PedalSolo {
+    pub pedalboard_id: u32,
+    pub pedal_id: u32,
+    // Active state of each pedal in the pedalboard before soloing, restored when the solo is released.
+    // Pedals turned on or off while soloing are updated, so the change is kept.
+    pre_solo_active: HashMap<u32, bool>
+}
+
 pub struct State {
     pub pedalboards: SavedPedalboards,
     socket: RefCell<ClientSocket>,
@@ -12,6 +96,11 @@ pub struct State {
     pub processor_settings: RefCell<ProcessorSettingsSave>,
     pub midi_state: RefCell<MidiState>,
     pub midi_command_receiver: Receiver<Command>,
+    pub clipboard: RefCell<Option<ClipboardItem>>,
+    // Index in the songs library of the song loaded into the stage, if any
+    pub active_song: Cell<Option<usize>>,
+    pub morph: RefCell<Option<PedalboardMorph>>,
+    pub solo: RefCell<Option<PedalSolo>>,
 
     // Utility state
     pub recording_time: Cell<Option<Instant>>,
@@ -19,7 +108,47 @@ pub struct State {
     pub metronome_active: Cell<bool>,
     pub metronome_bpm: Cell<u32>,
     pub metronome_volume: Cell<f32>,
+    // Beat in the bar and the time it was received from the processor
+    pub metronome_last_beat: Cell<Option<(usize, Instant)>>,
+    // Recorded parameter changes, and their playback
+    pub automation: RefCell<Automation>,
+    // Times of the recent tap tempo taps, oldest first
+    tempo_taps: RefCell<Vec<Instant>>,
     pub tuner_active: Cell<bool>,
+    // Whether the whole pedalboard chain is bypassed on the processor
+    pub bypass_active: Cell<bool>,
+    // Whether the processor output is muted
+    pub muted: Cell<bool>,
+    // Whether non-essential UI updates are paused, see ClientSettings::freeze_ui_updates
+    ui_updates_frozen: Cell<bool>,
+    // Observers mirror the processor that another client is controlling, without changing it
+    pub observer: Cell<bool>,
+    // Signal replacing the processor input, if any, and its level
+    pub test_signal: Cell<Option<TestSignalKind>>,
+    pub test_signal_level: Cell<f32>,
+    // Whether the processor is timing each pedal
+    pub pedal_profiling: Cell<bool>,
+    // Whether the processor sets the metronome tempo from the playing
+    pub transient_tempo: Cell<bool>,
+    // CPU usage (percentage of real time) of each pedal by ID, reported by the processor while profiling
+    pub pedal_cpu: RefCell<HashMap<u32, f32>>,
+    // Average output buffer fill percentage and the latency (ms) it represents, reported by the processor
+    pub buffer_fill: Cell<Option<(f32, f32)>>,
+    // Sample rate the processor runs pedals at
+    pub processor_sample_rate: Cell<Option<u32>>,
+    // Reason the last sample rate change was rejected by the processor, if it was
+    pub sample_rate_change_error: RefCell<Option<String>>,
+    // Total latency (in samples at the processor sample rate) of the pedals in the active pedalboard
+    pub pedal_latency: Cell<Option<usize>>,
+    // Percentage of the active pedalboard output that is not correlated with the dry input, reported by the processor
+    pub wetness: Cell<Option<u32>>,
+    // Pedal ID and progress (0-1) of the noise floor being measured for a noise gate, if any
+    pub noise_floor_learning: Cell<Option<(u32, f32)>>,
+
+    // Transient messages shown over the current screen
+    pub notifications: RefCell<Notifications>,
+    // Whether anything has changed since the state was last saved
+    unsaved_changes: Cell<bool>,
 
     pub prev_selected_screen: Cell<Option<Screen>>,
     pub selected_screen: Cell<Screen>
@@ -59,6 +188,11 @@ impl State {
     /// 
     /// Requires a lock on active_pedalboardstage and socket
     pub fn remove_pedalboard_from_stage(&self, index: usize, local: bool) {
+        if !local && self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
         let mut pedalboard_set = self.pedalboards.active_pedalboardstage.borrow_mut();
 
         if pedalboard_set.pedalboards.len() <= 1 {
@@ -82,6 +216,11 @@ impl State {
     /// 
     /// Requires a lock on active_pedalboardstage and socket
     pub fn move_pedalboard(&self, src_index: usize, dest_index: usize, local: bool) {
+        if !local && self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
         let mut pedalboard_set = self.pedalboards.active_pedalboardstage.borrow_mut();
         egui_dnd::utils::shift_vec(src_index, dest_index, &mut pedalboard_set.pedalboards);
 
@@ -95,6 +234,11 @@ impl State {
     /// 
     /// Requires a lock on active_pedalboardstage and socket
     pub fn add_pedalboard(&self, pedalboard: Pedalboard, local: bool) {
+        if !local && self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
         if !local {
             let mut socket = self.socket.borrow_mut();
             socket.send(Command::AddPedalboard(serde_json::to_string(&pedalboard).unwrap()));
@@ -106,6 +250,11 @@ impl State {
 
     /// Requires a lock on active_pedalboardstage, pedalboard_library, songs_library and socket
     pub fn rename_pedalboard(&self, pedalboard_id: u32, new_name: String) {
+        if self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
         let unique_name = self.pedalboards.unique_name(new_name.clone());
 
         // First rename any matching names in pedalboard library
@@ -125,10 +274,77 @@ impl State {
         }
     }
 
+    /// Add a tag to a pedalboard in the library and stage. Tags are only used by the client, so this isn't sent to the processor.
+    /// 
+    /// Requires a lock on active_pedalboardstage and pedalboard_library
+    pub fn add_pedalboard_tag(&self, pedalboard_id: u32, tag: &str) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.update_pedalboards(pedalboard_id, |pedalboard| {
+            pedalboard.add_tag(tag);
+        });
+    }
+
+    /// Requires a lock on active_pedalboardstage and pedalboard_library
+    pub fn remove_pedalboard_tag(&self, pedalboard_id: u32, tag: &str) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.update_pedalboards(pedalboard_id, |pedalboard| {
+            pedalboard.tags.retain(|t| !t.eq_ignore_ascii_case(tag));
+        });
+    }
+
+    /// Requires a lock on active_pedalboardstage and pedalboard_library
+    pub fn set_pedalboard_favorite(&self, pedalboard_id: u32, favorite: bool) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.update_pedalboards(pedalboard_id, |pedalboard| {
+            pedalboard.favorite = favorite;
+        });
+    }
+
+    /// Play a pedalboard from the library, playing it from the stage if it is already there,
+    /// or adding it to the end of the stage if not.
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library and socket
+    pub fn play_from_library(&self, pedalboard_id: u32) {
+        if self.is_observer() {
+            return;
+        }
+
+        let stage_index = self.pedalboards.active_pedalboardstage.borrow().pedalboards.iter().position(|p| p.get_id() == pedalboard_id);
+        let index = match stage_index {
+            Some(index) => index,
+            None => {
+                let pedalboard = match self.pedalboards.pedalboard_library.borrow().iter().find(|p| p.get_id() == pedalboard_id) {
+                    Some(pedalboard) => pedalboard.clone(),
+                    None => {
+                        tracing::warn!("Pedalboard {pedalboard_id} to play is not in the library");
+                        return;
+                    }
+                };
+                self.add_pedalboard(pedalboard, false);
+                self.pedalboards.active_pedalboardstage.borrow().pedalboards.len() - 1
+            }
+        };
+
+        self.play(index, false);
+    }
+
     /// Duplicate pedalboard in stage with same name
     /// 
     /// Requires a lock on active_pedalboardstage and socket
     pub fn duplicate_linked(&self, index: usize) {
+        if self.is_observer() {
+            return;
+        }
+
         let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow_mut();
         let pedalboard = &pedalboard_set.pedalboards[index];
         let new_pedalboard = pedalboard.clone();
@@ -143,6 +359,10 @@ impl State {
     /// 
     /// Requires a lock on active_pedalboardstage and socket
     pub fn duplicate_new(&self, index: usize) {
+        if self.is_observer() {
+            return;
+        }
+
         let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow_mut();
         let pedalboard = &pedalboard_set.pedalboards[index];
 
@@ -160,6 +380,11 @@ impl State {
     /// 
     /// Requires a lock on active_pedalboardstage, pedalboard_library, and socket
     pub fn add_pedal_to_pedalboard(&self, pedalboard_id: u32, pedal: &Pedal, local: bool) {
+        if !local && self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
         // Add in pedalboard library
         let mut pedalboard_library = self.pedalboards.pedalboard_library.borrow_mut();
         for pedalboard in pedalboard_library.iter_mut() {
@@ -197,6 +422,11 @@ impl State {
     /// 
     /// Requires a lock on active_pedalboardstage, pedalboard_library, and socket
     pub fn move_pedal(&self, pedalboard_id: u32, pedal_id: u32, mut to_index: usize, local: bool) {
+        if !local && self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
         let mut active_pedalboardstage = self.pedalboards.active_pedalboardstage.borrow_mut();
         let mut pedalboard_library = self.pedalboards.pedalboard_library.borrow_mut();
 
@@ -237,6 +467,16 @@ impl State {
     /// 
     /// Requires a lock on active_pedalboardstage, pedalboard_library, and socket
     pub fn delete_pedal(&self, pedalboard_id: u32, pedal_id: u32, local: bool) {
+        if !local && self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
+        // Turn the other pedals back on when the soloed pedal is deleted
+        if self.soloed_pedal() == Some((pedalboard_id, pedal_id)) {
+            self.release_solo();
+        }
+
         let mut active_pedalboardstage = self.pedalboards.active_pedalboardstage.borrow_mut();
         let mut pedalboard_library = self.pedalboards.pedalboard_library.borrow_mut();
 
@@ -260,10 +500,138 @@ impl State {
         }
     }
 
+    /// Set the parallel branch on all pedalboards, on stage and in library, with the given ID
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library and socket
+    pub fn set_parallel_branch(&self, pedalboard_id: u32, branch: Option<ParallelBranch>, local: bool) {
+        if !local && self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
+        for pedalboard in self.pedalboards.active_pedalboardstage.borrow_mut().pedalboards.iter_mut() {
+            if pedalboard.get_id() == pedalboard_id {
+                pedalboard.parallel_branch = branch;
+            }
+        }
+
+        for pedalboard in self.pedalboards.pedalboard_library.borrow_mut().iter_mut() {
+            if pedalboard.get_id() == pedalboard_id {
+                pedalboard.parallel_branch = branch;
+            }
+        }
+
+        if !local {
+            let mut socket = self.socket.borrow_mut();
+            socket.send(Command::SetParallelBranch(pedalboard_id, branch));
+        }
+    }
+
+    /// Reset every pedal in the pedalboards, on stage and in library, with the given ID to its default parameters.
+    /// The pedals keep their IDs and order.
+    /// 
+    /// The defaults are found here and sent as parameter updates, so the processor doesn't create default pedals on the audio thread.
+    /// 
+    /// Requires a lock on solo, active_pedalboardstage, pedalboard_library and socket
+    pub fn reset_pedals(&self, pedalboard_id: u32, ctx: &eframe::egui::Context) {
+        if self.is_observer() {
+            return;
+        }
+
+        // The pedals are all reset, so there is nothing for the solo to restore
+        if self.soloed_pedal().is_some_and(|(soloed_pedalboard_id, _)| soloed_pedalboard_id == pedalboard_id) {
+            self.solo.borrow_mut().take();
+        }
+
+        let changes: Vec<(u32, String, PedalParameterValue)> = {
+            let active_pedalboards = self.pedalboards.active_pedalboardstage.borrow();
+            let pedalboard_library = self.pedalboards.pedalboard_library.borrow();
+            active_pedalboards.pedalboards.iter().chain(pedalboard_library.iter())
+                .find(|pedalboard| pedalboard.get_id() == pedalboard_id)
+                .map(|pedalboard| pedalboard.pedals.iter()
+                    .flat_map(|pedal| {
+                        let pedal_id = pedal.get_id();
+                        pedal.default_parameter_changes().into_iter().map(move |(name, value)| (pedal_id, name, value))
+                    })
+                    .collect()
+                )
+                .unwrap_or_default()
+        };
+
+        for (pedal_id, parameter_name, parameter_value) in changes {
+            self.set_parameter(pedalboard_id, pedal_id, parameter_name, parameter_value, false, ctx);
+        }
+    }
+
+    /// Measure the input noise floor on the processor, to set the threshold of a noise gate pedal.
+    /// 
+    /// Requires a lock on socket.
+    pub fn learn_noise_floor(&self, pedalboard_id: u32, pedal_id: u32, local: bool) {
+        if !local && self.is_observer() {
+            return;
+        }
+
+        self.noise_floor_learning.set(Some((pedal_id, 0.0)));
+
+        if !local {
+            let mut socket = self.socket.borrow_mut();
+            socket.send(Command::LearnNoiseFloor(pedalboard_id, pedal_id));
+        }
+    }
+
+    /// Apply the gate threshold suggested by the processor, after measuring the noise floor.
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library and socket
+    pub fn apply_learned_gate_threshold(&self, pedalboard_id: u32, pedal_id: u32, threshold_db: f32, ctx: &eframe::egui::Context) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.noise_floor_learning.set(None);
+
+        let threshold_range = self.pedalboards.active_pedalboardstage.borrow().pedalboards.iter()
+            .filter(|pedalboard| pedalboard.get_id() == pedalboard_id)
+            .flat_map(|pedalboard| pedalboard.pedals.iter())
+            .find(|pedal| pedal.get_id() == pedal_id)
+            .and_then(|pedal| pedal.get_parameters().get("Threshold Db"))
+            .and_then(|parameter| Some((parameter.min.as_ref()?.as_float()?, parameter.max.as_ref()?.as_float()?)));
+
+        match threshold_range {
+            Some((min, max)) => {
+                let threshold_db = threshold_db.clamp(min, max);
+                tracing::info!("Setting learned noise gate threshold to {threshold_db}dB");
+                self.set_parameter(pedalboard_id, pedal_id, "Threshold Db".to_string(), PedalParameterValue::Float(threshold_db), false, ctx);
+            },
+            None => {
+                tracing::warn!("apply_learned_gate_threshold: Noise gate {} not found in pedalboard {}", pedal_id, pedalboard_id);
+            }
+        }
+    }
+
     /// Set a parameter on all pedalboards, on stage and in library, with the same name
     /// 
     /// Requires a lock on active_pedalboardstage, pedalboard_library and socket
     pub fn set_parameter(&self, pedalboard_id: u32, pedal_id: u32, parameter_name: String, parameter_value: PedalParameterValue, local: bool, ctx: &eframe::egui::Context) {
+        if !local && self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
+        self.automation.borrow_mut().record(ParameterPath {
+            pedalboard_id,
+            pedal_id,
+            parameter_name: parameter_name.clone()
+        }, parameter_value.clone());
+
+        // Keep manual changes to soloed out pedals when the solo is released
+        if parameter_name == "Active" {
+            if let (Some(solo), Some(active)) = (self.solo.borrow_mut().as_mut(), parameter_value.as_bool()) {
+                if solo.pedalboard_id == pedalboard_id {
+                    solo.pre_solo_active.insert(pedal_id, active);
+                }
+            }
+        }
+
         // Set parameter on pedalboard stage
         for pedalboard in self.pedalboards.active_pedalboardstage.borrow_mut().pedalboards.iter_mut() {
             if pedalboard.get_id() == pedalboard_id {
@@ -300,126 +668,1064 @@ impl State {
         }
     }
 
-    /// Tell the processor to load the client's active pedalboard stage
-    pub fn load_active_set(&self) {
-        let mut socket = self.socket.borrow_mut();
-        let active_pedalboardstage = self.pedalboards.active_pedalboardstage.borrow();
-        socket.send(Command::LoadSet(serde_json::to_string(&*active_pedalboardstage).unwrap()));
+    /// The parameters of a pedalboard on the stage that differ from the pedalboard saved in the library, in pedal order.
+    /// Returns None if the pedalboard is not in the library.
+    /// 
+    /// Pedals that were added or removed since saving are not compared.
+    /// 
+    /// Requires a lock on active_pedalboardstage and pedalboard_library
+    pub fn parameter_differences(&self, pedalboard_id: u32) -> Option<Vec<ParameterDifference>> {
+        let pedalboard_library = self.pedalboards.pedalboard_library.borrow();
+        let saved_pedalboard = pedalboard_library.iter().find(|pedalboard| pedalboard.get_id() == pedalboard_id)?;
+
+        let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+        let Some(pedalboard) = pedalboard_set.pedalboards.iter().find(|pedalboard| pedalboard.get_id() == pedalboard_id) else {
+            return Some(Vec::new());
+        };
+
+        let mut differences = Vec::new();
+        for pedal in &pedalboard.pedals {
+            let Some(saved_pedal) = saved_pedalboard.pedals.iter().find(|saved_pedal| saved_pedal.get_id() == pedal.get_id()) else {
+                continue;
+            };
+
+            let mut pedal_differences: Vec<ParameterDifference> = pedal.get_parameters().iter()
+                .filter_map(|(name, parameter)| {
+                    let saved_parameter = saved_pedal.get_parameters().get(name)?;
+                    (saved_parameter.value != parameter.value).then(|| ParameterDifference {
+                        pedal_id: pedal.get_id(),
+                        pedal_name: PedalDiscriminants::from(pedal).display_name(),
+                        parameter_name: name.clone(),
+                        saved: saved_parameter.value.clone(),
+                        current: parameter.value.clone()
+                    })
+                })
+                .collect();
+            pedal_differences.sort_by(|a, b| a.parameter_name.cmp(&b.parameter_name));
+            differences.extend(pedal_differences);
+        }
+
+        Some(differences)
     }
 
-    /// Play a pedalboard from the active stage
+    /// Set the parameters of a pedalboard on the stage back to the values saved in the library
     /// 
-    /// Requires a lock on active_pedalboardstage and socket
-    pub fn play(&self, pedalboard_index: usize, local: bool) {
-        let mut active_pedalboardset = self.pedalboards.active_pedalboardstage.borrow_mut();
-        active_pedalboardset.set_active_pedalboard(pedalboard_index);
+    /// Requires a lock on active_pedalboardstage, pedalboard_library and socket
+    pub fn revert_to_saved(&self, pedalboard_id: u32, ctx: &eframe::egui::Context) {
+        if self.is_observer() {
+            return;
+        }
 
-        let new_pedalboard_id = active_pedalboardset.pedalboards[active_pedalboardset.active_pedalboard].get_id();
-        self.midi_state.borrow().active_pedalboard_id.store(new_pedalboard_id, std::sync::atomic::Ordering::Relaxed);
+        for difference in self.parameter_differences(pedalboard_id).unwrap_or_default() {
+            self.set_parameter(pedalboard_id, difference.pedal_id, difference.parameter_name, difference.saved, false, ctx);
+        }
+    }
 
-        if !local {
-            let mut socket = self.socket.borrow_mut();
-            socket.send(Command::Play(pedalboard_index));
+    /// Copy a pedal into the clipboard
+    /// 
+    /// Requires a lock on clipboard
+    pub fn copy_pedal(&self, pedal: &Pedal) {
+        match serde_json::to_string(pedal) {
+            Ok(serialized) => *self.clipboard.borrow_mut() = Some(ClipboardItem::Pedal(serialized)),
+            Err(e) => tracing::error!("Failed to copy pedal: {e}")
         }
     }
 
-    /// Update the received messages from the socket thread.
+    /// Copy a pedalboard into the clipboard
     /// 
-    /// Requires a lock on socket.
-    pub fn update_socket_responses(&self) {
-        let mut socket = self.socket.borrow_mut();
-        socket.update_socket_responses();
+    /// Requires a lock on clipboard
+    pub fn copy_pedalboard(&self, pedalboard: &Pedalboard) {
+        match serde_json::to_string(pedalboard) {
+            Ok(serialized) => *self.clipboard.borrow_mut() = Some(ClipboardItem::Pedalboard(serialized)),
+            Err(e) => tracing::error!("Failed to copy pedalboard: {e}")
+        }
     }
 
-    /// Get a received command from the processor, beginning with the given prefix.
+    /// Get a new pedal from the clipboard, with a new ID, if the clipboard contains a pedal
     /// 
-    /// Requires a lock on socket
-    pub fn get_commands(&self, prefix: &str, into: &mut Vec<String>) {
-        let mut socket = self.socket.borrow_mut();
+    /// Requires a lock on clipboard
+    pub fn clipboard_pedal(&self) -> Option<Pedal> {
+        match &*self.clipboard.borrow() {
+            Some(ClipboardItem::Pedal(serialized)) => match serde_json::from_str::<Pedal>(serialized) {
+                Ok(pedal) => Some(pedal.clone_with_new_id()),
+                Err(e) => {
+                    tracing::error!("Failed to paste pedal: {e}");
+                    None
+                }
+            },
+            _ => None
+        }
+    }
 
-        // TODO: remove cloning with nightly `drain_filter`? 
-        socket.received_processor_commands.retain(|cmd| {
-            if cmd.starts_with(prefix) {
-                // Remove the prefix and push the command into the vector
-                let cmd_trim = cmd.trim_start_matches(prefix).trim().to_string();
-                into.push(cmd_trim);
-                false
-            } else {
-                true
-            }
-        });
+    /// Get a new pedalboard from the clipboard, with a new ID and unique name, if the clipboard contains a pedalboard
+    /// 
+    /// Requires a lock on clipboard, active_pedalboardstage and pedalboard_library
+    pub fn clipboard_pedalboard(&self) -> Option<Pedalboard> {
+        match &*self.clipboard.borrow() {
+            Some(ClipboardItem::Pedalboard(serialized)) => match serde_json::from_str::<Pedalboard>(serialized) {
+                Ok(pedalboard) => {
+                    let mut new_pedalboard = pedalboard.clone_with_new_id();
+                    new_pedalboard.name = self.pedalboards.unique_name(new_pedalboard.name.clone());
+                    Some(new_pedalboard)
+                },
+                Err(e) => {
+                    tracing::error!("Failed to paste pedalboard: {e}");
+                    None
+                }
+            },
+            _ => None
+        }
     }
 
-    /// Set whether the tuner is active.
+    /// Paste a pedal from the clipboard into the active pedalboard
     /// 
-    /// Requires a lock on socket.
-    pub fn set_tuner_active(&self, active: bool) {
-        self.tuner_active.set(active);
+    /// Requires a lock on clipboard, active_pedalboardstage, pedalboard_library, and socket
+    pub fn paste_pedal_to_active(&self) {
+        if self.is_observer() {
+            return;
+        }
 
-        let mut socket = self.socket.borrow_mut();
-        socket.send(Command::Tuner(active));
+        if let Some(pedal) = self.clipboard_pedal() {
+            self.add_pedal_to_active(&pedal, false);
+        }
     }
 
-    /// Set the metronome settings.
+    /// Paste a pedalboard from the clipboard into the active pedalboard stage
     /// 
-    /// Requires a lock on socket.
-    pub fn set_metronome(&self, active: bool, bpm: u32, volume: f32) {
-        self.metronome_active.set(active);
-        self.metronome_bpm.set(bpm);
-        self.metronome_volume.set(volume);
+    /// Requires a lock on clipboard, active_pedalboardstage, pedalboard_library and socket
+    pub fn paste_pedalboard_to_stage(&self) {
+        if self.is_observer() {
+            return;
+        }
 
-        let mut socket = self.socket.borrow_mut();
-        let rounded_volume = (volume * 100.0).round() / 100.0;
-        socket.send(Command::Metronome(active, bpm, rounded_volume));
+        if let Some(pedalboard) = self.clipboard_pedalboard() {
+            self.add_pedalboard(pedalboard, false);
+        }
     }
 
-    /// Set whether the volume monitor is active on the processor.
+    /// Paste a pedalboard from the clipboard into the pedalboard library
     /// 
-    /// Requires a lock on socket.
-    pub fn set_volume_monitor_active_processor(&self, active: bool) {
-        let mut socket = self.socket.borrow_mut();
-        socket.send(Command::VolumeMonitor(active));
+    /// Requires a lock on clipboard, active_pedalboardstage and pedalboard_library
+    pub fn paste_pedalboard_to_library(&self) {
+        if let Some(pedalboard) = self.clipboard_pedalboard() {
+            self.pedalboards.pedalboard_library.borrow_mut().push(pedalboard);
+            self.mark_unsaved();
+        }
     }
 
-    pub fn set_volume_normalization_processor(&self, mode: crate::settings::VolumeNormalizationMode, auto_decay: f32) {
-        let mut socket = self.socket.borrow_mut();
-        let rounded_auto_decay = (auto_decay * 1000.0).round() / 1000.0;
-        
-        if matches!(mode, VolumeNormalizationMode::Automatic) {
-            socket.send(Command::VolumeNormalization(VolumeNormalizationMode::Automatic, Some(rounded_auto_decay)));
-        } else {
-            socket.send(Command::VolumeNormalization(mode, None));
+    /// Replace the active pedalboard stage with the pedalboards of a song, in order, and play the pedalboard at `play_index`
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library, songs_library and socket
+    pub fn load_song(&self, song_index: usize, play_index: usize) {
+        if self.is_observer() {
+            return;
         }
-    }
+        self.mark_unsaved();
 
-    pub fn reset_volume_normalization_peak(&self) {
-        let mut socket = self.socket.borrow_mut();
-        socket.send(Command::VolumeNormalizationReset);
+        let Some(mut pedalboard_set) = self.pedalboards.song_pedalboard_set(song_index) else {
+            return;
+        };
+
+        pedalboard_set.active_pedalboard = play_index.min(pedalboard_set.pedalboards.len() - 1);
+        let active_pedalboard_id = pedalboard_set.pedalboards[pedalboard_set.active_pedalboard].get_id();
+        self.pedalboards.active_pedalboardstage.replace(pedalboard_set);
+        self.midi_state.borrow().active_pedalboard_id.store(active_pedalboard_id, std::sync::atomic::Ordering::Relaxed);
+        self.active_song.set(Some(song_index));
+        self.load_active_set();
     }
 
-    pub fn master_in_processor(&self, volume: f32) {
-        let mut socket = self.socket.borrow_mut();
-        let rounded_volume = (volume * 100.0).round() / 100.0;
-        socket.send(Command::MasterIn(rounded_volume));
-    }
+    /// Load the next song in the songs library, wrapping to the first
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library, songs_library and socket
+    pub fn next_song(&self) {
+        let song_count = self.pedalboards.songs_library.borrow().len();
+        if song_count == 0 {
+            return;
+        }
 
-    pub fn master_out_processor(&self, volume: f32) {
-        let mut socket = self.socket.borrow_mut();
-        let rounded_volume = (volume * 100.0).round() / 100.0;
-        socket.send(Command::MasterOut(rounded_volume));
+        let next_index = self.active_song.get().map_or(0, |index| (index + 1) % song_count);
+        self.load_song(next_index, 0);
     }
 
-    pub fn set_recording(&self, active: bool) {
-        let mut socket = self.socket.borrow_mut();
-        socket.send(Command::SetRecording(active));
+    /// Load the previous song in the songs library, wrapping to the last.
+    /// If `play_last` is true, the song's last pedalboard is played instead of its first.
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library, songs_library and socket
+    pub fn prev_song(&self, play_last: bool) {
+        let song_count = self.pedalboards.songs_library.borrow().len();
+        if song_count == 0 {
+            return;
+        }
+
+        let prev_index = match self.active_song.get() {
+            Some(0) | None => song_count - 1,
+            Some(index) => index - 1
+        };
+
+        let play_index = if play_last { usize::MAX } else { 0 };
+        self.load_song(prev_index, play_index);
+    }
+
+    /// Start morphing the active pedalboard towards the pedalboard at `to_index` in the stage
+    /// 
+    /// Requires a lock on active_pedalboardstage and morph
+    pub fn start_morph(&self, to_index: usize) {
+        if self.is_observer() {
+            return;
+        }
+
+        let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+        let Some(to) = pedalboard_set.pedalboards.get(to_index) else {
+            tracing::error!("start_morph: Invalid pedalboard index {to_index}");
+            return;
+        };
+
+        *self.morph.borrow_mut() = Some(PedalboardMorph {
+            from: pedalboard_set.pedalboards[pedalboard_set.active_pedalboard].clone(),
+            to: to.clone(),
+            amount: 0.0,
+            applied_amount: 0.0,
+            last_applied: Instant::now()
+        });
+    }
+
+    /// Stop morphing, returning the active pedalboard to its original parameters
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library, morph and socket
+    pub fn stop_morph(&self, ctx: &eframe::egui::Context) {
+        self.set_morph_amount(0.0);
+        self.apply_morph(ctx, true);
+        *self.morph.borrow_mut() = None;
+    }
+
+    /// Requires a lock on morph
+    pub fn set_morph_amount(&self, amount: f32) {
+        if let Some(morph) = self.morph.borrow_mut().as_mut() {
+            morph.amount = amount.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Apply the morph amount to the active pedalboard if it has changed, at most once per refresh duration.
+    /// The morph is stopped if its pedalboard is no longer active.
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library, morph and socket
+    pub fn update_morph(&self, ctx: &eframe::egui::Context) {
+        self.apply_morph(ctx, false);
+    }
+
+    /// If `force` is true, the morph is applied even if it was applied within the refresh duration
+    fn apply_morph(&self, ctx: &eframe::egui::Context, force: bool) {
+        let mut morph_ref = self.morph.borrow_mut();
+        let Some(morph) = morph_ref.as_mut() else {
+            return;
+        };
+
+        let (pedalboard_id, current_values) = {
+            let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+            let active_pedalboard = &pedalboard_set.pedalboards[pedalboard_set.active_pedalboard];
+            let current_values: Vec<(u32, String, PedalParameterValue)> = active_pedalboard.pedals.iter()
+                .flat_map(|pedal| pedal.get_parameters().iter().map(|(name, p)| (pedal.get_id(), name.clone(), p.value.clone())))
+                .collect();
+            (active_pedalboard.get_id(), current_values)
+        };
+
+        if pedalboard_id != morph.from.get_id() {
+            *morph_ref = None;
+            return;
+        }
+
+        if morph.applied_amount == morph.amount {
+            return;
+        }
+
+        if !force && morph.last_applied.elapsed() < DEFAULT_REFRESH_DURATION {
+            // Make sure the latest amount is applied even if nothing else causes a repaint
+            ctx.request_repaint_after(DEFAULT_REFRESH_DURATION);
+            return;
+        }
+
+        morph.applied_amount = morph.amount;
+        morph.last_applied = Instant::now();
+        let values = morph.parameter_values();
+        drop(morph_ref);
+
+        for (pedal_id, parameter_name, value) in values {
+            let unchanged = current_values.iter().any(|(id, name, current)| *id == pedal_id && *name == parameter_name && *current == value);
+            if unchanged {
+                continue;
+            }
+
+            self.set_parameter(pedalboard_id, pedal_id, parameter_name.clone(), value.clone(), true, ctx);
+            self.socket.borrow_mut().send(Command::ParameterUpdate(ParameterPath {
+                pedalboard_id,
+                pedal_id,
+                parameter_name
+            }, value));
+        }
+    }
+
+    /// Apply a change to the pedalboards with the given ID, in both the stage and the library
+    /// 
+    /// Requires a lock on active_pedalboardstage and pedalboard_library
+    fn update_pedalboards(&self, pedalboard_id: u32, mut f: impl FnMut(&mut Pedalboard)) {
+        self.mark_unsaved();
+
+        for pedalboard in self.pedalboards.active_pedalboardstage.borrow_mut().pedalboards.iter_mut() {
+            if pedalboard.get_id() == pedalboard_id {
+                f(pedalboard);
+            }
+        }
+
+        for pedalboard in self.pedalboards.pedalboard_library.borrow_mut().iter_mut() {
+            if pedalboard.get_id() == pedalboard_id {
+                f(pedalboard);
+            }
+        }
+    }
+
+    /// Add a scene to a pedalboard on the stage, with its current parameters, and make it the active scene.
+    /// Pedalboards without scenes first get a scene for their current parameters.
+    /// 
+    /// Requires a lock on active_pedalboardstage and pedalboard_library
+    pub fn add_scene(&self, pedalboard_id: u32) {
+        if self.is_observer() {
+            return;
+        }
+
+        let (mut scenes, active_scene) = {
+            let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+            let Some(pedalboard) = pedalboard_set.pedalboards.iter().find(|p| p.get_id() == pedalboard_id) else {
+                return;
+            };
+
+            let mut scenes = pedalboard.scenes.clone();
+            if scenes.is_empty() {
+                scenes.push(pedalboard.capture_scene(SCENE_NAMES[0].to_string()));
+            } else if scenes.len() >= MAX_SCENES {
+                return;
+            } else {
+                // Keep any changes made to the scene being left
+                let active_scene = pedalboard.active_scene.min(scenes.len() - 1);
+                scenes[active_scene] = pedalboard.capture_scene(scenes[active_scene].name.clone());
+            }
+
+            let name = SCENE_NAMES.iter()
+                .find(|name| !scenes.iter().any(|scene| scene.name == **name))
+                .unwrap_or(&SCENE_NAMES[0]);
+            scenes.push(pedalboard.capture_scene(name.to_string()));
+            let active_scene = scenes.len() - 1;
+            (scenes, active_scene)
+        };
+
+        self.update_pedalboards(pedalboard_id, |pedalboard| {
+            pedalboard.scenes = scenes.clone();
+            pedalboard.active_scene = active_scene;
+        });
+    }
+
+    /// Remove a scene from a pedalboard on the stage. If it is the active scene, a neighbouring scene is recalled first.
+    /// Removing the second last scene leaves the pedalboard without scenes.
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library and socket
+    pub fn delete_scene(&self, pedalboard_id: u32, index: usize, ctx: &egui::Context) {
+        if self.is_observer() {
+            return;
+        }
+
+        let (scene_count, active_scene) = {
+            let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+            let Some(pedalboard) = pedalboard_set.pedalboards.iter().find(|p| p.get_id() == pedalboard_id) else {
+                return;
+            };
+            (pedalboard.scenes.len(), pedalboard.active_scene)
+        };
+
+        if index >= scene_count {
+            return;
+        }
+
+        if index == active_scene {
+            self.set_scene(pedalboard_id, if index == 0 { 1 } else { index - 1 }, ctx);
+        }
+
+        self.update_pedalboards(pedalboard_id, |pedalboard| {
+            if index >= pedalboard.scenes.len() {
+                return;
+            }
+
+            pedalboard.scenes.remove(index);
+            if index < pedalboard.active_scene {
+                pedalboard.active_scene -= 1;
+            }
+            if pedalboard.scenes.len() <= 1 {
+                pedalboard.scenes.clear();
+                pedalboard.active_scene = 0;
+            }
+        });
+    }
+
+    /// Recall a scene of a pedalboard on the stage, storing the current parameters in the scene being left.
+    /// The changed parameters are sent to the processor.
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library and socket
+    pub fn set_scene(&self, pedalboard_id: u32, index: usize, ctx: &egui::Context) {
+        if self.is_observer() {
+            return;
+        }
+
+        let (scenes, changes) = {
+            let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+            let Some(pedalboard) = pedalboard_set.pedalboards.iter().find(|p| p.get_id() == pedalboard_id) else {
+                return;
+            };
+
+            if index >= pedalboard.scenes.len() || index == pedalboard.active_scene {
+                return;
+            }
+
+            let mut scenes = pedalboard.scenes.clone();
+            if let Some(scene) = scenes.get_mut(pedalboard.active_scene) {
+                *scene = pedalboard.capture_scene(scene.name.clone());
+            }
+            let changes = pedalboard.scene_changes(&scenes[index]);
+            (scenes, changes)
+        };
+
+        self.update_pedalboards(pedalboard_id, |pedalboard| {
+            pedalboard.scenes = scenes.clone();
+            pedalboard.active_scene = index;
+        });
+
+        for (pedal_id, parameter_name, value) in changes {
+            self.set_parameter(pedalboard_id, pedal_id, parameter_name.clone(), value.clone(), true, ctx);
+            self.socket.borrow_mut().send(Command::ParameterUpdate(ParameterPath {
+                pedalboard_id,
+                pedal_id,
+                parameter_name
+            }, value));
+        }
+    }
+
+    /// Recall the next scene of the active pedalboard, wrapping to the first
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library and socket
+    pub fn next_scene(&self, ctx: &egui::Context) {
+        let (pedalboard_id, scene_count, active_scene) = {
+            let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+            let pedalboard = &pedalboard_set.pedalboards[pedalboard_set.active_pedalboard];
+            (pedalboard.get_id(), pedalboard.scenes.len(), pedalboard.active_scene)
+        };
+
+        if scene_count < 2 {
+            return;
+        }
+
+        self.set_scene(pedalboard_id, (active_scene + 1) % scene_count, ctx);
+    }
+
+    /// Set the Active parameter of pedals in a pedalboard, on stage and in library, and send the changes to the processor
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library and socket
+    fn set_pedals_active(&self, pedalboard_id: u32, active_states: &HashMap<u32, bool>) {
+        self.update_pedalboards(pedalboard_id, |pedalboard| {
+            for pedal in pedalboard.pedals.iter_mut() {
+                if let Some(active) = active_states.get(&pedal.get_id()) {
+                    pedal.set_parameter_value("Active", PedalParameterValue::Bool(*active));
+                }
+            }
+        });
+
+        let mut socket = self.socket.borrow_mut();
+        for (pedal_id, active) in active_states {
+            socket.send(Command::ParameterUpdate(ParameterPath {
+                pedalboard_id,
+                pedal_id: *pedal_id,
+                parameter_name: "Active".to_string()
+            }, PedalParameterValue::Bool(*active)));
+        }
+    }
+
+    /// The pedalboard and pedal IDs of the soloed pedal, if any
+    /// 
+    /// Requires a lock on solo
+    pub fn soloed_pedal(&self) -> Option<(u32, u32)> {
+        self.solo.borrow().as_ref().map(|solo| (solo.pedalboard_id, solo.pedal_id))
+    }
+
+    /// Solo a pedal on the stage, turning off the other pedals in its pedalboard, or release the solo if the pedal is already soloed.
+    /// Soloing another pedal releases the current solo first.
+    /// 
+    /// Requires a lock on solo, active_pedalboardstage, pedalboard_library and socket
+    pub fn toggle_solo(&self, pedalboard_id: u32, pedal_id: u32) {
+        if self.is_observer() {
+            return;
+        }
+
+        let already_soloed = self.soloed_pedal() == Some((pedalboard_id, pedal_id));
+        self.release_solo();
+        if already_soloed {
+            return;
+        }
+
+        let (pre_solo_active, solo_active) = {
+            let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+            let Some(pedalboard) = pedalboard_set.pedalboards.iter().find(|p| p.get_id() == pedalboard_id) else {
+                return;
+            };
+
+            let pre_solo_active: HashMap<u32, bool> = pedalboard.pedals.iter()
+                .filter_map(|pedal| {
+                    let active = pedal.get_parameters().get("Active")?.value.as_bool()?;
+                    Some((pedal.get_id(), active))
+                })
+                .collect();
+
+            // Only send the pedals that change
+            let solo_active: HashMap<u32, bool> = pre_solo_active.iter()
+                .filter(|(id, active)| **active != (**id == pedal_id))
+                .map(|(id, _)| (*id, *id == pedal_id))
+                .collect();
+
+            (pre_solo_active, solo_active)
+        };
+
+        self.set_pedals_active(pedalboard_id, &solo_active);
+        *self.solo.borrow_mut() = Some(PedalSolo { pedalboard_id, pedal_id, pre_solo_active });
+    }
+
+    /// Restore the pedals turned off by the solo, if a pedal is soloed
+    /// 
+    /// Requires a lock on solo, active_pedalboardstage, pedalboard_library and socket
+    pub fn release_solo(&self) {
+        let Some(solo) = self.solo.borrow_mut().take() else {
+            return;
+        };
+
+        if self.is_observer() {
+            return;
+        }
+
+        // Pedals may have been removed, or changed by a scene, while soloing
+        let restore: HashMap<u32, bool> = {
+            let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+            let Some(pedalboard) = pedalboard_set.pedalboards.iter().find(|p| p.get_id() == solo.pedalboard_id) else {
+                return;
+            };
+
+            pedalboard.pedals.iter()
+                .filter_map(|pedal| {
+                    let pre_solo = *solo.pre_solo_active.get(&pedal.get_id())?;
+                    let current = pedal.get_parameters().get("Active")?.value.as_bool()?;
+                    (pre_solo != current).then_some((pedal.get_id(), pre_solo))
+                })
+                .collect()
+        };
+
+        self.set_pedals_active(solo.pedalboard_id, &restore);
+    }
+
+    /// Whether this client is only mirroring the processor, so must not change it
+    pub fn is_observer(&self) -> bool {
+        self.observer.get()
+    }
+
+    /// Set whether this client is an observer.
+    /// 
+    /// Requires a lock on socket and midi_state.
+    pub fn set_observer(&self, observer: bool) {
+        self.observer.set(observer);
+
+        // MIDI controls send commands straight to the processor, so observers don't give them the socket
+        let handle = if observer { None } else { self.socket.borrow().handle.clone() };
+        self.midi_state.borrow_mut().set_socket_handle(handle);
+    }
+
+    /// Ask the processor for the pedalboard set it is using, to mirror it.
+    /// 
+    /// Requires a lock on socket.
+    pub fn request_processor_set(&self) {
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::RequestSet);
+    }
+
+    /// Tell the processor to load the client's active pedalboard stage
+    pub fn load_active_set(&self) {
+        if self.is_observer() {
+            return;
+        }
+
+        // The processor activates the home pedalboard when it loads the set, so match it
+        let home_index = self.pedalboards.active_pedalboardstage.borrow().home_pedalboard_index();
+        if let Some(index) = home_index {
+            self.play(index, true);
+        }
+
+        let mut socket = self.socket.borrow_mut();
+        let active_pedalboardstage = self.pedalboards.active_pedalboardstage.borrow();
+        socket.send(Command::LoadSet(serde_json::to_string(&*active_pedalboardstage).unwrap()));
+    }
+
+    /// Set the pedalboard on the stage activated whenever the stage is loaded, or None to keep the last active pedalboard.
+    /// The processor uses it the next time the stage is loaded.
+    /// 
+    /// Requires a lock on active_pedalboardstage
+    pub fn set_home_pedalboard(&self, pedalboard_id: Option<u32>) {
+        if self.is_observer() {
+            return;
+        }
+        self.mark_unsaved();
+
+        self.pedalboards.active_pedalboardstage.borrow_mut().home_pedalboard = pedalboard_id;
+    }
+
+    /// Play a pedalboard from the active stage
+    /// 
+    /// Requires a lock on active_pedalboardstage and socket
+    pub fn play(&self, pedalboard_index: usize, local: bool) {
+        if !local && self.is_observer() {
+            return;
+        }
+
+        // A solo only applies to the pedalboard being edited
+        self.release_solo();
+
+        let mut active_pedalboardset = self.pedalboards.active_pedalboardstage.borrow_mut();
+        active_pedalboardset.set_active_pedalboard(pedalboard_index);
+
+        let new_pedalboard_id = active_pedalboardset.pedalboards[active_pedalboardset.active_pedalboard].get_id();
+        self.midi_state.borrow().active_pedalboard_id.store(new_pedalboard_id, std::sync::atomic::Ordering::Relaxed);
+
+        if !local {
+            let mut socket = self.socket.borrow_mut();
+            socket.send(Command::Play(pedalboard_index));
+        }
+    }
+
+    /// Update the received messages from the socket thread.
+    /// 
+    /// Requires a lock on socket.
+    pub fn update_socket_responses(&self) {
+        let mut socket = self.socket.borrow_mut();
+        socket.update_socket_responses();
+    }
+
+    /// Get a received command from the processor, beginning with the given prefix.
+    /// 
+    /// Requires a lock on socket
+    /// Show a transient notification over the current screen.
+    pub fn notify(&self, message: String) {
+        self.notifications.borrow_mut().push(message);
+    }
+
+    pub fn get_commands(&self, prefix: &str, into: &mut Vec<String>) {
+        let mut socket = self.socket.borrow_mut();
+
+        // TODO: remove cloning with nightly `drain_filter`? 
+        socket.received_processor_commands.retain(|cmd| {
+            if cmd.starts_with(prefix) {
+                // Remove the prefix and push the command into the vector
+                let cmd_trim = cmd.trim_start_matches(prefix).trim().to_string();
+                into.push(cmd_trim);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Set whether the tuner is active.
+    /// The processor mutes the output in tandem if mute while tuning is enabled (see `set_mute_while_tuning_processor`).
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_tuner_active(&self, active: bool) {
+        self.tuner_active.set(active);
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::Tuner(active));
+    }
+
+    /// Set whether the processor mutes its output while the tuner is active,
+    /// restoring the previous mute state when the tuner stops.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_mute_while_tuning_processor(&self, enabled: bool) {
+        if self.is_observer() {
+            return;
+        }
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::MuteWhileTuning(enabled));
+    }
+
+    /// Record a tap of the tap tempo, setting the metronome BPM from the average time between the recent taps.
+    /// 
+    /// Requires a lock on socket.
+    pub fn tap_tempo(&self) {
+        let now = Instant::now();
+        let mut taps = self.tempo_taps.borrow_mut();
+        if taps.last().is_some_and(|last| now.duration_since(*last) > TAP_TEMPO_TIMEOUT) {
+            taps.clear();
+        }
+        if taps.len() == TAP_TEMPO_TAPS {
+            taps.remove(0);
+        }
+        taps.push(now);
+
+        if taps.len() < 2 {
+            return;
+        }
+
+        let average_interval = now.duration_since(taps[0]).as_secs_f32() / (taps.len() - 1) as f32;
+        let bpm = ((60.0 / average_interval).round() as u32).clamp(*METRONOME_BPM_RANGE.start(), *METRONOME_BPM_RANGE.end());
+        drop(taps);
+        self.set_metronome(self.metronome_active.get(), bpm, self.metronome_volume.get());
+    }
+
+    /// Set the metronome settings.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_metronome(&self, active: bool, bpm: u32, volume: f32) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.metronome_active.set(active);
+        self.metronome_bpm.set(bpm);
+        self.metronome_volume.set(volume);
+
+        let mut socket = self.socket.borrow_mut();
+        let rounded_volume = (volume * 100.0).round() / 100.0;
+        socket.send(Command::Metronome(active, bpm, rounded_volume));
+    }
+
+    /// Set whether the volume monitor is active on the processor.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_volume_monitor_active_processor(&self, active: bool) {
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::VolumeMonitor(active));
+    }
+
+    pub fn set_volume_normalization_processor(&self, mode: crate::settings::VolumeNormalizationMode, auto_decay: f32) {
+        if self.is_observer() {
+            return;
+        }
+
+        let mut socket = self.socket.borrow_mut();
+        let rounded_auto_decay = (auto_decay * 1000.0).round() / 1000.0;
+        
+        if matches!(mode, VolumeNormalizationMode::Automatic) {
+            socket.send(Command::VolumeNormalization(VolumeNormalizationMode::Automatic, Some(rounded_auto_decay)));
+        } else {
+            socket.send(Command::VolumeNormalization(mode, None));
+        }
+    }
+
+    /// Set whether the output limiter is active on the processor.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_output_limiter_processor(&self, active: bool) {
+        if self.is_observer() {
+            return;
+        }
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::OutputLimiter(active));
+    }
+
+    /// Set the clean boost (dB) before the pedalboard chain on the processor.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_pre_gain_processor(&self, db: f32) {
+        if self.is_observer() {
+            return;
+        }
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::PreGain(db));
+    }
+
+    /// Set whether the whole pedalboard chain is bypassed on the processor.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_bypass(&self, active: bool) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.bypass_active.set(active);
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::Bypass(active));
+    }
+
+    /// Set whether the processor output is muted.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_mute(&self, mute: bool) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.muted.set(mute);
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::SetMute(mute));
+    }
+
+    /// Set the test signal that replaces the input on the processor (None to use the input).
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_test_signal(&self, kind: Option<TestSignalKind>, level: f32) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.test_signal.set(kind);
+        self.test_signal_level.set(level);
+
+        let mut socket = self.socket.borrow_mut();
+        let rounded_level = (level * 100.0).round() / 100.0;
+        socket.send(Command::TestSignal(kind, rounded_level));
+    }
+
+    /// Change the sample rate the processor runs pedals at, without restarting it.
+    /// 
+    /// Only possible if the device sample rate is unchanged, so in practice this changes the upsample passes or internal sample rate.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_processor_sample_rate(&self, sample_rate: Option<u32>, upsample_passes: u32, internal_sample_rate: Option<u32>) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.sample_rate_change_error.replace(None);
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::SetSampleRate(sample_rate, upsample_passes, internal_sample_rate));
+    }
+
+    /// Ask the processor to measure how much a library pedalboard changes the level, so its output trim can be set.
+    /// Returns false if not connected to the processor.
+    /// 
+    /// Requires a lock on pedalboard_library and socket.
+    pub fn measure_pedalboard_level(&self, pedalboard_id: u32) -> bool {
+        let mut socket = self.socket.borrow_mut();
+        if !socket.is_connected() {
+            return false;
+        }
+
+        let pedalboard_library = self.pedalboards.pedalboard_library.borrow();
+        match pedalboard_library.iter().find(|p| p.get_id() == pedalboard_id) {
+            Some(pedalboard) => {
+                socket.send(Command::MeasurePedalboardLevel(serde_json::to_string(pedalboard).unwrap()));
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Ask the processor to reload the files of a pedal, e.g. after its IR or NAM model was changed on disk.
+    /// The pedal is loaded on a separate thread and replaces the pedal on the processor once ready.
+    /// 
+    /// Requires a lock on socket.
+    pub fn reload_pedal_processor(&self, pedalboard_id: u32, pedal: &Pedal) {
+        if self.is_observer() {
+            return;
+        }
+
+        tracing::info!("Reloading files of pedal {}", pedal.get_id());
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::ReloadPedal(pedalboard_id, serde_json::to_string(pedal).unwrap()));
+    }
+
+    /// Set the output trim of a library pedalboard to cancel out the level change measured by the processor.
+    /// 
+    /// Requires a lock on pedalboard_library.
+    pub fn apply_measured_pedalboard_level(&self, pedalboard_id: u32, gain_db: f32) {
+        let mut pedalboard_library = self.pedalboards.pedalboard_library.borrow_mut();
+        if let Some(pedalboard) = pedalboard_library.iter_mut().find(|p| p.get_id() == pedalboard_id) {
+            pedalboard.output_trim = (-gain_db).clamp(-MAX_OUTPUT_TRIM_DB, MAX_OUTPUT_TRIM_DB);
+            self.mark_unsaved();
+            tracing::info!("Set output trim of {} to {:.1}dB", pedalboard.name, pedalboard.output_trim);
+        }
+    }
+
+    /// Set whether the processor reports the CPU usage of each pedal.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_pedal_profiling(&self, active: bool) {
+        self.pedal_profiling.set(active);
+        self.pedal_cpu.borrow_mut().clear();
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::PedalProfiling(active));
+    }
+
+    /// Set whether the processor infers the metronome tempo from transients in the input.
+    /// Off by default, as a false trigger changes the tempo.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_transient_tempo(&self, enabled: bool) {
+        if self.is_observer() {
+            return;
+        }
+
+        self.transient_tempo.set(enabled);
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::TransientTempo(enabled));
+    }
+
+    /// Set whether commands are logged in full, on the client and processor.
+    /// 
+    /// Requires a lock on socket.
+    pub fn set_log_full_commands(&self, enabled: bool) {
+        set_log_full_commands(enabled);
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::LogFullCommands(enabled));
+    }
+
+    pub fn reset_volume_normalization_peak(&self) {
+        if self.is_observer() {
+            return;
+        }
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::VolumeNormalizationReset);
+    }
+
+    pub fn master_in_processor(&self, volume: f32) {
+        if self.is_observer() {
+            return;
+        }
+
+        let mut socket = self.socket.borrow_mut();
+        let rounded_volume = (volume * 100.0).round() / 100.0;
+        socket.send(Command::MasterIn(rounded_volume));
+    }
+
+    pub fn master_out_processor(&self, volume: f32) {
+        if self.is_observer() {
+            return;
+        }
+
+        let mut socket = self.socket.borrow_mut();
+        let rounded_volume = (volume * 100.0).round() / 100.0;
+        socket.send(Command::MasterOut(rounded_volume));
+    }
+
+    pub fn set_recording(&self, active: bool) {
+        if self.is_observer() {
+            return;
+        }
+
+        let mut socket = self.socket.borrow_mut();
+        socket.send(Command::SetRecording(active));
+        drop(socket);
         if active {
             self.recording_time.set(Some(Instant::now()));
         } else {
             self.recording_time.set(None);
         }
+        self.sync_automation_with_recorder(active);
+    }
+
+    /// Start recording a new automation take, replacing the current take
+    /// 
+    /// Requires a lock on automation
+    pub fn start_automation_recording(&self) {
+        if self.is_observer() {
+            return;
+        }
+        self.automation.borrow_mut().start_recording();
+    }
+
+    /// Play the automation take from the start.
+    /// When quantizing with the metronome running, playback starts on the next beat so the changes land on the metronome's beats.
+    /// 
+    /// Requires a lock on automation
+    pub fn start_automation_playback(&self) {
+        if self.is_observer() {
+            return;
+        }
+
+        let mut automation = self.automation.borrow_mut();
+        let now = Instant::now();
+        let mut start = now;
+        if let (true, true, Some((_, last_beat))) = (automation.quantize, self.metronome_active.get(), self.metronome_last_beat.get()) {
+            let beat = beat_duration(self.metronome_bpm.get());
+            start = last_beat;
+            while start < now {
+                start += beat;
+            }
+        }
+        automation.start_playback(start);
+    }
+
+    /// Stop recording or playing automation
+    /// 
+    /// Requires a lock on automation
+    pub fn stop_automation(&self) {
+        self.automation.borrow_mut().stop();
+    }
+
+    /// If the automation is synced with the recorder, play the take when recording starts, or record a take if there isn't one
+    /// 
+    /// Requires a lock on automation
+    fn sync_automation_with_recorder(&self, recording: bool) {
+        let (synced, has_take) = {
+            let automation = self.automation.borrow();
+            (automation.sync_with_recorder, !automation.take().is_empty())
+        };
+        if !synced {
+            return;
+        }
+
+        match (recording, has_take) {
+            (true, true) => self.start_automation_playback(),
+            (true, false) => self.start_automation_recording(),
+            (false, _) => self.stop_automation()
+        }
+    }
+
+    /// Send the automation changes that are due, and request a repaint for the next change.
+    /// Changes to pedals that are no longer on the stage are skipped.
+    /// 
+    /// Requires a lock on active_pedalboardstage, pedalboard_library, automation and socket
+    pub fn update_automation(&self, ctx: &eframe::egui::Context) {
+        let events = {
+            let mut automation = self.automation.borrow_mut();
+            if !automation.is_playing() {
+                return;
+            }
+
+            let quantize_bpm = automation.quantize.then(|| self.metronome_bpm.get());
+            let now = Instant::now();
+            let events = automation.take_due_events(now, quantize_bpm);
+            if let Some(next) = automation.time_to_next_event(now, quantize_bpm) {
+                ctx.request_repaint_after(next);
+            }
+            events
+        };
+
+        for (path, value) in events {
+            let on_stage = self.pedalboards.active_pedalboardstage.borrow().pedalboards.iter()
+                .any(|pedalboard| pedalboard.get_id() == path.pedalboard_id && pedalboard.pedals.iter().any(|pedal| pedal.get_id() == path.pedal_id));
+            if !on_stage {
+                continue;
+            }
+
+            self.set_parameter(path.pedalboard_id, path.pedal_id, path.parameter_name.clone(), value.clone(), true, ctx);
+            self.socket.borrow_mut().send(Command::ParameterUpdate(path, value));
+        }
     }
 
     pub fn set_recorder_clean(&self, clean: bool) {
+        if self.is_observer() {
+            return;
+        }
+
         let mut socket = self.socket.borrow_mut();
         socket.send(Command::RecordClean(clean));
         self.recording_save_clean.set(clean);
@@ -432,11 +1738,13 @@ impl State {
         let active_pedalboard_index = active_pedalboard.active_pedalboard;
         let active_pedalboard_id = active_pedalboard.pedalboards[active_pedalboard_index].get_id();
         drop(active_pedalboard);
+        let utilities = pedalboards.utilities.get();
 
         tracing::debug!("State::load_state: creating client socket");
         let socket = ClientSocket::new(crate::PROCESSOR_PORT);
         tracing::debug!("State::load_state: loading client settings");
         let client_settings = ClientSettings::load_or_default();
+        let observer = client_settings.observer_mode;
 
         // Set NAM folders, IR folders and VST2 in ctx memory so pedals can access
         tracing::info!("Indexing NAM, IR and VST2 folders...");
@@ -460,6 +1768,13 @@ impl State {
             writer.data.insert_temp(egui::Id::new("vst2_folders_state"), 1u32);
             writer.data.insert_temp(egui::Id::new("vst2_folders"), vst2_root_nodes);
         });
+        client_settings.knob_style.set(&egui_ctx);
+        client_settings.plot_range.set(&egui_ctx);
+        FileWatcher::set_enabled(&egui_ctx, client_settings.hot_reload_files);
+        // The environment variable or feature can enable it without the setting
+        if client_settings.log_full_commands {
+            set_log_full_commands(true);
+        }
 
         tracing::debug!("State::load_state: loading processor settings");
         let processor_settings = ProcessorSettingsSave::load_or_default();
@@ -484,25 +1799,74 @@ impl State {
             processor_settings: RefCell::new(processor_settings),
             midi_state: RefCell::new(midi_state),
             midi_command_receiver,
+            clipboard: RefCell::new(None),
+            active_song: Cell::new(None),
+            morph: RefCell::new(None),
+            solo: RefCell::new(None),
             recording_time: Cell::new(None),
-            recording_save_clean: Cell::new(true),
+            recording_save_clean: Cell::new(utilities.recording_save_clean),
             metronome_active: Cell::new(false),
-            metronome_bpm: Cell::new(120),
-            metronome_volume: Cell::new(0.5),
+            metronome_bpm: Cell::new(utilities.metronome_bpm),
+            metronome_volume: Cell::new(utilities.metronome_volume),
+            metronome_last_beat: Cell::new(None),
+            automation: RefCell::new(Automation::new()),
+            tempo_taps: RefCell::new(Vec::with_capacity(TAP_TEMPO_TAPS)),
             tuner_active: Cell::new(false),
+            bypass_active: Cell::new(false),
+            muted: Cell::new(false),
+            ui_updates_frozen: Cell::new(false),
+            observer: Cell::new(observer),
+            test_signal: Cell::new(None),
+            test_signal_level: Cell::new(0.25),
+            pedal_profiling: Cell::new(false),
+            transient_tempo: Cell::new(false),
+            pedal_cpu: RefCell::new(HashMap::new()),
+            processor_sample_rate: Cell::new(None),
+            sample_rate_change_error: RefCell::new(None),
+            pedal_latency: Cell::new(None),
+            wetness: Cell::new(None),
+            buffer_fill: Cell::new(None),
+            noise_floor_learning: Cell::new(None),
+            notifications: RefCell::new(Notifications::new()),
+            unsaved_changes: Cell::new(false),
             prev_selected_screen: Cell::new(None),
             selected_screen: Cell::new(Screen::Stage)
         }
     }
 
     pub fn save_state(&self) -> Result<(), std::io::Error> {
+        self.pedalboards.utilities.set(UtilityState {
+            metronome_bpm: self.metronome_bpm.get(),
+            metronome_volume: self.metronome_volume.get(),
+            recording_save_clean: self.recording_save_clean.get(),
+        });
         self.pedalboards.save()?;
         self.client_settings.borrow().save()?;
         self.processor_settings.borrow().save()?;
         self.midi_state.borrow().save_settings()?;
+        self.unsaved_changes.set(false);
         Ok(())
     }
 
+    /// Record that the pedalboards or settings have changed since the last save, so they are auto saved
+    pub fn mark_unsaved(&self) {
+        self.unsaved_changes.set(true);
+    }
+
+    /// The client, processor and MIDI settings as they would be saved.
+    /// The settings screen edits these directly, so compares them before and after drawing to mark them unsaved.
+    pub fn settings_snapshot(&self) -> (String, String, String) {
+        (
+            serde_json::to_string(&*self.client_settings.borrow()).unwrap_or_default(),
+            serde_json::to_string(&*self.processor_settings.borrow()).unwrap_or_default(),
+            self.midi_state.borrow().settings_snapshot()
+        )
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.unsaved_changes.get()
+    }
+
     pub fn connect_to_processor(&self) -> Result<(), std::io::Error> {
         let mut socket = self.socket.borrow_mut();
         if !socket.is_connected() {
@@ -510,7 +1874,7 @@ impl State {
             if socket.is_connected() {
                 socket.send(Command::RequestSampleRate);
 
-                let new_handle = socket.handle.clone();
+                let new_handle = if self.is_observer() { None } else { socket.handle.clone() };
                 drop(socket);
                 let mut midi_state = self.midi_state.borrow_mut();
                 midi_state.disconnect_from_all_ports();
@@ -518,14 +1882,31 @@ impl State {
                 midi_state.connect_to_auto_connect_ports();
 
                 let client_settings = self.client_settings.borrow();
-                self.set_volume_monitor_active_processor(client_settings.show_volume_monitor);
+                self.set_volume_monitor_active_processor(client_settings.volume_monitor_needed() && !self.ui_updates_frozen.get());
+                self.set_mute_while_tuning_processor(client_settings.mute_while_tuning);
                 self.set_volume_normalization_processor(client_settings.volume_normalization, client_settings.auto_volume_normalization_decay);
                 self.master_in_processor(client_settings.input_volume);
+                self.set_output_limiter_processor(self.processor_settings.borrow().output_limiter);
+                self.set_pre_gain_processor(self.processor_settings.borrow().pre_gain_db);
+                self.set_bypass(self.bypass_active.get());
+                self.set_mute(self.muted.get());
+                self.set_test_signal(self.test_signal.get(), self.test_signal_level.get());
+                self.set_pedal_profiling(self.pedal_profiling.get());
+                self.set_transient_tempo(self.transient_tempo.get());
+                if self.client_settings.borrow().log_full_commands {
+                    self.set_log_full_commands(true);
+                }
                 self.set_recorder_clean(self.recording_save_clean.get());
                 self.set_metronome(self.metronome_active.get(), self.metronome_bpm.get(), self.metronome_volume.get());
                 self.recording_time.set(None);
                 self.tuner_active.set(false);
-                self.load_active_set();
+                self.buffer_fill.set(None);
+                self.processor_sample_rate.set(None);
+                self.sample_rate_change_error.replace(None);
+                self.pedal_latency.set(None);
+                self.wetness.set(None);
+                self.noise_floor_learning.set(None);
+                // The active set is loaded once the processor confirms no other clients are connected
             }
         }
         Ok(())
@@ -547,6 +1928,37 @@ impl State {
         socket.kill();
     }
 
+    /// Whether the processor should send volume monitor levels, taking into account frozen UI updates
+    /// 
+    /// Requires a lock on client_settings
+    pub fn volume_monitor_needed(&self) -> bool {
+        !self.ui_updates_frozen.get() && self.client_settings.borrow().volume_monitor_needed()
+    }
+
+    pub fn ui_updates_frozen(&self) -> bool {
+        self.ui_updates_frozen.get()
+    }
+
+    /// Pause or resume non-essential UI updates, turning the volume monitor on the processor off or back on.
+    /// Updates are frozen while the performance view is shown with the freeze UI updates setting,
+    /// so opening any other view resumes them.
+    /// 
+    /// Requires a lock on client_settings and socket
+    pub fn update_ui_freeze(&self, ctx: &eframe::egui::Context) {
+        let frozen = {
+            let client_settings = self.client_settings.borrow();
+            client_settings.freeze_ui_updates && client_settings.performance_mode && self.selected_screen.get() == Screen::Stage
+        };
+        if frozen == self.ui_updates_frozen.get() {
+            return;
+        }
+
+        tracing::info!("{} UI updates", if frozen { "Freezing" } else { "Resuming" });
+        self.ui_updates_frozen.set(frozen);
+        self.set_volume_monitor_active_processor(self.volume_monitor_needed());
+        ctx.request_repaint();
+    }
+
     pub fn set_screen(&self, screen: Screen) {
         if screen == Screen::Utilities {
             self.set_tuner_active(true);
@@ -564,227 +1976,366 @@ impl State {
     /// Update the state with commands that other threads have sent to the processor
     pub fn handle_other_thread_commands(&self, ctx: &eframe::egui::Context) {
         for command in self.midi_command_receiver.try_iter() {
-            match command {
-                Command::LoadSet(pedalboard_set_json) => {
-                    match serde_json::from_str::<rs_pedalboard::pedalboard_set::PedalboardSet>(&pedalboard_set_json) {
-                        Ok(pedalboard_set) => {
-                            self.pedalboards.active_pedalboardstage.replace(pedalboard_set);
-                        },
-                        Err(e) => {
-                            tracing::error!("Failed to parse pedalboard set JSON from processor: {}", e);
-                        }
+            if self.is_observer() && command.is_mutating() {
+                continue;
+            }
+
+            self.handle_local_command(command, ctx);
+        }
+    }
+
+    /// Send a command to the processor and update the state with it, the same as a global MIDI function would.
+    ///
+    /// Requires a lock on socket.
+    pub fn send_global_command(&self, command: Command, ctx: &eframe::egui::Context) {
+        if self.is_observer() && command.is_mutating() {
+            return;
+        }
+
+        self.socket.borrow_mut().send(command.clone());
+        self.handle_local_command(command, ctx);
+    }
+
+    /// Update the state with a command that another client connected to the processor has sent
+    pub fn apply_synced_command(&self, command: Command, ctx: &eframe::egui::Context) {
+        match command {
+            // The other client has already handled advancing songs, which would be synced as a new set
+            Command::NextPedalboard | Command::PrevPedalboard => {
+                let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+                let count = pedalboard_set.pedalboards.len();
+                let new_index = if matches!(command, Command::NextPedalboard) {
+                    (pedalboard_set.active_pedalboard + 1) % count
+                } else {
+                    (pedalboard_set.active_pedalboard + count - 1) % count
+                };
+                drop(pedalboard_set);
+                self.play(new_index, true);
+            },
+            command => self.handle_local_command(command, ctx)
+        }
+    }
+
+    /// Update the state with a command that was sent to the processor from elsewhere
+    fn handle_local_command(&self, command: Command, ctx: &eframe::egui::Context) {
+        // Mutating commands change the pedalboards or the saved settings
+        if command.is_mutating() {
+            self.mark_unsaved();
+        }
+
+        match command {
+            Command::LoadSet(pedalboard_set_json) => {
+                match serde_json::from_str::<rs_pedalboard::pedalboard_set::PedalboardSet>(&pedalboard_set_json) {
+                    Ok(pedalboard_set) => {
+                        self.pedalboards.active_pedalboardstage.replace(pedalboard_set);
+                    },
+                    Err(e) => {
+                        tracing::error!("Failed to parse pedalboard set JSON from processor: {}", e);
                     }
-                },
-                Command::Play(pedalboard_index) => {
-                    self.play(pedalboard_index, true);
-                },
-                Command::NextPedalboard => {
-                    let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
-                    let new_index = (pedalboard_set.active_pedalboard + 1) % pedalboard_set.pedalboards.len();
-                    drop(pedalboard_set);
+                }
+            },
+            Command::Play(pedalboard_index) => {
+                self.play(pedalboard_index, true);
+            },
+            Command::NextPedalboard => {
+                let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+                let at_end = pedalboard_set.active_pedalboard + 1 == pedalboard_set.pedalboards.len();
+                let new_index = (pedalboard_set.active_pedalboard + 1) % pedalboard_set.pedalboards.len();
+                drop(pedalboard_set);
+
+                if at_end && self.active_song.get().is_some() && self.client_settings.borrow().advance_to_next_song {
+                    // Overrides the processor's wrapped pedalboard by loading the whole set
+                    self.next_song();
+                } else {
                     self.play(new_index, true);
-                },
-                Command::PrevPedalboard => {
-                    let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
-                    let new_index = if pedalboard_set.active_pedalboard == 0 {
-                        pedalboard_set.pedalboards.len() - 1
-                    } else {
-                        pedalboard_set.active_pedalboard - 1
-                    };
-                    drop(pedalboard_set);
+                }
+            },
+            Command::PrevPedalboard => {
+                let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+                let at_start = pedalboard_set.active_pedalboard == 0;
+                let new_index = if pedalboard_set.active_pedalboard == 0 {
+                    pedalboard_set.pedalboards.len() - 1
+                } else {
+                    pedalboard_set.active_pedalboard - 1
+                };
+                drop(pedalboard_set);
+
+                if at_start && self.active_song.get().is_some() && self.client_settings.borrow().advance_to_next_song {
+                    self.prev_song(true);
+                } else {
                     self.play(new_index, true);
-                },
-                Command::MovePedal(pedalboard_id, pedal_id, to_index) => {
-                    self.move_pedal(pedalboard_id, pedal_id, to_index, true);
-                },
-                Command::DeletePedal(pedalboard_id, pedal_id) => {
-                    self.delete_pedal(pedalboard_id, pedal_id, true);
-                },
-                Command::MovePedalboard(src_index, dest_index) => {
-                    self.move_pedalboard(src_index, dest_index, true);
-                },
-                Command::DeletePedalboard(index) => {
-                    self.remove_pedalboard_from_stage(index, true);
-                },
-                Command::DeleteActivePedalboard => {
-                    let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
-                    let active_index = pedalboard_set.active_pedalboard;
-                    drop(pedalboard_set);
-                    self.remove_pedalboard_from_stage(active_index, true);
-                },
-                Command::AddPedalboard(pedalboard_json) => {
-                    match serde_json::from_str::<Pedalboard>(&pedalboard_json) {
-                        Ok(pedalboard) => {
-                            self.add_pedalboard(pedalboard, true);
-                        },
-                        Err(e) => {
-                            tracing::error!("Failed to parse pedalboard JSON from other thread: {}", e);
-                        }
-                    }
-                },
-                Command::AddPedal(pedalboard_id, pedal_json) => {
-                    match serde_json::from_str::<Pedal>(&pedal_json) {
-                        Ok(pedal) => {
-                            self.add_pedal_to_pedalboard(pedalboard_id, &pedal, true);
-                        },
-                        Err(e) => {
-                            tracing::error!("Failed to parse pedal JSON from other thread: {}", e);
-                        }
-                    }
-                },
-                Command::KillProcessor => {
-                    self.socket.borrow_mut().handle = None;
-                },
-                Command::MasterIn(vol) => {
-                    self.client_settings.borrow_mut().input_volume = vol;
-                },
-                Command::MasterOut(vol) => {
-                    self.client_settings.borrow_mut().output_volume = vol;
-                },
-                Command::VolumeNormalization(mode, decay) => {
-                    let mut client_settings = self.client_settings.borrow_mut();
-                    client_settings.volume_normalization = mode;
-                    if let Some(d) = decay {
-                        client_settings.auto_volume_normalization_decay = d;
-                    }
-                },
-                Command::SetRecording(active) => {
-                    if active {
-                        self.recording_time.set(Some(Instant::now()));
-                    } else {
-                        self.recording_time.set(None);
+                }
+            },
+            Command::LoadSong(song_index) => {
+                self.load_song(song_index, 0);
+            },
+            Command::NextSong => {
+                self.next_song();
+            },
+            Command::PrevSong => {
+                self.prev_song(false);
+            },
+            Command::Morph(amount) => {
+                self.set_morph_amount(amount);
+            },
+            Command::MovePedal(pedalboard_id, pedal_id, to_index) => {
+                self.move_pedal(pedalboard_id, pedal_id, to_index, true);
+            },
+            Command::DeletePedal(pedalboard_id, pedal_id) => {
+                self.delete_pedal(pedalboard_id, pedal_id, true);
+            },
+            Command::MovePedalboard(src_index, dest_index) => {
+                self.move_pedalboard(src_index, dest_index, true);
+            },
+            Command::DeletePedalboard(index) => {
+                self.remove_pedalboard_from_stage(index, true);
+            },
+            Command::DeleteActivePedalboard => {
+                let pedalboard_set = self.pedalboards.active_pedalboardstage.borrow();
+                let active_index = pedalboard_set.active_pedalboard;
+                drop(pedalboard_set);
+                self.remove_pedalboard_from_stage(active_index, true);
+            },
+            Command::AddPedalboard(pedalboard_json) => {
+                match serde_json::from_str::<Pedalboard>(&pedalboard_json) {
+                    Ok(pedalboard) => {
+                        self.add_pedalboard(pedalboard, true);
+                    },
+                    Err(e) => {
+                        tracing::error!("Failed to parse pedalboard JSON from other thread: {}", e);
                     }
-                },
-                Command::ToggleRecording => {
-                    let currently_recording = self.recording_time.get().is_some();
-                    if currently_recording {
-                        self.recording_time.set(None);
-                    } else {
-                        self.recording_time.set(Some(Instant::now()));
+                }
+            },
+            Command::AddPedal(pedalboard_id, pedal_json) => {
+                match serde_json::from_str::<Pedal>(&pedal_json) {
+                    Ok(pedal) => {
+                        self.add_pedal_to_pedalboard(pedalboard_id, &pedal, true);
+                    },
+                    Err(e) => {
+                        tracing::error!("Failed to parse pedal JSON from other thread: {}", e);
                     }
-                },
-                Command::RecordClean(clean) => {
-                    self.recording_save_clean.set(clean);
-                },
-                Command::ToggleClean => {
-                    let currently_clean = self.recording_save_clean.get();
-                    self.recording_save_clean.set(!currently_clean);
-                },
-                Command::VolumeMonitor(active) => {
-                    self.client_settings.borrow_mut().show_volume_monitor = active;
-                },
-                Command::ToggleVolumeMonitor => {
-                    let mut client_settings = self.client_settings.borrow_mut();
-                    client_settings.show_volume_monitor = !client_settings.show_volume_monitor;
-                },
-                Command::Metronome(active, bpm, volume) => {
-                    self.metronome_active.set(active);
-                    self.metronome_bpm.set(bpm);
-                    self.metronome_volume.set(volume);
-                },
-                Command::ToggleMetronome => {
-                    let currently_active = self.metronome_active.get();
-                    self.metronome_active.set(!currently_active);
-                },
-                Command::Tuner(active) => {
-                    self.tuner_active.set(active);
-                },
-                Command::ToggleTuner => {
-                    let currently_active = self.tuner_active.get();
-                    self.tuner_active.set(!currently_active);
-                },
-                Command::ParameterUpdate(path, value) => {
-                    self.set_parameter(
-                        path.pedalboard_id,
-                        path.pedal_id,
-                        path.parameter_name,
-                        value,
-                        true,
-                        ctx
-                    );
-                },
-                Command::VolumeNormalizationReset => {},
-                Command::SetMute(mute) => { tracing::info!("Set mute to {mute}") },
-                Command::ToggleMute => { tracing::info!("Toggled mute") },
-                Command::ChangeActiveParameter(value) => {
-                    let active_parameter = Self::get_active_parameter(ctx);
-                    if let Some(path) = active_parameter {
-                        let stage_pedalboards = self.pedalboards.active_pedalboardstage.borrow();
-                        if let Some(pedalboard) = stage_pedalboards.pedalboards.iter().find(|pb| pb.get_id() == path.pedalboard_id) {
-                            if let Some(pedal) = pedalboard.pedals.iter().find(|p| p.get_id() == path.pedal_id) {
-                                if let Some((_name, param)) = pedal.get_parameters().iter().find(|(name, _p)| *name == &path.parameter_name) {
-                                    match param.value {
-                                        PedalParameterValue::Bool(_) => {
-                                            let new_value = if value > 0.5 { true } else { false };
-                                            let path = path.clone();
-                                            drop(stage_pedalboards);
-                                            self.set_parameter(path.pedalboard_id, path.pedal_id, path.parameter_name, PedalParameterValue::Bool(new_value), false, ctx);
-                                        },
-                                        PedalParameterValue::Int(_) => {
-                                            let min_param = param.min.as_ref().expect("Int parameter should have min").as_int().unwrap();
-                                            let max_param = param.max.as_ref().expect("Int parameter should have max").as_int().unwrap();
-                                            let int_value = ((value * (max_param - min_param) as f32).round() as i16) + min_param;
-                                            let path = path.clone();
-                                            drop(stage_pedalboards);
-                                            self.set_parameter(path.pedalboard_id, path.pedal_id, path.parameter_name, PedalParameterValue::Int(int_value), false, ctx);
-                                        },
-                                        PedalParameterValue::Float(_) => {
-                                            let min_param = param.min.as_ref().expect("Float parameter should have min").as_float().unwrap();
-                                            let max_param = param.max.as_ref().expect("Float parameter should have max").as_float().unwrap();
-                                            let float_value = (value * (max_param - min_param)) + min_param;
-                                            let path = path.clone();
-                                            drop(stage_pedalboards);
-                                            self.set_parameter(path.pedalboard_id, path.pedal_id, path.parameter_name, PedalParameterValue::Float(float_value), false, ctx);
-                                        },
-                                        _ => tracing::warn!("Unsupported active parameter type")
-                                    }
+                }
+            },
+            Command::LearnNoiseFloor(pedalboard_id, pedal_id) => {
+                self.learn_noise_floor(pedalboard_id, pedal_id, true);
+            },
+            Command::SetParallelBranch(pedalboard_id, branch) => {
+                self.set_parallel_branch(pedalboard_id, branch, true);
+            },
+            Command::KillProcessor => {
+                self.socket.borrow_mut().handle = None;
+            },
+            Command::MasterIn(vol) => {
+                self.client_settings.borrow_mut().input_volume = vol;
+            },
+            Command::MasterOut(vol) => {
+                self.client_settings.borrow_mut().output_volume = vol;
+            },
+            Command::VolumeNormalization(mode, decay) => {
+                let mut client_settings = self.client_settings.borrow_mut();
+                client_settings.volume_normalization = mode;
+                if let Some(d) = decay {
+                    client_settings.auto_volume_normalization_decay = d;
+                }
+            },
+            Command::OutputLimiter(active) => {
+                self.processor_settings.borrow_mut().output_limiter = active;
+            },
+            Command::PreGain(db) => {
+                self.processor_settings.borrow_mut().pre_gain_db = db;
+            },
+            Command::SetRecording(active) => {
+                if active {
+                    self.recording_time.set(Some(Instant::now()));
+                } else {
+                    self.recording_time.set(None);
+                }
+                self.sync_automation_with_recorder(active);
+            },
+            Command::ToggleRecording => {
+                let currently_recording = self.recording_time.get().is_some();
+                if currently_recording {
+                    self.recording_time.set(None);
+                } else {
+                    self.recording_time.set(Some(Instant::now()));
+                }
+                self.sync_automation_with_recorder(!currently_recording);
+            },
+            Command::RecordClean(clean) => {
+                self.recording_save_clean.set(clean);
+            },
+            Command::ToggleClean => {
+                let currently_clean = self.recording_save_clean.get();
+                self.recording_save_clean.set(!currently_clean);
+            },
+            Command::VolumeMonitor(active) => {
+                self.client_settings.borrow_mut().show_volume_monitor = active;
+            },
+            Command::ToggleVolumeMonitor => {
+                let mut client_settings = self.client_settings.borrow_mut();
+                client_settings.show_volume_monitor = !client_settings.show_volume_monitor;
+            },
+            Command::Metronome(active, bpm, volume) => {
+                self.metronome_active.set(active);
+                self.metronome_bpm.set(bpm);
+                self.metronome_volume.set(volume);
+            },
+            Command::ToggleMetronome => {
+                let currently_active = self.metronome_active.get();
+                self.metronome_active.set(!currently_active);
+            },
+            Command::Tuner(active) => {
+                self.tuner_active.set(active);
+            },
+            Command::ToggleTuner => {
+                let currently_active = self.tuner_active.get();
+                self.tuner_active.set(!currently_active);
+            },
+            Command::ParameterUpdate(path, value) => {
+                self.set_parameter(
+                    path.pedalboard_id,
+                    path.pedal_id,
+                    path.parameter_name,
+                    value,
+                    true,
+                    ctx
+                );
+            },
+            Command::VolumeNormalizationReset => {},
+            Command::SetMute(mute) => {
+                self.muted.set(mute);
+            },
+            Command::ToggleMute => {
+                let currently_muted = self.muted.get();
+                self.muted.set(!currently_muted);
+            },
+            Command::TapTempo => {
+                self.tap_tempo();
+            },
+            Command::SetMetronomeBpm(bpm) => {
+                if bpm != self.metronome_bpm.get() {
+                    self.set_metronome(self.metronome_active.get(), bpm, self.metronome_volume.get());
+                }
+            },
+            Command::NextScene => {
+                self.next_scene(ctx);
+            },
+            Command::MuteWhileTuning(enabled) => {
+                self.client_settings.borrow_mut().mute_while_tuning = enabled;
+            },
+            Command::Bypass(active) => {
+                self.bypass_active.set(active);
+            },
+            Command::ToggleBypass => {
+                let currently_active = self.bypass_active.get();
+                self.bypass_active.set(!currently_active);
+            },
+            Command::TestSignal(kind, level) => {
+                self.test_signal.set(kind);
+                self.test_signal_level.set(level);
+            },
+            Command::SetSampleRate(sample_rate, upsample_passes, internal_sample_rate) => {
+                tracing::info!(
+                    "Requested sample rate change to {:?}hz with {} upsample passes, internal sample rate {:?}hz",
+                    sample_rate, upsample_passes, internal_sample_rate
+                );
+            },
+            Command::PedalProfiling(active) => {
+                self.pedal_profiling.set(active);
+                self.pedal_cpu.borrow_mut().clear();
+            },
+            Command::TransientTempo(enabled) => {
+                self.transient_tempo.set(enabled);
+            },
+            Command::LogFullCommands(enabled) => {
+                self.client_settings.borrow_mut().log_full_commands = enabled;
+            },
+            Command::MeasurePedalboardLevel(_) => {},
+            Command::ReloadPedal(_, _) => {},
+            Command::ChangeActiveParameter(value) => {
+                let active_parameter = Self::get_active_parameter(ctx);
+                if let Some(path) = active_parameter {
+                    let stage_pedalboards = self.pedalboards.active_pedalboardstage.borrow();
+                    if let Some(pedalboard) = stage_pedalboards.pedalboards.iter().find(|pb| pb.get_id() == path.pedalboard_id) {
+                        if let Some(pedal) = pedalboard.pedals.iter().find(|p| p.get_id() == path.pedal_id) {
+                            if let Some((_name, param)) = pedal.get_parameters().iter().find(|(name, _p)| *name == &path.parameter_name) {
+                                match param.value {
+                                    PedalParameterValue::Bool(_) => {
+                                        let new_value = if value > 0.5 { true } else { false };
+                                        let path = path.clone();
+                                        drop(stage_pedalboards);
+                                        self.set_parameter(path.pedalboard_id, path.pedal_id, path.parameter_name, PedalParameterValue::Bool(new_value), false, ctx);
+                                    },
+                                    PedalParameterValue::Int(_) => {
+                                        let min_param = param.min.as_ref().expect("Int parameter should have min").as_int().unwrap();
+                                        let max_param = param.max.as_ref().expect("Int parameter should have max").as_int().unwrap();
+                                        let int_value = ((value * (max_param - min_param) as f32).round() as i16) + min_param;
+                                        let path = path.clone();
+                                        drop(stage_pedalboards);
+                                        self.set_parameter(path.pedalboard_id, path.pedal_id, path.parameter_name, PedalParameterValue::Int(int_value), false, ctx);
+                                    },
+                                    PedalParameterValue::Float(_) => {
+                                        let min_param = param.min.as_ref().expect("Float parameter should have min").as_float().unwrap();
+                                        let max_param = param.max.as_ref().expect("Float parameter should have max").as_float().unwrap();
+                                        let float_value = (value * (max_param - min_param)) + min_param;
+                                        let path = path.clone();
+                                        drop(stage_pedalboards);
+                                        self.set_parameter(path.pedalboard_id, path.pedal_id, path.parameter_name, PedalParameterValue::Float(float_value), false, ctx);
+                                    },
+                                    PedalParameterValue::String(_) if param.choices.is_some() => {
+                                        let choice = param.value_in_range(value).unwrap();
+                                        let path = path.clone();
+                                        drop(stage_pedalboards);
+                                        self.set_parameter(path.pedalboard_id, path.pedal_id, path.parameter_name, choice, false, ctx);
+                                    },
+                                    _ => tracing::warn!("Unsupported active parameter type")
                                 }
                             }
                         }
                     }
-                },
-                Command::StageView => {
-                    // If we are already on this screen, go back to the previous screen
-                    if self.selected_screen.get() == Screen::Stage {
-                        self.set_screen(self.prev_selected_screen.get().unwrap_or(Screen::Stage));
-                    } else {
-                        self.set_screen(Screen::Stage);
-                    }
-                },
-                Command::LibraryView => {
-                    if self.selected_screen.get() == Screen::Library {
-                        self.set_screen(self.prev_selected_screen.get().unwrap_or(Screen::Library));
-                    } else {
-                        self.set_screen(Screen::Library);
-                    }
-                },
-                Command::UtilitiesView => {
-                    if self.selected_screen.get() == Screen::Utilities {
-                        let switching_to = self.prev_selected_screen.get().unwrap_or(Screen::Utilities);
-                        self.set_screen(switching_to);
-                    } else {
-                        self.set_screen(Screen::Utilities);
-                    }
-                },
-                Command::SongsView => {
-                    if self.selected_screen.get() == Screen::Songs {
-                        self.set_screen(self.prev_selected_screen.get().unwrap_or(Screen::Songs));
-                    } else {
-                        self.set_screen(Screen::Songs);
-                    }
-                },
-                Command::SettingsView => {
-                    if self.selected_screen.get() == Screen::Settings {
-                        self.set_screen(self.prev_selected_screen.get().unwrap_or(Screen::Settings));
-                    } else {
-                        self.set_screen(Screen::Settings);
-                    }
-                },
+                }
+            },
+            Command::StageView => {
+                // If we are already on this screen, go back to the previous screen
+                if self.selected_screen.get() == Screen::Stage {
+                    self.set_screen(self.prev_selected_screen.get().unwrap_or(Screen::Stage));
+                } else {
+                    self.set_screen(Screen::Stage);
+                }
+            },
+            Command::LibraryView => {
+                if self.selected_screen.get() == Screen::Library {
+                    self.set_screen(self.prev_selected_screen.get().unwrap_or(Screen::Library));
+                } else {
+                    self.set_screen(Screen::Library);
+                }
+            },
+            Command::UtilitiesView => {
+                if self.selected_screen.get() == Screen::Utilities {
+                    let switching_to = self.prev_selected_screen.get().unwrap_or(Screen::Utilities);
+                    self.set_screen(switching_to);
+                } else {
+                    self.set_screen(Screen::Utilities);
+                }
+            },
+            Command::SongsView => {
+                if self.selected_screen.get() == Screen::Songs {
+                    self.set_screen(self.prev_selected_screen.get().unwrap_or(Screen::Songs));
+                } else {
+                    self.set_screen(Screen::Songs);
+                }
+            },
+            Command::SettingsView => {
+                if self.selected_screen.get() == Screen::Settings {
+                    self.set_screen(self.prev_selected_screen.get().unwrap_or(Screen::Settings));
+                } else {
+                    self.set_screen(Screen::Settings);
+                }
+            },
 
-                Command::RequestSampleRate => tracing::error!("Unexpected RequestSampleRate command in other thread commands"),
-                Command::ThreadAliveTest => tracing::error!("Unexpected ThreadAliveTest command in other thread commands"),
-                Command::SubscribeToResponses(_) => tracing::error!("Unexpected SubscribeToResponses command in other thread commands"),
-            }
+            Command::RequestSampleRate => tracing::error!("Unexpected RequestSampleRate command in other thread commands"),
+            Command::RequestSet => tracing::error!("Unexpected RequestSet command in other thread commands"),
+            Command::ThreadAliveTest => tracing::error!("Unexpected ThreadAliveTest command in other thread commands"),
+            Command::SubscribeToResponses(_) => tracing::error!("Unexpected SubscribeToResponses command in other thread commands"),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,149 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui::{self, Color32, RichText, Vec2, Widget};
+
+use crate::{automation::MAX_AUTOMATION_EVENTS, state::State};
+use super::start_stop_icon;
+
+/// Colour of the record button and the timeline while recording
+const RECORD_COLOR: Color32 = Color32::from_rgb(200, 50, 50);
+
+pub struct AutomationWidget {
+    state: &'static State
+}
+
+impl AutomationWidget {
+    pub fn new(state: &'static State) -> Self {
+        Self {
+            state
+        }
+    }
+
+    /// Bar showing the changes in the take as ticks, with the position while recording or playing
+    fn timeline(&self, ui: &mut egui::Ui, now: Instant) {
+        let (rect, _) = ui.allocate_exact_size(Vec2::new(ui.available_width() * 0.8, 40.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 3.0, Color32::from_gray(25));
+
+        let automation = self.state.automation.borrow();
+        let position = automation.position(now);
+        // While recording the take grows, so the timeline shows the time recorded so far
+        let length = match (automation.is_recording(), position) {
+            (true, Some(position)) => position,
+            _ => automation.take().length()
+        };
+        if length.is_zero() {
+            return;
+        }
+
+        // Only draw one tick per pixel, as a take can hold many changes
+        let mut drawn = vec![false; rect.width().ceil() as usize + 1];
+        let tick_color = if automation.is_recording() { RECORD_COLOR } else { crate::THEME_COLOR };
+        for event in automation.take().events() {
+            let fraction = (event.time.as_secs_f32() / length.as_secs_f32()).min(1.0);
+            let column = (fraction * rect.width()) as usize;
+            if std::mem::replace(&mut drawn[column], true) {
+                continue;
+            }
+
+            let x = rect.left() + column as f32;
+            painter.line_segment([egui::pos2(x, rect.top() + 8.0), egui::pos2(x, rect.bottom() - 8.0)], (1.0, tick_color));
+        }
+
+        if let (true, Some(position)) = (automation.is_playing(), position) {
+            let fraction = (position.as_secs_f32() / length.as_secs_f32()).min(1.0);
+            let x = rect.left() + fraction * rect.width();
+            painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], (2.0, Color32::from_gray(220)));
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:02}:{:02}", duration.as_secs() / 60, duration.as_secs() % 60)
+}
+
+impl Widget for &mut AutomationWidget {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let now = Instant::now();
+        let (recording, playing, position, length, changes, full) = {
+            let automation = self.state.automation.borrow();
+            (
+                automation.is_recording(),
+                automation.is_playing(),
+                automation.position(now),
+                automation.take().length(),
+                automation.take().events().len(),
+                automation.take().is_full()
+            )
+        };
+        if recording || playing {
+            ui.ctx().request_repaint();
+        }
+
+        ui.vertical_centered(|ui| {
+            ui.add_space(10.0);
+            ui.label(RichText::from("Automation").size(28.0).color(Color32::from_gray(90)));
+            ui.add_space(7.0);
+
+            let status = if recording {
+                format!("Recording... {} ({changes} changes)", format_duration(position.unwrap_or_default()))
+            } else if playing {
+                format!("Playing {} / {}", format_duration(position.unwrap_or_default()), format_duration(length))
+            } else if changes == 0 {
+                "No take recorded".to_string()
+            } else {
+                format!("{} take ({changes} changes)", format_duration(length))
+            };
+            ui.label(RichText::new(status).size(30.0));
+            if full {
+                ui.label(RichText::new(format!("Take is full, only the first {MAX_AUTOMATION_EVENTS} changes were recorded")).color(crate::FAINT_TEXT_COLOR));
+            }
+
+            ui.add_space(5.0);
+            self.timeline(ui, now);
+            ui.add_space(10.0);
+
+            ui.horizontal(|ui| {
+                ui.add_space(ui.available_width() / 2.0 - 55.0);
+
+                // Record button, a circle to start and a square to stop
+                let record_response = ui.add_sized(Vec2::splat(50.0), egui::Button::new(""))
+                    .on_hover_text("Record the parameter changes made while recording, replacing the current take");
+                if record_response.clicked() {
+                    if recording {
+                        self.state.stop_automation();
+                    } else {
+                        self.state.start_automation_recording();
+                    }
+                }
+                if recording {
+                    start_stop_icon(ui, false, record_response.rect, 30.0);
+                } else {
+                    ui.painter().circle_filled(record_response.rect.center(), 13.0, RECORD_COLOR);
+                }
+
+                let play_response = ui.add_enabled(changes > 0 && !recording, egui::Button::new("").min_size(Vec2::splat(50.0)))
+                    .on_hover_text("Play the parameter changes in the take");
+                if play_response.clicked() {
+                    if playing {
+                        self.state.stop_automation();
+                    } else {
+                        self.state.start_automation_playback();
+                    }
+                }
+                start_stop_icon(ui, !playing, play_response.rect, 30.0);
+            });
+
+            ui.add_space(5.0);
+            let mut automation = self.state.automation.borrow_mut();
+            ui.checkbox(&mut automation.quantize, "Quantize To Beat")
+                .on_hover_text("Play each change on the nearest beat of the metronome tempo. If the metronome is running, playback starts on its next beat.");
+            ui.checkbox(&mut automation.looping, "Loop")
+                .on_hover_text("Play the take again from the start when it ends");
+            ui.checkbox(&mut automation.sync_with_recorder, "Sync With Recorder")
+                .on_hover_text("Play the take when the recorder starts, or record a take if there isn't one, and stop with the recorder");
+
+            ui.add_space(10.0);
+        }).response
+    }
+}
@@ -1,7 +1,12 @@
+use std::time::Duration;
+
 use eframe::egui::{self, Color32, RichText, Vec2, Widget};
 
 use crate::{state::State, utilities::start_stop_icon};
 
+/// How long the beat indicator stays lit after each beat
+const BEAT_FLASH_DURATION: Duration = Duration::from_millis(150);
+
 pub struct MetronomeWidget {
     pub state: &'static State,
 }
@@ -26,10 +31,31 @@ impl Widget for &mut MetronomeWidget {
             let mut volume = self.state.metronome_volume.get();
             ui.label(RichText::new(format!("{} BPM", bpm)).size(44.0));
 
+            // Beat indicator, flashes on each beat received from the processor
+            let (indicator_rect, _) = ui.allocate_exact_size(Vec2::splat(30.0), egui::Sense::hover());
+            let mut indicator_color = Color32::from_gray(40);
+            let mut indicator_radius = 10.0;
+            if let (true, Some((beat, time))) = (active, self.state.metronome_last_beat.get()) {
+                let elapsed = time.elapsed();
+                if elapsed < BEAT_FLASH_DURATION {
+                    let brightness = 1.0 - elapsed.as_secs_f32() / BEAT_FLASH_DURATION.as_secs_f32();
+                    let flash_color = if beat == 0 {
+                        // Accent the downbeat
+                        indicator_radius = 14.0;
+                        crate::THEME_COLOR
+                    } else {
+                        Color32::from_gray(200)
+                    };
+                    indicator_color = indicator_color.lerp_to_gamma(flash_color, brightness);
+                }
+                ui.ctx().request_repaint();
+            }
+            ui.painter().circle_filled(indicator_rect.center(), indicator_radius, indicator_color);
+
             // BPM Slider
             ui.style_mut().spacing.slider_width = ui.available_width()*0.5;
             if ui.add_sized(Vec2::new(ui.available_width()*0.5, 30.0),
-                egui::Slider::new(&mut bpm, 40..=360).show_value(false)
+                egui::Slider::new(&mut bpm, crate::state::METRONOME_BPM_RANGE).show_value(false)
             ).changed() {
                 self.state.set_metronome(active, bpm, volume)
             }
@@ -46,6 +72,15 @@ impl Widget for &mut MetronomeWidget {
                 }
             }
 
+            ui.add_space(5.0);
+            let mut transient_tempo = self.state.transient_tempo.get();
+            if ui.checkbox(&mut transient_tempo, "Set Tempo From Playing")
+                .on_hover_text("Set the tempo from the timing of the notes you play. Play at least four evenly spaced notes.")
+                .changed()
+            {
+                self.state.set_transient_tempo(transient_tempo);
+            }
+
             // Play/Pause button
             ui.add_space(5.0);
 
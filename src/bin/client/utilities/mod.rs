@@ -1,6 +1,8 @@
 pub mod tuner;
 pub mod metronome;
 pub mod recorder;
+pub mod test_signal;
+pub mod automation;
 
 use eframe::egui::{self, Color32, Layout, RichText, Vec2, Widget};
 
@@ -10,7 +12,9 @@ pub struct UtilitiesScreen {
     pub state: &'static State,
     pub tuner: tuner::TunerWidget,
     pub metronome: metronome::MetronomeWidget,
-    pub recorder: recorder::RecorderUtility
+    pub recorder: recorder::RecorderUtility,
+    pub test_signal: test_signal::TestSignalWidget,
+    pub automation: automation::AutomationWidget
 }
 
 impl UtilitiesScreen {
@@ -19,7 +23,9 @@ impl UtilitiesScreen {
             state,
             tuner: tuner::TunerWidget::new(state),
             metronome: metronome::MetronomeWidget::new(state),
-            recorder: recorder::RecorderUtility::new(state)
+            recorder: recorder::RecorderUtility::new(state),
+            test_signal: test_signal::TestSignalWidget::new(state),
+            automation: automation::AutomationWidget::new(state)
         }
     }
 }
@@ -68,6 +74,24 @@ impl Widget for &mut UtilitiesScreen {
                         ui.painter().rect_stroke(rect, 5.0, (1.0, border), egui::StrokeKind::Middle);
                     })
                 });
+                ui.add_space(spacing);
+                ui.allocate_ui_with_layout(Vec2::new(ui.available_width(), widget_height), Layout::left_to_right(egui::Align::Center), |ui| {
+                    let available_width = ui.available_width();
+                    ui.add_space(available_width*0.15);
+                    ui.allocate_ui_with_layout(Vec2::new(available_width*0.7, ui.available_height()), Layout::top_down(egui::Align::Center), |ui| {
+                        let rect = ui.add(&mut self.test_signal).rect;
+                        ui.painter().rect_stroke(rect, 5.0, (1.0, border), egui::StrokeKind::Middle);
+                    })
+                });
+                ui.add_space(spacing);
+                ui.allocate_ui_with_layout(Vec2::new(ui.available_width(), widget_height), Layout::left_to_right(egui::Align::Center), |ui| {
+                    let available_width = ui.available_width();
+                    ui.add_space(available_width*0.15);
+                    ui.allocate_ui_with_layout(Vec2::new(available_width*0.7, ui.available_height()), Layout::top_down(egui::Align::Center), |ui| {
+                        let rect = ui.add(&mut self.automation).rect;
+                        ui.painter().rect_stroke(rect, 5.0, (1.0, border), egui::StrokeKind::Middle);
+                    })
+                });
             }).response
         }).inner
     }
@@ -0,0 +1,88 @@
+use eframe::egui::{self, Color32, RichText, Vec2, Widget};
+use rs_pedalboard::dsp_algorithms::test_signal::TestSignalKind;
+
+use crate::{state::State, utilities::start_stop_icon};
+
+pub struct TestSignalWidget {
+    pub state: &'static State,
+    // Selected signal, kept while the signal is stopped
+    kind: TestSignalKind
+}
+
+impl TestSignalWidget {
+    pub fn new(state: &'static State) -> Self {
+        Self {
+            state,
+            kind: TestSignalKind::Sine(440.0)
+        }
+    }
+}
+
+impl Widget for &mut TestSignalWidget {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.vertical_centered(|ui| {
+            ui.add_space(10.0);
+            ui.label(RichText::from("Test Signal").size(28.0).color(Color32::from_gray(90)));
+            ui.add_space(7.0);
+
+            let active = self.state.test_signal.get().is_some();
+            let mut level = self.state.test_signal_level.get();
+            let mut changed = false;
+
+            ui.label("Replaces the input with a generated signal");
+            ui.add_space(5.0);
+
+            egui::ComboBox::from_id_salt("test_signal_kind")
+                .selected_text(self.kind.to_string())
+                .show_ui(ui, |ui| {
+                    let frequency = match self.kind {
+                        TestSignalKind::Sine(frequency) => frequency,
+                        _ => 440.0
+                    };
+                    for kind in [TestSignalKind::Sine(frequency), TestSignalKind::WhiteNoise, TestSignalKind::PinkNoise, TestSignalKind::Impulse] {
+                        changed |= ui.selectable_value(&mut self.kind, kind, kind.to_string()).changed();
+                    }
+                });
+
+            ui.style_mut().spacing.slider_width = ui.available_width()*0.5;
+
+            if let TestSignalKind::Sine(frequency) = &mut self.kind {
+                ui.add_space(10.0);
+                ui.label(format!("{:.0} Hz", frequency));
+                changed |= ui.add_sized(Vec2::new(ui.available_width()*0.5, 30.0),
+                    egui::Slider::new(frequency, 20.0..=20000.0).logarithmic(true).show_value(false)
+                ).changed();
+            }
+
+            ui.add_space(10.0);
+
+            // Level Slider
+            ui.label("Level");
+            changed |= ui.add_sized(Vec2::new(ui.available_width()*0.5, 30.0),
+                egui::Slider::new(&mut level, 0.0..=1.0).show_value(false)
+            ).changed();
+
+            if changed && active {
+                self.state.set_test_signal(Some(self.kind), level);
+            } else if changed {
+                self.state.test_signal_level.set(level);
+            }
+
+            // Play/Stop button
+            ui.add_space(5.0);
+
+            let button_response = ui.add_sized(
+                Vec2::splat(50.0),
+                egui::Button::new("")
+            );
+            if button_response.clicked() {
+                let kind = if active { None } else { Some(self.kind) };
+                self.state.set_test_signal(kind, level);
+            }
+
+            start_stop_icon(ui, !active, button_response.rect, 30.0);
+
+            ui.add_space(10.0);
+        }).response
+    }
+}
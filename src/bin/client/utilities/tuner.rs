@@ -3,7 +3,7 @@ use std::time::Instant;
 use eframe::egui::{self, Color32, RichText, Vec2, Widget};
 
 use crate::state::State;
-use rs_pedalboard::dsp_algorithms::yin::{freq_to_note, PROCESSOR_UPDATE_FREQ_MS};
+use rs_pedalboard::dsp_algorithms::yin::{freq_to_note_tuned, PROCESSOR_UPDATE_FREQ_MS};
 
 pub struct TunerWidget {
     pub state: &'static State,
@@ -57,7 +57,8 @@ impl Widget for &mut TunerWidget {
             let question = String::from("?");
             (question.clone(), question.clone(), 0.0)
         } else {
-            let recent_note = freq_to_note(self.recent_freq_smooth);
+            let client_settings = self.state.client_settings.borrow();
+            let recent_note = freq_to_note_tuned(self.recent_freq_smooth, client_settings.tuner_reference, client_settings.tuner_temperament);
             (recent_note.0.to_string(), recent_note.1.to_string(), recent_note.2)
         };
         
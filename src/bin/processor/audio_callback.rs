@@ -5,17 +5,27 @@ use cpal::{InputCallbackInfo, OutputCallbackInfo, StreamConfig, SupportedStreamC
 use cpal::{traits::DeviceTrait, Device, Stream};
 use smol::channel::{Receiver, Sender};
 use ringbuf::traits::Split;
-use ringbuf::{traits::Consumer, HeapRb};
+use ringbuf::{traits::{Consumer, Producer}, HeapRb};
 use rs_pedalboard::pedalboard_set::PedalboardSet;
-use rs_pedalboard::dsp_algorithms::resampler::Resampler;
+use rs_pedalboard::pedals::{set_fx_loop_io, FxLoopIo};
+use rs_pedalboard::dsp_algorithms::limiter::Limiter;
+use rs_pedalboard::dsp_algorithms::denormal::enable_flush_to_zero;
 
 use crate::audio_processor::AudioProcessor;
+use crate::deferred_drop::DeferredDrop;
+use crate::input_clip_detector::InputClipDetector;
 use crate::metronome_player::MetronomePlayer;
 use crate::recording::RecordingHandle;
+use crate::resampling::Resamplers;
 use crate::sample_conversion::*;
 use crate::settings::ProcessorSettings;
 use crate::stream_config::get_compatible_configs;
 use crate::volume_monitor::PeakVolumeMonitor;
+use crate::wetness_meter::WetnessMeter;
+
+const OUTPUT_LIMITER_THRESHOLD_DB: f32 = -0.5;
+const OUTPUT_LIMITER_KNEE_DB: f32 = 6.0;
+const OUTPUT_LIMITER_RELEASE_MS: f32 = 50.0;
 
 pub fn ring_buffer_size(buffer_size: usize, latency: f32, sample_rate: f32) -> usize {
     let latency_frames = (latency / 1000.0) * sample_rate;
@@ -295,6 +305,24 @@ fn build_output_stream(
     }
 }
 
+/// Load a saved pedalboard set to run before (or without) a client connecting
+fn load_startup_set(path: &std::path::Path, buffer_size: usize, sample_rate: u32) -> Result<PedalboardSet, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let mut pedalboard_set: PedalboardSet = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to deserialize {}: {e}", path.display()))?;
+
+    if pedalboard_set.pedalboards.is_empty() {
+        return Err(format!("{} contains no pedalboards", path.display()));
+    }
+    pedalboard_set.set_config(buffer_size, sample_rate);
+    pedalboard_set.activate_home_pedalboard();
+    let active_pedalboard = pedalboard_set.active_pedalboard.min(pedalboard_set.pedalboards.len() - 1);
+    pedalboard_set.set_active_pedalboard(active_pedalboard);
+
+    Ok(pedalboard_set)
+}
+
 #[tracing::instrument(level = "trace", skip_all)]
 pub fn create_linked_streams(
     in_device: Device,
@@ -318,7 +346,10 @@ pub fn create_linked_streams(
     }
 
     let used_sample_rate = in_configs[0].sample_rate().0;
-    let processing_sample_rate = used_sample_rate * (1 << settings.upsample_passes);
+    let processing_sample_rate = settings.processing_sample_rate(used_sample_rate);
+    tracing::info!("Processing at {}hz, device running at {}hz", processing_sample_rate, used_sample_rate);
+    // Largest processing buffer for one device buffer
+    let max_processing_block = (settings.frames_per_period * processing_sample_rate as usize).div_ceil(used_sample_rate as usize) + 1;
 
     let ring_buffer_size = ring_buffer_size(settings.frames_per_period, settings.buffer_latency, processing_sample_rate as f32);
     tracing::info!("Ring buffer size: {}", ring_buffer_size);
@@ -327,10 +358,56 @@ pub fn create_linked_streams(
     let (audio_buffer_writer, mut audio_buffer_reader) = ring_buffer.split();
     let mut maybe_writer = Some(audio_buffer_writer);
 
+    // Dry monitor: unprocessed input -> dry monitor output channel, only while recording
+    let (maybe_dry_monitor_writer, mut dry_monitor_reader) = match settings.dry_monitor_channel {
+        Some(channel) => {
+            tracing::info!("Dry monitor enabled on channel {}", channel);
+            let (writer, reader) = HeapRb::<f32>::new(ring_buffer_size).split();
+            (Some(writer), Some((channel, reader)))
+        },
+        None => (None, None)
+    };
+    let mut maybe_dry_monitor_writer = Some(maybe_dry_monitor_writer);
+
+    // FX loop: FX Loop pedal -> send output channel, return input channel -> FX Loop pedal.
+    // The pedal passes through if the channels aren't set up.
+    let (mut fx_send, mut fx_return) = match settings.fx_loop {
+        Some(_) if processing_sample_rate != used_sample_rate => {
+            tracing::warn!("FX loop is not available when resampling, as pedals run at a different sample rate to the device");
+            (None, None)
+        },
+        Some(channels) => {
+            tracing::info!("FX loop enabled: send channel {}, return channel {}", channels.send_channel, channels.return_channel);
+            let (send_producer, send_consumer) = HeapRb::<f32>::new(ring_buffer_size).split();
+            let (return_producer, return_consumer) = HeapRb::<f32>::new(ring_buffer_size).split();
+            set_fx_loop_io(Some(FxLoopIo { send: send_producer, return_: return_consumer }));
+            (Some((channels.send_channel, send_consumer)), Some((channels.return_channel, return_producer)))
+        },
+        None => (None, None)
+    };
+
+    // Loaded here, rather than in the audio callback, as it reads from disk
+    let mut maybe_startup_set = settings.startup_set.as_ref().and_then(|path| {
+        match load_startup_set(path, settings.frames_per_period, processing_sample_rate) {
+            Ok(pedalboard_set) => {
+                tracing::info!("Loaded startup pedalboard set from {}", path.display());
+                Some(pedalboard_set)
+            },
+            Err(e) => {
+                tracing::error!("Failed to load startup pedalboard set: {}", e);
+                None
+            }
+        }
+    });
+
+    // Started here, rather than in the audio callback, as it spawns a thread
+    let deferred_drop = DeferredDrop::start().expect("Failed to start deferred drop thread");
+
     let mut input_stream_running = false;
     let settings_clone = settings.clone();
 
     let mut mono_buffer = vec![0.0; settings.frames_per_period];
+    let requested_input_channel = settings.input_channel;
     let stream_in = build_input_stream(
         &in_device,
         &in_configs,
@@ -338,16 +415,39 @@ pub fn create_linked_streams(
         move |data: &[f32], _, channel_count| {
             let channel_count = channel_count as usize;
 
-            // Average into mono buffer if needed
+            // The FX loop return channel is excluded from the main input
+            let return_channel = fx_return.as_ref()
+                .map(|(channel, _)| *channel)
+                .filter(|channel| *channel < channel_count && channel_count > 1);
+            let input_channel = requested_input_channel.filter(|channel| *channel < channel_count && Some(*channel) != return_channel);
+
+            // Take the selected channel, or average into mono buffer if needed
             if channel_count > 1 {
                 let frame_count = data.len() / channel_count as usize;
                 mono_buffer.resize(frame_count, 0.0);
-                for i in 0..frame_count {
-                    let mut sum = 0.0;
-                    for ch in 0..channel_count {
-                        sum += data[i * channel_count as usize + ch as usize];
+                if let Some(channel) = input_channel {
+                    for (sample, frame) in mono_buffer.iter_mut().zip(data.chunks_exact(channel_count)) {
+                        *sample = frame[channel];
+                    }
+                } else {
+                    let averaged_channels = if return_channel.is_some() { channel_count - 1 } else { channel_count };
+                    for i in 0..frame_count {
+                        let mut sum = 0.0;
+                        for ch in 0..channel_count {
+                            if Some(ch) != return_channel {
+                                sum += data[i * channel_count as usize + ch as usize];
+                            }
+                        }
+                        mono_buffer[i] = sum / averaged_channels as f32;
+                    }
+                }
+
+                if let (Some(channel), Some((_, return_producer))) = (return_channel, &mut fx_return) {
+                    for i in 0..frame_count {
+                        if return_producer.try_push(data[i * channel_count + channel]).is_err() {
+                            break;
+                        }
                     }
-                    mono_buffer[i] = sum / channel_count as f32;
                 }
             } else {
                 mono_buffer.resize(data.len(), 0.0);
@@ -356,6 +456,12 @@ pub fn create_linked_streams(
 
             if !input_stream_running {
                 tracing::info!("Input stream started. Received {} samples.", data.len());
+                if let (Some((channel, _)), None) = (&fx_return, return_channel) {
+                    tracing::error!("FX loop return channel {} is not available on the input device ({} channels)", channel, channel_count);
+                }
+                if let (Some(channel), None) = (requested_input_channel, input_channel) {
+                    tracing::error!("Input channel {} is not available on the input device ({} channels), or is used by the FX loop. Mixing all channels.", channel, channel_count);
+                }
                 input_stream_running = true;
             }
 
@@ -369,39 +475,57 @@ pub fn create_linked_streams(
                 let input_processor = unsafe { &mut *ip.get() };
         
                 if input_processor.is_none() {
-                    let resamplers = if settings_clone.upsample_passes > 0 {
-                        let max_block = settings_clone.frames_per_period << settings_clone.upsample_passes;
-                        Some((
-                            Resampler::new(settings_clone.upsample_passes as usize, max_block),
-                            Resampler::new(settings_clone.upsample_passes as usize, max_block)
-                        ))
-                    } else {
-                        None
-                    };
+                    // Decaying tails (e.g. in the reverb) would otherwise become slow denormals.
+                    // The flags are per thread, and the processor is created on the audio thread.
+                    enable_flush_to_zero();
+
+                    let resamplers = Resamplers::new(&settings_clone, used_sample_rate);
 
                     *input_processor = Some(AudioProcessor {
-                        pedalboard_set: PedalboardSet::default(),
+                        pedalboard_set: maybe_startup_set.take().unwrap_or_default(),
                         command_receiver: command_receiver.clone(),
                         command_sender: in_command_sender.clone(),
                         writer: maybe_writer.take().expect("Writer moved more than once"),
+                        dry_monitor_writer: maybe_dry_monitor_writer.take().expect("Dry monitor writer moved more than once"),
                         data_buffer: Vec::with_capacity(data.len()),
-                        processing_buffer: Vec::with_capacity(data.len() << settings_clone.upsample_passes),
+                        processing_buffer: Vec::with_capacity(max_processing_block.max(data.len())),
                         master_in_volume: 1.0,
-                        master_out_volume: 1.0,
-                        pre_mute_volume: 1.0,
+                        pre_gain: AudioProcessor::pre_gain_linear(settings_clone.pre_gain_db),
+                        master_out_volume: AudioProcessor::output_ceiling_linear(settings_clone.output_ceiling_db),
+                        master_out_gain: AudioProcessor::output_volume_smoother(AudioProcessor::output_ceiling_linear(settings_clone.output_ceiling_db), processing_sample_rate),
+                        pre_mute_volume: AudioProcessor::output_ceiling_linear(settings_clone.output_ceiling_db),
+                        mute_while_tuning: (false, false),
                         tuner_handle: None,
                         pedal_command_to_client_buffer: Vec::with_capacity(12),
                         settings: settings_clone.clone(),
                         metronome: (false, MetronomePlayer::new(120, 0.5, used_sample_rate)),
                         volume_monitor: (false, Instant::now(), (0.0, 0.0), PeakVolumeMonitor::new(), PeakVolumeMonitor::new()),
                         volume_normalizer: None,
+                        output_limiter: (
+                            settings_clone.output_limiter,
+                            Limiter::new(OUTPUT_LIMITER_THRESHOLD_DB.min(settings_clone.output_ceiling_db), OUTPUT_LIMITER_KNEE_DB, OUTPUT_LIMITER_RELEASE_MS, used_sample_rate as f32)
+                        ),
+                        bypass: (false, 0.0),
+                        bypass_buffer: Vec::with_capacity(max_processing_block.max(data.len())),
+                        wetness: (None, WetnessMeter::new()),
+                        startup_fade: AudioProcessor::startup_fade_samples(used_sample_rate),
+                        test_signal: None,
+                        noise_floor_measurement: None,
+                        tempo_detector: None,
+                        input_clip_detector: InputClipDetector::new(used_sample_rate),
+                        pedal_profiler: None,
+                        buffer_fill: (Instant::now(), 0.0, 0),
+                        reported_latency: None,
+                        device_sample_rate: used_sample_rate,
                         processing_sample_rate,
                         resamplers,
                         recording: RecordingHandle::new(
                             (settings_clone.frames_per_period * 4).max(1024),
                             settings_clone.recording_dir.clone(),
                             used_sample_rate as f32
-                        )
+                        ),
+                        reloaded_pedals: crossbeam::channel::unbounded(),
+                        deferred_drop: deferred_drop.clone()
                     });
                 }
                 
@@ -412,6 +536,8 @@ pub fn create_linked_streams(
 
     let mut output_stream_running = false;
     let mut mono_buffer = vec![0.0; settings.frames_per_period];
+    let mut send_buffer = vec![0.0; settings.frames_per_period];
+    let mut dry_monitor_buffer = vec![0.0; settings.frames_per_period];
     
     let stream_out_and_channels = build_output_stream(
         &out_device,
@@ -421,8 +547,24 @@ pub fn create_linked_streams(
         move |data: &mut [f32], _, channel_count| {
             let channel_count = channel_count as usize;
 
+            // The FX loop send channel is excluded from the main output
+            let send_channel = fx_send.as_ref()
+                .map(|(channel, _)| *channel)
+                .filter(|channel| *channel < channel_count && channel_count > 1);
+
+            // The dry monitor channel is excluded from the main output
+            let dry_monitor_channel = dry_monitor_reader.as_ref()
+                .map(|(channel, _)| *channel)
+                .filter(|channel| *channel < channel_count && channel_count > 1 && Some(*channel) != send_channel);
+
             if !output_stream_running {
                 tracing::info!("Output stream started. Received {} samples.", data.len());
+                if let (Some((channel, _)), None) = (&dry_monitor_reader, dry_monitor_channel) {
+                    tracing::error!("Dry monitor channel {} is not available on the output device ({} channels), or is used by the FX loop", channel, channel_count);
+                }
+                if let (Some((channel, _)), None) = (&fx_send, send_channel) {
+                    tracing::error!("FX loop send channel {} is not available on the output device ({} channels)", channel, channel_count);
+                }
                 output_stream_running = true;
             }
 
@@ -432,15 +574,35 @@ pub fn create_linked_streams(
 
                 let read = audio_buffer_reader.pop_slice(&mut mono_buffer);
                 if read != frame_count {
-                    if let Err(e) = command_sender.try_send("xrun\n".into()) {
+                    let dropped = frame_count - read;
+                    if let Err(e) = command_sender.try_send(format!("xrun {dropped}\n").into()) {
                         tracing::error!("Failed to send xrun command: {}", e);
                     }
                     tracing::warn!("Failed to provide a full buffer to output device. Input is behind.");
                 };
 
+                // Always drained, so the send buffer doesn't fill if the channel isn't available
+                if let Some((_, send_consumer)) = &mut fx_send {
+                    send_buffer.resize(frame_count, 0.0);
+                    let send_read = send_consumer.pop_slice(&mut send_buffer);
+                    send_buffer[send_read..].fill(0.0);
+                }
+
+                if let Some((_, dry_monitor_consumer)) = &mut dry_monitor_reader {
+                    dry_monitor_buffer.resize(frame_count, 0.0);
+                    let dry_read = dry_monitor_consumer.pop_slice(&mut dry_monitor_buffer);
+                    dry_monitor_buffer[dry_read..].fill(0.0);
+                }
+
                 for (i, sample) in mono_buffer.iter().enumerate() {
                     for ch in 0..channel_count {
-                        data[i * channel_count + ch] = *sample;
+                        data[i * channel_count + ch] = if Some(ch) == send_channel {
+                            send_buffer[i]
+                        } else if Some(ch) == dry_monitor_channel {
+                            dry_monitor_buffer[i]
+                        } else {
+                            *sample
+                        };
                     }
                 }
             } else {
@@ -1,28 +1,49 @@
-use std::{sync::{atomic::AtomicBool, Arc}, time::Instant};
+use std::{sync::{atomic::AtomicBool, Arc}, time::{Duration, Instant}};
 use smol::channel::{Receiver as SmolReceiver, Sender as SmolSender};
-use crossbeam::channel::Receiver;
-use ringbuf::{traits::{Producer, Split}, HeapProd, HeapRb};
+use crossbeam::channel::{Receiver, Sender};
+use ringbuf::{traits::{Observer, Producer, Split}, HeapProd, HeapRb};
 
 use rs_pedalboard::{
-    dsp_algorithms::{resampler::Resampler, yin::Yin}, pedalboard::Pedalboard, pedalboard_set::PedalboardSet, pedals::{Pedal, PedalParameterValue, PedalTrait}, DEFAULT_VOLUME_MONITOR_UPDATE_RATE
+    dsp_algorithms::{limiter::Limiter, smoothed_param::SmoothedParam, test_signal::{TestSignalGenerator, TestSignalKind}, yin::Yin}, pedalboard::{ParallelBranch, Pedalboard}, pedalboard_set::PedalboardSet, pedals::{Pedal, PedalParameterValue, PedalTrait}, processor_settings::DisconnectAction, DEFAULT_VOLUME_MONITOR_UPDATE_RATE
 };
 use tracing::trace_span;
 
 use crate::{
-    metronome_player::MetronomePlayer, recording::RecordingHandle, settings::ProcessorSettings, volume_monitor::PeakVolumeMonitor, volume_normalization::PeakNormalizer
+    deferred_drop::{DeferredDrop, Garbage}, input_clip_detector::InputClipDetector, metronome_player::MetronomePlayer, noise_floor::NoiseFloorMeasurement, pedal_profiler::PedalProfiler, pedal_reload::ReloadedPedal, recording::RecordingHandle, resampling::Resamplers, settings::ProcessorSettings, tempo_detector::TempoDetector, volume_monitor::PeakVolumeMonitor, volume_normalization::PeakNormalizer, wetness_meter::WetnessMeter
 };
 
+/// How often the output ring buffer fill level is sent to the client
+const BUFFER_FILL_UPDATE_RATE: Duration = Duration::from_millis(500);
+/// Crossfade time when entering or leaving global bypass
+const BYPASS_FADE_DURATION: Duration = Duration::from_millis(20);
+/// Time the output is faded in over on startup and after changing sample rate, to avoid a pop
+const STARTUP_FADE_DURATION: Duration = Duration::from_millis(50);
+/// Time the output volume ramps over when muting or changing volume, to avoid a click
+const OUTPUT_VOLUME_RAMP_MS: f32 = 10.0;
+/// Largest clean boost before the pedalboard chain
+const MAX_PRE_GAIN_DB: f32 = 24.0;
+/// Most 2x upsample passes that can be switched to while running
+const MAX_UPSAMPLE_PASSES: u32 = 3;
+
 pub struct AudioProcessor {
     pub pedalboard_set: PedalboardSet,
     pub command_receiver: SmolReceiver<Box<str>>,
     pub command_sender: SmolSender<Box<str>>,
     pub writer: HeapProd<f32>,
+    // Unprocessed input sent to the dry monitor output channel, written in step with `writer`
+    pub dry_monitor_writer: Option<HeapProd<f32>>,
     pub data_buffer: Vec<f32>,
     pub processing_buffer: Vec<f32>,
     pub pedal_command_to_client_buffer: Vec<String>,
     pub master_in_volume: f32,
+    // Linear clean boost applied before the pedalboard chain
+    pub pre_gain: f32,
     pub master_out_volume: f32,
+    // master_out_volume, ramped so muting and volume changes don't click
+    pub master_out_gain: SmoothedParam,
     pub pre_mute_volume: f32,
+    // Mute while tuning enabled?, output currently muted by the tuner
+    pub mute_while_tuning: (bool, bool),
     pub settings: ProcessorSettings,
     // If tuner is enabled, this will contain the writer to the tuner buffer,
     // a receiver for frequency updates, and a kill flag
@@ -32,21 +53,58 @@ pub struct AudioProcessor {
     // Enabled?, last sent time, last sent values, input volume monitor, output volume monitor
     pub volume_monitor: (bool, Instant, (f32, f32), PeakVolumeMonitor, PeakVolumeMonitor),
     pub volume_normalizer: Option<PeakNormalizer>,
+    // Enabled?, limiter applied to the final output
+    pub output_limiter: (bool, Limiter),
+    // Enabled?, crossfade position (0 = processed, 1 = bypassed)
+    pub bypass: (bool, f32),
+    // Copy of the unprocessed input, used while fading in or out of bypass and to measure wetness
+    pub bypass_buffer: Vec<f32>,
+    // Last sent wetness percentage, measurement of how wet the pedalboard output is
+    pub wetness: (Option<u32>, WetnessMeter),
+    // Output samples remaining in the fade in from silence
+    pub startup_fade: usize,
+    // Generated signal that replaces the input, for testing without an instrument
+    pub test_signal: Option<TestSignalGenerator>,
+    // Measurement of the input noise floor, for a noise gate pedal
+    pub noise_floor_measurement: Option<NoiseFloorMeasurement>,
+    // Infers the metronome tempo from transients in the input, if enabled
+    pub tempo_detector: Option<TempoDetector>,
+    // Warns the client when the raw input is clipping, before any gain
+    pub input_clip_detector: InputClipDetector,
+    // Timing of each pedal in the active pedalboard, enabled while the client shows pedal CPU usage
+    pub pedal_profiler: Option<PedalProfiler>,
+    // Last sent time, sum of fill fractions since last sent, number of fill fractions since last sent
+    pub buffer_fill: (Instant, f32, usize),
+    // Total latency (in processing samples) of the active pedalboard last sent to the client
+    pub reported_latency: Option<usize>,
+    pub device_sample_rate: u32,
     pub processing_sample_rate: u32,
-    pub resamplers: Option<(Resampler, Resampler)>,
-    pub recording: RecordingHandle
+    pub resamplers: Option<Resamplers>,
+    pub recording: RecordingHandle,
+    // Pedals with reloaded files, loaded on another thread to replace the pedals on the audio thread
+    pub reloaded_pedals: (Sender<ReloadedPedal>, Receiver<ReloadedPedal>),
+    // Drops replaced pedals and pedalboard sets off the audio thread
+    pub deferred_drop: DeferredDrop
 }
 
 impl AudioProcessor {
     #[tracing::instrument(level = "trace", skip_all)]
     pub fn process_audio(&mut self, data: &[f32]) {
         self.recording.tick();
-        if self.recording.is_recording() {
-            if let Some(producer) = self.recording.clean_recording_producer() {
-                let written = producer.push_slice(data);
-                if written != data.len() {
-                    tracing::warn!("RecordingHandle: Recording ring buffer full, dropping samples.");
-                }
+        if self.recording.is_recording() && self.recording.is_clean() {
+            // Line up the clean recording with the processed recording, which is delayed by the pedals
+            let latency = self.pedalboard_set.pedalboards.get(self.pedalboard_set.active_pedalboard)
+                .map_or(0, |pedalboard| pedalboard.latency_samples());
+            let device_latency = (latency as u64 * self.device_sample_rate as u64 / self.processing_sample_rate as u64) as usize;
+            self.recording.set_clean_delay(device_latency);
+            self.recording.push_clean(data);
+        }
+
+        // Clipping at the interface, before any gain is applied
+        if self.input_clip_detector.add_samples(data) {
+            tracing::warn!("Input samples clipped");
+            if self.command_sender.try_send("inputclipped\n".into()).is_err() {
+                tracing::error!("Failed to send input clipped command to client");
             }
         }
 
@@ -61,19 +119,57 @@ impl AudioProcessor {
 
         self.data_buffer.iter_mut().for_each(|sample| *sample *= self.master_in_volume);
 
+        // Replace the input with the test signal
+        if let Some(generator) = &mut self.test_signal {
+            generator.fill(&mut self.data_buffer);
+        }
+
+        // Clean boost into the pedalboard chain
+        if self.pre_gain != 1.0 {
+            self.data_buffer.iter_mut().for_each(|sample| *sample *= self.pre_gain);
+        }
+
         // Update input volume monitor
         self.volume_monitor.3.add_samples(&self.data_buffer);
+
+        // Measure noise floor
+        if let Some(measurement) = &mut self.noise_floor_measurement {
+            measurement.add_samples(&self.data_buffer);
+
+            if measurement.is_finished() {
+                let command = format!("gatethreshold {} {} {:.1}\n", measurement.pedalboard_id, measurement.pedal_id, measurement.suggested_threshold_db());
+                if self.command_sender.try_send(command.into()).is_err() {
+                    tracing::error!("Failed to send gate threshold command to client");
+                }
+                self.noise_floor_measurement = None;
+            } else if let Some(progress) = measurement.take_progress() {
+                let command = format!("noisefloorprogress {} {:.2}\n", measurement.pedal_id, progress);
+                if self.command_sender.try_send(command.into()).is_err() {
+                    tracing::error!("Failed to send noise floor progress command to client");
+                }
+            }
+        }
         
-        // Upsample, if needed, into processing buffer
+        // Set the tempo from the playing
+        if let Some(detector) = &mut self.tempo_detector {
+            if let Some(bpm) = detector.add_samples(&self.data_buffer) {
+                self.metronome.1.bpm = bpm;
+                if self.command_sender.try_send(format!("detectedtempo {bpm}\n").into()).is_err() {
+                    tracing::error!("Failed to send detected tempo command to client");
+                }
+            }
+        }
+        
+        // Resample, if needed, into processing buffer
         self.processing_buffer.clear();
-        if let Some((upsampler, _)) = &mut self.resamplers {
-            self.processing_buffer.resize(upsampler.upsample_output_buffer_size(self.data_buffer.len()), 0.0);
-            upsampler.upsample(&self.data_buffer, self.processing_buffer.as_mut_slice());
+        if let Some(resamplers) = &mut self.resamplers {
+            resamplers.to_processing_rate(&self.data_buffer, &mut self.processing_buffer);
         } else {
             self.processing_buffer.extend_from_slice(&self.data_buffer);
         }
 
-        if self.data_buffer.iter().all(|&sample| sample == 0.0) {
+        // Test signals (e.g. impulses) may be silent for a whole buffer, but still need processing for the tails
+        if self.test_signal.is_none() && self.data_buffer.iter().all(|&sample| sample == 0.0) {
             tracing::debug!("Buffer is silent, skipping processing.");
         } else if let Some((tuner_writer, frequency_channel_recv, _kill)) = &mut self.tuner_handle {
             // Tuner
@@ -98,22 +194,45 @@ impl AudioProcessor {
             let span = trace_span!("process_audio", frames = self.processing_buffer.len());
             let enter = span.enter();
 
-            for i in 0..(self.processing_buffer.len() as f32 / self.settings.frames_per_period as f32).ceil() as usize {
-                let start = i * self.settings.frames_per_period;
-                let mut end = start + self.settings.frames_per_period;
-                end = end.min(self.processing_buffer.len());
-                let frame = &mut self.processing_buffer[start..end];
-                self.pedalboard_set.process_audio(frame, &mut self.pedal_command_to_client_buffer);
-            }
+            // When fully bypassed, the input is passed straight through
+            let fully_bypassed = self.bypass.0 && self.bypass.1 >= 1.0;
+            if !fully_bypassed {
+                let fading = self.bypass.0 || self.bypass.1 > 0.0;
+                self.bypass_buffer.clear();
+                self.bypass_buffer.extend_from_slice(&self.processing_buffer);
 
-            self.processing_buffer.iter_mut().for_each(|sample| *sample *= self.master_out_volume);
+                for i in 0..(self.processing_buffer.len() as f32 / self.settings.frames_per_period as f32).ceil() as usize {
+                    let start = i * self.settings.frames_per_period;
+                    let mut end = start + self.settings.frames_per_period;
+                    end = end.min(self.processing_buffer.len());
+                    let frame = &mut self.processing_buffer[start..end];
+                    self.pedalboard_set.process_audio(frame, &mut self.pedal_command_to_client_buffer);
+                }
+
+                if let Some(profiler) = &mut self.pedal_profiler {
+                    let audio_time = Duration::from_secs_f32(self.processing_buffer.len() as f32 / self.processing_sample_rate as f32);
+                    if let Some(pedalboard) = self.pedalboard_set.pedalboards.get_mut(self.pedalboard_set.active_pedalboard) {
+                        profiler.add_timings(pedalboard, audio_time);
+                    }
+                }
+
+                if fading {
+                    self.crossfade_bypass();
+                }
+
+                self.wetness.1.add_samples(&self.bypass_buffer, &self.processing_buffer);
+            }
 
             drop(enter);
         }
 
-        // Downsample, if needed, back into data buffer
-        if let Some((_, downsampler)) = &mut self.resamplers {
-            downsampler.downsample(&self.processing_buffer, self.data_buffer.as_mut_slice());
+        // Applied to the unprocessed signal passed through while tuning, so muting silences the tuner too
+        self.master_out_gain.set_target(self.master_out_volume);
+        self.master_out_gain.apply_gain(&mut self.processing_buffer);
+
+        // Resample, if needed, back into data buffer
+        if let Some(resamplers) = &mut self.resamplers {
+            resamplers.to_device_rate(&self.processing_buffer, &mut self.data_buffer);
         } else {
             self.data_buffer.clear();
             self.data_buffer.extend_from_slice(&self.processing_buffer);
@@ -133,18 +252,82 @@ impl AudioProcessor {
 
         // Add metronome click
         if self.metronome.0 {
-            self.metronome.1.add_to_buffer(&mut self.data_buffer);
+            if let Some(beat) = self.metronome.1.add_to_buffer(&mut self.data_buffer) {
+                if let Err(e) = self.command_sender.try_send(format!("beat {beat}\n").into()) {
+                    tracing::error!("Failed to send beat command: {}", e);
+                }
+            }
+        }
+
+        // Output limiter, always applied below the output ceiling so pedals can't boost past it
+        if self.output_limiter.0 || self.settings.output_ceiling_db < 0.0 {
+            self.output_limiter.1.process_buffer(&mut self.data_buffer);
+        }
+
+        if self.startup_fade > 0 {
+            self.apply_startup_fade();
+        }
+
+        // Dry monitor, silent when not recording. Always written so it stays aligned with the main output.
+        if let Some(dry_monitor_writer) = &mut self.dry_monitor_writer {
+            if self.recording.is_recording() {
+                dry_monitor_writer.push_slice(data);
+            } else {
+                for _ in 0..data.len() {
+                    if dry_monitor_writer.try_push(0.0).is_err() {
+                        break;
+                    }
+                }
+            }
         }
 
         let written = self.writer.push_slice(&self.data_buffer);
         if written != self.data_buffer.len() {
             // XRun occurred
-            if let Err(e) = self.command_sender.try_send("xrun\n".into()) {
+            let dropped = self.data_buffer.len() - written;
+            if let Err(e) = self.command_sender.try_send(format!("xrun {dropped}\n").into()) {
                 tracing::error!("Failed to send xrun command: {}", e);
             }
             tracing::warn!("Failed to write all processed data. Output is behind.")
         }
 
+        // Send average output buffer fill to client
+        self.buffer_fill.1 += self.writer.occupied_len() as f32 / self.writer.capacity().get() as f32;
+        self.buffer_fill.2 += 1;
+        if self.buffer_fill.0.elapsed() >= BUFFER_FILL_UPDATE_RATE {
+            let fill = self.buffer_fill.1 / self.buffer_fill.2 as f32;
+            let output_sample_rate = self.device_sample_rate as f32;
+            let latency_ms = (fill * self.writer.capacity().get() as f32 / output_sample_rate) * 1000.0;
+
+            let command = format!("bufferfill {:.1} {:.2}\n", fill * 100.0, latency_ms);
+            if self.command_sender.try_send(command.into()).is_err() {
+                tracing::error!("Failed to send buffer fill command to client");
+            }
+
+            self.buffer_fill = (Instant::now(), 0.0, 0);
+
+            // Send total pedal latency to client, if changed
+            let latency = self.pedalboard_set.pedalboards.get(self.pedalboard_set.active_pedalboard)
+                .map_or(0, |pedalboard| pedalboard.latency_samples());
+            if self.reported_latency != Some(latency) {
+                if self.command_sender.try_send(format!("latency {latency}\n").into()).is_err() {
+                    tracing::error!("Failed to send latency command to client");
+                } else {
+                    self.reported_latency = Some(latency);
+                }
+            }
+        }
+
+        // Send pedal CPU usage to client
+        if let Some(profiler) = &mut self.pedal_profiler {
+            let command_sender = &self.command_sender;
+            profiler.take_update(|pedal_id, percent| {
+                if command_sender.try_send(format!("pedalcpu {pedal_id} {percent:.2}\n").into()).is_err() {
+                    tracing::error!("Failed to send pedal CPU command to client");
+                }
+            });
+        }
+
         // Send volume monitor to client
         if self.volume_monitor.0 {
             if Instant::now().duration_since(self.volume_monitor.1) >= DEFAULT_VOLUME_MONITOR_UPDATE_RATE {
@@ -169,6 +352,18 @@ impl AudioProcessor {
             }
         }
 
+        // Send overall wetness to client, if changed
+        if let Some(wetness) = self.wetness.1.take_update() {
+            let percent = (wetness * 100.0).round() as u32;
+            if self.wetness.0 != Some(percent) {
+                if self.command_sender.try_send(format!("wetness {percent}\n").into()).is_err() {
+                    tracing::error!("Failed to send wetness command to client");
+                } else {
+                    self.wetness.0 = Some(percent);
+                }
+            }
+        }
+
         // Send any commands from pedals to client
         for mut command in self.pedal_command_to_client_buffer.drain(..) {
             command.push('\n');
@@ -177,14 +372,168 @@ impl AudioProcessor {
             }
         }
 
+        self.swap_reloaded_pedals();
+
         // Handle commands that have been received
         while let Ok(command) = self.command_receiver.try_recv() {
             if let Err(e) = self.handle_command(command) {
                 tracing::error!("Failed to handle command: {e}");
+                // Commands to the client are a single line
+                let command = format!("error {}\n", e.replace('\n', " "));
+                if self.command_sender.try_send(command.into()).is_err() {
+                    tracing::error!("Failed to send error command to client");
+                }
+            }
+        }
+    }
+
+    /// Replace pedals with their reloaded versions, once they have finished loading
+    fn swap_reloaded_pedals(&mut self) {
+        while let Ok((pedalboard_id, sample_rate, mut pedal)) = self.reloaded_pedals.1.try_recv() {
+            if sample_rate != self.processing_sample_rate {
+                tracing::warn!("Discarding reloaded pedal {} as the sample rate has changed", pedal.get_id());
+                continue;
+            }
+
+            let existing = self.pedalboard_set.pedalboards.iter_mut()
+                .filter(|pedalboard| pedalboard.get_id() == pedalboard_id)
+                .flat_map(|pedalboard| pedalboard.pedals.iter_mut())
+                .find(|existing| existing.get_id() == pedal.get_id());
+
+            match existing {
+                Some(existing) => {
+                    // Keep any parameter changes made while the pedal was reloading
+                    for (name, parameter) in existing.get_parameters() {
+                        if pedal.get_parameters().get(name).is_some_and(|reloaded| reloaded.value != parameter.value) {
+                            pedal.set_parameter_value(name, parameter.value.clone());
+                        }
+                    }
+
+                    let old_pedal = std::mem::replace(existing, pedal);
+                    self.deferred_drop.drop_later(Garbage::Pedal(old_pedal));
+                },
+                None => tracing::warn!("Reloaded pedal {} is no longer on the pedalboard", pedal.get_id())
+            }
+        }
+    }
+
+    /// Crossfade between the processed audio and the unprocessed copy in `bypass_buffer`,
+    /// moving towards the current bypass state.
+    fn crossfade_bypass(&mut self) {
+        let target = if self.bypass.0 { 1.0 } else { 0.0 };
+        let step = 1.0 / (BYPASS_FADE_DURATION.as_secs_f32() * self.processing_sample_rate as f32);
+
+        for (sample, dry) in self.processing_buffer.iter_mut().zip(self.bypass_buffer.iter()) {
+            if self.bypass.1 < target {
+                self.bypass.1 = (self.bypass.1 + step).min(target);
+            } else if self.bypass.1 > target {
+                self.bypass.1 = (self.bypass.1 - step).max(target);
+            }
+
+            *sample = *sample * (1.0 - self.bypass.1) + dry * self.bypass.1;
+        }
+
+        // Clear effect tails once bypassed, so they don't play when leaving bypass
+        if self.bypass.0 && self.bypass.1 >= 1.0 {
+            self.pedalboard_set.reset_active_pedalboard();
+        }
+    }
+
+    /// Linear gain of a pre gain in dB, limited to the supported range
+    pub fn pre_gain_linear(db: f32) -> f32 {
+        10f32.powf(db.clamp(0.0, MAX_PRE_GAIN_DB) / 20.0)
+    }
+
+    /// Highest linear output volume allowed by the output ceiling in dB
+    pub fn output_ceiling_linear(ceiling_db: f32) -> f32 {
+        10f32.powf(ceiling_db.min(0.0) / 20.0)
+    }
+
+    /// Mute the output when the tuner starts, if mute while tuning is enabled,
+    /// and unmute when it stops only if the tuner was what muted it.
+    fn set_tuner_mute(&mut self, tuning: bool) {
+        if tuning && self.mute_while_tuning.0 && self.master_out_volume != 0.0 {
+            self.pre_mute_volume = self.master_out_volume;
+            self.master_out_volume = 0.0;
+            self.mute_while_tuning.1 = true;
+        } else if !tuning && self.mute_while_tuning.1 {
+            self.master_out_volume = self.pre_mute_volume;
+            self.mute_while_tuning.1 = false;
+        }
+    }
+
+    /// Smoother for the output volume, starting at `volume`
+    pub fn output_volume_smoother(volume: f32, processing_sample_rate: u32) -> SmoothedParam {
+        SmoothedParam::new(volume, OUTPUT_VOLUME_RAMP_MS, processing_sample_rate as f32)
+    }
+
+    /// Number of output samples the startup fade lasts for
+    pub fn startup_fade_samples(device_sample_rate: u32) -> usize {
+        (STARTUP_FADE_DURATION.as_secs_f32() * device_sample_rate as f32) as usize
+    }
+
+    /// Ramp the output up from silence, continuing from where the previous buffer finished
+    fn apply_startup_fade(&mut self) {
+        let total = Self::startup_fade_samples(self.device_sample_rate).max(1);
+
+        for sample in self.data_buffer.iter_mut() {
+            if self.startup_fade == 0 {
+                break;
             }
+            *sample *= 1.0 - self.startup_fade as f32 / total as f32;
+            self.startup_fade -= 1;
         }
     }
 
+    /// Change the sample rate the pedals are processed at, without restarting the streams.
+    /// 
+    /// The device sample rate is fixed while the streams are running, so only the upsample passes
+    /// or internal sample rate can change. If a device sample rate is given, it must match the current device sample rate.
+    fn set_processing_sample_rate(&mut self, device_sample_rate: Option<u32>, upsample_passes: u32, internal_sample_rate: Option<u32>) -> Result<(), String> {
+        if let Some(device_sample_rate) = device_sample_rate {
+            if device_sample_rate != self.device_sample_rate {
+                return Err(format!(
+                    "The device is running at {}hz. Restart the processor to change the device sample rate to {device_sample_rate}hz.",
+                    self.device_sample_rate
+                ));
+            }
+        }
+
+        if upsample_passes > MAX_UPSAMPLE_PASSES {
+            return Err(format!("At most {MAX_UPSAMPLE_PASSES} upsample passes are supported"));
+        }
+        if internal_sample_rate == Some(0) {
+            return Err("Internal sample rate must be above 0hz".to_string());
+        }
+
+        let sample_rate = internal_sample_rate.unwrap_or(self.device_sample_rate << upsample_passes);
+        if sample_rate != self.device_sample_rate && self.settings.fx_loop.is_some() {
+            return Err("Resampling is not available while the FX loop is enabled".to_string());
+        }
+
+        if upsample_passes == self.settings.upsample_passes && internal_sample_rate == self.settings.internal_sample_rate {
+            return Ok(());
+        }
+
+        tracing::info!("Changing processing sample rate from {}hz to {}hz", self.processing_sample_rate, sample_rate);
+        self.settings.upsample_passes = upsample_passes;
+        self.settings.internal_sample_rate = internal_sample_rate;
+        self.resamplers = Resamplers::new(&self.settings, self.device_sample_rate);
+        self.processing_sample_rate = sample_rate;
+        self.master_out_gain.set_ramp_time(OUTPUT_VOLUME_RAMP_MS, sample_rate as f32);
+
+        let max_block = (self.settings.frames_per_period * sample_rate as usize).div_ceil(self.device_sample_rate as usize) + 1;
+        self.processing_buffer.reserve(max_block);
+        self.bypass_buffer.reserve(max_block);
+
+        self.pedalboard_set.set_config(self.settings.frames_per_period, self.processing_sample_rate);
+        // Latency is in samples at the processing sample rate
+        self.reported_latency = None;
+        self.startup_fade = Self::startup_fade_samples(self.device_sample_rate);
+
+        Ok(())
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     fn handle_command(&mut self, command: Box<str>) -> Result<(), String> {
         let mut arguments = command.split('|');
@@ -200,6 +549,28 @@ impl AudioProcessor {
                 if let Some((_, _, k)) = self.tuner_handle.take() {
                     k.store(true, std::sync::atomic::Ordering::Relaxed);
                 }
+                self.set_tuner_mute(false);
+                // Send the wetness to the next client, even if it hasn't changed
+                self.wetness.0 = None;
+
+                match self.settings.on_disconnect {
+                    DisconnectAction::KeepPlaying => {},
+                    DisconnectAction::Mute => {
+                        tracing::info!("Muting the output until a client connects");
+                        // The next client unmutes when it sends its mute state
+                        if self.master_out_volume != 0.0 {
+                            self.pre_mute_volume = self.master_out_volume;
+                            self.master_out_volume = 0.0;
+                        }
+                    },
+                    DisconnectAction::LoadDefault => {
+                        tracing::info!("Loading the default pedalboard set until a client connects");
+                        let mut pedalboard_set = PedalboardSet::default();
+                        pedalboard_set.set_config(self.settings.frames_per_period, self.processing_sample_rate);
+                        let old_set = std::mem::replace(&mut self.pedalboard_set, pedalboard_set);
+                        self.deferred_drop.drop_later(Garbage::PedalboardSet(old_set));
+                    }
+                }
             },
             "setparameter" => {
                 let pedalboard_id = arguments.next()
@@ -225,10 +596,28 @@ impl AudioProcessor {
                     oscillator.set_sample_rate(self.processing_sample_rate as f32);
                 }
 
+                let mut set_value = None;
                 for pedalboard in self.pedalboard_set.pedalboards.iter_mut().filter(|pedalboard| pedalboard.get_id() == pedalboard_id) {
-                    pedalboard.pedals.iter_mut().find(|pedal| pedal.get_id() == pedal_id)
-                        .ok_or_else(|| format!("setparameter: Pedal with ID {} not found in pedalboard {}", pedal_id, pedalboard_id))?
-                        .set_parameter_value(parameter_name, parameter_value.clone());
+                    let pedal = pedalboard.pedals.iter_mut().find(|pedal| pedal.get_id() == pedal_id)
+                        .ok_or_else(|| format!("setparameter: Pedal with ID {} not found in pedalboard {}", pedal_id, pedalboard_id))?;
+                    pedal.set_parameter_value(parameter_name, parameter_value.clone());
+                    set_value = pedal.get_parameters().get(parameter_name).map(|parameter| parameter.value.clone());
+                }
+
+                // Out of range values are clamped by the pedal, so tell the client the value that was actually set
+                if let Some(set_value @ (PedalParameterValue::Float(_) | PedalParameterValue::Int(_))) = set_value {
+                    if set_value != parameter_value {
+                        let command = format!(
+                            "parameterupdate {}|{}|{}|{}\n",
+                            pedalboard_id,
+                            pedal_id,
+                            parameter_name,
+                            serde_json::to_string(&set_value).expect("Failed to serialize parameter value")
+                        );
+                        if let Err(e) = self.command_sender.try_send(command.into()) {
+                            tracing::error!("Failed to send parameter update command: {}", e);
+                        }
+                    }
                 }
             },
             "movepedalboard" => {
@@ -256,12 +645,29 @@ impl AudioProcessor {
                 let mut pedalboard: Pedalboard = serde_json::from_str(&pedalboard_stringified)
                     .map_err(|e| format!("addpedalboard: Failed to deserialize pedalboard: {}", e))?;
 
-                for pedal in &mut pedalboard.pedals {
-                    pedal.set_config(self.settings.frames_per_period, self.processing_sample_rate);
-                }
+                pedalboard.set_config(self.settings.frames_per_period, self.processing_sample_rate);
 
                 self.pedalboard_set.pedalboards.push(pedalboard);
             },
+            "measurelevel" => {
+                let pedalboard_stringified = command[command_name.len() + 1..].to_string();
+                crate::level_measurement::start_level_measurement(pedalboard_stringified, self.processing_sample_rate, self.settings.frames_per_period, self.command_sender.clone())
+                    .map_err(|e| format!("measurelevel: Failed to start level measurement thread: {e}"))?;
+            },
+            "reloadpedal" => {
+                let pedalboard_id = arguments.next()
+                    .ok_or_else(|| "reloadpedal: Failed to get pedalboard ID".to_string())?
+                    .parse::<u32>()
+                    .map_err(|e| format!("reloadpedal: Failed to parse pedalboard ID: {e}"))?;
+
+                let pedal_ser_start = arguments.next()
+                    .ok_or_else(|| "reloadpedal: Failed to get pedal".to_string())?;
+                let pedal_ser_start_index = pedal_ser_start.as_ptr() as usize - command.as_ptr() as usize;
+                let pedal_stringified = command[pedal_ser_start_index..].to_string();
+
+                crate::pedal_reload::start_pedal_reload(pedalboard_id, pedal_stringified, self.processing_sample_rate, self.settings.frames_per_period, self.reloaded_pedals.0.clone())
+                    .map_err(|e| format!("reloadpedal: Failed to start pedal reload thread: {e}"))?;
+            },
             "deletepedalboard" => {
                 let pedalboard_index_str = arguments.next()
                     .ok_or_else(|| "deletepedalboard: Failed to get pedalboard index".to_string())?;
@@ -287,14 +693,28 @@ impl AudioProcessor {
                 
                 let mut pedal: Pedal = serde_json::from_str(&pedal_stringified)
                     .map_err(|e| format!("addpedal: Failed to deserialize pedal: {}", e))?;
-                pedal.set_config(self.settings.frames_per_period, self.processing_sample_rate);
+                pedal.configure(self.settings.frames_per_period, self.processing_sample_rate);
 
                 for pedalboard in self.pedalboard_set.pedalboards.iter_mut() {
                     if pedalboard.get_id() == pedalboard_id {
-                        pedalboard.pedals.push(pedal.clone());
+                        pedalboard.add_pedal(pedal.clone());
                     }
                 }
             },
+            "setparallelbranch" => {
+                let pedalboard_id = arguments.next()
+                    .ok_or_else(|| "setparallelbranch: Failed to get pedalboard ID".to_string())?
+                    .parse::<u32>()
+                    .map_err(|e| format!("setparallelbranch: Failed to parse pedalboard ID: {e}"))?;
+                let branch_stringified = arguments.next()
+                    .ok_or_else(|| "setparallelbranch: Failed to get branch".to_string())?;
+                let branch: Option<ParallelBranch> = serde_json::from_str(branch_stringified)
+                    .map_err(|e| format!("setparallelbranch: Failed to deserialize branch: {}", e))?;
+
+                for pedalboard in self.pedalboard_set.pedalboards.iter_mut().filter(|pedalboard| pedalboard.get_id() == pedalboard_id) {
+                    pedalboard.parallel_branch = branch;
+                }
+            },
             "deletepedal" => {
                 let pedalboard_id = arguments.next()
                     .ok_or_else(|| "deletepedal: Failed to get pedalboard ID".to_string())?
@@ -344,12 +764,8 @@ impl AudioProcessor {
                 let mut pedalboardset: PedalboardSet = serde_json::from_str(&pedalboardset_stringified).ok()
                     .ok_or_else(|| "loadset: Failed to deserialize pedalboard set".to_string())?;
 
-                // Call set_config on every pedal
-                for pedalboard in &mut pedalboardset.pedalboards {
-                    for pedal in &mut pedalboard.pedals {
-                        pedal.set_config(self.settings.frames_per_period, self.processing_sample_rate);
-                    }
-                }
+                pedalboardset.set_config(self.settings.frames_per_period, self.processing_sample_rate);
+                pedalboardset.activate_home_pedalboard();
 
                 self.pedalboard_set = pedalboardset;
             },
@@ -377,16 +793,29 @@ impl AudioProcessor {
                     .map_err(|e| format!("masterin: Failed to parse volume: {e}"))?;
                 self.master_in_volume = volume;
             },
+            "pregain" => {
+                let db = arguments.next()
+                    .ok_or_else(|| "pregain: Failed to get gain".to_string())?
+                    .parse::<f32>()
+                    .map_err(|e| format!("pregain: Failed to parse gain: {e}"))?
+                    .clamp(0.0, MAX_PRE_GAIN_DB);
+                self.pre_gain = Self::pre_gain_linear(db);
+                self.settings.pre_gain_db = db;
+            },
             "masterout" => {
                 let volume = arguments.next()
                     .ok_or_else(|| "masterout: Failed to get volume".to_string())?
                     .parse::<f32>()
                     .map_err(|e| format!("masterout: Failed to parse volume: {e}"))?;
-                self.master_out_volume = volume.clamp(0.0, 1.0);
+                self.master_out_volume = volume.clamp(0.0, Self::output_ceiling_linear(self.settings.output_ceiling_db));
+                // The new volume is kept when the tuner stops
+                self.mute_while_tuning.1 = false;
             },
             "mute" => {
                 let enable_str = arguments.next()
                     .ok_or_else(|| "mute: Failed to get on/off/toggle".to_string())?;
+                // Muting or unmuting while tuning is kept when the tuner stops
+                self.mute_while_tuning.1 = false;
                 match enable_str {
                     "on" => {
                         if self.master_out_volume != 0.0 {
@@ -412,6 +841,93 @@ impl AudioProcessor {
                     }
                 }
             },
+            "bypass" => {
+                let enable_str = arguments.next()
+                    .ok_or_else(|| "bypass: Failed to get on/off/toggle".to_string())?;
+                match enable_str {
+                    "on" => {
+                        self.bypass.0 = true;
+                    },
+                    "off" => {
+                        self.bypass.0 = false;
+                    },
+                    "toggle" => {
+                        self.bypass.0 = !self.bypass.0;
+                    },
+                    _ => {
+                        return Err("Invalid value for bypass command, expected on/off/toggle".to_string());
+                    }
+                }
+            },
+            "pedalprofile" => {
+                let enable_str = arguments.next()
+                    .ok_or_else(|| "pedalprofile: Failed to get on/off".to_string())?;
+                match enable_str {
+                    "on" => {
+                        if self.pedal_profiler.is_none() {
+                            self.pedal_profiler = Some(PedalProfiler::new());
+                        }
+                    },
+                    "off" => {
+                        self.pedal_profiler = None;
+                        for pedalboard in self.pedalboard_set.pedalboards.iter_mut() {
+                            pedalboard.set_profiling(false);
+                        }
+                    },
+                    _ => {
+                        return Err("Invalid value for pedalprofile command, expected on/off".to_string());
+                    }
+                }
+            },
+            "transienttempo" => {
+                match arguments.next() {
+                    Some("on") => {
+                        if self.tempo_detector.is_none() {
+                            self.tempo_detector = Some(TempoDetector::new(self.device_sample_rate));
+                        }
+                    },
+                    Some("off") => self.tempo_detector = None,
+                    _ => return Err("Invalid value for transienttempo command, expected on/off".to_string())
+                }
+            },
+            "logfullcommands" => {
+                match arguments.next() {
+                    Some("on") => rs_pedalboard::socket_helper::set_log_full_commands(true),
+                    Some("off") => rs_pedalboard::socket_helper::set_log_full_commands(false),
+                    _ => return Err("Invalid value for logfullcommands command, expected on/off".to_string())
+                }
+            },
+            "testsignal" => {
+                let kind_str = arguments.next()
+                    .ok_or_else(|| "testsignal: Failed to get signal type".to_string())?;
+                if kind_str == "off" {
+                    self.test_signal = None;
+                    return Ok(());
+                }
+
+                let level = arguments.next()
+                    .ok_or_else(|| "testsignal: Failed to get level".to_string())?
+                    .parse::<f32>()
+                    .map_err(|e| format!("testsignal: Failed to parse level: {e}"))?;
+                let kind = match kind_str {
+                    "sine" => {
+                        let frequency = arguments.next()
+                            .ok_or_else(|| "testsignal: Failed to get sine frequency".to_string())?
+                            .parse::<f32>()
+                            .map_err(|e| format!("testsignal: Failed to parse sine frequency: {e}"))?;
+                        TestSignalKind::Sine(frequency)
+                    },
+                    "white" => TestSignalKind::WhiteNoise,
+                    "pink" => TestSignalKind::PinkNoise,
+                    "impulse" => TestSignalKind::Impulse,
+                    _ => {
+                        return Err("Invalid value for testsignal command, expected off/sine/white/pink/impulse".to_string());
+                    }
+                };
+
+                // Generated before resampling
+                self.test_signal = Some(TestSignalGenerator::new(kind, level, self.device_sample_rate as f32));
+            },
             "tuner" => {
                 let enable_str = arguments.next()
                     .ok_or_else(|| "tuner: Failed to get on/off/toggle".to_string())?;
@@ -448,17 +964,36 @@ impl AudioProcessor {
                         tracing::error!("Failed to start tuner thread: {}", e);
                     } else {
                         self.tuner_handle = Some((tuner_writer, frequency_channel_recv, kill));
+                        self.set_tuner_mute(true);
                     }
                     
                 } else {
                     if let Some((_, _, kill)) = self.tuner_handle.take() {
                         kill.store(true, std::sync::atomic::Ordering::Relaxed);
                     }
+                    self.set_tuner_mute(false);
+                }
+            },
+            "mutewhiletuning" => {
+                let enable_str = arguments.next()
+                    .ok_or_else(|| "mutewhiletuning: Failed to get on/off".to_string())?;
+                match enable_str {
+                    "on" => {
+                        self.mute_while_tuning.0 = true;
+                    },
+                    "off" => {
+                        self.mute_while_tuning.0 = false;
+                        self.set_tuner_mute(false);
+                    },
+                    _ => {
+                        return Err("Invalid value for mutewhiletuning command, expected on/off".to_string());
+                    }
                 }
             },
             "metronome" => {
                 let enable_str = arguments.next()
                     .ok_or_else(|| "metronome: Failed to get on/off/toggle".to_string())?;
+                let was_enabled = self.metronome.0;
                 match enable_str {
                     "on" => {
                         self.metronome.0 = true;
@@ -474,6 +1009,11 @@ impl AudioProcessor {
                     }
                 }
 
+                // Start on the downbeat when turned on
+                if !was_enabled && self.metronome.0 {
+                    self.metronome.1.reset();
+                }
+
                 if let Some(bpm) = arguments.next().and_then(|s| s.parse::<u32>().ok()) {
                     self.metronome.1.bpm = bpm;
                 }
@@ -531,9 +1071,74 @@ impl AudioProcessor {
                     }
                 }
             },
+            "outputlimiter" => {
+                let enable_str = arguments.next()
+                    .ok_or_else(|| "outputlimiter: Failed to get on/off/toggle".to_string())?;
+                match enable_str {
+                    "on" => {
+                        self.output_limiter.0 = true;
+                    },
+                    "off" => {
+                        self.output_limiter.0 = false;
+                    },
+                    "toggle" => {
+                        self.output_limiter.0 = !self.output_limiter.0;
+                    },
+                    _ => {
+                        return Err("Invalid value for outputlimiter command, expected on/off/toggle".to_string());
+                    }
+                }
+                self.output_limiter.1.reset();
+                self.settings.output_limiter = self.output_limiter.0;
+            },
+            "learnnoisefloor" => {
+                let pedalboard_id = arguments.next()
+                    .ok_or_else(|| "learnnoisefloor: Failed to get pedalboard ID".to_string())?
+                    .parse::<u32>()
+                    .map_err(|e| format!("learnnoisefloor: Failed to parse pedalboard ID: {e}"))?;
+                let pedal_id = arguments.next()
+                    .ok_or_else(|| "learnnoisefloor: Failed to get pedal ID".to_string())?
+                    .parse::<u32>()
+                    .map_err(|e| format!("learnnoisefloor: Failed to parse pedal ID: {e}"))?;
+
+                self.noise_floor_measurement = Some(NoiseFloorMeasurement::new(pedalboard_id, pedal_id, self.device_sample_rate));
+            },
             "requestsr" => {
                 self.command_sender.try_send(format!("sr {}\n", self.processing_sample_rate).into())
                     .map_err(|e| format!("Failed to send sample rate response: {}", e))?;
+                // A new client has connected, so resend the latency
+                self.reported_latency = None;
+            },
+            "requestset" => {
+                // A client has joined while other clients are connected, so it uses the current set
+                let pedalboard_set = serde_json::to_string(&self.pedalboard_set)
+                    .map_err(|e| format!("requestset: Failed to serialize pedalboard set: {e}"))?;
+                self.command_sender.try_send(format!("pedalboardset {pedalboard_set}\n").into())
+                    .map_err(|e| format!("Failed to send pedalboard set response: {}", e))?;
+            },
+            "setsamplerate" => {
+                let device_sample_rate = match arguments.next().ok_or_else(|| "setsamplerate: Failed to get sample rate".to_string())? {
+                    "default" => None,
+                    sample_rate => Some(sample_rate.parse::<u32>().map_err(|e| format!("setsamplerate: Failed to parse sample rate: {e}"))?)
+                };
+                let upsample_passes = arguments.next()
+                    .ok_or_else(|| "setsamplerate: Failed to get upsample passes".to_string())?
+                    .parse::<u32>()
+                    .map_err(|e| format!("setsamplerate: Failed to parse upsample passes: {e}"))?;
+
+                let internal_sample_rate = match arguments.next().ok_or_else(|| "setsamplerate: Failed to get internal sample rate".to_string())? {
+                    "device" => None,
+                    sample_rate => Some(sample_rate.parse::<u32>().map_err(|e| format!("setsamplerate: Failed to parse internal sample rate: {e}"))?)
+                };
+
+                let result = self.set_processing_sample_rate(device_sample_rate, upsample_passes, internal_sample_rate);
+                let response = match &result {
+                    Ok(()) => format!("setsamplerate ok {}\n", self.processing_sample_rate),
+                    Err(e) => format!("setsamplerate failed {e}\n")
+                };
+                self.command_sender.try_send(response.into())
+                    .map_err(|e| format!("Failed to send sample rate change response: {}", e))?;
+                result.map_err(|e| format!("setsamplerate: {e}"))?;
             },
             "recording" => {
                 let enable_str = arguments.next()
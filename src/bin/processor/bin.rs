@@ -21,6 +21,15 @@ mod volume_monitor;
 mod volume_normalization;
 mod settings;
 mod recording;
+mod noise_floor;
+mod pedal_profiler;
+mod resampling;
+mod level_measurement;
+mod pedal_reload;
+mod wetness_meter;
+mod tempo_detector;
+mod input_clip_detector;
+mod deferred_drop;
 use settings::{ProcessorSettings, ProcessorArguments};
 
 use cpal::traits::StreamTrait;
@@ -45,6 +54,7 @@ fn main() {
 
     let settings = ProcessorSettings::new(ProcessorArguments::parse(), Some(ProcessorSettingsSave::load_or_default()));
     tracing::info!("Processor settings: {:?}", settings);
+    let port = settings.port;
 
     let (_host, input, output) = setup(
         settings.input_device.as_ref().map(|s| s.as_str()),
@@ -69,5 +79,5 @@ fn main() {
     after_setup(out_channels);
 
     // Will loop infinitely (unless panic)
-    socket::ProcessorSocket::new(29475, socket_command_sender, socket_command_receiver).start().expect("Failed to start processor");
+    socket::ProcessorSocket::new(port, socket_command_sender, socket_command_receiver).start().expect("Failed to start processor");
 }
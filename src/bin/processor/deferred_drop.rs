@@ -0,0 +1,36 @@
+use std::io;
+use crossbeam::channel::Sender;
+use rs_pedalboard::{pedalboard_set::PedalboardSet, pedals::Pedal};
+
+/// Values that can wait to be dropped. If the drop thread falls this far behind, values are dropped on the audio thread.
+const DEFERRED_DROP_CAPACITY: usize = 32;
+
+/// Values replaced on the audio thread, which may own large buffers (e.g. IR convolvers, NAM models and stretch buffers)
+pub enum Garbage {
+    PedalboardSet(PedalboardSet),
+    Pedal(Pedal),
+}
+
+/// Drops values on a separate thread, so freeing them doesn't block the audio thread
+#[derive(Clone)]
+pub struct DeferredDrop {
+    sender: Sender<Garbage>,
+}
+
+impl DeferredDrop {
+    pub fn start() -> io::Result<Self> {
+        let (sender, receiver) = crossbeam::channel::bounded::<Garbage>(DEFERRED_DROP_CAPACITY);
+        std::thread::Builder::new().name("DeferredDropThread".to_string()).spawn(move || {
+            // Each value is dropped at the end of an iteration, and the thread ends when the sender is dropped
+            for _garbage in receiver {}
+        })?;
+
+        Ok(Self { sender })
+    }
+
+    pub fn drop_later(&self, garbage: Garbage) {
+        if let Err(e) = self.sender.try_send(garbage) {
+            tracing::warn!("Failed to defer drop, dropping on the audio thread: {}", e);
+        }
+    }
+}
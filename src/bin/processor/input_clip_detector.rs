@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+/// Level of a raw input sample that is treated as full scale
+const FULL_SCALE_LEVEL: f32 = 0.99;
+/// Consecutive full scale samples that count as a clip.
+/// A single full scale peak may be a loud but clean note, while a flat top means the interface's converter clipped.
+const CLIP_RUN_SAMPLES: usize = 3;
+/// Clips within the window needed for a warning, so a single hard hit doesn't warn
+const CLIPS_FOR_WARNING: usize = 3;
+const CLIP_WINDOW_SECS: f32 = 2.0;
+/// While the input keeps clipping, the warning is repeated at most this often
+const WARNING_INTERVAL_SECS: f32 = 1.0;
+
+/// Detects sustained clipping of the raw input, before any gain is applied,
+/// which means the gain on the audio interface is too high.
+/// 
+/// This is separate from the output clipping detection, which is caused by the pedals or output volume.
+pub struct InputClipDetector {
+    sample_rate: f32,
+    // Samples seen, used as the clock so the detection doesn't depend on the buffer timing
+    samples: u64,
+    // Consecutive full scale samples up to the last sample
+    run: usize,
+    // Times (in samples) of the latest clips within the window, at most CLIPS_FOR_WARNING so it never reallocates
+    clips: VecDeque<u64>,
+    last_warning: Option<u64>,
+}
+
+impl InputClipDetector {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate as f32,
+            samples: 0,
+            run: 0,
+            clips: VecDeque::with_capacity(CLIPS_FOR_WARNING),
+            last_warning: None,
+        }
+    }
+
+    /// Returns true if a warning should be sent to the client
+    pub fn add_samples(&mut self, samples: &[f32]) -> bool {
+        for &sample in samples {
+            self.samples += 1;
+            if sample.abs() >= FULL_SCALE_LEVEL {
+                self.run += 1;
+                if self.run == CLIP_RUN_SAMPLES {
+                    // Only the latest clips decide whether to warn
+                    if self.clips.len() == CLIPS_FOR_WARNING {
+                        self.clips.pop_front();
+                    }
+                    self.clips.push_back(self.samples);
+                }
+            } else {
+                self.run = 0;
+            }
+        }
+
+        let window = (CLIP_WINDOW_SECS * self.sample_rate) as u64;
+        while self.clips.front().is_some_and(|&clip| self.samples - clip > window) {
+            self.clips.pop_front();
+        }
+
+        if self.clips.len() < CLIPS_FOR_WARNING {
+            return false;
+        }
+
+        let interval = (WARNING_INTERVAL_SECS * self.sample_rate) as u64;
+        if self.last_warning.is_some_and(|last| self.samples - last < interval) {
+            return false;
+        }
+        self.last_warning = Some(self.samples);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    fn sine(amplitude: f32, length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|i| (amplitude * (i as f32 * 220.0 * std::f32::consts::TAU / SAMPLE_RATE as f32).sin()).clamp(-1.0, 1.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_input_and_single_peaks_do_not_warn() {
+        let mut detector = InputClipDetector::new(SAMPLE_RATE);
+        assert!(!detector.add_samples(&sine(0.95, SAMPLE_RATE as usize)));
+
+        // Full scale peaks that aren't flat topped
+        let mut peaks = vec![0.0; SAMPLE_RATE as usize];
+        for i in (0..peaks.len()).step_by(1000) {
+            peaks[i] = 1.0;
+        }
+        assert!(!detector.add_samples(&peaks));
+    }
+
+    #[test]
+    fn test_sustained_clipping_warns() {
+        let mut detector = InputClipDetector::new(SAMPLE_RATE);
+        let clipped = sine(2.0, SAMPLE_RATE as usize / 10);
+        assert!(detector.add_samples(&clipped));
+
+        // Not repeated until the warning interval has passed
+        assert!(!detector.add_samples(&clipped));
+        for _ in 0..8 {
+            detector.add_samples(&clipped);
+        }
+        assert!(detector.add_samples(&clipped));
+
+        // Stops once the clipping is outside the window
+        assert!(!detector.add_samples(&vec![0.0; 3 * SAMPLE_RATE as usize]));
+    }
+}
@@ -0,0 +1,37 @@
+use std::io;
+use smol::channel::Sender;
+use rs_pedalboard::{pedalboard::Pedalboard, pedals::PedalTrait};
+
+/// Measure the level change of a serialized pedalboard on a separate thread, so the audio thread isn't blocked.
+///
+/// Sends `pedalboardlevel <pedalboard id> <gain db>` to the client, or `pedalboardlevel <pedalboard id> failed` if
+/// the pedalboard outputs silence.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn start_level_measurement(pedalboard_stringified: String, sample_rate: u32, buffer_size: usize, send_to: Sender<Box<str>>) -> io::Result<std::thread::JoinHandle<()>> {
+    std::thread::Builder::new().name("LevelMeasurementThread".to_string()).spawn(move || {
+        let mut pedalboard: Pedalboard = match serde_json::from_str(&pedalboard_stringified) {
+            Ok(pedalboard) => pedalboard,
+            Err(e) => {
+                tracing::error!("Failed to deserialize pedalboard to measure: {}", e);
+                return;
+            }
+        };
+
+        pedalboard.set_config(buffer_size, sample_rate);
+
+        let command = match pedalboard.measure_gain_db(sample_rate, buffer_size) {
+            Some(gain_db) => {
+                tracing::info!("Pedalboard {} changes the level by {:.1}dB", pedalboard.name, gain_db);
+                format!("pedalboardlevel {} {:.2}\n", pedalboard.get_id(), gain_db)
+            },
+            None => {
+                tracing::warn!("Pedalboard {} outputs silence, so its level can't be measured", pedalboard.name);
+                format!("pedalboardlevel {} failed\n", pedalboard.get_id())
+            }
+        };
+
+        if send_to.send_blocking(command.into()).is_err() {
+            tracing::error!("Failed to send pedalboard level to client");
+        }
+    })
+}
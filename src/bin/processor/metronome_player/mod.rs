@@ -1,14 +1,23 @@
 use std::io::Cursor;
 use hound::{WavReader, SampleFormat};
 
+/// Beat 0 of each bar is the accented downbeat
+pub const BEATS_PER_BAR: usize = 4;
+
 pub struct MetronomePlayer {
     pub bpm: u32,
     pub volume: f32,
     pub sample_rate: u32,
     click_audio: Vec<f32>,
-    // Current position, where 0 is the start of the click sound
-    // and the maximum value is the 'samples per beat'-1
-    current_position: usize,
+    // Fraction of the current beat that has elapsed, from 0 to 1.
+    // Stored as a fraction rather than a sample count so the phase is kept when the BPM changes.
+    beat_phase: f64,
+    // Whether the next sample is the start of a beat
+    beat_pending: bool,
+    // Samples since the start of the last click sound
+    click_position: usize,
+    // Beat in the current bar, from 0 to BEATS_PER_BAR-1
+    current_beat: usize,
 }
 
 impl MetronomePlayer {
@@ -61,32 +70,117 @@ impl MetronomePlayer {
             volume,
             sample_rate,
             click_audio,
-            current_position: 0,
+            beat_phase: 0.0,
+            beat_pending: true,
+            click_position: usize::MAX,
+            current_beat: 0,
         }
     }
 
-    fn samples_per_beat(&self) -> usize {
-        let seconds_per_beat = 60.0 / self.bpm as f32;
-        (seconds_per_beat * self.sample_rate as f32) as usize
+    /// Fraction of a beat that each sample advances the phase by
+    fn beats_per_sample(&self) -> f64 {
+        self.bpm as f64 / (60.0 * self.sample_rate as f64)
     }
 
-    pub fn add_to_buffer(&mut self, buffer: &mut [f32]) {
-        // Clamp current_position in case parameters have changed
-        self.current_position = self.current_position.min(self.samples_per_beat()-1);
+    /// Add the click to the buffer.
+    /// 
+    /// Returns the beat in the bar if a beat started in this buffer.
+    pub fn add_to_buffer(&mut self, buffer: &mut [f32]) -> Option<usize> {
+        let beats_per_sample = self.beats_per_sample();
 
+        let mut started_beat = None;
         for sample in buffer.iter_mut() {
-            if self.current_position < self.click_audio.len() {
+            if self.beat_pending {
+                self.beat_pending = false;
+                self.click_position = 0;
+                started_beat = Some(self.current_beat);
+            }
+
+            if self.click_position < self.click_audio.len() {
                 // Write the click sound
-                *sample += self.click_audio[self.current_position] * self.volume;
+                *sample += self.click_audio[self.click_position] * self.volume;
             }
+            self.click_position = self.click_position.saturating_add(1);
+
+            // Move to the next position, keeping the remainder so beats don't drift
+            self.beat_phase += beats_per_sample;
+            if self.beat_phase >= 1.0 {
+                self.beat_phase = (self.beat_phase - 1.0).min(1.0 - f64::EPSILON);
+                self.beat_pending = true;
+                self.current_beat = (self.current_beat + 1) % BEATS_PER_BAR;
+            }
+        }
+
+        started_beat
+    }
+
+    /// Start again from the downbeat
+    pub fn reset(&mut self) {
+        self.beat_phase = 0.0;
+        self.beat_pending = true;
+        self.current_beat = 0;
+    }
+}
 
-            // Move to the next position
-            self.current_position += 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // If we reached the end of the beat, reset to the start
-            if self.current_position >= self.samples_per_beat() {
-                self.current_position = 0;
+    /// Sample indices that a beat started on, processing one sample at a time.
+    /// The BPM is changed every `samples_per_bpm_step` samples, to the next value from `bpms`.
+    fn click_starts(player: &mut MetronomePlayer, bpms: impl Iterator<Item = u32>, samples_per_bpm_step: usize) -> Vec<usize> {
+        let mut starts = Vec::new();
+        let mut index = 0;
+        for bpm in bpms {
+            player.bpm = bpm;
+            for _ in 0..samples_per_bpm_step {
+                if player.add_to_buffer(&mut [0.0]).is_some() {
+                    starts.push(index);
+                }
+                index += 1;
             }
         }
+        starts
+    }
+
+    #[test]
+    fn test_steady_bpm_click_spacing() {
+        let mut player = MetronomePlayer::new(120, 1.0, 48000);
+        let starts = click_starts(&mut player, std::iter::repeat_n(120, 100), 4800);
+
+        assert_eq!(starts[0], 0);
+        assert_eq!(starts.len(), 20);
+        assert!(starts.windows(2).all(|w| w[1] - w[0] == 24000));
+    }
+
+    #[test]
+    fn test_dragging_bpm_keeps_click_spacing_monotonic() {
+        let sample_rate = 48000;
+
+        // Dragging the slider up changes the BPM every few buffers, so each beat should be no longer than the last
+        let mut player = MetronomePlayer::new(60, 1.0, sample_rate);
+        let starts = click_starts(&mut player, 60..=300, 2400);
+        let spacings: Vec<usize> = starts.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(spacings.len() > 10);
+        assert!(spacings.windows(2).all(|w| w[1] <= w[0]), "Click spacing increased while BPM increased: {spacings:?}");
+        assert!(spacings.iter().all(|&spacing| spacing >= (60 * sample_rate / 300) as usize), "Click double triggered: {spacings:?}");
+
+        // And no shorter than the last when dragging down
+        let mut player = MetronomePlayer::new(300, 1.0, sample_rate);
+        let starts = click_starts(&mut player, (60..=300).rev(), 2400);
+        let spacings: Vec<usize> = starts.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(spacings.len() > 10);
+        assert!(spacings.windows(2).all(|w| w[1] >= w[0]), "Click spacing decreased while BPM decreased: {spacings:?}");
+    }
+
+    #[test]
+    fn test_phase_kept_through_bpm_change() {
+        // Halfway through a beat at 120 BPM, doubling the BPM should leave a quarter of the original beat length
+        let mut player = MetronomePlayer::new(120, 1.0, 48000);
+        player.add_to_buffer(&mut vec![0.0; 12000]);
+        player.bpm = 240;
+
+        assert_eq!(player.add_to_buffer(&mut vec![0.0; 5990]), None);
+        assert_eq!(player.add_to_buffer(&mut [0.0; 20]), Some(1));
     }
 }
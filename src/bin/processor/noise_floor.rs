@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+/// How long the noise floor is measured for
+pub const NOISE_FLOOR_MEASUREMENT_DURATION: Duration = Duration::from_secs(2);
+/// How far above the measured noise floor the suggested gate threshold is
+const NOISE_FLOOR_MARGIN_DB: f32 = 6.0;
+/// How often measurement progress is sent to the client
+const PROGRESS_UPDATE_RATE: Duration = Duration::from_millis(100);
+
+/// Measures the loudest short-term power of the input over a window,
+/// to suggest a threshold for a noise gate pedal.
+pub struct NoiseFloorMeasurement {
+    pub pedalboard_id: u32,
+    pub pedal_id: u32,
+    total_samples: usize,
+    measured_samples: usize,
+    peak_power: f32,
+    last_progress_sent: Instant,
+}
+
+impl NoiseFloorMeasurement {
+    pub fn new(pedalboard_id: u32, pedal_id: u32, sample_rate: u32) -> Self {
+        Self {
+            pedalboard_id,
+            pedal_id,
+            total_samples: (NOISE_FLOOR_MEASUREMENT_DURATION.as_secs_f32() * sample_rate as f32) as usize,
+            measured_samples: 0,
+            peak_power: 0.0,
+            last_progress_sent: Instant::now(),
+        }
+    }
+
+    pub fn add_samples(&mut self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+        self.peak_power = self.peak_power.max(power);
+        self.measured_samples += samples.len();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.measured_samples >= self.total_samples
+    }
+
+    /// Returns the progress (0-1) if it is due to be sent to the client
+    pub fn take_progress(&mut self) -> Option<f32> {
+        if self.last_progress_sent.elapsed() >= PROGRESS_UPDATE_RATE {
+            self.last_progress_sent = Instant::now();
+            Some((self.measured_samples as f32 / self.total_samples as f32).min(1.0))
+        } else {
+            None
+        }
+    }
+
+    /// Suggested gate threshold in dB
+    pub fn suggested_threshold_db(&self) -> f32 {
+        10.0 * self.peak_power.max(1e-12).log10() + NOISE_FLOOR_MARGIN_DB
+    }
+}
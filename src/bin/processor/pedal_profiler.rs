@@ -0,0 +1,61 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use rs_pedalboard::pedalboard::Pedalboard;
+
+/// How often pedal CPU usage is sent to the client
+pub const PEDAL_PROFILE_UPDATE_RATE: Duration = Duration::from_millis(500);
+/// Weight of the newest measurement in the moving average
+const SMOOTHING: f32 = 0.3;
+
+/// Measures the share of real time spent processing each pedal in the active pedalboard.
+pub struct PedalProfiler {
+    last_sent: Instant,
+    // Duration of audio processed since last sent
+    audio_time: Duration,
+    // Time spent in each pedal since last sent
+    pedal_time: HashMap<u32, Duration>,
+    // Smoothed percentage of real time used by each pedal
+    averages: HashMap<u32, f32>,
+}
+
+impl PedalProfiler {
+    pub fn new() -> Self {
+        Self {
+            last_sent: Instant::now(),
+            audio_time: Duration::ZERO,
+            pedal_time: HashMap::new(),
+            averages: HashMap::new(),
+        }
+    }
+
+    /// Collect the pedal timings of the pedalboard that processed `audio_time` of audio.
+    pub fn add_timings(&mut self, pedalboard: &mut Pedalboard, audio_time: Duration) {
+        // The active pedalboard may have changed since profiling was enabled
+        pedalboard.set_profiling(true);
+
+        self.audio_time += audio_time;
+        pedalboard.take_pedal_timings(|id, duration| {
+            *self.pedal_time.entry(id).or_default() += duration;
+        });
+    }
+
+    /// If it is time for an update, calls `f` with each pedal ID and its smoothed CPU usage percentage.
+    pub fn take_update(&mut self, mut f: impl FnMut(u32, f32)) {
+        if self.last_sent.elapsed() < PEDAL_PROFILE_UPDATE_RATE || self.audio_time.is_zero() {
+            return;
+        }
+        self.last_sent = Instant::now();
+
+        // Pedals that are no longer timed (removed or bypassed) are forgotten
+        self.averages.retain(|id, _| self.pedal_time.contains_key(id));
+
+        for (id, duration) in self.pedal_time.drain() {
+            let percent = duration.as_secs_f32() / self.audio_time.as_secs_f32() * 100.0;
+            let average = self.averages.entry(id).or_insert(percent);
+            *average += SMOOTHING * (percent - *average);
+            f(id, *average);
+        }
+
+        self.audio_time = Duration::ZERO;
+    }
+}
@@ -0,0 +1,30 @@
+use std::io;
+use crossbeam::channel::Sender;
+use rs_pedalboard::pedals::{Pedal, PedalTrait};
+
+/// Pedalboard ID, sample rate the pedal was configured for, and the pedal
+pub type ReloadedPedal = (u32, u32, Pedal);
+
+/// Load a serialized pedal and its files (e.g. an IR or NAM model) on a separate thread, so the audio thread isn't blocked.
+///
+/// Sends the pedalboard ID, the sample rate it was loaded at and the loaded pedal, which replaces the pedal
+/// with the same ID on the audio thread.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn start_pedal_reload(pedalboard_id: u32, pedal_stringified: String, sample_rate: u32, buffer_size: usize, send_to: Sender<ReloadedPedal>) -> io::Result<std::thread::JoinHandle<()>> {
+    std::thread::Builder::new().name("PedalReloadThread".to_string()).spawn(move || {
+        let mut pedal: Pedal = match serde_json::from_str(&pedal_stringified) {
+            Ok(pedal) => pedal,
+            Err(e) => {
+                tracing::error!("Failed to deserialize pedal to reload: {}", e);
+                return;
+            }
+        };
+
+        pedal.configure(buffer_size, sample_rate);
+        tracing::info!("Reloaded pedal {}", pedal.get_id());
+
+        if send_to.send((pedalboard_id, sample_rate, pedal)).is_err() {
+            tracing::error!("Failed to send reloaded pedal to audio thread");
+        }
+    })
+}
@@ -2,7 +2,11 @@ use std::{io, path::{Path, PathBuf}};
 
 use crossbeam::channel::{Receiver, Sender, TryRecvError};
 use hound::WavWriter;
-use ringbuf::{traits::{Consumer, Observer, Split}, HeapCons, HeapProd};
+use ringbuf::{traits::{Consumer, Observer, Producer, Split}, HeapCons, HeapProd};
+use rs_pedalboard::dsp_algorithms::sample_delay::SampleDelay;
+
+/// Longest delay of the clean recording, in seconds. Longer pedal latencies are clamped to this.
+const MAX_CLEAN_DELAY_SECS: f32 = 1.0;
 
 pub enum RecordingHandleState {
     Active {
@@ -27,6 +31,8 @@ pub enum RecordingHandleState {
 pub struct RecordingHandle {
     state: RecordingHandleState,
     sample_rate: f32,
+    // Delays the clean recording by the latency of the pedals, so it lines up with the processed recording
+    clean_delay: SampleDelay,
     pub output_dir: PathBuf
 }
 
@@ -40,7 +46,42 @@ impl RecordingHandle {
                 clean: None
             },
             output_dir,
-            sample_rate
+            sample_rate,
+            clean_delay: SampleDelay::new((sample_rate * MAX_CLEAN_DELAY_SECS) as usize)
+        }
+    }
+
+    /// Set the delay of the clean recording in samples, which should be the latency of the processed recording.
+    /// 
+    /// Changing the delay while recording skips or repeats samples in the clean recording.
+    pub fn set_clean_delay(&mut self, samples: usize) {
+        self.clean_delay.set_delay(samples);
+    }
+
+    /// Write unprocessed samples to the clean recording, delayed by the clean delay
+    pub fn push_clean(&mut self, data: &[f32]) {
+        let clean_delay = &mut self.clean_delay;
+        let producer = match &mut self.state {
+            RecordingHandleState::Active { clean_prod: Some(prod), .. } => prod,
+            RecordingHandleState::Inactive { clean: Some((prod, _)), .. } => prod,
+            _ => return
+        };
+
+        let written = if clean_delay.delay() == 0 {
+            producer.push_slice(data)
+        } else {
+            let mut written = 0;
+            for &sample in data {
+                let delayed = clean_delay.process(sample);
+                if producer.try_push(delayed).is_ok() {
+                    written += 1;
+                }
+            }
+            written
+        };
+
+        if written != data.len() {
+            tracing::warn!("RecordingHandle: Clean recording ring buffer full, dropping samples.");
         }
     }
 
@@ -126,6 +167,9 @@ impl RecordingHandle {
             return;
         }
 
+        // Start the clean recording with silence for the length of the delay, like the processed recording
+        self.clean_delay.reset();
+
         if let RecordingHandleState::Inactive { processed, clean } = std::mem::replace(&mut self.state, RecordingHandleState::Starting) {
             let (alive_sender, alive_receiver) = crossbeam::channel::bounded(1);
             let (cons_sender, cons_receiver) = crossbeam::channel::bounded(1);
@@ -197,14 +241,6 @@ impl RecordingHandle {
             _ => None
         }
     }
-
-    pub fn clean_recording_producer(&mut self) -> Option<&mut HeapProd<f32>> {
-        match &mut self.state {
-            RecordingHandleState::Active { clean_prod, .. } => clean_prod.as_mut(),
-            RecordingHandleState::Inactive { clean, .. } => clean.as_mut().map(|(p, _)| p),
-            _ => None
-        }
-    }
 }
 
 #[tracing::instrument(level = "trace", skip(reader, clean_reader, alive_channel, cons_sender))]
@@ -302,3 +338,47 @@ pub fn start_file_writer_thread<P: AsRef<Path> + std::fmt::Debug>(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    /// Everything written to the processed and clean recordings of a handle that isn't recording
+    fn take_recorded(handle: &mut RecordingHandle) -> (Vec<f32>, Vec<f32>) {
+        match &mut handle.state {
+            RecordingHandleState::Inactive { processed: (_, cons), clean: Some((_, clean_cons)) } => {
+                (cons.pop_iter().collect(), clean_cons.pop_iter().collect())
+            },
+            _ => panic!("Expected an inactive handle with a clean recording")
+        }
+    }
+
+    #[test]
+    fn test_clean_recording_aligned_with_latency() {
+        let latency = 37;
+        let mut handle = RecordingHandle::new(1024, PathBuf::new(), 48000.0);
+        handle.set_clean(true);
+        handle.set_clean_delay(latency);
+
+        // A rig that halves the level, with a known latency
+        let mut rig = VecDeque::from(vec![0.0; latency]);
+        let input: Vec<f32> = (0..256).map(|i| ((i * 7) % 13) as f32 / 13.0 - 0.5).collect();
+        for block in input.chunks(64) {
+            handle.push_clean(block);
+
+            let processed: Vec<f32> = block.iter().map(|&sample| {
+                rig.push_back(sample * 0.5);
+                rig.pop_front().unwrap()
+            }).collect();
+            handle.recording_producer().unwrap().push_slice(&processed);
+        }
+
+        let (processed, clean) = take_recorded(&mut handle);
+        assert_eq!(processed.len(), clean.len());
+        assert!(processed.iter().zip(clean.iter()).all(|(processed, clean)| *processed == clean * 0.5));
+        assert!(clean[..latency].iter().all(|&sample| sample == 0.0));
+        assert_eq!(clean[latency..], input[..input.len() - latency]);
+    }
+}
@@ -0,0 +1,57 @@
+use rs_pedalboard::dsp_algorithms::{fractional_resampler::FractionalResampler, resampler::Resampler};
+
+use crate::settings::ProcessorSettings;
+
+/// Converts between the device sample rate and the sample rate pedals are processed at.
+pub enum Resamplers {
+    // 2x upsample passes around the device sample rate
+    HalfBand(Resampler, Resampler),
+    // Fixed internal sample rate, independent of the device sample rate
+    Fractional(FractionalResampler, FractionalResampler)
+}
+
+impl Resamplers {
+    /// Create the resamplers to run pedals at the processing sample rate given by the settings,
+    /// or None if it is the same as the device sample rate.
+    pub fn new(settings: &ProcessorSettings, device_sample_rate: u32) -> Option<Self> {
+        match settings.internal_sample_rate {
+            Some(sample_rate) if sample_rate == device_sample_rate => None,
+            Some(sample_rate) => Some(Resamplers::Fractional(
+                FractionalResampler::new(device_sample_rate, sample_rate, settings.frames_per_period, settings.resampler_quality),
+                FractionalResampler::new(sample_rate, device_sample_rate, settings.frames_per_period, settings.resampler_quality)
+            )),
+            None if settings.upsample_passes == 0 => None,
+            None => {
+                let max_block = settings.frames_per_period << settings.upsample_passes;
+                Some(Resamplers::HalfBand(
+                    Resampler::new(settings.upsample_passes as usize, max_block, settings.resampler_quality),
+                    Resampler::new(settings.upsample_passes as usize, max_block, settings.resampler_quality)
+                ))
+            }
+        }
+    }
+
+    /// Resample device audio to the processing sample rate, replacing the contents of `output`
+    pub fn to_processing_rate(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        match self {
+            Resamplers::HalfBand(upsampler, _) => {
+                output.resize(upsampler.upsample_output_buffer_size(input.len()), 0.0);
+                upsampler.upsample(input, output.as_mut_slice());
+            },
+            Resamplers::Fractional(resampler, _) => resampler.process(input, output)
+        }
+    }
+
+    /// Resample processed audio back to the device sample rate, replacing the contents of `output`.
+    ///
+    /// For a fixed internal sample rate, the number of samples may differ slightly from the device buffer size.
+    pub fn to_device_rate(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        match self {
+            Resamplers::HalfBand(_, downsampler) => {
+                output.resize(downsampler.downsample_output_buffer_size(input.len()), 0.0);
+                downsampler.downsample(input, output.as_mut_slice());
+            },
+            Resamplers::Fractional(_, resampler) => resampler.process(input, output)
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use std::{path::PathBuf, str::FromStr};
 
 use clap::Parser;
-use rs_pedalboard::processor_settings::{ProcessorSettingsSave, SupportedHost};
+use rs_pedalboard::dsp_algorithms::resampler::ResamplerQuality;
+use rs_pedalboard::processor_settings::{DisconnectAction, FxLoopChannels, ProcessorSettingsSave, SupportedHost, MIN_OUTPUT_CEILING_DB};
 
 #[cfg(target_os = "linux")]
 mod constants {
@@ -17,6 +18,8 @@ mod constants {
     pub const HOST_HELP_STR: &'static str = "Audio host to use (WASAPI (default) or ASIO)";
 }
 
+pub const DEFAULT_PORT: u16 = 29475;
+
 #[derive(Parser, Clone, Debug)]
 #[command(name = "Pedalboard Processor")]
 pub struct ProcessorArguments {
@@ -42,10 +45,34 @@ pub struct ProcessorArguments {
     pub preferred_sample_rate: Option<u32>,
     #[arg(long, help="Number of 2x upsample passes to apply before processing (default: 0)")]
     pub upsample_passes: Option<u32>,
+    #[arg(long, help="Sample rate to process pedals at, regardless of the device sample rate. Replaces upsampling if set.")]
+    pub internal_sample_rate: Option<u32>,
+    #[arg(long, help="Quality of the resampling for upsampling or an internal sample rate: fast, medium or high. Higher quality uses more CPU. (default: high)")]
+    pub resampler_quality: Option<ResamplerQuality>,
     #[arg(long, default_value_t=false, help="Ignore saved settings - use command line arguments/default")]
     pub ignore_save: bool,
     #[arg(long, help="Directory to save recordings to (default: ~/rs_pedalboard/Recordings)")]
-    pub recording_dir: Option<PathBuf>
+    pub recording_dir: Option<PathBuf>,
+    #[arg(long, help="Apply a soft limiter to the output (default: false)")]
+    pub output_limiter: Option<bool>,
+    #[arg(long, help="Clean boost in dB applied before the pedalboard chain (default: 0)")]
+    pub pre_gain: Option<f32>,
+    #[arg(long, allow_negative_numbers=true, help="Highest output volume in dB, from -40 to 0. Also lowers the output limiter threshold. (default: 0)")]
+    pub output_ceiling: Option<f32>,
+    #[arg(long, help="Pedalboard set (JSON) to load at startup, so the processor can run without a client")]
+    pub load: Option<PathBuf>,
+    #[arg(long, help="Port to listen for the client on (default: 29475)")]
+    pub port: Option<u16>,
+    #[arg(long, requires="fx_return_channel", help="Output channel (0-indexed) to send to external hardware from the FX Loop pedal")]
+    pub fx_send_channel: Option<usize>,
+    #[arg(long, requires="fx_send_channel", help="Input channel (0-indexed) to return from external hardware to the FX Loop pedal")]
+    pub fx_return_channel: Option<usize>,
+    #[arg(long, help="Output channel (0-indexed) to send the dry input to while recording, for monitoring")]
+    pub dry_monitor_channel: Option<usize>,
+    #[arg(long, help="Input channel (0-indexed) to process, e.g. the instrument input of a multi-input interface (default: mix all channels)")]
+    pub input_channel: Option<usize>,
+    #[arg(long, help="What to do once every client has disconnected: keep (keep playing), mute or default (load a clean pedalboard). (default: keep)")]
+    pub on_disconnect: Option<DisconnectAction>
 }
 
 /// All processor settings, compiled from args, save file and default values.
@@ -63,7 +90,18 @@ pub struct ProcessorSettings {
     pub output_device: Option<String>,
     pub preferred_sample_rate: Option<u32>,
     pub upsample_passes: u32,
-    pub recording_dir: PathBuf
+    pub internal_sample_rate: Option<u32>,
+    pub resampler_quality: ResamplerQuality,
+    pub recording_dir: PathBuf,
+    pub output_limiter: bool,
+    pub pre_gain_db: f32,
+    pub output_ceiling_db: f32,
+    pub startup_set: Option<PathBuf>,
+    pub port: u16,
+    pub fx_loop: Option<FxLoopChannels>,
+    pub dry_monitor_channel: Option<usize>,
+    pub input_channel: Option<usize>,
+    pub on_disconnect: DisconnectAction
 }
 
 impl ProcessorSettings {
@@ -131,6 +169,36 @@ impl ProcessorSettings {
             )
         });
 
+        let internal_sample_rate = args.internal_sample_rate.or_else(|| {
+            saved.as_ref().and_then(|s| s.internal_sample_rate)
+        });
+
+        let output_limiter = args.output_limiter.unwrap_or_else(|| {
+            saved.as_ref().map_or_else(
+                || false,
+                |s| s.output_limiter
+            )
+        });
+
+        let pre_gain_db = args.pre_gain.unwrap_or_else(|| {
+            saved.as_ref().map_or_else(
+                || 0.0,
+                |s| s.pre_gain_db
+            )
+        });
+
+        let output_ceiling_db = args.output_ceiling.unwrap_or_else(|| {
+            saved.as_ref().map_or_else(
+                || 0.0,
+                |s| s.output_ceiling_db
+            )
+        }).clamp(MIN_OUTPUT_CEILING_DB, 0.0);
+
+        let fx_loop = match (args.fx_send_channel, args.fx_return_channel) {
+            (Some(send_channel), Some(return_channel)) => Some(FxLoopChannels { send_channel, return_channel }),
+            _ => saved.as_ref().and_then(|s| s.fx_loop)
+        };
+
         ProcessorSettings {
             host,
             frames_per_period,
@@ -143,6 +211,25 @@ impl ProcessorSettings {
             output_device,
             preferred_sample_rate,
             upsample_passes,
+            internal_sample_rate,
+            resampler_quality: args.resampler_quality.unwrap_or_else(|| {
+                saved.as_ref().map_or_else(ResamplerQuality::default, |s| s.resampler_quality)
+            }),
+            output_limiter,
+            pre_gain_db,
+            output_ceiling_db,
+            startup_set: args.load,
+            port: args.port.unwrap_or(DEFAULT_PORT),
+            fx_loop,
+            dry_monitor_channel: args.dry_monitor_channel.or_else(|| {
+                saved.as_ref().and_then(|s| s.dry_monitor_channel)
+            }),
+            input_channel: args.input_channel.or_else(|| {
+                saved.as_ref().and_then(|s| s.input_channel)
+            }),
+            on_disconnect: args.on_disconnect.unwrap_or_else(|| {
+                saved.as_ref().map_or_else(DisconnectAction::default, |s| s.on_disconnect)
+            }),
             recording_dir: Self::recording_dir(
                 args.recording_dir,
                 saved.as_ref()
@@ -158,6 +245,11 @@ impl ProcessorSettings {
         self.frames_per_period * 2_usize.pow(self.upsample_passes)
     }
 
+    /// Sample rate pedals are processed at, for the given device sample rate
+    pub fn processing_sample_rate(&self, device_sample_rate: u32) -> u32 {
+        self.internal_sample_rate.unwrap_or(device_sample_rate << self.upsample_passes)
+    }
+
     pub fn default_recording_dir() -> Option<PathBuf> {
         let dir = homedir::my_home()
             .ok()
@@ -190,7 +282,16 @@ impl From<ProcessorSettings> for ProcessorSettingsSave {
             output_device: value.output_device,
             preferred_sample_rate: value.preferred_sample_rate,
             upsample_passes: value.upsample_passes,
-            recording_dir: Some(value.recording_dir)
+            internal_sample_rate: value.internal_sample_rate,
+            resampler_quality: value.resampler_quality,
+            recording_dir: Some(value.recording_dir),
+            output_limiter: value.output_limiter,
+            pre_gain_db: value.pre_gain_db,
+            output_ceiling_db: value.output_ceiling_db,
+            fx_loop: value.fx_loop,
+            dry_monitor_channel: value.dry_monitor_channel,
+            input_channel: value.input_channel,
+            on_disconnect: value.on_disconnect
         }
     }
 }
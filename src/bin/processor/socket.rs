@@ -1,14 +1,42 @@
+use std::{cell::RefCell, rc::Rc};
+
 use ringbuf::traits::{Consumer, Split};
-use smol::{io::AsyncWriteExt, net::{TcpListener, TcpStream, Ipv4Addr}, stream::StreamExt};
+use smol::{io::AsyncWriteExt, net::{TcpListener, TcpStream, Ipv4Addr}, stream::StreamExt, LocalExecutor};
 use futures::{FutureExt, select, pin_mut};
 use smol::channel::{Sender, Receiver};
-use rs_pedalboard::socket_helper::CommandReceiver;
+use rs_pedalboard::socket_helper::{log_full_commands, CommandReceiver};
+
+/// Commands that change the pedalboards, which are forwarded to the other connected clients to keep them in sync
+const SYNCED_COMMANDS: [&str; 12] = [
+    "setparameter",
+    "movepedal",
+    "deletepedal",
+    "setparallelbranch",
+    "addpedal",
+    "movepedalboard",
+    "deletepedalboard",
+    "addpedalboard",
+    "loadset",
+    "play",
+    "nextpedalboard",
+    "prevpedalboard"
+];
+
+struct ConnectedClient {
+    id: u32,
+    // Messages to write to the client
+    sender: Sender<Box<str>>,
+    // If the client joined while other clients were connected, this holds the synced commands
+    // received while waiting for the current pedalboard set, to be sent after it
+    pending: Option<Vec<Box<str>>>
+}
+
+type Clients = Rc<RefCell<Vec<ConnectedClient>>>;
 
 pub struct ProcessorSocket {
     port: u16,
     command_sender: Sender<Box<str>>,
-    command_receiver: Receiver<Box<str>>,
-    command_receive_helper: CommandReceiver
+    command_receiver: Receiver<Box<str>>
 }
 
 impl ProcessorSocket {
@@ -16,40 +44,119 @@ impl ProcessorSocket {
         ProcessorSocket {
             port,
             command_sender,
-            command_receiver,
-            command_receive_helper: CommandReceiver::new()
+            command_receiver
         }
     }
 
     pub fn start(&mut self) -> std::io::Result<()> {
-        smol::block_on(async {
+        let executor = LocalExecutor::new();
+        let clients: Clients = Rc::new(RefCell::new(Vec::new()));
+
+        smol::block_on(executor.run(async {
             tracing::info!("Starting processor on port {}", self.port);
             let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, self.port)).await?;
             tracing::info!("Processor listening on port {}", self.port);
 
+            executor.spawn(Self::broadcast(self.command_receiver.clone(), clients.clone())).detach();
+
+            let mut next_client_id = 0;
             while let Some(stream) = listener.incoming().next().await {
                 match stream {
                     Ok(stream) => {
                         tracing::info!("New connection: {}", stream.peer_addr()?);
-                        // Don't make a new thread as currently only one client is supported
-                        self.handle_client(stream).await;
-                        tracing::info!("Finished handling client.");
+                        let (sender, receiver) = smol::channel::unbounded();
+
+                        // A client joining a session uses the pedalboards of the clients already connected
+                        let joined = !clients.borrow().is_empty();
+                        let pending = if joined {
+                            if self.command_sender.send("requestset".into()).await.is_err() {
+                                tracing::error!("Failed to request pedalboard set from audio thread");
+                            }
+                            let _ = sender.try_send("session join\n".into());
+                            Some(Vec::new())
+                        } else {
+                            let _ = sender.try_send("session new\n".into());
+                            None
+                        };
+
+                        clients.borrow_mut().push(ConnectedClient { id: next_client_id, sender, pending });
+                        executor.spawn(Self::handle_client(
+                            next_client_id,
+                            stream,
+                            receiver,
+                            clients.clone(),
+                            self.command_sender.clone()
+                        )).detach();
+                        next_client_id += 1;
                     }
                     Err(e) => tracing::error!("Connection failed: {}", e),
                 }
             }
 
             Ok(())
-        })
+        }))
+    }
+
+    /// Send messages from the audio thread to every connected client
+    async fn broadcast(command_receiver: Receiver<Box<str>>, clients: Clients) {
+        while let Ok(command) = command_receiver.recv().await {
+            let is_set = command.starts_with("pedalboardset ");
+
+            for client in clients.borrow_mut().iter_mut() {
+                if is_set {
+                    // Only clients that have just joined need the current set
+                    let Some(pending) = client.pending.take() else {
+                        continue;
+                    };
+
+                    let _ = client.sender.try_send(command.clone());
+                    for synced in pending {
+                        let _ = client.sender.try_send(synced);
+                    }
+                } else if client.sender.try_send(command.clone()).is_err() {
+                    tracing::error!("Failed to send command to client {}", client.id);
+                }
+            }
+        }
+
+        tracing::error!("Audio thread channel has disconnected");
+    }
+
+    /// Forward a command that changed the pedalboards to every client other than the one that sent it
+    fn sync_to_other_clients(id: u32, command: &str, clients: &Clients) {
+        let synced: Box<str> = format!("sync {command}\n").into();
+
+        for client in clients.borrow_mut().iter_mut().filter(|client| client.id != id) {
+            match &mut client.pending {
+                Some(pending) => pending.push(synced.clone()),
+                None => if client.sender.try_send(synced.clone()).is_err() {
+                    tracing::error!("Failed to sync command to client {}", client.id);
+                }
+            }
+        }
+    }
+
+    /// Hold back synced commands for a client until it has received the pedalboard set it requested
+    fn await_set(id: u32, clients: &Clients) {
+        if let Some(client) = clients.borrow_mut().iter_mut().find(|client| client.id == id) {
+            client.pending.get_or_insert_with(Vec::new);
+        }
     }
 
-    async fn handle_client(&mut self, stream: TcpStream) {
+    async fn handle_client(
+        id: u32,
+        stream: TcpStream,
+        outgoing_receiver: Receiver<Box<str>>,
+        clients: Clients,
+        command_sender: Sender<Box<str>>
+    ) {
+        let mut command_receive_helper = CommandReceiver::new();
         let (mut received_commands_writer, mut received_commands_reader) = ringbuf::HeapRb::new(128).split();
         let (mut stream_reader, mut stream_writer) = smol::io::split(stream);
 
         loop {
-            let socket_fut = self.command_receive_helper.receive_commands_async(&mut stream_reader, &mut received_commands_writer).fuse();
-            let channel_fut = self.command_receiver.recv().fuse();
+            let socket_fut = command_receive_helper.receive_commands_async(&mut stream_reader, &mut received_commands_writer).fuse();
+            let channel_fut = outgoing_receiver.recv().fuse();
 
             pin_mut!(socket_fut, channel_fut);
 
@@ -58,10 +165,25 @@ impl ProcessorSocket {
                     match result {
                         Ok(closed) => {
                             for command in received_commands_reader.pop_iter() {
-                                if self.command_sender.send(command.into()).await.is_err() {
+                                let command_name = command.split('|').next().unwrap_or_default();
+                                let synced = SYNCED_COMMANDS.contains(&command_name).then(|| command.clone());
+                                let requests_set = command_name == "requestset";
+
+                                if command_sender.send(command.into()).await.is_err() {
                                     tracing::error!("Failed to send command to audio thread");
                                     break;
                                 }
+
+                                // Synced once the audio thread has the command, so a client waiting for
+                                // the current set receives only the commands that are not in it
+                                if let Some(synced) = synced {
+                                    Self::sync_to_other_clients(id, &synced, &clients);
+                                }
+
+                                // e.g. an observer that connected first, so uses the set that the processor already has
+                                if requests_set {
+                                    Self::await_set(id, &clients);
+                                }
                             }
 
                             if closed {
@@ -95,20 +217,27 @@ impl ProcessorSocket {
                                     break;
                                 }
                             }
-                            if command.len() <= 20 || cfg!(feature="log_full_commands") {
+                            if command.len() <= 20 || log_full_commands() {
                                 tracing::debug!("Sent command: {:?}", command);
                             } else {
                                 tracing::debug!("Sent command: {:?}...", &command[..20]);
                             }
                         },
                         Err(_) => {
-                            tracing::error!("Audio thread channel has disconnected");
+                            tracing::error!("Client channel has disconnected");
                             break;
                         }
                     }
                 }
             }
         }
-        self.command_sender.send("disconnect".into()).await.expect("Failed to send disconnect command");
+
+        tracing::info!("Finished handling client.");
+        clients.borrow_mut().retain(|client| client.id != id);
+
+        // Client features such as the tuner are only stopped once every client has disconnected
+        if clients.borrow().is_empty() {
+            command_sender.send("disconnect".into()).await.expect("Failed to send disconnect command");
+        }
     }
 }
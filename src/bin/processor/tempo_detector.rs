@@ -0,0 +1,133 @@
+use std::{collections::VecDeque, ops::RangeInclusive};
+use rs_pedalboard::dsp_algorithms::onset_detector::OnsetDetector;
+
+/// Onsets further apart than this start a new tempo
+const ONSET_TIMEOUT_SECS: f32 = 2.0;
+/// Number of most recent intervals between onsets that the tempo is inferred from
+const TEMPO_INTERVALS: usize = 3;
+/// Largest difference of an interval from the average interval, as a fraction of the average, for the onsets to be in time
+const INTERVAL_TOLERANCE: f32 = 0.1;
+/// Range of the inferred tempo, the same as the client's metronome range.
+/// Onsets closer together than the fastest tempo are ignored, as they are likely double triggers.
+const BPM_RANGE: RangeInclusive<u32> = 40..=360;
+
+/// Infers a tempo from the transients of playing, for setting the tempo without a footswitch.
+///
+/// Once the last few onsets are evenly spaced, their average interval sets the tempo.
+pub struct TempoDetector {
+    onset_detector: OnsetDetector,
+    sample_rate: f32,
+    // Samples since the last onset, None before the first onset
+    samples_since_onset: Option<usize>,
+    // Most recent intervals between onsets, in samples
+    intervals: VecDeque<usize>,
+    last_bpm: Option<u32>,
+}
+
+impl TempoDetector {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            onset_detector: OnsetDetector::new(sample_rate as f32),
+            sample_rate: sample_rate as f32,
+            samples_since_onset: None,
+            intervals: VecDeque::with_capacity(TEMPO_INTERVALS + 1),
+            last_bpm: None,
+        }
+    }
+
+    /// Returns the tempo in BPM if a different tempo was inferred from the samples
+    pub fn add_samples(&mut self, samples: &[f32]) -> Option<u32> {
+        let mut new_bpm = None;
+
+        for &sample in samples {
+            if let Some(since) = &mut self.samples_since_onset {
+                *since = since.saturating_add(1);
+            }
+
+            if self.onset_detector.process(sample) {
+                new_bpm = self.add_onset().or(new_bpm);
+            }
+        }
+
+        new_bpm
+    }
+
+    fn add_onset(&mut self) -> Option<u32> {
+        let min_interval = (60.0 / *BPM_RANGE.end() as f32 * self.sample_rate) as usize;
+        let timeout = (ONSET_TIMEOUT_SECS * self.sample_rate) as usize;
+
+        match self.samples_since_onset {
+            Some(interval) if interval < min_interval => return None,
+            Some(interval) if interval <= timeout => {
+                if self.intervals.len() == TEMPO_INTERVALS {
+                    self.intervals.pop_front();
+                }
+                self.intervals.push_back(interval);
+            },
+            _ => self.intervals.clear()
+        }
+        self.samples_since_onset = Some(0);
+
+        if self.intervals.len() < TEMPO_INTERVALS {
+            return None;
+        }
+
+        let average = self.intervals.iter().sum::<usize>() as f32 / self.intervals.len() as f32;
+        let in_time = self.intervals.iter().all(|&interval| (interval as f32 - average).abs() <= average * INTERVAL_TOLERANCE);
+        if !in_time {
+            return None;
+        }
+
+        let bpm = ((60.0 * self.sample_rate / average).round() as u32).clamp(*BPM_RANGE.start(), *BPM_RANGE.end());
+        if self.last_bpm == Some(bpm) {
+            return None;
+        }
+        self.last_bpm = Some(bpm);
+        Some(bpm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// A short note at each of the given times in seconds, with silence between
+    fn hits(times: &[f32], length_secs: f32) -> Vec<f32> {
+        let mut samples = vec![0.0; (length_secs * SAMPLE_RATE as f32) as usize];
+        let note_length = SAMPLE_RATE as usize / 20;
+        for &time in times {
+            let start = (time * SAMPLE_RATE as f32) as usize;
+            for (i, sample) in samples[start..start + note_length].iter_mut().enumerate() {
+                let decay = 1.0 - i as f32 / note_length as f32;
+                *sample = 0.5 * decay * (i as f32 * 440.0 * std::f32::consts::TAU / SAMPLE_RATE as f32).sin();
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn test_steady_hits_set_tempo() {
+        let mut detector = TempoDetector::new(SAMPLE_RATE);
+
+        // Three hits aren't enough to infer a tempo
+        let samples = hits(&[0.5, 1.0, 1.5, 2.0], 2.4);
+        assert_eq!(detector.add_samples(&samples[..(1.8 * SAMPLE_RATE as f32) as usize]), None);
+        assert_eq!(detector.add_samples(&samples[(1.8 * SAMPLE_RATE as f32) as usize..]), Some(120));
+
+        // The same tempo isn't reported again
+        assert_eq!(detector.add_samples(&hits(&[0.1], 0.4)), None);
+    }
+
+    #[test]
+    fn test_uneven_hits_are_ignored() {
+        let mut detector = TempoDetector::new(SAMPLE_RATE);
+        assert_eq!(detector.add_samples(&hits(&[0.2, 0.6, 1.4, 1.7, 2.5], 3.0)), None);
+
+        // Hits after a long gap aren't timed from the hits before it
+        assert_eq!(detector.add_samples(&[0.0; 3 * SAMPLE_RATE as usize]), None);
+        assert_eq!(detector.add_samples(&hits(&[0.5, 1.0, 1.5], 2.0)), None);
+        assert_eq!(detector.add_samples(&hits(&[0.0], 0.5)), Some(120));
+    }
+}
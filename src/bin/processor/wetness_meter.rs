@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+/// How often the overall wetness is sent to the client
+pub const WETNESS_UPDATE_RATE: Duration = Duration::from_millis(500);
+/// Energy per sample below which the signal is treated as silent, and no wetness is measured
+const SILENCE_ENERGY: f64 = 1e-8;
+
+/// Measures how much of the pedalboard output is 'wet', i.e. not explained by the dry input.
+///
+/// The wetness is the share of output energy that doesn't correlate with the dry input (1 - r²),
+/// so level changes (e.g. a boost) read as dry, while reverb, delay, modulation and distortion read as wet.
+/// Pedals that add latency also read as wet, as the output is compared with the input at the same time.
+pub struct WetnessMeter {
+    last_sent: Instant,
+    dry_energy: f64,
+    wet_energy: f64,
+    cross_energy: f64,
+    samples: usize,
+}
+
+impl WetnessMeter {
+    pub fn new() -> Self {
+        Self {
+            last_sent: Instant::now(),
+            dry_energy: 0.0,
+            wet_energy: 0.0,
+            cross_energy: 0.0,
+            samples: 0,
+        }
+    }
+
+    /// Add the input to the pedalboard and the output it produced
+    pub fn add_samples(&mut self, dry: &[f32], wet: &[f32]) {
+        for (&dry, &wet) in dry.iter().zip(wet.iter()) {
+            let (dry, wet) = (dry as f64, wet as f64);
+            self.dry_energy += dry * dry;
+            self.wet_energy += wet * wet;
+            self.cross_energy += dry * wet;
+        }
+        self.samples += dry.len().min(wet.len());
+    }
+
+    /// Wetness (0-1) of the samples since the last call, or None if either signal was silent
+    fn take_wetness(&mut self) -> Option<f32> {
+        let samples = self.samples.max(1) as f64;
+        let wetness = if self.dry_energy / samples < SILENCE_ENERGY || self.wet_energy / samples < SILENCE_ENERGY {
+            None
+        } else {
+            let correlation = self.cross_energy * self.cross_energy / (self.dry_energy * self.wet_energy);
+            Some((1.0 - correlation).clamp(0.0, 1.0) as f32)
+        };
+
+        self.dry_energy = 0.0;
+        self.wet_energy = 0.0;
+        self.cross_energy = 0.0;
+        self.samples = 0;
+        wetness
+    }
+
+    /// If it is time for an update, returns the wetness since the last update
+    pub fn take_update(&mut self) -> Option<f32> {
+        if self.last_sent.elapsed() < WETNESS_UPDATE_RATE {
+            return None;
+        }
+        self.last_sent = Instant::now();
+        self.take_wetness()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, length: usize) -> Vec<f32> {
+        (0..length).map(|i| (i as f32 * frequency * std::f32::consts::TAU / 48000.0).sin() * 0.5).collect()
+    }
+
+    #[test]
+    fn test_gain_change_is_dry() {
+        let dry = sine(440.0, 4800);
+        let boosted: Vec<f32> = dry.iter().map(|sample| -sample * 1.8).collect();
+
+        let mut meter = WetnessMeter::new();
+        meter.add_samples(&dry, &boosted);
+        assert!(meter.take_wetness().unwrap() < 1e-3);
+    }
+
+    #[test]
+    fn test_mixed_signal_wetness() {
+        let dry = sine(440.0, 4800);
+        let effect = sine(1000.0, 4800);
+
+        let mut meter = WetnessMeter::new();
+        meter.add_samples(&dry, &effect);
+        assert!(meter.take_wetness().unwrap() > 0.99);
+
+        // Equal parts dry and uncorrelated wet is half wet
+        let mixed: Vec<f32> = dry.iter().zip(effect.iter()).map(|(dry, wet)| dry + wet).collect();
+        meter.add_samples(&dry, &mixed);
+        assert!((meter.take_wetness().unwrap() - 0.5).abs() < 0.01);
+
+        // Silence isn't measured
+        meter.add_samples(&[0.0; 4800], &effect);
+        assert_eq!(meter.take_wetness(), None);
+    }
+}
@@ -0,0 +1,100 @@
+use super::onset_detector::OnsetDetector;
+
+/// Detects note onsets and fades in the gain after each one.
+///
+/// Used for violin-like volume swells without an expression pedal.
+#[derive(Debug, Clone)]
+pub struct AutoSwell {
+    sample_rate: f32,
+    onset_detector: OnsetDetector,
+    swell_samples: usize,
+    swell_position: usize,
+}
+
+impl AutoSwell {
+    pub fn new(sample_rate: f32, swell_ms: f32) -> Self {
+        let mut swell = Self {
+            sample_rate,
+            onset_detector: OnsetDetector::new(sample_rate),
+            swell_samples: 0,
+            swell_position: 0,
+        };
+        swell.set_swell_time(swell_ms);
+        // Start at full volume, until the first onset
+        swell.swell_position = swell.swell_samples;
+        swell
+    }
+
+    pub fn set_swell_time(&mut self, swell_ms: f32) {
+        self.swell_samples = ((swell_ms.max(1.0) / 1000.0) * self.sample_rate) as usize;
+        self.swell_position = self.swell_position.min(self.swell_samples);
+    }
+
+    /// Returns the gain to apply to the given sample
+    pub fn process(&mut self, sample: f32) -> f32 {
+        if self.onset_detector.process(sample) {
+            self.swell_position = 0;
+        }
+
+        if self.swell_position < self.swell_samples {
+            self.swell_position += 1;
+        }
+
+        self.swell_position as f32 / self.swell_samples as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.onset_detector.reset();
+        self.swell_position = self.swell_samples;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attack_is_ramped() {
+        let sample_rate = 48000.0;
+        let swell_ms = 200.0;
+        let mut swell = AutoSwell::new(sample_rate, swell_ms);
+
+        // Silence, then a sharp attack
+        for _ in 0..4800 {
+            swell.process(0.0);
+        }
+        let swell_samples = ((swell_ms / 1000.0) * sample_rate) as usize;
+        let gains: Vec<f32> = (0..swell_samples * 2)
+            .map(|i| swell.process(0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate).sin()))
+            .collect();
+
+        // Onset is detected within the first millisecond
+        assert!(gains[48] < 0.01, "Gain after attack {}", gains[48]);
+        assert!((gains[swell_samples / 2] - 0.5).abs() < 0.05, "Gain halfway through swell {}", gains[swell_samples / 2]);
+        assert!(gains[swell_samples / 2] < gains[swell_samples * 3 / 4]);
+        // Full volume after the swell time, and a sustained note doesn't retrigger the swell
+        assert!(gains[swell_samples + 48..].iter().all(|&g| g == 1.0));
+    }
+
+    #[test]
+    fn test_next_note_retriggers() {
+        let sample_rate = 48000.0;
+        let mut swell = AutoSwell::new(sample_rate, 100.0);
+        let note = |i: usize| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate).sin();
+
+        for i in 0..24000 {
+            swell.process(note(i));
+        }
+        for _ in 0..14400 {
+            swell.process(0.0);
+        }
+        let gains: Vec<f32> = (0..480).map(|i| swell.process(note(i))).collect();
+        assert!(gains[479] < 0.2, "Gain after second attack {}", gains[479]);
+    }
+
+    #[test]
+    fn test_full_gain_before_onset() {
+        let mut swell = AutoSwell::new(48000.0, 200.0);
+        assert_eq!(swell.process(0.0), 1.0);
+    }
+}
@@ -0,0 +1,156 @@
+use super::biquad::BiquadFilter;
+
+const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// 4th order Linkwitz-Riley crossover, made from two cascaded Butterworth filters per band.
+///
+/// The low and high outputs sum to an allpass of the input, so the magnitude is preserved.
+#[derive(Debug, Clone)]
+pub struct LinkwitzRileyCrossover {
+    low: [BiquadFilter; 2],
+    high: [BiquadFilter; 2],
+    sample_rate: f32,
+}
+
+impl LinkwitzRileyCrossover {
+    pub fn new(freq: f32, sample_rate: f32) -> Self {
+        let low = BiquadFilter::low_pass(freq, sample_rate, BUTTERWORTH_Q);
+        let high = BiquadFilter::high_pass(freq, sample_rate, BUTTERWORTH_Q);
+        Self {
+            low: [low, low],
+            high: [high, high],
+            sample_rate,
+        }
+    }
+
+    /// Change the crossover frequency, keeping the filter state
+    pub fn set_freq(&mut self, freq: f32) {
+        let low = BiquadFilter::low_pass(freq, self.sample_rate, BUTTERWORTH_Q);
+        let high = BiquadFilter::high_pass(freq, self.sample_rate, BUTTERWORTH_Q);
+
+        for filter in self.low.iter_mut() {
+            let (x, y) = (filter.x, filter.y);
+            *filter = low;
+            filter.x = x;
+            filter.y = y;
+        }
+        for filter in self.high.iter_mut() {
+            let (x, y) = (filter.x, filter.y);
+            *filter = high;
+            filter.x = x;
+            filter.y = y;
+        }
+    }
+
+    /// Returns the (low, high) bands
+    pub fn process(&mut self, sample: f32) -> (f32, f32) {
+        let low = self.low[0].process(sample);
+        let low = self.low[1].process(low);
+        let high = self.high[0].process(sample);
+        let high = self.high[1].process(high);
+        (low, high)
+    }
+
+    pub fn reset(&mut self) {
+        for filter in self.low.iter_mut().chain(self.high.iter_mut()) {
+            filter.x = [0.0; 2];
+            filter.y = [0.0; 2];
+        }
+    }
+}
+
+/// Splits a signal into low, mid and high bands with two Linkwitz-Riley crossovers.
+///
+/// The low band is passed through an allpass matching the upper crossover,
+/// so the summed bands have a flat magnitude response.
+#[derive(Debug, Clone)]
+pub struct ThreeBandSplitter {
+    low_mid: LinkwitzRileyCrossover,
+    mid_high: LinkwitzRileyCrossover,
+    low_allpass: LinkwitzRileyCrossover,
+}
+
+impl ThreeBandSplitter {
+    pub fn new(low_freq: f32, high_freq: f32, sample_rate: f32) -> Self {
+        Self {
+            low_mid: LinkwitzRileyCrossover::new(low_freq, sample_rate),
+            mid_high: LinkwitzRileyCrossover::new(high_freq, sample_rate),
+            low_allpass: LinkwitzRileyCrossover::new(high_freq, sample_rate),
+        }
+    }
+
+    pub fn set_freqs(&mut self, low_freq: f32, high_freq: f32) {
+        self.low_mid.set_freq(low_freq);
+        self.mid_high.set_freq(high_freq);
+        self.low_allpass.set_freq(high_freq);
+    }
+
+    /// Returns the [low, mid, high] bands
+    pub fn process(&mut self, sample: f32) -> [f32; 3] {
+        let (low, rest) = self.low_mid.process(sample);
+        let (mid, high) = self.mid_high.process(rest);
+        let (low_a, low_b) = self.low_allpass.process(low);
+        [low_a + low_b, mid, high]
+    }
+
+    pub fn reset(&mut self) {
+        self.low_mid.reset();
+        self.mid_high.reset();
+        self.low_allpass.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_wave(frequency: f32, sample_rate: f32, duration: f32) -> Vec<f32> {
+        let num_samples = (sample_rate * duration) as usize;
+        (0..num_samples)
+            .map(|i| {
+                let t = i as f32 / sample_rate;
+                (2.0 * std::f32::consts::PI * frequency * t).sin()
+            })
+            .collect()
+    }
+
+    fn rms_energy(signal: &[f32]) -> f32 {
+        let sum_of_squares: f32 = signal.iter().map(|&x| x * x).sum();
+        (sum_of_squares / signal.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_bands_sum_to_input() {
+        let sample_rate = 48000.0;
+
+        for frequency in [50.0, 250.0, 600.0, 1500.0, 4000.0, 10000.0] {
+            let mut splitter = ThreeBandSplitter::new(250.0, 4000.0, sample_rate);
+            let input = sine_wave(frequency, sample_rate, 0.5);
+            let output: Vec<f32> = input.iter()
+                .map(|&s| splitter.process(s).iter().sum())
+                .collect();
+
+            // Ignore the start while the filters settle
+            let settle = input.len() / 2;
+            let input_rms = rms_energy(&input[settle..]);
+            let output_rms = rms_energy(&output[settle..]);
+            let difference_db = 20.0 * (output_rms / input_rms).log10();
+
+            assert!(difference_db.abs() < 0.1, "Summed bands differ from input by {}dB at {}hz", difference_db, frequency);
+        }
+    }
+
+    #[test]
+    fn test_bands_are_separated() {
+        let sample_rate = 48000.0;
+        let mut splitter = ThreeBandSplitter::new(250.0, 4000.0, sample_rate);
+        let input = sine_wave(50.0, sample_rate, 0.5);
+        let bands: Vec<[f32; 3]> = input.iter().map(|&s| splitter.process(s)).collect();
+
+        let settle = bands.len() / 2;
+        let low: Vec<f32> = bands[settle..].iter().map(|b| b[0]).collect();
+        let high: Vec<f32> = bands[settle..].iter().map(|b| b[2]).collect();
+        assert!(rms_energy(&low) > 0.6);
+        assert!(rms_energy(&high) < 0.01);
+    }
+}
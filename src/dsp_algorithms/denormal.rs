@@ -0,0 +1,76 @@
+/// Values smaller than this (about -300dB) are treated as silence in feedback paths
+const DENORMAL_THRESHOLD: f32 = 1e-15;
+
+/// Replace a value that is decaying towards the denormal range with zero.
+///
+/// Used on samples written back into feedback loops, where a decaying tail would
+/// otherwise spend a long time as denormals, which are very slow on some CPUs.
+#[inline]
+pub fn flush_denormal(value: f32) -> f32 {
+    if value.abs() < DENORMAL_THRESHOLD {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// Set the CPU to flush denormal results to zero and treat denormal inputs as zero, on this thread only.
+///
+/// This covers code that [`flush_denormal`] can't be added to, such as external crates.
+/// Does nothing on architectures other than x86_64 and aarch64.
+pub fn enable_flush_to_zero() {
+    #[cfg(target_arch = "x86_64")]
+    {
+        // MXCSR flush to zero and denormals are zero bits
+        const FTZ: u32 = 1 << 15;
+        const DAZ: u32 = 1 << 6;
+
+        let mut csr: u32 = 0;
+        // SAFETY: Only the denormal handling bits of the SSE control register are changed
+        unsafe {
+            std::arch::asm!("stmxcsr [{}]", in(reg) &mut csr, options(nostack, preserves_flags));
+            csr |= FTZ | DAZ;
+            std::arch::asm!("ldmxcsr [{}]", in(reg) &csr, options(nostack, readonly, preserves_flags));
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        // FPCR flush to zero bit
+        const FZ: u64 = 1 << 24;
+
+        let mut fpcr: u64;
+        // SAFETY: Only the flush to zero bit of the floating point control register is changed
+        unsafe {
+            std::arch::asm!("mrs {}, fpcr", out(reg) fpcr, options(nomem, nostack, preserves_flags));
+            fpcr |= FZ;
+            std::arch::asm!("msr fpcr, {}", in(reg) fpcr, options(nomem, nostack, preserves_flags));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_values_are_flushed() {
+        assert_eq!(flush_denormal(f32::MIN_POSITIVE / 4.0), 0.0);
+        assert_eq!(flush_denormal(-1e-20), 0.0);
+        assert_eq!(flush_denormal(1e-6), 1e-6);
+        assert_eq!(flush_denormal(-0.5), -0.5);
+    }
+
+    #[test]
+    fn test_decaying_feedback_reaches_zero() {
+        // A feedback loop like a delay tail, which without flushing stays denormal for thousands of samples
+        let mut value = 1.0_f32;
+        let mut samples = 0;
+        while value != 0.0 {
+            value = flush_denormal(value * 0.9);
+            assert!(value == 0.0 || value.is_normal(), "Denormal value {value} after {samples} samples");
+            samples += 1;
+            assert!(samples < 1000, "Feedback did not decay to zero");
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use super::resampler::ResamplerQuality;
+
+/// Number of precomputed fractional positions between two input samples
+const PHASES: usize = 256;
+/// Cutoff as a fraction of the lower Nyquist frequency, leaving room for the filter transition
+const CUTOFF: f64 = 0.95;
+
+/// A streaming resampler between any two sample rates, using windowed sinc interpolation.
+///
+/// The number of output samples per block varies, as the ratio between the rates may not be a whole number.
+/// Each resampler converts in one direction only.
+pub struct FractionalResampler {
+    // Taps either side of the interpolated point
+    half_taps: usize,
+    // Input samples advanced per output sample
+    step: f64,
+    // Position of the next output sample in `history`
+    position: f64,
+    // Unconsumed input, including the samples needed either side of the next output sample
+    history: Vec<f32>,
+    // Filter taps for each phase (and one extra for interpolating the last phase), flattened
+    table: Vec<f32>,
+}
+
+impl FractionalResampler {
+    /// Windowed sinc taps either side of the interpolated point for each quality, or 1 for linear interpolation
+    fn quality_half_taps(quality: ResamplerQuality) -> usize {
+        match quality {
+            ResamplerQuality::Fast => 1,
+            ResamplerQuality::Medium => 8,
+            ResamplerQuality::High => 16
+        }
+    }
+
+    pub fn new(input_sample_rate: u32, output_sample_rate: u32, max_block: usize, quality: ResamplerQuality) -> Self {
+        let ratio = output_sample_rate as f64 / input_sample_rate as f64;
+        // When downsampling, the cutoff is lowered below the output Nyquist frequency
+        let cutoff = CUTOFF * ratio.min(1.0);
+        let half_taps = Self::quality_half_taps(quality);
+        let taps = half_taps * 2;
+
+        let mut table = vec![0.0; (PHASES + 1) * taps];
+        for phase in 0..=PHASES {
+            let fraction = phase as f64 / PHASES as f64;
+            let row = &mut table[phase * taps..(phase + 1) * taps];
+
+            for (j, tap) in row.iter_mut().enumerate() {
+                // Distance from the interpolated point to this input sample
+                let distance = fraction + (half_taps - 1) as f64 - j as f64;
+
+                if quality == ResamplerQuality::Fast {
+                    *tap = (1.0 - distance.abs()).max(0.0) as f32;
+                    continue;
+                }
+
+                let x = cutoff * distance;
+                let sinc = if x.abs() < 1e-9 { 1.0 } else { (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x) };
+                // Blackman window over the span of the taps
+                let w = distance / half_taps as f64;
+                let window = if w.abs() >= 1.0 {
+                    0.0
+                } else {
+                    0.42 + 0.5 * (std::f64::consts::PI * w).cos() + 0.08 * (2.0 * std::f64::consts::PI * w).cos()
+                };
+                *tap = (cutoff * sinc * window) as f32;
+            }
+
+            // Normalize DC gain
+            let sum: f32 = row.iter().sum();
+            row.iter_mut().for_each(|tap| *tap /= sum);
+        }
+
+        let mut history = Vec::with_capacity(max_block + taps * 2);
+        history.resize(half_taps, 0.0);
+
+        Self {
+            half_taps,
+            step: 1.0 / ratio,
+            position: (half_taps - 1) as f64,
+            history,
+            table,
+        }
+    }
+
+    /// Largest number of output samples that `process` may produce for the given number of input samples
+    pub fn max_output_size(&self, input_size: usize) -> usize {
+        (input_size as f64 / self.step).ceil() as usize + 1
+    }
+
+    /// Resample `input`, replacing the contents of `output`
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        output.clear();
+        self.history.extend_from_slice(input);
+
+        let half_taps = self.half_taps;
+        let taps = half_taps * 2;
+
+        // Every output sample needs `half_taps` input samples after it
+        while (self.position as usize) + half_taps < self.history.len() {
+            let index = self.position as usize;
+            let fraction = (self.position - index as f64) * PHASES as f64;
+            let phase = fraction as usize;
+            let phase_fraction = (fraction - phase as f64) as f32;
+
+            let start = index + 1 - half_taps;
+            let inputs = &self.history[start..start + taps];
+            let taps_a = &self.table[phase * taps..(phase + 1) * taps];
+            let taps_b = &self.table[(phase + 1) * taps..(phase + 2) * taps];
+
+            let mut a = 0.0;
+            let mut b = 0.0;
+            for ((x, ta), tb) in inputs.iter().zip(taps_a).zip(taps_b) {
+                a += x * ta;
+                b += x * tb;
+            }
+            output.push(a + (b - a) * phase_fraction);
+
+            self.position += self.step;
+        }
+
+        // Drop the input that is no longer needed, keeping the samples before the next output sample
+        let consumed = (self.position as usize + 1).saturating_sub(half_taps).min(self.history.len());
+        self.history.drain(..consumed);
+        self.position -= consumed as f64;
+    }
+
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.history.resize(self.half_taps, 0.0);
+        self.position = (self.half_taps - 1) as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, sample_rate: f32, length: usize) -> Vec<f32> {
+        (0..length).map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate).sin()).collect()
+    }
+
+    #[test]
+    fn test_output_length_follows_ratio() {
+        for (input_rate, output_rate) in [(44100, 48000), (48000, 96000), (96000, 48000), (48000, 44100)] {
+            let mut resampler = FractionalResampler::new(input_rate, output_rate, 256, ResamplerQuality::High);
+            let mut output = Vec::new();
+            let mut total = 0;
+            for _ in 0..100 {
+                resampler.process(&[0.0; 256], &mut output);
+                assert!(output.len() <= resampler.max_output_size(256));
+                total += output.len();
+            }
+
+            // Up to `half_taps` input samples are held back until the following samples arrive
+            let ratio = output_rate as f64 / input_rate as f64;
+            let expected = 25600.0 * ratio;
+            assert!((total as f64 - expected).abs() <= (resampler.half_taps + 1) as f64 * ratio, "{input_rate} -> {output_rate}: {total} samples, expected {expected}");
+        }
+    }
+
+    #[test]
+    fn test_sine_is_preserved() {
+        let (input_rate, output_rate) = (44100, 48000);
+        let input = sine(1000.0, input_rate as f32, 44100);
+        let mut resampler = FractionalResampler::new(input_rate, output_rate, 256, ResamplerQuality::High);
+
+        let mut resampled = Vec::new();
+        let mut output = Vec::new();
+        for block in input.chunks(256) {
+            resampler.process(block, &mut output);
+            resampled.extend_from_slice(&output);
+        }
+
+        // The history starts with silence, so output sample n is one sample behind input position n / ratio
+        let delay = 1.0;
+        let max_error = resampled.iter().enumerate().skip(100).take(40000)
+            .map(|(n, &s)| {
+                let t = n as f32 * input_rate as f32 / output_rate as f32 - delay;
+                (s - (2.0 * std::f32::consts::PI * 1000.0 * t / input_rate as f32).sin()).abs()
+            })
+            .fold(0.0, f32::max);
+        assert!(max_error < 0.01, "Max error {max_error}");
+    }
+
+    #[test]
+    fn test_downsampling_removes_aliasing() {
+        // 30kHz is above the Nyquist frequency of the 48kHz output, so must be filtered out
+        let input = sine(30000.0, 96000.0, 9600);
+        let mut resampler = FractionalResampler::new(96000, 48000, 9600, ResamplerQuality::High);
+        let mut output = Vec::new();
+        resampler.process(&input, &mut output);
+
+        let peak = output[100..].iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+        assert!(peak < 0.01, "Aliased peak {peak}");
+    }
+}
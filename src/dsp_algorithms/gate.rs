@@ -0,0 +1,189 @@
+/// Noise gate with separate open/close thresholds and a hold time.
+///
+/// When closed, gain is reduced by `reduction` dB for every dB the signal is below the open threshold.
+#[derive(Debug, Clone)]
+pub struct Gate {
+    sample_rate: f32,
+    open_threshold_db: f32,
+    close_threshold_db: f32,
+    reduction: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    hold_samples: usize,
+
+    open: bool,
+    hold_counter: usize,
+    level: f32,
+    gain: f32,
+}
+
+impl Gate {
+    // Smoothing for level estimation (RMS approximation)
+    const LEVEL_ALPHA: f32 = 0.99;
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut gate = Self {
+            sample_rate,
+            open_threshold_db: -50.0,
+            close_threshold_db: -50.0,
+            reduction: 10.0,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            hold_samples: 0,
+            open: true,
+            hold_counter: 0,
+            level: 0.0,
+            gain: 1.0,
+        };
+        gate.set_attack_release(5.0, 50.0);
+        gate
+    }
+
+    /// The gate closes when the level drops below `threshold_db - hysteresis_db`.
+    pub fn set_thresholds(&mut self, threshold_db: f32, hysteresis_db: f32) {
+        self.open_threshold_db = threshold_db;
+        self.close_threshold_db = threshold_db - hysteresis_db.max(0.0);
+    }
+
+    pub fn set_reduction(&mut self, reduction: f32) {
+        self.reduction = reduction;
+    }
+
+    pub fn set_attack_release(&mut self, attack_ms: f32, release_ms: f32) {
+        // Per sample smoothing coefficients (sample rate independent)
+        self.attack_coeff = (-1.0 / ((attack_ms / 1000.0) * self.sample_rate)).exp();
+        self.release_coeff = (-1.0 / ((release_ms / 1000.0) * self.sample_rate)).exp();
+    }
+
+    pub fn set_hold(&mut self, hold_ms: f32) {
+        self.hold_samples = ((hold_ms.max(0.0) / 1000.0) * self.sample_rate) as usize;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// The gain applied to the last sample
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Returns the gain to apply to the given sample
+    pub fn process(&mut self, sample: f32) -> f32 {
+        self.level = Self::LEVEL_ALPHA * self.level + (1.0 - Self::LEVEL_ALPHA) * (sample * sample);
+        let power_db = 10.0 * self.level.max(1e-12).log10();
+
+        if power_db >= self.open_threshold_db {
+            self.open = true;
+            self.hold_counter = self.hold_samples;
+        } else if power_db < self.close_threshold_db {
+            if self.hold_counter > 0 {
+                self.hold_counter -= 1;
+            } else {
+                self.open = false;
+            }
+        }
+
+        let gain_target = if self.open {
+            1.0
+        } else {
+            let reduction_db = (self.open_threshold_db - power_db).max(0.0) * self.reduction;
+            10f32.powf(-reduction_db / 20.0)
+        };
+
+        // Smoothly approach gain_target using attack/release
+        if gain_target > self.gain {
+            self.gain = self.attack_coeff * (self.gain - gain_target) + gain_target;
+        } else {
+            self.gain = self.release_coeff * (self.gain - gain_target) + gain_target;
+        }
+
+        self.gain
+    }
+
+    pub fn reset(&mut self) {
+        self.open = true;
+        self.hold_counter = 0;
+        self.level = 0.0;
+        self.gain = 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sine wave with a level that alternates between `threshold_db +- swing_db` every `period` seconds
+    fn oscillating_level_signal(threshold_db: f32, swing_db: f32, period: f32, sample_rate: f32, duration: f32) -> Vec<f32> {
+        let num_samples = (sample_rate * duration) as usize;
+        let period_samples = (period * sample_rate) as usize;
+        // Sine power is amplitude^2 / 2
+        let amplitude = |db: f32| (2.0 * 10f32.powf(db / 10.0)).sqrt();
+        let high = amplitude(threshold_db + swing_db);
+        let low = amplitude(threshold_db - swing_db);
+
+        (0..num_samples)
+            .map(|i| {
+                let t = i as f32 / sample_rate;
+                let amplitude = if (i / period_samples) % 2 == 0 { high } else { low };
+                amplitude * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+            })
+            .collect()
+    }
+
+    fn count_state_changes(gate: &mut Gate, signal: &[f32]) -> usize {
+        let mut changes = 0;
+        let mut was_open = gate.is_open();
+        for &s in signal {
+            gate.process(s);
+            if gate.is_open() != was_open {
+                changes += 1;
+                was_open = gate.is_open();
+            }
+        }
+        changes
+    }
+
+    #[test]
+    fn test_hysteresis_prevents_chatter() {
+        let sample_rate = 48000.0;
+        let threshold_db = -40.0;
+        let signal = oscillating_level_signal(threshold_db, 3.0, 0.01, sample_rate, 1.0);
+
+        let mut chattering_gate = Gate::new(sample_rate);
+        chattering_gate.set_thresholds(threshold_db, 0.0);
+        let chattering_changes = count_state_changes(&mut chattering_gate, &signal);
+        assert!(chattering_changes > 20, "Expected gate without hysteresis to chatter, got {} changes", chattering_changes);
+
+        let mut gate = Gate::new(sample_rate);
+        gate.set_thresholds(threshold_db, 10.0);
+        let changes = count_state_changes(&mut gate, &signal);
+        assert!(changes <= 2, "Gate with hysteresis changed state {} times", changes);
+    }
+
+    #[test]
+    fn test_hold_keeps_gate_open() {
+        let sample_rate = 48000.0;
+        let mut gate = Gate::new(sample_rate);
+        gate.set_thresholds(-40.0, 0.0);
+        gate.set_hold(100.0);
+
+        // Loud signal opens the gate
+        for i in 0..4800 {
+            gate.process((2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate).sin());
+        }
+        assert!(gate.is_open());
+
+        // 50ms of silence, gate is still held open
+        for _ in 0..2400 {
+            gate.process(0.0);
+        }
+        assert!(gate.is_open());
+
+        // Another 100ms of silence, hold has expired
+        for _ in 0..4800 {
+            gate.process(0.0);
+        }
+        assert!(!gate.is_open());
+    }
+}
@@ -57,6 +57,24 @@ impl IRConvolver {
         }
     }
 
+    /// Gain that scales the IR to unit energy, so that white noise keeps the same RMS level through it.
+    ///
+    /// Used to match the loudness of different IRs. Includes the scaling applied to IRs with a high frequency gain.
+    pub fn energy_normalization_gain(&self) -> f32 {
+        // Parseval's theorem, with the bins other than DC and Nyquist counted twice for the missing half of the spectrum
+        let last = self.ir_freq.len() - 1;
+        let spectrum_energy: f32 = self.ir_freq.iter().enumerate()
+            .map(|(i, bin)| if i == 0 || i == last { bin.norm_sqr() } else { 2.0 * bin.norm_sqr() })
+            .sum();
+        let energy = spectrum_energy / self.fft_size as f32;
+
+        if energy > 0.0 {
+            1.0 / energy.sqrt()
+        } else {
+            1.0
+        }
+    }
+
     pub fn process(&mut self, mut buffer: &mut [f32]) {
         if buffer.len() > self.block_size {
             tracing::warn!("IRConvolver: buffer size exceeds maximum block size.");
@@ -192,4 +210,39 @@ mod tests {
             1e-6
         );
     }
+
+    #[test]
+    fn test_energy_normalization_matches_levels() {
+        // A quiet decaying IR and a loud IR with a different shape
+        let quiet_ir: Vec<f32> = (0..256).map(|i| 0.005 * (-(i as f32) / 30.0).exp() * if i % 2 == 0 { 1.0 } else { 0.6 }).collect();
+        let loud_ir: Vec<f32> = (0..512).map(|i| (-(i as f32) / 20.0).exp() * (i as f32 * 0.7).cos()).collect();
+
+        // White noise reference signal
+        let mut rng_state = 0x1234_5678_u32;
+        let reference: Vec<f32> = (0..48000).map(|_| {
+            rng_state ^= rng_state << 13;
+            rng_state ^= rng_state >> 17;
+            rng_state ^= rng_state << 5;
+            rng_state as f32 / u32::MAX as f32 * 2.0 - 1.0
+        }).collect();
+
+        let output_rms = |ir: &[f32], normalize: bool| {
+            let block_size = 128;
+            let mut convolver = IRConvolver::new(ir, block_size);
+            let gain = if normalize { convolver.energy_normalization_gain() } else { 1.0 };
+
+            let mut output = reference.clone();
+            for block in output.chunks_mut(block_size) {
+                convolver.process(block);
+            }
+            (output.iter().map(|s| (s * gain).powi(2)).sum::<f32>() / output.len() as f32).sqrt()
+        };
+
+        let (quiet_rms, loud_rms) = (output_rms(&quiet_ir, false), output_rms(&loud_ir, false));
+        assert!(loud_rms / quiet_rms > 2.0, "IRs should start at different levels: {quiet_rms} vs {loud_rms}");
+
+        let (quiet_rms, loud_rms) = (output_rms(&quiet_ir, true), output_rms(&loud_ir, true));
+        let ratio_db = 20.0 * (loud_rms / quiet_rms).log10();
+        assert!(ratio_db.abs() < 0.5, "Normalized IRs differ by {ratio_db} dB");
+    }
 }
\ No newline at end of file
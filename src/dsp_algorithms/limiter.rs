@@ -0,0 +1,118 @@
+/// A lookahead-free peak limiter with a soft knee.
+///
+/// The peak envelope has an instant attack, so the output never exceeds the threshold,
+/// and an exponential release to avoid pumping once the peak has passed.
+#[derive(Debug, Clone)]
+pub struct Limiter {
+    threshold_db: f32,
+    knee_db: f32,
+    release_coeff: f32,
+    envelope: f32,
+}
+
+impl Limiter {
+    pub fn new(threshold_db: f32, knee_db: f32, release_ms: f32, sample_rate: f32) -> Self {
+        let release_samples = (release_ms * sample_rate) / 1000.0;
+        let release_coeff = if release_samples > 0.0 {
+            (-1.0 / release_samples).exp()
+        } else {
+            0.0
+        };
+
+        Self {
+            threshold_db,
+            knee_db: knee_db.max(0.0),
+            release_coeff,
+            envelope: 0.0,
+        }
+    }
+
+    /// Gain (in dB) to apply to a signal at the given level (in dB).
+    fn gain_db(&self, level_db: f32) -> f32 {
+        let overshoot = level_db - self.threshold_db;
+
+        if 2.0 * overshoot < -self.knee_db {
+            0.0
+        } else if self.knee_db > 0.0 && 2.0 * overshoot.abs() <= self.knee_db {
+            // Infinite ratio soft knee
+            let knee_position = overshoot + self.knee_db / 2.0;
+            -(knee_position * knee_position) / (2.0 * self.knee_db)
+        } else {
+            -overshoot
+        }
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let level = sample.abs();
+        self.envelope = level.max(self.envelope * self.release_coeff);
+
+        if self.envelope <= f32::EPSILON {
+            return sample;
+        }
+
+        let envelope_db = 20.0 * self.envelope.log10();
+        let gain = 10.0_f32.powf(self.gain_db(envelope_db) / 20.0);
+        sample * gain
+    }
+
+    pub fn process_buffer(&mut self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_wave(amplitude: f32, frequency: f32, sample_rate: f32, duration: f32) -> Vec<f32> {
+        let num_samples = (sample_rate * duration) as usize;
+        (0..num_samples)
+            .map(|i| {
+                let t = i as f32 / sample_rate;
+                amplitude * (2.0 * std::f32::consts::PI * frequency * t).sin()
+            })
+            .collect()
+    }
+
+    fn peak(signal: &[f32]) -> f32 {
+        signal.iter().fold(0.0, |acc, &s| acc.max(s.abs()))
+    }
+
+    #[test]
+    fn test_limits_hot_signal() {
+        let sample_rate = 48000.0;
+        let mut limiter = Limiter::new(-1.0, 6.0, 50.0, sample_rate);
+
+        // +6dB
+        let amplitude = 10.0_f32.powf(6.0 / 20.0);
+        let mut signal = sine_wave(amplitude, 440.0, sample_rate, 1.0);
+        limiter.process_buffer(&mut signal);
+
+        let output_peak = peak(&signal);
+        let output_peak_db = 20.0 * output_peak.log10();
+        assert!(output_peak <= 1.0, "Output peak {} exceeds 0 dBFS", output_peak);
+        assert!(output_peak_db > -1.5, "Output peak {}dB is over limited", output_peak_db);
+    }
+
+    #[test]
+    fn test_quiet_signal_unchanged() {
+        let sample_rate = 48000.0;
+        let mut limiter = Limiter::new(-1.0, 6.0, 50.0, sample_rate);
+
+        // -12dB is below the knee
+        let amplitude = 10.0_f32.powf(-12.0 / 20.0);
+        let original = sine_wave(amplitude, 440.0, sample_rate, 0.5);
+        let mut signal = original.clone();
+        limiter.process_buffer(&mut signal);
+
+        for (a, b) in original.iter().zip(signal.iter()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::f32::consts::FRAC_PI_2;
+
+/// Gains for the dry and wet signals of an equal-power crossfade.
+///
+/// A mix of 0.0 is fully dry, 1.0 is fully wet.
+pub fn equal_power_gains(mix: f32) -> (f32, f32) {
+    let angle = mix.clamp(0.0, 1.0) * FRAC_PI_2;
+    (angle.cos(), angle.sin())
+}
+
+/// Mix the wet buffer into the dry buffer with an equal-power crossfade.
+pub fn equal_power_mix(dry: &mut [f32], wet: &[f32], mix: f32) {
+    let (dry_gain, wet_gain) = equal_power_gains(mix);
+    for (d, w) in dry.iter_mut().zip(wet.iter()) {
+        *d = *d * dry_gain + *w * wet_gain;
+    }
+}
+
+/// Mix the wet buffer into the dry buffer with a linear crossfade.
+///
+/// For correlated signals (e.g. a processed copy of the dry signal), where an equal-power crossfade would be about 3dB louder in the middle.
+pub fn linear_mix(dry: &mut [f32], wet: &[f32], mix: f32) {
+    let mix = mix.clamp(0.0, 1.0);
+    for (d, w) in dry.iter_mut().zip(wet.iter()) {
+        *d = *d * (1.0 - mix) + *w * mix;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_power_gains() {
+        let (dry, wet) = equal_power_gains(0.0);
+        assert!((dry - 1.0).abs() < 1e-6 && wet.abs() < 1e-6);
+
+        let (dry, wet) = equal_power_gains(1.0);
+        assert!(dry.abs() < 1e-6 && (wet - 1.0).abs() < 1e-6);
+
+        for i in 0..=10 {
+            let (dry, wet) = equal_power_gains(i as f32 / 10.0);
+            assert!((dry * dry + wet * wet - 1.0).abs() < 1e-5, "Power not preserved at mix {}", i as f32 / 10.0);
+        }
+    }
+
+    #[test]
+    fn test_equal_power_mix() {
+        let mut dry = vec![1.0; 4];
+        let wet = vec![-1.0; 4];
+        equal_power_mix(&mut dry, &wet, 0.5);
+
+        // Equal gains cancel out opposite signals
+        for s in dry {
+            assert!(s.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_linear_mix_keeps_level_of_same_signal() {
+        let mut dry = vec![0.5; 4];
+        let wet = vec![0.5; 4];
+        linear_mix(&mut dry, &wet, 0.5);
+
+        for s in dry {
+            assert!((s - 0.5).abs() < 1e-6);
+        }
+    }
+}
@@ -7,7 +7,36 @@ pub mod yin;
 pub mod impluse_response;
 pub mod frequency_analysis;
 pub mod resampler;
+pub mod fractional_resampler;
 pub mod moving_bandpass;
+pub mod limiter;
+pub mod mixer;
+pub mod gate;
+pub mod crossover;
+pub mod rotary_speaker;
+pub mod pitch_correction;
+pub mod onset_detector;
+pub mod auto_swell;
+pub mod test_signal;
+pub mod denormal;
+pub mod smoothed_param;
+pub mod sample_delay;
+pub mod stretch;
+
+/// Lowest level returned by [`linear_to_db`], for silence
+pub const MIN_DB: f32 = -120.0;
+
+/// Convert a level in dB to a linear amplitude
+#[inline]
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Convert a linear amplitude to dB, with silence at [`MIN_DB`]
+#[inline]
+pub fn linear_to_db(linear: f32) -> f32 {
+    (20.0 * linear.abs().log10()).max(MIN_DB)
+}
 
 pub fn hann_window(size: usize) -> Vec<f32> {
     let mut window = vec![0.0; size];
@@ -0,0 +1,60 @@
+/// Detects note onsets (transients) with a fast and slow envelope follower.
+///
+/// An onset is when the fast envelope rises well above the slow envelope. Another onset can't be detected until the
+/// fast envelope has fallen back towards the slow envelope, so a single attack is only detected once.
+#[derive(Debug, Clone)]
+pub struct OnsetDetector {
+    fast_attack_coeff: f32,
+    fast_release_coeff: f32,
+    slow_coeff: f32,
+    fast_envelope: f32,
+    slow_envelope: f32,
+    armed: bool,
+}
+
+impl OnsetDetector {
+    // The fast envelope must be this many times the slow envelope for an onset
+    const ONSET_RATIO: f32 = 2.0;
+    // The fast envelope must fall back to this many times the slow envelope before another onset
+    const REARM_RATIO: f32 = 1.2;
+    // Envelope level below which onsets are ignored (-50dB)
+    const ONSET_THRESHOLD: f32 = 0.003;
+
+    pub fn new(sample_rate: f32) -> Self {
+        let coeff = |ms: f32| (-1.0 / ((ms / 1000.0) * sample_rate)).exp();
+        Self {
+            fast_attack_coeff: coeff(1.0),
+            fast_release_coeff: coeff(20.0),
+            slow_coeff: coeff(100.0),
+            fast_envelope: 0.0,
+            slow_envelope: 0.0,
+            armed: true,
+        }
+    }
+
+    /// Returns true if there is an onset at the given sample
+    #[inline]
+    pub fn process(&mut self, sample: f32) -> bool {
+        let level = sample.abs();
+        let fast_coeff = if level > self.fast_envelope { self.fast_attack_coeff } else { self.fast_release_coeff };
+        self.fast_envelope = fast_coeff * (self.fast_envelope - level) + level;
+        self.slow_envelope = self.slow_coeff * (self.slow_envelope - level) + level;
+
+        if self.armed {
+            if self.fast_envelope > Self::ONSET_THRESHOLD && self.fast_envelope > self.slow_envelope * Self::ONSET_RATIO {
+                self.armed = false;
+                return true;
+            }
+        } else if self.fast_envelope < self.slow_envelope * Self::REARM_RATIO {
+            self.armed = true;
+        }
+
+        false
+    }
+
+    pub fn reset(&mut self) {
+        self.fast_envelope = 0.0;
+        self.slow_envelope = 0.0;
+        self.armed = true;
+    }
+}
@@ -49,6 +49,16 @@ impl Oscillator {
         }
     }
 
+    /// Restart the cycle from the phase offset, e.g. to keep the modulation in time with a note
+    pub fn reset_phase(&mut self) {
+        match self {
+            Oscillator::Sine(sine) => sine.phase = OrderedFloat(0.0),
+            Oscillator::Square(square) => square.phase = OrderedFloat(0.0),
+            Oscillator::Sawtooth(sawtooth) => sawtooth.phase = OrderedFloat(0.0),
+            Oscillator::Triangle(triangle) => triangle.phase = OrderedFloat(0.0)
+        }
+    }
+
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         match self {
             Oscillator::Sine(sine) => sine.sample_rate = OrderedFloat(sample_rate),
@@ -0,0 +1,82 @@
+/// Semitones from the key note in each scale
+const SCALES: [&[i16]; 5] = [
+    // Chromatic
+    &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+    // Major
+    &[0, 2, 4, 5, 7, 9, 11],
+    // Minor
+    &[0, 2, 3, 5, 7, 8, 10],
+    // Major Pentatonic
+    &[0, 2, 4, 7, 9],
+    // Minor Pentatonic
+    &[0, 3, 5, 7, 10],
+];
+
+pub const SCALE_NAMES: [&str; 5] = ["Chromatic", "Major", "Minor", "Major Pentatonic", "Minor Pentatonic"];
+pub const KEY_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// Fractional MIDI note number of a frequency
+pub fn freq_to_midi(freq: f32) -> f32 {
+    69.0 + 12.0 * (freq / 440.0).log2()
+}
+
+/// Nearest MIDI note to `midi_note` that is in the given scale.
+///
+/// `key` is 0-11 starting from C, `scale` indexes into [`SCALE_NAMES`].
+pub fn nearest_scale_note(midi_note: f32, key: i16, scale: usize) -> f32 {
+    let scale = SCALES.get(scale).copied().unwrap_or(SCALES[0]);
+    let key = key.rem_euclid(12) as f32;
+
+    // Check the scale notes in the octaves either side of the note
+    let octave = ((midi_note - key) / 12.0).floor();
+    let mut nearest = midi_note.round();
+    let mut nearest_distance = f32::MAX;
+    for octave_offset in -1..=1 {
+        let octave_start = key + (octave + octave_offset as f32) * 12.0;
+        for &degree in scale {
+            let note = octave_start + degree as f32;
+            let distance = (note - midi_note).abs();
+            if distance < nearest_distance {
+                nearest = note;
+                nearest_distance = distance;
+            }
+        }
+    }
+
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freq_to_midi() {
+        assert!((freq_to_midi(440.0) - 69.0).abs() < 1e-4);
+        assert!((freq_to_midi(261.63) - 60.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_chromatic_rounds_to_nearest_semitone() {
+        assert_eq!(nearest_scale_note(60.4, 0, 0), 60.0);
+        assert_eq!(nearest_scale_note(60.6, 0, 0), 61.0);
+    }
+
+    #[test]
+    fn test_snaps_to_scale() {
+        // C# is not in C major, so snaps to C or D
+        let snapped = nearest_scale_note(61.2, 0, 1);
+        assert_eq!(snapped, 62.0);
+        let snapped = nearest_scale_note(60.8, 0, 1);
+        assert_eq!(snapped, 60.0);
+
+        // B is in C major
+        assert_eq!(nearest_scale_note(70.9, 0, 1), 71.0);
+        // Wraps into the next octave
+        assert_eq!(nearest_scale_note(71.8, 0, 1), 72.0);
+
+        // A minor pentatonic (A C D E G), G# snaps to G or A
+        assert_eq!(nearest_scale_note(68.4, 9, 4), 69.0);
+        assert_eq!(nearest_scale_note(67.6, 9, 4), 67.0);
+    }
+}
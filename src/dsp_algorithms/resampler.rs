@@ -1,3 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// Trade off between the CPU cost of resampling and how well it keeps the passband flat and removes images and aliasing
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResamplerQuality {
+    /// Linear interpolation. Cheapest, but rolls off the highs and leaves images.
+    Fast,
+    /// Short windowed sinc filter
+    Medium,
+    /// Long windowed sinc filter
+    #[default]
+    High
+}
+
+impl ResamplerQuality {
+    pub fn half_band_filter(&self) -> HalfBandFilter {
+        match self {
+            ResamplerQuality::Fast => HalfBandFilter::linear(),
+            ResamplerQuality::Medium => HalfBandFilter::new(31),
+            ResamplerQuality::High => HalfBandFilter::new(63)
+        }
+    }
+}
+
+impl std::fmt::Display for ResamplerQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::str::FromStr for ResamplerQuality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fast" => Ok(ResamplerQuality::Fast),
+            "medium" => Ok(ResamplerQuality::Medium),
+            "high" => Ok(ResamplerQuality::High),
+            _ => Err(format!("Invalid resampler quality: {s}, expected fast, medium or high"))
+        }
+    }
+}
+
 /// A polyphase half band resampler for upsampling and downsampling by powers of two.
 /// 
 /// Each resampler should be used for either upsampling or downsampling.
@@ -8,10 +51,10 @@ pub struct Resampler {
 }
 
 impl Resampler {
-    pub fn new(passes: usize, max_block: usize) -> Self {
+    pub fn new(passes: usize, max_block: usize, quality: ResamplerQuality) -> Self {
         let mut stages = Vec::with_capacity(passes);
         for _ in 0..passes {
-            stages.push(HalfBandFilter::new(63));
+            stages.push(quality.half_band_filter());
         }
         // scratch buffer must hold the max expanded size
         let scratch_a = vec![0.0; max_block << passes];
@@ -89,41 +132,47 @@ impl Resampler {
     }
 }
 
+/// A half band low pass filter (cutoff at a quarter of the higher sample rate) that upsamples or downsamples by 2.
+///
+/// Every other tap of a half band filter is zero, apart from the center tap,
+/// so each output sample only needs the even taps or the center tap.
 pub struct HalfBandFilter {
-    h_even: Vec<f32>,
+    // Taps at even indices, which are the only non-zero taps other than the center tap
+    even_taps: Vec<f32>,
     center_tap: f32,
+    // Delay, in samples at the lower sample rate, of the center tap
+    center_delay: usize,
+    // Samples at the lower sample rate, newest at `pos`. When downsampling, `delay` holds the odd samples and `center_delay_line` the even samples.
     delay: Vec<f32>,
+    center_delay_line: Vec<f32>,
     pos: usize,
-    len: usize,
-    mid: usize,
 }
 
 impl HalfBandFilter {
+    /// Windowed sinc half band filter. The length must be 4n + 3, so the center tap has an odd index.
     pub fn new(length: usize) -> Self {
-        assert!(length % 2 == 1, "length must be odd");
+        assert!(length % 4 == 3, "length must be 4n + 3");
         let mid = length / 2;
         let mut taps = vec![0.0f32; length];
 
-        // ideal halfband prototype
-        for n in 0..length {
+        // Ideal half band prototype, zero at even offsets from the center
+        for (n, tap) in taps.iter_mut().enumerate() {
             let k = n as isize - mid as isize;
             if k == 0 {
-                taps[n] = 0.5;
-            } else if k % 2 == 0 {
+                *tap = 0.5;
+            } else if k % 2 != 0 {
                 let kf = k as f32;
-                taps[n] = (std::f32::consts::PI * 0.5 * kf).sin() /
-                          (std::f32::consts::PI * kf);
-            } else {
-                taps[n] = 0.0; // exact zeros for odd indices
+                *tap = (std::f32::consts::PI * 0.5 * kf).sin() / (std::f32::consts::PI * kf);
             }
         }
 
-        // Blackman window
-        for n in 0..length {
+        // Blackman window, over the taps and the zero either side
+        for (n, tap) in taps.iter_mut().enumerate() {
+            let x = (n + 1) as f32 / (length + 1) as f32;
             let w = 0.42
-                - 0.5 * ((2.0 * std::f32::consts::PI * n as f32) / (length as f32 - 1.0)).cos()
-                + 0.08 * ((4.0 * std::f32::consts::PI * n as f32) / (length as f32 - 1.0)).cos();
-            taps[n] *= w;
+                - 0.5 * (2.0 * std::f32::consts::PI * x).cos()
+                + 0.08 * (4.0 * std::f32::consts::PI * x).cos();
+            *tap *= w;
         }
 
         // Normalize DC gain
@@ -133,52 +182,50 @@ impl HalfBandFilter {
         Self::new_from_taps(taps)
     }
 
+    /// Linear interpolation when upsampling, and a triangle filter when downsampling
+    pub fn linear() -> Self {
+        Self::new_from_taps(vec![0.25, 0.5, 0.25])
+    }
+
     pub fn new_from_taps(taps: Vec<f32>) -> Self {
         let len = taps.len();
-        assert!(len % 2 == 1, "taps length must be odd");
+        assert!(len % 4 == 3, "taps length must be 4n + 3");
         let mid = len / 2;
 
-        // keep only nonzero even taps
-        let mut h_even = Vec::new();
-        for (i, &c) in taps.iter().enumerate() {
-            if i % 2 == 0 && i != mid {
-                h_even.push(c);
-            }
-        }
+        let even_taps: Vec<f32> = taps.iter().step_by(2).copied().collect();
+        let center_delay = (mid - 1) / 2;
 
-        let center_tap = taps[mid];
-        let delay = vec![0.0f32; len];
+        Self {
+            delay: vec![0.0; even_taps.len()],
+            center_delay_line: vec![0.0; even_taps.len()],
+            even_taps,
+            center_tap: taps[mid],
+            center_delay,
+            pos: 0,
+        }
+    }
 
-        Self { h_even, center_tap, delay, pos: 0, len, mid }
+    /// Index in the delay lines of the sample `age` samples before the newest
+    fn idx(&self, age: usize) -> usize {
+        (self.pos + self.delay.len() - age) % self.delay.len()
     }
 
-    fn idx(&self, base: usize, offset: usize) -> usize {
-        if base >= offset { base - offset } else { base + self.len - offset }
+    fn even_taps_output(&self) -> f32 {
+        self.even_taps.iter().enumerate()
+            .map(|(age, &c)| c * self.delay[self.idx(age)])
+            .sum()
     }
 
     pub fn upsample(&mut self, input: &[f32], output: &mut [f32]) {
         assert_eq!(output.len(), input.len() * 2);
 
         for (i, &x) in input.iter().enumerate() {
+            self.pos = (self.pos + 1) % self.delay.len();
             self.delay[self.pos] = x;
-            let base = if self.pos == 0 { self.len - 1 } else { self.pos - 1 };
-
-            // y[2n] = convolution with even taps
-            let mut even_out = 0.0;
-            for (k, &c) in self.h_even.iter().enumerate() {
-                let d1 = self.idx(base, 2*k);
-                let d2 = self.idx(base, self.len - 1 - 2*k);
-                even_out += c * (self.delay[d1] + self.delay[d2]);
-            }
-            even_out += self.center_tap * self.delay[self.idx(base, self.mid)];
-
-            // y[2n+1] = center_tap * newest sample
-            let odd_out = self.center_tap * self.delay[self.pos];
-
-            output[2*i]   = even_out;
-            output[2*i+1] = odd_out;
 
-            self.pos = (self.pos + 1) % self.len;
+            // Zero stuffing halves the level, so the filter has a gain of 2
+            output[2*i] = 2.0 * self.even_taps_output();
+            output[2*i+1] = 2.0 * self.center_tap * self.delay[self.idx(self.center_delay)];
         }
     }
 
@@ -186,22 +233,11 @@ impl HalfBandFilter {
         assert_eq!(input.len(), output.len() * 2);
 
         for (i, chunk) in input.chunks_exact(2).enumerate() {
-            self.delay[self.pos] = chunk[0];
-            self.pos = (self.pos + 1) % self.len;
+            self.pos = (self.pos + 1) % self.delay.len();
+            self.center_delay_line[self.pos] = chunk[0];
             self.delay[self.pos] = chunk[1];
-            self.pos = (self.pos + 1) % self.len;
-
-            let base = if self.pos == 0 { self.len - 1 } else { self.pos - 1 };
-
-            let mut acc = 0.0;
-            for (k, &c) in self.h_even.iter().enumerate() {
-                let d1 = self.idx(base, 2*k);
-                let d2 = self.idx(base, self.len - 1 - 2*k);
-                acc += c * (self.delay[d1] + self.delay[d2]);
-            }
-            acc += self.center_tap * self.delay[self.idx(base, self.mid)];
 
-            output[i] = acc;
+            output[i] = self.even_taps_output() + self.center_tap * self.center_delay_line[self.idx(self.center_delay)];
         }
     }
 }
@@ -348,9 +384,68 @@ mod tests {
         down_writer.finalize().unwrap();
     }
 
+    /// Amplitude of the sine at `frequency` in `samples`, skipping the start where the filters are filling
+    fn sine_amplitude(samples: &[f32], frequency: f32, sample_rate: f32) -> f32 {
+        let start = samples.len() / 4;
+        let (mut sin_sum, mut cos_sum) = (0.0, 0.0);
+        for (i, &sample) in samples.iter().enumerate().skip(start) {
+            let phase = 2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate;
+            sin_sum += sample * phase.sin();
+            cos_sum += sample * phase.cos();
+        }
+        2.0 * (sin_sum * sin_sum + cos_sum * cos_sum).sqrt() / (samples.len() - start) as f32
+    }
+
+    /// Largest change in level in dB of a stepped sweep over the passband, when upsampled then downsampled
+    fn passband_deviation_db(quality: ResamplerQuality) -> f32 {
+        let sample_rate = 48000.0;
+        let length = 4800;
+
+        (1..=16).map(|khz| {
+            let frequency = khz as f32 * 1000.0;
+            let input: Vec<f32> = (0..length).map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate).sin()).collect();
+
+            let mut upsampler = Resampler::new(2, length, quality);
+            let mut downsampler = Resampler::new(2, length, quality);
+            let mut upsampled = vec![0.0; upsampler.upsample_output_buffer_size(length)];
+            upsampler.upsample(&input, &mut upsampled);
+            let mut output = vec![0.0; length];
+            downsampler.downsample(&upsampled, &mut output);
+
+            (20.0 * sine_amplitude(&output, frequency, sample_rate).log10()).abs()
+        }).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_passband_flatness_by_quality() {
+        let fast = passband_deviation_db(ResamplerQuality::Fast);
+        let medium = passband_deviation_db(ResamplerQuality::Medium);
+        let high = passband_deviation_db(ResamplerQuality::High);
+
+        assert!(high < 0.05, "High quality deviates by {high} dB");
+        assert!(medium < 0.5, "Medium quality deviates by {medium} dB");
+        // Linear interpolation rolls off the highs
+        assert!(fast > 3.0, "Fast quality deviates by {fast} dB");
+        assert!(high <= medium && medium < fast);
+    }
+
+    #[test]
+    fn test_upsampling_removes_images() {
+        // Upsampling a 15kHz sine from 48kHz leaves an image at 33kHz, which the filter must remove
+        let input: Vec<f32> = (0..4800).map(|i| (2.0 * std::f32::consts::PI * 15000.0 * i as f32 / 48000.0).sin()).collect();
+        let mut upsampler = Resampler::new(1, input.len(), ResamplerQuality::High);
+        let mut upsampled = vec![0.0; upsampler.upsample_output_buffer_size(input.len())];
+        upsampler.upsample(&input, &mut upsampled);
+
+        let signal = sine_amplitude(&upsampled, 15000.0, 96000.0);
+        let image = sine_amplitude(&upsampled, 33000.0, 96000.0);
+        assert!((signal - 1.0).abs() < 0.01, "Signal amplitude is {signal}");
+        assert!(image < 0.001, "Image amplitude is {image}");
+    }
+
     #[test]
     fn test_resampler() {
-        let mut resampler = Resampler::new(2, 100);
+        let mut resampler = Resampler::new(2, 100, ResamplerQuality::High);
 
         // Enter wav file to upsample
         print!("Enter a path to upsample: ");
@@ -364,7 +459,7 @@ mod tests {
 
     #[test]
     fn test_resampler_block() {
-        let mut resampler = Resampler::new(1, 100);
+        let mut resampler = Resampler::new(1, 100, ResamplerQuality::High);
 
         // Enter wav file to upsample
         print!("Enter a path to upsample in blocks: ");
@@ -0,0 +1,156 @@
+use super::{crossover::LinkwitzRileyCrossover, oscillator::{Oscillator, Sine}, variable_delay::VariableDelayLine};
+
+/// Frequency the signal is split at between the drum (low) and horn (high)
+const CROSSOVER_FREQ: f32 = 800.0;
+/// Delay at the centre of the doppler swing
+const BASE_DELAY_MS: f32 = 1.0;
+
+/// A single rotating speaker, modelled as a doppler shift (modulated delay)
+/// and amplitude modulation from the same rotation.
+#[derive(Clone)]
+struct Rotor {
+    oscillator: Oscillator,
+    delay: VariableDelayLine,
+    // f64 as the per sample change is too small for f32 near the end of slow ramps
+    speed: f64,
+    target_speed: f64,
+    ramp_coeff: f64,
+    doppler_samples: f32,
+    base_delay_samples: f32,
+    am_depth: f32,
+    sample_rate: f32,
+}
+
+impl Rotor {
+    fn new(sample_rate: f32, speed: f32, doppler_ms: f32, am_depth: f32, phase_offset: f32) -> Self {
+        let base_delay_samples = (BASE_DELAY_MS / 1000.0) * sample_rate;
+        let doppler_samples = (doppler_ms / 1000.0) * sample_rate;
+
+        Self {
+            oscillator: Oscillator::Sine(Sine::new(sample_rate, speed, phase_offset, 0.0)),
+            delay: VariableDelayLine::new((base_delay_samples + doppler_samples).ceil() as usize + 1),
+            speed: speed as f64,
+            target_speed: speed as f64,
+            ramp_coeff: 0.0,
+            doppler_samples,
+            base_delay_samples,
+            am_depth,
+            sample_rate,
+        }
+    }
+
+    fn set_ramp_time(&mut self, ramp_s: f32) {
+        self.ramp_coeff = (-1.0 / (ramp_s.max(0.01) as f64 * self.sample_rate as f64)).exp();
+    }
+
+    fn process(&mut self, sample: f32, depth: f32) -> f32 {
+        // Exponential spin up/down towards the target speed
+        self.speed = self.ramp_coeff * (self.speed - self.target_speed) + self.target_speed;
+        self.oscillator.set_frequency(self.speed as f32);
+        let position = self.oscillator.next().unwrap();
+
+        self.delay.buffer.pop_front();
+        self.delay.buffer.push_back(sample);
+
+        // Closest to the listener when position is 1, so shortest delay and loudest
+        let delay = self.base_delay_samples - position * self.doppler_samples * depth;
+        let gain = 1.0 - self.am_depth * depth * (1.0 - position) * 0.5;
+        self.delay.get_sample(delay) * gain
+    }
+}
+
+/// Leslie style rotary speaker, with a horn for the high frequencies and a drum for the lows
+#[derive(Clone)]
+pub struct RotarySpeaker {
+    crossover: LinkwitzRileyCrossover,
+    horn: Rotor,
+    drum: Rotor,
+    pub depth: f32,
+}
+
+impl RotarySpeaker {
+    pub fn new(horn_speed: f32, drum_speed: f32, sample_rate: f32) -> Self {
+        Self {
+            crossover: LinkwitzRileyCrossover::new(CROSSOVER_FREQ, sample_rate),
+            // The horn has a larger radius than the drum, so more doppler, and is more directional
+            horn: Rotor::new(sample_rate, horn_speed, 0.5, 0.6, 0.0),
+            drum: Rotor::new(sample_rate, drum_speed, 0.2, 0.3, 0.25),
+            depth: 1.0,
+        }
+    }
+
+    /// Set the speeds (Hz) the rotors ramp towards
+    pub fn set_target_speeds(&mut self, horn_speed: f32, drum_speed: f32) {
+        self.horn.target_speed = horn_speed as f64;
+        self.drum.target_speed = drum_speed as f64;
+    }
+
+    /// Set the time constants (seconds) of the spin up/down ramps
+    pub fn set_ramp_times(&mut self, horn_ramp_s: f32, drum_ramp_s: f32) {
+        self.horn.set_ramp_time(horn_ramp_s);
+        self.drum.set_ramp_time(drum_ramp_s);
+    }
+
+    /// Current (horn, drum) speeds in Hz
+    pub fn speeds(&self) -> (f32, f32) {
+        (self.horn.speed as f32, self.drum.speed as f32)
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let (low, high) = self.crossover.process(sample);
+        self.drum.process(low, self.depth) + self.horn.process(high, self.depth)
+    }
+
+    pub fn reset(&mut self) {
+        self.crossover.reset();
+        self.horn.delay.reset();
+        self.drum.delay.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_ramps_to_target() {
+        let sample_rate = 48000.0;
+        let mut rotary = RotarySpeaker::new(0.8, 0.7, sample_rate);
+        rotary.set_ramp_times(0.5, 2.0);
+        rotary.set_target_speeds(6.7, 5.8);
+
+        // After one horn time constant, the horn is ~63% of the way there and ahead of the drum
+        for _ in 0..(0.5 * sample_rate) as usize {
+            rotary.process(0.0);
+        }
+        let (horn, drum) = rotary.speeds();
+        let horn_progress = (horn - 0.8) / (6.7 - 0.8);
+        let drum_progress = (drum - 0.7) / (5.8 - 0.7);
+        assert!((horn_progress - 0.632).abs() < 0.01, "Horn progress {}", horn_progress);
+        assert!(drum_progress < horn_progress);
+
+        // Both settle at the target speeds
+        for _ in 0..(20.0 * sample_rate) as usize {
+            rotary.process(0.0);
+        }
+        let (horn, drum) = rotary.speeds();
+        assert!((horn - 6.7).abs() < 0.01);
+        assert!((drum - 5.8).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_no_depth_passes_signal() {
+        let sample_rate = 48000.0;
+        let mut rotary = RotarySpeaker::new(6.7, 5.8, sample_rate);
+        rotary.depth = 0.0;
+
+        let input: Vec<f32> = (0..24000)
+            .map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate).sin())
+            .collect();
+        let output: Vec<f32> = input.iter().map(|&s| rotary.process(s)).collect();
+
+        let rms = |s: &[f32]| (s.iter().map(|x| x * x).sum::<f32>() / s.len() as f32).sqrt();
+        let difference_db = 20.0 * (rms(&output[12000..]) / rms(&input[12000..])).log10();
+        assert!(difference_db.abs() < 0.1, "Output level differs by {}dB", difference_db);
+    }
+}
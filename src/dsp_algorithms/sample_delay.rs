@@ -0,0 +1,70 @@
+/// Delays a signal by a whole number of samples, up to a maximum set when created.
+///
+/// The buffer is only allocated in [`SampleDelay::new`], so the delay can be changed on the audio thread.
+#[derive(Clone, Default)]
+pub struct SampleDelay {
+    buffer: Vec<f32>,
+    write_index: usize,
+    delay: usize,
+}
+
+impl SampleDelay {
+    pub fn new(max_delay: usize) -> Self {
+        SampleDelay {
+            buffer: vec![0.0; max_delay + 1],
+            write_index: 0,
+            delay: 0,
+        }
+    }
+
+    pub fn max_delay(&self) -> usize {
+        self.buffer.len().saturating_sub(1)
+    }
+
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    /// Set the delay in samples, clamped to the maximum delay
+    pub fn set_delay(&mut self, delay: usize) {
+        self.delay = delay.min(self.max_delay());
+    }
+
+    #[inline]
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let len = self.buffer.len();
+        if len == 0 {
+            return sample;
+        }
+
+        self.buffer[self.write_index] = sample;
+        let read_index = (self.write_index + len - self.delay) % len;
+        self.write_index = (self.write_index + 1) % len;
+        self.buffer[read_index]
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|s| *s = 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_changes_without_reallocating() {
+        let mut delay = SampleDelay::new(4);
+        delay.set_delay(2);
+        let output: Vec<f32> = (1..=5).map(|i| delay.process(i as f32)).collect();
+        assert_eq!(output, vec![0.0, 0.0, 1.0, 2.0, 3.0]);
+
+        // Longer delays than the maximum are clamped
+        delay.set_delay(10);
+        assert_eq!(delay.delay(), 4);
+        assert_eq!(delay.process(6.0), 2.0);
+
+        delay.set_delay(0);
+        assert_eq!(delay.process(7.0), 7.0);
+    }
+}
@@ -0,0 +1,192 @@
+/// A parameter value that ramps linearly to a new target over a fixed time, so changes don't cause zipper noise.
+///
+/// Pedals set the target when a parameter changes, and read the smoothed value each sample in `process_audio`.
+///
+/// This isn't shared between threads: it's owned by whatever processes the audio, and new targets arrive there
+/// as parameter changes or commands, so no locking or atomics are needed on the audio thread.
+#[derive(Debug, Clone)]
+pub struct SmoothedParam {
+    current: f32,
+    target: f32,
+    // Change in value each sample, while ramping
+    step: f32,
+    // Samples left until the target is reached
+    remaining: usize,
+    ramp_samples: usize,
+}
+
+impl SmoothedParam {
+    pub fn new(value: f32, ramp_ms: f32, sample_rate: f32) -> Self {
+        let mut param = Self {
+            current: value,
+            target: value,
+            step: 0.0,
+            remaining: 0,
+            ramp_samples: 0,
+        };
+        param.set_ramp_time(ramp_ms, sample_rate);
+        param
+    }
+
+    /// Set the time taken to reach a new target. A ramp in progress keeps its current speed.
+    pub fn set_ramp_time(&mut self, ramp_ms: f32, sample_rate: f32) {
+        self.ramp_samples = ((ramp_ms.max(0.0) / 1000.0) * sample_rate).round() as usize;
+    }
+
+    /// Start ramping towards a new value, from the current value
+    pub fn set_target(&mut self, target: f32) {
+        if target == self.target {
+            return;
+        }
+
+        self.target = target;
+        if self.ramp_samples == 0 {
+            self.set_immediate(target);
+        } else {
+            self.remaining = self.ramp_samples;
+            self.step = (self.target - self.current) / self.ramp_samples as f32;
+        }
+    }
+
+    /// Jump to a value without ramping, e.g. when the pedal is first configured
+    pub fn set_immediate(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.step = 0.0;
+        self.remaining = 0;
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn is_smoothing(&self) -> bool {
+        self.remaining > 0
+    }
+
+    /// Returns the smoothed value for the next sample
+    #[inline]
+    pub fn next(&mut self) -> f32 {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            self.current = if self.remaining == 0 {
+                // Land exactly on the target, without accumulated rounding error
+                self.target
+            } else {
+                self.current + self.step
+            };
+        }
+        self.current
+    }
+
+    /// Advance by a number of samples and return the value reached, for values only updated once per chunk
+    pub fn advance(&mut self, samples: usize) -> f32 {
+        if samples >= self.remaining {
+            self.remaining = 0;
+            self.current = self.target;
+        } else {
+            self.remaining -= samples;
+            self.current += self.step * samples as f32;
+        }
+        self.current
+    }
+
+    /// Fill the buffer with the smoothed values for the next samples
+    pub fn fill(&mut self, buffer: &mut [f32]) {
+        if !self.is_smoothing() {
+            buffer.fill(self.current);
+            return;
+        }
+
+        for value in buffer.iter_mut() {
+            *value = self.next();
+        }
+    }
+
+    /// Multiply each sample by the smoothed value, e.g. for a gain parameter
+    pub fn apply_gain(&mut self, buffer: &mut [f32]) {
+        if !self.is_smoothing() {
+            let gain = self.current;
+            buffer.iter_mut().for_each(|sample| *sample *= gain);
+            return;
+        }
+
+        for sample in buffer.iter_mut() {
+            *sample *= self.next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_reaches_target_in_ramp_time() {
+        let sample_rate = 48000.0;
+        let mut param = SmoothedParam::new(0.0, 10.0, sample_rate);
+        param.set_target(1.0);
+
+        let ramp_samples = 480;
+        let mut values = vec![0.0; ramp_samples];
+        param.fill(&mut values);
+
+        assert!(values.windows(2).all(|w| w[1] > w[0]), "Ramp is not monotonic");
+        assert!(values[..ramp_samples - 1].iter().all(|&value| value < 1.0));
+        assert_eq!(values[ramp_samples - 1], 1.0);
+        assert!(!param.is_smoothing());
+        assert_eq!(param.next(), 1.0);
+
+        // Falling ramps are monotonic too
+        param.set_target(-0.5);
+        let mut previous = param.current();
+        for _ in 0..ramp_samples {
+            let value = param.next();
+            assert!(value < previous);
+            previous = value;
+        }
+        assert_eq!(previous, -0.5);
+    }
+
+    #[test]
+    fn test_retarget_during_ramp() {
+        let mut param = SmoothedParam::new(0.0, 1.0, 1000.0);
+        param.set_ramp_time(4.0, 1000.0);
+        param.set_target(4.0);
+        assert_eq!(param.next(), 1.0);
+        assert_eq!(param.next(), 2.0);
+
+        // A new target ramps from where the previous ramp got to, without jumping
+        param.set_target(0.0);
+        let values: Vec<f32> = (0..4).map(|_| param.next()).collect();
+        assert_eq!(values, vec![1.5, 1.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_apply_gain() {
+        let mut param = SmoothedParam::new(1.0, 2.0, 1000.0);
+        param.set_target(0.0);
+
+        let mut buffer = [1.0; 4];
+        param.apply_gain(&mut buffer);
+        assert_eq!(buffer, [0.5, 0.0, 0.0, 0.0]);
+
+        param.set_immediate(0.25);
+        let mut buffer = [1.0, -2.0];
+        param.apply_gain(&mut buffer);
+        assert_eq!(buffer, [0.25, -0.5]);
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut param = SmoothedParam::new(0.0, 4.0, 1000.0);
+        param.set_target(4.0);
+        assert_eq!(param.advance(2), 2.0);
+        assert_eq!(param.advance(3), 4.0);
+        assert!(!param.is_smoothing());
+    }
+}
@@ -0,0 +1,13 @@
+use signalsmith_stretch::Stretch;
+
+/// Create a mono Signalsmith Stretch, processing blocks of `block_size` samples every quarter block
+pub fn new_stretch(block_size: usize) -> Stretch {
+    Stretch::new(1, block_size, block_size / 4)
+}
+
+/// Latency in samples of a stretch created by [`new_stretch`].
+///
+/// Signalsmith Stretch delays by about one block, half on the input and half on the output.
+pub fn stretch_latency_samples(block_size: usize) -> usize {
+    block_size
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use super::oscillator::{Oscillator, Sine};
+
+/// Time between impulses
+const IMPULSE_INTERVAL_S: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TestSignalKind {
+    // Frequency
+    Sine(f32),
+    WhiteNoise,
+    PinkNoise,
+    Impulse
+}
+
+impl std::fmt::Display for TestSignalKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TestSignalKind::Sine(_) => "Sine",
+            TestSignalKind::WhiteNoise => "White Noise",
+            TestSignalKind::PinkNoise => "Pink Noise",
+            TestSignalKind::Impulse => "Impulse"
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Generates a test signal to replace the input, for checking a chain without an instrument
+pub struct TestSignalGenerator {
+    kind: TestSignalKind,
+    level: f32,
+    sine: Oscillator,
+    rng_state: u32,
+    // Paul Kellet's economy pink noise filter state
+    pink_state: [f32; 3],
+    impulse_interval: usize,
+    impulse_counter: usize
+}
+
+impl TestSignalGenerator {
+    pub fn new(kind: TestSignalKind, level: f32, sample_rate: f32) -> Self {
+        let frequency = match kind {
+            TestSignalKind::Sine(frequency) => frequency,
+            _ => 440.0
+        };
+
+        Self {
+            kind,
+            level,
+            sine: Oscillator::Sine(Sine::new(sample_rate, frequency, 0.0, 0.0)),
+            rng_state: 0x9E3779B9,
+            pink_state: [0.0; 3],
+            impulse_interval: (IMPULSE_INTERVAL_S * sample_rate) as usize,
+            impulse_counter: 0
+        }
+    }
+
+    pub fn kind(&self) -> TestSignalKind {
+        self.kind
+    }
+
+    /// Uniform white noise in -1 to 1 (xorshift)
+    fn next_white(&mut self) -> f32 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 17;
+        self.rng_state ^= self.rng_state << 5;
+        (self.rng_state as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        let sample = match self.kind {
+            TestSignalKind::Sine(_) => self.sine.next().unwrap(),
+            TestSignalKind::WhiteNoise => self.next_white(),
+            TestSignalKind::PinkNoise => {
+                let white = self.next_white();
+                self.pink_state[0] = 0.99765 * self.pink_state[0] + white * 0.0990460;
+                self.pink_state[1] = 0.96300 * self.pink_state[1] + white * 0.2965164;
+                self.pink_state[2] = 0.57000 * self.pink_state[2] + white * 1.0526913;
+                // Scaled to roughly -1 to 1
+                (self.pink_state[0] + self.pink_state[1] + self.pink_state[2] + white * 0.1848) * 0.125
+            },
+            TestSignalKind::Impulse => {
+                let sample = if self.impulse_counter == 0 { 1.0 } else { 0.0 };
+                self.impulse_counter = (self.impulse_counter + 1) % self.impulse_interval.max(1);
+                sample
+            }
+        };
+
+        sample * self.level
+    }
+
+    pub fn fill(&mut self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.next_sample();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_impulse_interval() {
+        let sample_rate = 1000.0;
+        let mut generator = TestSignalGenerator::new(TestSignalKind::Impulse, 0.5, sample_rate);
+        let mut buffer = vec![0.0; 2500];
+        generator.fill(&mut buffer);
+
+        let impulses: Vec<usize> = buffer.iter().enumerate().filter(|(_, &s)| s != 0.0).map(|(i, _)| i).collect();
+        assert_eq!(impulses, vec![0, 1000, 2000]);
+        assert_eq!(buffer[0], 0.5);
+    }
+
+    #[test]
+    fn test_noise_is_bounded_and_centred() {
+        for kind in [TestSignalKind::WhiteNoise, TestSignalKind::PinkNoise] {
+            let mut generator = TestSignalGenerator::new(kind, 1.0, 48000.0);
+            let mut buffer = vec![0.0; 480000];
+            generator.fill(&mut buffer);
+
+            let mean = buffer.iter().sum::<f32>() / buffer.len() as f32;
+            assert!(mean.abs() < 0.05, "{} mean {}", kind, mean);
+            assert!(buffer.iter().all(|s| s.abs() <= 1.0), "{} out of range", kind);
+        }
+    }
+}
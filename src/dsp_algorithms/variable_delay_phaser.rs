@@ -1,14 +1,26 @@
 use crate::dsp_algorithms::variable_delay::VariableDelayLine;
 use crate::dsp_algorithms::oscillator::Oscillator;
+use crate::dsp_algorithms::denormal::flush_denormal;
+use crate::dsp_algorithms::onset_detector::OnsetDetector;
 use std::iter::Iterator;
 
+/// Maximum number of voices, to limit CPU usage
+pub const MAX_VOICES: usize = 4;
+
 #[derive(Clone)]
 pub struct VariableDelayPhaser {
     pub mix: f32,
     delay: VariableDelayLine,
     min_delay_samples: usize,
     pub feedback: f32,
-    pub oscillator: Oscillator,
+    oscillator: Oscillator,
+    // Oscillators for voices after the first, each with an evenly spaced phase offset.
+    // Voices are taps on the same delay line, which is equivalent to separate lines but cheaper.
+    // All MAX_VOICES - 1 are created in new, and only the first voices - 1 are used.
+    extra_voices: Vec<Oscillator>,
+    voices: usize,
+    // Restarts the oscillators on each note onset, None if retrigger is off
+    onset_detector: Option<OnsetDetector>,
     sample_rate: f32
 }
 
@@ -22,23 +34,41 @@ impl VariableDelayPhaser {
             min_delay_samples: ((depth_min_ms / 1000.0) * sample_rate) as usize,
             delay: VariableDelayLine::new(depth_samples),
             feedback,
+            extra_voices: vec![oscillator.clone(); MAX_VOICES - 1],
+            voices: 1,
             oscillator,
+            onset_detector: None,
             sample_rate
         }
     }
 
+    fn delay_from_oscillator(oscillator: &mut Oscillator, min_delay_samples: usize, max_delay_samples: usize) -> f32 {
+        let oscillator_val = (oscillator.next().unwrap() + 1.0) / 2.0;
+        (oscillator_val * (max_delay_samples-min_delay_samples) as f32) + min_delay_samples as f32
+    }
+
     pub fn process_audio(&mut self, buffer: &mut [f32]) {
         for sample in buffer.iter_mut() {
+            if self.onset_detector.as_mut().is_some_and(|detector| detector.process(*sample)) {
+                self.reset_phase();
+            }
+
             let max_depth_samples = self.delay.max_delay().ceil() as usize;
 
-            let oscillator_val = (self.oscillator.next().unwrap() + 1.0) / 2.0;
-            let delay_val = (oscillator_val * (max_depth_samples-self.min_delay_samples) as f32) + self.min_delay_samples as f32;
+            let delay_val = Self::delay_from_oscillator(&mut self.oscillator, self.min_delay_samples, max_depth_samples);
+            let mut delayed_sample = self.delay.get_sample(delay_val);
 
-            let delayed_sample = self.delay.get_sample(delay_val);
+            if self.voices > 1 {
+                for voice in self.extra_voices[..self.voices - 1].iter_mut() {
+                    let voice_delay_val = Self::delay_from_oscillator(voice, self.min_delay_samples, max_depth_samples);
+                    delayed_sample += self.delay.get_sample(voice_delay_val);
+                }
+                delayed_sample /= self.voices as f32;
+            }
 
             // Apply feedback
             self.delay.buffer.pop_front();
-            let feedback_sample = delayed_sample * self.feedback + *sample;
+            let feedback_sample = flush_denormal(delayed_sample * self.feedback + *sample);
             self.delay.buffer.push_back(feedback_sample);
 
             *sample = self.mix * delayed_sample + (1.0 - self.mix) * *sample;
@@ -47,6 +77,47 @@ impl VariableDelayPhaser {
 
     pub fn set_rate(&mut self, rate_hz: f32) {
         self.oscillator.set_frequency(rate_hz);
+        for voice in self.extra_voices.iter_mut() {
+            voice.set_frequency(rate_hz);
+        }
+    }
+
+    pub fn set_oscillator(&mut self, oscillator: Oscillator) {
+        self.oscillator = oscillator;
+        self.set_voices(self.voices());
+    }
+
+    /// Restart the oscillators from their phase offsets, keeping the voices evenly spread
+    pub fn reset_phase(&mut self) {
+        self.oscillator.reset_phase();
+        for voice in self.extra_voices.iter_mut() {
+            voice.reset_phase();
+        }
+    }
+
+    /// Restart the oscillators on each note onset, so the sweep is in time with playing
+    pub fn set_retrigger(&mut self, retrigger: bool) {
+        if retrigger != self.onset_detector.is_some() {
+            self.onset_detector = retrigger.then(|| OnsetDetector::new(self.sample_rate));
+        }
+    }
+
+    pub fn voices(&self) -> usize {
+        self.voices
+    }
+
+    /// Set the number of voices (clamped to 1..=MAX_VOICES), spreading their phases evenly.
+    ///
+    /// The voices are updated in place, so this doesn't allocate.
+    pub fn set_voices(&mut self, voices: usize) {
+        let voices = voices.clamp(1, MAX_VOICES);
+        let base_phase_offset = self.oscillator.get_phase_offset();
+
+        for (i, voice) in self.extra_voices.iter_mut().enumerate() {
+            voice.clone_from(&self.oscillator);
+            voice.set_phase_offset(base_phase_offset + (i + 1) as f32 / voices as f32);
+        }
+        self.voices = voices;
     }
 
     pub fn set_min_depth(&mut self, depth_ms: f32) {
@@ -60,5 +131,8 @@ impl VariableDelayPhaser {
 
     pub fn reset(&mut self) {
         self.delay.buffer.iter_mut().for_each(|s| *s = 0.0);
+        if let Some(onset_detector) = &mut self.onset_detector {
+            onset_detector.reset();
+        }
     }
 }
\ No newline at end of file
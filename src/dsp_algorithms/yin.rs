@@ -2,6 +2,8 @@ use std::fmt::Display;
 
 /// Credit to https://github.com/saresend/yin/ for some functions
 use ringbuf::{traits::{Consumer, Observer}, HeapCons};
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 
 // How often in milliseconds the processor should calculate and send the tuner frequency when active
 pub const PROCESSOR_UPDATE_FREQ_MS: u64 = 100;
@@ -41,16 +43,69 @@ impl Display for Note {
     }
 }
 
-// Get note, octave, and offset in cents
+/// Tuning systems the tuner can show the offset from
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, EnumIter)]
+pub enum Temperament {
+    #[default]
+    Equal,
+    Just,
+    Pythagorean,
+    Meantone,
+}
+
+impl Display for Temperament {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Temperament::Equal => write!(f, "Equal"),
+            Temperament::Just => write!(f, "Just"),
+            Temperament::Pythagorean => write!(f, "Pythagorean"),
+            Temperament::Meantone => write!(f, "Quarter-Comma Meantone"),
+        }
+    }
+}
+
+impl Temperament {
+    /// Offset in cents from equal temperament of each note from C, for the temperament rooted on C
+    fn offsets_from_c(&self) -> [f32; 12] {
+        match self {
+            Temperament::Equal => [0.0; 12],
+            Temperament::Just => [0.0, 11.7, 3.9, 15.6, -13.7, -2.0, -9.8, 2.0, 13.7, -15.6, -3.9, -11.7],
+            Temperament::Pythagorean => [0.0, 13.7, 3.9, -9.8, 7.8, -2.0, 11.7, 2.0, 15.6, 5.9, -3.9, 9.8],
+            Temperament::Meantone => [0.0, -24.0, -6.8, 10.3, -13.7, 3.4, -20.5, -3.4, -27.4, -10.3, 6.8, -17.1],
+        }
+    }
+
+    /// Offset in cents from equal temperament of a note (0 = C, ..., 11 = B), with A kept at the reference pitch
+    pub fn cents_offset(&self, note_index: usize) -> f32 {
+        let offsets = self.offsets_from_c();
+        offsets[note_index % 12] - offsets[9]
+    }
+}
+
+// Get note, octave, and offset in cents, for A4 = 440hz in equal temperament
 pub fn freq_to_note(freq: f32) -> (Note, isize, f32) {
+    freq_to_note_tuned(freq, 440.0, Temperament::Equal)
+}
+
+/// Get note, octave, and offset in cents from the nearest note of the temperament, with A4 at `reference_a4` hz
+pub fn freq_to_note_tuned(freq: f32, reference_a4: f32, temperament: Temperament) -> (Note, isize, f32) {
     // Offset from A4 in cents
-    let cents_offset = 1200.0 * (freq / 440.0).log2();
-    // Offset from A4 in semitones
-    let semitone_offset = (cents_offset / 100.0).round() as isize;
+    let cents_offset = 1200.0 * (freq / reference_a4).log2();
+    // Offset from A4 in semitones. A temperament may move the nearest note to a neighbouring semitone.
+    let equal_semitone_offset = (cents_offset / 100.0).round() as isize;
+    let (semitone_offset, semitone_cents_offset) = [equal_semitone_offset - 1, equal_semitone_offset, equal_semitone_offset + 1]
+        .into_iter()
+        .map(|semitone_offset| {
+            let note_index = (69 + semitone_offset).rem_euclid(12) as usize;
+            (semitone_offset, cents_offset - semitone_offset as f32 * 100.0 - temperament.cents_offset(note_index))
+        })
+        .min_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+        .unwrap();
+
     // MIDI note number for A4 is 69
     let midi_note = 69 + semitone_offset;
-    // Note index (0 = A, 1 = A#, ..., 11 = G#)
-    let note_index = ((midi_note % 12 + 12) % 12) as u8;
+    // Note index (0 = C, 1 = C#, ..., 11 = B)
+    let note_index = midi_note.rem_euclid(12) as u8;
     let note = match note_index {
         0 => Note::C,
         1 => Note::CSharp,
@@ -68,9 +123,7 @@ pub fn freq_to_note(freq: f32) -> (Note, isize, f32) {
     };
 
     // Octave calculation: MIDI note 69 is A4, so octave = (midi_note / 12) - 1
-    let octave = (midi_note / 12) - 1;
-    // Offset from nearest semitone in cents
-    let semitone_cents_offset = cents_offset - (semitone_offset as f32 * 100.0);
+    let octave = midi_note.div_euclid(12) - 1;
     (note, octave, semitone_cents_offset)
 }
 
@@ -243,4 +296,21 @@ mod tests {
         let freq = estimator.process_buffer();
         assert!(freq - 20.0 < 0.5, "Yin frequency estimation failed: {} != 20.0", freq);
     }
+
+    #[test]
+    fn test_reference_pitch_and_temperament() {
+        let (note, octave, cents) = freq_to_note(440.0);
+        assert!(matches!(note, Note::A) && octave == 4 && cents.abs() < 1e-3);
+
+        // A4 at 432hz is 31.8 cents flat of 440hz
+        let (note, octave, cents) = freq_to_note_tuned(432.0, 432.0, Temperament::Equal);
+        assert!(matches!(note, Note::A) && octave == 4 && cents.abs() < 1e-3);
+        let (note, _, cents) = freq_to_note(432.0);
+        assert!(matches!(note, Note::A) && (cents + 31.8).abs() < 0.1, "{cents}");
+
+        // A just major third above C, relative to A: E is 1.9 cents higher than in equal temperament
+        let e_equal = 440.0 * 2f32.powf(-5.0 / 12.0);
+        let (note, octave, cents) = freq_to_note_tuned(e_equal, 440.0, Temperament::Just);
+        assert!(matches!(note, Note::E) && octave == 4 && (cents + 1.9).abs() < 0.1, "{cents}");
+    }
 }
@@ -1,6 +1,21 @@
 use serde::{Deserialize, Serialize};
-use crate::{pedals::{Pedal, PedalTrait}, unique_time_id};
-use std::{fmt::Write, hash::Hash};
+use crate::{dsp_algorithms::{mixer::linear_mix, sample_delay::SampleDelay, test_signal::{TestSignalGenerator, TestSignalKind}}, pedals::{Pedal, PedalParameterValue, PedalTrait}, unique_time_id};
+use std::{collections::HashMap, fmt::Write, hash::Hash, time::{Duration, Instant}};
+
+/// Peak level below which the output of an inactive pedal is considered silent
+const TAIL_SILENCE_THRESHOLD: f32 = 1e-5;
+/// Time an inactive pedal's output must be silent for before its tail is finished.
+/// Long enough that the gap between delay repeats isn't mistaken for the end of the tail.
+const TAIL_SILENCE_SECS: f32 = 4.0;
+/// Most scenes a pedalboard can have
+pub const MAX_SCENES: usize = 4;
+/// Names given to new scenes
+pub const SCENE_NAMES: [&str; MAX_SCENES] = ["A", "B", "C", "D"];
+
+/// For `skip_serializing_if`, so fields that are unchanged from their default aren't saved
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
 
 /// Can uniquely identify a parameter.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -10,6 +25,49 @@ pub struct ParameterPath {
     pub parameter_name: String,
 }
 
+/// A contiguous range of pedals that processes a copy of the signal,
+/// which is mixed back into the main path after the last pedal in the range.
+/// 
+/// The main path skips the pedals in the range.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ParallelBranch {
+    // IDs of the first and last pedals in the branch (inclusive)
+    pub start_pedal_id: u32,
+    pub end_pedal_id: u32,
+    // 0.0 = only main path, 1.0 = only branch
+    pub mix: f32,
+}
+
+/// Parameter values of the pedals in a pedalboard, which can be recalled to change sound without changing pedalboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scene {
+    pub name: String,
+    // Parameter values (including Active) by pedal ID
+    pub values: HashMap<u32, HashMap<String, PedalParameterValue>>,
+}
+
+/// Ringing out the tails of inactive pedals
+#[derive(Clone)]
+struct Tails {
+    // Silence processed by an inactive pedal, leaving only its tail
+    buffer: Vec<f32>,
+    // Consecutive silent samples output by each inactive pedal (by ID) with a tail
+    silent_samples: HashMap<u32, usize>,
+    // Consecutive silent samples before a tail is finished, TAIL_SILENCE_SECS at the configured sample rate
+    silence_samples: usize,
+}
+
+impl Default for Tails {
+    fn default() -> Self {
+        Tails {
+            buffer: Vec::new(),
+            silent_samples: HashMap::new(),
+            // Replaced in set_config
+            silence_samples: (TAIL_SILENCE_SECS * 48000.0) as usize,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct Pedalboard {
@@ -21,11 +79,39 @@ pub struct Pedalboard {
     #[serde(default)]
     pub name: String,
     pub pedals: Vec<Pedal>,
+    // Fully serial pedalboards have no branch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_branch: Option<ParallelBranch>,
+    // Gain in dB applied after all pedals, e.g. to match the level of an imported pedalboard
+    #[serde(skip_serializing_if = "is_default")]
+    pub output_trim: f32,
+    // Pedalboards without scenes only have their current parameter values
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub scenes: Vec<Scene>,
+    // Index of the scene the current parameter values belong to
+    #[serde(skip_serializing_if = "is_default")]
+    pub active_scene: usize,
+    // User tags for organising the library, e.g. "Clean" or "Live". Not used by the processor.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    // Shown in the quick access row on the client's stage screen
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
 
     #[serde(skip)]
     prepend_message: String,
     #[serde(skip)]
     pedal_message_buffer: Vec<String>,
+    #[serde(skip)]
+    branch_buffer: Vec<f32>,
+    // Delays the main path by the latency of the parallel branch
+    #[serde(skip)]
+    branch_latency_delay: SampleDelay,
+    // Time spent processing each pedal (by ID) since the timings were last taken, if profiling is enabled
+    #[serde(skip)]
+    pedal_timings: Option<HashMap<u32, Duration>>,
+    #[serde(skip)]
+    tails: Tails,
 }
 
 impl std::fmt::Debug for Pedalboard {
@@ -40,8 +126,18 @@ impl Default for Pedalboard {
             id: unique_time_id(),
             name: String::from("Default Pedalboard"),
             pedals: vec![Pedal::Volume(crate::pedals::Volume::new())],
+            parallel_branch: None,
+            output_trim: 0.0,
+            scenes: Vec::new(),
+            active_scene: 0,
+            tags: Vec::new(),
+            favorite: false,
             prepend_message: String::new(),
             pedal_message_buffer: Vec::with_capacity(12),
+            branch_buffer: Vec::new(),
+            branch_latency_delay: SampleDelay::default(),
+            pedal_timings: None,
+            tails: Tails::default(),
         }
     }
 }
@@ -67,6 +163,45 @@ impl Pedalboard {
         cloned
     }
 
+    /// Configure every pedal with [`Pedal::configure`], and size the buffers used when processing blocks of up to `buffer_size` samples,
+    /// so the audio thread doesn't allocate.
+    pub fn set_config(&mut self, buffer_size: usize, sample_rate: u32) {
+        for pedal in &mut self.pedals {
+            pedal.configure(buffer_size, sample_rate);
+        }
+
+        self.branch_buffer.clear();
+        self.branch_buffer.reserve(buffer_size);
+        self.tails.buffer.clear();
+        self.tails.buffer.reserve(buffer_size);
+        self.tails.silence_samples = (TAIL_SILENCE_SECS * sample_rate as f32) as usize;
+        self.reserve_tails();
+        self.reserve_branch_latency();
+    }
+
+    /// Add a pedal to the end of the pedalboard. The pedal should already be configured with `set_config`.
+    pub fn add_pedal(&mut self, pedal: Pedal) {
+        self.pedals.push(pedal);
+        self.reserve_tails();
+        self.reserve_branch_latency();
+    }
+
+    /// Allocate the branch latency delay for the largest latency any branch of the pedals can have,
+    /// so only the delay length changes on the audio thread
+    fn reserve_branch_latency(&mut self) {
+        let max_latency = self.pedals.iter().map(|p| p.max_latency_samples()).sum();
+        if max_latency != self.branch_latency_delay.max_delay() {
+            self.branch_latency_delay = SampleDelay::new(max_latency);
+        }
+    }
+
+    /// Make room to track the tail of every pedal, forgetting pedals that have been removed
+    fn reserve_tails(&mut self) {
+        let pedals = &self.pedals;
+        self.tails.silent_samples.retain(|id, _| pedals.iter().any(|pedal| pedal.get_id() == *id));
+        self.tails.silent_samples.reserve(pedals.len());
+    }
+
     pub fn from_pedals(name: String, pedals: Vec<Pedal>) -> Pedalboard {
         Pedalboard {
             id: unique_time_id(),
@@ -76,31 +211,329 @@ impl Pedalboard {
         }
     }
 
+    /// Add a tag, trimming whitespace. Returns false if the tag is empty or the pedalboard already has it (ignoring case).
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = tag.trim();
+        if tag.is_empty() || self.has_tag(tag) {
+            return false;
+        }
+        self.tags.push(tag.to_string());
+        true
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Indices of the first and last pedals in the parallel branch (inclusive), if there is a valid branch.
+    pub fn parallel_branch_range(&self) -> Option<(usize, usize)> {
+        let branch = self.parallel_branch.as_ref()?;
+        let start = self.pedals.iter().position(|p| p.get_id() == branch.start_pedal_id)?;
+        let end = self.pedals.iter().position(|p| p.get_id() == branch.end_pedal_id)?;
+        Some((start.min(end), start.max(end)))
+    }
+
+    /// The current parameter values of the pedals, as a scene
+    pub fn capture_scene(&self, name: String) -> Scene {
+        let values = self.pedals.iter()
+            .map(|pedal| {
+                let parameters = pedal.get_parameters().iter()
+                    .map(|(parameter_name, parameter)| (parameter_name.clone(), parameter.value.clone()))
+                    .collect();
+                (pedal.get_id(), parameters)
+            })
+            .collect();
+
+        Scene { name, values }
+    }
+
+    /// The parameter changes needed to recall a scene, as (pedal id, parameter name, value).
+    ///
+    /// Only values that differ from the current values are returned.
+    /// Pedals added since the scene was captured are left unchanged.
+    pub fn scene_changes(&self, scene: &Scene) -> Vec<(u32, String, PedalParameterValue)> {
+        let mut changes = Vec::new();
+
+        for pedal in &self.pedals {
+            let Some(scene_values) = scene.values.get(&pedal.get_id()) else {
+                continue;
+            };
+
+            for (parameter_name, parameter) in pedal.get_parameters() {
+                if let Some(value) = scene_values.get(parameter_name) {
+                    if *value != parameter.value {
+                        changes.push((pedal.get_id(), parameter_name.clone(), value.clone()));
+                    }
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// Total latency of the active pedals, in samples.
+    /// 
+    /// Pedals in a parallel branch are included, as the main path is delayed to line up with the branch.
+    pub fn latency_samples(&self) -> usize {
+        self.pedals.iter()
+            .filter(|p| p.is_active())
+            .map(|p| p.latency_samples())
+            .sum()
+    }
+
+    /// Enable or disable timing each pedal's processing.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        if !enabled {
+            self.pedal_timings = None;
+        } else if self.pedal_timings.is_none() {
+            self.pedal_timings = Some(HashMap::new());
+        }
+    }
+
+    /// Time spent processing each pedal since this was last called, if profiling is enabled.
+    /// 
+    /// The timings are reset after calling `f` with each pedal ID and duration.
+    pub fn take_pedal_timings(&mut self, mut f: impl FnMut(u32, Duration)) {
+        if let Some(timings) = &mut self.pedal_timings {
+            // Draining keeps the allocation, so the audio thread doesn't reallocate
+            for (id, duration) in timings.drain() {
+                f(id, duration);
+            }
+        }
+    }
+
     pub fn process_audio(&mut self, buffer: &mut [f32], message_buffer: &mut Vec<String>) {
-        self.pedals.iter_mut().for_each(|pedal| {
-            if !pedal.is_active() {
-                return;
+        let branch_range = self.parallel_branch_range();
+        let branch_latency: usize = branch_range.map_or(0, |(start, end)| {
+            self.pedals[start..=end].iter()
+                .filter(|p| p.is_active())
+                .map(|p| p.latency_samples())
+                .sum()
+        });
+
+        for (i, pedal) in self.pedals.iter_mut().enumerate() {
+            match branch_range {
+                Some((start, end)) if i >= start && i <= end => {
+                    if i == start {
+                        // The buffer's capacity is reserved in set_config
+                        self.branch_buffer.clear();
+                        self.branch_buffer.extend_from_slice(buffer);
+                    }
+
+                    Self::process_pedal(pedal, &mut self.branch_buffer, &mut self.prepend_message, &mut self.pedal_message_buffer, message_buffer, self.pedal_timings.as_mut(), &mut self.tails);
+
+                    if i == end {
+                        // Line up the main path with the branch
+                        self.branch_latency_delay.set_delay(branch_latency);
+                        for sample in buffer.iter_mut() {
+                            *sample = self.branch_latency_delay.process(*sample);
+                        }
+
+                        // The branch is mostly the same signal as the main path, so a linear crossfade keeps the level
+                        let mix = self.parallel_branch.as_ref().map_or(0.5, |b| b.mix);
+                        linear_mix(buffer, &self.branch_buffer, mix);
+                    }
+                },
+                _ => Self::process_pedal(pedal, buffer, &mut self.prepend_message, &mut self.pedal_message_buffer, message_buffer, self.pedal_timings.as_mut(), &mut self.tails)
             }
+        }
+
+        if self.output_trim != 0.0 {
+            let gain = 10f32.powf(self.output_trim / 20.0);
+            buffer.iter_mut().for_each(|sample| *sample *= gain);
+        }
+    }
+
+    /// Run pink noise through the pedalboard and return the change in RMS level in dB, excluding the output trim.
+    /// Returns None if the pedalboard outputs silence.
+    ///
+    /// The pedals must already be configured with `set_config` for `sample_rate` and `buffer_size`.
+    /// This processes several seconds of audio, so should not be called on the audio thread.
+    pub fn measure_gain_db(&mut self, sample_rate: u32, buffer_size: usize) -> Option<f32> {
+        // Time for delays, reverbs and envelopes to settle before measuring
+        const SETTLE_SECONDS: f32 = 1.0;
+        const MEASURE_SECONDS: f32 = 3.0;
+        // Roughly the level of a guitar DI signal
+        const REFERENCE_LEVEL: f32 = 0.25;
+
+        let mut generator = TestSignalGenerator::new(TestSignalKind::PinkNoise, REFERENCE_LEVEL, sample_rate as f32);
+        let mut buffer = vec![0.0; buffer_size];
+        let mut message_buffer = Vec::new();
 
-            // Clear the message buffer for each pedal
-            self.pedal_message_buffer.clear();
-            self.prepend_message.clear();
-            // Each message from a pedal will be preprended with "pedalmsg<id> "
-            if let Err(e) = write!(&mut self.prepend_message, "pedalmsg{} ", pedal.get_id()) {
-                tracing::warn!("Failed to write prepend message: {}", e);
+        let settle_samples = (SETTLE_SECONDS * sample_rate as f32) as usize;
+        let total_samples = settle_samples + (MEASURE_SECONDS * sample_rate as f32) as usize;
+        let (mut input_energy, mut output_energy) = (0.0f64, 0.0f64);
+
+        let mut processed = 0;
+        while processed < total_samples {
+            generator.fill(&mut buffer);
+            let block_input_energy: f64 = buffer.iter().map(|s| (*s as f64).powi(2)).sum();
+
+            self.process_audio(&mut buffer, &mut message_buffer);
+            message_buffer.clear();
+
+            if processed >= settle_samples {
+                input_energy += block_input_energy;
+                output_energy += buffer.iter().map(|s| (*s as f64).powi(2)).sum::<f64>();
             }
+            processed += buffer_size;
+        }
+
+        if output_energy <= f64::EPSILON || !output_energy.is_finite() {
+            return None;
+        }
+
+        let gain_db = 10.0 * (output_energy / input_energy).log10() as f32;
+        Some(gain_db - self.output_trim)
+    }
+
+    /// Process an active pedal, or ring out the tail of an inactive pedal.
+    /// 
+    /// Inactive pedals without a tail are skipped.
+    fn process_pedal(
+        pedal: &mut Pedal,
+        buffer: &mut [f32],
+        prepend_message: &mut String,
+        pedal_message_buffer: &mut Vec<String>,
+        message_buffer: &mut Vec<String>,
+        timings: Option<&mut HashMap<u32, Duration>>,
+        tails: &mut Tails
+    ) {
+        if pedal.is_active() {
+            tails.silent_samples.remove(&pedal.get_id());
+            Self::run_pedal(pedal, buffer, prepend_message, pedal_message_buffer, message_buffer, timings);
+            return;
+        }
+
+        if !pedal.has_tail() {
+            return;
+        }
+
+        // Room for every pedal is reserved in set_config and add_pedal, so this doesn't allocate
+        let silent_samples = tails.silent_samples.entry(pedal.get_id()).or_insert(0);
+        if *silent_samples >= tails.silence_samples {
+            return;
+        }
+
+        // The pedal processes silence, so its output is only the tail, which is added to the dry signal.
+        // The buffer's capacity is reserved in set_config.
+        tails.buffer.clear();
+        tails.buffer.resize(buffer.len(), 0.0);
+        Self::run_pedal(pedal, &mut tails.buffer, prepend_message, pedal_message_buffer, message_buffer, timings);
 
-            pedal.process_audio(buffer, &mut self.pedal_message_buffer);
+        let mut peak: f32 = 0.0;
+        for (sample, tail) in buffer.iter_mut().zip(tails.buffer.iter()) {
+            *sample += tail;
+            peak = peak.max(tail.abs());
+        }
 
-            for message in &mut self.pedal_message_buffer {
-                message.insert_str(0, &self.prepend_message);
+        if peak < TAIL_SILENCE_THRESHOLD {
+            *silent_samples += buffer.len();
+            if *silent_samples >= tails.silence_samples {
+                // Start from silence when next activated
+                pedal.reset_buffer();
             }
+        } else {
+            *silent_samples = 0;
+        }
+    }
 
-            message_buffer.append(&mut self.pedal_message_buffer);
-        });
+    fn run_pedal(
+        pedal: &mut Pedal,
+        buffer: &mut [f32],
+        prepend_message: &mut String,
+        pedal_message_buffer: &mut Vec<String>,
+        message_buffer: &mut Vec<String>,
+        timings: Option<&mut HashMap<u32, Duration>>
+    ) {
+
+        // Clear the message buffer for each pedal
+        pedal_message_buffer.clear();
+        prepend_message.clear();
+        // Each message from a pedal will be preprended with "pedalmsg<id> "
+        if let Err(e) = write!(prepend_message, "pedalmsg{} ", pedal.get_id()) {
+            tracing::warn!("Failed to write prepend message: {}", e);
+        }
+
+        if let Some(timings) = timings {
+            let start = Instant::now();
+            pedal.process_audio(buffer, pedal_message_buffer);
+            *timings.entry(pedal.get_id()).or_default() += start.elapsed();
+        } else {
+            pedal.process_audio(buffer, pedal_message_buffer);
+        }
+
+        for message in pedal_message_buffer.iter_mut() {
+            message.insert_str(0, prepend_message);
+        }
+
+        message_buffer.append(pedal_message_buffer);
     }
 
     pub fn get_id(&self) -> u32 {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pedals::Volume;
+
+    #[test]
+    fn test_scene_changes() {
+        let mut pedalboard = Pedalboard::from_pedals("Test".to_string(), vec![Pedal::Volume(Volume::new())]);
+        let scene = pedalboard.capture_scene("A".to_string());
+        assert!(pedalboard.scene_changes(&scene).is_empty());
+
+        let pedal_id = pedalboard.pedals[0].get_id();
+        pedalboard.pedals[0].set_parameter_value("Active", PedalParameterValue::Bool(false));
+        let changes = pedalboard.scene_changes(&scene);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0], (pedal_id, "Active".to_string(), PedalParameterValue::Bool(true)));
+
+        // Pedals that aren't in the scene are left as they are
+        let mut scene = scene;
+        scene.values.remove(&pedal_id);
+        assert!(pedalboard.scene_changes(&scene).is_empty());
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut pedalboard = Pedalboard::new("Test".to_string());
+        assert!(pedalboard.add_tag(" Clean "));
+        assert!(!pedalboard.add_tag("clean"));
+        assert!(!pedalboard.add_tag("  "));
+        assert_eq!(pedalboard.tags, vec!["Clean".to_string()]);
+        assert!(pedalboard.has_tag("CLEAN"));
+
+        // Tags are kept through serialization, and pedalboards saved without tags have none
+        let serialized = serde_json::to_string(&pedalboard).unwrap();
+        let deserialized: Pedalboard = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.tags, pedalboard.tags);
+
+        pedalboard.tags.clear();
+        let serialized = serde_json::to_string(&pedalboard).unwrap();
+        assert!(!serialized.contains("tags"));
+        let deserialized: Pedalboard = serde_json::from_str(&serialized).unwrap();
+        assert!(deserialized.tags.is_empty());
+    }
+
+    #[test]
+    fn test_default_fields_are_not_serialized() {
+        let mut pedalboard = Pedalboard::new("Test".to_string());
+        let serialized = serde_json::to_string(&pedalboard).unwrap();
+        assert!(!serialized.contains("output_trim"));
+        assert!(!serialized.contains("active_scene"));
+        let deserialized: Pedalboard = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.output_trim, 0.0);
+        assert_eq!(deserialized.active_scene, 0);
+
+        pedalboard.output_trim = -6.0;
+        pedalboard.active_scene = 2;
+        let serialized = serde_json::to_string(&pedalboard).unwrap();
+        let deserialized: Pedalboard = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.output_trim, -6.0);
+        assert_eq!(deserialized.active_scene, 2);
+    }
+}
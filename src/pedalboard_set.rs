@@ -4,14 +4,18 @@ use serde::{Serialize, Deserialize};
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PedalboardSet {
     pub pedalboards: Vec<Pedalboard>,
-    pub active_pedalboard: usize
+    pub active_pedalboard: usize,
+    // ID of the pedalboard activated whenever the set is loaded, instead of the last active pedalboard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub home_pedalboard: Option<u32>
 }
 
 impl Default for PedalboardSet {
     fn default() -> PedalboardSet {
         PedalboardSet {
             pedalboards: vec![Pedalboard::default()],
-            active_pedalboard: 0
+            active_pedalboard: 0,
+            home_pedalboard: None
         }
     }
 }
@@ -32,10 +36,24 @@ impl PedalboardSet {
 
         Ok(PedalboardSet {
             pedalboards,
-            active_pedalboard: 0
+            active_pedalboard: 0,
+            home_pedalboard: None
         })
     }
 
+    /// Index of the home pedalboard, if it is set and still in the set
+    pub fn home_pedalboard_index(&self) -> Option<usize> {
+        let home_id = self.home_pedalboard?;
+        self.pedalboards.iter().position(|pedalboard| pedalboard.get_id() == home_id)
+    }
+
+    /// Make the home pedalboard active, if there is one. Called when the set is loaded.
+    pub fn activate_home_pedalboard(&mut self) {
+        if let Some(index) = self.home_pedalboard_index() {
+            self.active_pedalboard = index;
+        }
+    }
+
     /// Return true if it was removed
     pub fn remove_pedalboard(&mut self, index: usize) -> bool {
         if index < self.pedalboards.len() {
@@ -57,17 +75,29 @@ impl PedalboardSet {
 
     pub fn set_active_pedalboard(&mut self, index: usize) {
         if index < self.pedalboards.len() {
-            let current_pedalboard = &mut self.pedalboards[self.active_pedalboard];
-            for pedal in &mut current_pedalboard.pedals {
-                pedal.reset_buffer();
-            }
-
+            self.reset_active_pedalboard();
             self.active_pedalboard = index;
         } else {
             tracing::error!("Pedalboard index out of bounds");
         }
     }
 
+    /// Call set_config on every pedalboard
+    pub fn set_config(&mut self, buffer_size: usize, sample_rate: u32) {
+        for pedalboard in &mut self.pedalboards {
+            pedalboard.set_config(buffer_size, sample_rate);
+        }
+    }
+
+    /// Clear the buffers (e.g. delay lines, reverb tails) of all pedals in the active pedalboard
+    pub fn reset_active_pedalboard(&mut self) {
+        if let Some(pedalboard) = self.pedalboards.get_mut(self.active_pedalboard) {
+            for pedal in &mut pedalboard.pedals {
+                pedal.reset_buffer();
+            }
+        }
+    }
+
     pub fn process_audio(&mut self, buffer: &mut [f32], message_buffer: &mut Vec<String>) {
         if self.pedalboards.is_empty() {
             return;
@@ -81,3 +111,49 @@ impl PedalboardSet {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activate_home_pedalboard() {
+        let pedalboards = vec![Pedalboard::default(), Pedalboard::default(), Pedalboard::default()];
+        let home_id = pedalboards[1].get_id();
+        let mut set = PedalboardSet::from_pedalboards(pedalboards).unwrap();
+        set.active_pedalboard = 2;
+
+        // Without a home pedalboard, the stored active pedalboard is kept
+        set.activate_home_pedalboard();
+        assert_eq!(set.active_pedalboard, 2);
+
+        set.home_pedalboard = Some(home_id);
+        set.activate_home_pedalboard();
+        assert_eq!(set.active_pedalboard, 1);
+
+        // A removed home pedalboard is ignored
+        set.remove_pedalboard(1);
+        set.active_pedalboard = 0;
+        set.activate_home_pedalboard();
+        assert_eq!(set.active_pedalboard, 0);
+    }
+
+    #[test]
+    fn test_set_config_sets_oscillator_sample_rate() {
+        use crate::pedals::{Pedal, PedalDiscriminants};
+
+        let mut tremolo = PedalDiscriminants::Tremolo.new_pedal();
+        tremolo.get_parameters_mut().get_mut("Oscillator").unwrap().value.as_oscillator_mut().unwrap().set_sample_rate(44100.0);
+        let mut pedalboard = Pedalboard::default();
+        pedalboard.pedals.push(tremolo);
+        let saved = serde_json::to_string(&PedalboardSet::from_pedalboards(vec![pedalboard]).unwrap()).unwrap();
+
+        // Loading the set on a processor at a different sample rate corrects the saved oscillator
+        let mut set: PedalboardSet = serde_json::from_str(&saved).unwrap();
+        set.set_config(512, 96000);
+
+        let tremolo = set.pedalboards[0].pedals.iter().find(|pedal| matches!(pedal, Pedal::Tremolo(_))).unwrap();
+        let oscillator = tremolo.get_parameters().get("Oscillator").unwrap().value.as_oscillator().unwrap();
+        assert_eq!(oscillator.get_sample_rate(), 96000.0);
+    }
+}
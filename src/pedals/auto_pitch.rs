@@ -0,0 +1,314 @@
+use std::{collections::HashMap, hash::Hash};
+use eframe::egui::{self, include_image, Color32, Layout, RichText, UiBuilder, Vec2};
+use ringbuf::{traits::{Producer, Split}, HeapProd, HeapRb};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
+use signalsmith_stretch::Stretch;
+
+use crate::{dsp_algorithms::{pitch_correction::{freq_to_midi, nearest_scale_note, KEY_NAMES, SCALE_NAMES}, stretch::{new_stretch, stretch_latency_samples}, yin::Yin}, pedals::ui::pedal_switch, unique_time_id};
+
+use super::{
+    index_to_choice, ui::pedal_knob,
+    PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait,
+};
+
+const STRETCH_BLOCK_SIZE: usize = 2048;
+const TONALITY_LIMIT_HZ: f32 = 4000.0;
+const DETECTOR_MIN_FREQ: u32 = 70;
+const DETECTOR_MAX_FREQ: u32 = 1500;
+const DETECTOR_PERIODS: usize = 3;
+const DETECTOR_THRESHOLD: f32 = 0.2;
+
+/// Pitch detector and shifter, created in set_config
+struct PitchCorrector {
+    detector_writer: HeapProd<f32>,
+    detector: Yin,
+    stretch: Stretch,
+    output_buffer: Vec<f32>,
+    // Current correction in semitones, smoothed by the retune speed
+    correction: f32,
+    sample_rate: u32,
+}
+
+impl PitchCorrector {
+    fn new(buffer_size: usize, sample_rate: u32) -> Self {
+        let min_buffer = Yin::minimum_buffer_length(sample_rate, DETECTOR_MIN_FREQ, DETECTOR_PERIODS);
+        let (detector_writer, detector_reader) = HeapRb::new(min_buffer + buffer_size).split();
+
+        let mut stretch = new_stretch(STRETCH_BLOCK_SIZE);
+        stretch.set_transpose_factor_semitones(0.0, Some(TONALITY_LIMIT_HZ / sample_rate as f32));
+
+        Self {
+            detector_writer,
+            detector: Yin::new(DETECTOR_THRESHOLD, DETECTOR_MIN_FREQ, DETECTOR_MAX_FREQ, sample_rate, DETECTOR_PERIODS, detector_reader),
+            stretch,
+            // Sized for the largest block here, so it is never resized while processing
+            output_buffer: vec![0.0; buffer_size.max(1)],
+            correction: 0.0,
+            sample_rate,
+        }
+    }
+}
+
+/// Detects the pitch and shifts it to the nearest note in a key and scale
+pub struct AutoPitch {
+    parameters: HashMap<String, PedalParameter>,
+    // Processor only
+    pitch_corrector: Option<PitchCorrector>,
+    // Buffer size and sample rate from set_config, used to recreate the pitch corrector when cloned
+    config: Option<(usize, u32)>,
+    id: u32,
+}
+
+impl Clone for AutoPitch {
+    fn clone(&self) -> Self {
+        AutoPitch {
+            parameters: self.parameters.clone(),
+            pitch_corrector: self.config.map(|(buffer_size, sample_rate)| PitchCorrector::new(buffer_size, sample_rate)),
+            config: self.config,
+            id: self.id
+        }
+    }
+}
+
+impl Serialize for AutoPitch {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser_map = serializer.serialize_map(Some(2))?;
+        ser_map.serialize_entry("id", &self.id)?;
+        ser_map.serialize_entry("parameters", &self.parameters)?;
+        ser_map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for AutoPitch {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct AutoPitchData {
+            id: u32,
+            parameters: HashMap<String, PedalParameter>,
+        }
+        let helper = AutoPitchData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Keys and scales saved before choice parameters were added are indexes into KEY_NAMES and SCALE_NAMES
+        index_to_choice(&mut parameters, "Key", AutoPitch::key_parameter());
+        index_to_choice(&mut parameters, "Scale", AutoPitch::scale_parameter());
+
+        Ok(AutoPitch {
+            parameters,
+            pitch_corrector: None,
+            config: None,
+            id: helper.id
+        })
+    }
+}
+
+impl AutoPitch {
+    pub fn new() -> Self {
+        let mut parameters = HashMap::new();
+
+        parameters.insert("Key".to_string(), Self::key_parameter());
+        parameters.insert("Scale".to_string(), Self::scale_parameter());
+
+        parameters.insert(
+            "Retune Speed".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(50.0),
+                min: Some(PedalParameterValue::Float(0.0)),
+                max: Some(PedalParameterValue::Float(500.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Time in ms taken to glide to the corrected note. Lower values give a more robotic sound.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Active".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Bool(true),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
+            },
+        );
+
+        Self {
+            parameters,
+            pitch_corrector: None,
+            config: None,
+            id: unique_time_id()
+        }
+    }
+
+    /// One of [`KEY_NAMES`], C to B
+    fn key_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::String(KEY_NAMES[0].to_string()),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: Some(KEY_NAMES.iter().map(|name| name.to_string()).collect()),
+            description: Some("Root note of the scale that notes are corrected to.".to_string()),
+            unit: ParameterUnit::None,
+        }
+    }
+
+    /// One of [`SCALE_NAMES`], defaulting to major
+    fn scale_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::String(SCALE_NAMES[1].to_string()),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: Some(SCALE_NAMES.iter().map(|name| name.to_string()).collect()),
+            description: Some("Scale that notes are corrected to.".to_string()),
+            unit: ParameterUnit::None,
+        }
+    }
+
+    pub fn clone_with_new_id(&self) -> Self {
+        let mut cloned = self.clone();
+        cloned.id = unique_time_id();
+        cloned
+    }
+}
+
+impl Hash for AutoPitch {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PedalTrait for AutoPitch {
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn has_tail(&self) -> bool {
+        false
+    }
+
+    fn set_config(&mut self, buffer_size: usize, sample_rate: u32) {
+        self.config = Some((buffer_size, sample_rate));
+        self.pitch_corrector = Some(PitchCorrector::new(buffer_size, sample_rate));
+    }
+
+    fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
+        let key_name = self.parameters["Key"].value.as_str().unwrap();
+        let key = KEY_NAMES.iter().position(|&name| name == key_name).unwrap_or(0) as i16;
+        let scale_name = self.parameters["Scale"].value.as_str().unwrap();
+        let scale = SCALE_NAMES.iter().position(|&name| name == scale_name).unwrap_or(0);
+        let retune_speed_ms = self.parameters["Retune Speed"].value.as_float().unwrap();
+
+        let corrector = match &mut self.pitch_corrector {
+            Some(corrector) => corrector,
+            None => {
+                tracing::warn!("AutoPitch: Call set_config before processing.");
+                return;
+            }
+        };
+
+        corrector.detector_writer.push_slice(buffer);
+        let freq = corrector.detector.process_buffer();
+
+        // Hold the last correction while no pitch is detected
+        if freq > 0.0 {
+            let midi_note = freq_to_midi(freq);
+            let target_correction = nearest_scale_note(midi_note, key, scale) - midi_note;
+
+            if retune_speed_ms <= 0.0 {
+                corrector.correction = target_correction;
+            } else {
+                let retune_samples = (retune_speed_ms / 1000.0) * corrector.sample_rate as f32;
+                let alpha = (-(buffer.len() as f32) / retune_samples).exp();
+                corrector.correction = alpha * corrector.correction + (1.0 - alpha) * target_correction;
+            }
+        }
+
+        corrector.stretch.set_transpose_factor_semitones(corrector.correction, Some(TONALITY_LIMIT_HZ / corrector.sample_rate as f32));
+
+        for chunk in buffer.chunks_mut(corrector.output_buffer.len()) {
+            let output = &mut corrector.output_buffer[..chunk.len()];
+            corrector.stretch.process(chunk.as_ref(), &mut *output);
+            chunk.copy_from_slice(output);
+        }
+    }
+
+    fn reset_buffer(&mut self) {
+        if let Some(corrector) = &mut self.pitch_corrector {
+            corrector.stretch.reset();
+            corrector.correction = 0.0;
+        }
+    }
+
+    fn latency_samples(&self) -> usize {
+        if self.pitch_corrector.is_some() {
+            stretch_latency_samples(STRETCH_BLOCK_SIZE)
+        } else {
+            0
+        }
+    }
+
+    fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
+        &self.parameters
+    }
+
+    fn get_parameters_mut(&mut self) -> &mut HashMap<String, PedalParameter> {
+        &mut self.parameters
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, _message_buffer: &[String]) -> Option<(String,PedalParameterValue)> {
+        let mut to_change = None;
+
+        let mut img_ui = ui.new_child(
+            UiBuilder::new()
+                .max_rect(ui.available_rect_before_wrap())
+        );
+
+        img_ui.add(egui::Image::new(include_image!("images/pedal_gradient.png")).tint(Color32::from_rgb(30, 60, 120)));
+
+        ui.allocate_ui_with_layout(
+            ui.available_size() * Vec2::new(0.9, 1.0),
+            Layout::top_down(egui::Align::Center),
+            |ui| {
+                ui.add_space(20.0);
+                ui.label(RichText::new("Auto Pitch").size(18.0));
+            }
+        );
+
+        let key = self.get_parameters().get("Key").unwrap().value.as_str().unwrap_or("").to_string();
+        let scale = self.get_parameters().get("Scale").unwrap().value.as_str().unwrap_or("").to_string();
+        let knobs = [
+            (key, "Key", Vec2::new(0.05, 0.2)),
+            (scale, "Scale", Vec2::new(0.37, 0.2)),
+            ("Retune".to_string(), "Retune Speed", Vec2::new(0.69, 0.2)),
+        ];
+
+        for (label, name, at) in knobs {
+            let param = self.get_parameters().get(name).unwrap();
+            if let Some(value) = pedal_knob(ui, RichText::new(label).color(Color32::WHITE).size(8.0), name, param, at, 0.26, self.id) {
+                to_change = Some((name.to_string(), value));
+            }
+        }
+
+        let active_param = self.get_parameters().get("Active").unwrap().value.as_bool().unwrap();
+        if let Some(value) = pedal_switch(ui, active_param, egui::Vec2::new(0.33, 0.72), 0.16) {
+            to_change = Some(("Active".to_string(), PedalParameterValue::Bool(value)));
+        }
+
+        to_change
+    }
+}
@@ -3,7 +3,7 @@ use std::hash::Hash;
 
 use crate::dsp_algorithms::moving_bandpass::MovingBandPass;
 use crate::pedals::ui::pedal_switch;
-use crate::pedals::{PedalParameter, PedalParameterValue, PedalTrait};
+use crate::pedals::{PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait};
 use super::ui::pedal_knob;
 
 use eframe::egui::{self, include_image};
@@ -66,6 +66,10 @@ impl AutoWah {
                 min: Some(PedalParameterValue::Float(0.1)),
                 max: Some(PedalParameterValue::Float(2.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Bandwidth of the filter. Lower values give a sharper, more vocal sweep.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
         parameters.insert(
@@ -75,6 +79,10 @@ impl AutoWah {
                 min: Some(PedalParameterValue::Float(100.0)),
                 max: Some(PedalParameterValue::Float(3000.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("How far in Hz the filter sweeps up as you play harder.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
         parameters.insert(
@@ -84,6 +92,10 @@ impl AutoWah {
                 min: Some(PedalParameterValue::Float(50.0)),
                 max: Some(PedalParameterValue::Float(1000.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Filter frequency in Hz when no signal is played.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
         parameters.insert(
@@ -93,6 +105,10 @@ impl AutoWah {
                 min: Some(PedalParameterValue::Float(0.999)),
                 max: Some(PedalParameterValue::Float(0.999999)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("How smoothly the filter follows your playing dynamics. Higher values respond more slowly.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
         parameters.insert(
@@ -102,6 +118,10 @@ impl AutoWah {
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed and filtered signal.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
         parameters.insert(
@@ -111,6 +131,10 @@ impl AutoWah {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
@@ -134,6 +158,10 @@ impl PedalTrait for AutoWah {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
         let (filter, _sample_rate) = match &mut self.filter {
             Some((f, sr)) => (f, sr),
@@ -173,17 +201,13 @@ impl PedalTrait for AutoWah {
     fn set_parameter_value(&mut self,name: &str,value:PedalParameterValue) {
         let parameters = self.get_parameters_mut();
         if let Some(parameter) = parameters.get_mut(name) {
-            if parameter.is_valid(&value) {
-                parameter.value = value;
-
-                if name == "Width" {
-                    let width = parameter.value.as_float().unwrap();
-                    if let Some((bandpass, _)) = &mut self.filter {
-                        bandpass.set_width(width);
-                    }
+            parameter.value = parameter.clamp(value);
+
+            if name == "Width" {
+                let width = parameter.value.as_float().unwrap();
+                if let Some((bandpass, _)) = &mut self.filter {
+                    bandpass.set_width(width);
                 }
-            } else {
-                tracing::warn!("Attempted to set invalid value for parameter {}: {:?}", name, value);
             }
         }
     }
@@ -3,16 +3,19 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::time::{Duration, Instant};
+use crate::dsp_algorithms::{db_to_linear, linear_to_db, sample_delay::SampleDelay};
 use crate::pedals::ui::pedal_switch;
 use crate::DEFAULT_REFRESH_DURATION;
 
-use super::{PedalTrait, PedalParameter, PedalParameterValue};
+use super::{PedalTrait, PedalParameter, PedalParameterValue, ParameterUnit};
 use super::ui::pedal_knob;
 use eframe::egui::{self, include_image, UiBuilder, Vec2};
 use serde::{ser::SerializeMap, Deserialize, Serialize};
 
 const ENVELOPE_UPDATE_RATE: Duration = Duration::from_millis(100);
 const EPS: f32 = 1e-8;
+/// Longest lookahead, which the lookahead delay is allocated for
+const MAX_LOOKAHEAD_MS: f32 = 10.0;
 
 #[derive(Clone)]
 pub struct Compressor {
@@ -26,6 +29,8 @@ pub struct Compressor {
     // Processor only
     envelope_last_sent_time: Instant,
     envelope_last_sent_value: f32,
+    // Delays the audio path relative to the detector
+    lookahead_delay: SampleDelay,
 
     id: u32,
 }
@@ -54,14 +59,27 @@ impl<'de> Deserialize<'de> for Compressor {
         }
 
         let helper = CompressorData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Compressors saved before lookahead was added have none
+        parameters.entry("Lookahead".to_string())
+            .or_insert_with(|| Compressor::lookahead_parameter());
+        // Compressors saved before parameters had units show them as plain numbers
+        ParameterUnit::set_units(&mut parameters, &[
+            ("Threshold", ParameterUnit::Db),
+            ("Soft Knee", ParameterUnit::Db),
+            ("Level", ParameterUnit::GainAsDb)
+        ]);
+
         Ok(Compressor {
             id: helper.id,
-            parameters: helper.parameters,
+            parameters,
             sample_rate: None,
             envelope: 0.0,
             current_envelope: 0.0,
             envelope_last_sent_time: Instant::now(),
             envelope_last_sent_value: 0.0,
+            lookahead_delay: SampleDelay::default(),
         })
     }
 }
@@ -83,6 +101,10 @@ impl Compressor {
                 min: Some(PedalParameterValue::Float(-30.0)),
                 max: Some(PedalParameterValue::Float(0.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Level in dB above which the signal is compressed.".to_string()),
+                unit: ParameterUnit::Db,
             },
         );
         parameters.insert("Attack".into(), PedalParameter {
@@ -90,30 +112,50 @@ impl Compressor {
             min: Some(PedalParameterValue::Float(1.0)),
             max: Some(PedalParameterValue::Float(50.0)),
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("Time in ms taken to start compressing once the signal passes the threshold.".to_string()),
+            unit: ParameterUnit::None,
         });
         parameters.insert("Release".into(), PedalParameter {
             value: PedalParameterValue::Float(100.0),
             min: Some(PedalParameterValue::Float(5.0)),
             max: Some(PedalParameterValue::Float(300.0)),
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("Time in ms taken to stop compressing once the signal falls below the threshold.".to_string()),
+            unit: ParameterUnit::None,
         });
         parameters.insert("Level".into(), PedalParameter {
             value: PedalParameterValue::Float(1.0),
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(5.0)),
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("Output gain applied after compression.".to_string()),
+            unit: ParameterUnit::GainAsDb,
         });
         parameters.insert("Ratio".into(), PedalParameter {
             value: PedalParameterValue::Float(5.0),
             min: Some(PedalParameterValue::Float(1.0)),
             max: Some(PedalParameterValue::Float(20.0)),
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("Amount of compression. At 4, the signal must rise 4dB above the threshold for the output to rise 1dB.".to_string()),
+            unit: ParameterUnit::None,
         });
         parameters.insert("Dry/Wet".into(), PedalParameter {
             value: PedalParameterValue::Float(1.0),
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(1.0)),
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("Mix between the unprocessed and compressed signal, for parallel compression.".to_string()),
+            unit: ParameterUnit::None,
         });
         parameters.insert(
             "Soft Knee".into(),
@@ -122,8 +164,13 @@ impl Compressor {
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(12.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Width in dB of the region around the threshold where compression is gradually applied.".to_string()),
+                unit: ParameterUnit::Db,
             },
         );
+        parameters.insert("Lookahead".into(), Self::lookahead_parameter());
         parameters.insert(
             "Active".to_string(),
             PedalParameter {
@@ -131,6 +178,10 @@ impl Compressor {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
@@ -141,10 +192,37 @@ impl Compressor {
             sample_rate: None,
             envelope_last_sent_time: Instant::now(),
             envelope_last_sent_value: 0.0,
+            lookahead_delay: SampleDelay::default(),
             id: crate::unique_time_id(),
         }
     }
 
+    /// Lookahead in ms
+    fn lookahead_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::Float(0.0),
+            min: Some(PedalParameterValue::Float(0.0)),
+            max: Some(PedalParameterValue::Float(MAX_LOOKAHEAD_MS)),
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Time in ms the signal is delayed by so the compressor can react to peaks before they arrive. Adds latency.".to_string()),
+            unit: ParameterUnit::None,
+        }
+    }
+
+    fn lookahead_samples(lookahead_ms: f32, sample_rate: f32) -> usize {
+        ((lookahead_ms / 1000.0) * sample_rate) as usize
+    }
+
+    /// Only changes the delay, as the buffer is allocated for the longest lookahead in set_config
+    fn update_lookahead(&mut self) {
+        if let Some(sample_rate) = self.sample_rate {
+            let lookahead_ms = self.parameters["Lookahead"].value.as_float().unwrap();
+            self.lookahead_delay.set_delay(Self::lookahead_samples(lookahead_ms, sample_rate));
+        }
+    }
+
     pub fn clone_with_new_id(&self) -> Self {
         let mut cloned = self.clone();
         cloned.id = crate::unique_time_id();
@@ -157,8 +235,14 @@ impl PedalTrait for Compressor {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn set_config(&mut self, _buffer_size: usize, sample_rate: u32) {
         self.sample_rate = Some(sample_rate as f32);
+        self.lookahead_delay = SampleDelay::new(Self::lookahead_samples(MAX_LOOKAHEAD_MS, sample_rate as f32));
+        self.update_lookahead();
     }
 
     fn process_audio(&mut self, buffer: &mut [f32], messages: &mut Vec<String>) {
@@ -190,9 +274,11 @@ impl PedalTrait for Compressor {
                 release_coeff * (self.envelope - sample.abs()) + sample.abs()
             };
 
-            // Convert envelope to dB
-            let env_lin = (self.envelope).max(EPS);
-            let env_db = 20.0 * env_lin.log10();
+            // The detector sees the sample before the audio path does.
+            // Both the dry and compressed signals are delayed so they stay in phase.
+            *sample = self.lookahead_delay.process(*sample);
+
+            let env_db = linear_to_db(self.envelope);
 
             let knee_start = threshold_db - soft_knee_db / 2.0;
             let knee_end = threshold_db + soft_knee_db / 2.0;
@@ -209,7 +295,7 @@ impl PedalTrait for Compressor {
 
             // gain in dB to apply = out_db - env_db (usually <= 0)
             let gain_db = out_db - env_db;
-            let gain_lin = 10f32.powf(gain_db / 20.0);
+            let gain_lin = db_to_linear(gain_db);
 
             let compressed_sample = *sample * gain_lin * level;
 
@@ -232,6 +318,15 @@ impl PedalTrait for Compressor {
 
     fn reset_buffer(&mut self) {
         self.envelope = 0.0;
+        self.lookahead_delay.reset();
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.lookahead_delay.delay()
+    }
+
+    fn max_latency_samples(&self) -> usize {
+        self.lookahead_delay.max_delay()
     }
 
     fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
@@ -245,10 +340,9 @@ impl PedalTrait for Compressor {
     fn set_parameter_value(&mut self,name: &str,value:PedalParameterValue){
         let parameters = self.get_parameters_mut();
         if let Some(parameter) = parameters.get_mut(name){
-            if parameter.is_valid(&value){
-                parameter.value = value;
-            } else {
-                tracing::warn!("Attempted to set invalid value for parameter {}: {:?}",name,value);
+            parameter.value = parameter.clamp(value);
+            if name == "Lookahead" {
+                self.update_lookahead();
             }
         }
     }
@@ -317,7 +411,7 @@ impl PedalTrait for Compressor {
 
         draw_compressor_graph(
             &mut graph_ui,
-            20.0*self.current_envelope.log10(), // dB conversion
+            linear_to_db(self.current_envelope),
             self.parameters["Threshold"].value.as_float().unwrap(),
             self.parameters["Ratio"].value.as_float().unwrap(),
             self.parameters["Soft Knee"].value.as_float().unwrap(),
@@ -2,11 +2,11 @@ use std::collections::{HashMap, VecDeque};
 use std::iter;
 use std::hash::Hash;
 
-use crate::dsp_algorithms::{biquad, eq};
+use crate::dsp_algorithms::{biquad, denormal::flush_denormal, eq};
 use crate::pedals::ui::pedal_switch;
 use crate::unique_time_id;
 use super::ui::pedal_knob;
-use super::{PedalParameter, PedalParameterValue, PedalTrait};
+use super::{PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait};
 
 use eframe::egui::{self, include_image};
 use serde::ser::SerializeMap;
@@ -52,9 +52,24 @@ impl<'de> Deserialize<'de> for Delay {
         }
 
         let helper = DelayData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Pedals saved before Kill Dry was added keep their dry signal
+        parameters.entry("Kill Dry".to_string())
+            .or_insert_with(|| PedalParameter {
+                value: PedalParameterValue::Bool(false),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Only output the repeats at full level, ignoring Dry/Wet, for use in a parallel effects chain.".to_string()),
+                unit: ParameterUnit::None
+            });
+
         Ok(Delay {
             id: helper.id,
-            parameters: helper.parameters,
+            parameters,
             delay_buffer: None,
             tone_eq: None,
             sample_rate: None,
@@ -75,7 +90,11 @@ impl Delay {
                 value: PedalParameterValue::Float(init_delay),
                 min: Some(PedalParameterValue::Float(10.0)),
                 max: Some(PedalParameterValue::Float(1000.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Time in ms between repeats.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -84,7 +103,11 @@ impl Delay {
                 value: PedalParameterValue::Float(0.5),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Level of each repeat relative to the previous one. Higher values give more repeats.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -93,7 +116,11 @@ impl Delay {
                 value: PedalParameterValue::Float(0.5),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed and delayed signal.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -102,7 +129,24 @@ impl Delay {
                 value: PedalParameterValue::Float(init_warmth),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Darkens each repeat, like an analog or tape delay.".to_string()),
+                unit: ParameterUnit::None
+            },
+        );
+        parameters.insert(
+            "Kill Dry".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Bool(false),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Only output the repeats at full level, ignoring Dry/Wet, for use in a parallel effects chain.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
         parameters.insert(
@@ -112,6 +156,10 @@ impl Delay {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
@@ -162,14 +210,17 @@ impl PedalTrait for Delay {
 
         let decay = self.parameters.get("Decay").unwrap().value.as_float().unwrap();
         let mix = self.parameters.get("Dry/Wet").unwrap().value.as_float().unwrap();
+        let kill_dry = self.parameters.get("Kill Dry").unwrap().value.as_bool().unwrap();
+        // With the dry killed the repeats are output at full level, rather than scaled by the mix
+        let (dry, wet) = if kill_dry { (0.0, 1.0) } else { (1.0 - mix, mix) };
         for sample in buffer.iter_mut() {
             let delay_sample = self.delay_buffer.as_mut().unwrap().pop_front().unwrap();
 
             let mut new_sample = *sample + (delay_sample * decay);
-            new_sample = self.tone_eq.as_mut().unwrap().process(new_sample);
+            new_sample = flush_denormal(self.tone_eq.as_mut().unwrap().process(new_sample));
             self.delay_buffer.as_mut().unwrap().push_back(new_sample);
 
-            *sample = *sample * (1.0 - mix) + delay_sample * mix;
+            *sample = *sample * dry + delay_sample * wet;
         }
     }
 
@@ -190,33 +241,32 @@ impl PedalTrait for Delay {
     fn set_parameter_value(&mut self, name: &str, value: PedalParameterValue) {
         let parameters = self.get_parameters_mut();
         if let Some(parameter) = parameters.get_mut(name) {
-            if parameter.is_valid(&value) {
-                if name == "Delay" {
-                    let delay_ms = value.as_float().unwrap();
-                    parameter.value = value;
-
-                    if let Some(delay_buffer) = &mut self.delay_buffer {
-                        if let Some(sample_rate) = self.sample_rate {
-                            let delay_samples = ((delay_ms / 1000.0) * sample_rate) as usize;
-                            if delay_samples > delay_buffer.len() {
-                                delay_buffer.extend(iter::repeat(0.0).take(delay_samples - delay_buffer.len()));
-                            } else {
-                                delay_buffer.truncate(delay_samples);
-                            }
-                        }
-                    }
-                } else if name == "Warmth" {
-                    let warmth = value.as_float().unwrap();
-                    parameter.value = value;
+            let value = parameter.clamp(value);
+            if name == "Delay" {
+                let delay_ms = value.as_float().unwrap();
+                parameter.value = value;
+
+                if let Some(delay_buffer) = &mut self.delay_buffer {
                     if let Some(sample_rate) = self.sample_rate {
-                        self.tone_eq = Some(
-                            Self::eq_from_warmth(warmth, sample_rate)
-                        );
+                        let delay_samples = ((delay_ms / 1000.0) * sample_rate) as usize;
+                        if delay_samples > delay_buffer.len() {
+                            delay_buffer.extend(iter::repeat(0.0).take(delay_samples - delay_buffer.len()));
+                        } else {
+                            delay_buffer.truncate(delay_samples);
+                        }
                     }
-                    
-                } else {
-                    parameter.value = value;
                 }
+            } else if name == "Warmth" {
+                let warmth = value.as_float().unwrap();
+                parameter.value = value;
+                if let Some(sample_rate) = self.sample_rate {
+                    self.tone_eq = Some(
+                        Self::eq_from_warmth(warmth, sample_rate)
+                    );
+                }
+                
+            } else {
+                parameter.value = value;
             }
         }
     }
@@ -9,6 +9,7 @@ use crate::unique_time_id;
 use super::PedalTrait;
 use super::PedalParameter;
 use super::PedalParameterValue;
+use super::ParameterUnit;
 use super::ui::{pedal_knob, pedal_switch};
 
 use eframe::egui::Image;
@@ -75,7 +76,11 @@ impl Distortion {
                 value: PedalParameterValue::Float(10.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(50.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Amount of gain before clipping.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -84,7 +89,11 @@ impl Distortion {
                 value: PedalParameterValue::Float(0.5),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Brightness of the output. Lower values are darker.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -93,7 +102,11 @@ impl Distortion {
                 value: PedalParameterValue::Float(1.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(3.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Output volume.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -102,7 +115,11 @@ impl Distortion {
                 value: PedalParameterValue::Bool(true),
                 min: None,
                 max: None,
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -111,7 +128,11 @@ impl Distortion {
             value: PedalParameterValue::Float(0.5),
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(1.0)),
-            step: None
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Clips the positive and negative halves of the wave differently, adding even harmonics.".to_string()),
+            unit: ParameterUnit::None
         },
     );
         Distortion { parameters, low_tilt: None, high_tilt: None, sample_rate: None, id: unique_time_id() }
@@ -146,6 +167,10 @@ impl PedalTrait for Distortion {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn set_config(&mut self,_buffer_size:usize, sample_rate: u32) {
         self.sample_rate = Some(sample_rate as f32);
         let (low_tilt, high_tilt) = Self::post_eq(sample_rate as f32);
@@ -196,11 +221,7 @@ impl PedalTrait for Distortion {
     fn set_parameter_value(&mut self,name: &str,value:PedalParameterValue) {
         let parameters = self.get_parameters_mut();
         if let Some(parameter) = parameters.get_mut(name) {
-            if parameter.is_valid(&value) {
-                parameter.value = value;
-            } else {
-                tracing::warn!("Attempted to set invalid value for parameter {}: {:?}", name, value);
-            }
+            parameter.value = parameter.clamp(value);
         }
     }
 
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, time::Instant};
+use std::collections::HashMap;
 use std::hash::Hash;
 
 use eframe::egui::{self, include_image, Color32, Image, ImageButton, UiBuilder, Vec2};
@@ -6,41 +6,11 @@ use egui_plot::{HLine, Line, Plot, PlotPoint, VLine};
 use serde::ser::SerializeMap;
 use serde::{Deserialize, Serialize};
 
-use super::{PedalParameter, PedalParameterValue, PedalTrait};
+use super::{live_spectrum::{LiveSpectrum, PlotRange}, PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait};
 
-use crate::{dsp_algorithms::{eq::{self, Equalizer}, frequency_analysis::FrequencyAnalyser}, pedals::ui::pedal_knob, unique_time_id, DEFAULT_REFRESH_DURATION};
+use crate::{dsp_algorithms::eq::{self, Equalizer}, pedals::ui::pedal_knob, unique_time_id};
 
-const PLOT_POINTS: usize = 80;
-const LIVE_FREQUENCY_UPDATE_MS: usize = 100;
 const EQ_DB_GAIN: f32 = 15.0;
-const OVERSAMPLE: f32 = 10.0;
-
-pub fn serialize_plot_points(plot_points: &mut [PlotPoint]) -> String {
-    // First round the points to 2 decimal places to reduce size
-    for point in plot_points.iter_mut() {
-        point.x = (point.x * 100.0).round() / 100.0;
-        point.y = (point.y * 100.0).round() / 100.0;
-    }
-
-    // Hoping the compiler will optimise this
-    let plot_points_floats: Vec<[f64; 2]> = plot_points.iter()
-        .map(|p| [p.x, p.y])
-        .collect();
-
-    serde_json::to_string(&plot_points_floats).expect("Failed to serialize plot points")
-}
-
-pub fn deserialize_plot_points(data: &str) -> serde_json::Result<Vec<PlotPoint>> {
-    let plot_points_floats: Vec<[f64; 2]> = serde_json::from_str(data)?;
-
-    // Hoping the compiler will optimise this
-    let plot_points = plot_points_floats.into_iter()
-        .map(|p| PlotPoint::new(p[0], p[1]))
-        .collect::<Vec<PlotPoint>>();
-
-    Ok(plot_points)
-}
-
 
 pub struct GraphicEq7 {
     parameters: HashMap<String, PedalParameter>,
@@ -48,18 +18,8 @@ pub struct GraphicEq7 {
     sample_rate: f32,
     id: u32,
     response_plot: Vec<PlotPoint>,
-
-    // Only exists on processor
-    frequency_analyser: Option<FrequencyAnalyser>,
-    last_frequencies_sent: Instant,
-
-    // Used for smoothing the frequency plot
-    prev_live_frequency_plot: Vec<PlotPoint>,
-    target_live_frequency_plot: Vec<PlotPoint>,
-    last_frame: Instant,
-
-    // Used to clamp the live frequency plot values
-    dynamic_max: f32
+    response_plot_range: PlotRange,
+    live_spectrum: LiveSpectrum
 }
 
 impl Clone for GraphicEq7 {
@@ -70,12 +30,8 @@ impl Clone for GraphicEq7 {
             sample_rate: self.sample_rate,
             id: self.id,
             response_plot: self.response_plot.clone(),
-            frequency_analyser: None,
-            last_frequencies_sent: Instant::now(),
-            prev_live_frequency_plot: Vec::with_capacity(PLOT_POINTS),
-            target_live_frequency_plot: Vec::with_capacity(PLOT_POINTS),
-            last_frame: Instant::now(),
-            dynamic_max: 0.0
+            response_plot_range: self.response_plot_range,
+            live_spectrum: self.live_spectrum.clone()
         }
     }
 }
@@ -120,8 +76,16 @@ impl<'a> Deserialize<'a> for GraphicEq7 {
                 value: PedalParameterValue::Bool(false),
                 min: None,
                 max: None,
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Show the frequency spectrum of the output. Uses extra CPU.".to_string()),
+                unit: ParameterUnit::None
             });
+        // The plot range is a client setting, and was briefly saved as parameters
+        for name in ["Plot Points", "Plot Min Frequency", "Plot Max Frequency"] {
+            parameters.remove(name);
+        }
 
         let high_shelf_enabled = parameters.get("High Shelf")
             .and_then(|p| p.value.as_float())
@@ -139,16 +103,12 @@ impl<'a> Deserialize<'a> for GraphicEq7 {
         );
         Ok(GraphicEq7 {
             parameters,
-            response_plot: Self::amplitude_response_plot(&eq, 48000.0),
+            response_plot: Self::amplitude_response_plot(&eq, 48000.0, PlotRange::default()),
+            response_plot_range: PlotRange::default(),
             eq,
             sample_rate: 48000.0, // Default sample rate, can be set later
             id: helper.id,
-            prev_live_frequency_plot: Vec::with_capacity(PLOT_POINTS),
-            target_live_frequency_plot: Vec::with_capacity(PLOT_POINTS),
-            last_frame: Instant::now(),
-            frequency_analyser: None,
-            last_frequencies_sent: Instant::now(),
-            dynamic_max: 0.0
+            live_spectrum: LiveSpectrum::new(EQ_DB_GAIN)
         })
     }
 }
@@ -167,7 +127,11 @@ impl GraphicEq7 {
                     value: PedalParameterValue::Float(init_gain),
                     min: Some(PedalParameterValue::Float(-EQ_DB_GAIN)),
                     max: Some(PedalParameterValue::Float(EQ_DB_GAIN)),
-                    step: Some(PedalParameterValue::Float(0.1))
+                    step: Some(PedalParameterValue::Float(0.1)),
+                    snap: false,
+                    choices: None,
+                    description: Some("Boost or cut in dB for this band.".to_string()),
+                    unit: ParameterUnit::None
                 },
             );
             parameters.insert(
@@ -176,7 +140,11 @@ impl GraphicEq7 {
                     value: PedalParameterValue::Float(init_bandwidth),
                     min: Some(PedalParameterValue::Float(0.1)),
                     max: Some(PedalParameterValue::Float(2.0)),
-                    step: Some(PedalParameterValue::Float(0.01))
+                    step: Some(PedalParameterValue::Float(0.01)),
+                    snap: false,
+                    choices: None,
+                    description: Some("Width of this band in octaves.".to_string()),
+                    unit: ParameterUnit::None
                 },
             );
         }
@@ -187,7 +155,11 @@ impl GraphicEq7 {
                 value: PedalParameterValue::Float(0.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: Some(PedalParameterValue::Float(1.0))
+                step: Some(PedalParameterValue::Float(1.0)),
+                snap: false,
+                choices: None,
+                description: Some("Use a shelf for the lowest band, boosting or cutting everything below it.".to_string()),
+                unit: ParameterUnit::None
             },
         );
 
@@ -197,7 +169,11 @@ impl GraphicEq7 {
                 value: PedalParameterValue::Float(1.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: Some(PedalParameterValue::Float(1.0))
+                step: Some(PedalParameterValue::Float(1.0)),
+                snap: false,
+                choices: None,
+                description: Some("Use a shelf for the highest band, boosting or cutting everything above it.".to_string()),
+                unit: ParameterUnit::None
             },
         );
 
@@ -207,7 +183,11 @@ impl GraphicEq7 {
                 value: PedalParameterValue::Bool(false),
                 min: None,
                 max: None,
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Show the frequency spectrum of the output. Uses extra CPU.".to_string()),
+                unit: ParameterUnit::None
             },
         );
 
@@ -217,7 +197,11 @@ impl GraphicEq7 {
                 value: PedalParameterValue::Float(1.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed and equalised signal.".to_string()),
+                unit: ParameterUnit::None
             },
         );
 
@@ -228,23 +212,23 @@ impl GraphicEq7 {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
         let eq = Self::build_eq([init_bandwidth; 7], [init_gain; 7], true, false, 48000.0);
 
         GraphicEq7 {
-            response_plot: Self::amplitude_response_plot(&eq, 48000.0),
+            response_plot: Self::amplitude_response_plot(&eq, 48000.0, PlotRange::default()),
+            response_plot_range: PlotRange::default(),
             id: unique_time_id(),
             parameters,
             eq,
             sample_rate: 48000.0, // Default sample rate, can be set later
-            prev_live_frequency_plot: Vec::with_capacity(PLOT_POINTS),
-            target_live_frequency_plot: Vec::with_capacity(PLOT_POINTS),
-            last_frame: Instant::now(),
-            frequency_analyser: None,
-            last_frequencies_sent: Instant::now(),
-            dynamic_max: 0.0
+            live_spectrum: LiveSpectrum::new(EQ_DB_GAIN)
         }
     }
 
@@ -254,12 +238,8 @@ impl GraphicEq7 {
         cloned
     }
 
-    pub fn frequency_analyser(sample_rate: f32) -> FrequencyAnalyser {
-        FrequencyAnalyser::new(sample_rate, 60.0, 11000.0, PLOT_POINTS, OVERSAMPLE)
-    }
-
-    pub fn amplitude_response_plot(eq: &Equalizer, sample_rate: f32) -> Vec<PlotPoint> {
-        eq.amplitude_response_plot(sample_rate as f64, 60.0, 11000.0, PLOT_POINTS)
+    pub fn amplitude_response_plot(eq: &Equalizer, sample_rate: f32, range: PlotRange) -> Vec<PlotPoint> {
+        eq.amplitude_response_plot(sample_rate as f64, range.min_frequency as f64, range.max_frequency as f64, range.points)
     }
 
     pub fn get_gains(parameters: &HashMap<String, PedalParameter>) -> [f32; 7] {
@@ -310,6 +290,10 @@ impl PedalTrait for GraphicEq7 {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn process_audio(&mut self, buffer: &mut [f32], message_buffer: &mut Vec<String>) {
         let dry_wet = self.parameters.get("Dry/Wet").unwrap().value.as_float().unwrap();
         for sample in buffer.iter_mut() {
@@ -317,26 +301,13 @@ impl PedalTrait for GraphicEq7 {
         }
 
         if self.parameters.get("Live Frequency Plot").unwrap().value.as_bool().unwrap() {
-            let frequency_analyser = self.frequency_analyser.as_mut().expect("Frequency Analyser should not be None on processor");
-            frequency_analyser.push_samples(buffer);
-
-            // Check if enough time has passed since the last update
-            if self.last_frequencies_sent.elapsed().as_millis() as usize >= LIVE_FREQUENCY_UPDATE_MS {
-                if frequency_analyser.analyse_log2(&mut self.target_live_frequency_plot) {
-                    // New frequency data available, serialize and send to client
-                    self.last_frequencies_sent = Instant::now();
-                    let message = serialize_plot_points(&mut self.target_live_frequency_plot);
-                    message_buffer.push(message);
-                }
-            }
+            self.live_spectrum.process(buffer, message_buffer);
         }
     }
 
     fn set_config(&mut self, _buffer_size:usize, sample_rate:u32) {
         self.sample_rate = sample_rate as f32;
-        if self.frequency_analyser.is_none() {
-            self.frequency_analyser = Some(Self::frequency_analyser(self.sample_rate));
-        }
+        self.live_spectrum.set_config(self.sample_rate);
         self.eq = Self::build_eq(
             Self::get_bandwidths(&self.parameters),
             Self::get_gains(&self.parameters),
@@ -356,17 +327,15 @@ impl PedalTrait for GraphicEq7 {
 
     fn set_parameter_value(&mut self, name: &str, value: PedalParameterValue) {
         if let Some(param) = self.parameters.get_mut(name) {
-            if param.is_valid(&value) {
-                param.value = value;
-
-                if name.starts_with("Gain ") || name.starts_with("Bandwidth ") || name == "Low Shelf" || name == "High Shelf" {
-                    let low_shelf = self.parameters.get("Low Shelf").unwrap().value.as_float().unwrap() > 0.0;
-                    let high_shelf = self.parameters.get("High Shelf").unwrap().value.as_float().unwrap() > 0.0;
-                    let gains = Self::get_gains(&self.parameters);
-                    let bandwidths = Self::get_bandwidths(&self.parameters);
-                    self.eq = Self::build_eq(bandwidths, gains, high_shelf, low_shelf, self.sample_rate);
-                    self.response_plot = Self::amplitude_response_plot(&self.eq, self.sample_rate);
-                }
+            param.value = param.clamp(value);
+
+            if name.starts_with("Gain ") || name.starts_with("Bandwidth ") || name == "Low Shelf" || name == "High Shelf" {
+                let low_shelf = self.parameters.get("Low Shelf").unwrap().value.as_float().unwrap() > 0.0;
+                let high_shelf = self.parameters.get("High Shelf").unwrap().value.as_float().unwrap() > 0.0;
+                let gains = Self::get_gains(&self.parameters);
+                let bandwidths = Self::get_bandwidths(&self.parameters);
+                self.eq = Self::build_eq(bandwidths, gains, high_shelf, low_shelf, self.sample_rate);
+                self.response_plot = Self::amplitude_response_plot(&self.eq, self.sample_rate, self.response_plot_range);
             }
         }
     }
@@ -375,42 +344,15 @@ impl PedalTrait for GraphicEq7 {
         let live_frequency_enabled = self.parameters.get("Live Frequency Plot").unwrap().value.as_bool().unwrap();
         if live_frequency_enabled {
             // Update the live frequency plot smoothly
-            if self.prev_live_frequency_plot.is_empty() {
-                self.prev_live_frequency_plot = self.target_live_frequency_plot.clone();
-            }
-
-            let time_since_last_frame_ms = self.last_frame.elapsed().as_millis() as usize;
-            let smooth_factor = (time_since_last_frame_ms as f64 / LIVE_FREQUENCY_UPDATE_MS as f64).min(1.0);
-            self.last_frame = Instant::now();
-
-            for (prev, target) in self.prev_live_frequency_plot.iter_mut().zip(self.target_live_frequency_plot.iter()) {
-                prev.x = prev.x * (1.0 - smooth_factor) + target.x * smooth_factor;
-                prev.y = prev.y * (1.0 - smooth_factor) + target.y * smooth_factor;
-            }
-
-            ui.ctx().request_repaint_after(DEFAULT_REFRESH_DURATION);
+            self.live_spectrum.update(ui.ctx());
         }
 
-        if message_buffer.len() > 0 {
-            // Deserialize the frequency response plot from the message buffer
-            if let Ok(mut plot_points) = deserialize_plot_points(&message_buffer[0]) {
-                // Scale plot points to 0-EQ_DB_GAIN
-                let max_value = plot_points.iter()
-                    .map(|p| p.y)
-                    .fold(f64::NEG_INFINITY, |a, b| a.max(b));
+        self.live_spectrum.receive(message_buffer);
 
-                // Smoothly adjust dynamic max
-                self.dynamic_max = (self.dynamic_max*0.9).max(max_value as f32);
-
-                let scale_factor = EQ_DB_GAIN / self.dynamic_max as f32;
-                for point in plot_points.iter_mut() {
-                    point.y *= scale_factor as f64;
-                }
-
-                self.target_live_frequency_plot = plot_points;
-            } else {
-                tracing::error!("Failed to deserialize frequency response plot");
-            }
+        let plot_range = PlotRange::get(ui.ctx());
+        if plot_range != self.response_plot_range {
+            self.response_plot_range = plot_range;
+            self.response_plot = Self::amplitude_response_plot(&self.eq, self.sample_rate, plot_range);
         }
 
         let mut changed_param = None;
@@ -532,7 +474,7 @@ impl PedalTrait for GraphicEq7 {
 
                 if self.parameters.get("Live Frequency Plot").unwrap().value.as_bool().unwrap() {
                     plot_ui.line(
-                        Line::new("live_frequency", self.prev_live_frequency_plot.as_slice())
+                        Line::new("live_frequency", plot_range.clip(self.live_spectrum.plot_points()))
                             .color(Color32::from_rgb(200, 0, 0))
                             .width(1.0)
                     );
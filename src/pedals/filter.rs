@@ -0,0 +1,279 @@
+use std::{collections::HashMap, hash::Hash};
+use eframe::egui::{self, include_image, Color32, Layout, RichText, UiBuilder, Vec2};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
+
+use crate::{dsp_algorithms::biquad::BiquadFilter, pedals::ui::pedal_switch, unique_time_id};
+
+use super::{
+    index_to_choice, ui::pedal_knob,
+    PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait,
+};
+
+/// Names of the filter types, chosen by the Type parameter
+pub const FILTER_TYPE_NAMES: [&str; 7] = ["Low Pass", "High Pass", "Band Pass", "Notch", "Low Shelf", "High Shelf", "Peak"];
+
+/// A single biquad filter, for basic tone shaping
+#[derive(Clone)]
+pub struct Filter {
+    parameters: HashMap<String, PedalParameter>,
+    // Processor only, created in set_config
+    filter: Option<BiquadFilter>,
+    sample_rate: Option<f32>,
+    id: u32,
+}
+
+impl Serialize for Filter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser_map = serializer.serialize_map(Some(2))?;
+        ser_map.serialize_entry("id", &self.id)?;
+        ser_map.serialize_entry("parameters", &self.parameters)?;
+        ser_map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct FilterData {
+            id: u32,
+            parameters: HashMap<String, PedalParameter>,
+        }
+        let helper = FilterData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Types saved before choice parameters were added are indexes into FILTER_TYPE_NAMES
+        index_to_choice(&mut parameters, "Type", Filter::type_parameter());
+
+        Ok(Filter {
+            parameters,
+            filter: None,
+            sample_rate: None,
+            id: helper.id
+        })
+    }
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        let mut parameters = HashMap::new();
+
+        parameters.insert("Type".to_string(), Self::type_parameter());
+
+        parameters.insert(
+            "Cutoff".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(1000.0),
+                min: Some(PedalParameterValue::Float(20.0)),
+                max: Some(PedalParameterValue::Float(20000.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Cutoff or centre frequency of the filter in Hz.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Q".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(std::f32::consts::FRAC_1_SQRT_2),
+                min: Some(PedalParameterValue::Float(0.1)),
+                max: Some(PedalParameterValue::Float(10.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Resonance of the filter. Higher values give a sharper peak or narrower band.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Gain".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(0.0),
+                min: Some(PedalParameterValue::Float(-18.0)),
+                max: Some(PedalParameterValue::Float(18.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Boost or cut in dB. Only used by the shelf and peak filters.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Active".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Bool(true),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None
+            },
+        );
+
+        Filter { parameters, filter: None, sample_rate: None, id: unique_time_id() }
+    }
+
+    /// One of [`FILTER_TYPE_NAMES`]
+    fn type_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::String(FILTER_TYPE_NAMES[0].to_string()),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: Some(FILTER_TYPE_NAMES.iter().map(|name| name.to_string()).collect()),
+            description: Some("Type of filter: low pass, high pass, band pass, notch, low shelf, high shelf or peak.".to_string()),
+            unit: ParameterUnit::None,
+        }
+    }
+
+    /// Create the filter for the current parameters
+    fn build_filter(&self, sample_rate: f32) -> BiquadFilter {
+        let p = &self.parameters;
+        let filter_type = p["Type"].value.as_str().unwrap();
+        // Keep the cutoff below nyquist, where the coefficients are unstable
+        let cutoff = p["Cutoff"].value.as_float().unwrap().min(sample_rate * 0.45);
+        let q = p["Q"].value.as_float().unwrap();
+        let gain = p["Gain"].value.as_float().unwrap();
+
+        match filter_type {
+            "High Pass" => BiquadFilter::high_pass(cutoff, sample_rate, q),
+            "Band Pass" => BiquadFilter::band_pass(cutoff, sample_rate, q),
+            "Notch" => BiquadFilter::notch(cutoff, sample_rate, q),
+            "Low Shelf" => BiquadFilter::low_shelf(cutoff, sample_rate, q, gain),
+            "High Shelf" => BiquadFilter::high_shelf(cutoff, sample_rate, q, gain),
+            "Peak" => BiquadFilter::peaking(cutoff, sample_rate, q, gain),
+            _ => BiquadFilter::low_pass(cutoff, sample_rate, q),
+        }
+    }
+
+    /// Recompute the coefficients, keeping the filter state so there is no click
+    fn update_filter(&mut self) {
+        if let Some(sample_rate) = self.sample_rate {
+            let mut filter = self.build_filter(sample_rate);
+            if let Some(old_filter) = &self.filter {
+                filter.x = old_filter.x;
+                filter.y = old_filter.y;
+            }
+            self.filter = Some(filter);
+        }
+    }
+
+    pub fn clone_with_new_id(&self) -> Self {
+        let mut cloned = self.clone();
+        cloned.id = unique_time_id();
+        cloned
+    }
+}
+
+impl Hash for Filter {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PedalTrait for Filter {
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn has_tail(&self) -> bool {
+        false
+    }
+
+    fn set_config(&mut self, _buffer_size: usize, sample_rate: u32) {
+        self.sample_rate = Some(sample_rate as f32);
+        self.filter = Some(self.build_filter(sample_rate as f32));
+    }
+
+    fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
+        let filter = match &mut self.filter {
+            Some(filter) => filter,
+            None => {
+                tracing::warn!("Filter: Sample rate not set. Call set_config first.");
+                return;
+            }
+        };
+
+        for sample in buffer.iter_mut() {
+            *sample = filter.process(*sample);
+        }
+    }
+
+    fn reset_buffer(&mut self) {
+        if let Some(filter) = &mut self.filter {
+            filter.x = [0.0; 2];
+            filter.y = [0.0; 2];
+        }
+    }
+
+    fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
+        &self.parameters
+    }
+
+    fn get_parameters_mut(&mut self) -> &mut HashMap<String, PedalParameter> {
+        &mut self.parameters
+    }
+
+    fn set_parameter_value(&mut self, name: &str, value: PedalParameterValue) {
+        if let Some(parameter) = self.parameters.get_mut(name) {
+            parameter.value = parameter.clamp(value);
+            if name != "Active" {
+                self.update_filter();
+            }
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, _message_buffer: &[String]) -> Option<(String,PedalParameterValue)> {
+        let mut to_change = None;
+
+        let mut img_ui = ui.new_child(
+            UiBuilder::new()
+                .max_rect(ui.available_rect_before_wrap())
+        );
+
+        img_ui.add(egui::Image::new(include_image!("images/pedal_gradient.png")).tint(Color32::from_rgb(35, 95, 100)));
+
+        ui.allocate_ui_with_layout(
+            ui.available_size() * Vec2::new(0.9, 1.0),
+            Layout::top_down(egui::Align::Center),
+            |ui| {
+                ui.add_space(20.0);
+                ui.label(RichText::new("Filter").size(20.0));
+            }
+        );
+
+        let filter_type = self.get_parameters().get("Type").unwrap().value.as_str().unwrap_or("").to_string();
+        let knobs = [
+            (filter_type, "Type", Vec2::new(0.05, 0.17)),
+            ("Cutoff".to_string(), "Cutoff", Vec2::new(0.37, 0.17)),
+            ("Q".to_string(), "Q", Vec2::new(0.69, 0.17)),
+            ("Gain".to_string(), "Gain", Vec2::new(0.37, 0.42)),
+        ];
+
+        for (label, name, at) in knobs {
+            let param = self.get_parameters().get(name).unwrap();
+            if let Some(value) = pedal_knob(ui, RichText::new(label).color(Color32::WHITE).size(8.0), name, param, at, 0.26, self.id) {
+                to_change = Some((name.to_string(), value));
+            }
+        }
+
+        let active_param = self.get_parameters().get("Active").unwrap().value.as_bool().unwrap();
+        if let Some(value) = pedal_switch(ui, active_param, egui::Vec2::new(0.33, 0.72), 0.16) {
+            to_change = Some(("Active".to_string(), PedalParameterValue::Bool(value)));
+        }
+
+        to_change
+    }
+}
@@ -1,24 +1,76 @@
 use std::collections::HashMap;
 use std::hash::Hash;
+use crate::dsp_algorithms::db_to_linear;
 use crate::pedals::ui::pedal_switch;
 use crate::unique_time_id;
 
 use super::PedalTrait;
 use super::PedalParameter;
 use super::PedalParameterValue;
+use super::ParameterUnit;
 use super::ui::pedal_knob;
 
 use eframe::egui;
 use eframe::egui::include_image;
-use eframe::egui::Vec2;
-use serde::{Serialize, Deserialize};
+use eframe::egui::{Color32, RichText, Vec2};
+use serde::{ser::SerializeMap, Serialize, Deserialize};
 
-#[derive(Serialize, Deserialize, Clone)]
+/// Attack of the post gate
+const GATE_ATTACK_MS: f32 = 1.0;
+
+#[derive(Clone)]
 pub struct Fuzz {
     parameters: HashMap<String, PedalParameter>,
+    // Processor only, post gate state kept across buffers
+    sample_rate: Option<f32>,
+    gate_envelope: f32,
+    gate_gain: f32,
     id: u32
 }
 
+impl Serialize for Fuzz {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser_map = serializer.serialize_map(Some(2))?;
+        ser_map.serialize_entry("id", &self.id)?;
+        ser_map.serialize_entry("parameters", &self.parameters)?;
+        ser_map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Fuzz {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct FuzzData {
+            id: u32,
+            parameters: HashMap<String, PedalParameter>,
+        }
+        let helper = FuzzData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Fuzzes saved before the post gate was added
+        parameters.entry("Gate Threshold".to_string())
+            .or_insert_with(|| Fuzz::gate_threshold_parameter());
+        parameters.entry("Spatter".to_string())
+            .or_insert_with(|| Fuzz::spatter_parameter());
+        // Fuzzes saved before parameters had units show them as plain numbers
+        ParameterUnit::set_units(&mut parameters, &[("Gate Threshold", ParameterUnit::Db)]);
+
+        Ok(Fuzz {
+            parameters,
+            sample_rate: None,
+            gate_envelope: 0.0,
+            gate_gain: 1.0,
+            id: helper.id
+        })
+    }
+}
+
 impl Hash for Fuzz {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.id.hash(state);
@@ -34,7 +86,11 @@ impl Fuzz {
                 value: PedalParameterValue::Float(20.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(100.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Amount of gain before clipping.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -43,7 +99,11 @@ impl Fuzz {
                 value: PedalParameterValue::Float(1.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(3.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Output volume.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -52,7 +112,11 @@ impl Fuzz {
                 value: PedalParameterValue::Int(0),
                 min: Some(PedalParameterValue::Int(0)),
                 max: Some(PedalParameterValue::Int(3)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Shape of the clipping curve, from smooth to harsh.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -61,9 +125,15 @@ impl Fuzz {
                 value: PedalParameterValue::Float(1.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed and fuzzed signal.".to_string()),
+                unit: ParameterUnit::None
             },
         );
+        parameters.insert("Gate Threshold".to_string(), Self::gate_threshold_parameter());
+        parameters.insert("Spatter".to_string(), Self::spatter_parameter());
         parameters.insert(
             "Active".to_string(),
             PedalParameter {
@@ -71,9 +141,41 @@ impl Fuzz {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
-        Fuzz { parameters, id: unique_time_id()}
+        Fuzz { parameters, sample_rate: None, gate_envelope: 0.0, gate_gain: 1.0, id: unique_time_id()}
+    }
+
+    /// Input level in dB below which the post gate closes. Defaults low enough to be effectively off.
+    fn gate_threshold_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::Float(-100.0),
+            min: Some(PedalParameterValue::Float(-100.0)),
+            max: Some(PedalParameterValue::Float(0.0)),
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Level in dB below which the output is silenced, cutting hiss between notes.".to_string()),
+            unit: ParameterUnit::Db
+        }
+    }
+
+    /// How abruptly the post gate closes. High values make it chatter on each cycle for a velcro decay.
+    fn spatter_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::Float(0.0),
+            min: Some(PedalParameterValue::Float(0.0)),
+            max: Some(PedalParameterValue::Float(1.0)),
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Makes the gate close quickly as notes decay, giving a sputtering, broken up sound.".to_string()),
+            unit: ParameterUnit::None
+        }
     }
 
     pub fn clone_with_new_id(&self) -> Self {
@@ -88,14 +190,53 @@ impl PedalTrait for Fuzz {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
+    fn set_config(&mut self, _buffer_size: usize, sample_rate: u32) {
+        self.sample_rate = Some(sample_rate as f32);
+    }
+
+    fn reset_buffer(&mut self) {
+        self.gate_envelope = 0.0;
+        self.gate_gain = 1.0;
+    }
+
     fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
+        let sample_rate = match self.sample_rate {
+            Some(sample_rate) => sample_rate,
+            None => {
+                tracing::warn!("Fuzz: Sample rate not set. Call set_config first.");
+                return;
+            }
+        };
 
         let gain = self.parameters.get("Gain").unwrap().value.as_float().unwrap();
         let level = self.parameters.get("Level").unwrap().value.as_float().unwrap();
         let fuzz_type = self.parameters.get("Type").unwrap().value.as_int().unwrap();
         let dry_wet = self.parameters.get("Dry/Wet").unwrap().value.as_float().unwrap();
+        let gate_threshold = db_to_linear(self.parameters.get("Gate Threshold").unwrap().value.as_float().unwrap());
+        let spatter = self.parameters.get("Spatter").unwrap().value.as_float().unwrap();
+
+        // More spatter makes the envelope follow individual cycles and the gate close faster, so it stutters
+        let coeff = |ms: f32| (-1.0 / ((ms / 1000.0) * sample_rate)).exp();
+        let envelope_release_coeff = coeff(20.0 + (1.0 - 20.0) * spatter);
+        let gate_attack_coeff = coeff(GATE_ATTACK_MS);
+        let gate_release_coeff = coeff(100.0 + (1.0 - 100.0) * spatter);
         
         for sample in buffer.iter_mut() {
+            // Gate on the input level, as the fuzzed signal is heavily compressed
+            let level = sample.abs();
+            if level > self.gate_envelope {
+                self.gate_envelope = level;
+            } else {
+                self.gate_envelope = envelope_release_coeff * (self.gate_envelope - level) + level;
+            }
+            let gate_target = if self.gate_envelope >= gate_threshold { 1.0 } else { 0.0 };
+            let gate_coeff = if gate_target > self.gate_gain { gate_attack_coeff } else { gate_release_coeff };
+            self.gate_gain = gate_coeff * (self.gate_gain - gate_target) + gate_target;
+
             let x = *sample * gain;
 
             let unmixed_sample = match fuzz_type {
@@ -113,7 +254,7 @@ impl PedalTrait for Fuzz {
                 }
             };
 
-            *sample = (unmixed_sample * dry_wet) + (*sample * (1.0 - dry_wet));
+            *sample = (unmixed_sample * self.gate_gain * dry_wet) + (*sample * (1.0 - dry_wet));
         }
         for sample in buffer.iter_mut() {
             *sample *= level;
@@ -152,6 +293,16 @@ impl PedalTrait for Fuzz {
             to_change = Some(("Dry/Wet".to_string(), value));
         }
 
+        let gate_param = self.get_parameters().get("Gate Threshold").unwrap();
+        if let Some(value) = pedal_knob(ui, RichText::new("Gate").color(Color32::WHITE).size(8.0), "Gate Threshold", gate_param, Vec2::new(0.14, 0.52), 0.2, self.id) {
+            to_change = Some(("Gate Threshold".to_string(), value));
+        }
+
+        let spatter_param = self.get_parameters().get("Spatter").unwrap();
+        if let Some(value) = pedal_knob(ui, RichText::new("Spatter").color(Color32::WHITE).size(8.0), "Spatter", spatter_param, Vec2::new(0.66, 0.52), 0.2, self.id) {
+            to_change = Some(("Spatter".to_string(), value));
+        }
+
         let active_param = self.get_parameters().get("Active").unwrap().value.as_bool().unwrap();
         if let Some(value) = pedal_switch(ui, active_param, egui::Vec2::new(0.33, 0.72), 0.16) {
             to_change = Some(("Active".to_string(), PedalParameterValue::Bool(value)));
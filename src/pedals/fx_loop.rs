@@ -0,0 +1,292 @@
+use std::{collections::HashMap, hash::Hash, sync::Mutex, time::{Duration, Instant}};
+use eframe::egui::{self, include_image, Color32, Layout, RichText, UiBuilder, Vec2};
+use ringbuf::{traits::{Consumer, Observer, Producer}, HeapCons, HeapProd};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
+
+use crate::{dsp_algorithms::sample_delay::SampleDelay, pedals::ui::pedal_switch, unique_time_id};
+
+use super::{
+    ui::pedal_knob,
+    PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait,
+};
+
+/// The pedal side of the FX loop. The processor holds the other ends, connected to the physical channels.
+pub struct FxLoopIo {
+    pub send: HeapProd<f32>,
+    pub return_: HeapCons<f32>
+}
+
+/// Number of buffers of returned audio kept to absorb timing jitter, older samples are skipped
+const RETURN_BUFFERS_KEPT: usize = 2;
+/// Longest send/return round trip that the dry signal is delayed to match
+const MAX_ROUND_TRIP_SECS: f32 = 0.5;
+/// Another FX Loop pedal can take the channels once the pedal using them hasn't processed for this long
+const OWNER_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The FX loop channels, and the FX Loop pedal using them
+struct FxLoopState {
+    io: FxLoopIo,
+    // Id of the pedal using the channels, and when it last processed
+    owner: Option<(u32, Instant)>,
+    // Samples sent, and returned (popped or skipped), since the owner took the channels.
+    // The difference is the round trip through the hardware.
+    sent: u64,
+    returned: u64,
+}
+
+static FX_LOOP_IO: Mutex<Option<FxLoopState>> = Mutex::new(None);
+
+/// Set by the processor when the FX loop channels are available
+pub fn set_fx_loop_io(io: Option<FxLoopIo>) {
+    match FX_LOOP_IO.lock() {
+        Ok(mut guard) => *guard = io.map(|io| FxLoopState { io, owner: None, sent: 0, returned: 0 }),
+        Err(e) => tracing::error!("Failed to lock FX loop IO: {}", e)
+    }
+}
+
+/// Routes the signal out of a physical output and back from a physical input, to insert external hardware.
+/// 
+/// Passes through when the FX loop channels are not available. There is only one pair of channels, so only one
+/// FX Loop pedal uses them at a time. Others pass through until it stops processing (e.g. it is bypassed or removed).
+#[derive(Clone)]
+pub struct FxLoop {
+    parameters: HashMap<String, PedalParameter>,
+    // Processor only
+    dry_buffer: Vec<f32>,
+    // Delays the dry signal by the round trip, so it lines up with the returned signal
+    dry_delay: SampleDelay,
+    id: u32,
+}
+
+impl Serialize for FxLoop {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser_map = serializer.serialize_map(Some(2))?;
+        ser_map.serialize_entry("id", &self.id)?;
+        ser_map.serialize_entry("parameters", &self.parameters)?;
+        ser_map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for FxLoop {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct FxLoopData {
+            id: u32,
+            parameters: HashMap<String, PedalParameter>,
+        }
+        let helper = FxLoopData::deserialize(deserializer)?;
+        Ok(FxLoop {
+            parameters: helper.parameters,
+            dry_buffer: Vec::new(),
+            dry_delay: SampleDelay::default(),
+            id: helper.id
+        })
+    }
+}
+
+impl FxLoop {
+    pub fn new() -> Self {
+        let mut parameters = HashMap::new();
+
+        parameters.insert(
+            "Send Level".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(1.0),
+                min: Some(PedalParameterValue::Float(0.0)),
+                max: Some(PedalParameterValue::Float(2.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Gain of the signal sent to the external effects.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+        parameters.insert(
+            "Return Level".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(1.0),
+                min: Some(PedalParameterValue::Float(0.0)),
+                max: Some(PedalParameterValue::Float(2.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Gain of the signal returned from the external effects.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+        parameters.insert(
+            "Dry/Wet".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(1.0),
+                min: Some(PedalParameterValue::Float(0.0)),
+                max: Some(PedalParameterValue::Float(1.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed signal and the signal returned from the external effects.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+        parameters.insert(
+            "Active".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Bool(true),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
+            },
+        );
+
+        Self {
+            parameters,
+            dry_buffer: Vec::new(),
+            dry_delay: SampleDelay::default(),
+            id: unique_time_id()
+        }
+    }
+
+    pub fn clone_with_new_id(&self) -> Self {
+        let mut cloned = self.clone();
+        cloned.id = unique_time_id();
+        cloned
+    }
+}
+
+impl Hash for FxLoop {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PedalTrait for FxLoop {
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn has_tail(&self) -> bool {
+        false
+    }
+
+    fn set_config(&mut self, buffer_size: usize, sample_rate: u32) {
+        self.dry_buffer = Vec::with_capacity(buffer_size);
+        self.dry_delay = SampleDelay::new((MAX_ROUND_TRIP_SECS * sample_rate as f32) as usize);
+    }
+
+    fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
+        let send_level = self.parameters["Send Level"].value.as_float().unwrap();
+        let return_level = self.parameters["Return Level"].value.as_float().unwrap();
+        let dry_wet = self.parameters["Dry/Wet"].value.as_float().unwrap();
+
+        // Never block the audio thread, pass through if the processor is changing the IO
+        let mut guard = match FX_LOOP_IO.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => return
+        };
+        let state = match guard.as_mut() {
+            Some(state) => state,
+            None => return
+        };
+
+        let now = Instant::now();
+        match state.owner {
+            Some((owner, _)) if owner == self.id => {},
+            Some((_, last_processed)) if now.duration_since(last_processed) < OWNER_TIMEOUT => return,
+            _ => {
+                // Take the channels, starting the round trip from this pedal's first send
+                let stale = state.io.return_.occupied_len();
+                state.io.return_.skip(stale);
+                state.sent = 0;
+                state.returned = 0;
+                self.dry_delay.reset();
+            }
+        }
+        state.owner = Some((self.id, now));
+        let io = &mut state.io;
+
+        self.dry_buffer.clear();
+        self.dry_buffer.extend_from_slice(buffer);
+
+        for sample in buffer.iter() {
+            if io.send.try_push(sample * send_level).is_err() {
+                tracing::warn!("FxLoop: Send buffer full, dropping samples.");
+                break;
+            }
+            state.sent += 1;
+        }
+
+        // Until a full buffer has made the round trip through the hardware (or if the return channel
+        // doesn't exist), pass through
+        if io.return_.occupied_len() < buffer.len() {
+            return;
+        }
+        // Skip old samples that built up (e.g. while no FX Loop pedal was active), so latency doesn't grow
+        let excess = io.return_.occupied_len().saturating_sub(buffer.len() * RETURN_BUFFERS_KEPT);
+        io.return_.skip(excess);
+        io.return_.pop_slice(buffer);
+        state.returned += (excess + buffer.len()) as u64;
+
+        self.dry_delay.set_delay(state.sent.saturating_sub(state.returned) as usize);
+        for (sample, dry) in buffer.iter_mut().zip(self.dry_buffer.iter()) {
+            let dry = self.dry_delay.process(*dry);
+            *sample = *sample * return_level * dry_wet + dry * (1.0 - dry_wet);
+        }
+    }
+
+    fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
+        &self.parameters
+    }
+
+    fn get_parameters_mut(&mut self) -> &mut HashMap<String, PedalParameter> {
+        &mut self.parameters
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, _message_buffer: &[String]) -> Option<(String,PedalParameterValue)> {
+        let mut to_change = None;
+
+        let mut img_ui = ui.new_child(
+            UiBuilder::new()
+                .max_rect(ui.available_rect_before_wrap())
+        );
+
+        img_ui.add(egui::Image::new(include_image!("images/pedal_gradient.png")).tint(Color32::from_rgb(70, 70, 80)));
+
+        ui.allocate_ui_with_layout(
+            ui.available_size() * Vec2::new(0.9, 1.0),
+            Layout::top_down(egui::Align::Center),
+            |ui| {
+                ui.add_space(20.0);
+                ui.label(RichText::new("FX Loop").size(20.0));
+            }
+        );
+
+        let knobs = [
+            ("Send", "Send Level", Vec2::new(0.05, 0.2)),
+            ("Return", "Return Level", Vec2::new(0.37, 0.2)),
+            ("Dry/Wet", "Dry/Wet", Vec2::new(0.69, 0.2)),
+        ];
+
+        for (label, name, at) in knobs {
+            let param = self.get_parameters().get(name).unwrap();
+            if let Some(value) = pedal_knob(ui, RichText::new(label).color(Color32::WHITE).size(8.0), name, param, at, 0.26, self.id) {
+                to_change = Some((name.to_string(), value));
+            }
+        }
+
+        let active_param = self.get_parameters().get("Active").unwrap().value.as_bool().unwrap();
+        if let Some(value) = pedal_switch(ui, active_param, egui::Vec2::new(0.33, 0.72), 0.16) {
+            to_change = Some(("Active".to_string(), PedalParameterValue::Bool(value)));
+        }
+
+        to_change
+    }
+}
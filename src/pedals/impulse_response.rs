@@ -3,8 +3,8 @@ use std::collections::HashMap;
 use std::hash::Hash;
 use std::sync::Arc;
 
-use crate::dsp_algorithms::impluse_response::IRConvolver;
-use crate::pedals::ui::{pedal_switch, sideways_arrow};
+use crate::dsp_algorithms::{biquad::BiquadFilter, eq::Equalizer, impluse_response::IRConvolver};
+use crate::pedals::ui::{pedal_switch, sideways_arrow, waveform_preview, FileWatcher};
 use crate::pedals::ParameterUILocation;
 use crate::processor_api::load_wav;
 use crate::{forward_slash_path, unique_time_id, SAVE_DIR};
@@ -12,11 +12,60 @@ use egui_directory_combobox::{DirectoryComboBox, DirectoryNode};
 use serde::{ser::SerializeMap, Deserialize, Serialize};
 use eframe::egui::{self, include_image, Vec2};
 
-use super::{ui::pedal_knob, PedalParameter, PedalParameterValue, PedalTrait};
+use super::{index_to_choice, ui::pedal_knob, PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait};
 
 pub const IR_SAVE_PATH: &str = r"IR";
 pub const OVERRIDE_DEFAULT_FOLDERS_ENV_VAR: &str = "RSPEDALBOARD_IR_FOLDER";
 
+/// Sample rate the IR is loaded at for the preview on the pedal
+const PREVIEW_SAMPLE_RATE: f32 = 48000.0;
+/// Number of sections the IR is split into for the preview waveform
+const PREVIEW_POINTS: usize = 48;
+
+/// Waveform and length of an IR file, shown on the pedal
+#[derive(Clone)]
+struct IrPreview {
+    path: String,
+    // Peak of each section of the IR, normalized to the IR's peak
+    peaks: Vec<f32>,
+    // None if the file failed to load
+    length_ms: Option<f32>
+}
+
+impl IrPreview {
+    fn load(path: &str) -> Self {
+        match load_wav(path, PREVIEW_SAMPLE_RATE, false) {
+            Ok(channels) => {
+                let samples = channels.first().map(|c| c.as_slice()).unwrap_or_default();
+                let section_length = samples.len().div_ceil(PREVIEW_POINTS).max(1);
+                let mut peaks: Vec<f32> = samples.chunks(section_length)
+                    .map(|section| section.iter().fold(0.0_f32, |peak, s| peak.max(s.abs())))
+                    .collect();
+
+                let max_peak = peaks.iter().cloned().fold(0.0_f32, f32::max);
+                if max_peak > 0.0 {
+                    peaks.iter_mut().for_each(|peak| *peak /= max_peak);
+                }
+
+                Self {
+                    path: path.to_string(),
+                    peaks,
+                    length_ms: Some(samples.len() as f32 / PREVIEW_SAMPLE_RATE * 1000.0)
+                }
+            },
+            Err(e) => {
+                tracing::warn!("Failed to load IR preview: {}", e);
+                Self { path: path.to_string(), peaks: Vec::new(), length_ms: None }
+            }
+        }
+    }
+}
+
+/// Sources of the speaker sim, chosen by the Mode parameter
+pub const MODE_NAMES: [&str; 2] = ["IR File", "EQ Cab"];
+/// Centre frequency of the presence bump in EQ cab mode
+const PRESENCE_FREQUENCY: f32 = 2500.0;
+
 pub fn set_ir_save_path(new_path: &str) {
     std::env::set_var(OVERRIDE_DEFAULT_FOLDERS_ENV_VAR, new_path);
 }
@@ -31,11 +80,19 @@ pub struct ImpulseResponse {
     folders_state: u32,
     id: u32,
 
+    // Client only
+    preview: Option<IrPreview>,
+    file_watcher: FileWatcher,
+
     // Processor only
     // IRConvolver requires block size. This is set on the processor after being created, and not set on client at all.
     dry_buffer: Vec<f32>,
     max_buffer_size: usize,
     ir: Option<IRConvolver>,
+    // Used instead of the IR in EQ cab mode
+    cab_eq: Option<Equalizer>,
+    // Gain matching the loudness of the loaded IR to other IRs, computed when it is loaded
+    normalization_gain: f32,
     sample_rate: Option<f32>,
 }
 
@@ -112,6 +169,14 @@ impl<'a> Deserialize<'a> for ImpulseResponse {
             }
         }
 
+        // Pedals saved before normalization was added keep their level
+        helper.parameters.entry("Normalize".to_string()).or_insert_with(|| Self::normalize_parameter(false));
+        for (name, parameter) in Self::cab_eq_parameters() {
+            helper.parameters.entry(name).or_insert(parameter);
+        }
+        // Modes saved before choice parameters were added are indexes into MODE_NAMES
+        index_to_choice(&mut helper.parameters, "Mode", Self::mode_parameter());
+
         combobox_widget.set_selection(model_path.as_ref());
         helper.parameters.get_mut("IR").map(|p| {
             if let Some(path) = model_path {
@@ -123,6 +188,7 @@ impl<'a> Deserialize<'a> for ImpulseResponse {
 
         Ok(Self {
             ir: None,
+            cab_eq: None,
             parameters: helper.parameters,
             dry_buffer: vec![0.0; 512],
             combobox_widget,
@@ -131,7 +197,10 @@ impl<'a> Deserialize<'a> for ImpulseResponse {
             folders_state: 0,
             max_buffer_size: 0,
             id,
+            normalization_gain: 1.0,
             sample_rate: None,
+            preview: None,
+            file_watcher: FileWatcher::default(),
         })
     }
 }
@@ -148,6 +217,10 @@ impl ImpulseResponse {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Impulse response file, such as a cabinet or room, to convolve the signal with.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
@@ -158,6 +231,10 @@ impl ImpulseResponse {
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
                 step: Some(PedalParameterValue::Float(0.01)),
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed and convolved signal.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
@@ -168,6 +245,10 @@ impl ImpulseResponse {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
@@ -178,13 +259,21 @@ impl ImpulseResponse {
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(3.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Output volume.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
+        parameters.insert("Normalize".to_string(), Self::normalize_parameter(true));
+        parameters.extend(Self::cab_eq_parameters());
+
         let id = unique_time_id();
 
         Self {
             ir: None,
+            cab_eq: None,
             parameters,
             dry_buffer: Vec::new(),
             combobox_widget: Self::get_empty_directory_combo_box(id),
@@ -193,7 +282,96 @@ impl ImpulseResponse {
             folders_state: 0,
             max_buffer_size: 0,
             id,
+            normalization_gain: 1.0,
             sample_rate: None,
+            preview: None,
+            file_watcher: FileWatcher::default(),
+        }
+    }
+
+    /// One of [`MODE_NAMES`]
+    fn mode_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::String(MODE_NAMES[0].to_string()),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: Some(MODE_NAMES.iter().map(|name| name.to_string()).collect()),
+            description: Some("Use an IR file, or an EQ that roughly imitates a speaker cabinet.".to_string()),
+            unit: ParameterUnit::None,
+        }
+    }
+
+    /// The mode and the parameters of the EQ cab, which are used when no IR file is available
+    fn cab_eq_parameters() -> Vec<(String, PedalParameter)> {
+        vec![
+            ("Mode".to_string(), Self::mode_parameter()),
+            ("Low Cut".to_string(), PedalParameter {
+                value: PedalParameterValue::Float(90.0),
+                min: Some(PedalParameterValue::Float(40.0)),
+                max: Some(PedalParameterValue::Float(200.0)),
+                step: Some(PedalParameterValue::Float(1.0)),
+                snap: false,
+                choices: None,
+                description: Some("Frequency (hz) below which the EQ cab rolls off, with a slight resonance at the cutoff.".to_string()),
+                unit: ParameterUnit::None,
+            }),
+            ("High Cut".to_string(), PedalParameter {
+                value: PedalParameterValue::Float(4500.0),
+                min: Some(PedalParameterValue::Float(2000.0)),
+                max: Some(PedalParameterValue::Float(8000.0)),
+                step: Some(PedalParameterValue::Float(10.0)),
+                snap: false,
+                choices: None,
+                description: Some("Frequency (hz) above which the EQ cab rolls off steeply, like a guitar speaker.".to_string()),
+                unit: ParameterUnit::None,
+            }),
+            ("Presence".to_string(), PedalParameter {
+                value: PedalParameterValue::Float(3.0),
+                min: Some(PedalParameterValue::Float(-6.0)),
+                max: Some(PedalParameterValue::Float(9.0)),
+                step: Some(PedalParameterValue::Float(0.5)),
+                snap: false,
+                choices: None,
+                description: Some("Boost (dB) of the upper mids in the EQ cab.".to_string()),
+                unit: ParameterUnit::None,
+            }),
+        ]
+    }
+
+    fn eq_cab_mode(&self) -> bool {
+        self.parameters.get("Mode").unwrap().value.as_str() == Some(MODE_NAMES[1])
+    }
+
+    fn update_cab_eq(&mut self) {
+        let Some(sample_rate) = self.sample_rate else {
+            return;
+        };
+
+        let low_cut = self.parameters.get("Low Cut").unwrap().value.as_float().unwrap();
+        let high_cut = self.parameters.get("High Cut").unwrap().value.as_float().unwrap().min(sample_rate * 0.45);
+        let presence = self.parameters.get("Presence").unwrap().value.as_float().unwrap();
+
+        // Two low passes for a 24dB/octave high cut
+        self.cab_eq = Some(Equalizer::new(vec![
+            BiquadFilter::high_pass(low_cut, sample_rate, 1.1),
+            BiquadFilter::peaking(PRESENCE_FREQUENCY, sample_rate, 1.0, presence),
+            BiquadFilter::low_pass(high_cut, sample_rate, 0.54),
+            BiquadFilter::low_pass(high_cut, sample_rate, 1.31)
+        ]));
+    }
+
+    fn normalize_parameter(normalize: bool) -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::Bool(normalize),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Match the loudness of different IRs, so that changing IR doesn't change the volume.".to_string()),
+            unit: ParameterUnit::None,
         }
     }
 
@@ -253,7 +431,9 @@ impl ImpulseResponse {
 
         match load_wav(ir_path.as_ref(), sample_rate, false) {
             Ok(ir) => {
-                self.ir = Some(IRConvolver::new(ir.first().expect("IR has no channels").as_slice(), self.max_buffer_size));
+                let convolver = IRConvolver::new(ir.first().expect("IR has no channels").as_slice(), self.max_buffer_size);
+                self.normalization_gain = convolver.energy_normalization_gain();
+                self.ir = Some(convolver);
 
                 // Update combobox to match new selection (in case it was not set from the combobox itself)
                 self.combobox_widget.set_selection(Some(&string_path));
@@ -395,6 +575,7 @@ impl PedalTrait for ImpulseResponse {
 
         let ir_path = self.parameters.get("IR").unwrap().value.as_str().unwrap().to_string();
         self.set_ir_convolver(&ir_path, sample_rate as f32);
+        self.update_cab_eq();
     }
 
     fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
@@ -403,17 +584,29 @@ impl PedalTrait for ImpulseResponse {
             return;
         }
 
-        if self.ir.is_none() {
+        let eq_cab_mode = self.eq_cab_mode();
+        if !eq_cab_mode && self.ir.is_none() {
             return;
         }
 
         let dry_wet = self.parameters.get("Dry/Wet").unwrap().value.as_float().unwrap();
-        let level = self.parameters.get("Level").unwrap().value.as_float().unwrap();
+        let mut level = self.parameters.get("Level").unwrap().value.as_float().unwrap();
+        if !eq_cab_mode && self.parameters.get("Normalize").unwrap().value.as_bool().unwrap() {
+            level *= self.normalization_gain;
+        }
 
         self.dry_buffer.clear();
         self.dry_buffer.extend_from_slice(buffer);
 
-        self.ir.as_mut().unwrap().process(buffer);
+        if eq_cab_mode {
+            if let Some(cab_eq) = self.cab_eq.as_mut() {
+                for sample in buffer.iter_mut() {
+                    *sample = cab_eq.process(*sample);
+                }
+            }
+        } else {
+            self.ir.as_mut().unwrap().process(buffer);
+        }
 
         for (i, sample) in buffer.iter_mut().enumerate() {
             *sample = ((*sample * dry_wet) + (self.dry_buffer[i] * (1.0 - dry_wet))) * level;
@@ -424,6 +617,7 @@ impl PedalTrait for ImpulseResponse {
         if let Some(ir) = &mut self.ir {
             ir.reset();
         }
+        self.update_cab_eq();
     }
 
     fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
@@ -449,16 +643,15 @@ impl PedalTrait for ImpulseResponse {
             }
         }
 
-        if !self.parameters.get(name).unwrap().is_valid(&value) {
-            tracing::warn!("Attempted to set invalid value for parameter {}: {:?}", name, value);
-            return;
-        }
-
         if let Some(param) = self.parameters.get_mut(name) {
-            param.value = value;
+            param.value = param.clamp(value);
         } else {
             tracing::error!("Parameter {} not found", name);
         }
+
+        if matches!(name, "Low Cut" | "High Cut" | "Presence") {
+            self.update_cab_eq();
+        }
     }
 
     fn get_string_values(&self,_parameter_name: &str) -> Option<Vec<String>> {
@@ -541,6 +734,44 @@ impl PedalTrait for ImpulseResponse {
             }
         };
 
+        // Reload the preview when the IR changes
+        let ir_path = self.parameters.get("IR").unwrap().value.as_str().unwrap_or_default();
+        if self.file_watcher.changed(ui.ctx(), ir_path) {
+            FileWatcher::request_reload(ui.ctx(), self.id);
+            self.preview = None;
+        }
+        if ir_path.is_empty() {
+            self.preview = None;
+        } else if self.preview.as_ref().is_none_or(|preview| preview.path != ir_path) {
+            self.preview = Some(IrPreview::load(ir_path));
+        }
+
+        if self.eq_cab_mode() {
+            let label_rect = pedal_rect
+                .scale_from_center2(Vec2::new(0.9, 0.1))
+                .translate(Vec2::new(0.0, 0.08*pedal_rect.height()));
+            ui.painter().text(
+                label_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                MODE_NAMES[1],
+                egui::FontId::proportional(label_rect.height() * 0.4),
+                egui::Color32::from_gray(220)
+            );
+        } else if let Some(preview) = &self.preview {
+            let response = waveform_preview(ui, &preview.peaks, Vec2::new(0.05, 0.53), Vec2::new(0.9, 0.1));
+            let length_text = match preview.length_ms {
+                Some(length_ms) => format!("{length_ms:.0} ms"),
+                None => "Failed to load".to_string()
+            };
+            ui.painter().text(
+                response.rect.right_bottom() - Vec2::new(3.0, 2.0),
+                egui::Align2::RIGHT_BOTTOM,
+                length_text,
+                egui::FontId::proportional(response.rect.height() * 0.3),
+                egui::Color32::from_gray(220)
+            );
+        }
+
         if let Some(value) = pedal_knob(ui, "", "Dry/Wet", self.parameters.get("Dry/Wet").unwrap(), Vec2::new(0.325, 0.037), 0.35, self.id) {
             to_change = Some(("Dry/Wet".to_string(), value));
         }
@@ -0,0 +1,197 @@
+use std::time::Instant;
+
+use eframe::egui::{self, Id};
+use egui_plot::PlotPoint;
+use serde::{Deserialize, Serialize};
+
+use crate::{dsp_algorithms::frequency_analysis::FrequencyAnalyser, DEFAULT_REFRESH_DURATION};
+
+const LIVE_FREQUENCY_UPDATE_MS: usize = 100;
+const OVERSAMPLE: f32 = 10.0;
+
+pub fn serialize_plot_points(plot_points: &mut [PlotPoint]) -> String {
+    // First round the points to 2 decimal places to reduce size
+    for point in plot_points.iter_mut() {
+        point.x = (point.x * 100.0).round() / 100.0;
+        point.y = (point.y * 100.0).round() / 100.0;
+    }
+
+    // Hoping the compiler will optimise this
+    let plot_points_floats: Vec<[f64; 2]> = plot_points.iter()
+        .map(|p| [p.x, p.y])
+        .collect();
+
+    serde_json::to_string(&plot_points_floats).expect("Failed to serialize plot points")
+}
+
+pub fn deserialize_plot_points(data: &str) -> serde_json::Result<Vec<PlotPoint>> {
+    let plot_points_floats: Vec<[f64; 2]> = serde_json::from_str(data)?;
+
+    // Hoping the compiler will optimise this
+    let plot_points = plot_points_floats.into_iter()
+        .map(|p| PlotPoint::new(p[0], p[1]))
+        .collect::<Vec<PlotPoint>>();
+
+    Ok(plot_points)
+}
+
+/// Number of points and frequency range of the frequency plots drawn by pedals.
+///
+/// This is a client setting that only changes what is drawn. The live spectrum is always analysed on the processor
+/// with the default range, and is cut to the plot's frequency range.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PlotRange {
+    pub points: usize,
+    pub min_frequency: f32,
+    pub max_frequency: f32,
+}
+
+impl Default for PlotRange {
+    fn default() -> Self {
+        PlotRange {
+            points: 80,
+            min_frequency: 60.0,
+            max_frequency: 11000.0,
+        }
+    }
+}
+
+impl PlotRange {
+    /// The plot range set for the context, or default
+    pub fn get(ctx: &egui::Context) -> Self {
+        ctx.memory(|m| m.data.get_temp::<PlotRange>(Id::new("plot_range"))).unwrap_or_default()
+    }
+
+    /// Use this range for all frequency plots drawn with the context
+    pub fn set(self, ctx: &egui::Context) {
+        ctx.memory_mut(|m| m.data.insert_temp(Id::new("plot_range"), self));
+    }
+
+    /// Part of `points`, sorted by x (log2 of the frequency), within the frequency range
+    pub fn clip<'a>(&self, points: &'a [PlotPoint]) -> &'a [PlotPoint] {
+        let min = (self.min_frequency as f64).log2();
+        let max = (self.max_frequency as f64).log2();
+        let start = points.partition_point(|point| point.x < min);
+        let end = points.partition_point(|point| point.x <= max).max(start);
+        &points[start..end]
+    }
+}
+
+/// Live frequency spectrum of a pedal's output, for drawing over the pedal's own plot.
+///
+/// The processor analyses the output in [`LiveSpectrum::process`] and sends the spectrum as a pedal message,
+/// which the client passes to [`LiveSpectrum::receive`]. The client smooths between updates with [`LiveSpectrum::update`].
+/// The x values are log2 of the frequency, and the y values are scaled to 0 to `max_value`.
+pub struct LiveSpectrum {
+    max_value: f32,
+
+    // Only exists on processor
+    frequency_analyser: Option<FrequencyAnalyser>,
+    last_frequencies_sent: Instant,
+
+    // Used for smoothing the frequency plot
+    prev_live_frequency_plot: Vec<PlotPoint>,
+    target_live_frequency_plot: Vec<PlotPoint>,
+    last_frame: Instant,
+
+    // Used to clamp the live frequency plot values
+    dynamic_max: f32
+}
+
+impl Clone for LiveSpectrum {
+    fn clone(&self) -> Self {
+        Self::new(self.max_value)
+    }
+}
+
+impl LiveSpectrum {
+    pub fn new(max_value: f32) -> Self {
+        let points = PlotRange::default().points;
+        LiveSpectrum {
+            max_value,
+            frequency_analyser: None,
+            last_frequencies_sent: Instant::now(),
+            prev_live_frequency_plot: Vec::with_capacity(points),
+            target_live_frequency_plot: Vec::with_capacity(points),
+            last_frame: Instant::now(),
+            dynamic_max: 0.0
+        }
+    }
+
+    /// Analyser over the default plot range, which doesn't change with the client's plot range
+    pub fn frequency_analyser(sample_rate: f32) -> FrequencyAnalyser {
+        let range = PlotRange::default();
+        FrequencyAnalyser::new(sample_rate, range.min_frequency, range.max_frequency, range.points, OVERSAMPLE)
+    }
+
+    /// Create the frequency analyser, if it doesn't exist yet. Called from the pedal's `set_config`.
+    pub fn set_config(&mut self, sample_rate: f32) {
+        if self.frequency_analyser.is_none() {
+            self.frequency_analyser = Some(Self::frequency_analyser(sample_rate));
+        }
+    }
+
+    /// Analyse the processed buffer, pushing the spectrum to the message buffer when it is time for an update
+    pub fn process(&mut self, buffer: &[f32], message_buffer: &mut Vec<String>) {
+        let frequency_analyser = self.frequency_analyser.as_mut().expect("Frequency Analyser should not be None on processor");
+        frequency_analyser.push_samples(buffer);
+
+        // Check if enough time has passed since the last update
+        if self.last_frequencies_sent.elapsed().as_millis() as usize >= LIVE_FREQUENCY_UPDATE_MS {
+            if frequency_analyser.analyse_log2(&mut self.target_live_frequency_plot) {
+                // New frequency data available, serialize and send to client
+                self.last_frequencies_sent = Instant::now();
+                let message = serialize_plot_points(&mut self.target_live_frequency_plot);
+                message_buffer.push(message);
+            }
+        }
+    }
+
+    /// Move the shown spectrum towards the latest received spectrum, and request a repaint to keep animating
+    pub fn update(&mut self, ctx: &egui::Context) {
+        if self.prev_live_frequency_plot.is_empty() {
+            self.prev_live_frequency_plot = self.target_live_frequency_plot.clone();
+        }
+
+        let time_since_last_frame_ms = self.last_frame.elapsed().as_millis() as usize;
+        let smooth_factor = (time_since_last_frame_ms as f64 / LIVE_FREQUENCY_UPDATE_MS as f64).min(1.0);
+        self.last_frame = Instant::now();
+
+        for (prev, target) in self.prev_live_frequency_plot.iter_mut().zip(self.target_live_frequency_plot.iter()) {
+            prev.x = prev.x * (1.0 - smooth_factor) + target.x * smooth_factor;
+            prev.y = prev.y * (1.0 - smooth_factor) + target.y * smooth_factor;
+        }
+
+        ctx.request_repaint_after(DEFAULT_REFRESH_DURATION);
+    }
+
+    /// Take a new spectrum from the pedal's messages, if there is one
+    pub fn receive(&mut self, message_buffer: &[String]) {
+        if !message_buffer.is_empty() {
+            // Deserialize the frequency response plot from the message buffer
+            if let Ok(mut plot_points) = deserialize_plot_points(&message_buffer[0]) {
+                // Scale plot points to 0-max_value
+                let max_value = plot_points.iter()
+                    .map(|p| p.y)
+                    .fold(f64::NEG_INFINITY, |a, b| a.max(b));
+
+                // Smoothly adjust dynamic max
+                self.dynamic_max = (self.dynamic_max*0.9).max(max_value as f32);
+
+                let scale_factor = self.max_value / self.dynamic_max;
+                for point in plot_points.iter_mut() {
+                    point.y *= scale_factor as f64;
+                }
+
+                self.target_live_frequency_plot = plot_points;
+            } else {
+                tracing::error!("Failed to deserialize frequency response plot");
+            }
+        }
+    }
+
+    /// The smoothed spectrum to draw
+    pub fn plot_points(&self) -> &[PlotPoint] {
+        &self.prev_live_frequency_plot
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::hash::Hash;
-use crate::dsp_algorithms::oscillator::Oscillator;
+use crate::dsp_algorithms::{db_to_linear, linear_to_db, oscillator::Oscillator};
 use enum_dispatch::enum_dispatch;
 use serde::{ Deserialize, Serialize};
 use eframe::egui;
@@ -12,6 +12,12 @@ mod fuzz;
 pub use fuzz::Fuzz;
 mod pitch_shift;
 pub use pitch_shift::PitchShift;
+mod auto_pitch;
+pub use auto_pitch::AutoPitch;
+mod whammy;
+pub use whammy::Whammy;
+mod fx_loop;
+pub use fx_loop::{FxLoop, FxLoopIo, set_fx_loop_io};
 mod modulation;
 pub use modulation::{Chorus, Flanger};
 mod delay;
@@ -26,6 +32,8 @@ pub use impulse_response::ImpulseResponse;
 pub use impulse_response::set_ir_save_path;
 mod noise_gate;
 pub use noise_gate::NoiseGate;
+mod multiband_gate;
+pub use multiband_gate::MultibandGate;
 mod vst2;
 pub use vst2::Vst2;
 pub use vst2::set_vst2_save_path;
@@ -35,6 +43,8 @@ mod vibrato;
 pub use vibrato::Vibrato;
 mod tremolo;
 pub use tremolo::Tremolo;
+mod rotary;
+pub use rotary::Rotary;
 mod autowah;
 pub use autowah::AutoWah;
 mod wah;
@@ -45,8 +55,13 @@ mod overdrive;
 pub use overdrive::Overdrive;
 mod distortion;
 pub use distortion::Distortion;
+mod filter;
+pub use filter::Filter;
 
 mod ui;
+mod live_spectrum;
+pub use ui::{FileWatcher, KnobSkin, KnobStyle};
+pub use live_spectrum::PlotRange;
 pub mod info;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -59,7 +74,75 @@ pub struct PedalParameter {
     pub max: Option<PedalParameterValue>,
     // For floats only
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub step: Option<PedalParameterValue>
+    pub step: Option<PedalParameterValue>,
+    // If true, floats are snapped to `step` when set. Otherwise `step` is only a hint to the UI.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub snap: bool,
+    // For strings only, restricts the value to one of these
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub choices: Option<Vec<String>>,
+    // Shown when hovering over the parameter's editor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // How a float value is shown in the UI
+    #[serde(default, skip_serializing_if = "ParameterUnit::is_none")]
+    pub unit: ParameterUnit
+}
+
+/// Unit a float parameter is shown in by the UI. The stored value is never converted.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParameterUnit {
+    #[default]
+    None,
+    /// The value is a level in dB
+    Db,
+    /// The value is a linear gain, which is shown in dB
+    GainAsDb
+}
+
+impl ParameterUnit {
+    /// Shown for linear gains of 0, and the lowest value of their editor
+    pub const GAIN_FLOOR_DB: f32 = -60.0;
+
+    pub fn is_none(&self) -> bool {
+        *self == ParameterUnit::None
+    }
+
+    /// Set the units of the named parameters, for pedals saved before parameters had units
+    pub fn set_units(parameters: &mut HashMap<String, PedalParameter>, units: &[(&str, ParameterUnit)]) {
+        for (name, unit) in units {
+            if let Some(parameter) = parameters.get_mut(*name) {
+                parameter.unit = *unit;
+            }
+        }
+    }
+}
+
+/// Replace a parameter saved as an int index with `parameter`, set to the choice at the saved index.
+/// For choice parameters that were int indexes in older saves.
+fn index_to_choice(parameters: &mut HashMap<String, PedalParameter>, name: &str, mut parameter: PedalParameter) {
+    let Some(index) = parameters.get(name).and_then(|saved| saved.value.as_int()) else {
+        return;
+    };
+
+    if let Some(choice) = parameter.choices.as_ref().and_then(|choices| choices.get(index.max(0) as usize)) {
+        parameter.value = PedalParameterValue::String(choice.clone());
+    }
+    parameters.insert(name.to_string(), parameter);
+}
+
+/// Parameter of oscillator pedals to restart the oscillator on each note onset, so the modulation is in time with playing
+fn retrigger_parameter() -> PedalParameter {
+    PedalParameter {
+        value: PedalParameterValue::Bool(false),
+        min: None,
+        max: None,
+        step: None,
+        snap: false,
+        choices: None,
+        description: Some("Restart the oscillator at the start of each note, so the modulation always begins at the same point of its cycle.".to_string()),
+        unit: ParameterUnit::None
+    }
 }
 
 impl PedalParameter {
@@ -76,8 +159,8 @@ impl PedalParameter {
                         return false;
                     }
                 }
-                
-                // Don't validate float step, but it can be used for hinting to UI
+
+                // Float step isn't validated, values are snapped to it when set if `snap` is true
 
                 true
             }
@@ -94,17 +177,98 @@ impl PedalParameter {
                 }
                 true
             },
+            PedalParameterValue::String(value) => {
+                match &self.choices {
+                    Some(choices) => choices.contains(value),
+                    None => true
+                }
+            },
             _ => true
         }
     }
 
+    /// Clamp a float or int value into the parameter's range, snapping floats to `step` if `snap` is set.
+    /// A string that is not one of the parameter's choices is replaced by the current value.
+    /// Other values are returned unchanged.
+    pub fn clamp(&self, value: PedalParameterValue) -> PedalParameterValue {
+        match value {
+            PedalParameterValue::Float(mut value) => {
+                // max/min rather than f32::clamp, so NaN becomes the minimum
+                if let Some(PedalParameterValue::Float(min)) = self.min {
+                    value = value.max(min);
+                }
+                if let Some(PedalParameterValue::Float(max)) = self.max {
+                    value = value.min(max);
+                }
+                if let (true, Some(PedalParameterValue::Float(step))) = (self.snap, &self.step) {
+                    // Steps are counted from the minimum, and the last step may be past the maximum
+                    let base = self.min.as_ref().and_then(|min| min.as_float()).unwrap_or(0.0);
+                    value = base + ui::float_round(value - base, *step);
+                    if let Some(PedalParameterValue::Float(max)) = self.max {
+                        if value > max {
+                            value -= *step;
+                        }
+                    }
+                }
+                PedalParameterValue::Float(value)
+            },
+            PedalParameterValue::Int(mut value) => {
+                if let Some(PedalParameterValue::Int(min)) = self.min {
+                    value = value.max(min);
+                }
+                if let Some(PedalParameterValue::Int(max)) = self.max {
+                    value = value.min(max);
+                }
+                PedalParameterValue::Int(value)
+            },
+            PedalParameterValue::String(value) if self.choices.as_ref().is_some_and(|choices| !choices.contains(&value)) => {
+                tracing::warn!("Attempted to set {} which is not one of the parameter's choices", value);
+                self.value.clone()
+            },
+            value => value
+        }
+    }
+
+    /// The value `fraction` (0 to 1) of the way through the parameter's range, rounded to `step` if it has one.
+    /// For strings with choices, the choice `fraction` of the way through the list.
+    /// Returns None if the parameter is not a float, int or choice.
+    pub fn value_in_range(&self, fraction: f32) -> Option<PedalParameterValue> {
+        match self.value {
+            PedalParameterValue::Float(_) => {
+                let min = self.min.as_ref()?.as_float()?;
+                let max = self.max.as_ref()?.as_float()?;
+                let mut value = min + fraction * (max - min);
+                if let Some(PedalParameterValue::Float(step)) = self.step {
+                    value = min + ui::float_round(value - min, step);
+                }
+                Some(self.clamp(PedalParameterValue::Float(value)))
+            },
+            PedalParameterValue::Int(_) => {
+                let min = self.min.as_ref()?.as_int()?;
+                let max = self.max.as_ref()?.as_int()?;
+                let value = min as f32 + (fraction * (max - min) as f32).round();
+                Some(self.clamp(PedalParameterValue::Int(value as i16)))
+            },
+            PedalParameterValue::String(_) => {
+                let choices = self.choices.as_ref().filter(|choices| !choices.is_empty())?;
+                let index = (fraction.clamp(0.0, 1.0) * (choices.len() - 1) as f32).round() as usize;
+                Some(PedalParameterValue::String(choices[index].clone()))
+            },
+            _ => None
+        }
+    }
+
     pub fn int_to_float(&self) -> Self {
         if let PedalParameterValue::Int(value) = self.value {
             let new_parameter = PedalParameter {
                 value: PedalParameterValue::Float(value as f32),
                 min: Some(PedalParameterValue::Float(self.min.clone().unwrap().as_int().unwrap() as f32)),
                 max: Some(PedalParameterValue::Float(self.max.clone().unwrap().as_int().unwrap() as f32)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: self.description.clone(),
+                unit: self.unit
             };
             new_parameter
         } else {
@@ -118,7 +282,11 @@ impl PedalParameter {
                 value: PedalParameterValue::Int(value as i16),
                 min: Some(PedalParameterValue::Int(self.min.clone().unwrap().as_float().unwrap() as i16)),
                 max: Some(PedalParameterValue::Int(self.max.clone().unwrap().as_float().unwrap() as i16)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: self.description.clone(),
+                unit: self.unit
             };
             new_parameter
         } else {
@@ -135,8 +303,25 @@ impl PedalParameter {
                 let init_value = f;
                 let min = self.min.clone().unwrap().as_float().unwrap_or(0.0);
                 let max = self.max.clone().unwrap().as_float().unwrap_or(1.0);
-                let response = ui.add(egui::Slider::new(&mut f, min..=max).max_decimals(2));
-                f = f.clamp(min, max);
+                let step = self.step.as_ref().and_then(|s| s.as_float()).map(|s| s as f64);
+                let response = match self.unit {
+                    ParameterUnit::None => ui::parameter_slider(ui, &mut f, min, max, step, Some(2), false, None),
+                    ParameterUnit::Db => ui::parameter_slider(ui, &mut f, min, max, step, Some(1), false, Some(" dB")),
+                    ParameterUnit::GainAsDb => {
+                        // Edited in dB, with gains at or below the floor shown as the floor
+                        let floor = ParameterUnit::GAIN_FLOOR_DB;
+                        let min_db = if min > 0.0 { linear_to_db(min).max(floor) } else { floor };
+                        let max_db = linear_to_db(max).max(min_db);
+                        let mut db = linear_to_db(f).clamp(min_db, max_db);
+                        let init_db = db;
+                        let response = ui::parameter_slider(ui, &mut db, min_db, max_db, None, Some(1), false, Some(" dB"));
+                        if db != init_db {
+                            f = if db <= floor && min <= 0.0 { 0.0 } else { db_to_linear(db) };
+                        }
+                        response
+                    }
+                };
+                f = self.clamp(PedalParameterValue::Float(f)).as_float().unwrap();
                 if f != init_value {
                     to_change = Some(PedalParameterValue::Float(f));
                 }
@@ -154,7 +339,7 @@ impl PedalParameter {
                 let init_value = i;
                 let min = self.min.clone().unwrap().as_int().unwrap_or(0);
                 let max = self.max.clone().unwrap().as_int().unwrap_or(100);
-                let response = ui.add(egui::Slider::new(&mut i, min..=max));
+                let response = ui::parameter_slider(ui, &mut i, min, max, None, None, false, None);
 
                 if i != init_value {
                     to_change = Some(PedalParameterValue::Int(i));
@@ -170,6 +355,19 @@ impl PedalParameter {
                 inner_response.response
             }
 
+            PedalParameterValue::String(ref selected) if self.choices.is_some() => {
+                let inner_response = egui::ComboBox::from_id_salt(ui.next_auto_id())
+                    .selected_text(selected.as_str())
+                    .width(width)
+                    .show_ui(ui, |ui| {
+                        for choice in self.choices.as_ref().unwrap() {
+                            if ui.selectable_label(choice == selected, choice).clicked() && choice != selected {
+                                to_change = Some(PedalParameterValue::String(choice.clone()));
+                            }
+                        }
+                    });
+                inner_response.response
+            }
             PedalParameterValue::String(_) => {
                 let mut text = self.value.as_str().unwrap().to_string();
                 let response = ui.text_edit_singleline(&mut text);
@@ -180,6 +378,11 @@ impl PedalParameter {
             }
         };
 
+        let response = match &self.description {
+            Some(description) => response.on_hover_text(description.as_str()),
+            None => response
+        };
+
         egui::InnerResponse::new(to_change, response)
     }
 }
@@ -207,6 +410,23 @@ impl Hash for PedalParameterValue {
 }
 
 impl PedalParameterValue {
+    /// Interpolate between this value and `other`, where `t` is 0.0 to 1.0.
+    /// 
+    /// Floats and ints are interpolated linearly, other values switch to `other` at 0.5.
+    /// If the values are different types, this value is returned.
+    pub fn interpolate(&self, other: &PedalParameterValue, t: f32) -> PedalParameterValue {
+        match (self, other) {
+            (PedalParameterValue::Float(a), PedalParameterValue::Float(b)) => PedalParameterValue::Float(a + (b - a) * t),
+            // The difference is taken as f32, as it can overflow an i16
+            (PedalParameterValue::Int(a), PedalParameterValue::Int(b)) => PedalParameterValue::Int(
+                (*a as f32 + (*b as f32 - *a as f32) * t).round() as i16
+            ),
+            (PedalParameterValue::Float(_), _) | (PedalParameterValue::Int(_), _) => self.clone(),
+            _ if std::mem::discriminant(self) != std::mem::discriminant(other) => self.clone(),
+            _ => if t >= 0.5 { other.clone() } else { self.clone() }
+        }
+    }
+
     pub fn as_float(&self) -> Option<f32> {
         match self {
             PedalParameterValue::Float(value) => Some(*value),
@@ -265,14 +485,11 @@ pub trait PedalTrait {
     fn get_parameters(&self) -> &HashMap<String, PedalParameter>;
     fn get_parameters_mut(&mut self) -> &mut HashMap<String, PedalParameter>;
 
+    /// Out of range floats and ints are clamped into the parameter's range.
     fn set_parameter_value(&mut self, name: &str, value: PedalParameterValue) {
         let parameters = self.get_parameters_mut();
         if let Some(parameter) = parameters.get_mut(name) {
-            if parameter.is_valid(&value) {
-                parameter.value = value;
-            } else {
-                tracing::warn!("Attempted to set invalid value for parameter {}: {:?}", name, value);
-            }
+            parameter.value = parameter.clamp(value);
         }
     }
 
@@ -281,7 +498,8 @@ pub trait PedalTrait {
     /// `message_buffer` contains messages from the pedal on the processor to the client
     fn ui(&mut self, _ui: &mut egui::Ui, _message_buffer: &[String]) -> Option<(String, PedalParameterValue)> { None }
 
-    /// Call after creating a pedal so that it can set up its internal state
+    /// Call after creating a pedal so that it can set up its internal state.
+    /// For a [`Pedal`], call [`Pedal::configure`] instead, which also sets the oscillators' sample rates.
     fn set_config(&mut self, _buffer_size: usize, _sample_rate: u32) {}
 
     fn is_active(&self) -> bool {
@@ -299,12 +517,26 @@ pub trait PedalTrait {
         parameter.parameter_editor_ui(ui)
     }
 
-    fn get_string_values(&self, _parameter_name: &str) -> Option<Vec<String>> {
-        None
+    fn get_string_values(&self, parameter_name: &str) -> Option<Vec<String>> {
+        self.get_parameters().get(parameter_name).and_then(|parameter| parameter.choices.clone())
     }
 
     /// Only call after set_config
     fn reset_buffer(&mut self) {}
+
+    /// Whether the pedal keeps sounding after its input stops, e.g. the repeats of a delay.
+    /// 
+    /// While inactive, pedals with a tail process silence until the tail has rung out.
+    /// Pedals without a tail are not processed at all while inactive.
+    fn has_tail(&self) -> bool { true }
+
+    /// Number of samples the pedal delays the signal by.
+    /// Used to line up signals that don't pass through the pedal.
+    fn latency_samples(&self) -> usize { 0 }
+
+    /// Largest latency the pedal can have with any parameter values, for preallocating delays that line up other signals.
+    /// Only call after set_config.
+    fn max_latency_samples(&self) -> usize { self.latency_samples() }
 }
 
 /// Wrapper enum type for serialization in Vec
@@ -312,25 +544,102 @@ pub trait PedalTrait {
 #[strum_discriminants(derive(EnumIter, Serialize, Deserialize, Hash))]
 #[enum_dispatch(PedalTrait)]
 pub enum Pedal {
+    AutoPitch(AutoPitch),
     AutoWah(AutoWah),
     Chorus(Chorus),
     Compressor(Compressor),
     Delay(Delay),
     Distortion(Distortion),
+    Filter(Filter),
     Flanger(Flanger),
     Fuzz(Fuzz),
+    FxLoop(FxLoop),
     GraphicEq7(GraphicEq7),
     ImpulseResponse(ImpulseResponse),
+    MultibandGate(MultibandGate),
     Nam(Nam),
     NoiseGate(NoiseGate),
     Overdrive(Overdrive),
     PitchShift(PitchShift),
     Reverb(Reverb),
+    Rotary(Rotary),
     Tremolo(Tremolo),
     Vibrato(Vibrato),
     Volume(Volume),
     Vst2(Vst2),
     Wah(Wah),
+    Whammy(Whammy),
+}
+
+impl Pedal {
+    /// Clone the pedal, giving the clone a new unique ID
+    pub fn clone_with_new_id(&self) -> Pedal {
+        match self {
+            Pedal::AutoPitch(pedal) => Pedal::AutoPitch(pedal.clone_with_new_id()),
+            Pedal::AutoWah(pedal) => Pedal::AutoWah(pedal.clone_with_new_id()),
+            Pedal::Chorus(pedal) => Pedal::Chorus(pedal.clone_with_new_id()),
+            Pedal::Compressor(pedal) => Pedal::Compressor(pedal.clone_with_new_id()),
+            Pedal::Delay(pedal) => Pedal::Delay(pedal.clone_with_new_id()),
+            Pedal::Distortion(pedal) => Pedal::Distortion(pedal.clone_with_new_id()),
+            Pedal::Filter(pedal) => Pedal::Filter(pedal.clone_with_new_id()),
+            Pedal::Flanger(pedal) => Pedal::Flanger(pedal.clone_with_new_id()),
+            Pedal::Fuzz(pedal) => Pedal::Fuzz(pedal.clone_with_new_id()),
+            Pedal::FxLoop(pedal) => Pedal::FxLoop(pedal.clone_with_new_id()),
+            Pedal::GraphicEq7(pedal) => Pedal::GraphicEq7(pedal.clone_with_new_id()),
+            Pedal::ImpulseResponse(pedal) => Pedal::ImpulseResponse(pedal.clone_with_new_id()),
+            Pedal::MultibandGate(pedal) => Pedal::MultibandGate(pedal.clone_with_new_id()),
+            Pedal::Nam(pedal) => Pedal::Nam(pedal.clone_with_new_id()),
+            Pedal::NoiseGate(pedal) => Pedal::NoiseGate(pedal.clone_with_new_id()),
+            Pedal::Overdrive(pedal) => Pedal::Overdrive(pedal.clone_with_new_id()),
+            Pedal::PitchShift(pedal) => Pedal::PitchShift(pedal.clone_with_new_id()),
+            Pedal::Reverb(pedal) => Pedal::Reverb(pedal.clone_with_new_id()),
+            Pedal::Rotary(pedal) => Pedal::Rotary(pedal.clone_with_new_id()),
+            Pedal::Tremolo(pedal) => Pedal::Tremolo(pedal.clone_with_new_id()),
+            Pedal::Vibrato(pedal) => Pedal::Vibrato(pedal.clone_with_new_id()),
+            Pedal::Volume(pedal) => Pedal::Volume(pedal.clone_with_new_id()),
+            Pedal::Vst2(pedal) => Pedal::Vst2(pedal.clone_with_new_id()),
+            Pedal::Wah(pedal) => Pedal::Wah(pedal.clone_with_new_id()),
+            Pedal::Whammy(pedal) => Pedal::Whammy(pedal.clone_with_new_id()),
+        }
+    }
+
+    /// Set the sample rate of every oscillator parameter, then call the pedal's [`PedalTrait::set_config`].
+    ///
+    /// Use this rather than the trait method, so every pedal that is configured (e.g. loaded in a set, added or reloaded)
+    /// has its oscillators at the processing sample rate, whatever rate they were saved with.
+    pub fn configure(&mut self, buffer_size: usize, sample_rate: u32) {
+        for parameter in self.get_parameters_mut().values_mut() {
+            if let Some(oscillator) = parameter.value.as_oscillator_mut() {
+                oscillator.set_sample_rate(sample_rate as f32);
+            }
+        }
+        PedalTrait::set_config(self, buffer_size, sample_rate);
+    }
+
+    /// The parameters that differ from a new pedal of the same type, with their default values.
+    ///
+    /// Creates a whole default pedal, so this is for the client, which sends the changes to the processor as parameter updates.
+    /// Oscillators keep their current sample rate.
+    pub fn default_parameter_changes(&self) -> Vec<(String, PedalParameterValue)> {
+        let default_pedal = PedalDiscriminants::from(self).new_pedal();
+
+        let mut changes = Vec::new();
+        for (name, default_parameter) in default_pedal.get_parameters() {
+            let Some(parameter) = self.get_parameters().get(name) else {
+                continue;
+            };
+
+            let mut value = default_parameter.value.clone();
+            if let (Some(oscillator), Some(current)) = (value.as_oscillator_mut(), parameter.value.as_oscillator()) {
+                oscillator.set_sample_rate(current.get_sample_rate());
+            }
+
+            if value != parameter.value {
+                changes.push((name.clone(), value));
+            }
+        }
+        changes
+    }
 }
 
 impl PedalDiscriminants {
@@ -339,6 +648,9 @@ impl PedalDiscriminants {
             PedalDiscriminants::Volume => Pedal::Volume(Volume::new()),
             PedalDiscriminants::Fuzz => Pedal::Fuzz(Fuzz::new()),
             PedalDiscriminants::PitchShift => Pedal::PitchShift(PitchShift::new()),
+            PedalDiscriminants::AutoPitch => Pedal::AutoPitch(AutoPitch::new()),
+            PedalDiscriminants::Whammy => Pedal::Whammy(Whammy::new()),
+            PedalDiscriminants::FxLoop => Pedal::FxLoop(FxLoop::new()),
             PedalDiscriminants::Chorus => Pedal::Chorus(Chorus::new()),
             PedalDiscriminants::Flanger => Pedal::Flanger(Flanger::new()),
             PedalDiscriminants::Delay => Pedal::Delay(Delay::new()),
@@ -346,15 +658,18 @@ impl PedalDiscriminants {
             PedalDiscriminants::Nam => Pedal::Nam(Nam::new()),
             PedalDiscriminants::ImpulseResponse => Pedal::ImpulseResponse(ImpulseResponse::new()),
             PedalDiscriminants::NoiseGate => Pedal::NoiseGate(NoiseGate::new()),
+            PedalDiscriminants::MultibandGate => Pedal::MultibandGate(MultibandGate::new()),
             PedalDiscriminants::Vst2 => Pedal::Vst2(Vst2::new()),
             PedalDiscriminants::Reverb => Pedal::Reverb(Reverb::new()),
             PedalDiscriminants::Vibrato => Pedal::Vibrato(Vibrato::new()),
             PedalDiscriminants::Tremolo => Pedal::Tremolo(Tremolo::new()),
+            PedalDiscriminants::Rotary => Pedal::Rotary(Rotary::new()),
             PedalDiscriminants::AutoWah => Pedal::AutoWah(AutoWah::new()),
             PedalDiscriminants::Wah => Pedal::Wah(Wah::new()),
             PedalDiscriminants::Compressor => Pedal::Compressor(Compressor::new()),
             PedalDiscriminants::Overdrive => Pedal::Overdrive(Overdrive::new()),
             PedalDiscriminants::Distortion => Pedal::Distortion(Distortion::new()),
+            PedalDiscriminants::Filter => Pedal::Filter(Filter::new()),
         }
     }
 
@@ -363,6 +678,9 @@ impl PedalDiscriminants {
             PedalDiscriminants::Volume => "Volume",
             PedalDiscriminants::Fuzz => "Fuzz",
             PedalDiscriminants::PitchShift => "Pitch Shift",
+            PedalDiscriminants::AutoPitch => "Auto Pitch",
+            PedalDiscriminants::Whammy => "Whammy",
+            PedalDiscriminants::FxLoop => "FX Loop",
             PedalDiscriminants::Chorus => "Chorus",
             PedalDiscriminants::Flanger => "Flanger",
             PedalDiscriminants::Delay => "Delay",
@@ -370,15 +688,235 @@ impl PedalDiscriminants {
             PedalDiscriminants::Nam => "Neural Amp Modeler",
             PedalDiscriminants::ImpulseResponse => "Impulse Response",
             PedalDiscriminants::NoiseGate => "Noise Gate",
+            PedalDiscriminants::MultibandGate => "Multiband Gate",
             PedalDiscriminants::Vst2 => "VST2 Plugin",
             PedalDiscriminants::Reverb => "Reverb",
             PedalDiscriminants::Vibrato => "Vibrato",
             PedalDiscriminants::Tremolo => "Tremolo",
+            PedalDiscriminants::Rotary => "Rotary",
             PedalDiscriminants::AutoWah => "Auto Wah",
             PedalDiscriminants::Wah => "Wah",
             PedalDiscriminants::Compressor => "Compressor",
             PedalDiscriminants::Overdrive => "Overdrive",
             PedalDiscriminants::Distortion => "Distortion",
+            PedalDiscriminants::Filter => "Filter",
         }
     }
+
+    pub fn category(&self) -> PedalCategory {
+        match self {
+            PedalDiscriminants::Fuzz => PedalCategory::Drive,
+            PedalDiscriminants::Nam => PedalCategory::Drive,
+            PedalDiscriminants::Overdrive => PedalCategory::Drive,
+            PedalDiscriminants::Distortion => PedalCategory::Drive,
+            PedalDiscriminants::PitchShift => PedalCategory::Modulation,
+            PedalDiscriminants::AutoPitch => PedalCategory::Modulation,
+            PedalDiscriminants::Whammy => PedalCategory::Modulation,
+            PedalDiscriminants::Chorus => PedalCategory::Modulation,
+            PedalDiscriminants::Flanger => PedalCategory::Modulation,
+            PedalDiscriminants::Vibrato => PedalCategory::Modulation,
+            PedalDiscriminants::Tremolo => PedalCategory::Modulation,
+            PedalDiscriminants::Rotary => PedalCategory::Modulation,
+            PedalDiscriminants::AutoWah => PedalCategory::Modulation,
+            PedalDiscriminants::Wah => PedalCategory::Modulation,
+            PedalDiscriminants::Delay => PedalCategory::Time,
+            PedalDiscriminants::Reverb => PedalCategory::Time,
+            PedalDiscriminants::ImpulseResponse => PedalCategory::Time,
+            PedalDiscriminants::NoiseGate => PedalCategory::Dynamics,
+            PedalDiscriminants::MultibandGate => PedalCategory::Dynamics,
+            PedalDiscriminants::Compressor => PedalCategory::Dynamics,
+            PedalDiscriminants::Volume => PedalCategory::Utility,
+            PedalDiscriminants::GraphicEq7 => PedalCategory::Utility,
+            PedalDiscriminants::Vst2 => PedalCategory::Utility,
+            PedalDiscriminants::FxLoop => PedalCategory::Utility,
+            PedalDiscriminants::Filter => PedalCategory::Utility,
+        }
+    }
+}
+
+/// Broad grouping of pedals, used to organise the pedal picker
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter)]
+pub enum PedalCategory {
+    Drive,
+    Modulation,
+    Time,
+    Dynamics,
+    Utility
+}
+
+impl PedalCategory {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            PedalCategory::Drive => "Drive",
+            PedalCategory::Modulation => "Modulation",
+            PedalCategory::Time => "Time",
+            PedalCategory::Dynamics => "Dynamics",
+            PedalCategory::Utility => "Utility",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_range_value_is_clamped() {
+        let mut volume = Volume::new();
+
+        volume.set_parameter_value("Volume", PedalParameterValue::Float(100.0));
+        assert_eq!(volume.get_parameters()["Volume"].value, PedalParameterValue::Float(5.0));
+
+        volume.set_parameter_value("Volume", PedalParameterValue::Float(-1.0));
+        assert_eq!(volume.get_parameters()["Volume"].value, PedalParameterValue::Float(0.0));
+    }
+
+    #[test]
+    fn test_clamp_leaves_in_range_and_non_numeric_values() {
+        let parameter = PedalParameter {
+            value: PedalParameterValue::Int(1),
+            min: Some(PedalParameterValue::Int(0)),
+            max: Some(PedalParameterValue::Int(4)),
+            step: None,
+            snap: false,
+            choices: None,
+            description: None,
+            unit: ParameterUnit::None
+        };
+
+        assert_eq!(parameter.clamp(PedalParameterValue::Int(2)), PedalParameterValue::Int(2));
+        assert_eq!(parameter.clamp(PedalParameterValue::Int(9)), PedalParameterValue::Int(4));
+        assert_eq!(parameter.clamp(PedalParameterValue::Bool(true)), PedalParameterValue::Bool(true));
+    }
+
+    #[test]
+    fn test_string_outside_choices_is_rejected() {
+        let parameter = PedalParameter {
+            value: PedalParameterValue::String("Hall".to_string()),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: Some(vec!["Hall".to_string(), "Plate".to_string()]),
+            description: None,
+            unit: ParameterUnit::None
+        };
+
+        assert!(parameter.is_valid(&PedalParameterValue::String("Plate".to_string())));
+        assert!(!parameter.is_valid(&PedalParameterValue::String("Spring".to_string())));
+        assert_eq!(parameter.clamp(PedalParameterValue::String("Spring".to_string())), PedalParameterValue::String("Hall".to_string()));
+    }
+
+    #[test]
+    fn test_snapped_float_is_rounded_to_step() {
+        let mut parameter = PedalParameter {
+            value: PedalParameterValue::Float(0.0),
+            min: Some(PedalParameterValue::Float(0.5)),
+            max: Some(PedalParameterValue::Float(2.0)),
+            step: Some(PedalParameterValue::Float(0.25)),
+            snap: true,
+            choices: None,
+            description: None,
+            unit: ParameterUnit::None
+        };
+
+        assert_eq!(parameter.clamp(PedalParameterValue::Float(1.1)), PedalParameterValue::Float(1.0));
+        assert_eq!(parameter.clamp(PedalParameterValue::Float(1.9)), PedalParameterValue::Float(2.0));
+        assert_eq!(parameter.clamp(PedalParameterValue::Float(0.0)), PedalParameterValue::Float(0.5));
+
+        parameter.snap = false;
+        assert_eq!(parameter.clamp(PedalParameterValue::Float(1.1)), PedalParameterValue::Float(1.1));
+    }
+
+    #[test]
+    fn test_value_in_range() {
+        let parameter = PedalParameter {
+            value: PedalParameterValue::Float(0.0),
+            min: Some(PedalParameterValue::Float(-10.0)),
+            max: Some(PedalParameterValue::Float(10.0)),
+            step: Some(PedalParameterValue::Float(1.0)),
+            snap: false,
+            choices: None,
+            description: None,
+            unit: ParameterUnit::None
+        };
+
+        assert_eq!(parameter.value_in_range(0.0), Some(PedalParameterValue::Float(-10.0)));
+        assert_eq!(parameter.value_in_range(0.52), Some(PedalParameterValue::Float(0.0)));
+        assert_eq!(parameter.value_in_range(1.0), Some(PedalParameterValue::Float(10.0)));
+
+        let bool_parameter = PedalParameter { value: PedalParameterValue::Bool(true), min: None, max: None, ..parameter };
+        assert_eq!(bool_parameter.value_in_range(0.5), None);
+
+        let choice_parameter = PedalParameter {
+            value: PedalParameterValue::String("Low".to_string()),
+            choices: Some(vec!["Low".to_string(), "Mid".to_string(), "High".to_string()]),
+            ..bool_parameter
+        };
+        assert_eq!(choice_parameter.value_in_range(0.6), Some(PedalParameterValue::String("Mid".to_string())));
+        assert_eq!(choice_parameter.value_in_range(1.0), Some(PedalParameterValue::String("High".to_string())));
+    }
+
+    #[test]
+    fn test_interpolate_ints_across_full_range() {
+        let (min, max) = (PedalParameterValue::Int(i16::MIN), PedalParameterValue::Int(i16::MAX));
+        assert_eq!(min.interpolate(&max, 0.0), PedalParameterValue::Int(i16::MIN));
+        assert_eq!(min.interpolate(&max, 1.0), PedalParameterValue::Int(i16::MAX));
+        // The difference of 60000 doesn't fit in an i16
+        let (low, high) = (PedalParameterValue::Int(-30000), PedalParameterValue::Int(30000));
+        assert_eq!(high.interpolate(&low, 0.5), PedalParameterValue::Int(0));
+    }
+
+    #[test]
+    fn test_saved_index_becomes_choice() {
+        let mut parameters = HashMap::new();
+        parameters.insert("Voicing".to_string(), PedalParameter {
+            value: PedalParameterValue::Int(2),
+            min: Some(PedalParameterValue::Int(0)),
+            max: Some(PedalParameterValue::Int(2)),
+            step: None,
+            snap: false,
+            choices: None,
+            description: None,
+            unit: ParameterUnit::None
+        });
+        let choice_parameter = PedalParameter {
+            value: PedalParameterValue::String("Low".to_string()),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: Some(vec!["Low".to_string(), "Mid".to_string(), "High".to_string()]),
+            description: None,
+            unit: ParameterUnit::None
+        };
+
+        index_to_choice(&mut parameters, "Voicing", choice_parameter.clone());
+        assert_eq!(parameters["Voicing"].value, PedalParameterValue::String("High".to_string()));
+        assert!(parameters["Voicing"].choices.is_some());
+
+        // Parameters already saved as a choice are kept
+        parameters.get_mut("Voicing").unwrap().value = PedalParameterValue::String("Mid".to_string());
+        index_to_choice(&mut parameters, "Voicing", choice_parameter);
+        assert_eq!(parameters["Voicing"].value, PedalParameterValue::String("Mid".to_string()));
+    }
+
+    #[test]
+    fn test_default_parameter_changes() {
+        let mut tremolo = PedalDiscriminants::Tremolo.new_pedal();
+        let default_depth = tremolo.get_parameters()["Depth"].value.clone();
+        assert!(tremolo.default_parameter_changes().is_empty());
+
+        tremolo.set_parameter_value("Depth", PedalParameterValue::Float(0.1));
+        tremolo.set_parameter_value("Active", PedalParameterValue::Bool(false));
+        // The oscillator at the configured sample rate isn't a change
+        tremolo.configure(512, 96000);
+
+        let mut changes = tremolo.default_parameter_changes();
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(changes, vec![
+            ("Active".to_string(), PedalParameterValue::Bool(true)),
+            ("Depth".to_string(), default_depth),
+        ]);
+    }
 }
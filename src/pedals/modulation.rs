@@ -1,15 +1,15 @@
 use std::collections::HashMap;
 use std::hash::Hash;
-use crate::dsp_algorithms::variable_delay_phaser::VariableDelayPhaser;
+use crate::dsp_algorithms::variable_delay_phaser::{VariableDelayPhaser, MAX_VOICES};
 use crate::dsp_algorithms::oscillator::{Oscillator, Sine};
-use super::{PedalTrait, PedalParameter, PedalParameterValue};
+use super::{retrigger_parameter, PedalTrait, PedalParameter, PedalParameterValue, ParameterUnit};
 use super::ui::{pedal_knob, pedal_switch};
 use eframe::egui::{self, include_image, Vec2};
 use serde::{Serialize, Deserialize, ser::SerializeMap};
 
 
 macro_rules! var_delay_phaser {
-    ($name:ident, ($default_rate:expr, $min_rate:expr, $max_rate:expr), ($default_min_depth:expr, $default_max_depth:expr, $min_depth: expr, $max_depth: expr), ($incl_feedback: expr, $default_feedback:expr, $max_feedback:expr), $default_dry_wet: expr) => {
+    ($name:ident, ($default_rate:expr, $min_rate:expr, $max_rate:expr), ($default_min_depth:expr, $default_max_depth:expr, $min_depth: expr, $max_depth: expr), ($incl_feedback: expr, $default_feedback:expr, $max_feedback:expr), $default_dry_wet: expr, $max_voices: expr) => {
         #[derive(Clone)]
         pub struct $name {
             variable_delay_phaser: Option<VariableDelayPhaser>, // Processor only
@@ -48,9 +48,19 @@ macro_rules! var_delay_phaser {
 
                 let helper = VariableDelayPhaserData::deserialize(deserializer)?;
 
+                let mut parameters = helper.parameters;
+                // Pedals saved before voices were added have a single voice
+                if $max_voices > 1 {
+                    parameters.entry("Voices".to_string())
+                        .or_insert_with(|| Self::voices_parameter());
+                }
+                // Pedals saved before retrigger was added run freely
+                parameters.entry("Retrigger".to_string())
+                    .or_insert_with(retrigger_parameter);
+
                 Ok(Self {
                     variable_delay_phaser: None,
-                    parameters: helper.parameters,
+                    parameters,
                     id: helper.id,
                 })
             }
@@ -73,7 +83,11 @@ macro_rules! var_delay_phaser {
                         value: PedalParameterValue::Float(init_min_depth),
                         min: Some(PedalParameterValue::Float($min_depth)),
                         max: Some(PedalParameterValue::Float($max_depth)),
-                        step: None
+                        step: None,
+                        snap: false,
+                        choices: None,
+                        description: Some("Shortest delay in ms that the oscillator sweeps to.".to_string()),
+                        unit: ParameterUnit::None
                     },
                 );
                 parameters.insert(
@@ -82,7 +96,11 @@ macro_rules! var_delay_phaser {
                         value: PedalParameterValue::Float(init_max_depth),
                         min: Some(PedalParameterValue::Float($min_depth)),
                         max: Some(PedalParameterValue::Float($max_depth)),
-                        step: None
+                        step: None,
+                        snap: false,
+                        choices: None,
+                        description: Some("Longest delay in ms that the oscillator sweeps to.".to_string()),
+                        unit: ParameterUnit::None
                     },
                 );
                 parameters.insert(
@@ -91,7 +109,11 @@ macro_rules! var_delay_phaser {
                         value: PedalParameterValue::Float(init_dry_wet),
                         min: Some(PedalParameterValue::Float(0.0)),
                         max: Some(PedalParameterValue::Float(1.0)),
-                        step: None
+                        step: None,
+                        snap: false,
+                        choices: None,
+                        description: Some("Mix between the unprocessed and modulated signal.".to_string()),
+                        unit: ParameterUnit::None
                     },
                 );
                 parameters.insert(
@@ -100,7 +122,11 @@ macro_rules! var_delay_phaser {
                         value: PedalParameterValue::Oscillator(init_oscillator.clone()),
                         min: Some(PedalParameterValue::Float($min_rate)),
                         max: Some(PedalParameterValue::Float($max_rate)),
-                        step: None
+                        step: None,
+                        snap: false,
+                        choices: None,
+                        description: Some("Shape and rate of the modulation.".to_string()),
+                        unit: ParameterUnit::None
                     },
                 );
 
@@ -111,11 +137,20 @@ macro_rules! var_delay_phaser {
                             value: PedalParameterValue::Float($default_feedback),
                             min: Some(PedalParameterValue::Float(0.0)),
                             max: Some(PedalParameterValue::Float($max_feedback)),
-                            step: None
+                            step: None,
+                            snap: false,
+                            choices: None,
+                            description: Some("Amount of output fed back into the delay, for a more resonant sound.".to_string()),
+                            unit: ParameterUnit::None
                         },
                     );
                 }
 
+                if $max_voices > 1 {
+                    parameters.insert("Voices".to_string(), Self::voices_parameter());
+                }
+                parameters.insert("Retrigger".to_string(), retrigger_parameter());
+
                 parameters.insert(
                     "Active".to_string(),
                     PedalParameter {
@@ -123,6 +158,10 @@ macro_rules! var_delay_phaser {
                         min: None,
                         max: None,
                         step: None,
+                        snap: false,
+                        choices: None,
+                        description: None,
+                        unit: ParameterUnit::None,
                     },
                 );
         
@@ -133,6 +172,20 @@ macro_rules! var_delay_phaser {
                 }
             }
 
+            /// Number of phase offset delay voices
+            fn voices_parameter() -> PedalParameter {
+                PedalParameter {
+                    value: PedalParameterValue::Int(1),
+                    min: Some(PedalParameterValue::Int(1)),
+                    max: Some(PedalParameterValue::Int(($max_voices as usize).min(MAX_VOICES) as i16)),
+                    step: None,
+                    snap: false,
+                    choices: None,
+                    description: Some("Number of modulated copies of the signal, each with a different phase.".to_string()),
+                    unit: ParameterUnit::None
+                }
+            }
+
             pub fn clone_with_new_id(&self) -> Self {
                 let mut cloned = self.clone();
                 cloned.id = crate::unique_time_id();
@@ -168,9 +221,10 @@ macro_rules! var_delay_phaser {
             }
 
             fn set_parameter_value(&mut self, name: &str, value: PedalParameterValue) {
-                if !self.parameters.contains_key(name) || !self.parameters.get(name).unwrap().is_valid(&value) {
+                let Some(parameter) = self.parameters.get(name) else {
                     return;
-                }
+                };
+                let value = parameter.clamp(value);
 
                 match name {
                     "Min Depth" => {
@@ -206,7 +260,7 @@ macro_rules! var_delay_phaser {
                     "Oscillator" => {
                         if let PedalParameterValue::Oscillator(oscillator) = value {
                             if let Some(variable_delay_phaser) = &mut self.variable_delay_phaser {
-                                variable_delay_phaser.oscillator = oscillator.clone();
+                                variable_delay_phaser.set_oscillator(oscillator.clone());
                             }
 
                             self.parameters.get_mut(name).unwrap().value = PedalParameterValue::Oscillator(oscillator);
@@ -224,6 +278,22 @@ macro_rules! var_delay_phaser {
                             self.parameters.get_mut(name).unwrap().value = PedalParameterValue::Float(feedback);
                         }
                     },
+                    "Voices" => {
+                        if let PedalParameterValue::Int(voices) = value {
+                            if let Some(variable_delay_phaser) = &mut self.variable_delay_phaser {
+                                variable_delay_phaser.set_voices(voices as usize);
+                            }
+                            self.parameters.get_mut(name).unwrap().value = PedalParameterValue::Int(voices);
+                        }
+                    },
+                    "Retrigger" => {
+                        if let PedalParameterValue::Bool(retrigger) = value {
+                            if let Some(variable_delay_phaser) = &mut self.variable_delay_phaser {
+                                variable_delay_phaser.set_retrigger(retrigger);
+                            }
+                            self.parameters.get_mut(name).unwrap().value = PedalParameterValue::Bool(retrigger);
+                        }
+                    },
                     _ => {
                         if let Some(parameter) = self.parameters.get_mut(name) {
                             parameter.value = value;
@@ -278,9 +348,8 @@ macro_rules! var_delay_phaser {
             }
 
             fn set_config(&mut self, _buffer_size: usize, sample_rate: u32) {
-                let parameter_oscillator = self.parameters.get_mut("Oscillator").unwrap().value.as_oscillator_mut().unwrap();
-                parameter_oscillator.set_sample_rate(sample_rate as f32);
-                let variable_delay_phaser_oscillator = parameter_oscillator.clone();
+                // The oscillator parameter's sample rate is set by Pedal::configure
+                let variable_delay_phaser_oscillator = self.parameters.get("Oscillator").unwrap().value.as_oscillator().unwrap().clone();
 
                 let min_depth = self.parameters.get("Min Depth").unwrap().value.as_float().unwrap();
                 let max_depth = self.parameters.get("Max Depth").unwrap().value.as_float().unwrap();
@@ -291,12 +360,17 @@ macro_rules! var_delay_phaser {
                     0.0
                 };
 
-                self.variable_delay_phaser = Some(VariableDelayPhaser::new(min_depth, max_depth, dry_wet, variable_delay_phaser_oscillator, feedback, sample_rate as f32));
+                let mut variable_delay_phaser = VariableDelayPhaser::new(min_depth, max_depth, dry_wet, variable_delay_phaser_oscillator, feedback, sample_rate as f32);
+                if let Some(voices) = self.parameters.get("Voices") {
+                    variable_delay_phaser.set_voices(voices.value.as_int().unwrap() as usize);
+                }
+                variable_delay_phaser.set_retrigger(self.parameters.get("Retrigger").unwrap().value.as_bool().unwrap());
+                self.variable_delay_phaser = Some(variable_delay_phaser);
             }
         }
     };
 }
 
-// name, (default_rate, min_rate, max_rate) hz, (default_min_depth, default_max_depth, min_depth, max_depth) ms, (incl_feedback, default_feedback, max_feedback), default_dry_wet
-var_delay_phaser!(Chorus, (1.0, 0.1, 5.0), (5.0, 15.0, 3.0, 40.0), (false, 0.0, 0.0), 0.5);
-var_delay_phaser!(Flanger, (0.25, 0.05, 2.0), (0.3, 2.0, 0.1, 6.0), (true, 0.0, 0.95), 0.5);
\ No newline at end of file
+// name, (default_rate, min_rate, max_rate) hz, (default_min_depth, default_max_depth, min_depth, max_depth) ms, (incl_feedback, default_feedback, max_feedback), default_dry_wet, max_voices
+var_delay_phaser!(Chorus, (1.0, 0.1, 5.0), (5.0, 15.0, 3.0, 40.0), (false, 0.0, 0.0), 0.5, 4);
+var_delay_phaser!(Flanger, (0.25, 0.05, 2.0), (0.3, 2.0, 0.1, 6.0), (true, 0.0, 0.95), 0.5, 1);
\ No newline at end of file
@@ -0,0 +1,306 @@
+use std::{collections::HashMap, hash::Hash};
+use eframe::egui::{self, include_image, Color32, Layout, RichText, UiBuilder, Vec2};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
+
+use crate::{dsp_algorithms::{crossover::ThreeBandSplitter, gate::Gate}, pedals::ui::pedal_switch, unique_time_id};
+
+use super::{
+    ui::pedal_knob,
+    PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait,
+};
+
+const BAND_THRESHOLD_PARAMETERS: [&str; 3] = ["Low Threshold Db", "Mid Threshold Db", "High Threshold Db"];
+
+/// Noise gate that splits the signal into low, mid and high bands and gates each independently
+#[derive(Clone)]
+pub struct MultibandGate {
+    parameters: HashMap<String, PedalParameter>,
+    // Processor only, created in set_config
+    splitter: Option<ThreeBandSplitter>,
+    gates: Option<[Gate; 3]>,
+    id: u32,
+}
+
+impl Serialize for MultibandGate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser_map = serializer.serialize_map(Some(2))?;
+        ser_map.serialize_entry("id", &self.id)?;
+        ser_map.serialize_entry("parameters", &self.parameters)?;
+        ser_map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for MultibandGate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct MultibandGateData {
+            id: u32,
+            parameters: HashMap<String, PedalParameter>,
+        }
+        let helper = MultibandGateData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Gates saved before parameters had units show them as plain numbers
+        ParameterUnit::set_units(&mut parameters, &BAND_THRESHOLD_PARAMETERS.map(|name| (name, ParameterUnit::Db)));
+
+        Ok(MultibandGate {
+            parameters,
+            splitter: None,
+            gates: None,
+            id: helper.id
+        })
+    }
+}
+
+impl MultibandGate {
+    pub fn new() -> Self {
+        let mut parameters = HashMap::new();
+
+        parameters.insert(
+            "Low Crossover".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(250.0),
+                min: Some(PedalParameterValue::Float(50.0)),
+                max: Some(PedalParameterValue::Float(1000.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Frequency in Hz separating the low and mid bands.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "High Crossover".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(4000.0),
+                min: Some(PedalParameterValue::Float(1000.0)),
+                max: Some(PedalParameterValue::Float(10000.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Frequency in Hz separating the mid and high bands.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        for name in BAND_THRESHOLD_PARAMETERS {
+            parameters.insert(
+                name.to_string(),
+                PedalParameter {
+                    value: PedalParameterValue::Float(-50.0),
+                    min: Some(PedalParameterValue::Float(-70.0)),
+                    max: Some(PedalParameterValue::Float(0.0)),
+                    step: None,
+                    snap: false,
+                    choices: None,
+                    description: Some("Level in dB below which this band is gated.".to_string()),
+                    unit: ParameterUnit::Db,
+                },
+            );
+        }
+
+        parameters.insert(
+            "Reduction".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(10.0),
+                min: Some(PedalParameterValue::Float(1.0)),
+                max: Some(PedalParameterValue::Float(20.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("How much a band is reduced by when it is gated.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Attack".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(5.0),
+                min: Some(PedalParameterValue::Float(1.0)),
+                max: Some(PedalParameterValue::Float(50.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Time in ms taken for a band to open.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Release".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(50.0),
+                min: Some(PedalParameterValue::Float(1.0)),
+                max: Some(PedalParameterValue::Float(50.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Time in ms taken for a band to close.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Active".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Bool(true),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
+            },
+        );
+
+        Self {
+            parameters,
+            splitter: None,
+            gates: None,
+            id: unique_time_id()
+        }
+    }
+
+    fn sync_dsp(&mut self) {
+        let p = &self.parameters;
+        if let Some(splitter) = &mut self.splitter {
+            splitter.set_freqs(p["Low Crossover"].value.as_float().unwrap(), p["High Crossover"].value.as_float().unwrap());
+        }
+
+        if let Some(gates) = &mut self.gates {
+            for (gate, threshold_name) in gates.iter_mut().zip(BAND_THRESHOLD_PARAMETERS) {
+                gate.set_thresholds(p[threshold_name].value.as_float().unwrap(), 0.0);
+                gate.set_reduction(p["Reduction"].value.as_float().unwrap());
+                gate.set_attack_release(p["Attack"].value.as_float().unwrap(), p["Release"].value.as_float().unwrap());
+            }
+        }
+    }
+
+    pub fn clone_with_new_id(&self) -> Self {
+        let mut cloned = self.clone();
+        cloned.id = unique_time_id();
+        cloned
+    }
+}
+
+impl Hash for MultibandGate {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PedalTrait for MultibandGate {
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn has_tail(&self) -> bool {
+        false
+    }
+
+    fn set_config(&mut self, _buffer_size: usize, sample_rate: u32) {
+        let p = &self.parameters;
+        self.splitter = Some(ThreeBandSplitter::new(
+            p["Low Crossover"].value.as_float().unwrap(),
+            p["High Crossover"].value.as_float().unwrap(),
+            sample_rate as f32
+        ));
+        self.gates = Some(std::array::from_fn(|_| Gate::new(sample_rate as f32)));
+        self.sync_dsp();
+    }
+
+    fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
+        let (splitter, gates) = match (&mut self.splitter, &mut self.gates) {
+            (Some(splitter), Some(gates)) => (splitter, gates),
+            _ => {
+                tracing::warn!("MultibandGate: Sample rate not set. Call set_config first.");
+                return;
+            }
+        };
+
+        for sample in buffer.iter_mut() {
+            let bands = splitter.process(*sample);
+            *sample = bands.iter()
+                .zip(gates.iter_mut())
+                .map(|(band, gate)| band * gate.process(*band))
+                .sum();
+        }
+    }
+
+    fn reset_buffer(&mut self) {
+        if let Some(splitter) = &mut self.splitter {
+            splitter.reset();
+        }
+        if let Some(gates) = &mut self.gates {
+            for gate in gates.iter_mut() {
+                gate.reset();
+            }
+        }
+    }
+
+    fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
+        &self.parameters
+    }
+
+    fn get_parameters_mut(&mut self) -> &mut HashMap<String, PedalParameter> {
+        &mut self.parameters
+    }
+
+    fn set_parameter_value(&mut self, name: &str, value: PedalParameterValue) {
+        if let Some(parameter) = self.parameters.get_mut(name) {
+            parameter.value = parameter.clamp(value);
+            self.sync_dsp();
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, _message_buffer: &[String]) -> Option<(String,PedalParameterValue)> {
+        let mut to_change = None;
+
+        let mut img_ui = ui.new_child(
+            UiBuilder::new()
+                .max_rect(ui.available_rect_before_wrap())
+        );
+
+        img_ui.add(egui::Image::new(include_image!("images/pedal_gradient.png")).tint(Color32::from_rgb(40, 40, 40)));
+
+        ui.allocate_ui_with_layout(
+            ui.available_size() * Vec2::new(0.9, 1.0),
+            Layout::top_down(egui::Align::Center),
+            |ui| {
+                ui.add_space(20.0);
+                ui.label(RichText::new("Multiband Gate").size(18.0));
+            }
+        );
+
+        let knobs = [
+            ("Low", "Low Threshold Db", Vec2::new(0.05, 0.17)),
+            ("Mid", "Mid Threshold Db", Vec2::new(0.37, 0.17)),
+            ("High", "High Threshold Db", Vec2::new(0.69, 0.17)),
+            ("Low X", "Low Crossover", Vec2::new(0.2, 0.42)),
+            ("High X", "High Crossover", Vec2::new(0.54, 0.42)),
+        ];
+
+        for (label, name, at) in knobs {
+            let param = self.get_parameters().get(name).unwrap();
+            if let Some(value) = pedal_knob(ui, RichText::new(label).color(Color32::WHITE).size(8.0), name, param, at, 0.26, self.id) {
+                to_change = Some((name.to_string(), value));
+            }
+        }
+
+        let active_param = self.get_parameters().get("Active").unwrap().value.as_bool().unwrap();
+        if let Some(value) = pedal_switch(ui, active_param, egui::Vec2::new(0.33, 0.72), 0.16) {
+            to_change = Some(("Active".to_string(), PedalParameterValue::Bool(value)));
+        }
+
+        to_change
+    }
+}
@@ -8,14 +8,94 @@ use serde::{ser::SerializeMap, Deserialize, Serialize};
 use eframe::egui::{self, include_image, Vec2};
 use egui_directory_combobox::{DirectoryComboBox, DirectoryNode};
 
-use super::{ui::pedal_knob, PedalParameter, PedalParameterValue, PedalTrait};
-use crate::pedals::ui::{pedal_switch, sideways_arrow};
+use super::{ui::pedal_knob, PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait};
+use crate::pedals::ui::{pedal_switch, sideways_arrow, FileWatcher};
 use crate::pedals::ParameterUILocation;
 use crate::{forward_slash_path, unique_time_id, SAVE_DIR};
 
 pub const NAM_SAVE_PATH: &str = r"NAM";
 pub const OVERRIDE_DEFAULT_FOLDERS_ENV_VAR: &str = "RSPEDALBOARD_NAM_FOLDER";
 
+/// Descriptive metadata stored in a .nam model file, shown on the pedal
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+struct NamMetadata {
+    name: Option<String>,
+    modeled_by: Option<String>,
+    gear_make: Option<String>,
+    gear_model: Option<String>,
+    gear_type: Option<String>,
+    tone_type: Option<String>,
+    // Output level of the model in dB
+    loudness: Option<f32>
+}
+
+/// Metadata of a model file, parsed on the client when the model changes
+#[derive(Clone)]
+struct ModelInfo {
+    path: String,
+    metadata: Option<NamMetadata>,
+    sample_rate: Option<f32>
+}
+
+impl ModelInfo {
+    fn load(path: &str) -> Self {
+        #[derive(Deserialize)]
+        struct NamFile {
+            #[serde(default)]
+            metadata: NamMetadata,
+            sample_rate: Option<f32>
+        }
+
+        let file = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_json::from_str::<NamFile>(&data).map_err(|e| e.to_string()));
+
+        match file {
+            Ok(file) => Self { path: path.to_string(), metadata: Some(file.metadata), sample_rate: file.sample_rate },
+            Err(e) => {
+                tracing::warn!("Failed to read NAM model metadata: {}", e);
+                Self { path: path.to_string(), metadata: None, sample_rate: None }
+            }
+        }
+    }
+
+    /// Short lines describing the model, to fit on the pedal
+    fn summary(&self) -> Vec<String> {
+        let Some(metadata) = &self.metadata else {
+            return vec!["Failed to read model".to_string()];
+        };
+
+        let mut lines = Vec::new();
+        let gear = [metadata.gear_make.as_deref(), metadata.gear_model.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !gear.is_empty() {
+            lines.push(gear);
+        } else if let Some(name) = &metadata.name {
+            lines.push(name.clone());
+        }
+
+        let details: Vec<String> = [
+            metadata.gear_type.clone(),
+            metadata.tone_type.clone(),
+            self.sample_rate.map(|sr| format!("{:.1} kHz", sr / 1000.0)),
+            metadata.loudness.map(|loudness| format!("{loudness:.1} dB"))
+        ].into_iter().flatten().collect();
+        if !details.is_empty() {
+            lines.push(details.join(" · "));
+        }
+
+        if let Some(modeled_by) = &metadata.modeled_by {
+            lines.push(format!("by {modeled_by}"));
+        }
+
+        lines
+    }
+}
+
 pub fn set_nam_save_path(new_path: &str) {
     std::env::set_var(OVERRIDE_DEFAULT_FOLDERS_ENV_VAR, new_path);
 }
@@ -30,7 +110,10 @@ pub struct Nam {
     midi_min_combobox_widget: DirectoryComboBox,
     midi_max_combobox_widget: DirectoryComboBox,
     folders_state: u32, // Used to track changes in the root directories settings
-    id: u32
+    id: u32,
+    // Client only
+    model_info: Option<ModelInfo>,
+    file_watcher: FileWatcher
 }
 
 impl Clone for Nam {
@@ -46,7 +129,9 @@ impl Clone for Nam {
             midi_min_combobox_widget: self.midi_min_combobox_widget.clone(),
             midi_max_combobox_widget: self.midi_max_combobox_widget.clone(),
             folders_state: self.folders_state,
-            id: self.id
+            id: self.id,
+            model_info: self.model_info.clone(),
+            file_watcher: self.file_watcher.clone()
         };
 
         if cfg!(feature = "processor") {
@@ -135,7 +220,9 @@ impl<'a> Deserialize<'a> for Nam {
             combobox_widget: Self::get_empty_directory_combo_box(helper.id),
             midi_min_combobox_widget: Self::get_empty_directory_combo_box(egui::Id::new(helper.id).with("midi_min")),
             midi_max_combobox_widget: Self::get_empty_directory_combo_box(egui::Id::new(helper.id).with("midi_max")),
-            id: helper.id
+            id: helper.id,
+            model_info: None,
+            file_watcher: FileWatcher::default()
         };
 
         if cfg!(feature = "processor") {
@@ -170,6 +257,10 @@ impl Nam {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Neural Amp Modeler model file to run.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
@@ -180,6 +271,10 @@ impl Nam {
                 min: Some(PedalParameterValue::Float(0.00)),
                 max: Some(PedalParameterValue::Float(3.0)),
                 step: Some(PedalParameterValue::Float(0.05)),
+                snap: false,
+                choices: None,
+                description: Some("Input gain into the model.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
@@ -190,6 +285,10 @@ impl Nam {
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
                 step: Some(PedalParameterValue::Float(0.01)),
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed signal and the model output.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
@@ -200,6 +299,10 @@ impl Nam {
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(3.0)),
                 step: Some(PedalParameterValue::Float(0.05)),
+                snap: false,
+                choices: None,
+                description: Some("Output volume.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
@@ -210,6 +313,10 @@ impl Nam {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
@@ -224,7 +331,9 @@ impl Nam {
             combobox_widget: Self::get_empty_directory_combo_box(id),
             midi_min_combobox_widget: Self::get_empty_directory_combo_box(egui::Id::new(id).with("midi_min")),
             midi_max_combobox_widget: Self::get_empty_directory_combo_box(egui::Id::new(id).with("midi_max")),
-            id
+            id,
+            model_info: None,
+            file_watcher: FileWatcher::default()
         }
     }
 
@@ -424,6 +533,10 @@ impl PedalTrait for Nam {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn set_config(&mut self, buffer_size: usize, sample_rate: u32) {
         if self.has_loaded_model() {
             self.modeler.set_maximum_buffer_size(buffer_size);
@@ -478,10 +591,7 @@ impl PedalTrait for Nam {
             return;
         };
 
-        if !existing_param.is_valid(&value) {
-            tracing::warn!("Attempted to set invalid value for parameter {}: {:?}", name, value);
-            return;
-        }
+        let value = existing_param.clamp(value);
 
         if let Some(param) = self.parameters.get_mut(name) {
             if name == "Model" {
@@ -591,6 +701,36 @@ impl PedalTrait for Nam {
             }
         };
 
+        // Reload the metadata when the model changes
+        let model_path = self.parameters.get("Model").unwrap().value.as_str().unwrap_or_default();
+        if self.file_watcher.changed(ui.ctx(), model_path) {
+            FileWatcher::request_reload(ui.ctx(), self.id);
+            self.model_info = None;
+        }
+        if model_path.is_empty() {
+            self.model_info = None;
+        } else if self.model_info.as_ref().is_none_or(|info| info.path != model_path) {
+            self.model_info = Some(ModelInfo::load(model_path));
+        }
+
+        if let Some(info) = &self.model_info {
+            let info_rect = pedal_rect
+                .scale_from_center2(Vec2::new(0.9, 0.13))
+                .translate(Vec2::new(0.0, 0.09*pedal_rect.height()));
+            let mut info_ui = ui.new_child(
+                egui::UiBuilder::new()
+                    .max_rect(info_rect)
+                    .layout(egui::Layout::top_down(egui::Align::Center))
+            );
+            let font_size = info_rect.height() * 0.22;
+            for line in info.summary() {
+                info_ui.add(
+                    egui::Label::new(egui::RichText::new(line).size(font_size).color(egui::Color32::from_gray(210)))
+                        .truncate()
+                );
+            }
+        }
+
         if let Some(value) = pedal_knob(ui, "", "Gain", self.parameters.get("Gain").unwrap(), Vec2::new(0.05, 0.06), 0.25, self.id) {
             to_change = Some(("Gain".to_string(), value));
         }
@@ -1,20 +1,37 @@
-use std::{collections::HashMap, hash::Hash};
-use eframe::egui::{self, include_image};
+use std::{collections::HashMap, hash::Hash, time::{Duration, Instant}};
+use eframe::egui::{self, include_image, Color32, Vec2};
 use serde::{ser::SerializeMap, Deserialize, Serialize};
 
-use crate::{pedals::ui::pedal_switch, unique_time_id};
+use crate::{dsp_algorithms::gate::Gate, pedals::ui::pedal_switch, unique_time_id, DEFAULT_REFRESH_DURATION};
 
 use super::{
     ui::pedal_knob,
-    PedalParameter, PedalParameterValue, PedalTrait,
+    PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait,
 };
 
+const GATE_METER_UPDATE_RATE: Duration = Duration::from_millis(50);
+/// Gain reduction in dB that fills the gain meter
+const GATE_METER_RANGE_DB: f32 = 40.0;
+/// Time in seconds the open light takes to fade after the gate closes, so brief openings are visible
+const GATE_LIGHT_FADE_SECS: f32 = 0.3;
+
 #[derive(Clone)]
 pub struct NoiseGate {
     parameters: HashMap<String, PedalParameter>,
-    gain: f32,
-    level: f32,
-    sample_rate: Option<f32>,
+    // Processor only, created in set_config
+    gate: Option<Gate>,
+
+    // Processor only
+    meter_last_sent_time: Instant,
+    // Whether the gate opened at any point since the last meter update, so brief openings are sent
+    meter_opened: bool,
+    meter_last_sent: (bool, f32),
+
+    // Client only, latest received gate state and gain in dB, and the brightness (0-1) of the open light
+    gate_open: bool,
+    gate_gain_db: f32,
+    open_light: f32,
+
     id: u32,
 }
 
@@ -41,12 +58,30 @@ impl<'de> Deserialize<'de> for NoiseGate {
             parameters: HashMap<String, PedalParameter>,
         }
         let helper = NoiseGateData::deserialize(deserializer)?;
-        
+
+        let mut parameters = helper.parameters;
+        // Gates saved before hysteresis and hold were added have neither
+        parameters.entry("Hysteresis Db".to_string())
+            .or_insert_with(|| NoiseGate::hysteresis_parameter());
+        parameters.entry("Hold".to_string())
+            .or_insert_with(|| NoiseGate::hold_parameter());
+        // Gates saved before parameters had units show them as plain numbers.
+        // Reduction is a ratio, and was briefly saved in dB.
+        ParameterUnit::set_units(&mut parameters, &[
+            ("Threshold Db", ParameterUnit::Db),
+            ("Reduction", ParameterUnit::None),
+            ("Hysteresis Db", ParameterUnit::Db)
+        ]);
+
         Ok(NoiseGate {
-            parameters: helper.parameters,
-            gain: 1.0,
-            level: 0.0,
-            sample_rate: None,
+            parameters,
+            gate: None,
+            meter_last_sent_time: Instant::now(),
+            meter_opened: false,
+            meter_last_sent: (true, 0.0),
+            gate_open: true,
+            gate_gain_db: 0.0,
+            open_light: 0.0,
             id: helper.id
         })
     }
@@ -63,6 +98,10 @@ impl NoiseGate {
                 min: Some(PedalParameterValue::Float(-70.0)),
                 max: Some(PedalParameterValue::Float(0.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Level in dB below which the signal is gated.".to_string()),
+                unit: ParameterUnit::Db,
             },
         );
 
@@ -73,6 +112,10 @@ impl NoiseGate {
                 min: Some(PedalParameterValue::Float(1.0)),
                 max: Some(PedalParameterValue::Float(20.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("How much the signal is reduced by when the gate is closed.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
@@ -83,6 +126,10 @@ impl NoiseGate {
                 min: Some(PedalParameterValue::Float(1.0)),
                 max: Some(PedalParameterValue::Float(50.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Time in ms taken for the gate to open.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
@@ -93,9 +140,16 @@ impl NoiseGate {
                 min: Some(PedalParameterValue::Float(1.0)),
                 max: Some(PedalParameterValue::Float(50.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Time in ms taken for the gate to close.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
+        parameters.insert("Hysteresis Db".to_string(), Self::hysteresis_parameter());
+        parameters.insert("Hold".to_string(), Self::hold_parameter());
+
         parameters.insert(
             "Dry/Wet".to_string(),
             PedalParameter {
@@ -103,6 +157,10 @@ impl NoiseGate {
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed and gated signal.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
 
@@ -113,18 +171,120 @@ impl NoiseGate {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
         Self {
             parameters,
-            gain: 1.0,
-            level: 0.0,
-            sample_rate: None,
+            gate: None,
+            meter_last_sent_time: Instant::now(),
+            meter_opened: false,
+            meter_last_sent: (true, 0.0),
+            gate_open: true,
+            gate_gain_db: 0.0,
+            open_light: 0.0,
             id: unique_time_id()
         }
     }
 
+    /// Difference between the open and close thresholds
+    fn hysteresis_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::Float(0.0),
+            min: Some(PedalParameterValue::Float(0.0)),
+            max: Some(PedalParameterValue::Float(20.0)),
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("How far in dB below the threshold the signal must fall before the gate closes, to stop chattering.".to_string()),
+            unit: ParameterUnit::Db,
+        }
+    }
+
+    /// Time in ms the gate is held open after the signal drops below the close threshold
+    fn hold_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::Float(0.0),
+            min: Some(PedalParameterValue::Float(0.0)),
+            max: Some(PedalParameterValue::Float(500.0)),
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Time in ms the gate stays open after the signal falls below the threshold.".to_string()),
+            unit: ParameterUnit::None,
+        }
+    }
+
+    fn sync_gate(&mut self) {
+        let p = &self.parameters;
+        if let Some(gate) = &mut self.gate {
+            gate.set_thresholds(p["Threshold Db"].value.as_float().unwrap(), p["Hysteresis Db"].value.as_float().unwrap());
+            gate.set_reduction(p["Reduction"].value.as_float().unwrap());
+            gate.set_attack_release(p["Attack"].value.as_float().unwrap(), p["Release"].value.as_float().unwrap());
+            gate.set_hold(p["Hold"].value.as_float().unwrap());
+        }
+    }
+
+    /// Read the latest gate state, sent by the processor as "{open} {gain_db}", and fade the open light
+    fn update_meter(&mut self, ui: &egui::Ui, message_buffer: &[String]) {
+        if let Some(message) = message_buffer.last() {
+            let mut parts = message.split_whitespace();
+            match (parts.next().and_then(|open| open.parse::<u8>().ok()), parts.next().and_then(|gain| gain.parse::<f32>().ok())) {
+                (Some(open), Some(gain_db)) => {
+                    self.gate_open = open != 0;
+                    self.gate_gain_db = gain_db;
+                },
+                _ => tracing::warn!("NoiseGate: Invalid gate meter message: {}", message)
+            }
+        }
+
+        // Light up instantly when the gate opens, and fade when it closes
+        if self.gate_open {
+            self.open_light = 1.0;
+        } else {
+            let dt = ui.input(|i| i.stable_dt);
+            self.open_light = (self.open_light - dt / GATE_LIGHT_FADE_SECS).max(0.0);
+        }
+    }
+
+    /// Draw the open light and the gain being applied, as a bar that fills with gain reduction
+    fn draw_meter(&self, ui: &mut egui::Ui, at: Vec2, size: Vec2) {
+        let pedal_rect = ui.max_rect();
+        let meter_rect = egui::Rect::from_min_size(
+            pedal_rect.min + at * pedal_rect.size(),
+            size * pedal_rect.size()
+        );
+        let painter = ui.painter();
+
+        let light_radius = meter_rect.height() / 2.0;
+        let light_center = meter_rect.left_center() + Vec2::new(light_radius, 0.0);
+        let closed_color = Color32::from_rgb(90, 20, 20);
+        let open_color = Color32::from_rgb(60, 230, 90);
+        painter.circle_filled(light_center, light_radius, closed_color.lerp_to_gamma(open_color, self.open_light));
+        painter.circle_stroke(light_center, light_radius, egui::Stroke::new(1.0, Color32::BLACK));
+
+        let bar_rect = meter_rect.with_min_x(light_center.x + light_radius * 2.0).shrink2(Vec2::new(0.0, meter_rect.height() * 0.2));
+        painter.rect_filled(bar_rect, 2.0, Color32::from_black_alpha(120));
+
+        let reduction = (-self.gate_gain_db / GATE_METER_RANGE_DB).clamp(0.0, 1.0);
+        if reduction > 0.0 {
+            let reduction_rect = bar_rect.with_max_x(bar_rect.min.x + bar_rect.width() * reduction);
+            painter.rect_filled(reduction_rect, 2.0, Color32::from_rgb(220, 120, 30));
+        }
+
+        painter.text(
+            bar_rect.center(),
+            egui::Align2::CENTER_CENTER,
+            format!("{:.0} dB", self.gate_gain_db),
+            egui::FontId::proportional(bar_rect.height() * 0.8),
+            Color32::WHITE
+        );
+    }
+
     pub fn clone_with_new_id(&self) -> Self {
         let mut cloned = self.clone();
         cloned.id = unique_time_id();
@@ -143,60 +303,51 @@ impl PedalTrait for NoiseGate {
         self.id
     }
 
-    fn set_config(&mut self,_buffer_size:usize, sample_rate:u32) {
-        self.sample_rate = Some(sample_rate as f32);
+    fn has_tail(&self) -> bool {
+        false
     }
 
-    fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
-        if self.sample_rate.is_none() {
-            tracing::warn!("NoiseGate: Sample rate not set. Call set_config first.");
-            return;
-        }
+    fn set_config(&mut self,_buffer_size:usize, sample_rate:u32) {
+        self.gate = Some(Gate::new(sample_rate as f32));
+        self.sync_gate();
+    }
 
-        let threshold_db = self.parameters["Threshold Db"].value.as_float().unwrap();
-        let reduction_ratio = self.parameters["Reduction"].value.as_float().unwrap();
-        let attack_ms = self.parameters["Attack"].value.as_float().unwrap();
-        let release_ms = self.parameters["Release"].value.as_float().unwrap();
+    fn process_audio(&mut self, buffer: &mut [f32], message_buffer: &mut Vec<String>) {
         let dry_wet = self.parameters["Dry/Wet"].value.as_float().unwrap();
 
-        // per sample smoothing coefficients (sample rate independent)
-        let attack_coeff = (-1.0 / ((attack_ms / 1000.0) * self.sample_rate.unwrap())).exp();
-        let release_coeff = (-1.0 / ((release_ms / 1000.0) * self.sample_rate.unwrap())).exp();
-
-        let alpha = 0.99; // Smoothing for level estimation (RMS approximation)
-        let mut level = self.level;
+        let gate = match &mut self.gate {
+            Some(gate) => gate,
+            None => {
+                tracing::warn!("NoiseGate: Sample rate not set. Call set_config first.");
+                return;
+            }
+        };
 
         for sample in buffer.iter_mut() {
-            let x = *sample;
-
-            // Estimate signal power (RMS-like)
-            level = alpha * level + (1.0 - alpha) * (x * x);
-            let power_db = 10.0 * level.max(1e-12).log10();
-
-            // Compute gain target based on threshold and ratio
-            let mut gain_target = 1.0;
-            if power_db < threshold_db {
-                let diff = threshold_db - power_db;
-                let reduction_db = diff * reduction_ratio;
-                gain_target = 10f32.powf(-reduction_db / 20.0);
-            }
+            let gain = gate.process(*sample);
+            *sample *= gain * dry_wet + (1.0 - dry_wet);
+            self.meter_opened |= gate.is_open();
+        }
 
-            // Smoothly approach gain_target using attack/release
-            if gain_target > self.gain {
-                self.gain = attack_coeff * (self.gain - gain_target) + gain_target;
-            } else {
-                self.gain = release_coeff * (self.gain - gain_target) + gain_target;
+        // Send the gate state and gain to the client
+        if self.meter_last_sent_time.elapsed() >= GATE_METER_UPDATE_RATE {
+            let gain_db = (20.0 * gate.gain().max(1e-6).log10() * 2.0).round() / 2.0;
+            let state = (self.meter_opened, gain_db);
+
+            if state != self.meter_last_sent {
+                message_buffer.push(format!("{} {:?}", state.0 as u8, state.1));
+                self.meter_last_sent = state;
             }
 
-            *sample *= self.gain * dry_wet + x * (1.0 - dry_wet);
+            self.meter_opened = gate.is_open();
+            self.meter_last_sent_time = Instant::now();
         }
-
-        self.level = level;
     }
 
     fn reset_buffer(&mut self) {
-        self.gain = 1.0;
-        self.level = 0.0;
+        if let Some(gate) = &mut self.gate {
+            gate.reset();
+        }
     }
 
     fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
@@ -207,7 +358,17 @@ impl PedalTrait for NoiseGate {
         &mut self.parameters
     }
 
-    fn ui(&mut self, ui: &mut egui::Ui, _message_buffer: &[String]) -> Option<(String,PedalParameterValue)> {
+    fn set_parameter_value(&mut self, name: &str, value: PedalParameterValue) {
+        if let Some(parameter) = self.parameters.get_mut(name) {
+            parameter.value = parameter.clamp(value);
+            self.sync_gate();
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, message_buffer: &[String]) -> Option<(String,PedalParameterValue)> {
+        ui.ctx().request_repaint_after(DEFAULT_REFRESH_DURATION);
+        self.update_meter(ui, message_buffer);
+
         ui.add(egui::Image::new(include_image!("images/noise_gate.png")));
 
         let mut to_change = None;
@@ -232,6 +393,8 @@ impl PedalTrait for NoiseGate {
             to_change = Some(("Release".to_string(), value));
         }
 
+        self.draw_meter(ui, Vec2::new(0.1, 0.63), Vec2::new(0.8, 0.05));
+
         let active_param = self.get_parameters().get("Active").unwrap().value.as_bool().unwrap();
         if let Some(value) = pedal_switch(ui, active_param, egui::Vec2::new(0.33, 0.72), 0.16) {
             to_change = Some(("Active".to_string(), PedalParameterValue::Bool(value)));
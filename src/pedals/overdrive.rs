@@ -7,9 +7,11 @@ use crate::dsp_algorithms::biquad::BiquadFilter;
 use crate::dsp_algorithms::eq;
 use crate::unique_time_id;
 
+use super::index_to_choice;
 use super::PedalTrait;
 use super::PedalParameter;
 use super::PedalParameterValue;
+use super::ParameterUnit;
 use super::ui::{pedal_knob, pedal_switch};
 
 use eframe::egui::Image;
@@ -17,12 +19,17 @@ use eframe::egui::{include_image, self, Vec2};
 use serde::ser::SerializeMap;
 use serde::{Serialize, Deserialize};
 
+/// Choices of the Voicing parameter
+pub const VOICING_NAMES: [&str; 4] = ["Flat", "Tube Screamer", "Transparent", "Dark"];
+
 #[derive(Clone)]
 pub struct Overdrive {
     parameters: HashMap<String, PedalParameter>,
     // Processor only
     pre_eq: Option<eq::Equalizer>,
     post_eq: Option<(BiquadFilter, BiquadFilter)>,
+    // Pre/post emphasis filters around the clipper for the selected voicing
+    voicing_eq: Option<(eq::Equalizer, eq::Equalizer)>,
     sample_rate: Option<f32>,
     id: u32,
 }
@@ -50,10 +57,21 @@ impl<'a> Deserialize<'a> for Overdrive {
             parameters: HashMap<String, PedalParameter>,
         }
         let helper = OverdriveData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Overdrives saved before voicings were added are flat
+        parameters.entry("Voicing".to_string())
+            .or_insert_with(|| Overdrive::voicing_parameter());
+        // Voicings saved before choice parameters were added are indexes into VOICING_NAMES
+        index_to_choice(&mut parameters, "Voicing", Overdrive::voicing_parameter());
+        // Overdrives saved before parameters had units show the level as a plain number
+        ParameterUnit::set_units(&mut parameters, &[("Level", ParameterUnit::GainAsDb)]);
+
         Ok(Overdrive {
-            parameters: helper.parameters,
+            parameters,
             pre_eq: None,
             post_eq: None,
+            voicing_eq: None,
             sample_rate: None,
             id: helper.id
         })
@@ -75,7 +93,11 @@ impl Overdrive {
                 value: PedalParameterValue::Float(5.0),
                 min: Some(PedalParameterValue::Float(1.0)),
                 max: Some(PedalParameterValue::Float(20.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Amount of gain before clipping.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -84,7 +106,11 @@ impl Overdrive {
                 value: PedalParameterValue::Float(0.5),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Brightness of the output. Lower values are darker.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -93,9 +119,14 @@ impl Overdrive {
                 value: PedalParameterValue::Float(1.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(3.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Output volume.".to_string()),
+                unit: ParameterUnit::GainAsDb
             },
         );
+        parameters.insert("Voicing".to_string(), Self::voicing_parameter());
 
         parameters.insert(
             "Active".to_string(),
@@ -103,10 +134,28 @@ impl Overdrive {
                 value: PedalParameterValue::Bool(true),
                 min: None,
                 max: None,
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None
             },
         );
-        Overdrive { parameters, pre_eq: None, post_eq: None, sample_rate: None, id: unique_time_id() }
+        Overdrive { parameters, pre_eq: None, post_eq: None, voicing_eq: None, sample_rate: None, id: unique_time_id() }
+    }
+
+    /// One of [`VOICING_NAMES`]
+    fn voicing_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::String(VOICING_NAMES[0].to_string()),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: Some(VOICING_NAMES.iter().map(|name| name.to_string()).collect()),
+            description: Some("Character of the EQ applied before clipping.".to_string()),
+            unit: ParameterUnit::None
+        }
     }
 
     pub fn diode_soft_clip(x: f32, knee: f32) -> f32 {
@@ -126,6 +175,30 @@ impl Overdrive {
         (low, high)
     }
 
+    /// (pre-emphasis, de-emphasis) filters for a voicing, emulating different amp front ends
+    pub fn voicing_eq(voicing: &str, sample_rate: f32) -> (eq::Equalizer, eq::Equalizer) {
+        let (pre, post) = match voicing {
+            // Extra mid hump into the clipper, softened highs after
+            "Tube Screamer" => (
+                vec![BiquadFilter::peaking(900.0, sample_rate, 0.8, 4.0)],
+                vec![BiquadFilter::high_shelf(3000.0, sample_rate, 0.7, -4.0)]
+            ),
+            // Restore the lows cut before clipping and flatten the mid hump
+            "Transparent" => (
+                vec![BiquadFilter::low_shelf(200.0, sample_rate, 0.7, 4.0)],
+                vec![BiquadFilter::peaking(720.0, sample_rate, 0.7, -2.0)]
+            ),
+            // Less high end into the clipper and a low pass after
+            "Dark" => (
+                vec![BiquadFilter::high_shelf(2000.0, sample_rate, 0.7, -6.0)],
+                vec![BiquadFilter::low_pass(3000.0, sample_rate, std::f32::consts::FRAC_1_SQRT_2)]
+            ),
+            // Flat
+            _ => (Vec::new(), Vec::new())
+        };
+        (eq::Equalizer::new(pre), eq::Equalizer::new(post))
+    }
+
     pub fn clone_with_new_id(&self) -> Self {
         let mut cloned = self.clone();
         cloned.id = unique_time_id();
@@ -138,14 +211,20 @@ impl PedalTrait for Overdrive {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn set_config(&mut self,_buffer_size:usize, sample_rate: u32) {
         self.pre_eq = Some(Self::pre_clip_eq(sample_rate as f32));
         self.post_eq = Some(Self::post_eq(sample_rate as f32));
+        let voicing = self.parameters.get("Voicing").unwrap().value.as_str().unwrap();
+        self.voicing_eq = Some(Self::voicing_eq(voicing, sample_rate as f32));
         self.sample_rate = Some(sample_rate as f32);
     }
 
     fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
-        if self.pre_eq.is_none() || self.post_eq.is_none() {
+        if self.pre_eq.is_none() || self.post_eq.is_none() || self.voicing_eq.is_none() {
             tracing::warn!("Overdrive: Filters not initialized. Call set_config first.");
             return;
         }
@@ -155,9 +234,11 @@ impl PedalTrait for Overdrive {
         let tone = self.get_parameters().get("Tone").unwrap().value.as_float().unwrap();
         let pre_eq = self.pre_eq.as_mut().unwrap();
         let (post_lowpass, post_highpass) = self.post_eq.as_mut().unwrap();
+        let (pre_emphasis, de_emphasis) = self.voicing_eq.as_mut().unwrap();
         
         for sample in buffer.iter_mut() {
             let mut x = *sample;
+            x = pre_emphasis.process(x);
             x = pre_eq.process(x);
 
             x *= drive;
@@ -167,6 +248,7 @@ impl PedalTrait for Overdrive {
             let low = post_lowpass.process(x);
             let high = post_highpass.process(x);
             x = low * (1.0 - tone) + high * tone;
+            x = de_emphasis.process(x);
 
             x *= volume;
             *sample = x;
@@ -174,12 +256,13 @@ impl PedalTrait for Overdrive {
     }
 
     fn set_parameter_value(&mut self,name: &str,value:PedalParameterValue) {
-        let parameters = self.get_parameters_mut();
-        if let Some(parameter) = parameters.get_mut(name) {
-            if parameter.is_valid(&value) {
-                parameter.value = value;
-            } else {
-                tracing::warn!("Attempted to set invalid value for parameter {}: {:?}", name, value);
+        if let Some(parameter) = self.parameters.get_mut(name) {
+            parameter.value = parameter.clamp(value);
+            if name == "Voicing" {
+                if let Some(sample_rate) = self.sample_rate {
+                    let voicing = parameter.value.as_str().unwrap();
+                    self.voicing_eq = Some(Self::voicing_eq(voicing, sample_rate));
+                }
             }
         }
     }
@@ -2,12 +2,14 @@ use std::collections::HashMap;
 use std::hash::Hash;
 use crate::dsp_algorithms::biquad::BiquadFilter;
 use crate::dsp_algorithms::eq::Equalizer;
+use crate::dsp_algorithms::stretch::{new_stretch, stretch_latency_samples};
 use crate::pedals::ui::pedal_switch;
 use crate::unique_time_id;
 
 use super::PedalTrait;
 use super::PedalParameter;
 use super::PedalParameterValue;
+use super::ParameterUnit;
 use super::ui::pedal_knob;
 
 use eframe::egui::{include_image, self};
@@ -104,6 +106,10 @@ impl PitchShift {
                 min: Some(PedalParameterValue::Int(-12)),
                 max: Some(PedalParameterValue::Int(12)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Interval to shift the pitch by.".to_string()),
+                unit: ParameterUnit::None,
             }
         );
 
@@ -115,6 +121,10 @@ impl PitchShift {
                 min: Some(PedalParameterValue::Int(1)),
                 max: Some(PedalParameterValue::Int(4096 / 128)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Size of the blocks the signal is analysed in. Larger blocks sound smoother but add latency.".to_string()),
+                unit: ParameterUnit::None,
             }
         );
 
@@ -125,6 +135,10 @@ impl PitchShift {
                 min: Some(PedalParameterValue::Float(100.0)),
                 max: Some(PedalParameterValue::Float(6000.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Frequency in Hz above which the tone of the signal is not shifted, keeping it more natural.".to_string()),
+                unit: ParameterUnit::None,
             }
         );
 
@@ -135,6 +149,10 @@ impl PitchShift {
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(10.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Boosts the high frequencies of the shifted signal.".to_string()),
+                unit: ParameterUnit::None,
             }
         );
 
@@ -145,6 +163,10 @@ impl PitchShift {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
@@ -168,7 +190,7 @@ impl PitchShift {
         let tonality_limit_hz = parameters.get("Tonality Limit").unwrap().value.as_float().unwrap();
         let tonality_limit = tonality_limit_hz / sample_rate;
 
-        let mut stretch = Stretch::new(1, block_size, block_size/4);
+        let mut stretch = new_stretch(block_size);
         stretch.set_transpose_factor_semitones(semitones as f32, Some(tonality_limit));
 
         stretch
@@ -181,6 +203,10 @@ impl PedalTrait for PitchShift {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn set_config(&mut self,_buffer_size:usize, sample_rate:u32) {
         // Set eq
         let eq = Self::eq_from_presence(self.parameters.get("Presence").unwrap().value.as_float().unwrap(), sample_rate as f32);
@@ -214,6 +240,23 @@ impl PedalTrait for PitchShift {
         }
     }
 
+    fn latency_samples(&self) -> usize {
+        if self.signalsmith_stretch.is_some() {
+            stretch_latency_samples(self.parameters.get("Block Size").unwrap().value.as_int().unwrap() as usize * 128)
+        } else {
+            0
+        }
+    }
+
+    fn max_latency_samples(&self) -> usize {
+        // The latency grows with the block size, up to the Block Size parameter's maximum
+        if self.signalsmith_stretch.is_some() {
+            stretch_latency_samples(self.parameters.get("Block Size").unwrap().max.as_ref().and_then(|max| max.as_int()).unwrap_or(0) as usize * 128)
+        } else {
+            0
+        }
+    }
+
     fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
         &self.parameters
     }
@@ -225,18 +268,17 @@ impl PedalTrait for PitchShift {
     fn set_parameter_value(&mut self, name: &str, value: PedalParameterValue) {
         let parameters = self.get_parameters_mut();
         if let Some(parameter) = parameters.get_mut(name) {
-            if parameter.is_valid(&value) {
-                if name == "Presence" {
-                    let presence = value.as_float().unwrap();
-                    parameter.value = value;
-                    if let Some((_eq, sample_rate)) = &self.eq {
-                        self.eq = Some((Self::eq_from_presence(presence, *sample_rate as f32), *sample_rate));
-                    }
-                } else {
-                    parameter.value = value;
-                    if let Some((_eq, sample_rate)) = &self.eq {
-                        self.signalsmith_stretch = Some(Self::stretch_from_parameters(&self.parameters, *sample_rate as f32));
-                    }
+            let value = parameter.clamp(value);
+            if name == "Presence" {
+                let presence = value.as_float().unwrap();
+                parameter.value = value;
+                if let Some((_eq, sample_rate)) = &self.eq {
+                    self.eq = Some((Self::eq_from_presence(presence, *sample_rate as f32), *sample_rate));
+                }
+            } else {
+                parameter.value = value;
+                if let Some((_eq, sample_rate)) = &self.eq {
+                    self.signalsmith_stretch = Some(Self::stretch_from_parameters(&self.parameters, *sample_rate as f32));
                 }
             }
         }
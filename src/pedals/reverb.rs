@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::hash::Hash;
 use crate::{pedals::ui::pedal_switch, unique_time_id};
 
-use super::{PedalTrait, PedalParameter, PedalParameterValue, ui::pedal_knob};
+use super::{PedalTrait, PedalParameter, PedalParameterValue, ParameterUnit, ui::pedal_knob};
 use eframe::egui::{self, include_image};
 use serde::{ser::SerializeMap, Deserialize, Serialize};
 use freeverb::Freeverb;
@@ -44,9 +44,24 @@ impl<'a> Deserialize<'a> for Reverb {
             parameters: HashMap<String, PedalParameter>,
         }
         let helper = ReverbData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Pedals saved before Kill Dry was added keep their dry signal
+        parameters.entry("Kill Dry".to_string())
+            .or_insert_with(|| PedalParameter {
+                value: PedalParameterValue::Bool(false),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Only output the reverb at full level, ignoring Dry/Wet, for use in a parallel effects chain.".to_string()),
+                unit: ParameterUnit::None
+            });
+
         Ok(Reverb {
             reverb: None,
-            parameters: helper.parameters,
+            parameters,
             id: helper.id
         })
     }
@@ -77,6 +92,10 @@ impl Reverb {
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(1.0)),
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("Size of the simulated room. Larger rooms have longer tails.".to_string()),
+            unit: ParameterUnit::None,
         });
 
         parameters.insert("Dampening".into(), PedalParameter {
@@ -84,6 +103,10 @@ impl Reverb {
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(1.0)),
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("How quickly high frequencies die away in the tail.".to_string()),
+            unit: ParameterUnit::None,
         });
 
         parameters.insert("Width".into(), PedalParameter {
@@ -91,6 +114,10 @@ impl Reverb {
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(1.0)),
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("Stereo width of the reverb.".to_string()),
+            unit: ParameterUnit::None,
         });
 
         parameters.insert("Freeze".into(), PedalParameter {
@@ -98,6 +125,10 @@ impl Reverb {
             min: None,
             max: None,
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("Hold the current reverb tail indefinitely.".to_string()),
+            unit: ParameterUnit::None,
         });
 
         parameters.insert("Dry/Wet".into(), PedalParameter {
@@ -105,6 +136,21 @@ impl Reverb {
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(1.0)),
             step: None,
+            snap: false,
+            choices: None,
+            description: Some("Mix between the unprocessed and reverberated signal.".to_string()),
+            unit: ParameterUnit::None,
+        });
+
+        parameters.insert("Kill Dry".into(), PedalParameter {
+            value: PedalParameterValue::Bool(false),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Only output the reverb at full level, ignoring Dry/Wet, for use in a parallel effects chain.".to_string()),
+            unit: ParameterUnit::None,
         });
 
         parameters.insert(
@@ -114,6 +160,10 @@ impl Reverb {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
@@ -136,8 +186,12 @@ impl Reverb {
         let p = &self.parameters;
 
         let dry_wet = p["Dry/Wet"].value.as_float().unwrap().clamp(0.0, 1.0);
-        let wet = dry_wet;
-        let dry = 1.0 - dry_wet;
+        // With the dry killed the reverb is output at full level, rather than scaled by the mix
+        let (dry, wet) = if p["Kill Dry"].value.as_bool().unwrap_or(false) {
+            (0.0, 1.0)
+        } else {
+            (1.0 - dry_wet, dry_wet)
+        };
 
         if let Some((ref mut reverb, _sample_rate)) = &mut self.reverb {
             reverb.set_room_size(p["Room Size"].value.as_float().unwrap() as f64);
@@ -195,13 +249,9 @@ impl PedalTrait for Reverb {
     fn set_parameter_value(&mut self, name: &str, value:PedalParameterValue) {
         let parameters = self.get_parameters_mut();
         if let Some(parameter) = parameters.get_mut(name) {
-            if parameter.is_valid(&value) {
-                parameter.value = value;
-                if name == "Room Size" || name == "Dampening" || name == "Width" || name == "Dry/Wet" || name == "Freeze" {
-                    self.sync_parameters();
-                }
-            } else {
-                tracing::warn!("Attempted to set invalid value for parameter {}: {:?}", name, value);
+            parameter.value = parameter.clamp(value);
+            if name == "Room Size" || name == "Dampening" || name == "Width" || name == "Dry/Wet" || name == "Freeze" || name == "Kill Dry" {
+                self.sync_parameters();
             }
         }
     }
@@ -0,0 +1,319 @@
+use std::{collections::HashMap, hash::Hash};
+use eframe::egui::{self, include_image, Button, Color32, Layout, RichText, UiBuilder, Vec2};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
+
+use crate::{dsp_algorithms::rotary_speaker::RotarySpeaker, pedals::ui::pedal_switch, unique_time_id};
+
+use super::{
+    ui::pedal_knob,
+    PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait,
+};
+
+/// Leslie style rotary speaker with a slow/fast switch
+#[derive(Clone)]
+pub struct Rotary {
+    parameters: HashMap<String, PedalParameter>,
+    // Processor only, created in set_config
+    rotary_speaker: Option<RotarySpeaker>,
+    id: u32,
+}
+
+impl Serialize for Rotary {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser_map = serializer.serialize_map(Some(2))?;
+        ser_map.serialize_entry("id", &self.id)?;
+        ser_map.serialize_entry("parameters", &self.parameters)?;
+        ser_map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Rotary {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RotaryData {
+            id: u32,
+            parameters: HashMap<String, PedalParameter>,
+        }
+        let helper = RotaryData::deserialize(deserializer)?;
+        Ok(Rotary {
+            parameters: helper.parameters,
+            rotary_speaker: None,
+            id: helper.id
+        })
+    }
+}
+
+impl Rotary {
+    pub fn new() -> Self {
+        let mut parameters = HashMap::new();
+
+        parameters.insert(
+            "Fast".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Bool(false),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Switch between the slow and fast rotor speeds.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Horn Slow".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(0.8),
+                min: Some(PedalParameterValue::Float(0.1)),
+                max: Some(PedalParameterValue::Float(2.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Slow speed of the treble horn in Hz.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Horn Fast".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(6.7),
+                min: Some(PedalParameterValue::Float(3.0)),
+                max: Some(PedalParameterValue::Float(10.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Fast speed of the treble horn in Hz.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Drum Slow".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(0.7),
+                min: Some(PedalParameterValue::Float(0.1)),
+                max: Some(PedalParameterValue::Float(2.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Slow speed of the bass drum in Hz.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Drum Fast".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(5.8),
+                min: Some(PedalParameterValue::Float(3.0)),
+                max: Some(PedalParameterValue::Float(10.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Fast speed of the bass drum in Hz.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Horn Ramp".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(0.8),
+                min: Some(PedalParameterValue::Float(0.1)),
+                max: Some(PedalParameterValue::Float(3.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Time in seconds for the horn to change speed.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Drum Ramp".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(3.5),
+                min: Some(PedalParameterValue::Float(0.5)),
+                max: Some(PedalParameterValue::Float(8.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Time in seconds for the drum to change speed. The heavier drum is usually slower than the horn.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Depth".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(0.8),
+                min: Some(PedalParameterValue::Float(0.0)),
+                max: Some(PedalParameterValue::Float(1.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Strength of the volume and pitch modulation.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Active".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Bool(true),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
+            },
+        );
+
+        Self {
+            parameters,
+            rotary_speaker: None,
+            id: unique_time_id()
+        }
+    }
+
+    /// Target (horn, drum) speeds in Hz for the current slow/fast setting
+    fn target_speeds(&self) -> (f32, f32) {
+        let p = &self.parameters;
+        if p["Fast"].value.as_bool().unwrap() {
+            (p["Horn Fast"].value.as_float().unwrap(), p["Drum Fast"].value.as_float().unwrap())
+        } else {
+            (p["Horn Slow"].value.as_float().unwrap(), p["Drum Slow"].value.as_float().unwrap())
+        }
+    }
+
+    fn sync_rotary_speaker(&mut self) {
+        let (horn_speed, drum_speed) = self.target_speeds();
+        let p = &self.parameters;
+        if let Some(rotary_speaker) = &mut self.rotary_speaker {
+            rotary_speaker.set_target_speeds(horn_speed, drum_speed);
+            rotary_speaker.set_ramp_times(p["Horn Ramp"].value.as_float().unwrap(), p["Drum Ramp"].value.as_float().unwrap());
+            rotary_speaker.depth = p["Depth"].value.as_float().unwrap();
+        }
+    }
+
+    pub fn clone_with_new_id(&self) -> Self {
+        let mut cloned = self.clone();
+        cloned.id = unique_time_id();
+        cloned
+    }
+}
+
+impl Hash for Rotary {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PedalTrait for Rotary {
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn has_tail(&self) -> bool {
+        false
+    }
+
+    fn set_config(&mut self, _buffer_size: usize, sample_rate: u32) {
+        let (horn_speed, drum_speed) = self.target_speeds();
+        self.rotary_speaker = Some(RotarySpeaker::new(horn_speed, drum_speed, sample_rate as f32));
+        self.sync_rotary_speaker();
+    }
+
+    fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
+        let rotary_speaker = match &mut self.rotary_speaker {
+            Some(rotary_speaker) => rotary_speaker,
+            None => {
+                tracing::warn!("Rotary: Sample rate not set. Call set_config first.");
+                return;
+            }
+        };
+
+        for sample in buffer.iter_mut() {
+            *sample = rotary_speaker.process(*sample);
+        }
+    }
+
+    fn reset_buffer(&mut self) {
+        if let Some(rotary_speaker) = &mut self.rotary_speaker {
+            rotary_speaker.reset();
+        }
+    }
+
+    fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
+        &self.parameters
+    }
+
+    fn get_parameters_mut(&mut self) -> &mut HashMap<String, PedalParameter> {
+        &mut self.parameters
+    }
+
+    fn set_parameter_value(&mut self, name: &str, value: PedalParameterValue) {
+        if let Some(parameter) = self.parameters.get_mut(name) {
+            parameter.value = parameter.clamp(value);
+            self.sync_rotary_speaker();
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, _message_buffer: &[String]) -> Option<(String,PedalParameterValue)> {
+        let mut to_change = None;
+
+        let mut img_ui = ui.new_child(
+            UiBuilder::new()
+                .max_rect(ui.available_rect_before_wrap())
+        );
+
+        img_ui.add(egui::Image::new(include_image!("images/pedal_gradient.png")).tint(Color32::from_rgb(110, 60, 25)));
+
+        let fast = self.get_parameters().get("Fast").unwrap().value.as_bool().unwrap();
+        ui.allocate_ui_with_layout(
+            ui.available_size() * Vec2::new(0.9, 1.0),
+            Layout::top_down(egui::Align::Center),
+            |ui| {
+                ui.add_space(20.0);
+                ui.label(RichText::new("Rotary").size(20.0));
+
+                ui.add_space(ui.available_height() * 0.3);
+
+                let speed_text = if fast { "Fast" } else { "Slow" };
+                if ui.add(Button::new(RichText::new(speed_text).size(14.0)).selected(fast)).clicked() {
+                    to_change = Some(("Fast".to_string(), PedalParameterValue::Bool(!fast)));
+                }
+            }
+        );
+
+        let knobs = [
+            ("Depth", "Depth", Vec2::new(0.05, 0.2)),
+            ("Horn Ramp", "Horn Ramp", Vec2::new(0.37, 0.2)),
+            ("Drum Ramp", "Drum Ramp", Vec2::new(0.69, 0.2)),
+        ];
+
+        for (label, name, at) in knobs {
+            let param = self.get_parameters().get(name).unwrap();
+            if let Some(value) = pedal_knob(ui, RichText::new(label).color(Color32::WHITE).size(8.0), name, param, at, 0.26, self.id) {
+                to_change = Some((name.to_string(), value));
+            }
+        }
+
+        let active_param = self.get_parameters().get("Active").unwrap().value.as_bool().unwrap();
+        if let Some(value) = pedal_switch(ui, active_param, egui::Vec2::new(0.33, 0.72), 0.16) {
+            to_change = Some(("Active".to_string(), PedalParameterValue::Bool(value)));
+        }
+
+        to_change
+    }
+}
@@ -1,19 +1,74 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 use eframe::egui::{self, include_image};
-use serde::{Serialize, Deserialize};
+use serde::{ser::SerializeMap, Serialize, Deserialize};
+use crate::dsp_algorithms::crossover::LinkwitzRileyCrossover;
+use crate::dsp_algorithms::onset_detector::OnsetDetector;
 use crate::dsp_algorithms::oscillator::{Oscillator, Sine};
 use crate::pedals::ui::pedal_switch;
 use crate::unique_time_id;
-use super::{PedalTrait, PedalParameter, PedalParameterValue};
+use super::{index_to_choice, retrigger_parameter, PedalTrait, PedalParameter, PedalParameterValue, ParameterUnit};
 use super::ui::pedal_knob;
 
-#[derive(Serialize, Deserialize, Clone)]
+/// Frequency the signal is split at for the harmonic mode
+const HARMONIC_CROSSOVER_FREQ: f32 = 700.0;
+/// Drive into the soft clipper for the bias mode
+const BIAS_DRIVE: f32 = 2.0;
+/// Choices of the Mode parameter
+pub const MODE_NAMES: [&str; 3] = ["Standard", "Harmonic", "Bias"];
+
+#[derive(Clone)]
 pub struct Tremolo {
     parameters: HashMap<String, PedalParameter>,
+    // Processor only, created in set_config
+    crossover: Option<LinkwitzRileyCrossover>,
+    onset_detector: Option<OnsetDetector>,
     id: u32
 }
 
+impl Serialize for Tremolo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser_map = serializer.serialize_map(Some(2))?;
+        ser_map.serialize_entry("id", &self.id)?;
+        ser_map.serialize_entry("parameters", &self.parameters)?;
+        ser_map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Tremolo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct TremoloData {
+            id: u32,
+            parameters: HashMap<String, PedalParameter>,
+        }
+        let helper = TremoloData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Tremolos saved before modes were added are standard
+        parameters.entry("Mode".to_string())
+            .or_insert_with(|| Tremolo::mode_parameter());
+        // Modes saved before choice parameters were added are indexes into MODE_NAMES
+        index_to_choice(&mut parameters, "Mode", Tremolo::mode_parameter());
+        // Tremolos saved before retrigger was added run freely
+        parameters.entry("Retrigger".to_string())
+            .or_insert_with(retrigger_parameter);
+
+        Ok(Tremolo {
+            parameters,
+            crossover: None,
+            onset_detector: None,
+            id: helper.id
+        })
+    }
+}
+
 impl Hash for Tremolo {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.id.hash(state);
@@ -31,6 +86,10 @@ impl Tremolo {
                 min: Some(PedalParameterValue::Float(0.1)),
                 max: Some(PedalParameterValue::Float(20.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Shape and rate of the volume modulation.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
         parameters.insert(
@@ -40,8 +99,14 @@ impl Tremolo {
                 min: Some(PedalParameterValue::Float(0.01)),
                 max: Some(PedalParameterValue::Float(1.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("How far the volume drops at the bottom of each cycle.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
+        parameters.insert("Mode".to_string(), Self::mode_parameter());
+        parameters.insert("Retrigger".to_string(), retrigger_parameter());
         parameters.insert(
             "Active".to_string(),
             PedalParameter {
@@ -49,15 +114,35 @@ impl Tremolo {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
         Tremolo {
             parameters,
+            crossover: None,
+            onset_detector: None,
             id: unique_time_id()
         }
     }
 
+    /// One of [`MODE_NAMES`]
+    fn mode_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::String(MODE_NAMES[0].to_string()),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: Some(MODE_NAMES.iter().map(|name| name.to_string()).collect()),
+            description: Some("Standard modulates the volume, Harmonic modulates the low and high bands out of phase, and Bias modulates a soft clipper.".to_string()),
+            unit: ParameterUnit::None,
+        }
+    }
+
     pub fn clone_with_new_id(&self) -> Self {
         let mut cloned = self.clone();
         cloned.id = unique_time_id();
@@ -70,14 +155,73 @@ impl PedalTrait for Tremolo {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
         let depth = self.parameters.get("Depth").unwrap().value.as_float().unwrap();
+        // A static name, so the mode isn't borrowed from the parameters while the oscillator is
+        let mode_name = self.parameters.get("Mode").unwrap().value.as_str().unwrap();
+        let mode = MODE_NAMES.into_iter().find(|&name| name == mode_name).unwrap_or(MODE_NAMES[0]);
+        let retrigger = self.parameters.get("Retrigger").unwrap().value.as_bool().unwrap();
         let oscillator = self.parameters.get_mut("Oscillator").unwrap().value.as_oscillator_mut().unwrap();
+        let mut onset_detector = self.onset_detector.as_mut().filter(|_| retrigger);
+
+        match mode {
+            "Harmonic" => {
+                let crossover = match &mut self.crossover {
+                    Some(crossover) => crossover,
+                    None => {
+                        tracing::warn!("Tremolo: Sample rate not set. Call set_config first.");
+                        return;
+                    }
+                };
 
-        for sample in buffer.iter_mut() {
-            let oscillator_value = oscillator.next().unwrap();
-            let modulated_value = oscillator_value * depth;
-            *sample *= modulated_value;
+                for sample in buffer.iter_mut() {
+                    if onset_detector.as_mut().is_some_and(|detector| detector.process(*sample)) {
+                        oscillator.reset_phase();
+                    }
+                    let oscillator_value = oscillator.next().unwrap();
+                    let (low, high) = crossover.process(*sample);
+                    let low_gain = 1.0 - depth * (1.0 + oscillator_value) * 0.5;
+                    let high_gain = 1.0 - depth * (1.0 - oscillator_value) * 0.5;
+                    *sample = low * low_gain + high * high_gain;
+                }
+            },
+            "Bias" => {
+                for sample in buffer.iter_mut() {
+                    if onset_detector.as_mut().is_some_and(|detector| detector.process(*sample)) {
+                        oscillator.reset_phase();
+                    }
+                    let oscillator_value = oscillator.next().unwrap();
+                    // Shifting the bias of the clipper changes its gain and adds asymmetric harmonics
+                    let bias = 0.3 * depth * oscillator_value;
+                    let gain = 1.0 - depth * (1.0 + oscillator_value) * 0.5;
+                    let clipped = ((*sample + bias) * BIAS_DRIVE).tanh() - (bias * BIAS_DRIVE).tanh();
+                    *sample = gain * clipped / BIAS_DRIVE;
+                }
+            },
+            // Standard
+            _ => {
+                for sample in buffer.iter_mut() {
+                    if onset_detector.as_mut().is_some_and(|detector| detector.process(*sample)) {
+                        oscillator.reset_phase();
+                    }
+                    let oscillator_value = oscillator.next().unwrap();
+                    let modulated_value = oscillator_value * depth;
+                    *sample *= modulated_value;
+                }
+            }
+        }
+    }
+
+    fn reset_buffer(&mut self) {
+        if let Some(crossover) = &mut self.crossover {
+            crossover.reset();
+        }
+        if let Some(onset_detector) = &mut self.onset_detector {
+            onset_detector.reset();
         }
     }
 
@@ -90,7 +234,8 @@ impl PedalTrait for Tremolo {
     }
 
     fn set_config(&mut self, _buffer_size:usize,sample_rate:u32) {
-        self.parameters.get_mut("Oscillator").unwrap().value.as_oscillator_mut().unwrap().set_sample_rate(sample_rate as f32);
+        self.crossover = Some(LinkwitzRileyCrossover::new(HARMONIC_CROSSOVER_FREQ, sample_rate as f32));
+        self.onset_detector = Some(OnsetDetector::new(sample_rate as f32));
     }
 
     fn ui(&mut self, ui: &mut egui::Ui, _message_buffer: &[String]) -> Option<(String, PedalParameterValue)> {
@@ -1,4 +1,8 @@
-use eframe::egui::{self, Color32, Id, Vec2, WidgetText};
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::egui::{self, Color32, Id, Stroke, Vec2, WidgetText};
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 
 use crate::{dsp_algorithms::oscillator::{self, Oscillator}, pedalboard::ParameterPath};
 
@@ -9,6 +13,122 @@ const KNOB_MIN_ANGLE: f32 = -2.618;
 // 150 deg
 const KNOB_MAX_ANGLE: f32 = 2.618;
 
+// Multiplier on slider drag speed while shift is held
+const FINE_DRAG_FACTOR: f64 = 0.1;
+// Fraction of a slider's length moved by a scroll or arrow key nudge, without a step
+const NUDGE_FRACTION: f64 = 0.01;
+// egui's longest delay between the clicks of a double click, in seconds
+const DOUBLE_CLICK_DELAY_SECS: f64 = 0.3;
+
+// Tint of the knob whose parameter is selected
+const ACTIVE_KNOB_TINT: Color32 = Color32::from_rgb(150, 150, 255);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default, EnumIter)]
+pub enum KnobSkin {
+    // Rendered knob images
+    #[default]
+    Classic,
+    // Plain circle with a line indicator
+    Flat,
+}
+
+/// Appearance of every pedal knob, chosen in the client settings.
+/// 
+/// Stored in the egui context memory, so pedals can read it without it being passed through `PedalTrait::ui`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct KnobStyle {
+    pub skin: KnobSkin,
+    // Tint of classic knobs, or fill of flat knobs
+    pub color: [u8; 3],
+}
+
+impl Default for KnobStyle {
+    fn default() -> Self {
+        Self {
+            skin: KnobSkin::Classic,
+            // No tint, the original knob images
+            color: [255, 255, 255],
+        }
+    }
+}
+
+impl KnobStyle {
+    /// The knob style set for the context, or default
+    pub fn get(ctx: &egui::Context) -> Self {
+        ctx.memory(|m| m.data.get_temp::<KnobStyle>(Id::new("knob_style"))).unwrap_or_default()
+    }
+
+    /// Use this knob style for all pedal knobs drawn with the context
+    pub fn set(self, ctx: &egui::Context) {
+        ctx.memory_mut(|m| m.data.insert_temp(Id::new("knob_style"), self));
+    }
+
+    pub fn color32(&self) -> Color32 {
+        Color32::from_rgb(self.color[0], self.color[1], self.color[2])
+    }
+}
+
+/// How often a watched file is checked for changes, while its pedal is shown
+const FILE_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches the modified time of a file loaded by a pedal (e.g. an IR or NAM model), so it can be reloaded
+/// when it is changed by another program.
+///
+/// Only checks the file while the pedal UI is shown and hot reloading is enabled with [`FileWatcher::set_enabled`].
+#[derive(Clone, Default)]
+pub struct FileWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+    last_checked: Option<Instant>
+}
+
+impl FileWatcher {
+    /// Enable or disable hot reloading of files for all pedals shown with the context
+    pub fn set_enabled(ctx: &egui::Context, enabled: bool) {
+        ctx.memory_mut(|m| m.data.insert_temp(Id::new("hot_reload_files"), enabled));
+    }
+
+    fn enabled(ctx: &egui::Context) -> bool {
+        ctx.memory(|m| m.data.get_temp::<bool>(Id::new("hot_reload_files"))).unwrap_or(false)
+    }
+
+    /// Whether the file at `path` has been modified since the last check.
+    /// 
+    /// Selecting a different file is not a change.
+    pub fn changed(&mut self, ctx: &egui::Context, path: &str) -> bool {
+        if path.is_empty() || !Self::enabled(ctx) {
+            return false;
+        }
+
+        if self.path == path && self.last_checked.is_some_and(|checked| checked.elapsed() < FILE_WATCH_INTERVAL) {
+            ctx.request_repaint_after(FILE_WATCH_INTERVAL);
+            return false;
+        }
+        self.last_checked = Some(Instant::now());
+        ctx.request_repaint_after(FILE_WATCH_INTERVAL);
+
+        let modified = std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        let changed = self.path == path && modified.is_some() && modified != self.modified;
+        if self.path != path {
+            self.path = path.to_string();
+        }
+        self.modified = modified;
+
+        changed
+    }
+
+    /// Ask the client to reload the pedal's file on the processor
+    pub fn request_reload(ctx: &egui::Context, pedal_id: u32) {
+        ctx.memory_mut(|m| m.data.insert_temp(Id::new("reload_pedal").with(pedal_id), true));
+    }
+
+    /// Whether a reload was requested for the pedal since this was last called
+    pub fn take_reload_request(ctx: &egui::Context, pedal_id: u32) -> bool {
+        ctx.memory_mut(|m| m.data.remove_temp::<bool>(Id::new("reload_pedal").with(pedal_id))).unwrap_or(false)
+    }
+}
+
 pub fn float_round(value: f32, step: f32) -> f32 {
     let rounded = (value / step).round() * step;
     rounded
@@ -32,6 +152,17 @@ pub fn pedal_knob(
         PedalParameterValue::Int(_) => {
             pedal_parameter_float = parameter.int_to_float();
         },
+        // Choices are turned through by index, like an int parameter
+        PedalParameterValue::String(ref choice) if parameter.choices.as_ref().is_some_and(|choices| choices.len() > 1) => {
+            let choices = parameter.choices.as_ref().unwrap();
+            let index = choices.iter().position(|c| c == choice).unwrap_or(0);
+            pedal_parameter_float = PedalParameter {
+                value: PedalParameterValue::Float(index as f32),
+                min: Some(PedalParameterValue::Float(0.0)),
+                max: Some(PedalParameterValue::Float((choices.len() - 1) as f32)),
+                ..parameter.clone()
+            };
+        },
         _ => {
             ui.label("Invalid parameter type.");
             return None;
@@ -44,7 +175,8 @@ pub fn pedal_knob(
     } else {
         false
     };
-    let tint = if is_active { Color32::from_rgb(150, 150, 255) } else { Color32::WHITE };
+    let style = KnobStyle::get(ui.ctx());
+    let tint = if is_active { ACTIVE_KNOB_TINT } else { style.color32() };
 
     let value = pedal_parameter_float.value.as_float().unwrap();
     let min = pedal_parameter_float.min.unwrap().as_float().unwrap();
@@ -66,28 +198,32 @@ pub fn pedal_knob(
         .layout(egui::Layout::top_down(egui::Align::Center))
         .sense(egui::Sense::click_and_drag()),
     |ui| {
-            let mut main_knob_im_ui = ui.new_child(
-                egui::UiBuilder::new()
-                    .max_rect(ui.available_rect_before_wrap())
-                    .layout(egui::Layout::top_down(egui::Align::Center))
-            );
+            let knob_response = match style.skin {
+                KnobSkin::Classic => {
+                    let mut main_knob_im_ui = ui.new_child(
+                        egui::UiBuilder::new()
+                            .max_rect(ui.available_rect_before_wrap())
+                            .layout(egui::Layout::top_down(egui::Align::Center))
+                    );
 
-            main_knob_im_ui.add(egui::Image::new(egui::include_image!("images/pedal_knob_blender_base.png"))
-                .rotate(knob_rotate, Vec2::splat(0.5))
-                .max_width(size_px)
-                .tint(tint)
-            );
+                    main_knob_im_ui.add(egui::Image::new(egui::include_image!("images/pedal_knob_blender_base.png"))
+                        .rotate(knob_rotate, Vec2::splat(0.5))
+                        .max_width(size_px)
+                        .tint(tint)
+                    );
 
-            let knob_im_shine_overlay = ui.add(egui::Image::new(egui::include_image!("images/pedal_knob_blender_shine.png"))
-                .max_width(size_px)
-                .sense(egui::Sense::click_and_drag())
-            );
+                    ui.add(egui::Image::new(egui::include_image!("images/pedal_knob_blender_shine.png"))
+                        .max_width(size_px)
+                        .sense(egui::Sense::click_and_drag())
+                    )
+                },
+                KnobSkin::Flat => flat_knob(ui, size_px, knob_rotate, tint)
+            };
 
-            
-            if knob_im_shine_overlay.dragged() {
+            if knob_response.dragged() {
                 let current_y = ui.input(|i| i.pointer.interact_pos().expect("Failed to get cursor location")).y;
 
-                let (init_y, init_value) = if knob_im_shine_overlay.drag_started() {
+                let (init_y, init_value) = if knob_response.drag_started() {
                     // Store the initial y position and value of the drag
                     ui.ctx().memory_mut(|m| {
                         m.data.insert_temp(Id::new("knob_drag_init_y"), (current_y, value));
@@ -108,7 +244,7 @@ pub fn pedal_knob(
                 }
             }
 
-            if knob_im_shine_overlay.hovered() {
+            if knob_response.hovered() {
                 ui.ctx().output_mut(|o| o.cursor_icon = egui::CursorIcon::ResizeVertical);
             }
 
@@ -117,18 +253,238 @@ pub fn pedal_knob(
     );
 
     if new_value_float != value {
-        if matches!(parameter.value, PedalParameterValue::Int(_)) {
-            if new_value_float as i16 != value as i16 {
-                Some(PedalParameterValue::Int(new_value_float as i16))
+        match &parameter.value {
+            PedalParameterValue::Int(_) => {
+                if new_value_float as i16 != value as i16 {
+                    Some(PedalParameterValue::Int(new_value_float as i16))
+                } else {
+                    None
+                }
+            },
+            PedalParameterValue::String(_) => {
+                if new_value_float as usize != value as usize {
+                    parameter.choices.as_ref()
+                        .and_then(|choices| choices.get(new_value_float as usize))
+                        .map(|choice| PedalParameterValue::String(choice.clone()))
+                } else {
+                    None
+                }
+            },
+            _ => Some(PedalParameterValue::Float(new_value_float))
+        }
+    } else {
+        None
+    }
+}
+
+/// A plain circular knob, with a line from the centre at `rotation` radians clockwise from the top
+fn flat_knob(ui: &mut egui::Ui, size_px: f32, rotation: f32, fill: Color32) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(Vec2::splat(size_px), egui::Sense::click_and_drag());
+    let center = rect.center();
+    let radius = size_px * 0.42;
+
+    // Indicator contrasts with the fill
+    let luminance = 0.299 * fill.r() as f32 + 0.587 * fill.g() as f32 + 0.114 * fill.b() as f32;
+    let indicator_color = if luminance > 140.0 { Color32::from_gray(20) } else { Color32::from_gray(235) };
+    let direction = Vec2::new(rotation.sin(), -rotation.cos());
+
+    let painter = ui.painter();
+    painter.circle(center, radius, fill, Stroke::new(size_px * 0.03, Color32::from_gray(25)));
+    painter.line_segment(
+        [center + direction * radius * 0.35, center + direction * radius * 0.9],
+        Stroke::new(size_px * 0.05, indicator_color)
+    );
+
+    response
+}
+
+/// Slider for a numeric parameter.
+/// 
+/// Double click to type a value, which is clamped between `min` and `max`. Escape cancels the entry, restoring the value
+/// from before the double click. Hold shift while dragging for fine adjustment.
+/// Without shift, the slider snaps to `step` if given.
+/// 
+/// The value can also be nudged by `step` (or 1% of the range for floats without a step) with the scroll wheel while hovered,
+/// or the arrow keys while focused. Logarithmic sliders are nudged by 1% of their length instead, rounded to `step`.
+/// Shift also makes nudging finer.
+/// 
+/// `suffix` is shown after the value, e.g. a unit, and may be included when typing a value.
+pub fn parameter_slider<N: egui::emath::Numeric>(
+    ui: &mut egui::Ui,
+    value: &mut N,
+    min: N,
+    max: N,
+    step: Option<f64>,
+    max_decimals: Option<usize>,
+    logarithmic: bool,
+    suffix: Option<&str>
+) -> egui::Response {
+    let base_id = ui.next_auto_id();
+    let entry_id = base_id.with("numeric_entry");
+    let text_edit_id = base_id.with("numeric_entry_text");
+    let fine_drag_id = base_id.with("fine_drag");
+    // Value before the clicks that opened the numeric entry, and when the first click was pressed
+    let press_origin_id = base_id.with("press_origin");
+
+    // === Numeric entry ===
+    if let Some(mut text) = ui.data(|d| d.get_temp::<String>(entry_id)) {
+        let mut response = ui.add(
+            egui::TextEdit::singleline(&mut text)
+                .id(text_edit_id)
+                .desired_width(ui.spacing().slider_width)
+        );
+
+        if response.lost_focus() {
+            ui.data_mut(|d| d.remove::<String>(entry_id));
+
+            let original = ui.data(|d| d.get_temp::<(f64, f64)>(press_origin_id)).map(|(original, _)| original);
+            ui.data_mut(|d| d.remove::<(f64, f64)>(press_origin_id));
+            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                // The clicks that opened the entry have already changed the value, so cancelling restores it
+                if let Some(original) = original {
+                    if original != value.to_f64() {
+                        *value = N::from_f64(original);
+                        response.mark_changed();
+                    }
+                }
             } else {
-                None
+                let entered_text = suffix.map_or(text.trim(), |suffix| text.trim().trim_end_matches(suffix.trim()).trim_end());
+                match entered_text.parse::<f64>() {
+                    Ok(entered) if entered.is_finite() => {
+                        let clamped = entered.clamp(min.to_f64(), max.to_f64());
+                        let new_value = if N::INTEGRAL { N::from_f64(clamped.round()) } else { N::from_f64(clamped) };
+                        if new_value.to_f64() != value.to_f64() {
+                            *value = new_value;
+                            response.mark_changed();
+                        }
+                    },
+                    _ => tracing::warn!("Ignoring invalid numeric parameter entry: {:?}", text)
+                }
             }
         } else {
-            Some(PedalParameterValue::Float(new_value_float))
+            ui.data_mut(|d| d.insert_temp(entry_id, text));
+        }
+
+        return response;
+    }
+
+    // === Slider ===
+    let init_value = value.to_f64();
+    let fine = ui.input(|i| i.modifiers.shift);
+
+    // Arrow keys are consumed before the slider is added, so the slider doesn't also handle them
+    let mut nudges = 0.0;
+    if ui.memory(|m| m.has_focus(base_id)) {
+        nudges = ui.input_mut(|i| {
+            let modifiers = i.modifiers;
+            let increase = i.count_and_consume_key(modifiers, egui::Key::ArrowRight) + i.count_and_consume_key(modifiers, egui::Key::ArrowUp);
+            let decrease = i.count_and_consume_key(modifiers, egui::Key::ArrowLeft) + i.count_and_consume_key(modifiers, egui::Key::ArrowDown);
+            increase as f64 - decrease as f64
+        });
+    }
+
+    let mut slider = egui::Slider::new(value, min..=max);
+    if let Some(step) = step {
+        if !fine {
+            slider = slider.step_by(step);
+        }
+    }
+    if let Some(max_decimals) = max_decimals {
+        slider = slider.max_decimals(max_decimals);
+    }
+    if let Some(suffix) = suffix {
+        slider = slider.suffix(suffix);
+    }
+    let mut response = ui.add(slider.logarithmic(logarithmic));
+
+    if response.hovered() {
+        let scroll = ui.input(|i| i.raw_scroll_delta.y);
+        if scroll != 0.0 {
+            nudges += scroll.signum() as f64;
+        }
+
+        // Stop any surrounding scroll area from scrolling
+        ui.input_mut(|i| {
+            i.raw_scroll_delta = Vec2::ZERO;
+            i.smooth_scroll_delta = Vec2::ZERO;
+        });
+    }
+
+    if nudges != 0.0 {
+        let (min_f, max_f) = (min.to_f64(), max.to_f64());
+        let nudged = if logarithmic && min_f > 0.0 && max_f > min_f {
+            // Move evenly along the slider as it is drawn, so nudges aren't tiny at the bottom and huge at the top
+            let fraction = if fine { NUDGE_FRACTION * FINE_DRAG_FACTOR } else { NUDGE_FRACTION };
+            let normalized = log_normalized(init_value.max(min_f), min_f, max_f) + nudges * fraction;
+            let mut nudged = log_from_normalized(normalized, min_f, max_f);
+            if let (Some(step), false) = (step, fine) {
+                nudged = (nudged / step).round() * step;
+            }
+            // Rounding to the step may undo a small nudge
+            let min_nudge = if fine { None } else { step.or(N::INTEGRAL.then_some(1.0)) };
+            match min_nudge {
+                Some(min_nudge) if (nudged - init_value).abs() < min_nudge => init_value + nudges.signum() * min_nudge,
+                _ => nudged
+            }
+        } else {
+            let nudge_step = match step {
+                Some(step) => step,
+                None if N::INTEGRAL => 1.0,
+                None => (max_f - min_f) * NUDGE_FRACTION
+            };
+            let nudge_step = if fine && !N::INTEGRAL { nudge_step * FINE_DRAG_FACTOR } else { nudge_step };
+            value.to_f64() + nudges * nudge_step
+        };
+        let nudged = nudged.clamp(min_f, max_f);
+        *value = if N::INTEGRAL { N::from_f64(nudged.round()) } else { N::from_f64(nudged) };
+        if value.to_f64() != init_value {
+            response.mark_changed();
+        }
+    }
+
+    // Keep the value from before the first click of a double click, which has already been applied by the time
+    // the double click is detected
+    if ui.input(|i| i.pointer.any_pressed()) && response.is_pointer_button_down_on() {
+        let now = ui.input(|i| i.time);
+        let first_click = ui.data(|d| d.get_temp::<(f64, f64)>(press_origin_id))
+            .map_or(true, |(_, pressed)| now - pressed > DOUBLE_CLICK_DELAY_SECS);
+        if first_click {
+            ui.data_mut(|d| d.insert_temp(press_origin_id, (init_value, now)));
+        }
+    }
+
+    if response.double_clicked() {
+        // Undo any change from the second click, and open the numeric entry
+        *value = N::from_f64(init_value);
+        ui.data_mut(|d| d.insert_temp(entry_id, format!("{}", init_value)));
+        ui.memory_mut(|m| m.request_focus(text_edit_id));
+    } else if fine && response.dragged() {
+        // The fine value is accumulated separately so that integer parameters can move slower than 1 per frame
+        let range = max.to_f64() - min.to_f64();
+        let delta = response.drag_delta().x as f64 * range / response.rect.width().max(1.0) as f64 * FINE_DRAG_FACTOR;
+        let fine_value = (ui.data(|d| d.get_temp::<f64>(fine_drag_id)).unwrap_or(init_value) + delta)
+            .clamp(min.to_f64(), max.to_f64());
+        ui.data_mut(|d| d.insert_temp(fine_drag_id, fine_value));
+
+        *value = if N::INTEGRAL { N::from_f64(fine_value.round()) } else { N::from_f64(fine_value) };
+        if value.to_f64() != init_value {
+            response.mark_changed();
         }
     } else {
-        None
+        ui.data_mut(|d| d.remove::<f64>(fine_drag_id));
     }
+
+    response
+}
+
+/// Position of `value` along a logarithmic slider from `min` to `max`, from 0.0 to 1.0. The range must be positive.
+fn log_normalized(value: f64, min: f64, max: f64) -> f64 {
+    (value / min).ln() / (max / min).ln()
+}
+
+/// Value at a position from 0.0 to 1.0 along a logarithmic slider from `min` to `max`. The range must be positive.
+fn log_from_normalized(normalized: f64, min: f64, max: f64) -> f64 {
+    min * (max / min).powf(normalized)
 }
 
 pub fn pedal_switch(
@@ -162,6 +518,38 @@ pub fn pedal_switch(
     }
 }
 
+/// Draw a waveform from the peak level of each section of a sound, normalized to 0.0-1.0, at a position and size relative to the pedal.
+pub fn waveform_preview(
+    ui: &mut egui::Ui,
+    peaks: &[f32],
+    at: egui::Vec2,
+    size: egui::Vec2
+) -> egui::Response {
+    let rect = egui::Rect::from_min_size(
+        ui.max_rect().min + Vec2::new(at.x * ui.max_rect().width(), at.y * ui.max_rect().height()),
+        Vec2::new(size.x * ui.max_rect().width(), size.y * ui.max_rect().height())
+    );
+    let response = ui.allocate_rect(rect, egui::Sense::hover());
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 3.0, Color32::from_black_alpha(120));
+
+    if !peaks.is_empty() {
+        let bar_width = rect.width() / peaks.len() as f32;
+        let half_height = rect.height() * 0.45;
+        for (i, peak) in peaks.iter().enumerate() {
+            let x = rect.left() + (i as f32 + 0.5) * bar_width;
+            let extent = (peak * half_height).max(0.5);
+            painter.line_segment(
+                [egui::pos2(x, rect.center().y - extent), egui::pos2(x, rect.center().y + extent)],
+                Stroke::new((bar_width * 0.7).max(1.0), Color32::from_gray(200))
+            );
+        }
+    }
+
+    response
+}
+
 pub fn sideways_arrow(
     ui: &mut egui::Ui,
     button_rect: egui::Rect,
@@ -249,7 +637,7 @@ pub fn oscillator_selection_window(
                 let max_freq = parameter.max.as_ref().and_then(|p| p.as_float()).unwrap_or(20.0);
                 min_freq..=max_freq
             };
-            ui.add(egui::Slider::new(&mut frequency_value, frequency_range).logarithmic(true).max_decimals(2));
+            parameter_slider(ui, &mut frequency_value, *frequency_range.start(), *frequency_range.end(), None, Some(2), true, None);
 
             if frequency_value != selected_oscillator.get_frequency() {
                 let mut cloned = selected_oscillator.clone();
@@ -260,7 +648,7 @@ pub fn oscillator_selection_window(
             // Phase
             ui.label("Phase Offset");
             let mut phase_offset_value = selected_oscillator.get_phase_offset();
-            ui.add(egui::Slider::new(&mut phase_offset_value, -0.5..=0.5).max_decimals(2));
+            parameter_slider(ui, &mut phase_offset_value, -0.5, 0.5, None, Some(2), false, None);
 
             if phase_offset_value != selected_oscillator.get_phase_offset() {
                 let mut cloned = selected_oscillator.clone();
@@ -272,7 +660,7 @@ pub fn oscillator_selection_window(
             if let Oscillator::Sine(sine) = selected_oscillator {
                 ui.label("Squareness");
                 let mut squareness_value = sine.get_squareness();
-                ui.add(egui::Slider::new(&mut squareness_value, 0.0..=1.0).max_decimals(2));
+                parameter_slider(ui, &mut squareness_value, 0.0, 1.0, None, Some(2), false, None);
                 if squareness_value != sine.get_squareness() {
                     let new_sine = oscillator::Sine::new(
                         48000.0,
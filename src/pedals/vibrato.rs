@@ -2,9 +2,10 @@ use std::collections::HashMap;
 use std::hash::Hash;
 use eframe::egui::{self, include_image};
 use serde::{ser::SerializeMap, Deserialize, Serialize};
-use super::{PedalTrait, PedalParameter, PedalParameterValue};
+use super::{retrigger_parameter, PedalTrait, PedalParameter, PedalParameterValue, ParameterUnit};
 use crate::{
     dsp_algorithms::{oscillator::{Oscillator, Sine},
+    onset_detector::OnsetDetector,
     variable_delay::VariableDelayLine},
     pedals::ui::{pedal_knob, pedal_switch},
     unique_time_id
@@ -13,6 +14,8 @@ use crate::{
 #[derive(Clone)]
 pub struct Vibrato {
     delay_line: Option<VariableDelayLine>,
+    // Processor only, created in set_config
+    onset_detector: Option<OnsetDetector>,
     parameters: HashMap<String, PedalParameter>,
     id: u32
 }
@@ -46,9 +49,16 @@ impl<'a> Deserialize<'a> for Vibrato {
             parameters: HashMap<String, PedalParameter>,
         }
         let helper = VibratoData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Vibratos saved before retrigger was added run freely
+        parameters.entry("Retrigger".to_string())
+            .or_insert_with(retrigger_parameter);
+
         Ok(Vibrato {
             delay_line: None,
-            parameters: helper.parameters,
+            onset_detector: None,
+            parameters,
             id: helper.id
         })
     }
@@ -67,6 +77,10 @@ impl Vibrato {
                 min: Some(PedalParameterValue::Float(0.01)),
                 max: Some(PedalParameterValue::Float(15.0)),
                 step: Some(PedalParameterValue::Float(0.1)),
+                snap: false,
+                choices: None,
+                description: Some("Maximum delay in ms swept by the oscillator. Higher values give a wider pitch change.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
         parameters.insert(
@@ -76,6 +90,10 @@ impl Vibrato {
                 min: Some(PedalParameterValue::Float(0.1)),
                 max: Some(PedalParameterValue::Float(20.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Shape and rate of the pitch modulation.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
         parameters.insert(
@@ -85,8 +103,13 @@ impl Vibrato {
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
                 step: None,
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed and modulated signal. Mixing in the dry signal gives a chorus.".to_string()),
+                unit: ParameterUnit::None,
             },
         );
+        parameters.insert("Retrigger".to_string(), retrigger_parameter());
         parameters.insert(
             "Active".to_string(),
             PedalParameter {
@@ -94,11 +117,16 @@ impl Vibrato {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
         Self {
             delay_line: None,
+            onset_detector: None,
             parameters,
             id: unique_time_id()
         }
@@ -116,6 +144,10 @@ impl PedalTrait for Vibrato {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
         if self.delay_line.is_none() {
             tracing::warn!("Vibrato pedal not initialized. Call set_config before processing audio.");
@@ -123,10 +155,16 @@ impl PedalTrait for Vibrato {
         }
 
         let dry_wet = self.parameters.get("Dry/Wet").unwrap().value.as_float().unwrap();
+        let retrigger = self.parameters.get("Retrigger").unwrap().value.as_bool().unwrap();
         let oscillator = self.parameters.get_mut("Oscillator").unwrap().value.as_oscillator_mut().unwrap();
         let delay_line = self.delay_line.as_mut().unwrap();
+        let mut onset_detector = self.onset_detector.as_mut().filter(|_| retrigger);
 
         for sample in buffer.iter_mut() {
+            if onset_detector.as_mut().is_some_and(|detector| detector.process(*sample)) {
+                oscillator.reset_phase();
+            }
+
             delay_line.buffer.push_front(*sample);
             delay_line.buffer.pop_back();
     
@@ -144,6 +182,9 @@ impl PedalTrait for Vibrato {
         if let Some(delay_line) = &mut self.delay_line {
             delay_line.reset();
         }
+        if let Some(onset_detector) = &mut self.onset_detector {
+            onset_detector.reset();
+        }
     }
 
     fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
@@ -157,12 +198,7 @@ impl PedalTrait for Vibrato {
     fn set_parameter_value(&mut self,name: &str,value:PedalParameterValue) {
         let parameters = self.get_parameters_mut();
         if let Some(parameter) = parameters.get_mut(name) {
-            if !parameter.is_valid(&value) {
-                tracing::warn!("Attempted to set invalid value for parameter {}: {:?}", name, value);
-                return;
-            }
-
-            parameter.value = value;
+            parameter.value = parameter.clamp(value);
             if name == "Depth" {
                 let depth_ms = parameter.value.as_float().unwrap();
                 if let Some(osc) = parameters.get_mut("Oscillator") {
@@ -199,7 +235,6 @@ impl PedalTrait for Vibrato {
         let max_delay_samples = (sample_rate as f32 * depth_ms / 1000.0).ceil() as usize;
 
         self.delay_line = Some(VariableDelayLine::new(max_delay_samples));
-
-        self.parameters.get_mut("Oscillator").unwrap().value.as_oscillator_mut().unwrap().set_sample_rate(sample_rate as f32);
+        self.onset_detector = Some(OnsetDetector::new(sample_rate as f32));
     }
 }
\ No newline at end of file
@@ -1,26 +1,71 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 
+use crate::dsp_algorithms::auto_swell::AutoSwell;
 use crate::unique_time_id;
 
 use super::PedalTrait;
 use super::PedalParameter;
 use super::PedalParameterValue;
+use super::ParameterUnit;
 use super::ui::{pedal_knob, pedal_switch};
 
 use eframe::egui::Color32;
 use eframe::egui::Image;
 use eframe::egui::RichText;
 use eframe::egui::{include_image, self, Vec2};
-use serde::{Serialize, Deserialize};
+use serde::{ser::SerializeMap, Serialize, Deserialize};
 
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Clone)]
 pub struct Volume {
     parameters: HashMap<String, PedalParameter>,
+    // Processor only, created in set_config
+    auto_swell: Option<AutoSwell>,
     id: u32,
 }
 
+impl Serialize for Volume {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser_map = serializer.serialize_map(Some(2))?;
+        ser_map.serialize_entry("id", &self.id)?;
+        ser_map.serialize_entry("parameters", &self.parameters)?;
+        ser_map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Volume {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct VolumeData {
+            id: u32,
+            parameters: HashMap<String, PedalParameter>,
+        }
+        let helper = VolumeData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Volumes saved before auto swell was added
+        parameters.entry("Auto Swell".to_string())
+            .or_insert_with(|| Volume::auto_swell_parameter());
+        parameters.entry("Swell Time".to_string())
+            .or_insert_with(|| Volume::swell_time_parameter());
+        // Volumes saved before parameters had units show the volume as a plain number
+        ParameterUnit::set_units(&mut parameters, &[("Volume", ParameterUnit::GainAsDb)]);
+
+        Ok(Volume {
+            parameters,
+            auto_swell: None,
+            id: helper.id
+        })
+    }
+}
+
 impl Hash for Volume {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.id.hash(state);
@@ -36,19 +81,57 @@ impl Volume {
                 value: PedalParameterValue::Float(1.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(5.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Gain applied to the signal.".to_string()),
+                unit: ParameterUnit::GainAsDb
             },
         );
+        parameters.insert("Auto Swell".to_string(), Self::auto_swell_parameter());
+        parameters.insert("Swell Time".to_string(), Self::swell_time_parameter());
         parameters.insert(
             "Active".to_string(),
             PedalParameter {
                 value: PedalParameterValue::Bool(true),
                 min: None,
                 max: None,
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None
             },
         );
-        Volume { parameters, id: unique_time_id() }
+        Volume { parameters, auto_swell: None, id: unique_time_id() }
+    }
+
+    /// Fade in automatically after each note onset
+    fn auto_swell_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::Bool(false),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Fade in automatically after each note is picked, like a volume pedal swell.".to_string()),
+            unit: ParameterUnit::None
+        }
+    }
+
+    /// Fade in time in ms for auto swell
+    fn swell_time_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::Float(300.0),
+            min: Some(PedalParameterValue::Float(10.0)),
+            max: Some(PedalParameterValue::Float(2000.0)),
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Time in ms taken to fade in after each note when Auto Swell is on.".to_string()),
+            unit: ParameterUnit::None
+        }
     }
 
     pub fn clone_with_new_id(&self) -> Self {
@@ -63,11 +146,43 @@ impl PedalTrait for Volume {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
         let volume = self.parameters.get("Volume").unwrap().value.as_float().unwrap();
-        
-        for sample in buffer.iter_mut() {
-            *sample *= volume;
+        let auto_swell = self.parameters.get("Auto Swell").unwrap().value.as_bool().unwrap();
+
+        if auto_swell {
+            let swell_time = self.parameters.get("Swell Time").unwrap().value.as_float().unwrap();
+            let swell = match &mut self.auto_swell {
+                Some(swell) => swell,
+                None => {
+                    tracing::warn!("Volume: Sample rate not set. Call set_config first.");
+                    return;
+                }
+            };
+            swell.set_swell_time(swell_time);
+
+            for sample in buffer.iter_mut() {
+                *sample *= volume * swell.process(*sample);
+            }
+        } else {
+            for sample in buffer.iter_mut() {
+                *sample *= volume;
+            }
+        }
+    }
+
+    fn set_config(&mut self, _buffer_size: usize, sample_rate: u32) {
+        let swell_time = self.parameters.get("Swell Time").unwrap().value.as_float().unwrap();
+        self.auto_swell = Some(AutoSwell::new(sample_rate as f32, swell_time));
+    }
+
+    fn reset_buffer(&mut self) {
+        if let Some(swell) = &mut self.auto_swell {
+            swell.reset();
         }
     }
 
@@ -7,6 +7,7 @@ use std::sync::Arc;
 use super::PedalTrait;
 use super::PedalParameter;
 use super::PedalParameterValue;
+use super::ParameterUnit;
 use super::ui::pedal_knob;
 
 use crate::forward_slash_path;
@@ -109,19 +110,31 @@ impl<'a> Deserialize<'a> for Vst2 {
             value: PedalParameterValue::String(path.clone()),
             min: None,
             max: None,
-            step: None
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("VST2 plugin to run.".to_string()),
+            unit: ParameterUnit::None
         });
         parameters.insert(String::from("Dry/Wet"), PedalParameter {
             value: PedalParameterValue::Float(dry_wet),
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(1.0)),
-            step: None
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Mix between the unprocessed signal and the plugin output.".to_string()),
+            unit: ParameterUnit::None
         });
         parameters.insert(String::from("Active"), PedalParameter {
             value: PedalParameterValue::Bool(active),
             min: None,
             max: None,
-            step: None
+            step: None,
+            snap: false,
+            choices: None,
+            description: None,
+            unit: ParameterUnit::None
         });
 
         let mut param_index_map = HashMap::new();
@@ -189,7 +202,11 @@ impl Vst2 {
                 value: PedalParameterValue::String("".to_string()),
                 min: None,
                 max: None,
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("VST2 plugin to run.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -198,7 +215,11 @@ impl Vst2 {
                 value: PedalParameterValue::Float(1.0),
                 min: Some(PedalParameterValue::Float(0.0)),
                 max: Some(PedalParameterValue::Float(1.0)),
-                step: None
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed signal and the plugin output.".to_string()),
+                unit: ParameterUnit::None
             },
         );
         parameters.insert(
@@ -208,6 +229,10 @@ impl Vst2 {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
@@ -282,7 +307,11 @@ impl Vst2 {
                             value: PedalParameterValue::String(path.to_string()),
                             min: None,
                             max: None,
-                            step: None
+                            step: None,
+                            snap: false,
+                            choices: None,
+                            description: Some("VST2 plugin to run.".to_string()),
+                            unit: ParameterUnit::None
                         },
                     );
                 },
@@ -294,7 +323,11 @@ impl Vst2 {
                             value: PedalParameterValue::String("".to_string()),
                             min: None,
                             max: None,
-                            step: None
+                            step: None,
+                            snap: false,
+                            choices: None,
+                            description: Some("VST2 plugin to run.".to_string()),
+                            unit: ParameterUnit::None
                         },
                     );
                     self.instance = None;
@@ -312,7 +345,11 @@ impl Vst2 {
                         value: PedalParameterValue::Float(value),
                         min: Some(PedalParameterValue::Float(0.0)),
                         max: Some(PedalParameterValue::Float(1.0)),
-                        step: None
+                        step: None,
+                        snap: false,
+                        choices: None,
+                        description: None,
+                        unit: ParameterUnit::None
                     }
                 );
 
@@ -325,7 +362,11 @@ impl Vst2 {
                     value: PedalParameterValue::String("".to_string()),
                     min: None,
                     max: None,
-                    step: None
+                    step: None,
+                    snap: false,
+                    choices: None,
+                    description: Some("VST2 plugin to run.".to_string()),
+                    unit: ParameterUnit::None
                 },
             );
             self.param_index_map.clear();
@@ -551,14 +592,12 @@ impl PedalTrait for Vst2 {
         }
 
         if let Some(parameter) = self.parameters.get_mut(name) {
-            if parameter.is_valid(&value) {
-                parameter.value = value;
+            parameter.value = parameter.clamp(value);
 
-                // If the parameter is a parameter on the plugin, then set it on the plugin instance
-                if let Some(&index) = self.param_index_map.get(name) {
-                    if let Some(instance) = self.instance.as_mut() {
-                        instance.set_parameter_value(index, parameter.value.as_float().unwrap());
-                    }
+            // If the parameter is a parameter on the plugin, then set it on the plugin instance
+            if let Some(&index) = self.param_index_map.get(name) {
+                if let Some(instance) = self.instance.as_mut() {
+                    instance.set_parameter_value(index, parameter.value.as_float().unwrap());
                 }
             }
         } else {
@@ -1,19 +1,57 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 
-use super::{PedalTrait, PedalParameter, PedalParameterValue};
+use super::{index_to_choice, PedalTrait, PedalParameter, PedalParameterValue, ParameterUnit};
 use serde::{ser::SerializeMap, Deserialize, Serialize};
 use crate::{dsp_algorithms::moving_bandpass::MovingBandPass, pedals::ui::pedal_switch, unique_time_id};
 use super::ui::pedal_knob;
 
 use eframe::egui::{self, include_image};
 
+/// Choices of the Voicing parameter
+pub const VOICING_NAMES: [&str; 4] = ["Standard", "Cry Baby", "Vox", "Wide"];
+
+/// Time the position must be unchanged before the wah starts returning to the heel
+const AUTO_RETURN_IDLE_SECS: f32 = 0.4;
+/// Time taken to glide back to the heel once idle
+const AUTO_RETURN_GLIDE_SECS: f32 = 0.15;
+
+/// How a voicing shapes the sweep set by the Base Frequency, Sensitivity and Width parameters
+struct Voicing {
+    // Multiplier of the sensitivity
+    range_scale: f32,
+    // Multiplier of the filter bandwidth
+    width_scale: f32,
+    // Sweep evenly in pitch rather than in Hz, like the taper of a real wah pot
+    exponential: bool
+}
+
+impl Voicing {
+    fn from_name(voicing: &str) -> Self {
+        match voicing {
+            // Sharp peak, sweeping evenly up the range
+            "Cry Baby" => Voicing { range_scale: 1.0, width_scale: 0.7, exponential: true },
+            // Narrower sweep with a broader, throatier peak
+            "Vox" => Voicing { range_scale: 0.75, width_scale: 1.3, exponential: true },
+            // Extended range for dramatic sweeps
+            "Wide" => Voicing { range_scale: 1.6, width_scale: 0.85, exponential: true },
+            // Standard
+            _ => Voicing { range_scale: 1.0, width_scale: 1.0, exponential: false }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Wah {
     parameters: HashMap<String, PedalParameter>,
     // Processor only
     sample_rate: Option<f32>,
     moving_bandpass_filter: Option<MovingBandPass>,
+    // (heel, toe) filter frequencies for the voicing
+    range: (f32, f32),
+    // Position when the expression input last moved, and the number of samples since
+    last_position: f32,
+    idle_samples: usize,
 
     id: u32,
 }
@@ -47,10 +85,23 @@ impl<'a> Deserialize<'a> for Wah {
             parameters: HashMap<String, PedalParameter>,
         }
         let helper = WahData::deserialize(deserializer)?;
+
+        let mut parameters = helper.parameters;
+        // Wahs saved before voicings and auto return were added are a standard sweep
+        parameters.entry("Voicing".to_string())
+            .or_insert_with(|| Wah::voicing_parameter());
+        parameters.entry("Auto Return".to_string())
+            .or_insert_with(|| Wah::auto_return_parameter());
+        // Voicings saved before choice parameters were added are indexes into VOICING_NAMES
+        index_to_choice(&mut parameters, "Voicing", Wah::voicing_parameter());
+
         Ok(Wah {
-            parameters: helper.parameters,
+            parameters,
             sample_rate: None,
             moving_bandpass_filter: None,
+            range: (0.0, 0.0),
+            last_position: f32::NAN,
+            idle_samples: 0,
             id: helper.id
         })
     }
@@ -64,37 +115,60 @@ impl Wah {
             value: PedalParameterValue::Float(0.5),
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(1.0)),
-            step: None
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Position of the wah pedal, from heel to toe.".to_string()),
+            unit: ParameterUnit::None
         });
 
         parameters.insert("Base Frequency".to_string(), PedalParameter {
             value: PedalParameterValue::Float(100.0),
             min: Some(PedalParameterValue::Float(50.0)),
             max: Some(PedalParameterValue::Float(1000.0)),
-            step: None
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Filter frequency in Hz with the pedal in the heel position.".to_string()),
+            unit: ParameterUnit::None
         });
 
         parameters.insert("Width".to_string(), PedalParameter {
             value: PedalParameterValue::Float(0.5),
             min: Some(PedalParameterValue::Float(0.1)),
             max: Some(PedalParameterValue::Float(2.0)),
-            step: None
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Bandwidth of the filter. Lower values give a sharper, more vocal sweep.".to_string()),
+            unit: ParameterUnit::None
         });
 
         parameters.insert("Sensitivity".to_string(), PedalParameter {
             value: PedalParameterValue::Float(1000.0),
             min: Some(PedalParameterValue::Float(100.0)),
             max: Some(PedalParameterValue::Float(4000.0)),
-            step: None
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("How far in Hz the filter sweeps up from heel to toe.".to_string()),
+            unit: ParameterUnit::None
         });
 
         parameters.insert("Dry/Wet".to_string(), PedalParameter {
             value: PedalParameterValue::Float(1.0),
             min: Some(PedalParameterValue::Float(0.0)),
             max: Some(PedalParameterValue::Float(1.0)),
-            step: None
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Mix between the unprocessed and filtered signal.".to_string()),
+            unit: ParameterUnit::None
         });
         
+        parameters.insert("Voicing".to_string(), Self::voicing_parameter());
+        parameters.insert("Auto Return".to_string(), Self::auto_return_parameter());
+
         parameters.insert(
             "Active".to_string(),
             PedalParameter {
@@ -102,6 +176,10 @@ impl Wah {
                 min: None,
                 max: None,
                 step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
             },
         );
 
@@ -109,10 +187,83 @@ impl Wah {
             parameters,
             sample_rate: None,
             moving_bandpass_filter: None,
+            range: (0.0, 0.0),
+            last_position: f32::NAN,
+            idle_samples: 0,
             id: unique_time_id(),
         }
     }
 
+    /// One of [`VOICING_NAMES`]
+    fn voicing_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::String(VOICING_NAMES[0].to_string()),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: Some(VOICING_NAMES.iter().map(|name| name.to_string()).collect()),
+            description: Some("Range and peak of the sweep, emulating classic wahs.".to_string()),
+            unit: ParameterUnit::None
+        }
+    }
+
+    fn auto_return_parameter() -> PedalParameter {
+        PedalParameter {
+            value: PedalParameterValue::Bool(false),
+            min: None,
+            max: None,
+            step: None,
+            snap: false,
+            choices: None,
+            description: Some("Return to the heel position when the position stops moving.".to_string()),
+            unit: ParameterUnit::None
+        }
+    }
+
+    fn voicing(&self) -> Voicing {
+        Voicing::from_name(self.parameters.get("Voicing").unwrap().value.as_str().unwrap())
+    }
+
+    /// Recalculate the heel and toe frequencies from the parameters, keeping the toe below the Nyquist frequency
+    fn update_range(&mut self) {
+        let Some(sample_rate) = self.sample_rate else {
+            return;
+        };
+
+        let base_freq = self.parameters.get("Base Frequency").unwrap().value.as_float().unwrap();
+        let sensitivity = self.parameters.get("Sensitivity").unwrap().value.as_float().unwrap();
+        let toe_freq = (base_freq + sensitivity * self.voicing().range_scale).min(sample_rate * 0.45);
+        self.range = (base_freq, toe_freq.max(base_freq));
+    }
+
+    fn scaled_width(&self) -> f32 {
+        self.parameters.get("Width").unwrap().value.as_float().unwrap() * self.voicing().width_scale
+    }
+
+    fn sweep_frequency(&self, position: f32) -> f32 {
+        let (heel, toe) = self.range;
+        if self.voicing().exponential {
+            heel * (toe / heel).powf(position)
+        } else {
+            heel + position * (toe - heel)
+        }
+    }
+
+    /// The position to use, gliding back to the heel once `position` has not changed for a while
+    fn auto_return_position(&mut self, position: f32, samples: usize, sample_rate: f32) -> f32 {
+        if position != self.last_position {
+            self.last_position = position;
+            self.idle_samples = 0;
+            return position;
+        }
+
+        self.idle_samples = self.idle_samples.saturating_add(samples);
+        let idle_secs = self.idle_samples as f32 / sample_rate;
+        let returned = ((idle_secs - AUTO_RETURN_IDLE_SECS) / AUTO_RETURN_GLIDE_SECS).clamp(0.0, 1.0);
+        position * (1.0 - returned)
+    }
+
     pub fn clone_with_new_id(&self) -> Self {
         let mut cloned = self.clone();
         cloned.id = unique_time_id();
@@ -125,14 +276,19 @@ impl PedalTrait for Wah {
         self.id
     }
 
+    fn has_tail(&self) -> bool {
+        false
+    }
+
     fn set_config(&mut self, _buffer_size: usize, sample_rate: u32) {
         self.sample_rate = Some(sample_rate as f32);
+        self.update_range();
 
         // Initialize the moving bandpass filter
         self.moving_bandpass_filter = Some(MovingBandPass::new(
-            self.parameters.get("Base Frequency").and_then(|p| p.value.as_float()).unwrap(),
+            self.range.0,
             sample_rate as f32,
-            self.parameters.get("Width").and_then(|p| p.value.as_float()).unwrap(),
+            self.scaled_width(),
             64,
             2.0
         ));
@@ -141,13 +297,16 @@ impl PedalTrait for Wah {
     fn set_parameter_value(&mut self,name: &str,value:PedalParameterValue) {
         let parameters = self.get_parameters_mut();
         if let Some(parameter) = parameters.get_mut(name) {
-            if parameter.is_valid(&value) {
-                parameter.value = value;
+            parameter.value = parameter.clamp(value);
+
+            if matches!(name, "Base Frequency" | "Sensitivity" | "Voicing") {
+                self.update_range();
+            }
 
-                if name == "Width" {
-                    if let Some(filter) = &mut self.moving_bandpass_filter {
-                        filter.set_width(self.parameters.get("Width").unwrap().value.as_float().unwrap());
-                    }
+            if matches!(name, "Width" | "Voicing") {
+                let width = self.scaled_width();
+                if let Some(filter) = &mut self.moving_bandpass_filter {
+                    filter.set_width(width);
                 }
             }
         }
@@ -159,13 +318,16 @@ impl PedalTrait for Wah {
             return;
         }
 
-        let position = self.parameters.get("Position").unwrap().value.as_float().unwrap();
-        let base_freq = self.parameters.get("Base Frequency").unwrap().value.as_float().unwrap();
-        let sensitivity = self.parameters.get("Sensitivity").unwrap().value.as_float().unwrap();
+        let mut position = self.parameters.get("Position").unwrap().value.as_float().unwrap();
         let dry_wet = self.parameters.get("Dry/Wet").unwrap().value.as_float().unwrap();
 
+        if self.parameters.get("Auto Return").unwrap().value.as_bool().unwrap() {
+            position = self.auto_return_position(position, buffer.len(), self.sample_rate.unwrap());
+        }
+        let freq = self.sweep_frequency(position);
+
         let filter = self.moving_bandpass_filter.as_mut().unwrap();
-        filter.set_freq(base_freq + position * sensitivity);
+        filter.set_freq(freq);
 
         for sample in buffer.iter_mut() {
             *sample = filter.process(*sample) * dry_wet + *sample * (1.0 - dry_wet);
@@ -0,0 +1,296 @@
+use std::{collections::HashMap, hash::Hash};
+use eframe::egui::{self, include_image, Color32, Layout, RichText, UiBuilder, Vec2};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
+use signalsmith_stretch::Stretch;
+
+use crate::{dsp_algorithms::{sample_delay::SampleDelay, smoothed_param::SmoothedParam, stretch::{new_stretch, stretch_latency_samples}}, pedals::ui::pedal_switch, unique_time_id};
+
+use super::{
+    ui::pedal_knob,
+    PedalParameter, PedalParameterValue, ParameterUnit, PedalTrait,
+};
+
+// Smaller than the Pitch Shift default, as the whammy is played in real time
+const STRETCH_BLOCK_SIZE: usize = 1024;
+const TONALITY_LIMIT_HZ: f32 = 4000.0;
+/// Time the shift amount ramps over to reach a new expression position
+const SHIFT_SMOOTHING_MS: f32 = 20.0;
+/// The shift is updated at most this many samples apart
+const SHIFT_UPDATE_SAMPLES: usize = 32;
+
+/// Pitch shifter and its smoothed state, created in set_config
+struct WhammyShifter {
+    stretch: Stretch,
+    dry_buffer: Vec<f32>,
+    // Delays the dry signal by the stretch latency, so it lines up with the shifted signal when mixed
+    dry_delay: SampleDelay,
+    output_buffer: Vec<f32>,
+    // Current shift in semitones, smoothed towards the expression target
+    semitones: SmoothedParam,
+    tonality_limit: f32,
+}
+
+impl WhammyShifter {
+    fn new(buffer_size: usize, sample_rate: u32) -> Self {
+        let latency = stretch_latency_samples(STRETCH_BLOCK_SIZE);
+        let mut dry_delay = SampleDelay::new(latency);
+        dry_delay.set_delay(latency);
+
+        Self {
+            stretch: new_stretch(STRETCH_BLOCK_SIZE),
+            dry_buffer: Vec::with_capacity(buffer_size),
+            dry_delay,
+            output_buffer: Vec::with_capacity(SHIFT_UPDATE_SAMPLES),
+            semitones: SmoothedParam::new(0.0, SHIFT_SMOOTHING_MS, sample_rate as f32),
+            tonality_limit: TONALITY_LIMIT_HZ / sample_rate as f32,
+        }
+    }
+}
+
+/// Pitch shift swept by an expression parameter, intended to be mapped to a MIDI expression pedal
+pub struct Whammy {
+    parameters: HashMap<String, PedalParameter>,
+    // Processor only
+    pitch_shifter: Option<WhammyShifter>,
+    // Buffer size and sample rate from set_config, used to recreate the pitch shifter when cloned
+    config: Option<(usize, u32)>,
+    id: u32,
+}
+
+impl Clone for Whammy {
+    fn clone(&self) -> Self {
+        Whammy {
+            parameters: self.parameters.clone(),
+            pitch_shifter: self.config.map(|(buffer_size, sample_rate)| WhammyShifter::new(buffer_size, sample_rate)),
+            config: self.config,
+            id: self.id
+        }
+    }
+}
+
+impl Serialize for Whammy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser_map = serializer.serialize_map(Some(2))?;
+        ser_map.serialize_entry("id", &self.id)?;
+        ser_map.serialize_entry("parameters", &self.parameters)?;
+        ser_map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Whammy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct WhammyData {
+            id: u32,
+            parameters: HashMap<String, PedalParameter>,
+        }
+        let helper = WhammyData::deserialize(deserializer)?;
+        Ok(Whammy {
+            parameters: helper.parameters,
+            pitch_shifter: None,
+            config: None,
+            id: helper.id
+        })
+    }
+}
+
+impl Whammy {
+    pub fn new() -> Self {
+        let mut parameters = HashMap::new();
+
+        // Position of the expression pedal, 0 is no shift and 1 is the full range
+        parameters.insert(
+            "Expression".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(0.0),
+                min: Some(PedalParameterValue::Float(0.0)),
+                max: Some(PedalParameterValue::Float(1.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Position of the expression pedal. At the toe, the pitch is shifted by the full range.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        // Shift in semitones at full expression
+        parameters.insert(
+            "Range".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Int(12),
+                min: Some(PedalParameterValue::Int(-24)),
+                max: Some(PedalParameterValue::Int(24)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Interval in semitones the pitch is shifted by with the expression pedal fully down.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Dry/Wet".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Float(1.0),
+                min: Some(PedalParameterValue::Float(0.0)),
+                max: Some(PedalParameterValue::Float(1.0)),
+                step: None,
+                snap: false,
+                choices: None,
+                description: Some("Mix between the unprocessed and shifted signal. Mixing in the dry signal gives a harmony.".to_string()),
+                unit: ParameterUnit::None,
+            },
+        );
+
+        parameters.insert(
+            "Active".to_string(),
+            PedalParameter {
+                value: PedalParameterValue::Bool(true),
+                min: None,
+                max: None,
+                step: None,
+                snap: false,
+                choices: None,
+                description: None,
+                unit: ParameterUnit::None,
+            },
+        );
+
+        Self {
+            parameters,
+            pitch_shifter: None,
+            config: None,
+            id: unique_time_id()
+        }
+    }
+
+    pub fn clone_with_new_id(&self) -> Self {
+        let mut cloned = self.clone();
+        cloned.id = unique_time_id();
+        cloned
+    }
+}
+
+impl Hash for Whammy {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PedalTrait for Whammy {
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn has_tail(&self) -> bool {
+        false
+    }
+
+    fn set_config(&mut self, buffer_size: usize, sample_rate: u32) {
+        self.config = Some((buffer_size, sample_rate));
+        self.pitch_shifter = Some(WhammyShifter::new(buffer_size, sample_rate));
+    }
+
+    fn process_audio(&mut self, buffer: &mut [f32], _message_buffer: &mut Vec<String>) {
+        let expression = self.parameters["Expression"].value.as_float().unwrap();
+        let range = self.parameters["Range"].value.as_int().unwrap() as f32;
+        let dry_wet = self.parameters["Dry/Wet"].value.as_float().unwrap();
+        let target_semitones = expression * range;
+
+        let shifter = match &mut self.pitch_shifter {
+            Some(shifter) => shifter,
+            None => {
+                tracing::warn!("Whammy: Call set_config before processing.");
+                return;
+            }
+        };
+
+        shifter.dry_buffer.clear();
+        shifter.dry_buffer.extend_from_slice(buffer);
+
+        // Update the shift in small chunks so fast expression changes don't cause zipper noise
+        shifter.semitones.set_target(target_semitones);
+        for chunk in buffer.chunks_mut(SHIFT_UPDATE_SAMPLES) {
+            let semitones = shifter.semitones.advance(chunk.len());
+            shifter.stretch.set_transpose_factor_semitones(semitones, Some(shifter.tonality_limit));
+
+            shifter.output_buffer.resize(chunk.len(), 0.0);
+            shifter.stretch.process(chunk.as_ref(), &mut shifter.output_buffer);
+            chunk.copy_from_slice(&shifter.output_buffer);
+        }
+
+        for (sample, dry) in buffer.iter_mut().zip(shifter.dry_buffer.iter()) {
+            *sample = *sample * dry_wet + shifter.dry_delay.process(*dry) * (1.0 - dry_wet);
+        }
+    }
+
+    fn reset_buffer(&mut self) {
+        if let Some(shifter) = &mut self.pitch_shifter {
+            shifter.stretch.reset();
+            shifter.dry_delay.reset();
+        }
+    }
+
+    fn latency_samples(&self) -> usize {
+        if self.pitch_shifter.is_some() {
+            stretch_latency_samples(STRETCH_BLOCK_SIZE)
+        } else {
+            0
+        }
+    }
+
+    fn get_parameters(&self) -> &HashMap<String, PedalParameter> {
+        &self.parameters
+    }
+
+    fn get_parameters_mut(&mut self) -> &mut HashMap<String, PedalParameter> {
+        &mut self.parameters
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, _message_buffer: &[String]) -> Option<(String,PedalParameterValue)> {
+        let mut to_change = None;
+
+        let mut img_ui = ui.new_child(
+            UiBuilder::new()
+                .max_rect(ui.available_rect_before_wrap())
+        );
+
+        img_ui.add(egui::Image::new(include_image!("images/pedal_gradient.png")).tint(Color32::from_rgb(150, 20, 20)));
+
+        ui.allocate_ui_with_layout(
+            ui.available_size() * Vec2::new(0.9, 1.0),
+            Layout::top_down(egui::Align::Center),
+            |ui| {
+                ui.add_space(20.0);
+                ui.label(RichText::new("Whammy").size(20.0));
+            }
+        );
+
+        let range = self.get_parameters().get("Range").unwrap().value.as_int().unwrap();
+        let knobs = [
+            ("Expression".to_string(), "Expression", Vec2::new(0.05, 0.2)),
+            (format!("{:+} st", range), "Range", Vec2::new(0.37, 0.2)),
+            ("Dry/Wet".to_string(), "Dry/Wet", Vec2::new(0.69, 0.2)),
+        ];
+
+        for (label, name, at) in knobs {
+            let param = self.get_parameters().get(name).unwrap();
+            if let Some(value) = pedal_knob(ui, RichText::new(label).color(Color32::WHITE).size(8.0), name, param, at, 0.26, self.id) {
+                to_change = Some((name.to_string(), value));
+            }
+        }
+
+        let active_param = self.get_parameters().get("Active").unwrap().value.as_bool().unwrap();
+        if let Some(value) = pedal_switch(ui, active_param, egui::Vec2::new(0.33, 0.72), 0.16) {
+            to_change = Some(("Active".to_string(), PedalParameterValue::Bool(value)));
+        }
+
+        to_change
+    }
+}
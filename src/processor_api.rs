@@ -112,9 +112,7 @@ fn save_wav<P: AsRef<std::path::Path>>(wav_path: P, buffer: &[f32], sample_rate:
 pub fn process_audio(audio: &mut [f32], pedalboard: &mut Pedalboard, sample_rate: f32, normalise: bool) {
     let mut pedal_command_to_client_buffer: Vec<String> = Vec::new();
 
-    for pedal in &mut pedalboard.pedals {
-        pedal.set_config(PROCESSING_BUFFER_SIZE, sample_rate as u32);
-    }
+    pedalboard.set_config(PROCESSING_BUFFER_SIZE, sample_rate as u32);
 
     for i in 0..(audio.len() as f32 / PROCESSING_BUFFER_SIZE as f32).ceil() as usize {
         let start = i * PROCESSING_BUFFER_SIZE;
@@ -135,9 +133,7 @@ pub fn process_audio(audio: &mut [f32], pedalboard: &mut Pedalboard, sample_rate
 }
 
 pub fn process_audio_file(src_path: &std::path::Path, pedalboard: &mut Pedalboard, sample_rate: f32, normalise: bool) -> Result<Vec<f32>, String> {
-    for pedal in &mut pedalboard.pedals {
-        pedal.set_config(PROCESSING_BUFFER_SIZE, sample_rate as u32);
-    }
+    pedalboard.set_config(PROCESSING_BUFFER_SIZE, sample_rate as u32);
 
     let mut processing_buffer = match load_wav(src_path, sample_rate, false) {
         Ok(channels) => {
@@ -1,4 +1,4 @@
-use crate::SAVE_DIR;
+use crate::{dsp_algorithms::resampler::ResamplerQuality, SAVE_DIR};
 use serde::{Serialize, Deserialize};
 use std::{fmt::Display, path::PathBuf, str::FromStr};
 use strum_macros::EnumIter;
@@ -62,6 +62,62 @@ impl From<SupportedHost> for cpal::HostId {
     }
 }
 
+/// Physical channels (0-indexed) used to send to and return from external hardware with the FX Loop pedal
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct FxLoopChannels {
+    pub send_channel: usize,
+    pub return_channel: usize
+}
+
+/// What the processor does once every client has disconnected, e.g. if the client crashes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisconnectAction {
+    /// Keep processing the last pedalboard set
+    #[default]
+    KeepPlaying,
+    /// Mute the output until a client connects
+    Mute,
+    /// Replace the pedalboard set with a clean pedalboard with no pedals
+    LoadDefault
+}
+
+impl Display for DisconnectAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisconnectAction::KeepPlaying => write!(f, "Keep Playing"),
+            DisconnectAction::Mute => write!(f, "Mute"),
+            DisconnectAction::LoadDefault => write!(f, "Load Default")
+        }
+    }
+}
+
+impl FromStr for DisconnectAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace(' ', "").as_str() {
+            "keep" | "keepplaying" => Ok(DisconnectAction::KeepPlaying),
+            "mute" => Ok(DisconnectAction::Mute),
+            "default" | "loaddefault" => Ok(DisconnectAction::LoadDefault),
+            _ => Err(format!("Invalid disconnect action: {s}, expected keep, mute or default"))
+        }
+    }
+}
+
+impl DisconnectAction {
+    /// Value passed to the processor's --on-disconnect argument
+    pub fn arg(&self) -> &'static str {
+        match self {
+            DisconnectAction::KeepPlaying => "keep",
+            DisconnectAction::Mute => "mute",
+            DisconnectAction::LoadDefault => "default"
+        }
+    }
+}
+
+/// Lowest output ceiling in dB. The ceiling is between this and 0 dB.
+pub const MIN_OUTPUT_CEILING_DB: f32 = -40.0;
+
 /// Processor settings that will be saved to a file.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(default)]
@@ -80,8 +136,28 @@ pub struct ProcessorSettingsSave {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub preferred_sample_rate: Option<u32>,
     pub upsample_passes: u32,
+    // Sample rate pedals are processed at, regardless of the device sample rate. Replaces upsampling if set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub internal_sample_rate: Option<u32>,
+    // Quality of the resampling for upsampling or an internal sample rate
+    pub resampler_quality: ResamplerQuality,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recording_dir: Option<PathBuf>,
+    pub output_limiter: bool,
+    // Clean boost in dB applied to the input before the pedalboard chain
+    pub pre_gain_db: f32,
+    // Highest output volume in dB, which no command can raise the output above
+    pub output_ceiling_db: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fx_loop: Option<FxLoopChannels>,
+    // Physical output channel (0-indexed) that the dry input is sent to while recording
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_monitor_channel: Option<usize>,
+    // Physical input channel (0-indexed) that is processed. If not set, all input channels are mixed.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub recording_dir: Option<PathBuf>
+    pub input_channel: Option<usize>,
+    // What to do once every client has disconnected
+    pub on_disconnect: DisconnectAction
 }
 
 impl Default for ProcessorSettingsSave {
@@ -96,7 +172,16 @@ impl Default for ProcessorSettingsSave {
             output_device: None,
             preferred_sample_rate: None,
             upsample_passes: 0,
-            recording_dir: None
+            internal_sample_rate: None,
+            resampler_quality: ResamplerQuality::default(),
+            recording_dir: None,
+            output_limiter: false,
+            pre_gain_db: 0.0,
+            output_ceiling_db: 0.0,
+            fx_loop: None,
+            dry_monitor_channel: None,
+            input_channel: None,
+            on_disconnect: DisconnectAction::default()
         }
     }
 }
@@ -1,7 +1,26 @@
-use std::io::{self, Read};
+use std::{io::{self, Read}, sync::{atomic::{AtomicBool, Ordering}, LazyLock}};
 
 use smol::io::{AsyncRead, AsyncReadExt};
 
+/// Environment variable that enables logging full commands instead of truncating them, when set to anything other than 0
+pub const LOG_FULL_COMMANDS_ENV_VAR: &str = "RSPEDALBOARD_LOG_FULL_COMMANDS";
+
+static LOG_FULL_COMMANDS: LazyLock<AtomicBool> = LazyLock::new(|| {
+    let from_env = std::env::var(LOG_FULL_COMMANDS_ENV_VAR).is_ok_and(|value| value != "0");
+    AtomicBool::new(cfg!(feature = "log_full_commands") || from_env)
+});
+
+/// Whether sent and received commands are logged in full, rather than truncated
+pub fn log_full_commands() -> bool {
+    LOG_FULL_COMMANDS.load(Ordering::Relaxed)
+}
+
+/// Set whether sent and received commands are logged in full, for this process only
+pub fn set_log_full_commands(enabled: bool) {
+    tracing::info!("Full command logging {}", if enabled { "enabled" } else { "disabled" });
+    LOG_FULL_COMMANDS.store(enabled, Ordering::Relaxed);
+}
+
 pub struct CommandReceiver {
     partial_buffer: Vec<u8>,
     temp_command_buffer: Vec<String>,
@@ -36,7 +55,7 @@ impl CommandReceiver {
             };
 
             if !line.is_empty() {
-                if line.len() < 40 || cfg!(feature = "log_full_commands") {
+                if line.len() < 40 || log_full_commands() {
                     tracing::debug!("Received command: {:?}", line);
                 } else {
                     tracing::debug!("Received command: {:?}...", &line[..40]);